@@ -0,0 +1,1668 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use lune_utils::luaurc::{LuauConfig, MergedAliases};
+use lune_utils::namespace::NamespaceManifest;
+use lune_utils::packages::PACKAGE_MANIFESTS;
+use lune_utils::path::{clean_path_and_make_absolute, is_data_file};
+use lune_utils::platform::{PLATFORM_DEFAULT, Platform};
+use lune_utils::remote::{
+    Lockfile, RemoteCache, is_remote_path, normalize_remote_url, remote_bundle_path,
+    resolve_remote_relative,
+};
+use lune_utils::require_graph::RequireGraph;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::cache::BundleCache;
+use crate::fs::{BundleFs, OsFs};
+
+/// Index directories that package managers install packages into, inside of package
+/// directories such as `Packages` - `_Index` for wally and pesde (roblox targets),
+/// and `.pesde` for pesde (luau and lune targets)
+const PACKAGE_INDEX_DIRS: &[&str] = &["_Index", ".pesde"];
+
+/// A single step in a `script`-based require, such as `script.Parent._Index["foo"]`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+enum ScriptStep {
+    Parent,
+    Child(String),
+}
+
+/// Requires found in a single file, before being resolved
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ParsedRequires {
+    /// String requires, such as `require("./foo")`, excluding built-in libraries
+    pub requires: Vec<String>,
+    /// Requires using the `script` global, split into their steps
+    script_requires: Vec<Vec<ScriptStep>>,
+    /// Requires using `require.platform`, as pairs of platform names and paths
+    #[serde(default)]
+    platform_requires: Vec<Vec<(String, String)>>,
+}
+
+/// Result of bundling: files and alias mappings
+pub struct BundleResult {
+    /// Bundle path of the entry file
+    pub entry: String,
+    /// Bundle paths of the extra entry files, in the order they were given
+    pub extra_entries: Vec<String>,
+    /// The bundled files, including the entry file: bundle path -> source
+    pub files: HashMap<String, Vec<u8>>,
+    /// Alias mappings: alias or bare path -> bundle path
+    pub aliases: HashMap<String, String>,
+    /// Files reached through more than one path, such as through symlinks,
+    /// which are only bundled once: bundle path -> bundle path of the file
+    pub links: HashMap<String, String>,
+    /// Original file paths of all bundled files: bundle path -> logical path
+    pub sources: HashMap<String, PathBuf>,
+    /// Files that required each bundled file: bundle path -> sorted bundle paths
+    pub required_by: HashMap<String, Vec<String>>,
+    /// String requires of each bundled file, as resolved when bundling:
+    /// bundle path -> require path -> bundle path of the required file
+    pub requires: HashMap<String, BTreeMap<String, String>>,
+    /// Requires of modules in namespaces provided by the host application, which are not bundled
+    pub namespace_requires: BTreeSet<String>,
+}
+
+impl BundleResult {
+    /// Link the string requires of all bundled files into a graph, numbered in
+    /// dependency order from the entry files, see [`RequireGraph`]
+    #[must_use]
+    pub fn require_graph(&self) -> RequireGraph {
+        let mut entries = vec![self.entry.clone()];
+        entries.extend(self.extra_entries.iter().cloned());
+        RequireGraph::link(&entries, &self.requires)
+    }
+}
+
+/// A bundler that resolves all dependencies of a Luau file
+pub struct Bundler {
+    /// Base directory for computing relative paths (project root)
+    /// This may be expanded as we discover files outside the initial base
+    base_dir: PathBuf,
+    /// Cached .luaurc configs by directory
+    configs: HashMap<PathBuf, Option<LuauConfig>>,
+    /// Cached aliases of .luaurc files and package manifests by directory
+    merged_aliases: HashMap<PathBuf, MergedAliases>,
+    /// Already processed files to avoid cycles: canonical path -> logical path
+    processed: HashMap<PathBuf, PathBuf>,
+    /// The bundled files: logical path -> source (relativized at the end)
+    files_logical: HashMap<PathBuf, Vec<u8>>,
+    /// Alias mappings: alias or bare path -> logical path (relativized at the end)
+    aliases_logical: HashMap<String, PathBuf>,
+    /// Files reached through another logical path: logical path -> bundled logical path
+    links_logical: HashMap<PathBuf, PathBuf>,
+    /// Files that required each bundled file: bundled logical path -> requiring logical paths
+    required_by_logical: HashMap<PathBuf, BTreeSet<PathBuf>>,
+    /// String requires of each bundled file: logical path -> require path -> logical path
+    requires_logical: HashMap<PathBuf, BTreeMap<String, PathBuf>>,
+    /// Namespaces of modules provided by the host application, which are not bundled
+    namespaces: NamespaceManifest,
+    /// Requires of modules in namespaces provided by the host application
+    namespace_requires: BTreeSet<String>,
+    /// Lockfile and cache that remote modules are vendored from, if enabled
+    remote: Option<(Lockfile, RemoteCache)>,
+    /// Vendored remote modules: URL -> source
+    remote_files: BTreeMap<String, Vec<u8>>,
+    /// Cache of the requires found in files from previous builds, if enabled
+    cache: Option<BundleCache>,
+    /// Number of files that were unchanged since the previous build
+    cache_hits: usize,
+    /// Platform being built for, if known, to only bundle its `require.platform` variants
+    platform: Option<Platform>,
+    /// Source of the entry file, if it was not read from disk, such as for scripts from stdin
+    entry_source: Option<Vec<u8>>,
+    /// Other files to bundle as entry files, along with the entry file
+    extra_entries: Vec<PathBuf>,
+    /// File system that files are resolved and read through
+    fs: Box<dyn BundleFs>,
+    /// Regex to find require calls
+    require_regex: Regex,
+    /// Regex to find `require.platform` calls with platform names and paths
+    platform_require_regex: Regex,
+    /// Regex to find `require.platform` calls with a table of paths
+    platform_table_regex: Regex,
+    /// Regex to split the table of a `require.platform` call into its entries
+    platform_entry_regex: Regex,
+    /// Regex to find require calls using the `script` global, as used by package stubs
+    script_require_regex: Regex,
+    /// Regex to split a `script` expression into its steps
+    script_step_regex: Regex,
+}
+
+impl Bundler {
+    /// Create a bundler for the given entry file, which resolves
+    /// and reads all files through the real file system
+    ///
+    /// # Errors
+    ///
+    /// - If the regexes used to find requires could not be compiled
+    pub fn new(entry_path: &Path) -> Result<Self> {
+        Self::new_with_fs(entry_path, OsFs)
+    }
+
+    /// Create a bundler that resolves and reads all files through the given
+    /// file system, instead of the real one, such as for fixture trees in tests
+    ///
+    /// # Errors
+    ///
+    /// - If the regexes used to find requires could not be compiled
+    pub fn new_with_fs(entry_path: &Path, fs: impl BundleFs + 'static) -> Result<Self> {
+        // Find the project root by searching upward for .luaurc files
+        let base_dir = Self::find_project_root(&fs, entry_path);
+        Ok(Self {
+            base_dir,
+            configs: HashMap::new(),
+            merged_aliases: HashMap::new(),
+            processed: HashMap::new(),
+            files_logical: HashMap::new(),
+            aliases_logical: HashMap::new(),
+            links_logical: HashMap::new(),
+            required_by_logical: HashMap::new(),
+            requires_logical: HashMap::new(),
+            namespaces: NamespaceManifest::default(),
+            namespace_requires: BTreeSet::new(),
+            remote: None,
+            remote_files: BTreeMap::new(),
+            cache: None,
+            cache_hits: 0,
+            platform: None,
+            entry_source: None,
+            extra_entries: Vec::new(),
+            fs: Box::new(fs),
+            // Match require("...") or require('...')
+            require_regex: Regex::new(r#"require\s*\(\s*["']([^"']+)["']\s*\)"#)?,
+            // Match require.platform("windows", "./a") and require.platform("windows", "./a", "./b")
+            platform_require_regex: Regex::new(concat!(
+                r#"require\s*\.\s*platform\s*\(\s*["']([^"']+)["']\s*,\s*["']([^"']+)["']\s*"#,
+                r#"(?:,\s*["']([^"']+)["']\s*)?\)"#,
+            ))?,
+            // Match require.platform({ windows = "./a", default = "./b" })
+            platform_table_regex: Regex::new(r"require\s*\.\s*platform\s*\(\s*\{([^}]*)\}\s*\)")?,
+            platform_entry_regex: Regex::new(concat!(
+                r#"(?:\[\s*["']([^"']+)["']\s*\]|([A-Za-z_]\w*))"#,
+                r#"\s*=\s*["']([^"']+)["']"#,
+            ))?,
+            // Match require(script.Parent._Index["foo"]["bar"]) and similar
+            script_require_regex: Regex::new(concat!(
+                r#"require\s*\(\s*(script(?:\s*(?:\.\s*[A-Za-z_]\w*|\[\s*["'][^"']*["']\s*\]"#,
+                r#"|:\s*(?:WaitForChild|FindFirstChild)\s*\(\s*["'][^"']*["']\s*\)))*)\s*\)"#,
+            ))?,
+            script_step_regex: Regex::new(concat!(
+                r#"\.\s*([A-Za-z_]\w*)|\[\s*["']([^"']*)["']\s*\]"#,
+                r#"|:\s*(?:WaitForChild|FindFirstChild)\s*\(\s*["']([^"']*)["']\s*\)"#,
+            ))?,
+        })
+    }
+
+    /// Use an on-disk cache for the project in the given directory, so that
+    /// files which have not changed since the previous build are not parsed again
+    #[must_use]
+    pub fn with_cache(mut self, cache_dir: impl AsRef<Path>) -> Self {
+        self.cache = Some(BundleCache::load(cache_dir.as_ref(), &self.base_dir));
+        self
+    }
+
+    /// Leave requires of modules in the given namespaces to the host application,
+    /// instead of resolving them using the aliases of .luaurc files
+    #[must_use]
+    pub fn with_namespaces(mut self, namespaces: NamespaceManifest) -> Self {
+        self.namespaces = namespaces;
+        self
+    }
+
+    /// Vendor remote modules required using their URLs, reading them from the given cache
+    /// using the hashes recorded in the given lockfile, instead of downloading them
+    #[must_use]
+    pub fn with_remote_modules(mut self, lockfile: Lockfile, cache: RemoteCache) -> Self {
+        self.remote = Some((lockfile, cache));
+        self
+    }
+
+    /// Only bundle the variants of `require.platform` calls for the given platform,
+    /// instead of all of them, since the others can never be required at runtime
+    #[must_use]
+    pub fn with_platform(mut self, platform: Platform) -> Self {
+        self.platform = Some(platform);
+        self
+    }
+
+    /// Use the given source for the entry file, instead of reading it from disk,
+    /// for scripts that do not exist as files, such as those given through stdin
+    #[must_use]
+    pub fn with_entry_source(mut self, source: Vec<u8>) -> Self {
+        self.entry_source = Some(source);
+        self
+    }
+
+    /// Also bundle the given files, and all of their dependencies, as entry files,
+    /// for binaries that may run one of several scripts
+    #[must_use]
+    pub fn with_extra_entries(mut self, paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        self.extra_entries.extend(paths);
+        self
+    }
+
+    /// Get the number of files that were unchanged since the previous build
+    #[must_use]
+    pub fn cache_hits(&self) -> usize {
+        self.cache_hits
+    }
+
+    /// Get the base directory (project root) for making paths relative
+    #[must_use]
+    pub fn base_dir(&self) -> &Path {
+        &self.base_dir
+    }
+
+    /// Find the project root by searching upward for .luaurc files and package manifests.
+    /// Returns the highest-level directory containing either of them, or the
+    /// entry file's parent directory if none are found.
+    ///
+    /// Package manifests (`wally.toml`, `pesde.toml`) are included so that
+    /// package directories next to them are always inside of the project root,
+    /// keeping bundle paths and alias mappings stable for packages.
+    fn find_project_root(fs: &dyn BundleFs, entry_path: &Path) -> PathBuf {
+        let start_dir = clean_path_and_make_absolute(entry_path)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+
+        let mut highest_luaurc_dir: Option<PathBuf> = None;
+        let mut search_dir = start_dir.clone();
+
+        loop {
+            let config_path = search_dir.join(".luaurc");
+            let has_manifest = PACKAGE_MANIFESTS
+                .iter()
+                .any(|manifest| fs.is_file(&search_dir.join(manifest)));
+            if fs.exists(&config_path) || has_manifest {
+                highest_luaurc_dir = Some(search_dir.clone());
+            }
+
+            if !search_dir.pop() {
+                break;
+            }
+        }
+
+        highest_luaurc_dir.unwrap_or(start_dir)
+    }
+
+    /// Find the common ancestor directory of two paths
+    fn common_ancestor(path1: &Path, path2: &Path) -> PathBuf {
+        let components1: Vec<_> = path1.components().collect();
+        let components2: Vec<_> = path2.components().collect();
+
+        let mut common = PathBuf::new();
+        for (c1, c2) in components1.iter().zip(components2.iter()) {
+            if c1 == c2 {
+                common.push(c1);
+            } else {
+                break;
+            }
+        }
+
+        // Ensure we return at least the root
+        if common.as_os_str().is_empty() {
+            PathBuf::from("/")
+        } else {
+            common
+        }
+    }
+
+    /// Expand base_dir to include a new logical path if needed
+    fn expand_base_dir(&mut self, path: &Path) {
+        if !path.starts_with(&self.base_dir) {
+            self.base_dir = Self::common_ancestor(&self.base_dir, path);
+        }
+    }
+
+    /// Bundle all dependencies starting from the entry file
+    ///
+    /// # Errors
+    ///
+    /// - If the entry file, or any file that it requires, could not be read
+    /// - If a package module required using the `script` global could not be found
+    /// - If a remote module is not recorded in the lockfile, or missing from the cache
+    pub fn bundle(&mut self, entry_path: &Path) -> Result<BundleResult> {
+        // First pass: collect all files with logical paths
+        self.process_file(entry_path, None)?;
+        for extra_entry in self.extra_entries.clone() {
+            self.process_file(&extra_entry, None)?;
+        }
+        if let Some(cache) = self.cache.take() {
+            self.cache_hits = cache.hits();
+            cache.save();
+        }
+
+        // Now relativize all paths using the (possibly expanded) base_dir
+        let entry = self.normalize_path(&clean_path_and_make_absolute(entry_path));
+        let extra_entries = self
+            .extra_entries
+            .iter()
+            .map(|path| self.normalize_path(&clean_path_and_make_absolute(path)))
+            .collect();
+        let mut files = HashMap::new();
+        let mut sources = HashMap::new();
+        for (logical_path, source) in &self.files_logical {
+            let key = self.normalize_path(logical_path);
+            files.insert(key.clone(), source.clone());
+            sources.insert(key, logical_path.clone());
+        }
+
+        let mut aliases = HashMap::new();
+        for (alias, logical_path) in &self.aliases_logical {
+            let relative_path = self.normalize_path(logical_path);
+            aliases.insert(alias.clone(), relative_path);
+        }
+
+        let mut required_by = HashMap::new();
+        for (logical_path, requirers) in &self.required_by_logical {
+            required_by.insert(
+                self.normalize_path(logical_path),
+                requirers.iter().map(|p| self.normalize_path(p)).collect(),
+            );
+        }
+
+        let mut links = HashMap::new();
+        for (link_path, logical_path) in &self.links_logical {
+            links.insert(
+                self.normalize_path(link_path),
+                self.normalize_path(logical_path),
+            );
+        }
+
+        // Requires of files reached through links resolve to the bundled file
+        let mut requires = HashMap::new();
+        for (logical_path, required) in &self.requires_logical {
+            let required = required
+                .iter()
+                .map(|(require, path)| {
+                    let bundled = self.links_logical.get(path).unwrap_or(path);
+                    (require.clone(), self.normalize_path(bundled))
+                })
+                .collect();
+            requires.insert(self.normalize_path(logical_path), required);
+        }
+
+        // Remote modules keep their URLs as aliases, and are vendored under their hosts
+        for (url, source) in &self.remote_files {
+            let key = remote_bundle_path(url);
+            files.insert(key.clone(), source.clone());
+            sources.insert(key.clone(), PathBuf::from(url));
+            aliases.insert(url.clone(), key);
+        }
+
+        Ok(BundleResult {
+            entry,
+            extra_entries,
+            files,
+            aliases,
+            links,
+            sources,
+            required_by,
+            requires,
+            namespace_requires: self.namespace_requires.clone(),
+        })
+    }
+
+    /// Process a single file and its dependencies
+    ///
+    /// Files are stored using their logical path, which keeps any symlinks in the
+    /// path as-is, so that linked dependencies keep their in-project paths - but
+    /// they are deduplicated using their canonical path, so that a file reached
+    /// through several links is only bundled once, and linked to from the others.
+    fn process_file(&mut self, file_path: &Path, required_by: Option<&Path>) -> Result<()> {
+        let logical = clean_path_and_make_absolute(file_path);
+        let canonical = self
+            .fs
+            .canonicalize(&logical)
+            .unwrap_or_else(|_| logical.clone());
+
+        let bundled = self.processed.get(&canonical).cloned();
+        if let Some(required_by) = required_by {
+            self.required_by_logical
+                .entry(bundled.clone().unwrap_or_else(|| logical.clone()))
+                .or_default()
+                .insert(required_by.to_path_buf());
+        }
+
+        if let Some(bundled) = bundled {
+            if bundled != logical {
+                self.links_logical.insert(logical, bundled);
+            }
+            return Ok(());
+        }
+        self.processed.insert(canonical, logical.clone());
+
+        // Expand base_dir if this file is outside the current base
+        self.expand_base_dir(&logical);
+
+        // Read the file, unless it is the entry file and its source was given
+        let entry_source = required_by.is_none().then(|| self.entry_source.take());
+        let is_inline = matches!(entry_source, Some(Some(_)));
+        let source = match entry_source.flatten() {
+            Some(source) => source,
+            None => self
+                .fs
+                .read(&logical)
+                .with_context(|| format!("failed to read file: {}", logical.display()))?,
+        };
+
+        // Store the file with its logical path (will be relativized at the end)
+        self.files_logical.insert(logical.clone(), source.clone());
+
+        // Data files are decoded when required, and can not require anything
+        if is_data_file(&logical) {
+            return Ok(());
+        }
+
+        // Find all require paths first (to avoid borrow issues)
+        let file_dir = logical.parent().unwrap_or(Path::new(".")).to_path_buf();
+        // Inline sources have no file, so they can not be cached using its metadata
+        let parsed = match self.cache.take() {
+            Some(cache) if is_inline => {
+                self.cache = Some(cache);
+                self.parse_requires(&source)
+            }
+            Some(mut cache) => {
+                let parsed = cache.get_or_parse(&logical, &source, || self.parse_requires(&source));
+                self.cache = Some(cache);
+                parsed
+            }
+            None => self.parse_requires(&source),
+        };
+
+        // Requires using the script global, used by wally and pesde package
+        // stubs, and by packages requiring their own dependencies in _Index
+        let script_requires: Vec<PathBuf> = parsed
+            .script_requires
+            .iter()
+            .map(|steps| resolve_script_steps(&script_location(&logical), steps))
+            .collect();
+        for resolved in script_requires {
+            if let Some(module_path) = self.find_module_file(&resolved) {
+                self.process_file(&module_path, Some(&logical))?;
+            } else if is_in_package_dir(&resolved) {
+                anyhow::bail!(
+                    "failed to find package module {} required from {}",
+                    resolved.display(),
+                    file_path.display()
+                );
+            }
+        }
+
+        // Requires using require.platform, only for the platform being built for if known
+        let platform = self.platform;
+        let platform_requires =
+            parsed
+                .platform_requires
+                .into_iter()
+                .flat_map(|variants| match platform {
+                    Some(platform) => platform.select(variants).into_iter().collect::<Vec<_>>(),
+                    None => variants.into_iter().map(|(_, path)| path).collect(),
+                });
+        let requires = parsed
+            .requires
+            .into_iter()
+            .chain(platform_requires)
+            .collect::<Vec<_>>();
+
+        // Now process each require
+        for require_path in requires {
+            if is_remote_path(&require_path) {
+                self.process_remote(&normalize_remote_url(&require_path))?;
+                continue;
+            }
+            if self.namespaces.contains(&require_path) {
+                self.namespace_requires.insert(require_path);
+                continue;
+            }
+            if let Some(resolved) = self.resolve_require(&require_path, &file_dir) {
+                let actual_file = self.find_module_file(&resolved);
+                if let Some(module_path) = actual_file {
+                    if self.fs.exists(&module_path) {
+                        self.process_file(&module_path, Some(&logical))?;
+                        // Absolute requires are bundle paths at runtime, not file paths
+                        if !require_path.starts_with('/') {
+                            self.requires_logical
+                                .entry(logical.clone())
+                                .or_default()
+                                .insert(require_path, module_path);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Vendor a remote module and the remote modules that it requires, if enabled
+    ///
+    /// Remote modules are read from the cache using the hashes recorded in the lockfile,
+    /// which are only written once scripts have required them, so they are never downloaded
+    /// when bundling, and binaries always contain exactly the modules that were locked.
+    fn process_remote(&mut self, url: &str) -> Result<()> {
+        let Some((lockfile, cache)) = &self.remote else {
+            return Ok(());
+        };
+        if self.remote_files.contains_key(url) {
+            return Ok(());
+        }
+        let hash = lockfile.get(url).with_context(|| {
+            format!(
+                "remote module {url} is not recorded in the lockfile - \
+                run the script using `lune run --allow-url-requires` first"
+            )
+        })?;
+        let source = cache.read(hash).with_context(|| {
+            format!(
+                "remote module {url} is missing from the cache - \
+                run the script using `lune run --allow-url-requires` first"
+            )
+        })?;
+        self.remote_files.insert(url.to_string(), source.clone());
+
+        // Remote modules can only require other remote modules, relative to their URLs
+        for require_path in self.parse_requires(&source).requires {
+            let required = if is_remote_path(&require_path) {
+                Some(normalize_remote_url(&require_path))
+            } else {
+                resolve_remote_relative(url, &require_path)
+            };
+            if let Some(required) = required {
+                self.process_remote(&required)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Find all requires in the source of a file, without resolving them
+    fn parse_requires(&self, source: &[u8]) -> ParsedRequires {
+        let source_str = String::from_utf8_lossy(source);
+
+        let requires = self
+            .require_regex
+            .captures_iter(&source_str)
+            .filter_map(|cap| cap.get(1).map(|m| m.as_str().to_string()))
+            .filter(|p| !p.starts_with("@lune/"))
+            .collect();
+
+        let script_requires = self
+            .script_require_regex
+            .captures_iter(&source_str)
+            .filter_map(|cap| cap.get(1))
+            .map(|m| self.parse_script_steps(m.as_str()))
+            .collect();
+
+        let platform_requires = self
+            .platform_require_regex
+            .captures_iter(&source_str)
+            .filter_map(|cap| {
+                let names = cap.get(1)?.as_str().to_string();
+                let mut variants = vec![(names, cap.get(2)?.as_str().to_string())];
+                if let Some(otherwise) = cap.get(3) {
+                    variants.push((PLATFORM_DEFAULT.to_string(), otherwise.as_str().to_string()));
+                }
+                Some(variants)
+            })
+            .chain(
+                self.platform_table_regex
+                    .captures_iter(&source_str)
+                    .filter_map(|cap| cap.get(1))
+                    .map(|entries| {
+                        self.platform_entry_regex
+                            .captures_iter(entries.as_str())
+                            .filter_map(|cap| {
+                                let names = cap.get(1).or(cap.get(2))?.as_str().to_string();
+                                Some((names, cap.get(3)?.as_str().to_string()))
+                            })
+                            .collect()
+                    }),
+            )
+            .collect();
+
+        ParsedRequires {
+            requires,
+            script_requires,
+            platform_requires,
+        }
+    }
+
+    /// Normalize a logical path for use as a bundle key.
+    /// Returns a path relative to the base directory, starting with '/'.
+    /// Uses forward slashes on all platforms for portable bundled binaries.
+    fn normalize_path(&self, path: &Path) -> String {
+        normalize_bundle_path(path, &self.base_dir)
+    }
+
+    /// Split a `script` expression, such as `script.Parent._Index["foo"]`, into its steps
+    fn parse_script_steps(&self, expr: &str) -> Vec<ScriptStep> {
+        let rest = expr.trim_start().strip_prefix("script").unwrap_or(expr);
+        self.script_step_regex
+            .captures_iter(rest)
+            .filter_map(|cap| {
+                let name = cap.get(1).or(cap.get(2)).or(cap.get(3))?.as_str();
+                Some(if cap.get(1).is_some() && name == "Parent" {
+                    ScriptStep::Parent
+                } else {
+                    ScriptStep::Child(name.to_string())
+                })
+            })
+            .collect()
+    }
+
+    /// Find the actual module file (handles init.luau pattern)
+    ///
+    /// The path is cleaned first, so that `..` components are resolved the same way
+    /// as at runtime - lexically, instead of by following symlinks - and the returned
+    /// path is always an absolute logical path.
+    fn find_module_file(&self, path: &Path) -> Option<PathBuf> {
+        let path = &clean_path_and_make_absolute(path);
+        // Try exact path with extensions - note that extensions are appended instead
+        // of replaced, since package directories such as `foo@1.0.0` contain dots
+        for ext in &["", ".luau", ".lua"] {
+            let mut with_ext = path.as_os_str().to_os_string();
+            with_ext.push(ext);
+            let with_ext = PathBuf::from(with_ext);
+            if self.fs.is_file(&with_ext) {
+                return Some(with_ext);
+            }
+        }
+
+        // Try as directory with init.luau
+        if self.fs.is_dir(path) {
+            let init = path.join("init.luau");
+            if self.fs.is_file(&init) {
+                return Some(init);
+            }
+            let init_lua = path.join("init.lua");
+            if self.fs.is_file(&init_lua) {
+                return Some(init_lua);
+            }
+        }
+
+        // Try adding /init.luau
+        let init = path.join("init.luau");
+        if self.fs.is_file(&init) {
+            return Some(init);
+        }
+
+        None
+    }
+
+    /// Resolve a require path to an absolute path
+    fn resolve_require(&mut self, require_path: &str, caller_dir: &Path) -> Option<PathBuf> {
+        if require_path.starts_with('@') {
+            // Alias path
+            self.resolve_alias(require_path, caller_dir)
+        } else if require_path.starts_with("./") || require_path.starts_with("../") {
+            // Relative path
+            Some(caller_dir.join(require_path))
+        } else if require_path.starts_with('/') {
+            // Absolute path
+            Some(PathBuf::from(require_path))
+        } else if let Some(resolved) = self.resolve_search_path(require_path, caller_dir) {
+            // Bare path found in the paths of a .luaurc file
+            Some(resolved)
+        } else {
+            // Bare path - treat as relative
+            Some(caller_dir.join(require_path))
+        }
+    }
+
+    /// Resolve a bare require path like foo/bar by searching the paths of .luaurc files
+    fn resolve_search_path(&mut self, require_path: &str, caller_dir: &Path) -> Option<PathBuf> {
+        let mut search_dir = caller_dir.to_path_buf();
+        loop {
+            if let Some(cfg) = self.get_config(&search_dir) {
+                for search_path in &cfg.paths {
+                    let resolved = search_dir.join(search_path).join(require_path);
+                    let Some(actual_file) = self.find_module_file(&resolved) else {
+                        continue;
+                    };
+
+                    // Record the mapping for runtime resolution, the same as for aliases,
+                    // since the .luaurc files are not available to standalone binaries
+                    self.aliases_logical
+                        .insert(require_path.to_string(), actual_file);
+
+                    return Some(resolved);
+                }
+            }
+
+            if !search_dir.pop() {
+                break;
+            }
+        }
+
+        None
+    }
+
+    /// Resolve an alias like @packages/Foo to an absolute path
+    fn resolve_alias(&mut self, alias: &str, caller_dir: &Path) -> Option<PathBuf> {
+        let alias_path = alias.strip_prefix('@')?;
+
+        let (alias_name, rest) = match alias_path.find('/') {
+            Some(idx) => (&alias_path[..idx], Some(&alias_path[idx + 1..])),
+            None => (alias_path, None),
+        };
+
+        // Special case: @self refers to caller's directory
+        if alias_name == "self" {
+            let mut resolved = caller_dir.to_path_buf();
+            if let Some(rest_path) = rest {
+                resolved = resolved.join(rest_path);
+            }
+            return Some(resolved);
+        }
+
+        // Merge the aliases of all .luaurc files and package manifests, starting from the
+        // root directory, so that nested configs inherit and override those of their ancestors
+        let merged = self.get_aliases(caller_dir);
+        let mut resolved = merged.get(alias_name)?.to_path_buf();
+        if let Some(rest_path) = rest {
+            resolved = resolved.join(rest_path);
+        }
+
+        // Record the alias mapping for runtime resolution
+        // Store logical path (will be relativized at the end)
+        if let Some(actual_file) = self.find_module_file(&resolved) {
+            self.aliases_logical
+                .insert(format!("@{}", alias_path), actual_file);
+        }
+
+        Some(resolved)
+    }
+
+    /// Get or load a .luaurc config for a directory
+    fn get_config(&mut self, dir: &Path) -> Option<LuauConfig> {
+        if let Some(cached) = self.configs.get(dir) {
+            return cached.clone();
+        }
+
+        let config_path = dir.join(".luaurc");
+        let config = if self.fs.exists(&config_path) {
+            self.fs
+                .read(&config_path)
+                .ok()
+                .and_then(|content| LuauConfig::parse(&content))
+        } else {
+            None
+        };
+
+        self.configs.insert(dir.to_path_buf(), config.clone());
+        config
+    }
+
+    /// Get or discover the aliases of all .luaurc files and package manifests for a directory
+    fn get_aliases(&mut self, dir: &Path) -> MergedAliases {
+        if let Some(cached) = self.merged_aliases.get(dir) {
+            return cached.clone();
+        }
+
+        let aliases = MergedAliases::discover_with(dir, |path| self.fs.read(path).ok());
+
+        self.merged_aliases
+            .insert(dir.to_path_buf(), aliases.clone());
+        aliases
+    }
+}
+
+/// Get the location that the `script` global refers to for a file, which is the
+/// file without its extension, or the directory for `init.luau` and `init.lua` files.
+fn script_location(file_path: &Path) -> PathBuf {
+    let is_init = file_path.file_stem().is_some_and(|stem| stem == "init");
+    if is_init {
+        file_path.parent().unwrap_or(file_path).to_path_buf()
+    } else {
+        file_path.with_extension("")
+    }
+}
+
+/// Resolve the steps of a `script` expression, starting from the given script location.
+/// Children are looked up directly in the file system, without any project file mappings.
+fn resolve_script_steps(location: &Path, steps: &[ScriptStep]) -> PathBuf {
+    let mut resolved = location.to_path_buf();
+    for step in steps {
+        match step {
+            ScriptStep::Parent => {
+                resolved.pop();
+            }
+            ScriptStep::Child(name) => resolved.push(name),
+        }
+    }
+    resolved
+}
+
+/// Check if a path is inside of a package index directory, such as `Packages/_Index`
+fn is_in_package_dir(path: &Path) -> bool {
+    path.components().any(|component| {
+        PACKAGE_INDEX_DIRS
+            .iter()
+            .any(|dir| component.as_os_str() == *dir)
+    })
+}
+
+/// Normalize an absolute path into a portable bundle key.
+/// Makes the path relative to base_dir with a leading '/', using forward
+/// slashes on all platforms. Falls back to the full path if it's outside
+/// base_dir.
+#[must_use]
+pub fn normalize_bundle_path(path: &Path, base_dir: &Path) -> String {
+    if let Ok(relative) = path.strip_prefix(base_dir) {
+        format!("/{}", relative.display()).replace('\\', "/")
+    } else {
+        path.display().to_string().replace('\\', "/")
+    }
+}
+
+/// Create a source map from bundle paths to original file paths.
+/// Original paths are relative to the given root directory, without a
+/// leading '/', and fall back to the full path if outside of the root.
+#[must_use]
+pub fn create_source_map(
+    sources: &HashMap<String, PathBuf>,
+    root: &Path,
+) -> HashMap<String, String> {
+    sources
+        .iter()
+        .map(|(key, canonical)| {
+            let original = match canonical.strip_prefix(root) {
+                Ok(relative) => relative.display().to_string(),
+                Err(_) => canonical.display().to_string(),
+            };
+            (key.clone(), original.replace('\\', "/"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::fs::MemoryFs;
+
+    // -- normalize_bundle_path (relative to base_dir) --
+
+    #[test]
+    fn unix_simple_relative() {
+        let result = normalize_bundle_path(
+            Path::new("/home/user/project/src/main.luau"),
+            Path::new("/home/user/project"),
+        );
+        assert_eq!(result, "/src/main.luau");
+    }
+
+    #[test]
+    fn unix_nested_relative() {
+        let result = normalize_bundle_path(
+            Path::new("/home/user/project/src/lib/utils/format.luau"),
+            Path::new("/home/user/project"),
+        );
+        assert_eq!(result, "/src/lib/utils/format.luau");
+    }
+
+    #[test]
+    fn unix_file_at_root() {
+        let result = normalize_bundle_path(
+            Path::new("/home/user/project/init.luau"),
+            Path::new("/home/user/project"),
+        );
+        assert_eq!(result, "/init.luau");
+    }
+
+    #[test]
+    fn unix_outside_base_dir() {
+        let result = normalize_bundle_path(
+            Path::new("/other/location/lib.luau"),
+            Path::new("/home/user/project"),
+        );
+        assert_eq!(result, "/other/location/lib.luau");
+    }
+
+    #[test]
+    fn unix_packages_alias() {
+        let result = normalize_bundle_path(
+            Path::new("/home/user/project/Packages/Commandline.luau"),
+            Path::new("/home/user/project"),
+        );
+        assert_eq!(result, "/Packages/Commandline.luau");
+    }
+
+    #[test]
+    fn unix_init_luau_in_subdir() {
+        let result = normalize_bundle_path(
+            Path::new("/home/user/project/Source/MyModule/init.luau"),
+            Path::new("/home/user/project"),
+        );
+        assert_eq!(result, "/Source/MyModule/init.luau");
+    }
+
+    // -- Windows path simulation --
+    //
+    // On non-Windows, Path::new("C:\\foo\\bar") is treated as a single
+    // component, so strip_prefix won't match. We test the backslash
+    // replacement by constructing OsStrings that contain backslashes,
+    // which is what actually happens on Windows.
+
+    #[test]
+    fn backslashes_in_relative_component() {
+        // Simulate what Windows produces after strip_prefix: the relative
+        // portion contains backslashes. We can't use strip_prefix
+        // cross-platform, so test the replace logic directly.
+        let with_backslash = format!("/{}", "Packages\\Commandline.luau");
+        let normalized = with_backslash.replace('\\', "/");
+        assert_eq!(normalized, "/Packages/Commandline.luau");
+    }
+
+    #[test]
+    fn backslashes_deeply_nested() {
+        let with_backslash = format!("/{}", "Source\\Lib\\Utils\\format.luau");
+        let normalized = with_backslash.replace('\\', "/");
+        assert_eq!(normalized, "/Source/Lib/Utils/format.luau");
+    }
+
+    #[test]
+    fn mixed_separators() {
+        let with_mixed = format!("/{}", "Source/Lib\\Utils\\format.luau");
+        let normalized = with_mixed.replace('\\', "/");
+        assert_eq!(normalized, "/Source/Lib/Utils/format.luau");
+    }
+
+    #[test]
+    fn windows_drive_letter_fallback() {
+        // When path is outside base_dir, the full path is used as-is.
+        // On Windows this would include the drive letter with backslashes.
+        let full_path = "C:\\Users\\dev\\project\\lib.luau";
+        let normalized = full_path.replace('\\', "/");
+        assert_eq!(normalized, "C:/Users/dev/project/lib.luau");
+    }
+
+    #[test]
+    fn windows_unc_path_fallback() {
+        let unc = "\\\\server\\share\\project\\lib.luau";
+        let normalized = unc.replace('\\', "/");
+        assert_eq!(normalized, "//server/share/project/lib.luau");
+    }
+
+    #[test]
+    fn no_double_slashes_on_unix() {
+        let result = normalize_bundle_path(
+            Path::new("/home/user/project/src/main.luau"),
+            Path::new("/home/user/project"),
+        );
+        assert!(!result.contains("//"), "Should not produce double slashes");
+    }
+
+    #[test]
+    fn empty_relative_produces_root() {
+        // When canonical == base_dir, relative is empty -> "/"
+        let result = normalize_bundle_path(
+            Path::new("/home/user/project"),
+            Path::new("/home/user/project"),
+        );
+        assert_eq!(result, "/");
+    }
+
+    #[test]
+    fn dot_luau_and_dot_lua_extensions() {
+        let luau = normalize_bundle_path(Path::new("/project/src/mod.luau"), Path::new("/project"));
+        let lua = normalize_bundle_path(Path::new("/project/src/mod.lua"), Path::new("/project"));
+        assert_eq!(luau, "/src/mod.luau");
+        assert_eq!(lua, "/src/mod.lua");
+    }
+
+    #[test]
+    fn path_with_spaces() {
+        let result = normalize_bundle_path(
+            Path::new("/home/user/my project/src/main.luau"),
+            Path::new("/home/user/my project"),
+        );
+        assert_eq!(result, "/src/main.luau");
+    }
+
+    // -- create_source_map (relative to the build directory) --
+
+    #[test]
+    fn source_map_relative_to_root() {
+        let sources = HashMap::from([
+            (
+                "/main.luau".to_string(),
+                PathBuf::from("/home/user/repo/tools/cli/main.luau"),
+            ),
+            (
+                "/lib/util.luau".to_string(),
+                PathBuf::from("/home/user/repo/tools/cli/lib/util.luau"),
+            ),
+        ]);
+        let map = create_source_map(&sources, Path::new("/home/user/repo"));
+        assert_eq!(map["/main.luau"], "tools/cli/main.luau");
+        assert_eq!(map["/lib/util.luau"], "tools/cli/lib/util.luau");
+    }
+
+    #[test]
+    fn source_map_outside_root() {
+        let sources = HashMap::from([(
+            "/other/lib.luau".to_string(),
+            PathBuf::from("/other/lib.luau"),
+        )]);
+        let map = create_source_map(&sources, Path::new("/home/user/repo"));
+        assert_eq!(map["/other/lib.luau"], "/other/lib.luau");
+    }
+
+    #[test]
+    fn path_with_unicode() {
+        let result = normalize_bundle_path(
+            Path::new("/home/user/проект/src/main.luau"),
+            Path::new("/home/user/проект"),
+        );
+        assert_eq!(result, "/src/main.luau");
+    }
+
+    // -- script-based requires (wally and pesde package stubs) --
+
+    fn bundler() -> Bundler {
+        Bundler::new(Path::new("/project/src/main.luau")).unwrap()
+    }
+
+    #[test]
+    fn script_steps_wally_stub() {
+        let steps = bundler()
+            .parse_script_steps(r#"script.Parent._Index["sleitnick_signal@1.5.0"]["signal"]"#);
+        assert_eq!(
+            steps,
+            vec![
+                ScriptStep::Parent,
+                ScriptStep::Child("_Index".to_string()),
+                ScriptStep::Child("sleitnick_signal@1.5.0".to_string()),
+                ScriptStep::Child("signal".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn script_steps_wait_for_child() {
+        let steps = bundler()
+            .parse_script_steps(r#"script.Parent:WaitForChild('Packages'):FindFirstChild("foo")"#);
+        assert_eq!(
+            steps,
+            vec![
+                ScriptStep::Parent,
+                ScriptStep::Child("Packages".to_string()),
+                ScriptStep::Child("foo".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn script_require_regex_matches_stub() {
+        let source = r#"return require(script.Parent._Index["a_b@1.0.0"]["b"])"#;
+        let captured = bundler()
+            .script_require_regex
+            .captures(source)
+            .and_then(|cap| cap.get(1))
+            .map(|m| m.as_str().to_string());
+        assert_eq!(
+            captured.as_deref(),
+            Some(r#"script.Parent._Index["a_b@1.0.0"]["b"]"#)
+        );
+    }
+
+    #[test]
+    fn script_location_of_files() {
+        assert_eq!(
+            script_location(Path::new("/project/Packages/Signal.lua")),
+            PathBuf::from("/project/Packages/Signal")
+        );
+        assert_eq!(
+            script_location(Path::new("/project/src/lib/init.luau")),
+            PathBuf::from("/project/src/lib")
+        );
+    }
+
+    #[test]
+    fn script_steps_resolve_into_index() {
+        let steps = bundler()
+            .parse_script_steps(r#"script.Parent._Index["sleitnick_signal@1.5.0"]["signal"]"#);
+        let resolved = resolve_script_steps(Path::new("/project/Packages/Signal"), &steps);
+        assert_eq!(
+            resolved,
+            PathBuf::from("/project/Packages/_Index/sleitnick_signal@1.5.0/signal")
+        );
+        assert!(is_in_package_dir(&resolved));
+        assert!(!is_in_package_dir(Path::new("/project/src/lib")));
+    }
+
+    // -- symlinked dependencies --
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_dirs_keep_logical_paths() {
+        use std::os::unix::fs::symlink;
+
+        let dir = std::env::temp_dir().join(format!("lune-bundler-links-{}", std::process::id()));
+        let root = dir.join("project");
+        let shared = dir.join("shared").join("pkg");
+        fs::create_dir_all(root.join("deps")).unwrap();
+        fs::create_dir_all(&shared).unwrap();
+        fs::write(root.join(".luaurc"), "{}").unwrap();
+        fs::write(
+            root.join("main.luau"),
+            "local a = require(\"./deps/a\")\nlocal b = require(\"./deps/b\")",
+        )
+        .unwrap();
+        fs::write(shared.join("init.luau"), "return 1").unwrap();
+        symlink(&shared, root.join("deps").join("a")).unwrap();
+        symlink(&shared, root.join("deps").join("b")).unwrap();
+
+        let entry = root.join("main.luau");
+        let mut bundler = Bundler::new(&entry).unwrap();
+        let result = bundler.bundle(&entry);
+        fs::remove_dir_all(&dir).unwrap();
+        let result = result.unwrap();
+
+        assert_eq!(bundler.base_dir(), root);
+        let mut keys = result.files.keys().cloned().collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(keys, vec!["/deps/a/init.luau", "/main.luau"]);
+        assert_eq!(result.links["/deps/b/init.luau"], "/deps/a/init.luau");
+        assert_eq!(result.required_by["/deps/a/init.luau"], vec!["/main.luau"]);
+    }
+
+    // -- namespaces of the host application --
+
+    #[test]
+    fn namespace_requires_are_not_bundled() {
+        let dir = std::env::temp_dir().join(format!("lune-bundler-ns-{}", std::process::id()));
+        let local = dir.join("mycompany");
+        fs::create_dir_all(&local).unwrap();
+        fs::write(
+            dir.join(".luaurc"),
+            r#"{"aliases":{"mycompany":"./mycompany"}}"#,
+        )
+        .unwrap();
+        fs::write(local.join("http.luau"), "return 1").unwrap();
+        fs::write(
+            dir.join("main.luau"),
+            "local http = require(\"@mycompany/http\")",
+        )
+        .unwrap();
+
+        let entry = dir.join("main.luau");
+        let manifest = NamespaceManifest {
+            namespaces: vec!["@mycompany".to_string()],
+        };
+        let mut bundler = Bundler::new(&entry).unwrap().with_namespaces(manifest);
+        let result = bundler.bundle(&entry);
+        fs::remove_dir_all(&dir).unwrap();
+        let result = result.unwrap();
+
+        assert_eq!(result.files.len(), 1);
+        assert!(result.aliases.is_empty());
+        assert_eq!(
+            result.namespace_requires.iter().collect::<Vec<_>>(),
+            vec!["@mycompany/http"]
+        );
+    }
+
+    #[test]
+    fn platform_requires_are_parsed() {
+        let source = br#"
+            local a = require.platform("windows", "./win", "./unix")
+            local b = require.platform({ linux = "./linux", ["macos, ios"] = './apple' })
+        "#;
+        let parsed = bundler().parse_requires(source);
+        assert!(parsed.requires.is_empty());
+        assert_eq!(
+            parsed.platform_requires,
+            vec![
+                vec![
+                    ("windows".to_string(), "./win".to_string()),
+                    ("default".to_string(), "./unix".to_string()),
+                ],
+                vec![
+                    ("linux".to_string(), "./linux".to_string()),
+                    ("macos, ios".to_string(), "./apple".to_string()),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn platform_requires_only_bundle_target_variant() {
+        let dir =
+            std::env::temp_dir().join(format!("lune-bundler-platform-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("win.luau"), "return 1").unwrap();
+        fs::write(dir.join("unix.luau"), "return 2").unwrap();
+        fs::write(
+            dir.join("main.luau"),
+            "local impl = require.platform(\"windows\", \"./win\", \"./unix\")",
+        )
+        .unwrap();
+
+        let entry = dir.join("main.luau");
+        let targeted = Bundler::new(&entry)
+            .unwrap()
+            .with_platform(Platform::new("linux", "unix"))
+            .bundle(&entry);
+        let untargeted = Bundler::new(&entry).unwrap().bundle(&entry);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut targeted = targeted.unwrap().files.into_keys().collect::<Vec<_>>();
+        targeted.sort();
+        assert_eq!(targeted, vec!["/main.luau", "/unix.luau"]);
+        assert_eq!(untargeted.unwrap().files.len(), 3);
+    }
+
+    #[test]
+    fn inline_entry_source_is_not_read_from_disk() {
+        let dir = std::env::temp_dir().join(format!("lune-bundler-inline-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("dep.luau"), "return 1").unwrap();
+
+        let entry = dir.join("stdin.luau");
+        let result = Bundler::new(&entry)
+            .unwrap()
+            .with_entry_source(b"return require(\"./dep\")".to_vec())
+            .bundle(&entry);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let result = result.unwrap();
+        assert_eq!(
+            result.files.get("/stdin.luau").map(Vec::as_slice),
+            Some(b"return require(\"./dep\")".as_slice())
+        );
+        assert!(result.files.contains_key("/dep.luau"));
+    }
+
+    // -- fixture trees, resolved through an in-memory file system --
+
+    /// Bundles a fixture tree, and renders everything that was
+    /// resolved as sorted lines, for comparing against a snapshot
+    fn snapshot(fs: MemoryFs, entry: &str) -> Vec<String> {
+        let entry = Path::new(entry);
+        let result = Bundler::new_with_fs(entry, fs)
+            .unwrap()
+            .bundle(entry)
+            .unwrap();
+
+        let mut files = result
+            .files
+            .keys()
+            .map(|path| format!("file {path}"))
+            .collect::<Vec<_>>();
+        let mut aliases = result
+            .aliases
+            .iter()
+            .map(|(alias, path)| format!("alias {alias} -> {path}"))
+            .collect::<Vec<_>>();
+        let mut links = result
+            .links
+            .iter()
+            .map(|(link, path)| format!("link {link} -> {path}"))
+            .collect::<Vec<_>>();
+        let mut required_by = result
+            .required_by
+            .iter()
+            .map(|(path, requirers)| format!("required {path} <- {}", requirers.join(", ")))
+            .collect::<Vec<_>>();
+
+        let mut lines = Vec::new();
+        for section in [&mut files, &mut aliases, &mut links, &mut required_by] {
+            section.sort();
+            lines.append(section);
+        }
+        lines
+    }
+
+    #[test]
+    fn fixture_aliases_and_search_paths() {
+        let fs = MemoryFs::new()
+            .with_file(
+                "/project/.luaurc",
+                r#"{"aliases":{"pkg":"./Packages"},"paths":["./modules"]}"#,
+            )
+            .with_file(
+                "/project/src/main.luau",
+                concat!(
+                    "local foo = require(\"@pkg/foo\")\n",
+                    "local util = require(\"@self/util\")\n",
+                    "local strings = require(\"utils/strings\")\n",
+                ),
+            )
+            .with_file("/project/src/util.luau", "return 1")
+            .with_file("/project/Packages/foo.luau", "return 2")
+            .with_file("/project/modules/utils/strings.luau", "return 3");
+        assert_eq!(
+            snapshot(fs, "/project/src/main.luau"),
+            [
+                "file /Packages/foo.luau",
+                "file /modules/utils/strings.luau",
+                "file /src/main.luau",
+                "file /src/util.luau",
+                "alias @pkg/foo -> /Packages/foo.luau",
+                "alias utils/strings -> /modules/utils/strings.luau",
+                "required /Packages/foo.luau <- /src/main.luau",
+                "required /modules/utils/strings.luau <- /src/main.luau",
+                "required /src/util.luau <- /src/main.luau",
+            ]
+        );
+    }
+
+    #[test]
+    fn fixture_package_manifest_aliases() {
+        let fs = MemoryFs::new()
+            .with_file(
+                "/project/wally.toml",
+                concat!(
+                    "[dependencies]\n",
+                    "Roact = \"roblox/roact@1.4.0\"\n",
+                    "Signal = \"sleitnick/signal@1.5.0\"\n",
+                ),
+            )
+            .with_file(
+                "/project/.luaurc",
+                r#"{"aliases":{"signal":"./vendor/signal"}}"#,
+            )
+            .with_file(
+                "/project/src/main.luau",
+                concat!(
+                    "local roact = require(\"@roact\")\n",
+                    "local signal = require(\"@signal\")\n",
+                ),
+            )
+            .with_file("/project/Packages/Roact.lua", "return 1")
+            .with_file("/project/Packages/Signal.lua", "return 2")
+            .with_file("/project/vendor/signal.luau", "return 3");
+        assert_eq!(
+            snapshot(fs, "/project/src/main.luau"),
+            [
+                "file /Packages/Roact.lua",
+                "file /src/main.luau",
+                "file /vendor/signal.luau",
+                "alias @roact -> /Packages/Roact.lua",
+                "alias @signal -> /vendor/signal.luau",
+                "required /Packages/Roact.lua <- /src/main.luau",
+                "required /vendor/signal.luau <- /src/main.luau",
+            ]
+        );
+    }
+
+    #[test]
+    fn fixture_nested_config_aliases() {
+        let fs = MemoryFs::new()
+            .with_file(
+                "/project/.luaurc",
+                r#"{"aliases":{"Utils":"./utils","shared":"./shared"}}"#,
+            )
+            .with_file(
+                "/project/src/.luaurc",
+                r#"{"aliases":{"shared":"./shared"}}"#,
+            )
+            .with_file(
+                "/project/src/main.luau",
+                concat!(
+                    "local utils = require(\"@utils/strings\")\n",
+                    "local shared = require(\"@shared\")\n",
+                ),
+            )
+            .with_file("/project/utils/strings.luau", "return 1")
+            .with_file("/project/shared.luau", "return 2")
+            .with_file("/project/src/shared.luau", "return 3");
+        assert_eq!(
+            snapshot(fs, "/project/src/main.luau"),
+            [
+                "file /src/main.luau",
+                "file /src/shared.luau",
+                "file /utils/strings.luau",
+                "alias @shared -> /src/shared.luau",
+                "alias @utils/strings -> /utils/strings.luau",
+                "required /src/shared.luau <- /src/main.luau",
+                "required /utils/strings.luau <- /src/main.luau",
+            ]
+        );
+    }
+
+    #[test]
+    fn fixture_init_files() {
+        let fs = MemoryFs::new()
+            .with_file(
+                "/project/main.luau",
+                "local lib = require(\"./lib\")\nlocal legacy = require(\"./legacy\")",
+            )
+            .with_file("/project/lib/init.luau", "return require(\"@self/helper\")")
+            .with_file("/project/lib/helper.luau", "return 1")
+            .with_file("/project/legacy/init.lua", "return 2");
+        assert_eq!(
+            snapshot(fs, "/project/main.luau"),
+            [
+                "file /legacy/init.lua",
+                "file /lib/helper.luau",
+                "file /lib/init.luau",
+                "file /main.luau",
+                "required /legacy/init.lua <- /main.luau",
+                "required /lib/helper.luau <- /lib/init.luau",
+                "required /lib/init.luau <- /main.luau",
+            ]
+        );
+    }
+
+    #[test]
+    fn fixture_requires_outside_of_root() {
+        let fs = MemoryFs::new()
+            .with_file("/work/project/.luaurc", "{}")
+            .with_file(
+                "/work/project/main.luau",
+                "return require(\"../shared/util\")",
+            )
+            .with_file("/work/shared/util.luau", "return 1");
+        assert_eq!(
+            snapshot(fs, "/work/project/main.luau"),
+            [
+                "file /project/main.luau",
+                "file /shared/util.luau",
+                "required /shared/util.luau <- /project/main.luau",
+            ]
+        );
+    }
+
+    #[test]
+    fn fixture_cycles() {
+        let fs = MemoryFs::new()
+            .with_file("/project/main.luau", "return require(\"./a\")")
+            .with_file("/project/a.luau", "return require(\"./b\")")
+            .with_file("/project/b.luau", "return require(\"./a\")");
+        assert_eq!(
+            snapshot(fs, "/project/main.luau"),
+            [
+                "file /a.luau",
+                "file /b.luau",
+                "file /main.luau",
+                "required /a.luau <- /b.luau, /main.luau",
+                "required /b.luau <- /a.luau",
+            ]
+        );
+    }
+
+    #[test]
+    fn fixture_data_files() {
+        let fs = MemoryFs::new()
+            .with_file(
+                "/project/main.luau",
+                "local config = require(\"./config.json\")\nlocal dev = require(\"./dev.toml\")",
+            )
+            .with_file(
+                "/project/config.json",
+                r#"{"script":"require('./missing')"}"#,
+            )
+            .with_file("/project/dev.toml", "debug = true")
+            .with_file("/project/config.yaml", "unused: true");
+        assert_eq!(
+            snapshot(fs, "/project/main.luau"),
+            [
+                "file /config.json",
+                "file /dev.toml",
+                "file /main.luau",
+                "required /config.json <- /main.luau",
+                "required /dev.toml <- /main.luau",
+            ]
+        );
+    }
+
+    #[test]
+    fn fixture_require_graph() {
+        let fs = MemoryFs::new()
+            .with_file("/project/.luaurc", r#"{"aliases":{"pkg":"./Packages"}}"#)
+            .with_file(
+                "/project/main.luau",
+                "local a = require(\"./a\")\nlocal foo = require(\"@pkg/foo\")",
+            )
+            .with_file("/project/a.luau", "return require(\"./Packages/foo\")")
+            .with_file("/project/Packages/foo.luau", "return 1");
+        let entry = Path::new("/project/main.luau");
+        let result = Bundler::new_with_fs(entry, fs)
+            .unwrap()
+            .bundle(entry)
+            .unwrap();
+
+        assert_eq!(
+            result.requires["/main.luau"],
+            BTreeMap::from([
+                ("./a".to_string(), "/a.luau".to_string()),
+                ("@pkg/foo".to_string(), "/Packages/foo.luau".to_string()),
+            ])
+        );
+
+        let graph = result.require_graph();
+        assert_eq!(
+            graph.modules,
+            ["/Packages/foo.luau", "/a.luau", "/main.luau"]
+        );
+        let main = graph.numbers()["/main.luau"];
+        assert_eq!(graph.resolve(main, "@pkg/foo"), Some("/Packages/foo.luau"));
+    }
+
+    #[test]
+    fn fixture_remote_modules() {
+        let dir = std::env::temp_dir().join(format!("lune-bundler-remote-{}", std::process::id()));
+        let cache = RemoteCache::new(&dir);
+        let mut lockfile = Lockfile::default();
+        for (url, source) in [
+            (
+                "https://example.com/lib/mod.luau",
+                "return require(\"./util\")",
+            ),
+            ("https://example.com/lib/util.luau", "return 1"),
+        ] {
+            lockfile.insert(url, cache.write(source.as_bytes()).unwrap());
+        }
+
+        let fs = MemoryFs::new().with_file(
+            "/project/main.luau",
+            "return require(\"https://example.com/lib/mod\")",
+        );
+        let entry = Path::new("/project/main.luau");
+        let result = Bundler::new_with_fs(entry, fs)
+            .unwrap()
+            .with_remote_modules(lockfile, cache.clone())
+            .bundle(entry)
+            .unwrap();
+        assert_eq!(
+            result.files["/@remote/example.com/lib/util.luau"],
+            b"return 1"
+        );
+        assert_eq!(
+            result.aliases["https://example.com/lib/mod.luau"],
+            "/@remote/example.com/lib/mod.luau"
+        );
+
+        // Modules that were never required when running can not be vendored
+        let fs = MemoryFs::new().with_file(
+            "/project/main.luau",
+            "return require(\"https://example.com/other.luau\")",
+        );
+        let result = Bundler::new_with_fs(entry, fs)
+            .unwrap()
+            .with_remote_modules(Lockfile::default(), cache)
+            .bundle(entry);
+        assert!(result.is_err());
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn fixture_linked_dirs() {
+        let fs = MemoryFs::new()
+            .with_file("/project/.luaurc", "{}")
+            .with_file(
+                "/project/main.luau",
+                "local a = require(\"./deps/a\")\nlocal b = require(\"./deps/b\")",
+            )
+            .with_file("/shared/pkg/init.luau", "return 1")
+            .with_link("/project/deps/a", "/shared/pkg")
+            .with_link("/project/deps/b", "/shared/pkg");
+        assert_eq!(
+            snapshot(fs, "/project/main.luau"),
+            [
+                "file /deps/a/init.luau",
+                "file /main.luau",
+                "link /deps/b/init.luau -> /deps/a/init.luau",
+                "required /deps/a/init.luau <- /main.luau",
+            ]
+        );
+    }
+
+    #[test]
+    fn entry_is_relative_to_expanded_root() {
+        let fs = MemoryFs::new()
+            .with_file("/work/project/.luaurc", "{}")
+            .with_file(
+                "/work/project/main.luau",
+                "return require(\"../shared/util\")",
+            )
+            .with_file("/work/shared/util.luau", "return 1");
+        let entry = Path::new("/work/project/main.luau");
+        let result = Bundler::new_with_fs(entry, fs)
+            .unwrap()
+            .bundle(entry)
+            .unwrap();
+        assert_eq!(result.entry, "/project/main.luau");
+        assert!(result.files.contains_key(&result.entry));
+    }
+
+    #[test]
+    fn extra_entries_are_bundled_with_dependencies() {
+        let fs = MemoryFs::new()
+            .with_file("/project/main.luau", "return require(\"./shared\")")
+            .with_file("/project/tools/fmt.luau", "return require(\"../shared\")")
+            .with_file("/project/tools/lint.luau", "return require(\"./rules\")")
+            .with_file("/project/tools/rules.luau", "return 1")
+            .with_file("/project/shared.luau", "return 2");
+        let entry = Path::new("/project/main.luau");
+        let result = Bundler::new_with_fs(entry, fs)
+            .unwrap()
+            .with_extra_entries([
+                PathBuf::from("/project/tools/lint.luau"),
+                PathBuf::from("/project/tools/fmt.luau"),
+            ])
+            .bundle(entry)
+            .unwrap();
+        assert_eq!(result.entry, "/main.luau");
+        assert_eq!(
+            result.extra_entries,
+            ["/tools/lint.luau", "/tools/fmt.luau"]
+        );
+        assert_eq!(result.files.len(), 5);
+        assert_eq!(
+            result.required_by.get("/shared.luau").unwrap(),
+            &["/main.luau", "/tools/fmt.luau"]
+        );
+    }
+}
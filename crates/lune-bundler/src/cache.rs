@@ -0,0 +1,223 @@
+/*!
+    An on-disk cache of the requires found in bundled files.
+
+    Finding requires means running several regexes over every bundled file,
+    which adds up for projects with thousands of modules. The cache stores the
+    requires found in each file, along with its modification time, size and
+    hash, so that files that have not changed since the last build do not need
+    to be parsed again. Requires are still resolved on every build, since they
+    depend on other files, `.luaurc` configs and package manifests, which may
+    have changed.
+
+    Each project gets its own cache file in the cache directory, which is
+    `~/.lune/cache/bundle` for `lune build`, named after a hash of the project
+    root, and the cache is dropped whenever the version of the bundler changes,
+    since the way requires are found may change with it.
+*/
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::bundler::ParsedRequires;
+
+const CACHE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// NOTE: Files modified this close to when they were cached may have been
+// modified again within the resolution of file system timestamps, so
+// their modification times can not be trusted, the same as in git
+const RACY_WINDOW_NANOS: u128 = 2_000_000_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    cached_at: u128,
+    modified: u128,
+    size: u64,
+    hash: String,
+    requires: ParsedRequires,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    version: String,
+    entries: HashMap<PathBuf, CacheEntry>,
+}
+
+/**
+    A cache of parsed requires for the files of a single project.
+*/
+#[derive(Debug)]
+pub struct BundleCache {
+    path: PathBuf,
+    entries: HashMap<PathBuf, CacheEntry>,
+    seen: HashSet<PathBuf>,
+    hits: usize,
+}
+
+impl BundleCache {
+    /**
+        Loads the cache for the project with the given root directory from the
+        given cache directory, or creates an empty cache if there is none, or it is invalid.
+    */
+    pub fn load(cache_dir: &Path, project_root: &Path) -> Self {
+        let name = format!(
+            "{:x}.json",
+            Sha256::digest(project_root.as_os_str().as_encoded_bytes())
+        );
+        Self::load_from(cache_dir.join(name))
+    }
+
+    fn load_from(path: PathBuf) -> Self {
+        let entries = fs::read(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_slice::<CacheFile>(&contents).ok())
+            .filter(|file| file.version == CACHE_VERSION)
+            .map(|file| file.entries)
+            .unwrap_or_default();
+        Self {
+            path,
+            entries,
+            seen: HashSet::new(),
+            hits: 0,
+        }
+    }
+
+    /**
+        Returns the number of files that were found in the cache, unchanged.
+    */
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /**
+        Returns the cached requires for the given file, if it has not
+        changed since they were cached, or parses them using the given
+        function and caches them otherwise.
+
+        Files are considered unchanged if their modification time and size
+        are the same, or, if either of those changed, or the file was modified
+        right before it was cached, if their hash is the same.
+    */
+    pub fn get_or_parse(
+        &mut self,
+        path: &Path,
+        source: &[u8],
+        parse: impl FnOnce() -> ParsedRequires,
+    ) -> ParsedRequires {
+        self.seen.insert(path.to_path_buf());
+
+        let modified = fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .map_or(0, nanos_since_epoch);
+        let size = source.len() as u64;
+        let now = nanos_since_epoch(SystemTime::now());
+
+        if let Some(entry) = self.entries.get_mut(path) {
+            let is_racy = entry.cached_at < modified.saturating_add(RACY_WINDOW_NANOS);
+            if !is_racy && entry.modified == modified && entry.size == size {
+                self.hits += 1;
+                return entry.requires.clone();
+            }
+            let hash = hash_source(source);
+            if entry.hash == hash {
+                entry.cached_at = now;
+                entry.modified = modified;
+                entry.size = size;
+                self.hits += 1;
+                return entry.requires.clone();
+            }
+        }
+
+        let requires = parse();
+        self.entries.insert(
+            path.to_path_buf(),
+            CacheEntry {
+                cached_at: now,
+                modified,
+                size,
+                hash: hash_source(source),
+                requires: requires.clone(),
+            },
+        );
+        requires
+    }
+
+    /**
+        Saves the cache to disk, dropping any files that were not used in
+        this build, so that the cache does not grow forever as files move.
+
+        Failing to save the cache is not an error, it only makes the next build slower.
+    */
+    pub fn save(mut self) {
+        self.entries.retain(|path, _| self.seen.contains(path));
+        let file = CacheFile {
+            version: CACHE_VERSION.to_string(),
+            entries: self.entries,
+        };
+        if let Some(dir) = self.path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Ok(contents) = serde_json::to_vec(&file) {
+            let _ = fs::write(&self.path, contents);
+        }
+    }
+}
+
+fn nanos_since_epoch(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos())
+}
+
+fn hash_source(source: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(source))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unchanged_files_are_not_parsed_again() {
+        let dir = std::env::temp_dir().join(format!("lune-bundle-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("main.luau");
+        let cache_path = dir.join("cache.json");
+        fs::write(&file, "require(\"./lib\")").unwrap();
+
+        let parsed = || {
+            let mut parsed = ParsedRequires::default();
+            parsed.requires.push("./lib".to_string());
+            parsed
+        };
+
+        let mut cache = BundleCache::load_from(cache_path.clone());
+        let first = cache.get_or_parse(&file, b"require(\"./lib\")", parsed);
+        assert_eq!(cache.hits(), 0);
+        cache.save();
+
+        let mut cache = BundleCache::load_from(cache_path.clone());
+        let second = cache.get_or_parse(&file, b"require(\"./lib\")", || {
+            panic!("unchanged file was parsed again")
+        });
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(first, second);
+
+        // Changed contents must be parsed again, even with the same size
+        fs::write(&file, "require(\"./lob\")").unwrap();
+        let mut cache = BundleCache::load_from(cache_path);
+        let mut parsed_again = false;
+        cache.get_or_parse(&file, b"require(\"./lob\")", || {
+            parsed_again = true;
+            ParsedRequires::default()
+        });
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(parsed_again);
+    }
+}
@@ -0,0 +1,269 @@
+/*!
+    Encryption of the source code and bundled files in standalone binaries.
+
+    Binaries built with `--encrypt-key` store the entry point source and all
+    bundled files as a single encrypted payload, which is only decrypted in
+    memory when the binary starts. The key used to decrypt the payload is either
+    read from the `LUNE_STANDALONE_KEY` environment variable, or, if the binary
+    was built with `--embed-key`, from an obfuscated copy stored in the binary.
+
+    Note that embedding the key only offers basic tamper resistance - anyone with
+    access to the binary and enough determination will be able to recover it.
+*/
+
+use std::{collections::HashMap, env, num::NonZeroU32};
+
+use anyhow::{Context, Result, anyhow, bail};
+use ring::{
+    aead::{Aad, CHACHA20_POLY1305, LessSafeKey, NONCE_LEN, Nonce, UnboundKey},
+    pbkdf2,
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, Serialize};
+
+/// Environment variable that the key for encrypted binaries is read from
+pub const KEY_ENV_VAR: &str = "LUNE_STANDALONE_KEY";
+
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const PBKDF2_ITERATIONS: NonZeroU32 = NonZeroU32::new(100_000).unwrap();
+
+/**
+    A derived key, stored in the binary as the key XOR-ed with a random mask,
+    so that it does not show up as-is when looking through the binary.
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ObfuscatedKey {
+    mask: Vec<u8>,
+    masked: Vec<u8>,
+}
+
+impl ObfuscatedKey {
+    fn new(key: &[u8; KEY_LEN], rng: &SystemRandom) -> Result<Self> {
+        let mask = random_bytes::<KEY_LEN>(rng)?;
+        let masked = key.iter().zip(mask).map(|(k, m)| k ^ m).collect();
+        Ok(Self {
+            mask: mask.to_vec(),
+            masked,
+        })
+    }
+
+    fn reveal(&self) -> Result<[u8; KEY_LEN]> {
+        let key = self
+            .masked
+            .iter()
+            .zip(&self.mask)
+            .map(|(k, m)| k ^ m)
+            .collect::<Vec<_>>();
+        key.try_into()
+            .map_err(|_| anyhow!("embedded key has an invalid length"))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Plaintext {
+    source: Vec<u8>,
+    files: HashMap<String, Vec<u8>>,
+}
+
+/**
+    The encrypted entry point source and bundled files of a standalone binary.
+
+    The entry point path is used as additional authenticated data, so
+    that the payload can not be moved to a different entry point.
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedPayload {
+    salt: Vec<u8>,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+    #[serde(default)]
+    embedded_key: Option<ObfuscatedKey>,
+}
+
+impl EncryptedPayload {
+    /**
+        Encrypts the given entry point source and bundled files using the given key.
+
+        If `embed_key` is `true`, an obfuscated copy of the derived key
+        is stored in the payload, and no key is needed to decrypt it.
+
+        # Errors
+
+        - If no secure random numbers could be generated
+        - If the payload could not be serialized or encrypted
+    */
+    pub fn encrypt(
+        entry_path: &str,
+        source: Vec<u8>,
+        files: HashMap<String, Vec<u8>>,
+        key: &str,
+        embed_key: bool,
+    ) -> Result<Self> {
+        let rng = SystemRandom::new();
+        let salt = random_bytes::<SALT_LEN>(&rng)?;
+        let nonce = random_bytes::<NONCE_LEN>(&rng)?;
+        let derived = derive_key(key, &salt);
+
+        let mut ciphertext = serde_json::to_vec(&Plaintext { source, files })?;
+        cipher(&derived)?
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(entry_path.as_bytes()),
+                &mut ciphertext,
+            )
+            .map_err(|_| anyhow!("failed to encrypt bundled files"))?;
+
+        let embedded_key = if embed_key {
+            Some(ObfuscatedKey::new(&derived, &rng)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            salt: salt.to_vec(),
+            nonce: nonce.to_vec(),
+            ciphertext,
+            embedded_key,
+        })
+    }
+
+    /**
+        Decrypts the payload, returning the entry point source and bundled files.
+
+        Uses the embedded key if there is one, otherwise the given key.
+
+        # Errors
+
+        - If there is no embedded key, and no key was given
+        - If the key is incorrect, or the payload or entry path have been modified
+    */
+    pub fn decrypt(
+        &self,
+        entry_path: &str,
+        key: Option<&str>,
+    ) -> Result<(Vec<u8>, HashMap<String, Vec<u8>>)> {
+        let derived = match (&self.embedded_key, key) {
+            (Some(embedded), _) => embedded.reveal()?,
+            (None, Some(key)) => derive_key(key, &self.salt),
+            (None, None) => bail!(
+                "this binary is encrypted - the key to decrypt it must be given using {KEY_ENV_VAR}"
+            ),
+        };
+        let nonce = Nonce::try_assume_unique_for_key(&self.nonce)
+            .map_err(|_| anyhow!("encrypted payload has an invalid nonce"))?;
+
+        let mut in_out = self.ciphertext.clone();
+        let plaintext = cipher(&derived)?
+            .open_in_place(nonce, Aad::from(entry_path.as_bytes()), &mut in_out)
+            .map_err(|_| {
+                anyhow!(
+                    "failed to decrypt binary - the key is incorrect, or the binary was modified"
+                )
+            })?;
+
+        let Plaintext { source, files } =
+            serde_json::from_slice(plaintext).context("decrypted payload is invalid")?;
+        Ok((source, files))
+    }
+
+    /**
+        Decrypts the payload using the key from the `LUNE_STANDALONE_KEY`
+        environment variable, if the payload does not have an embedded key.
+
+        # Errors
+
+        See [`EncryptedPayload::decrypt`].
+    */
+    pub fn decrypt_from_env(
+        &self,
+        entry_path: &str,
+    ) -> Result<(Vec<u8>, HashMap<String, Vec<u8>>)> {
+        let key = env::var(KEY_ENV_VAR).ok();
+        self.decrypt(entry_path, key.as_deref())
+    }
+}
+
+fn derive_key(key: &str, salt: &[u8]) -> [u8; KEY_LEN] {
+    let mut derived = [0u8; KEY_LEN];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        PBKDF2_ITERATIONS,
+        salt,
+        key.as_bytes(),
+        &mut derived,
+    );
+    derived
+}
+
+fn cipher(key: &[u8; KEY_LEN]) -> Result<LessSafeKey> {
+    let key = UnboundKey::new(&CHACHA20_POLY1305, key)
+        .map_err(|_| anyhow!("failed to create encryption key"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+fn random_bytes<const N: usize>(rng: &SystemRandom) -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    rng.fill(&mut bytes)
+        .map_err(|_| anyhow!("failed to generate secure random bytes"))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files() -> HashMap<String, Vec<u8>> {
+        HashMap::from([("/lib.luau".to_string(), b"return 1".to_vec())])
+    }
+
+    #[test]
+    fn roundtrip_with_key() {
+        let payload = EncryptedPayload::encrypt(
+            "/main.luau",
+            b"print(1)".to_vec(),
+            files(),
+            "hunter2",
+            false,
+        )
+        .unwrap();
+        assert!(!payload.ciphertext.windows(8).any(|w| w == b"print(1)"));
+
+        let (source, decrypted) = payload.decrypt("/main.luau", Some("hunter2")).unwrap();
+        assert_eq!(source, b"print(1)");
+        assert_eq!(decrypted, files());
+    }
+
+    #[test]
+    fn roundtrip_with_embedded_key() {
+        let payload =
+            EncryptedPayload::encrypt("/main.luau", b"print(1)".to_vec(), files(), "hunter2", true)
+                .unwrap();
+        let (source, _) = payload.decrypt("/main.luau", None).unwrap();
+        assert_eq!(source, b"print(1)");
+    }
+
+    #[test]
+    fn wrong_key_or_missing_key_fails() {
+        let payload = EncryptedPayload::encrypt(
+            "/main.luau",
+            b"print(1)".to_vec(),
+            files(),
+            "hunter2",
+            false,
+        )
+        .unwrap();
+        assert!(payload.decrypt("/main.luau", Some("hunter3")).is_err());
+        assert!(payload.decrypt("/main.luau", None).is_err());
+    }
+
+    #[test]
+    fn tampering_fails() {
+        let mut payload =
+            EncryptedPayload::encrypt("/main.luau", b"print(1)".to_vec(), files(), "hunter2", true)
+                .unwrap();
+        assert!(payload.decrypt("/other.luau", None).is_err());
+        payload.ciphertext[0] ^= 1;
+        assert!(payload.decrypt("/main.luau", None).is_err());
+    }
+}
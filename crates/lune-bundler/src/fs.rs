@@ -0,0 +1,198 @@
+/*!
+    The file system that the bundler resolves and reads files through.
+
+    Bundling normally reads from the real file system, but resolution only ever
+    needs to read files, check what exists, and follow symlinks, so those are
+    abstracted here. This lets the resolution of aliases, init files, requires
+    outside of the project root and cycles be tested against fixture trees
+    that live entirely in memory, without touching the disk, and lets build
+    tools bundle files that only exist in memory.
+*/
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::{Error as IoError, ErrorKind, Result as IoResult},
+    path::{Path, PathBuf},
+};
+
+use lune_utils::path::clean_path_and_make_absolute;
+
+/**
+    A file system that the bundler can resolve and read files through.
+*/
+pub trait BundleFs {
+    /**
+        Reads the full contents of a file.
+
+        # Errors
+
+        - If the file does not exist, or could not be read
+    */
+    fn read(&self, path: &Path) -> IoResult<Vec<u8>>;
+
+    /// Checks if the path exists and is a file, following symlinks
+    fn is_file(&self, path: &Path) -> bool;
+
+    /// Checks if the path exists and is a directory, following symlinks
+    fn is_dir(&self, path: &Path) -> bool;
+
+    /**
+        Resolves all symlinks in the path, returning the path it points to.
+
+        # Errors
+
+        - If the path does not exist
+    */
+    fn canonicalize(&self, path: &Path) -> IoResult<PathBuf>;
+
+    /// Checks if the path exists, as either a file or a directory
+    fn exists(&self, path: &Path) -> bool {
+        self.is_file(path) || self.is_dir(path)
+    }
+}
+
+/**
+    The real file system, used for all builds.
+*/
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsFs;
+
+impl BundleFs for OsFs {
+    fn read(&self, path: &Path) -> IoResult<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn canonicalize(&self, path: &Path) -> IoResult<PathBuf> {
+        path.canonicalize()
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+}
+
+/**
+    A file system that lives entirely in memory, for fixture trees in tests,
+    or for bundling files that were generated without writing them to disk.
+
+    Directories exist implicitly for every ancestor of a file, and
+    symlinks may point to either files or directories.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFs {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+    dirs: BTreeSet<PathBuf>,
+    links: BTreeMap<PathBuf, PathBuf>,
+}
+
+impl MemoryFs {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file with the given contents, and all of its parent directories
+    #[must_use]
+    pub fn with_file(mut self, path: impl AsRef<Path>, contents: impl Into<Vec<u8>>) -> Self {
+        let path = clean_path_and_make_absolute(path);
+        self.dirs
+            .extend(path.ancestors().skip(1).map(Path::to_path_buf));
+        self.files.insert(path, contents.into());
+        self
+    }
+
+    /// Adds a symlink at the given path, pointing to the given absolute target
+    #[must_use]
+    pub fn with_link(mut self, path: impl AsRef<Path>, target: impl AsRef<Path>) -> Self {
+        let path = clean_path_and_make_absolute(path);
+        self.dirs
+            .extend(path.ancestors().skip(1).map(Path::to_path_buf));
+        self.links
+            .insert(path, clean_path_and_make_absolute(target));
+        self
+    }
+
+    /// Follows the symlinks in every component of the path, without checking that it exists
+    fn resolve(&self, path: &Path) -> PathBuf {
+        let mut resolved = PathBuf::new();
+        for component in clean_path_and_make_absolute(path).components() {
+            resolved.push(component);
+            // NOTE: Links pointing to each other would loop forever, so give up on them
+            for _ in 0..32 {
+                match self.links.get(&resolved) {
+                    Some(target) => resolved.clone_from(target),
+                    None => break,
+                }
+            }
+        }
+        resolved
+    }
+}
+
+impl BundleFs for MemoryFs {
+    fn read(&self, path: &Path) -> IoResult<Vec<u8>> {
+        self.files
+            .get(&self.resolve(path))
+            .cloned()
+            .ok_or_else(|| IoError::from(ErrorKind::NotFound))
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.files.contains_key(&self.resolve(path))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.dirs.contains(&self.resolve(path))
+    }
+
+    fn canonicalize(&self, path: &Path) -> IoResult<PathBuf> {
+        let resolved = self.resolve(path);
+        if self.files.contains_key(&resolved) || self.dirs.contains(&resolved) {
+            Ok(resolved)
+        } else {
+            Err(IoError::from(ErrorKind::NotFound))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_fs_has_implicit_dirs() {
+        let fs = MemoryFs::new().with_file("/project/src/main.luau", "return 1");
+        assert!(fs.is_file(Path::new("/project/src/main.luau")));
+        assert!(fs.is_dir(Path::new("/project/src")));
+        assert!(fs.is_dir(Path::new("/project")));
+        assert!(!fs.exists(Path::new("/project/src/other.luau")));
+        assert_eq!(
+            fs.read(Path::new("/project/src/main.luau")).unwrap(),
+            b"return 1"
+        );
+    }
+
+    #[test]
+    fn memory_fs_follows_links() {
+        let fs = MemoryFs::new()
+            .with_file("/shared/pkg/init.luau", "return 1")
+            .with_link("/project/deps/pkg", "/shared/pkg");
+        assert!(fs.is_dir(Path::new("/project/deps/pkg")));
+        assert!(fs.is_file(Path::new("/project/deps/pkg/init.luau")));
+        assert_eq!(
+            fs.canonicalize(Path::new("/project/deps/pkg/init.luau"))
+                .unwrap(),
+            clean_path_and_make_absolute("/shared/pkg/init.luau")
+        );
+        assert!(fs.canonicalize(Path::new("/project/deps/missing")).is_err());
+    }
+}
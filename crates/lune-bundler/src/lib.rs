@@ -0,0 +1,84 @@
+#![allow(clippy::cargo_common_metadata)]
+
+/*!
+    Bundling of Lune scripts into standalone executables.
+
+    This is the library behind `lune build`, for build tools that want to produce
+    standalone executables programmatically. A script is first bundled along with
+    everything it requires, using [`bundle`] or a [`Bundler`] for more control,
+    and the bundle is then appended to a base executable - a Lune binary of the
+    same version - using [`patch_executable`], or [`Metadata`] for more control.
+
+    ```no_run
+    let bundle = lune_bundler::bundle("src/main.luau")?;
+    let base = std::fs::read("lune")?;
+    let exe = lune_bundler::patch_executable(base, &bundle)?;
+    std::fs::write("main", exe)?;
+    # Ok::<(), anyhow::Error>(())
+    ```
+*/
+
+use std::path::Path;
+
+use anyhow::Result;
+
+mod cache;
+
+pub mod bundler;
+pub mod encryption;
+pub mod fs;
+pub mod metadata;
+pub mod module_cache;
+pub mod section;
+pub mod signature;
+
+pub use self::bundler::{BundleResult, Bundler};
+pub use self::fs::{BundleFs, MemoryFs, OsFs};
+pub use self::metadata::Metadata;
+
+/**
+    Bundles the given entry file and all of the files that it
+    requires, resolving them through the real file system.
+
+    See [`Bundler`] for bundling with more options, such as for a specific platform.
+
+    # Errors
+
+    - If the entry file, or any file that it requires, could not be read
+*/
+pub fn bundle(entry_path: impl AsRef<Path>) -> Result<BundleResult> {
+    let entry_path = entry_path.as_ref();
+    Bundler::new(entry_path)?.bundle(entry_path)
+}
+
+/**
+    Creates a standalone executable from the given base executable and bundle,
+    which runs the entry file of the bundle when started.
+
+    The base executable must be a Lune binary of the same version as this
+    library, since the format of the bundle may change between versions.
+
+    See [`Metadata`] for creating executables with more options, such as signing.
+
+    # Errors
+
+    - If the bundle does not contain its entry file
+*/
+pub fn patch_executable(base_exe: Vec<u8>, bundle: &BundleResult) -> Result<Vec<u8>> {
+    Metadata::from_bundle(bundle)?.create_env_patched_bin(base_exe, None)
+}
+
+/**
+    Strips the shebang line from the given source code, if it has one,
+    keeping the newline after it so that line numbers stay the same.
+*/
+#[must_use]
+pub fn strip_shebang(mut contents: Vec<u8>) -> Vec<u8> {
+    if contents.starts_with(b"#!") {
+        if let Some(idx) = contents.iter().position(|&c| c == b'\n') {
+            // Keep the newline to preserve line numbers
+            contents.drain(..idx);
+        }
+    }
+    contents
+}
@@ -0,0 +1,923 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Write;
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use lune_utils::build_info::BuildInfo;
+use lune_utils::exit_codes::ExitCodes;
+use lune_utils::require_graph::RequireGraph;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::bundler::BundleResult;
+use crate::encryption::EncryptedPayload;
+use crate::section::find_metadata_section;
+use crate::signature::{self, MetadataSigningKey, PUBLIC_KEY_LEN, SIGNATURE_LEN};
+
+/// Magic of binaries with metadata in the versioned format
+const MAGIC: &[u8; 8] = b"cr3sc3nv";
+/// Magic of binaries with metadata in the legacy JSON format
+const LEGACY_MAGIC: &[u8; 8] = b"cr3sc3nt";
+/// Magic of binaries with signed metadata in the legacy JSON format
+const LEGACY_MAGIC_SIGNED: &[u8; 8] = b"cr3sc3ns";
+
+/// Version of the format that metadata is serialized in, stored before it -
+/// must be bumped whenever the fields of [`Metadata`] change, since postcard
+/// is not self-describing, and metadata of other versions can not be read
+const FORMAT_VERSION: u8 = 6;
+/// Flag set when the metadata is followed by its signature, see [`crate::signature`]
+const FLAG_SIGNED: u8 = 0x01;
+/// Flag set when the metadata is followed by a checksum of it, to detect corrupted binaries
+const FLAG_CHECKSUM: u8 = 0x02;
+
+/// Length of the SHA-256 checksum stored after metadata
+const CHECKSUM_LEN: usize = 32;
+
+/// Length of the signature and public key stored after signed metadata
+const SIGNATURE_TRAILER_LEN: usize = SIGNATURE_LEN + PUBLIC_KEY_LEN;
+
+fn has_metadata(bytes: &[u8]) -> bool {
+    bytes.ends_with(MAGIC) || bytes.ends_with(LEGACY_MAGIC) || bytes.ends_with(LEGACY_MAGIC_SIGNED)
+}
+
+/// Reads the size stored in the 8 bytes before the magic, at the end of the given metadata
+fn read_trailer_size(bytes: &[u8]) -> Result<usize> {
+    let mut size = [0; 8];
+    size.copy_from_slice(&bytes[bytes.len() - 16..bytes.len() - 8]);
+    Ok(usize::try_from(u64::from_be_bytes(size))?)
+}
+
+/**
+    Returns the length of the metadata appended to the end of the given binary,
+    including its trailer, or `None` if the binary has no appended metadata.
+*/
+fn appended_len(bytes: &[u8]) -> Option<usize> {
+    if !has_metadata(bytes) || bytes.len() < 16 {
+        return None;
+    }
+    let size = read_trailer_size(bytes).ok()?;
+    let trailer_len = if bytes.ends_with(LEGACY_MAGIC_SIGNED) {
+        SIGNATURE_TRAILER_LEN + 16
+    } else {
+        16
+    };
+    size.checked_add(trailer_len)
+        .filter(|len| *len <= bytes.len())
+}
+
+/// Finds metadata either appended to the given executable, or in its metadata section
+fn find_metadata(bytes: &[u8]) -> Option<&[u8]> {
+    if has_metadata(bytes) {
+        Some(bytes)
+    } else {
+        find_metadata_section(bytes).filter(|section| has_metadata(section))
+    }
+}
+
+/// Metadata found in the bytes of a standalone binary, before it is deserialized
+enum Payload<'a> {
+    /// Postcard data in the versioned format, with its checksum and signature verified
+    Versioned(&'a [u8]),
+    /// Metadata in the legacy JSON format, including its trailer
+    Legacy(&'a [u8]),
+}
+
+/**
+    Finds the metadata in the given bytes of a standalone binary, verifying
+    its checksum and signature if it is in the versioned format.
+*/
+fn find_payload(bytes: &[u8]) -> Result<Payload<'_>> {
+    // Minimum size: 8 (magic) + 8 (chunk_size) = 16
+    let Some(bytes) = find_metadata(bytes).filter(|bytes| bytes.len() >= 16) else {
+        bail!("not a standalone binary")
+    };
+    if !bytes.ends_with(MAGIC) {
+        return Ok(Payload::Legacy(bytes));
+    }
+
+    // Extract the chunk, which is the format version, flags,
+    // metadata, and signature and public key if signed
+    let chunk_size = read_trailer_size(bytes)?;
+    let Some(chunk_start) = (bytes.len() - 16).checked_sub(chunk_size) else {
+        bail!("standalone binary has truncated metadata")
+    };
+    let chunk = &bytes[chunk_start..bytes.len() - 16];
+
+    let [version, flags, ..] = *chunk else {
+        bail!("standalone binary has truncated metadata")
+    };
+    if version != FORMAT_VERSION {
+        bail!(
+            "standalone binary has metadata of format version {version}, \
+            but this version of Lune only supports version {FORMAT_VERSION} - \
+            the binary must be built again using this version of Lune"
+        )
+    }
+
+    let trailer_len = if flags & FLAG_SIGNED == 0 {
+        0
+    } else {
+        SIGNATURE_TRAILER_LEN
+    };
+    let Some(signed_len) = chunk.len().checked_sub(trailer_len).filter(|len| *len >= 2) else {
+        bail!("standalone binary has truncated metadata")
+    };
+    let (signed, trailer) = chunk.split_at(signed_len);
+
+    // NOTE: The checksum is verified before the signature, so that corrupted
+    // binaries are reported as such, instead of as having been tampered with
+    let mut data = &signed[2..];
+    if flags & FLAG_CHECKSUM != 0 {
+        let Some(data_len) = data.len().checked_sub(CHECKSUM_LEN) else {
+            bail!("standalone binary has truncated metadata")
+        };
+        let (contents, checksum) = data.split_at(data_len);
+        if Sha256::digest(&signed[..2 + data_len]).as_slice() != checksum {
+            bail!(
+                "standalone binary is corrupted - the checksum of its embedded \
+                payload does not match, it may have been damaged while downloading \
+                or copying, and must be downloaded or built again"
+            )
+        }
+        data = contents;
+    }
+
+    if flags & FLAG_SIGNED != 0 {
+        let (signature, public_key) = trailer.split_at(SIGNATURE_LEN);
+        signature::verify(signed, public_key, signature)?;
+    }
+
+    Ok(Payload::Versioned(data))
+}
+
+/**
+    Metadata for a standalone Lune executable. Can be used to
+    discover and load the source code contained in a standalone binary.
+
+    Stores the entry point source, its path, and all bundled dependencies.
+    The contents of bundled files are generic, so that they may also be read
+    in place from the bytes of a binary, see [`Metadata::from_bytes_mapped`].
+
+    Fields must not be skipped when serializing, since the versioned format
+    uses postcard, which relies on all fields being present, in order.
+    Defaults are only used when reading metadata in the legacy JSON format.
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Metadata<F = Vec<u8>> {
+    /// The entry point source code
+    pub source: Vec<u8>,
+    /// The entry point path (for chunk naming)
+    pub entry_path: String,
+    /// Bundled module files: canonical path -> source
+    #[serde(default)]
+    pub files: HashMap<String, F>,
+    /// Alias mappings: alias (e.g., "@packages/Foo") or bare path found
+    /// through the `paths` of a .luaurc file (e.g., "Foo") -> canonical path
+    #[serde(default)]
+    pub aliases: HashMap<String, String>,
+    /// Files reached through more than one path, such as through symlinks, which
+    /// are only bundled once: bundle path -> bundle path of the bundled file
+    #[serde(default)]
+    pub links: HashMap<String, String>,
+    /// Directory to write crash reports to, if crash reports are enabled
+    #[serde(default)]
+    pub crash_report_dir: Option<String>,
+    /// Whether the author of the binary has opted in to calling runtime reporters,
+    /// see `lune::Runtime::with_reporter` - users may still opt out using the
+    /// `DO_NOT_TRACK` or `LUNE_NO_REPORTING` environment variables
+    #[serde(default)]
+    pub reporting_consent: bool,
+    /// Source map of bundled files: bundle path -> original path, if enabled
+    #[serde(default)]
+    pub source_map: HashMap<String, String>,
+    /// Whether the entry point and bundled files are stored only as bytecode
+    #[serde(default)]
+    pub source_stripped: bool,
+    /// The entry point source and bundled files, if they were encrypted -
+    /// `source` and `files` are empty until decrypted using [`Metadata::decrypt`]
+    #[serde(default)]
+    pub encrypted: Option<EncryptedPayload>,
+    /// Values given using `--define`, exposed to scripts through the `_DEFINES` global
+    #[serde(default)]
+    pub defines: BTreeMap<String, String>,
+    /// Default environment variables, given using `--env-file`, which
+    /// are used unless the same variables are set when the binary runs
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Default arguments, given using `--arg`, which are passed to the
+    /// bundled script before the arguments given when the binary runs
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Process exit codes for each class of failure, read from `lune.toml`
+    #[serde(default)]
+    pub exit_codes: ExitCodes,
+    /// Whether setting up the console for UTF-8 and ANSI escape sequences on Windows is disabled
+    #[serde(default)]
+    pub no_console_setup: bool,
+    /// Information about how the binary was built, exposed to scripts through the `_BUILD` global
+    #[serde(default)]
+    pub build: Option<BuildInfo>,
+    /// Named entry points, given using `--entry`, which are selected by the name that
+    /// the binary is invoked as, or using `--lune-entry`: name -> bundle path of the entry file
+    #[serde(default)]
+    pub entries: BTreeMap<String, String>,
+    /// Bundled files with the same contents as another bundled file, which are only stored
+    /// once when serialized: bundle path -> bundle path of the file that is stored - these
+    /// are only used while serialized, and are restored into `files` when deserializing
+    #[serde(default)]
+    pub duplicates: HashMap<String, String>,
+    /// Requires of all bundled files, resolved when building, if linked - standalone
+    /// binaries then look requires up in this graph instead of resolving their paths
+    #[serde(default)]
+    pub require_graph: Option<RequireGraph>,
+}
+
+impl Metadata {
+    /**
+        Returns whether or not the given bytes of a binary or library are those
+        of a standalone binary, with metadata that can be read using [`Metadata::from_bytes`].
+    */
+    #[must_use]
+    pub fn is_standalone(bytes: &[u8]) -> bool {
+        find_metadata(bytes).is_some()
+    }
+
+    /**
+        Creates new metadata from the given script contents, with no bundled files or options.
+    */
+    #[must_use]
+    pub fn new(script_contents: impl Into<Vec<u8>>, entry_path: impl Into<String>) -> Self {
+        Self {
+            source: script_contents.into(),
+            entry_path: entry_path.into(),
+            files: HashMap::new(),
+            aliases: HashMap::new(),
+            links: HashMap::new(),
+            crash_report_dir: None,
+            reporting_consent: false,
+            source_map: HashMap::new(),
+            source_stripped: false,
+            encrypted: None,
+            defines: BTreeMap::new(),
+            env: BTreeMap::new(),
+            args: Vec::new(),
+            exit_codes: ExitCodes::default(),
+            no_console_setup: false,
+            build: None,
+            entries: BTreeMap::new(),
+            duplicates: HashMap::new(),
+            require_graph: None,
+        }
+    }
+
+    /**
+        Creates new metadata from the given bundle, which runs its entry file,
+        with its shebang line stripped, with no other options.
+
+        # Errors
+
+        - If the bundle does not contain its entry file
+    */
+    pub fn from_bundle(bundle: &BundleResult) -> Result<Self> {
+        let Some(source) = bundle.files.get(&bundle.entry) else {
+            bail!("bundle does not contain its entry file {}", bundle.entry)
+        };
+        Ok(Self {
+            files: bundle.files.clone(),
+            aliases: bundle.aliases.clone(),
+            links: bundle.links.clone(),
+            ..Self::new(crate::strip_shebang(source.clone()), bundle.entry.clone())
+        })
+    }
+
+    /**
+        Encrypts the entry point source and all bundled files using the given key.
+
+        See [`EncryptedPayload::encrypt`] for more information.
+
+        # Errors
+
+        - If the payload could not be encrypted
+    */
+    pub fn encrypt(&mut self, key: &str, embed_key: bool) -> Result<()> {
+        let source = std::mem::take(&mut self.source);
+        let files = std::mem::take(&mut self.files);
+        let payload = EncryptedPayload::encrypt(&self.entry_path, source, files, key, embed_key)?;
+        self.encrypted = Some(payload);
+        Ok(())
+    }
+
+    /**
+        Returns the paths and contents of all bundled files, sorted by path.
+
+        The entry point is also included, under its [`Metadata::entry_path`].
+        Files of encrypted metadata are only included once it has been
+        decrypted using [`Metadata::decrypt`].
+    */
+    pub fn embedded_files(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        let mut files = self
+            .files
+            .iter()
+            .map(|(path, contents)| (path.as_str(), contents.as_slice()))
+            .collect::<Vec<_>>();
+        let has_entry = self.files.contains_key(&self.entry_path);
+        if !has_entry && self.encrypted.is_none() {
+            files.push((self.entry_path.as_str(), self.source.as_slice()));
+        }
+        files.sort_unstable_by_key(|(path, _)| *path);
+        files.into_iter()
+    }
+
+    /**
+        Creates a patched standalone binary from the given base executable
+        contents, and this metadata, signed using the given key, if any.
+
+        The base executable may be any Lune binary of the same version - if
+        it is a standalone binary itself, its appended metadata is replaced.
+
+        # Errors
+
+        - If the metadata could not be serialized
+    */
+    pub fn create_env_patched_bin(
+        &self,
+        base_exe: Vec<u8>,
+        signing_key: Option<&MetadataSigningKey>,
+    ) -> Result<Vec<u8>> {
+        let mut patched_bin = base_exe;
+        if let Some(len) = appended_len(&patched_bin) {
+            patched_bin.truncate(patched_bin.len() - len);
+        }
+        patched_bin.extend_from_slice(&self.to_bytes(signing_key)?);
+        Ok(patched_bin)
+    }
+
+    /**
+        Creates a patched standalone binary from the base executable at the given
+        path, and this metadata, signed using the given key, if any, and writes
+        it to the output path, which is made executable on Unix platforms.
+
+        See [`Metadata::create_env_patched_bin`] for more information.
+
+        # Errors
+
+        - If the base executable could not be read
+        - If the metadata could not be serialized
+        - If the patched binary could not be written
+    */
+    pub fn patch_file(
+        &self,
+        base_path: impl AsRef<Path>,
+        output_path: impl AsRef<Path>,
+        signing_key: Option<&MetadataSigningKey>,
+    ) -> Result<()> {
+        let base_path = base_path.as_ref();
+        let output_path = output_path.as_ref();
+        let base_exe = fs::read(base_path)
+            .with_context(|| format!("failed to read base executable {}", base_path.display()))?;
+        let patched_bin = self.create_env_patched_bin(base_exe, signing_key)?;
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o755); // Read & execute for all, write for owner
+        }
+        options
+            .open(output_path)
+            .and_then(|mut file| file.write_all(&patched_bin))
+            .with_context(|| format!("failed to write {}", output_path.display()))?;
+        Ok(())
+    }
+
+    /**
+        Reads the metadata of the standalone binary or library at the given
+        path, without running it, such as for inspecting the bundled files
+        of binaries built by others.
+
+        Note that WASI modules store their metadata in a data segment
+        instead, and are not supported.
+
+        See [`Metadata::from_bytes`] for more information.
+
+        # Errors
+
+        - If the file could not be read
+        - If the file is not a standalone binary, or its metadata is invalid
+    */
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        Self::from_bytes(bytes)
+            .with_context(|| format!("failed to read metadata of {}", path.display()))
+    }
+
+    /**
+        Tries to read a standalone binary from the given bytes.
+
+        The metadata may either be appended to the end of the binary,
+        or stored in a dedicated section, see [`crate::section`].
+
+        The checksum of the metadata is verified, and an error is returned if
+        the binary was corrupted, such as by an incomplete download.
+
+        If the metadata of the binary is signed, its signature is verified, and
+        an error is returned if the metadata was modified after it was signed.
+
+        Metadata in the legacy JSON format, written by older versions of Lune, is also read.
+
+        # Errors
+
+        - If the bytes are not those of a standalone binary
+        - If the metadata is truncated, corrupted, or of an unsupported format version
+        - If the metadata is signed, and its signature is invalid
+    */
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Result<Self> {
+        match find_payload(bytes.as_ref())? {
+            Payload::Versioned(data) => {
+                let mut meta: Self =
+                    postcard::from_bytes(data).context("invalid standalone metadata")?;
+                meta.restore_duplicates()?;
+                Ok(meta)
+            }
+            Payload::Legacy(bytes) => Self::from_legacy_bytes(bytes),
+        }
+    }
+
+    /**
+        Reads metadata the same way as [`Metadata::from_bytes`], but without copying
+        bundled files out of the given bytes, returning their ranges within the given
+        bytes instead, so that they may be read in place, such as from a binary that
+        was mapped into memory, only once they are needed.
+
+        Returns `None` for metadata in the legacy JSON format, which can not be read
+        in place, and must be read using [`Metadata::from_bytes`] instead. Files
+        of encrypted metadata are always copied once decrypted.
+
+        # Errors
+
+        The same as for [`Metadata::from_bytes`].
+    */
+    pub fn from_bytes_mapped(bytes: &[u8]) -> Result<Option<Metadata<Range<usize>>>> {
+        let Payload::Versioned(data) = find_payload(bytes)? else {
+            return Ok(None);
+        };
+        // NOTE: Postcard stores byte vectors the same as byte slices, so the
+        // files may be borrowed from the bytes, and turned into ranges of them
+        let meta: Metadata<&[u8]> =
+            postcard::from_bytes(data).context("invalid standalone metadata")?;
+        let base = bytes.as_ptr().addr();
+        let mut meta = meta.map_files(|contents| {
+            let start = contents.as_ptr().addr() - base;
+            start..start + contents.len()
+        });
+        meta.restore_duplicates()?;
+        Ok(Some(meta))
+    }
+
+    /**
+        Reads metadata in the legacy JSON format, from binaries built by older versions of Lune.
+
+        Format: [json_data][json_size: u64][LEGACY_MAGIC: 8 bytes], or, when signed,
+        [json_data][signature: 64 bytes][public_key: 32 bytes][json_size: u64][LEGACY_MAGIC_SIGNED: 8 bytes]
+    */
+    fn from_legacy_bytes(bytes: &[u8]) -> Result<Self> {
+        let is_signed = bytes.ends_with(LEGACY_MAGIC_SIGNED);
+
+        // Extract JSON size (8 bytes before magic)
+        let json_size = read_trailer_size(bytes)?;
+
+        // Extract JSON data, which is followed by the signature and public key if signed
+        let trailer_len = if is_signed { SIGNATURE_TRAILER_LEN } else { 0 };
+        let Some(json_start) = (bytes.len() - 16)
+            .checked_sub(trailer_len)
+            .and_then(|json_end| json_end.checked_sub(json_size))
+        else {
+            bail!("standalone binary has truncated metadata")
+        };
+        let json_end = json_start + json_size;
+        let json_bytes = &bytes[json_start..json_end];
+
+        if is_signed {
+            let signature = &bytes[json_end..json_end + SIGNATURE_LEN];
+            let public_key = &bytes[json_end + SIGNATURE_LEN..json_end + SIGNATURE_TRAILER_LEN];
+            signature::verify(json_bytes, public_key, signature)?;
+        }
+
+        // Deserialize
+        let meta: Self = serde_json::from_slice(json_bytes)?;
+        Ok(meta)
+    }
+
+    /**
+        Writes the metadata chunk to a byte vector, to later be read using `from_bytes`,
+        signing it using the given key, if any.
+
+        Bundled files with the same contents, such as the files of a package that
+        is vendored at several paths, are only stored once, see [`Metadata::duplicates`].
+
+        Format: [format_version: u8][flags: u8][postcard_data][checksum: 32 bytes]
+        [chunk_size: u64][MAGIC: 8 bytes], where the checksum is a SHA-256 hash of the format
+        version, flags, and postcard data, and signed metadata also has [signature: 64 bytes]
+        [public_key: 32 bytes] before the chunk size - the signature covers everything before it.
+
+        # Errors
+
+        - If the metadata could not be serialized
+    */
+    pub fn to_bytes(&self, signing_key: Option<&MetadataSigningKey>) -> Result<Vec<u8>> {
+        let flags = if signing_key.is_some() {
+            FLAG_SIGNED | FLAG_CHECKSUM
+        } else {
+            FLAG_CHECKSUM
+        };
+        let mut deduplicated = self.clone();
+        deduplicated.deduplicate_files();
+        let mut bytes = vec![FORMAT_VERSION, flags];
+        bytes.extend_from_slice(&postcard::to_stdvec(&deduplicated)?);
+        let checksum = Sha256::digest(&bytes);
+        bytes.extend_from_slice(&checksum);
+        if let Some(key) = signing_key {
+            let signature = key.sign(&bytes);
+            bytes.extend_from_slice(&signature);
+            bytes.extend_from_slice(&key.public_key());
+        }
+        bytes.extend_from_slice(&(bytes.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(MAGIC);
+        Ok(bytes)
+    }
+}
+
+impl Metadata {
+    /**
+        Moves bundled files with the same contents as another bundled file into
+        [`Metadata::duplicates`], so that their contents are only stored once.
+
+        The file that is kept is the one with the lowest bundle path, so that the
+        stored files do not depend on the order that files were bundled in.
+    */
+    fn deduplicate_files(&mut self) {
+        let mut paths = self.files.keys().cloned().collect::<Vec<_>>();
+        paths.sort_unstable();
+        let mut stored: HashMap<&[u8], &str> = HashMap::new();
+        let mut duplicates = Vec::new();
+        for path in &paths {
+            let contents = self.files[path].as_slice();
+            match stored.get(contents) {
+                Some(original) => duplicates.push((path.clone(), (*original).to_string())),
+                None => {
+                    stored.insert(contents, path);
+                }
+            }
+        }
+        for (path, original) in duplicates {
+            self.files.remove(&path);
+            self.duplicates.insert(path, original);
+        }
+    }
+}
+
+impl<F: Clone> Metadata<F> {
+    /**
+        Restores bundled files that were only stored once, see [`Metadata::duplicates`].
+    */
+    fn restore_duplicates(&mut self) -> Result<()> {
+        for (path, original) in std::mem::take(&mut self.duplicates) {
+            let Some(contents) = self.files.get(&original).cloned() else {
+                bail!("standalone binary is missing the file {original}, which {path} duplicates")
+            };
+            self.files.insert(path, contents);
+        }
+        Ok(())
+    }
+}
+
+impl<F> Metadata<F> {
+    /**
+        Converts the contents of all bundled files using the given function,
+        keeping everything else as-is.
+    */
+    #[must_use]
+    pub fn map_files<G>(self, mut f: impl FnMut(F) -> G) -> Metadata<G> {
+        Metadata {
+            source: self.source,
+            entry_path: self.entry_path,
+            files: self
+                .files
+                .into_iter()
+                .map(|(path, contents)| (path, f(contents)))
+                .collect(),
+            aliases: self.aliases,
+            links: self.links,
+            crash_report_dir: self.crash_report_dir,
+            reporting_consent: self.reporting_consent,
+            source_map: self.source_map,
+            source_stripped: self.source_stripped,
+            encrypted: self.encrypted,
+            defines: self.defines,
+            env: self.env,
+            args: self.args,
+            exit_codes: self.exit_codes,
+            no_console_setup: self.no_console_setup,
+            build: self.build,
+            entries: self.entries,
+            duplicates: self.duplicates,
+            require_graph: self.require_graph,
+        }
+    }
+}
+
+impl<F: AsRef<[u8]> + From<Vec<u8>>> Metadata<F> {
+    /**
+        Decrypts the entry point source and all bundled files, if they were encrypted.
+
+        See [`EncryptedPayload::decrypt_from_env`] for more information.
+
+        # Errors
+
+        - If the payload could not be decrypted, such as when no key or the wrong key was given
+    */
+    pub fn decrypt(&mut self) -> Result<()> {
+        if let Some(payload) = self.encrypted.take() {
+            let (source, files) = payload.decrypt_from_env(&self.entry_path)?;
+            self.source = source;
+            self.files = files
+                .into_iter()
+                .map(|(path, contents)| (path, F::from(contents)))
+                .collect();
+        }
+        Ok(())
+    }
+
+    /**
+        Makes the named entry point the one that runs, instead of the default entry point.
+
+        Must be called after decrypting, since entry points are read from the bundled files.
+
+        # Errors
+
+        - If there is no entry point with the given name
+        - If the file of the entry point was not bundled
+    */
+    pub fn select_entry(&mut self, name: &str) -> Result<()> {
+        let Some(path) = self.entries.get(name) else {
+            let names = self.entries.keys().cloned().collect::<Vec<_>>();
+            bail!(
+                "binary has no entry point named '{name}', expected one of: {}",
+                names.join(", ")
+            )
+        };
+        let Some(source) = self.files.get(path) else {
+            bail!("binary does not contain the file {path} of entry point '{name}'")
+        };
+        let source = source.as_ref().to_vec();
+        self.source = if self.source_stripped {
+            source
+        } else {
+            crate::strip_shebang(source)
+        };
+        self.entry_path.clone_from(path);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> Metadata {
+        let mut meta = Metadata::new("print(require(\"@lib/foo\"))", "main.luau");
+        meta.files
+            .insert("foo.luau".to_string(), b"return 1".to_vec());
+        meta.aliases
+            .insert("@lib/foo".to_string(), "foo.luau".to_string());
+        meta.defines.insert("DEBUG".to_string(), "true".to_string());
+        meta.args = vec!["serve".to_string(), "--port=8080".to_string()];
+        meta.build = Some(BuildInfo {
+            timestamp: 1_700_000_000,
+            lune_version: "0.10.0".to_string(),
+            target: "linux-x86_64".to_string(),
+            commit: Some("0123456789abcdef".to_string()),
+            fields: BTreeMap::from([("channel".to_string(), "beta".to_string())]),
+        });
+        meta.entries
+            .insert("foo".to_string(), "foo.luau".to_string());
+        meta.require_graph = Some(RequireGraph {
+            modules: vec!["foo.luau".to_string(), "main.luau".to_string()],
+            requires: vec![
+                BTreeMap::new(),
+                BTreeMap::from([("@lib/foo".to_string(), 0)]),
+            ],
+        });
+        meta
+    }
+
+    #[test]
+    fn metadata_roundtrips() {
+        let meta = metadata();
+        let mut bin = b"base executable".to_vec();
+        bin.extend_from_slice(&meta.to_bytes(None).unwrap());
+        assert!(has_metadata(&bin));
+
+        let read = Metadata::from_bytes(&bin).unwrap();
+        assert_eq!(read.source, meta.source);
+        assert_eq!(read.entry_path, meta.entry_path);
+        assert_eq!(read.files, meta.files);
+        assert_eq!(read.aliases, meta.aliases);
+        assert_eq!(read.defines, meta.defines);
+        assert_eq!(read.args, meta.args);
+        assert_eq!(read.build, meta.build);
+        assert_eq!(read.entries, meta.entries);
+        assert_eq!(read.require_graph, meta.require_graph);
+    }
+
+    #[test]
+    fn mapped_metadata_reads_files_in_place() {
+        let meta = metadata();
+        let mut bin = b"base executable".to_vec();
+        bin.extend_from_slice(&meta.to_bytes(None).unwrap());
+
+        let read = Metadata::from_bytes_mapped(&bin).unwrap().unwrap();
+        assert_eq!(read.source, meta.source);
+        assert_eq!(read.aliases, meta.aliases);
+        assert_eq!(read.entries, meta.entries);
+        assert_eq!(read.files.len(), meta.files.len());
+        for (path, range) in read.files {
+            assert_eq!(&bin[range], meta.files[&path]);
+        }
+    }
+
+    #[test]
+    fn duplicate_files_are_stored_once() {
+        let mut meta = metadata();
+        let vendored = b"return 'a large vendored package'".repeat(64);
+        for path in ["a/pkg.luau", "b/pkg.luau", "c/pkg.luau"] {
+            meta.files.insert(path.to_string(), vendored.clone());
+        }
+        let bytes = meta.to_bytes(None).unwrap();
+        let stored = bytes
+            .windows(vendored.len())
+            .filter(|window| *window == vendored.as_slice())
+            .count();
+        assert_eq!(stored, 1);
+
+        let read = Metadata::from_bytes(&bytes).unwrap();
+        assert_eq!(read.files, meta.files);
+        assert!(read.duplicates.is_empty());
+
+        let mapped = Metadata::from_bytes_mapped(&bytes).unwrap().unwrap();
+        assert_eq!(mapped.files.len(), meta.files.len());
+        assert_eq!(mapped.files["b/pkg.luau"], mapped.files["a/pkg.luau"]);
+        assert_eq!(&bytes[mapped.files["c/pkg.luau"].clone()], vendored);
+    }
+
+    #[test]
+    fn unknown_format_versions_are_rejected() {
+        let mut bytes = metadata().to_bytes(None).unwrap();
+        bytes[0] = FORMAT_VERSION + 1;
+        let err = Metadata::from_bytes(&bytes).unwrap_err();
+        assert!(err.to_string().contains("format version"));
+    }
+
+    #[test]
+    fn corrupted_metadata_is_rejected() {
+        let mut bytes = metadata().to_bytes(None).unwrap();
+        // Flip a byte of the entry point source, which is still valid postcard
+        let source = bytes
+            .windows(6)
+            .position(|window| window == b"print(")
+            .unwrap();
+        bytes[source] = b'q';
+        let err = Metadata::from_bytes(&bytes).unwrap_err();
+        assert!(err.to_string().contains("corrupted"));
+    }
+
+    #[test]
+    fn metadata_without_checksum_is_read() {
+        let meta = metadata();
+        let mut bytes = vec![FORMAT_VERSION, 0];
+        bytes.extend_from_slice(&postcard::to_stdvec(&meta).unwrap());
+        bytes.extend_from_slice(&(bytes.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(MAGIC);
+
+        let read = Metadata::from_bytes(&bytes).unwrap();
+        assert_eq!(read.source, meta.source);
+    }
+
+    #[test]
+    fn metadata_from_bundle() {
+        let bundle = BundleResult {
+            entry: "/main.luau".to_string(),
+            extra_entries: Vec::new(),
+            files: HashMap::from([
+                (
+                    "/main.luau".to_string(),
+                    b"#!/usr/bin/env lune\nreturn 1".to_vec(),
+                ),
+                ("/lib.luau".to_string(), b"return 2".to_vec()),
+            ]),
+            aliases: HashMap::new(),
+            links: HashMap::new(),
+            sources: HashMap::new(),
+            required_by: HashMap::new(),
+            requires: HashMap::new(),
+            namespace_requires: std::collections::BTreeSet::new(),
+        };
+        let meta = Metadata::from_bundle(&bundle).unwrap();
+        assert_eq!(meta.entry_path, "/main.luau");
+        assert_eq!(meta.source, b"\nreturn 1");
+        assert_eq!(meta.files.len(), 2);
+    }
+
+    #[test]
+    fn select_entry_runs_named_entry() {
+        let mut meta = metadata();
+        meta.files.insert(
+            "tool.luau".to_string(),
+            b"#!/usr/bin/env lune\nreturn 2".to_vec(),
+        );
+        meta.entries
+            .insert("tool".to_string(), "tool.luau".to_string());
+
+        meta.select_entry("tool").unwrap();
+        assert_eq!(meta.entry_path, "tool.luau");
+        assert_eq!(meta.source, b"\nreturn 2");
+
+        let err = meta.select_entry("missing").unwrap_err().to_string();
+        assert!(err.contains("foo, tool"), "{err}");
+    }
+
+    #[test]
+    fn embedded_files_are_sorted() {
+        let mut meta = metadata();
+        meta.files
+            .insert("bar.luau".to_string(), b"return 2".to_vec());
+        let files = meta.embedded_files().collect::<Vec<_>>();
+        assert_eq!(
+            files,
+            vec![
+                ("bar.luau", b"return 2".as_slice()),
+                ("foo.luau", b"return 1".as_slice()),
+                ("main.luau", meta.source.as_slice()),
+            ]
+        );
+    }
+
+    #[test]
+    fn patching_standalone_base_replaces_metadata() {
+        let base = b"base executable".to_vec();
+        let first = metadata()
+            .create_env_patched_bin(base.clone(), None)
+            .unwrap();
+
+        let mut other = metadata();
+        other.entry_path = "other.luau".to_string();
+        let second = other.create_env_patched_bin(first, None).unwrap();
+
+        let expected = other.create_env_patched_bin(base, None).unwrap();
+        assert_eq!(second, expected);
+        assert_eq!(
+            Metadata::from_bytes(&second).unwrap().entry_path,
+            "other.luau"
+        );
+    }
+
+    #[test]
+    fn metadata_is_read_from_files() {
+        let dir = std::env::temp_dir().join(format!("lune-metadata-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let base = dir.join("base");
+        let output = dir.join("output");
+        fs::write(&base, b"base executable").unwrap();
+
+        let result = metadata()
+            .patch_file(&base, &output, None)
+            .and_then(|()| Metadata::from_file(&output));
+        let missing = Metadata::from_file(dir.join("missing"));
+        let not_standalone = Metadata::from_file(&base);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(result.unwrap().entry_path, "main.luau");
+        assert!(missing.is_err());
+        assert!(not_standalone.is_err());
+    }
+
+    #[test]
+    fn legacy_metadata_is_read() {
+        let json = br#"{"source":[114,101,116,117,114,110],"entry_path":"main.luau","aliases":{"@lib/foo":"foo.luau"}}"#;
+        let mut bin = b"base executable".to_vec();
+        bin.extend_from_slice(json);
+        bin.extend_from_slice(&(json.len() as u64).to_be_bytes());
+        bin.extend_from_slice(LEGACY_MAGIC);
+
+        let read = Metadata::from_bytes(&bin).unwrap();
+        assert_eq!(read.source, b"return");
+        assert_eq!(read.entry_path, "main.luau");
+        assert_eq!(read.aliases["@lib/foo"], "foo.luau");
+        assert!(read.files.is_empty());
+        assert!(Metadata::from_bytes_mapped(&bin).unwrap().is_none());
+    }
+}
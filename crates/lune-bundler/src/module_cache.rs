@@ -0,0 +1,187 @@
+/*!
+    An on-disk, content-addressed cache of processed modules, shared by all projects.
+
+    Processing a module, such as compiling it to bytecode, gives the same output
+    for the same input, regardless of which project it is in, so outputs are stored
+    by a hash of their input instead of by path. Projects that vendor the same
+    packages, or CI runs that start from a fresh checkout, then only process each
+    module once per machine. For `lune build`, this only stores bytecode compiled
+    using `--strip`, in `~/.lune/cache/modules` - next to the bundle and remote
+    module caches, so that all caches of Lune are kept and cleared in one place.
+
+    Each entry is stored in a file named after its key, in a directory named after
+    the first two characters of the key, and starts with a hash of its output,
+    which is verified when read - so that entries that were corrupted, or written
+    only partially, are processed and written again, instead of being used.
+    Entries are written to a temporary file first, and then renamed into place,
+    so that concurrent builds never read entries that are still being written.
+
+    A cache may be shared by many threads, such as when compiling files in parallel.
+*/
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+
+const CACHE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const HASH_LEN: usize = 32;
+
+/// Counter for names of temporary files, unique within this process
+static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/**
+    A content-addressed cache of processed modules, in a directory shared by all projects.
+*/
+#[derive(Debug)]
+pub struct ModuleCache {
+    dir: PathBuf,
+    hits: AtomicUsize,
+}
+
+impl ModuleCache {
+    /**
+        Creates a cache that stores its entries in the given directory.
+    */
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            hits: AtomicUsize::new(0),
+        }
+    }
+
+    /**
+        Returns the number of modules that were found in the cache.
+    */
+    #[must_use]
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /**
+        Returns the cached output of processing the given input, in the given way, or
+        processes it using the given function and caches its output otherwise.
+
+        The kind of processing must include any options that change its output,
+        such as `bytecode-O1-D1`, since it is a part of the key of the entry.
+
+        Failing to read or write the cache is not an error, it only makes builds slower.
+
+        # Errors
+
+        - If the input had to be processed, and processing it failed
+    */
+    pub fn get_or_insert_with(
+        &self,
+        kind: &str,
+        input: &[u8],
+        process: impl FnOnce() -> Result<Vec<u8>>,
+    ) -> Result<Vec<u8>> {
+        let path = self.entry_path(kind, input);
+        if let Some(output) = read_entry(&path) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(output);
+        }
+        let output = process()?;
+        write_entry(&path, &output);
+        Ok(output)
+    }
+
+    fn entry_path(&self, kind: &str, input: &[u8]) -> PathBuf {
+        let mut hasher = Sha256::new();
+        hasher.update(CACHE_VERSION.as_bytes());
+        hasher.update([0]);
+        hasher.update(kind.as_bytes());
+        hasher.update([0]);
+        hasher.update(input);
+        let key = format!("{:x}", hasher.finalize());
+        self.dir.join(&key[..2]).join(key)
+    }
+}
+
+fn read_entry(path: &Path) -> Option<Vec<u8>> {
+    let mut contents = fs::read(path).ok()?;
+    if contents.len() < HASH_LEN {
+        return None;
+    }
+    let output = contents.split_off(HASH_LEN);
+    (Sha256::digest(&output).as_slice() == contents).then_some(output)
+}
+
+fn write_entry(path: &Path, output: &[u8]) {
+    let Some(dir) = path.parent() else {
+        return;
+    };
+    if fs::create_dir_all(dir).is_err() {
+        return;
+    }
+    let mut contents = Sha256::digest(output).to_vec();
+    contents.extend_from_slice(output);
+    let temp_path = path.with_extension(format!(
+        "tmp-{}-{}",
+        std::process::id(),
+        TEMP_FILES.fetch_add(1, Ordering::Relaxed)
+    ));
+    if fs::write(&temp_path, contents).is_err() || fs::rename(&temp_path, path).is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outputs_are_shared_by_contents() {
+        let dir = std::env::temp_dir().join(format!("lune-module-cache-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let cache = ModuleCache::new(&dir);
+        let first = cache.get_or_insert_with("upper", b"return 1", || Ok(b"RETURN 1".to_vec()));
+        assert_eq!(first.unwrap(), b"RETURN 1");
+        assert_eq!(cache.hits(), 0);
+
+        // Another project with the same module should not process it again
+        let other = ModuleCache::new(&dir);
+        let second = other.get_or_insert_with("upper", b"return 1", || unreachable!());
+        assert_eq!(second.unwrap(), b"RETURN 1");
+        assert_eq!(other.hits(), 1);
+
+        // Processing in another way should not share outputs
+        let third = other.get_or_insert_with("lower", b"return 1", || Ok(b"return 1".to_vec()));
+        assert_eq!(third.unwrap(), b"return 1");
+        assert_eq!(other.hits(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn corrupted_entries_are_processed_again() {
+        let dir =
+            std::env::temp_dir().join(format!("lune-module-cache-bad-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let cache = ModuleCache::new(&dir);
+        cache
+            .get_or_insert_with("upper", b"a", || Ok(b"A".to_vec()))
+            .unwrap();
+        let path = cache.entry_path("upper", b"a");
+        let mut contents = fs::read(&path).unwrap();
+        *contents.last_mut().unwrap() = b'B';
+        fs::write(&path, contents).unwrap();
+
+        let output = cache
+            .get_or_insert_with("upper", b"a", || Ok(b"A".to_vec()))
+            .unwrap();
+        assert_eq!(output, b"A");
+        assert_eq!(cache.hits(), 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
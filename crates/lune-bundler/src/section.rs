@@ -0,0 +1,179 @@
+/*!
+    Discovery of metadata stored in a dedicated section of an executable.
+
+    Standalone binaries built using `lune build --section` store their metadata
+    in a named section, instead of appending it to the end of the executable,
+    which keeps code signatures, virus scanners and `strip` tools happy. The
+    section is found through the headers of the executable - `.lune` for ELF
+    and PE executables, and the `__lune` section of the `__LUNE` segment for
+    Mach-O executables, including any slice of a universal binary.
+
+    The contents of the section are the same as the metadata that would
+    otherwise be appended, so they are read the same way once found.
+*/
+
+/// Name of the section that metadata is stored in, for ELF executables
+pub const ELF_SECTION_NAME: &[u8] = b".lune";
+/// Name of the section that metadata is stored in, for PE executables
+pub const PE_SECTION_NAME: &[u8; 8] = b".lune\0\0\0";
+/// Name of the segment that metadata is stored in, for Mach-O executables
+pub const MACHO_SEGMENT_NAME: &[u8] = b"__LUNE";
+/// Name of the section that metadata is stored in, inside of [`MACHO_SEGMENT_NAME`]
+pub const MACHO_SECTION_NAME: &[u8] = b"__lune";
+
+const ELF_MAGIC: &[u8; 4] = b"\x7fELF";
+const MACHO_MAGIC_64: u32 = 0xfeed_facf;
+const FAT_MAGIC: u32 = 0xcafe_babe;
+const LC_SEGMENT_64: u32 = 0x19;
+
+fn read_u16_le(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(offset..offset.checked_add(2)?)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset.checked_add(4)?)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32_be(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset.checked_add(4)?)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64_le(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        data.get(offset..offset.checked_add(8)?)?.try_into().ok()?,
+    ))
+}
+
+fn read_usize_le(data: &[u8], offset: usize) -> Option<usize> {
+    usize::try_from(read_u64_le(data, offset)?).ok()
+}
+
+fn slice(data: &[u8], offset: usize, len: usize) -> Option<&[u8]> {
+    data.get(offset..offset.checked_add(len)?)
+}
+
+/// Compares a fixed-size, nul-padded name from a header to the given name
+fn name_matches(field: &[u8], name: &[u8]) -> bool {
+    field.starts_with(name) && field[name.len()..].iter().all(|byte| *byte == 0)
+}
+
+/**
+    Finds the contents of the metadata section of the given executable, if it has one.
+
+    Returns `None` if the executable has no metadata section, or is not
+    a 64-bit little-endian ELF, PE or Mach-O executable.
+*/
+#[must_use]
+pub fn find_metadata_section(data: &[u8]) -> Option<&[u8]> {
+    if data.starts_with(ELF_MAGIC) {
+        find_elf_section(data)
+    } else if data.starts_with(b"MZ") {
+        find_pe_section(data)
+    } else if read_u32_le(data, 0) == Some(MACHO_MAGIC_64) {
+        find_macho_section(data)
+    } else if read_u32_be(data, 0) == Some(FAT_MAGIC) {
+        find_fat_section(data)
+    } else {
+        None
+    }
+}
+
+fn find_elf_section(data: &[u8]) -> Option<&[u8]> {
+    let shoff = read_usize_le(data, 0x28)?;
+    let shentsize = usize::from(read_u16_le(data, 0x3A)?);
+    let shnum = usize::from(read_u16_le(data, 0x3C)?);
+    let shstrndx = usize::from(read_u16_le(data, 0x3E)?);
+
+    // Section headers: name at +0x00, offset at +0x18, size at +0x20
+    let header = |index: usize| shoff.checked_add(index.checked_mul(shentsize)?);
+    let contents = |header: usize| {
+        slice(
+            data,
+            read_usize_le(data, header.checked_add(0x18)?)?,
+            read_usize_le(data, header.checked_add(0x20)?)?,
+        )
+    };
+    let names = contents(header(shstrndx)?)?;
+
+    (0..shnum).find_map(|index| {
+        let header = header(index)?;
+        let name_offset = usize::try_from(read_u32_le(data, header)?).ok()?;
+        let name = names.get(name_offset..)?.split(|byte| *byte == 0).next()?;
+        if name == ELF_SECTION_NAME {
+            contents(header)
+        } else {
+            None
+        }
+    })
+}
+
+fn find_pe_section(data: &[u8]) -> Option<&[u8]> {
+    let pe_offset = usize::try_from(read_u32_le(data, 0x3C)?).ok()?;
+    if slice(data, pe_offset, 4)? != b"PE\0\0" {
+        return None;
+    }
+    let coff_offset = pe_offset + 4;
+    let section_count = usize::from(read_u16_le(data, coff_offset + 2)?);
+    let optional_header_size = usize::from(read_u16_le(data, coff_offset + 16)?);
+    let section_table = coff_offset + 20 + optional_header_size;
+
+    (0..section_count).find_map(|index| {
+        let header = section_table + index * 40;
+        if slice(data, header, 8)? != PE_SECTION_NAME {
+            return None;
+        }
+        // NOTE: The raw size is padded to the file alignment,
+        // while the virtual size is the size of the contents
+        let virtual_size = read_u32_le(data, header + 8)?;
+        let raw_size = read_u32_le(data, header + 16)?;
+        let raw_pointer = read_u32_le(data, header + 20)?;
+        slice(
+            data,
+            usize::try_from(raw_pointer).ok()?,
+            usize::try_from(virtual_size.min(raw_size)).ok()?,
+        )
+    })
+}
+
+fn find_macho_section(data: &[u8]) -> Option<&[u8]> {
+    let ncmds = read_u32_le(data, 16)?;
+    let mut offset = 32usize;
+    for _ in 0..ncmds {
+        let cmd = read_u32_le(data, offset)?;
+        let cmdsize = usize::try_from(read_u32_le(data, offset + 4)?).ok()?;
+        if cmdsize < 8 {
+            return None;
+        }
+        if cmd == LC_SEGMENT_64 && name_matches(slice(data, offset + 8, 16)?, MACHO_SEGMENT_NAME) {
+            let nsects = read_u32_le(data, offset + 64)?;
+            for index in 0..usize::try_from(nsects).ok()? {
+                // section_64: sectname at +0, size at +40, offset at +48
+                let section = offset + 72 + index * 80;
+                if name_matches(slice(data, section, 16)?, MACHO_SECTION_NAME) {
+                    let size = read_usize_le(data, section + 40)?;
+                    let file_offset = usize::try_from(read_u32_le(data, section + 48)?).ok()?;
+                    return slice(data, file_offset, size);
+                }
+            }
+        }
+        offset = offset.checked_add(cmdsize)?;
+    }
+    None
+}
+
+fn find_fat_section(data: &[u8]) -> Option<&[u8]> {
+    // NOTE: Every slice of a universal binary contains the same metadata
+    let count = read_u32_be(data, 4)?;
+    (0..usize::try_from(count).ok()?).find_map(|index| {
+        let arch = 8 + index * 20;
+        let offset = usize::try_from(read_u32_be(data, arch + 8)?).ok()?;
+        let size = usize::try_from(read_u32_be(data, arch + 12)?).ok()?;
+        find_macho_section(slice(data, offset, size)?)
+    })
+}
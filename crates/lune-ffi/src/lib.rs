@@ -0,0 +1,109 @@
+/*!
+    Base shared library for Lune projects built using `lune build --lib`.
+
+    The bundled project is appended to a copy of this library, the same way
+    as for standalone executables, and is run by calling the exported C function:
+
+    ```c
+    int lune_run(int argc, const char *const *argv);
+    ```
+
+    Arguments follow the same convention as `main` in C, meaning `argv[0]` is
+    the name of the program and is not given to the script, and the returned
+    value is the exit code that the script finished with.
+*/
+
+#![allow(clippy::cargo_common_metadata)]
+
+use std::{
+    ffi::{CStr, c_char, c_int},
+    panic::{AssertUnwindSafe, catch_unwind},
+};
+
+// NOTE: These modules are shared with the Lune executable, so that
+// libraries run bundled projects exactly like standalone binaries do
+#[allow(dead_code)]
+#[path = "../../lune/src/require_trace.rs"]
+mod require_trace;
+#[allow(dead_code)]
+#[path = "../../lune/src/standalone/mod.rs"]
+mod standalone;
+#[allow(dead_code)]
+#[path = "../../lune/src/terminal.rs"]
+mod terminal;
+
+mod location;
+
+/// Exit code returned when the project could not be loaded or run at all
+const EXIT_CODE_FAILURE: c_int = 1;
+
+/**
+    Runs the project bundled into this library, with the given arguments.
+
+    Returns the exit code of the script, or `1` if the library does
+    not contain a bundled project, or it could not be loaded.
+
+    # Safety
+
+    `argv` must point to at least `argc` pointers to valid, nul-terminated
+    strings, or may be null if `argc` is zero. Arguments that are not valid
+    UTF-8 are converted lossily.
+*/
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lune_run(argc: c_int, argv: *const *const c_char) -> c_int {
+    // SAFETY: The caller guarantees that argv is valid for argc arguments
+    let args = unsafe { collect_args(argc, argv) };
+
+    // NOTE: Unwinding across the C ABI aborts the host application,
+    // so any panics are caught and turned into a failing exit code
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        async_io::block_on(async {
+            let Some(path) = location::library_path() else {
+                eprintln!("lune: failed to find the path of the loaded library");
+                return EXIT_CODE_FAILURE;
+            };
+            let Some(patched_lib) = standalone::metadata::check_path(&path) else {
+                eprintln!(
+                    "lune: library at {} does not contain a bundled project",
+                    path.display()
+                );
+                return EXIT_CODE_FAILURE;
+            };
+            match standalone::run_with_args(patched_lib, args).await {
+                Ok(code) => c_int::from(code),
+                Err(err) => {
+                    eprintln!("{err:?}");
+                    EXIT_CODE_FAILURE
+                }
+            }
+        })
+    }));
+
+    result.unwrap_or(EXIT_CODE_FAILURE)
+}
+
+/**
+    Collects the arguments given to [`lune_run`], skipping the program name.
+
+    # Safety
+
+    See [`lune_run`].
+*/
+unsafe fn collect_args(argc: c_int, argv: *const *const c_char) -> Vec<String> {
+    let count = usize::try_from(argc).unwrap_or_default();
+    if argv.is_null() || count == 0 {
+        return Vec::new();
+    }
+    (1..count)
+        .filter_map(|index| {
+            // SAFETY: The caller guarantees that argv has at least argc entries
+            let arg = unsafe { *argv.add(index) };
+            if arg.is_null() {
+                return None;
+            }
+            // SAFETY: The caller guarantees that each argument is nul-terminated
+            let arg = unsafe { CStr::from_ptr(arg) };
+            Some(arg.to_string_lossy().into_owned())
+        })
+        .collect()
+}
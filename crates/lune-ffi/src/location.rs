@@ -0,0 +1,76 @@
+use std::path::PathBuf;
+
+/**
+    Returns the path to this library, as loaded into the current process.
+
+    This is different from the path to the current executable, which
+    is the host application that loaded the library, and not the
+    library that the bundled project has been appended to.
+*/
+#[cfg(unix)]
+pub fn library_path() -> Option<PathBuf> {
+    use std::{ffi::CStr, os::unix::ffi::OsStrExt};
+
+    let address = crate::lune_run as *const libc::c_void;
+    let mut info = std::mem::MaybeUninit::<libc::Dl_info>::uninit();
+
+    // SAFETY: The address is that of a function in this library,
+    // and the info struct is only read if dladdr succeeded
+    unsafe {
+        if libc::dladdr(address, info.as_mut_ptr()) == 0 {
+            return None;
+        }
+        let info = info.assume_init();
+        if info.dli_fname.is_null() {
+            return None;
+        }
+        let name = CStr::from_ptr(info.dli_fname);
+        Some(PathBuf::from(std::ffi::OsStr::from_bytes(name.to_bytes())))
+    }
+}
+
+/**
+    Returns the path to this library, as loaded into the current process.
+
+    This is different from the path to the current executable, which
+    is the host application that loaded the library, and not the
+    library that the bundled project has been appended to.
+*/
+#[cfg(windows)]
+pub fn library_path() -> Option<PathBuf> {
+    use std::{ffi::OsString, os::windows::ffi::OsStringExt};
+
+    use windows_sys::Win32::{
+        Foundation::HMODULE,
+        System::LibraryLoader::{
+            GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS, GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT,
+            GetModuleFileNameW, GetModuleHandleExW,
+        },
+    };
+
+    let address = crate::lune_run as *const u16;
+    let mut module: HMODULE = std::ptr::null_mut();
+
+    // SAFETY: The address is that of a function in this library, the module
+    // handle is not reference counted, and the buffer outlives both calls
+    unsafe {
+        let flags =
+            GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS | GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT;
+        if GetModuleHandleExW(flags, address, &raw mut module) == 0 {
+            return None;
+        }
+        let mut buffer = vec![0u16; 1024];
+        loop {
+            let len = GetModuleFileNameW(module, buffer.as_mut_ptr(), buffer.len() as u32);
+            if len == 0 {
+                return None;
+            }
+            if (len as usize) < buffer.len() {
+                buffer.truncate(len as usize);
+                return Some(PathBuf::from(OsString::from_wide(&buffer)));
+            }
+            // The path was truncated, try again with a larger buffer
+            buffer.resize(buffer.len() * 2, 0);
+        }
+    }
+}
@@ -0,0 +1,244 @@
+/*!
+    Batch operations on Roblox datatypes, which work on whole arrays of values
+    in a single call, so that tooling processing many thousands of values does
+    not need to call into Rust for every single one of them.
+
+    Values may be given either as arrays of datatypes, or packed into buffers
+    of little-endian `f32` components, which avoids creating any userdata at
+    all - `Vector3` values are packed as `X, Y, Z`, and `CFrame` values as the
+    12 components returned by `CFrame:GetComponents()`. Results are returned
+    packed into a new buffer whenever any of the given values were packed.
+*/
+
+use glam::{Mat4, Vec3};
+use mlua::{Buffer as LuaBuffer, prelude::*};
+
+use super::types::{CFrame, Vector3};
+
+const COMPONENT_SIZE: usize = size_of::<f32>();
+
+/**
+    A datatype that may be packed into a buffer, as a fixed number of `f32` components.
+*/
+pub(crate) trait Packable: LuaUserData + Copy + 'static {
+    const NAME: &'static str;
+    const COMPONENTS: usize;
+
+    fn unpack(components: &[f32]) -> Self;
+    fn pack(&self, out: &mut Vec<f32>);
+}
+
+impl Packable for Vector3 {
+    const NAME: &'static str = "Vector3";
+    const COMPONENTS: usize = 3;
+
+    fn unpack(components: &[f32]) -> Self {
+        Vector3(Vec3::from_slice(components))
+    }
+
+    fn pack(&self, out: &mut Vec<f32>) {
+        out.extend_from_slice(&self.0.to_array());
+    }
+}
+
+impl Packable for CFrame {
+    const NAME: &'static str = "CFrame";
+    const COMPONENTS: usize = 12;
+
+    fn unpack(c: &[f32]) -> Self {
+        CFrame(Mat4::from_cols_array_2d(&[
+            [c[3], c[6], c[9], 0.0],
+            [c[4], c[7], c[10], 0.0],
+            [c[5], c[8], c[11], 0.0],
+            [c[0], c[1], c[2], 1.0],
+        ]))
+    }
+
+    fn pack(&self, out: &mut Vec<f32>) {
+        let [x, y, z, w] = [self.0.x_axis, self.0.y_axis, self.0.z_axis, self.0.w_axis];
+        #[rustfmt::skip]
+        out.extend_from_slice(&[
+            w.x, w.y, w.z,
+            x.x, y.x, z.x,
+            x.y, y.y, z.y,
+            x.z, y.z, z.z,
+        ]);
+    }
+}
+
+/// How the values of a batch were given, which results are returned in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BatchForm {
+    /// A single value, which is used for every value of the other batch
+    Single,
+    /// An array of datatypes
+    Array,
+    /// A buffer of packed components
+    Packed,
+}
+
+/**
+    Values given to a batch operation.
+*/
+pub(crate) struct Batch<T> {
+    values: Vec<T>,
+    form: BatchForm,
+}
+
+impl<T: Packable> Batch<T> {
+    fn get(&self, index: usize) -> T {
+        match self.form {
+            BatchForm::Single => self.values[0],
+            _ => self.values[index],
+        }
+    }
+
+    /// Returns the given results in the given form, as an array or a packed buffer
+    fn results_into_lua(lua: &Lua, results: Vec<T>, form: BatchForm) -> LuaResult<LuaValue> {
+        if form == BatchForm::Packed {
+            let mut components = Vec::with_capacity(results.len() * T::COMPONENTS);
+            for value in &results {
+                value.pack(&mut components);
+            }
+            let bytes = components
+                .iter()
+                .flat_map(|component| component.to_le_bytes())
+                .collect::<Vec<_>>();
+            lua.create_buffer(bytes).map(LuaValue::Buffer)
+        } else {
+            lua.create_sequence_from(results).map(LuaValue::Table)
+        }
+    }
+}
+
+impl<T: Packable> FromLua for Batch<T> {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::UserData(ud) => Ok(Self {
+                values: vec![*ud.borrow::<T>()?],
+                form: BatchForm::Single,
+            }),
+            LuaValue::Table(table) => Ok(Self {
+                values: table
+                    .sequence_values::<LuaUserDataRef<T>>()
+                    .map(|value| value.map(|value| *value))
+                    .collect::<LuaResult<_>>()?,
+                form: BatchForm::Array,
+            }),
+            LuaValue::Buffer(buffer) => Ok(Self {
+                values: unpack_buffer(&buffer)?,
+                form: BatchForm::Packed,
+            }),
+            value => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: format!("batch of {}", T::NAME),
+                message: Some(format!(
+                    "Expected {}, array of {}, or buffer, got {}",
+                    T::NAME,
+                    T::NAME,
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+fn unpack_buffer<T: Packable>(buffer: &LuaBuffer) -> LuaResult<Vec<T>> {
+    let stride = T::COMPONENTS * COMPONENT_SIZE;
+    if !buffer.len().is_multiple_of(stride) {
+        return Err(LuaError::runtime(format!(
+            "Buffer of packed {} values must have a size that is a multiple of {stride} bytes, got {}",
+            T::NAME,
+            buffer.len()
+        )));
+    }
+    let components = buffer
+        .to_vec()
+        .chunks_exact(COMPONENT_SIZE)
+        .map(|chunk| f32::from_le_bytes(chunk.try_into().expect("chunk is a component")))
+        .collect::<Vec<_>>();
+    Ok(components
+        .chunks_exact(T::COMPONENTS)
+        .map(T::unpack)
+        .collect())
+}
+
+/**
+    Returns the number of results of an operation on the given batches, where single
+    values are used for every value of the other batch, and the form to return them in.
+*/
+fn batch_len<A: Packable, B: Packable>(
+    a: &Batch<A>,
+    b: &Batch<B>,
+) -> LuaResult<(usize, BatchForm)> {
+    let len = match (a.form, b.form) {
+        (BatchForm::Single, _) => b.values.len(),
+        (_, BatchForm::Single) => a.values.len(),
+        _ if a.values.len() == b.values.len() => a.values.len(),
+        _ => {
+            return Err(LuaError::runtime(format!(
+                "Expected batches of the same length, got {} and {}",
+                a.values.len(),
+                b.values.len()
+            )));
+        }
+    };
+    let form = if a.form == BatchForm::Packed || b.form == BatchForm::Packed {
+        BatchForm::Packed
+    } else {
+        BatchForm::Array
+    };
+    Ok((len, form))
+}
+
+/**
+    Implementation of `CFrame.batchMultiply`, which multiplies each
+    `CFrame` of the first batch with the `CFrame` of the second at
+    the same index, the same as the `*` operator would.
+*/
+pub(crate) fn cframe_batch_multiply(
+    lua: &Lua,
+    (a, b): (Batch<CFrame>, Batch<CFrame>),
+) -> LuaResult<LuaValue> {
+    let (len, form) = batch_len(&a, &b)?;
+    let results = (0..len).map(|i| a.get(i) * b.get(i)).collect();
+    Batch::results_into_lua(lua, results, form)
+}
+
+/**
+    Implementation of `Vector3.batchTransform`, which transforms each point
+    of the given batch by the given `CFrame`, the same as `cframe * point` would.
+*/
+pub(crate) fn vector3_batch_transform(
+    lua: &Lua,
+    (cframe, points): (LuaUserDataRef<CFrame>, Batch<Vector3>),
+) -> LuaResult<LuaValue> {
+    let cframe = *cframe;
+    let results = points.values.iter().map(|point| cframe * *point).collect();
+    let form = match points.form {
+        BatchForm::Packed => BatchForm::Packed,
+        _ => BatchForm::Array,
+    };
+    Batch::results_into_lua(lua, results, form)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packed_cframes_roundtrip() {
+        let cframe = CFrame(Mat4::from_cols_array(&[
+            1.0, 2.0, 3.0, 0.0, 4.0, 5.0, 6.0, 0.0, 7.0, 8.0, 9.0, 0.0, 10.0, 11.0, 12.0, 1.0,
+        ]));
+        let mut components = Vec::new();
+        cframe.pack(&mut components);
+
+        // The same order as CFrame:GetComponents()
+        let expected = [
+            10.0, 11.0, 12.0, 1.0, 4.0, 7.0, 2.0, 5.0, 8.0, 3.0, 6.0, 9.0,
+        ];
+        assert_eq!(components, expected);
+        assert_eq!(CFrame::unpack(&components), cframe);
+    }
+}
@@ -5,6 +5,8 @@ pub mod extension;
 #[cfg(feature = "mlua")]
 pub mod attributes;
 #[cfg(feature = "mlua")]
+pub(crate) mod batch;
+#[cfg(feature = "mlua")]
 pub mod conversion;
 #[cfg(feature = "mlua")]
 pub mod result;
@@ -9,7 +9,7 @@ use rbx_dom_weak::types::{CFrame as DomCFrame, Matrix3 as DomMatrix3, Vector3 as
 
 use lune_utils::TableBuilder;
 
-use crate::exports::LuaExportsTable;
+use crate::{datatypes::batch, exports::LuaExportsTable};
 
 use super::{super::*, Vector3};
 
@@ -162,6 +162,7 @@ impl LuaExportsTable for CFrame {
 
         TableBuilder::new(lua)?
             .with_function("Angles", cframe_angles)?
+            .with_function("batchMultiply", batch::cframe_batch_multiply)?
             .with_value("identity", CFrame(Mat4::IDENTITY))?
             .with_function("fromAxisAngle", cframe_from_axis_angle)?
             .with_function("fromEulerAnglesXYZ", cframe_from_euler_angles_xyz)?
@@ -7,7 +7,10 @@ use rbx_dom_weak::types::Vector3 as DomVector3;
 
 use lune_utils::TableBuilder;
 
-use crate::{datatypes::util::round_float_decimal, exports::LuaExportsTable};
+use crate::{
+    datatypes::{batch, util::round_float_decimal},
+    exports::LuaExportsTable,
+};
 
 use super::{super::*, EnumItem};
 
@@ -85,6 +88,7 @@ impl LuaExportsTable for Vector3 {
             .with_value("zAxis", Vector3(Vec3::Z))?
             .with_value("zero", Vector3(Vec3::ZERO))?
             .with_value("one", Vector3(Vec3::ONE))?
+            .with_function("batchTransform", batch::vector3_batch_transform)?
             .with_function("fromAxis", vector3_from_axis)?
             .with_function("fromNormalId", vector3_from_normal_id)?
             .with_function("new", vector3_new)?
@@ -0,0 +1,419 @@
+/*!
+    Geometry utilities for parts in documents, such as bounding boxes, finding
+    overlapping parts, and raycasting, which read parts straight from the
+    internal document, so that scripts auditing large places do not need
+    to read and compare the properties of every single part from Luau.
+
+    Parts are treated as boxes given by their `CFrame` and `Size`,
+    regardless of their shape, the same as bounding boxes in Roblox.
+*/
+
+use std::collections::{HashMap, HashSet};
+
+use glam::{IVec3, Mat3, Vec3};
+use rbx_dom_weak::types::{Ref as DomRef, Variant as DomValue, Vector3 as DomVector3};
+
+use crate::{
+    instance::Instance,
+    shared::instance::{class_is_a, find_property_info},
+};
+
+/// Parts that only touch, such as parts sharing a face, are not overlapping
+const OVERLAP_TOLERANCE: f32 = 1e-3;
+
+/// Parts spanning more cells than this are compared with all other parts
+/// instead, so that very large parts such as baseplates do not fill the hash
+const MAX_CELLS_PER_PART: i64 = 64;
+
+fn vec3(v: DomVector3) -> Vec3 {
+    Vec3::new(v.x, v.y, v.z)
+}
+
+/**
+    An axis-aligned box in world space.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    #[must_use]
+    pub fn center(&self) -> Vec3 {
+        self.min.lerp(self.max, 0.5)
+    }
+
+    #[must_use]
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    #[must_use]
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    #[must_use]
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.min.cmplt(other.max).all() && other.min.cmplt(self.max).all()
+    }
+}
+
+/**
+    A part, as an oriented box in world space.
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct PartBox {
+    pub instance: Instance,
+    pub position: Vec3,
+    pub rotation: Mat3,
+    pub half_size: Vec3,
+}
+
+impl PartBox {
+    /**
+        Reads the box of the given instance, or returns `None` if it is not a `BasePart`.
+
+        Properties that were never set use their default values.
+    */
+    #[must_use]
+    pub fn from_instance(instance: Instance) -> Option<Self> {
+        if !class_is_a(instance.get_class_name(), "BasePart").unwrap_or(false) {
+            return None;
+        }
+        let property = |name: &str| {
+            instance.get_property(name).or_else(|| {
+                find_property_info(instance.get_class_name(), name)
+                    .and_then(|info| info.value_default.cloned())
+            })
+        };
+        let Some(DomValue::Vector3(size)) = property("Size") else {
+            return None;
+        };
+        let (position, rotation) = match property("CFrame") {
+            Some(DomValue::CFrame(cframe)) => {
+                let rows = cframe.orientation;
+                let rotation =
+                    Mat3::from_cols(vec3(rows.x), vec3(rows.y), vec3(rows.z)).transpose();
+                (vec3(cframe.position), rotation)
+            }
+            _ => (Vec3::ZERO, Mat3::IDENTITY),
+        };
+        Some(Self {
+            instance,
+            position,
+            rotation,
+            half_size: vec3(size).abs() * 0.5,
+        })
+    }
+
+    /// Returns the smallest axis-aligned box containing this box
+    #[must_use]
+    pub fn aabb(&self) -> Aabb {
+        let abs = Mat3::from_cols(
+            self.rotation.x_axis.abs(),
+            self.rotation.y_axis.abs(),
+            self.rotation.z_axis.abs(),
+        );
+        let extents = abs * self.half_size;
+        Aabb {
+            min: self.position - extents,
+            max: self.position + extents,
+        }
+    }
+
+    /**
+        Returns whether this box overlaps the other box, using the separating axis theorem.
+    */
+    #[must_use]
+    pub fn overlaps(&self, other: &Self) -> bool {
+        let a = [
+            self.rotation.x_axis,
+            self.rotation.y_axis,
+            self.rotation.z_axis,
+        ];
+        let b = [
+            other.rotation.x_axis,
+            other.rotation.y_axis,
+            other.rotation.z_axis,
+        ];
+        let offset = other.position - self.position;
+        let separated_along = |axis: Vec3| {
+            if axis.length_squared() < 1e-6 {
+                // Parallel edges give no axis, and are covered by the face axes
+                return false;
+            }
+            let radius = |axes: &[Vec3; 3], half: Vec3| {
+                half.x * axes[0].dot(axis).abs()
+                    + half.y * axes[1].dot(axis).abs()
+                    + half.z * axes[2].dot(axis).abs()
+            };
+            let distance = offset.dot(axis).abs();
+            let tolerance = OVERLAP_TOLERANCE * axis.length();
+            distance >= radius(&a, self.half_size) + radius(&b, other.half_size) - tolerance
+        };
+        let face_axes = a.iter().chain(b.iter()).copied();
+        let edge_axes = a.iter().flat_map(|a| b.iter().map(move |b| a.cross(*b)));
+        !face_axes.chain(edge_axes).any(separated_along)
+    }
+
+    /**
+        Returns the fraction of the given ray at which it enters this box, and the
+        normal of the face it enters through, or `None` if the ray misses the box.
+
+        Rays starting inside of the box never hit it, the same as in Roblox.
+    */
+    #[must_use]
+    pub fn intersect_ray(&self, origin: Vec3, direction: Vec3) -> Option<(f32, Vec3)> {
+        // Rotations are orthonormal, so their inverse is their transpose
+        let inverse = self.rotation.transpose();
+        let origin = inverse * (origin - self.position);
+        let direction = inverse * direction;
+
+        let mut enter = f32::NEG_INFINITY;
+        let mut exit = f32::INFINITY;
+        let mut normal = Vec3::ZERO;
+        for axis in 0..3 {
+            let (o, d, half) = (origin[axis], direction[axis], self.half_size[axis]);
+            if d.abs() < f32::EPSILON {
+                if o.abs() > half {
+                    return None;
+                }
+                continue;
+            }
+            let (near, far) = ((-half - o) / d, (half - o) / d);
+            let (near, far, sign) = if near <= far {
+                (near, far, -1.0)
+            } else {
+                (far, near, 1.0)
+            };
+            if near > enter {
+                enter = near;
+                normal = Vec3::ZERO;
+                normal[axis] = sign;
+            }
+            exit = exit.min(far);
+        }
+
+        if enter > exit || enter < 0.0 || enter > 1.0 {
+            return None;
+        }
+        Some((enter, (self.rotation * normal).normalize()))
+    }
+}
+
+/**
+    Reads the boxes of all parts among the given instances and their
+    descendants, skipping any instances that are given more than once.
+*/
+#[must_use]
+pub fn collect_parts(instances: &[Instance]) -> Vec<PartBox> {
+    let mut seen = HashSet::new();
+    let mut parts = Vec::new();
+    for instance in instances {
+        let descendants = instance.get_descendants();
+        for instance in std::iter::once(*instance).chain(descendants) {
+            if seen.insert(DomRef::from(instance))
+                && let Some(part) = PartBox::from_instance(instance)
+            {
+                parts.push(part);
+            }
+        }
+    }
+    parts
+}
+
+/**
+    Returns the axis-aligned box containing all of the given
+    parts, or `None` if there are no parts.
+*/
+#[must_use]
+pub fn bounding_box(parts: &[PartBox]) -> Option<Aabb> {
+    parts
+        .iter()
+        .map(PartBox::aabb)
+        .reduce(|bounds, aabb| bounds.union(&aabb))
+}
+
+/**
+    Finds all pairs of overlapping parts, as indices of the given parts.
+
+    Parts are sorted into the cells of a spatial hash first, so that only parts
+    sharing a cell are compared. Cells are as large as the average part, unless
+    another size is given.
+*/
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn find_overlapping(parts: &[PartBox], cell_size: Option<f32>) -> Vec<(usize, usize)> {
+    let aabbs = parts.iter().map(PartBox::aabb).collect::<Vec<_>>();
+    let cell_size = cell_size
+        .filter(|size| *size > 0.0)
+        .unwrap_or_else(|| {
+            let total = aabbs
+                .iter()
+                .map(|aabb| aabb.size().max_element())
+                .sum::<f32>();
+            total / aabbs.len().max(1) as f32
+        })
+        .max(f32::EPSILON);
+
+    let mut cells = HashMap::<IVec3, Vec<usize>>::new();
+    let mut large = Vec::new();
+    for (index, aabb) in aabbs.iter().enumerate() {
+        let min = (aabb.min / cell_size).floor().as_ivec3();
+        let max = (aabb.max / cell_size).floor().as_ivec3();
+        let span = (max - min + IVec3::ONE).as_i64vec3();
+        if span.x * span.y * span.z > MAX_CELLS_PER_PART {
+            large.push(index);
+            continue;
+        }
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    cells.entry(IVec3::new(x, y, z)).or_default().push(index);
+                }
+            }
+        }
+    }
+
+    let mut pairs = HashSet::new();
+    let mut check = |a: usize, b: usize| {
+        let pair = (a.min(b), a.max(b));
+        if a != b
+            && !pairs.contains(&pair)
+            && aabbs[a].overlaps(&aabbs[b])
+            && parts[a].overlaps(&parts[b])
+        {
+            pairs.insert(pair);
+        }
+    };
+    for indices in cells.values() {
+        for (i, a) in indices.iter().enumerate() {
+            for b in &indices[i + 1..] {
+                check(*a, *b);
+            }
+        }
+    }
+    for a in &large {
+        for b in 0..parts.len() {
+            check(*a, b);
+        }
+    }
+
+    let mut pairs = pairs.into_iter().collect::<Vec<_>>();
+    pairs.sort_unstable();
+    pairs
+}
+
+/**
+    A part hit by a ray.
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct RaycastHit {
+    /// The index of the part that was hit
+    pub index: usize,
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub distance: f32,
+}
+
+/**
+    Casts a ray from the given origin, along the given direction, the length of
+    which is the maximum distance of the ray, and returns the closest part it hits.
+*/
+#[must_use]
+pub fn raycast(parts: &[PartBox], origin: Vec3, direction: Vec3) -> Option<RaycastHit> {
+    let (index, fraction, normal) = parts
+        .iter()
+        .enumerate()
+        .filter_map(|(index, part)| {
+            let (fraction, normal) = part.intersect_ray(origin, direction)?;
+            Some((index, fraction, normal))
+        })
+        .min_by(|(_, a, _), (_, b, _)| a.total_cmp(b))?;
+    Some(RaycastHit {
+        index,
+        position: origin + direction * fraction,
+        normal,
+        distance: direction.length() * fraction,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Quat;
+
+    use super::*;
+
+    fn part(position: Vec3, size: Vec3) -> PartBox {
+        PartBox {
+            instance: Instance::new_orphaned("Part"),
+            position,
+            rotation: Mat3::IDENTITY,
+            half_size: size * 0.5,
+        }
+    }
+
+    #[test]
+    fn bounding_box_of_parts() {
+        let parts = [
+            part(Vec3::ZERO, Vec3::splat(2.0)),
+            part(Vec3::new(10.0, 0.0, 0.0), Vec3::new(2.0, 4.0, 2.0)),
+        ];
+        let bounds = bounding_box(&parts).unwrap();
+        assert_eq!(bounds.min, Vec3::new(-1.0, -2.0, -1.0));
+        assert_eq!(bounds.max, Vec3::new(11.0, 2.0, 1.0));
+        assert!(bounding_box(&[]).is_none());
+    }
+
+    #[test]
+    fn rotated_parts_have_larger_aabbs() {
+        let mut rotated = part(Vec3::ZERO, Vec3::new(4.0, 1.0, 1.0));
+        rotated.rotation = Mat3::from_quat(Quat::from_rotation_y(std::f32::consts::FRAC_PI_2));
+        let aabb = rotated.aabb();
+        assert!((aabb.size() - Vec3::new(1.0, 1.0, 4.0)).abs().max_element() < 1e-5);
+    }
+
+    #[test]
+    fn finds_overlapping_parts() {
+        let parts = [
+            part(Vec3::ZERO, Vec3::splat(2.0)),
+            part(Vec3::new(1.5, 0.0, 0.0), Vec3::splat(2.0)),
+            // Touches the second part, but does not overlap it
+            part(Vec3::new(3.5, 0.0, 0.0), Vec3::splat(2.0)),
+            part(Vec3::new(50.0, 0.0, 0.0), Vec3::splat(2.0)),
+            // A baseplate, which spans too many cells to be hashed
+            part(Vec3::new(0.0, -1.5, 0.0), Vec3::new(512.0, 1.0, 512.0)),
+        ];
+        assert_eq!(find_overlapping(&parts, None), [(0, 1)]);
+        assert_eq!(find_overlapping(&parts, Some(1.0)), [(0, 1)]);
+    }
+
+    #[test]
+    fn separates_rotated_parts() {
+        let a = part(Vec3::ZERO, Vec3::splat(2.0));
+        let mut b = part(Vec3::new(2.3, 0.0, 0.0), Vec3::splat(2.0));
+        assert!(!a.overlaps(&b));
+        // Rotating the cube by 45 degrees makes its corner reach into the other
+        b.rotation = Mat3::from_quat(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4));
+        assert!(a.overlaps(&b));
+    }
+
+    #[test]
+    fn raycasts_closest_part() {
+        let parts = [
+            part(Vec3::new(0.0, 0.0, -10.0), Vec3::splat(2.0)),
+            part(Vec3::new(0.0, 0.0, -5.0), Vec3::splat(2.0)),
+        ];
+        let hit = raycast(&parts, Vec3::ZERO, Vec3::new(0.0, 0.0, -20.0)).unwrap();
+        assert_eq!(hit.index, 1);
+        assert!((hit.distance - 4.0).abs() < 1e-5);
+        assert_eq!(hit.normal, Vec3::Z);
+        assert!(raycast(&parts, Vec3::ZERO, Vec3::new(0.0, 0.0, -3.0)).is_none());
+        assert!(raycast(&parts, Vec3::ZERO, Vec3::new(0.0, 0.0, 20.0)).is_none());
+    }
+}
@@ -1,6 +1,7 @@
 #![allow(clippy::items_after_statements)]
 
 use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSchedulerExt;
 
 use rbx_dom_weak::{
     Instance as DomInstance,
@@ -17,7 +18,7 @@ use crate::{
     shared::instance::{class_is_a, find_property_info},
 };
 
-use super::{Instance, data_model, registry::InstanceRegistry};
+use super::{Instance, data_model, query::Selector, registry::InstanceRegistry, transaction};
 
 #[allow(clippy::too_many_lines)]
 pub fn add_methods<M: LuaUserDataMethods<Instance>>(m: &mut M) {
@@ -48,6 +49,11 @@ pub fn add_methods<M: LuaUserDataMethods<Instance>>(m: &mut M) {
         ensure_not_destroyed(this)?;
         this.get_descendants().into_lua(lua)
     });
+    m.add_method("QueryDescendants", |lua, this, selector: String| {
+        ensure_not_destroyed(this)?;
+        let selector = selector.parse::<Selector>()?;
+        this.query_descendants(&selector).into_lua(lua)
+    });
     m.add_method("GetFullName", |lua, this, ()| {
         ensure_not_destroyed(this)?;
         this.get_full_name().into_lua(lua)
@@ -187,6 +193,9 @@ pub fn add_methods<M: LuaUserDataMethods<Instance>>(m: &mut M) {
         this.remove_tag(tag);
         Ok(())
     });
+    m.add_async_method("transact", |lua, this, args| {
+        instance_transact(lua, *this, args)
+    });
 }
 
 fn ensure_not_destroyed(inst: &Instance) -> LuaResult<()> {
@@ -199,6 +208,33 @@ fn ensure_not_destroyed(inst: &Instance) -> LuaResult<()> {
     }
 }
 
+/*
+    Runs a callback while a transaction is active for an instance and its descendants.
+
+    The transaction is committed if the callback returns, and rolled back if it errors. The
+    transaction is also dropped, and therefore rolled back, if the calling thread is cancelled.
+*/
+async fn instance_transact(
+    lua: Lua,
+    this: Instance,
+    (callback, args): (LuaFunction, LuaMultiValue),
+) -> LuaResult<LuaTable> {
+    ensure_not_destroyed(&this)?;
+    let inner_lua = lua.clone();
+    lua.with_cancellation(async move {
+        let lua = inner_lua;
+        let transaction = transaction::begin(this)?;
+        match callback.call_async::<LuaMultiValue>(args).await {
+            Ok(_) => lua.create_sequence_from(transaction.commit()),
+            Err(e) => {
+                transaction.rollback();
+                Err(e)
+            }
+        }
+    })
+    .await
+}
+
 /*
     Gets a property value for an instance.
 
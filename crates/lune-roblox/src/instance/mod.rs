@@ -33,8 +33,10 @@ pub(crate) mod terrain;
 #[cfg(feature = "mlua")]
 pub(crate) mod workspace;
 
+pub mod query;
 #[cfg(feature = "mlua")]
 pub mod registry;
+pub mod transaction;
 
 const PROPERTY_NAME_ATTRIBUTES: &str = "Attributes";
 const PROPERTY_NAME_TAGS: &str = "Tags";
@@ -210,7 +212,9 @@ impl Instance {
         } else {
             let mut dom = INTERNAL_DOM.lock().expect("Failed to lock document");
 
-            dom.destroy(self.dom_ref);
+            if !transaction::bury(&mut dom, *self) {
+                dom.destroy(self.dom_ref);
+            }
             true
         }
     }
@@ -240,7 +244,10 @@ impl Instance {
 
         let child_refs = instance.children().to_vec();
         for child_ref in child_refs {
-            dom.destroy(child_ref);
+            let child = transaction::instance_at(&dom, child_ref).expect("child exists");
+            if !transaction::bury(&mut dom, child) {
+                dom.destroy(child_ref);
+            }
         }
     }
 
@@ -294,10 +301,17 @@ impl Instance {
     */
     pub fn set_name(&self, name: impl Into<String>) {
         let mut dom = INTERNAL_DOM.lock().expect("Failed to lock document");
+        let scope = transaction::scope(&dom, self.dom_ref);
 
-        dom.get_by_ref_mut(self.dom_ref)
-            .expect("Failed to find instance in document")
-            .name = name.into();
+        let inst = dom
+            .get_by_ref_mut(self.dom_ref)
+            .expect("Failed to find instance in document");
+        let old = std::mem::replace(&mut inst.name, name.into());
+        transaction::record(scope, || transaction::Change::Name {
+            instance: *self,
+            old,
+            new: inst.name.clone(),
+        });
     }
 
     /**
@@ -335,6 +349,19 @@ impl Instance {
         let mut dom = INTERNAL_DOM.lock().expect("Failed to lock document");
 
         let parent_ref = parent.map_or_else(|| dom.root_ref(), |parent| parent.dom_ref);
+        // Moving an instance into or out of a document is a change to that document
+        let scope = transaction::scope(&dom, self.dom_ref)
+            .or_else(|| transaction::scope(&dom, parent?.dom_ref));
+
+        let old_ref = dom
+            .get_by_ref(self.dom_ref)
+            .expect("Failed to find instance in document")
+            .parent();
+        transaction::record(scope, || transaction::Change::Parent {
+            instance: *self,
+            old: transaction::instance_at(&dom, old_ref),
+            new: parent,
+        });
 
         dom.transfer_within(self.dom_ref, parent_ref);
     }
@@ -360,13 +387,20 @@ impl Instance {
         property does not actually exist for the instance class.
     */
     pub fn set_property(&self, name: impl AsRef<str>, value: DomValue) {
-        INTERNAL_DOM
-            .lock()
-            .expect("Failed to lock document")
+        let name = ustr(name.as_ref());
+        let mut dom = INTERNAL_DOM.lock().expect("Failed to lock document");
+        let scope = transaction::scope(&dom, self.dom_ref);
+        let properties = &mut dom
             .get_by_ref_mut(self.dom_ref)
             .expect("Failed to find instance in document")
-            .properties
-            .insert(ustr(name.as_ref()), value);
+            .properties;
+        transaction::record(scope, || transaction::Change::Property {
+            instance: *self,
+            name,
+            old: properties.get(&name).cloned(),
+            new: value.clone(),
+        });
+        properties.insert(name, value);
     }
 
     /**
@@ -420,6 +454,7 @@ impl Instance {
     */
     pub fn set_attribute(&self, name: impl AsRef<str>, value: DomValue) {
         let mut dom = INTERNAL_DOM.lock().expect("Failed to lock document");
+        let scope = transaction::scope(&dom, self.dom_ref);
         let inst = dom
             .get_by_ref_mut(self.dom_ref)
             .expect("Failed to find instance in document");
@@ -429,6 +464,15 @@ impl Instance {
             DomValue::Int64(i) => DomValue::Float64(i as f64),
             value => value,
         };
+        transaction::record(scope, || transaction::Change::Attribute {
+            instance: *self,
+            name: name.as_ref().to_string(),
+            old: match inst.properties.get(&ustr(PROPERTY_NAME_ATTRIBUTES)) {
+                Some(DomValue::Attributes(attributes)) => attributes.get(name.as_ref()).cloned(),
+                _ => None,
+            },
+            new: Some(value.clone()),
+        });
         if let Some(DomValue::Attributes(attributes)) =
             inst.properties.get_mut(&ustr(PROPERTY_NAME_ATTRIBUTES))
         {
@@ -453,13 +497,22 @@ impl Instance {
     */
     pub fn remove_attribute(&self, name: impl AsRef<str>) {
         let mut dom = INTERNAL_DOM.lock().expect("Failed to lock document");
+        let scope = transaction::scope(&dom, self.dom_ref);
         let inst = dom
             .get_by_ref_mut(self.dom_ref)
             .expect("Failed to find instance in document");
         if let Some(DomValue::Attributes(attributes)) =
             inst.properties.get_mut(&ustr(PROPERTY_NAME_ATTRIBUTES))
         {
-            attributes.remove(name.as_ref());
+            let old = attributes.remove(name.as_ref());
+            if old.is_some() {
+                transaction::record(scope, || transaction::Change::Attribute {
+                    instance: *self,
+                    name: name.as_ref().to_string(),
+                    old,
+                    new: None,
+                });
+            }
             if attributes.is_empty() {
                 inst.properties.remove(&ustr(PROPERTY_NAME_ATTRIBUTES));
             }
@@ -475,9 +528,15 @@ impl Instance {
     */
     pub fn add_tag(&self, name: impl AsRef<str>) {
         let mut dom = INTERNAL_DOM.lock().expect("Failed to lock document");
+        let scope = transaction::scope(&dom, self.dom_ref);
         let inst = dom
             .get_by_ref_mut(self.dom_ref)
             .expect("Failed to find instance in document");
+        transaction::record(scope, || transaction::Change::Tag {
+            instance: *self,
+            tag: name.as_ref().to_string(),
+            added: true,
+        });
         if let Some(DomValue::Tags(tags)) = inst.properties.get_mut(&ustr(PROPERTY_NAME_TAGS)) {
             tags.push(name.as_ref());
         } else {
@@ -536,13 +595,22 @@ impl Instance {
     */
     pub fn remove_tag(&self, name: impl AsRef<str>) {
         let mut dom = INTERNAL_DOM.lock().expect("Failed to lock document");
+        let scope = transaction::scope(&dom, self.dom_ref);
         let inst = dom
             .get_by_ref_mut(self.dom_ref)
             .expect("Failed to find instance in document");
         if let Some(DomValue::Tags(tags)) = inst.properties.get_mut(&ustr(PROPERTY_NAME_TAGS)) {
             let name = name.as_ref();
             let mut new_tags = tags.iter().map(ToString::to_string).collect::<Vec<_>>();
+            let old_len = new_tags.len();
             new_tags.retain(|tag| tag != name);
+            for _ in new_tags.len()..old_len {
+                transaction::record(scope, || transaction::Change::Tag {
+                    instance: *self,
+                    tag: name.to_string(),
+                    added: false,
+                });
+            }
             inst.properties
                 .insert(ustr(PROPERTY_NAME_TAGS), DomValue::Tags(new_tags.into()));
         }
@@ -0,0 +1,499 @@
+/*!
+    Selectors for querying the descendants of instances, similar to CSS selectors.
+
+    A selector consists of one or more compound selectors, separated by combinators:
+
+    - `Model Part` matches any `Part` that is a descendant of a `Model`
+    - `Model > Part` matches any `Part` that is a direct child of a `Model`
+    - `> Part` matches any `Part` that is a direct child of the queried instance
+
+    Each compound selector may start with a class name, matched using `IsA`, or `*`
+    for any class, followed by any number of these filters, in any order:
+
+    - `#Name` matches instances with the given name
+    - `.Tag` matches instances with the given tag
+    - `[Property = value]` matches instances with the given property value
+    - `[$Attribute = value]` matches instances with the given attribute value
+    - `[Property]` and `[$Attribute]` match instances that have the property or attribute set
+
+    Values may be quoted strings, numbers, `true`, `false`, or unquoted words, which
+    are treated as strings. Enum properties may be compared with either the name or
+    the value of an enum item. Several selectors may be separated using commas.
+*/
+
+use std::{borrow::Cow, collections::VecDeque, str::FromStr};
+
+use rbx_dom_weak::{
+    Instance as DomInstance, WeakDom,
+    types::{Enum as DomEnum, Ref as DomRef, Variant as DomValue},
+    ustr,
+};
+use thiserror::Error;
+
+#[cfg(feature = "mlua")]
+use mlua::prelude::*;
+
+use crate::shared::instance::{class_is_a, find_property_info};
+
+use super::{INTERNAL_DOM, Instance, PROPERTY_NAME_ATTRIBUTES, PROPERTY_NAME_TAGS};
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Invalid selector at position {position} - {message}")]
+pub struct SelectorError {
+    pub position: usize,
+    pub message: String,
+}
+
+#[cfg(feature = "mlua")]
+impl From<SelectorError> for LuaError {
+    fn from(value: SelectorError) -> Self {
+        Self::RuntimeError(value.to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Literal {
+    String(String),
+    Number(f64),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    Name(String),
+    Tag(String),
+    Property(String, Option<Literal>),
+    Attribute(String, Option<Literal>),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Compound {
+    class_name: Option<String>,
+    filters: Vec<Filter>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Combinator {
+    Child,
+    Descendant,
+}
+
+/// A single complex selector, stored in reverse, from the rightmost compound to the leftmost
+#[derive(Debug, Clone, PartialEq)]
+struct Complex {
+    last: Compound,
+    rest: Vec<(Combinator, Compound)>,
+    /// If the leftmost compound must be a direct child of the queried instance
+    anchored: bool,
+}
+
+/**
+    A parsed selector, which may be used to query the descendants of instances.
+
+    See the module documentation for the syntax of selectors.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector {
+    alternatives: Vec<Complex>,
+}
+
+impl FromStr for Selector {
+    type Err = SelectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { source: s, pos: 0 };
+        let mut alternatives = vec![parser.parse_complex()?];
+        while parser.eat(',') {
+            alternatives.push(parser.parse_complex()?);
+        }
+        parser.skip_whitespace();
+        if parser.pos < s.len() {
+            return Err(parser.error("expected ',' or the end of the selector"));
+        }
+        Ok(Self { alternatives })
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: impl Into<String>) -> SelectorError {
+        SelectorError {
+            position: self.pos,
+            message: message.into(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) -> bool {
+        let rest = &self.source[self.pos..];
+        let trimmed = rest.trim_start();
+        self.pos += rest.len() - trimmed.len();
+        rest.len() != trimmed.len()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_identifier(&mut self, what: &str) -> Result<String, SelectorError> {
+        let rest = &self.source[self.pos..];
+        let len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.error(format!("expected {what}")));
+        }
+        self.pos += len;
+        Ok(rest[..len].to_string())
+    }
+
+    fn parse_complex(&mut self) -> Result<Complex, SelectorError> {
+        let anchored = self.eat('>');
+        self.skip_whitespace();
+
+        let mut compounds = vec![self.parse_compound()?];
+        let mut combinators = Vec::new();
+        loop {
+            let had_whitespace = self.skip_whitespace();
+            let combinator = match self.peek() {
+                Some('>') => {
+                    self.pos += 1;
+                    self.skip_whitespace();
+                    Combinator::Child
+                }
+                Some(',') | None => break,
+                Some(_) if had_whitespace => Combinator::Descendant,
+                Some(_) => return Err(self.error("expected a combinator")),
+            };
+            combinators.push(combinator);
+            compounds.push(self.parse_compound()?);
+        }
+
+        let last = compounds.pop().expect("parsed at least one compound");
+        let rest = combinators
+            .into_iter()
+            .rev()
+            .zip(compounds.into_iter().rev());
+        Ok(Complex {
+            last,
+            rest: rest.collect(),
+            anchored,
+        })
+    }
+
+    fn parse_compound(&mut self) -> Result<Compound, SelectorError> {
+        let mut compound = Compound::default();
+        let mut any = false;
+        match self.peek() {
+            Some('*') => {
+                self.pos += 1;
+                any = true;
+            }
+            Some(c) if c.is_alphanumeric() || c == '_' => {
+                compound.class_name = Some(self.parse_identifier("a class name")?);
+            }
+            _ => {}
+        }
+        loop {
+            let filter = match self.peek() {
+                Some('#') => {
+                    self.pos += 1;
+                    Filter::Name(self.parse_identifier("a name")?)
+                }
+                Some('.') => {
+                    self.pos += 1;
+                    Filter::Tag(self.parse_identifier("a tag")?)
+                }
+                Some('[') => {
+                    self.pos += 1;
+                    self.parse_predicate()?
+                }
+                _ => break,
+            };
+            compound.filters.push(filter);
+        }
+        if !any && compound == Compound::default() {
+            return Err(self.error("expected a class name, '*', '#', '.' or '['"));
+        }
+        Ok(compound)
+    }
+
+    fn parse_predicate(&mut self) -> Result<Filter, SelectorError> {
+        self.skip_whitespace();
+        let is_attribute = self.peek() == Some('$');
+        if is_attribute {
+            self.pos += 1;
+        }
+        let key = self.parse_identifier(if is_attribute {
+            "an attribute name"
+        } else {
+            "a property name"
+        })?;
+        let value = if self.eat('=') {
+            self.skip_whitespace();
+            Some(self.parse_literal()?)
+        } else {
+            None
+        };
+        if !self.eat(']') {
+            return Err(self.error("expected ']'"));
+        }
+        Ok(if is_attribute {
+            Filter::Attribute(key, value)
+        } else if key == "Name" {
+            match value {
+                Some(Literal::String(name)) => Filter::Name(name),
+                _ => return Err(self.error("expected a string for the name")),
+            }
+        } else {
+            Filter::Property(key, value)
+        })
+    }
+
+    fn parse_literal(&mut self) -> Result<Literal, SelectorError> {
+        let rest = &self.source[self.pos..];
+        if let Some(quote) = rest.chars().next().filter(|c| matches!(c, '\'' | '"')) {
+            let Some(len) = rest[1..].find(quote) else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += len + 2;
+            return Ok(Literal::String(rest[1..=len].to_string()));
+        }
+        let len = rest
+            .find(|c: char| c.is_whitespace() || c == ']')
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.error("expected a value"));
+        }
+        self.pos += len;
+        let word = &rest[..len];
+        Ok(match word {
+            "true" => Literal::Bool(true),
+            "false" => Literal::Bool(false),
+            _ => match word.parse::<f64>() {
+                Ok(number) => Literal::Number(number),
+                Err(_) => Literal::String(word.to_string()),
+            },
+        })
+    }
+}
+
+impl Compound {
+    fn matches(&self, inst: &DomInstance) -> bool {
+        if let Some(class_name) = &self.class_name
+            && !class_is_a(inst.class, class_name).unwrap_or(false)
+        {
+            return false;
+        }
+        self.filters.iter().all(|filter| filter.matches(inst))
+    }
+}
+
+impl Filter {
+    fn matches(&self, inst: &DomInstance) -> bool {
+        match self {
+            Self::Name(name) => inst.name == *name,
+            Self::Tag(tag) => match inst.properties.get(&ustr(PROPERTY_NAME_TAGS)) {
+                Some(DomValue::Tags(tags)) => tags.iter().any(|t| t == tag.as_str()),
+                _ => false,
+            },
+            Self::Attribute(name, literal) => {
+                let value = match inst.properties.get(&ustr(PROPERTY_NAME_ATTRIBUTES)) {
+                    Some(DomValue::Attributes(attributes)) => attributes.get(name.as_str()),
+                    _ => None,
+                };
+                match (value, literal) {
+                    (Some(value), Some(literal)) => literal_matches(value, literal, None),
+                    (value, None) => value.is_some(),
+                    (None, Some(_)) => false,
+                }
+            }
+            Self::Property(name, literal) => {
+                if name == "ClassName" {
+                    return match literal {
+                        Some(Literal::String(class)) => inst.class == *class,
+                        Some(_) => false,
+                        None => true,
+                    };
+                }
+                let info = find_property_info(inst.class, name);
+                let value = inst.properties.get(&ustr(name)).map(Cow::Borrowed);
+                let Some(literal) = literal else {
+                    return value.is_some();
+                };
+                // Properties that were never set are compared using their default values
+                let value = value.or_else(|| {
+                    let info = info.as_ref()?;
+                    info.value_default.map(Cow::Borrowed).or_else(|| {
+                        let default = info.enum_default?;
+                        Some(Cow::Owned(DomValue::Enum(DomEnum::from_u32(default))))
+                    })
+                });
+                let enum_name = info.as_ref().and_then(|info| info.enum_name.as_deref());
+                value.is_some_and(|value| literal_matches(&value, literal, enum_name))
+            }
+        }
+    }
+}
+
+// Values from selectors are exact, such as integers, so they are also compared exactly
+#[allow(clippy::cast_precision_loss, clippy::float_cmp)]
+fn literal_matches(value: &DomValue, literal: &Literal, enum_name: Option<&str>) -> bool {
+    match (value, literal) {
+        (DomValue::String(s), Literal::String(l)) => s == l,
+        (DomValue::Bool(b), Literal::Bool(l)) => b == l,
+        (DomValue::Float32(f), Literal::Number(l)) => f64::from(*f) == *l,
+        (DomValue::Float64(f), Literal::Number(l)) => f == l,
+        (DomValue::Int32(i), Literal::Number(l)) => f64::from(*i) == *l,
+        (DomValue::Int64(i), Literal::Number(l)) => *i as f64 == *l,
+        (DomValue::Enum(e), Literal::Number(l)) => f64::from(e.to_u32()) == *l,
+        (DomValue::Enum(e), Literal::String(l)) => enum_name
+            .and_then(|enum_name| rbx_reflection_database::get().ok()?.enums.get(enum_name))
+            .and_then(|descriptor| descriptor.items.get(l.as_str()))
+            .is_some_and(|value| *value == e.to_u32()),
+        _ => false,
+    }
+}
+
+impl Complex {
+    /**
+        Checks if the instance matches this selector, where `ancestors` are the refs of all
+        ancestors of the instance, up to but not including the queried instance, nearest first.
+    */
+    fn matches(&self, dom: &WeakDom, inst: &DomInstance, ancestors: &[DomRef]) -> bool {
+        self.last.matches(inst) && self.matches_rest(dom, 0, ancestors)
+    }
+
+    fn matches_rest(&self, dom: &WeakDom, index: usize, ancestors: &[DomRef]) -> bool {
+        let Some((combinator, compound)) = self.rest.get(index) else {
+            // All compounds matched, anchored selectors must have reached the queried instance
+            return !self.anchored || ancestors.is_empty();
+        };
+        let candidates = match combinator {
+            Combinator::Child => ancestors.len().min(1),
+            Combinator::Descendant => ancestors.len(),
+        };
+        (0..candidates).any(|i| {
+            let inst = dom.get_by_ref(ancestors[i]).expect("ancestor exists");
+            compound.matches(inst) && self.matches_rest(dom, index + 1, &ancestors[i + 1..])
+        })
+    }
+}
+
+impl Selector {
+    fn matches(&self, dom: &WeakDom, inst: &DomInstance, ancestors: &[DomRef]) -> bool {
+        self.alternatives
+            .iter()
+            .any(|complex| complex.matches(dom, inst, ancestors))
+    }
+}
+
+impl Instance {
+    /**
+        Finds all descendants of this instance that match the given
+        selector, in the same order as [`Instance::get_descendants`].
+    */
+    pub fn query_descendants(&self, selector: &Selector) -> Vec<Instance> {
+        let dom = INTERNAL_DOM.lock().expect("Failed to lock document");
+
+        let mut found = Vec::new();
+        let mut queue = dom
+            .get_by_ref(self.dom_ref)
+            .expect("Failed to find instance in document")
+            .children()
+            .iter()
+            .map(|child| (*child, Vec::new()))
+            .collect::<VecDeque<_>>();
+
+        while let Some((inst_ref, ancestors)) = queue.pop_front() {
+            let inst = dom.get_by_ref(inst_ref).unwrap();
+            if selector.matches(&dom, inst, &ancestors) {
+                found.push(inst_ref);
+            }
+            for child_ref in inst.children().iter().rev() {
+                let mut child_ancestors = Vec::with_capacity(ancestors.len() + 1);
+                child_ancestors.push(inst_ref);
+                child_ancestors.extend_from_slice(&ancestors);
+                queue.push_back((*child_ref, child_ancestors));
+            }
+        }
+
+        drop(dom); // Self::new needs mutex handle, drop it first
+        found.into_iter().map(Self::new).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Selector {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_selectors() {
+        let selector = parse("Workspace > Model[Name='Car'] BasePart.Wheel, #Baseplate");
+        assert_eq!(selector.alternatives.len(), 2);
+
+        let complex = &selector.alternatives[0];
+        assert_eq!(complex.last.class_name.as_deref(), Some("BasePart"));
+        assert_eq!(complex.last.filters, [Filter::Tag("Wheel".to_string())]);
+        assert_eq!(complex.rest.len(), 2);
+        assert_eq!(complex.rest[0].0, Combinator::Descendant);
+        assert_eq!(complex.rest[0].1.filters, [Filter::Name("Car".to_string())]);
+        assert_eq!(complex.rest[1].0, Combinator::Child);
+        assert!(!complex.anchored);
+
+        let name = &selector.alternatives[1];
+        assert_eq!(name.last.class_name, None);
+        assert_eq!(name.last.filters, [Filter::Name("Baseplate".to_string())]);
+    }
+
+    #[test]
+    fn parses_predicates() {
+        let selector = parse("> *[Anchored = true][$Health=100][$Team][Material=Plastic]");
+        let complex = &selector.alternatives[0];
+        assert!(complex.anchored);
+        assert_eq!(
+            complex.last.filters,
+            [
+                Filter::Property("Anchored".to_string(), Some(Literal::Bool(true))),
+                Filter::Attribute("Health".to_string(), Some(Literal::Number(100.0))),
+                Filter::Attribute("Team".to_string(), None),
+                Filter::Property(
+                    "Material".to_string(),
+                    Some(Literal::String("Plastic".to_string()))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_selectors() {
+        for invalid in [
+            "",
+            "Part >",
+            "Part,",
+            "[Name='x",
+            "Part[Name]",
+            "#",
+            "Part)",
+        ] {
+            assert!(invalid.parse::<Selector>().is_err(), "{invalid}");
+        }
+    }
+}
@@ -0,0 +1,423 @@
+/*!
+    Transactions that record all changes made to the instances of a document while
+    they are active, so that the changes may be rolled back, such as when a script
+    editing a place file errors halfway through, or returned as a list, for reviewing edits.
+
+    A document is an instance and all of its descendants, and only one transaction
+    may be active for a document at a time. Instances destroyed during a transaction
+    are moved out of the document instead of being destroyed, until the transaction
+    ends, so that a rollback can restore them and their descendants.
+
+    Note that rolling back restores the parents of instances, but not necessarily
+    the order of instances among their siblings.
+*/
+
+use std::sync::{LazyLock, Mutex};
+
+use rbx_dom_weak::{
+    InstanceBuilder as DomInstanceBuilder, Ustr, WeakDom,
+    types::{Attributes as DomAttributes, Ref as DomRef, Variant as DomValue},
+    ustr,
+};
+use thiserror::Error;
+
+#[cfg(feature = "mlua")]
+use mlua::prelude::*;
+
+#[cfg(feature = "mlua")]
+use crate::datatypes::conversion::DomValueToLua;
+
+use super::{INTERNAL_DOM, Instance, PROPERTY_NAME_ATTRIBUTES, PROPERTY_NAME_TAGS};
+
+static ACTIVE: LazyLock<Mutex<Vec<TransactionLog>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TransactionError {
+    #[error("A transaction is already active for this document")]
+    AlreadyActive,
+    #[error("Transactions can not be started for destroyed instances")]
+    Destroyed,
+}
+
+#[cfg(feature = "mlua")]
+impl From<TransactionError> for LuaError {
+    fn from(value: TransactionError) -> Self {
+        Self::RuntimeError(value.to_string())
+    }
+}
+
+/**
+    A single change made to an instance during a transaction.
+
+    Parents that are `None` mean that the instance was orphaned, meaning that
+    a change of parent from `None` is an instance being added to the document.
+*/
+#[derive(Debug, Clone)]
+pub enum Change {
+    Destroyed {
+        instance: Instance,
+        parent: Option<Instance>,
+    },
+    Name {
+        instance: Instance,
+        old: String,
+        new: String,
+    },
+    Parent {
+        instance: Instance,
+        old: Option<Instance>,
+        new: Option<Instance>,
+    },
+    Property {
+        instance: Instance,
+        name: Ustr,
+        old: Option<DomValue>,
+        new: DomValue,
+    },
+    Attribute {
+        instance: Instance,
+        name: String,
+        old: Option<DomValue>,
+        new: Option<DomValue>,
+    },
+    Tag {
+        instance: Instance,
+        tag: String,
+        added: bool,
+    },
+}
+
+struct TransactionLog {
+    root: DomRef,
+    changes: Vec<Change>,
+    /// Instances destroyed during the transaction, kept until it ends
+    graveyard: WeakDom,
+}
+
+/**
+    An active transaction for a document.
+
+    The transaction ends when it is either committed using [`Transaction::commit`], or rolled
+    back using [`Transaction::rollback`]. Dropping it without doing either rolls it back, so
+    that a transaction always ends, even if the code making the changes errors or is cancelled.
+*/
+#[derive(Debug)]
+#[must_use = "Transactions are rolled back when dropped"]
+pub struct Transaction {
+    root: DomRef,
+    ended: bool,
+}
+
+impl Transaction {
+    /**
+        Ends the transaction, keeping all of its changes, and returns them in the order they were made.
+
+        Instances destroyed during the transaction are now destroyed for good.
+    */
+    pub fn commit(mut self) -> Vec<Change> {
+        self.ended = true;
+        take(self.root).map(|log| log.changes).unwrap_or_default()
+    }
+
+    /**
+        Ends the transaction, reverting all of its changes in reverse order,
+        and returns the changes that were reverted, in the order they were made.
+    */
+    pub fn rollback(mut self) -> Vec<Change> {
+        self.ended = true;
+        revert_all(self.root)
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if !self.ended {
+            revert_all(self.root);
+        }
+    }
+}
+
+/**
+    Begins a new transaction for the document with the given root instance, recording
+    all changes made to the root and its descendants until the transaction ends.
+
+    # Errors
+
+    - If the instance has been destroyed
+    - If a transaction is already active for the instance, or one of its ancestors or descendants
+*/
+pub fn begin(root: Instance) -> Result<Transaction, TransactionError> {
+    let dom = INTERNAL_DOM.lock().expect("Failed to lock document");
+    if dom.get_by_ref(root.dom_ref).is_none() {
+        return Err(TransactionError::Destroyed);
+    }
+    let mut active = ACTIVE.lock().expect("Failed to lock transaction");
+    let overlaps = active.iter().any(|log| {
+        is_ancestor_or_self(&dom, log.root, root.dom_ref)
+            || is_ancestor_or_self(&dom, root.dom_ref, log.root)
+    });
+    if overlaps {
+        return Err(TransactionError::AlreadyActive);
+    }
+    active.push(TransactionLog {
+        root: root.dom_ref,
+        changes: Vec::new(),
+        graveyard: WeakDom::new(DomInstanceBuilder::new("ROOT")),
+    });
+    Ok(Transaction {
+        root: root.dom_ref,
+        ended: false,
+    })
+}
+
+/**
+    Returns the root of the document with an active transaction that
+    the given instance belongs to, if any, for use with [`record`].
+*/
+pub(super) fn scope(dom: &WeakDom, dom_ref: DomRef) -> Option<DomRef> {
+    let active = ACTIVE.lock().expect("Failed to lock transaction");
+    if active.is_empty() {
+        return None;
+    }
+    let mut current = dom.get_by_ref(dom_ref);
+    while let Some(inst) = current {
+        if active.iter().any(|log| log.root == inst.referent()) {
+            return Some(inst.referent());
+        }
+        current = dom.get_by_ref(inst.parent());
+    }
+    None
+}
+
+/**
+    Records a change made to an instance, if the given scope from
+    [`scope`] belongs to a document with an active transaction.
+
+    The change is only created when it will be recorded.
+*/
+pub(super) fn record(scope: Option<DomRef>, change: impl FnOnce() -> Change) {
+    let Some(scope) = scope else {
+        return;
+    };
+    let mut active = ACTIVE.lock().expect("Failed to lock transaction");
+    if let Some(log) = active.iter_mut().find(|log| log.root == scope) {
+        log.changes.push(change());
+    }
+}
+
+/**
+    Moves an instance that is being destroyed out of the given document, if the
+    instance belongs to a document with an active transaction, so that it may be restored
+    if the transaction is rolled back. Returns `false` if the instance should be destroyed as usual.
+*/
+pub(super) fn bury(dom: &mut WeakDom, instance: Instance) -> bool {
+    let Some(scope) = scope(dom, instance.dom_ref) else {
+        return false;
+    };
+    let mut active = ACTIVE.lock().expect("Failed to lock transaction");
+    let Some(log) = active.iter_mut().find(|log| log.root == scope) else {
+        return false;
+    };
+    let Some(inst) = dom.get_by_ref(instance.dom_ref) else {
+        return false;
+    };
+    let parent = instance_at(dom, inst.parent());
+    let graveyard_root = log.graveyard.root_ref();
+    dom.transfer(instance.dom_ref, &mut log.graveyard, graveyard_root);
+    log.changes.push(Change::Destroyed { instance, parent });
+    true
+}
+
+/**
+    Returns the instance with the given ref, or `None` if it is the root of the document.
+*/
+pub(super) fn instance_at(dom: &WeakDom, dom_ref: DomRef) -> Option<Instance> {
+    if dom_ref == dom.root_ref() {
+        return None;
+    }
+    dom.get_by_ref(dom_ref).map(|inst| Instance {
+        dom_ref,
+        class_name: inst.class,
+    })
+}
+
+fn is_ancestor_or_self(dom: &WeakDom, ancestor: DomRef, dom_ref: DomRef) -> bool {
+    let mut current = dom.get_by_ref(dom_ref);
+    while let Some(inst) = current {
+        if inst.referent() == ancestor {
+            return true;
+        }
+        current = dom.get_by_ref(inst.parent());
+    }
+    false
+}
+
+fn take(root: DomRef) -> Option<TransactionLog> {
+    let mut active = ACTIVE.lock().expect("Failed to lock transaction");
+    let index = active.iter().position(|log| log.root == root)?;
+    Some(active.swap_remove(index))
+}
+
+fn revert_all(root: DomRef) -> Vec<Change> {
+    let Some(mut log) = take(root) else {
+        return Vec::new();
+    };
+    let mut dom = INTERNAL_DOM.lock().expect("Failed to lock document");
+    for change in log.changes.iter().rev() {
+        revert(&mut dom, &mut log.graveyard, change);
+    }
+    log.changes
+}
+
+fn parent_ref(dom: &WeakDom, parent: Option<Instance>) -> DomRef {
+    parent
+        .map(|parent| parent.dom_ref)
+        .filter(|parent_ref| dom.get_by_ref(*parent_ref).is_some())
+        .unwrap_or_else(|| dom.root_ref())
+}
+
+fn revert(dom: &mut WeakDom, graveyard: &mut WeakDom, change: &Change) {
+    match change {
+        Change::Destroyed { instance, parent } => {
+            if graveyard.get_by_ref(instance.dom_ref).is_some() {
+                let parent_ref = parent_ref(dom, *parent);
+                graveyard.transfer(instance.dom_ref, dom, parent_ref);
+            }
+        }
+        Change::Parent { instance, old, .. } => {
+            if dom.get_by_ref(instance.dom_ref).is_some() {
+                let parent_ref = parent_ref(dom, *old);
+                dom.transfer_within(instance.dom_ref, parent_ref);
+            }
+        }
+        Change::Name { instance, old, .. } => {
+            if let Some(inst) = dom.get_by_ref_mut(instance.dom_ref) {
+                inst.name.clone_from(old);
+            }
+        }
+        Change::Property {
+            instance,
+            name,
+            old,
+            ..
+        } => {
+            if let Some(inst) = dom.get_by_ref_mut(instance.dom_ref) {
+                match old {
+                    Some(old) => inst.properties.insert(*name, old.clone()),
+                    None => inst.properties.remove(name),
+                };
+            }
+        }
+        Change::Attribute {
+            instance,
+            name,
+            old,
+            ..
+        } => {
+            let Some(inst) = dom.get_by_ref_mut(instance.dom_ref) else {
+                return;
+            };
+            let key = ustr(PROPERTY_NAME_ATTRIBUTES);
+            let mut attributes = match inst.properties.remove(&key) {
+                Some(DomValue::Attributes(attributes)) => attributes,
+                _ => DomAttributes::new(),
+            };
+            match old {
+                Some(old) => attributes.insert(name.clone(), old.clone()),
+                None => attributes.remove(name.as_str()),
+            };
+            if !attributes.is_empty() {
+                inst.properties
+                    .insert(key, DomValue::Attributes(attributes));
+            }
+        }
+        Change::Tag {
+            instance,
+            tag,
+            added,
+        } => {
+            let Some(inst) = dom.get_by_ref_mut(instance.dom_ref) else {
+                return;
+            };
+            let key = ustr(PROPERTY_NAME_TAGS);
+            let mut tags = match inst.properties.get(&key) {
+                Some(DomValue::Tags(tags)) => tags.iter().map(ToString::to_string).collect(),
+                _ => Vec::new(),
+            };
+            if *added {
+                if let Some(index) = tags.iter().rposition(|t| t == tag) {
+                    tags.remove(index);
+                }
+            } else {
+                tags.push(tag.clone());
+            }
+            inst.properties.insert(key, DomValue::Tags(tags.into()));
+        }
+    }
+}
+
+#[cfg(feature = "mlua")]
+impl IntoLua for Change {
+    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        let value = |value: Option<DomValue>| -> LuaResult<LuaValue> {
+            match value {
+                Some(value) => Ok(LuaValue::dom_value_to_lua(lua, &value)?),
+                None => Ok(LuaValue::Nil),
+            }
+        };
+        let table = lua.create_table()?;
+        match self {
+            Self::Destroyed { instance, parent } => {
+                table.set("Kind", "Destroyed")?;
+                table.set("Instance", instance)?;
+                table.set("Old", parent)?;
+            }
+            Self::Name { instance, old, new } => {
+                table.set("Kind", "Name")?;
+                table.set("Instance", instance)?;
+                table.set("Old", old)?;
+                table.set("New", new)?;
+            }
+            Self::Parent { instance, old, new } => {
+                table.set("Kind", "Parent")?;
+                table.set("Instance", instance)?;
+                table.set("Old", old)?;
+                table.set("New", new)?;
+            }
+            Self::Property {
+                instance,
+                name,
+                old,
+                new,
+            } => {
+                table.set("Kind", "Property")?;
+                table.set("Instance", instance)?;
+                table.set("Name", name.as_str())?;
+                table.set("Old", value(old)?)?;
+                table.set("New", value(Some(new))?)?;
+            }
+            Self::Attribute {
+                instance,
+                name,
+                old,
+                new,
+            } => {
+                table.set("Kind", "Attribute")?;
+                table.set("Instance", instance)?;
+                table.set("Name", name)?;
+                table.set("Old", value(old)?)?;
+                table.set("New", value(new)?)?;
+            }
+            Self::Tag {
+                instance,
+                tag,
+                added,
+            } => {
+                table.set("Kind", if added { "TagAdded" } else { "TagRemoved" })?;
+                table.set("Instance", instance)?;
+                table.set("Name", tag)?;
+            }
+        }
+        Ok(LuaValue::Table(table))
+    }
+}
@@ -8,6 +8,7 @@ use lune_utils::TableBuilder;
 
 pub mod datatypes;
 pub mod document;
+pub mod geometry;
 pub mod instance;
 pub mod reflection;
 
@@ -0,0 +1,182 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use std::ops::Range;
+
+use mlua::{Buffer as LuaBuffer, prelude::*};
+
+use lune_utils::TableBuilder;
+
+mod ops;
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `buffer` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `buffer` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_function("xor", buffer_xor)?
+        .with_function("fill", buffer_fill)?
+        .with_function("find", buffer_find)?
+        .with_function("crc32", buffer_crc32)?
+        .with_function("swapEndian", buffer_swap_endian)?
+        .build_readonly()
+}
+
+/**
+    Returns the contents of the given buffer as a slice.
+
+    # Safety
+
+    The slice must not outlive the call of the function that it is used
+    in, during which Luau can not resize, move, or write to the buffer.
+*/
+unsafe fn buffer_bytes(buffer: &LuaBuffer) -> &[u8] {
+    // NOTE: Buffer has a read + write interface instead of slices, so the
+    // slice is re-created from the pointer to the buffer, the same as for
+    // bodies of requests and responses in the net standard library
+    let ptr = LuaValue::Buffer(buffer.clone()).to_pointer().cast::<u8>();
+    unsafe { std::slice::from_raw_parts(ptr, buffer.len()) }
+}
+
+/**
+    Returns the contents of the given buffer as a mutable slice.
+
+    # Safety
+
+    The same as for [`buffer_bytes`], and no other slice
+    of the same buffer may be used at the same time.
+*/
+#[allow(clippy::mut_from_ref)]
+unsafe fn buffer_bytes_mut(buffer: &LuaBuffer) -> &mut [u8] {
+    let ptr = LuaValue::Buffer(buffer.clone())
+        .to_pointer()
+        .cast::<u8>()
+        .cast_mut();
+    unsafe { std::slice::from_raw_parts_mut(ptr, buffer.len()) }
+}
+
+/**
+    Returns the range of the given offset and count within a buffer of the given
+    length, where a missing count means the rest of the buffer after the offset.
+*/
+fn buffer_range(
+    len: usize,
+    offset: Option<usize>,
+    count: Option<usize>,
+) -> LuaResult<Range<usize>> {
+    let offset = offset.unwrap_or(0);
+    let count = count.unwrap_or(len.saturating_sub(offset));
+    match offset.checked_add(count) {
+        Some(end) if offset <= len && end <= len => Ok(offset..end),
+        _ => Err(LuaError::runtime(format!(
+            "Range {offset}..{} is out of bounds for buffer of size {len}",
+            offset.saturating_add(count),
+        ))),
+    }
+}
+
+/**
+    A sequence of bytes given as either a buffer or a string, which are copied
+    out of the value, so that they may be used while writing to any buffer.
+*/
+struct Bytes(Vec<u8>);
+
+impl FromLua for Bytes {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Buffer(buffer) => Ok(Self(buffer.to_vec())),
+            LuaValue::String(string) => Ok(Self(string.as_bytes().to_vec())),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "Bytes".to_string(),
+                message: Some(format!(
+                    "Expected buffer or string, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+fn buffer_xor(
+    _: &Lua,
+    (target, Bytes(key), offset, count): (LuaBuffer, Bytes, Option<usize>, Option<usize>),
+) -> LuaResult<()> {
+    let range = buffer_range(target.len(), offset, count)?;
+    if key.is_empty() {
+        return Err(LuaError::runtime("Key must not be empty"));
+    }
+    // SAFETY: The slice is only used within this call, and the key was copied
+    let bytes = unsafe { buffer_bytes_mut(&target) };
+    ops::xor(&mut bytes[range], &key);
+    Ok(())
+}
+
+fn buffer_fill(
+    _: &Lua,
+    (target, Bytes(pattern), offset, count): (LuaBuffer, Bytes, Option<usize>, Option<usize>),
+) -> LuaResult<()> {
+    let range = buffer_range(target.len(), offset, count)?;
+    if pattern.is_empty() {
+        return Err(LuaError::runtime("Pattern must not be empty"));
+    }
+    // SAFETY: The slice is only used within this call, and the pattern was copied
+    let bytes = unsafe { buffer_bytes_mut(&target) };
+    ops::fill(&mut bytes[range], &pattern);
+    Ok(())
+}
+
+fn buffer_find(
+    _: &Lua,
+    (haystack, Bytes(needle), init): (LuaBuffer, Bytes, Option<usize>),
+) -> LuaResult<Option<usize>> {
+    let range = buffer_range(haystack.len(), init, None)?;
+    // SAFETY: The slice is only used within this call, and is only read from
+    let bytes = unsafe { buffer_bytes(&haystack) };
+    Ok(ops::find(&bytes[range.clone()], &needle).map(|index| range.start + index))
+}
+
+fn buffer_crc32(
+    _: &Lua,
+    (source, offset, count): (LuaBuffer, Option<usize>, Option<usize>),
+) -> LuaResult<u32> {
+    let range = buffer_range(source.len(), offset, count)?;
+    // SAFETY: The slice is only used within this call, and is only read from
+    let bytes = unsafe { buffer_bytes(&source) };
+    Ok(ops::crc32(&bytes[range]))
+}
+
+fn buffer_swap_endian(
+    _: &Lua,
+    (target, width, offset, count): (LuaBuffer, usize, Option<usize>, Option<usize>),
+) -> LuaResult<()> {
+    if !matches!(width, 2 | 4 | 8) {
+        return Err(LuaError::runtime(format!(
+            "Width must be 2, 4 or 8 bytes, got {width}"
+        )));
+    }
+    let range = buffer_range(target.len(), offset, count)?;
+    if !range.len().is_multiple_of(width) {
+        return Err(LuaError::runtime(format!(
+            "Count must be a multiple of the width of {width} bytes, got {}",
+            range.len()
+        )));
+    }
+    // SAFETY: The slice is only used within this call
+    let bytes = unsafe { buffer_bytes_mut(&target) };
+    ops::swap_endian(&mut bytes[range], width);
+    Ok(())
+}
@@ -0,0 +1,167 @@
+/*!
+    Bulk operations on byte slices.
+
+    These are written so that they may be vectorized by the compiler, by working
+    on whole words or fixed-size chunks instead of single bytes where possible,
+    while searching and checksums use libraries with explicit SIMD implementations.
+*/
+
+const WORD: usize = size_of::<u64>();
+
+/**
+    XORs the given bytes in place with the given key, repeating the key as needed.
+*/
+pub fn xor(target: &mut [u8], key: &[u8]) {
+    if key.is_empty() {
+        return;
+    }
+
+    // Keys that fit into a word are repeated into a whole word, so that
+    // the bulk of the target can be XORed a word at a time
+    if WORD.is_multiple_of(key.len()) {
+        let mut word = [0; WORD];
+        for (i, byte) in word.iter_mut().enumerate() {
+            *byte = key[i % key.len()];
+        }
+        let word = u64::from_ne_bytes(word);
+
+        let mut chunks = target.chunks_exact_mut(WORD);
+        for chunk in &mut chunks {
+            let value = u64::from_ne_bytes((&*chunk).try_into().expect("chunk is a word"));
+            chunk.copy_from_slice(&(value ^ word).to_ne_bytes());
+        }
+        for (byte, key) in chunks.into_remainder().iter_mut().zip(key.iter().cycle()) {
+            *byte ^= key;
+        }
+    } else {
+        for chunk in target.chunks_mut(key.len()) {
+            for (byte, key) in chunk.iter_mut().zip(key) {
+                *byte ^= key;
+            }
+        }
+    }
+}
+
+/**
+    Fills the given bytes with the given pattern, repeating the pattern as needed.
+
+    The pattern is copied into the target once, and the filled part of the target is
+    then copied into the rest of it, doubling in size each time, so that large fills
+    only take a few calls to `copy_within`.
+*/
+pub fn fill(target: &mut [u8], pattern: &[u8]) {
+    if pattern.is_empty() || target.is_empty() {
+        return;
+    }
+    if let [byte] = pattern {
+        target.fill(*byte);
+        return;
+    }
+
+    let first = pattern.len().min(target.len());
+    target[..first].copy_from_slice(&pattern[..first]);
+    let mut filled = first;
+    while filled < target.len() {
+        let count = filled.min(target.len() - filled);
+        target.copy_within(..count, filled);
+        filled += count;
+    }
+}
+
+/**
+    Finds the first occurrence of the needle in the given haystack, if any.
+*/
+pub fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    memchr::memmem::find(haystack, needle)
+}
+
+/**
+    Computes the CRC-32 (IEEE) checksum of the given bytes.
+*/
+pub fn crc32(bytes: &[u8]) -> u32 {
+    crc32fast::hash(bytes)
+}
+
+/**
+    Reverses the byte order of each element of the given width in place,
+    converting an array of elements between little and big endian.
+
+    The length of the target must be a multiple of the width.
+*/
+pub fn swap_endian(target: &mut [u8], width: usize) {
+    debug_assert!(target.len().is_multiple_of(width));
+    match width {
+        2 => swap_chunks::<2>(target),
+        4 => swap_chunks::<4>(target),
+        8 => swap_chunks::<8>(target),
+        _ => target.chunks_exact_mut(width).for_each(<[u8]>::reverse),
+    }
+}
+
+fn swap_chunks<const N: usize>(target: &mut [u8]) {
+    for chunk in target.chunks_exact_mut(N) {
+        let chunk: &mut [u8; N] = chunk.try_into().expect("chunk has the given width");
+        chunk.reverse();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xor_repeats_key() {
+        for key in [
+            &[0xFF][..],
+            &[1, 2],
+            &[1, 2, 3],
+            &[1, 2, 3, 4, 5, 6, 7, 8, 9],
+        ] {
+            let original = (0..37).collect::<Vec<u8>>();
+            let mut target = original.clone();
+            xor(&mut target, key);
+            for (i, (byte, original)) in target.iter().zip(&original).enumerate() {
+                assert_eq!(*byte, original ^ key[i % key.len()]);
+            }
+            xor(&mut target, key);
+            assert_eq!(target, original);
+        }
+    }
+
+    #[test]
+    fn fill_repeats_pattern() {
+        let mut target = [0; 11];
+        fill(&mut target, b"abc");
+        assert_eq!(&target, b"abcabcabcab");
+
+        let mut target = [0; 2];
+        fill(&mut target, b"abc");
+        assert_eq!(&target, b"ab");
+
+        let mut target = [0; 4];
+        fill(&mut target, b"z");
+        assert_eq!(&target, b"zzzz");
+    }
+
+    #[test]
+    fn find_and_crc32() {
+        assert_eq!(find(b"hello, lune!", b"lune"), Some(7));
+        assert_eq!(find(b"hello, lune!", b"luau"), None);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn swap_endian_reverses_elements() {
+        let mut target = [1, 2, 3, 4, 5, 6, 7, 8];
+        swap_endian(&mut target, 2);
+        assert_eq!(target, [2, 1, 4, 3, 6, 5, 8, 7]);
+
+        let mut target = 0x0102_0304_u32.to_le_bytes();
+        swap_endian(&mut target, 4);
+        assert_eq!(target, 0x0102_0304_u32.to_be_bytes());
+
+        let mut target = [1, 2, 3, 4, 5, 6];
+        swap_endian(&mut target, 3);
+        assert_eq!(target, [3, 2, 1, 6, 5, 4]);
+    }
+}
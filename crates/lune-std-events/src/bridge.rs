@@ -0,0 +1,174 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_channel::{Receiver, Sender};
+use parking_lot::Mutex;
+use serde_json::Value as JsonValue;
+
+/// An event posted by the host: name, value
+pub(crate) type PostedEvent = (String, JsonValue);
+
+/**
+    A bridge between the host application and the `events` standard library.
+
+    The host posts events into the Lua VM from any thread, which are queued and then
+    delivered to listeners added using `events.listen` and `events.wait`, and subscribes
+    to events emitted from Lua using `events.emit`. Values of events are JSON values.
+
+    Bridges are cheap to clone, and all clones share the same events, so a bridge must
+    be stored in Lua app data by the runtime that the `events` library is used in.
+*/
+#[derive(Debug, Clone)]
+pub struct EventBridge {
+    posted_tx: Sender<PostedEvent>,
+    posted_rx: Receiver<PostedEvent>,
+    subscribers: Arc<Mutex<HashMap<String, Vec<Sender<JsonValue>>>>>,
+}
+
+impl EventBridge {
+    /**
+        Creates a new bridge, without any events or subscribers.
+    */
+    #[must_use]
+    pub fn new() -> Self {
+        let (posted_tx, posted_rx) = async_channel::unbounded();
+        Self {
+            posted_tx,
+            posted_rx,
+            subscribers: Arc::default(),
+        }
+    }
+
+    /**
+        Posts an event with the given name and value into the Lua VM.
+
+        This never blocks - the event is queued until the script listens for events,
+        and is then delivered to all listeners for the given name at that time.
+    */
+    pub fn post(&self, name: impl Into<String>, value: impl Into<JsonValue>) {
+        // The bridge holds a receiver itself, so the channel is never closed
+        let _ = self.posted_tx.try_send((name.into(), value.into()));
+    }
+
+    /**
+        Subscribes to events with the given name, emitted from Lua using `events.emit`.
+
+        Only events emitted after subscribing are received, and
+        the subscription ends once the returned value is dropped.
+    */
+    #[must_use]
+    pub fn subscribe(&self, name: impl Into<String>) -> EventSubscription {
+        let (tx, rx) = async_channel::unbounded();
+        self.subscribers
+            .lock()
+            .entry(name.into())
+            .or_default()
+            .push(tx);
+        EventSubscription { rx }
+    }
+
+    /**
+        Sends an event emitted from Lua to all subscribers for its name,
+        returning the number of subscribers that the event was sent to.
+    */
+    pub(crate) fn emit(&self, name: &str, value: &JsonValue) -> usize {
+        let mut subscribers = self.subscribers.lock();
+        let Some(senders) = subscribers.get_mut(name) else {
+            return 0;
+        };
+        senders.retain(|tx| tx.try_send(value.clone()).is_ok());
+        let count = senders.len();
+        if count == 0 {
+            subscribers.remove(name);
+        }
+        count
+    }
+
+    /**
+        Returns the queue of events posted by the host.
+    */
+    pub(crate) fn posted(&self) -> &Receiver<PostedEvent> {
+        &self.posted_rx
+    }
+}
+
+impl Default for EventBridge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/**
+    A subscription to events with a single name, emitted from Lua using `events.emit`.
+
+    Subscriptions may be moved to, and received from, any thread.
+*/
+#[derive(Debug)]
+pub struct EventSubscription {
+    rx: Receiver<JsonValue>,
+}
+
+impl EventSubscription {
+    /**
+        Returns the value of the next event, if one has been emitted, without waiting.
+    */
+    #[must_use]
+    pub fn try_recv(&self) -> Option<JsonValue> {
+        self.rx.try_recv().ok()
+    }
+
+    /**
+        Waits for the value of the next event, blocking the current thread.
+
+        Returns `None` once every clone of the bridge has been dropped.
+    */
+    #[must_use]
+    pub fn recv_blocking(&self) -> Option<JsonValue> {
+        self.rx.recv_blocking().ok()
+    }
+
+    /**
+        Waits for the value of the next event.
+
+        Returns `None` once every clone of the bridge has been dropped.
+    */
+    pub async fn recv(&self) -> Option<JsonValue> {
+        self.rx.recv().await.ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emits_to_subscribers_by_name() {
+        let bridge = EventBridge::new();
+        let first = bridge.subscribe("resize");
+        let second = bridge.subscribe("resize");
+        let other = bridge.subscribe("close");
+
+        assert_eq!(bridge.emit("resize", &JsonValue::from(800)), 2);
+        assert_eq!(first.try_recv(), Some(JsonValue::from(800)));
+        assert_eq!(second.try_recv(), Some(JsonValue::from(800)));
+        assert_eq!(other.try_recv(), None);
+
+        drop(first);
+        assert_eq!(bridge.emit("resize", &JsonValue::Null), 1);
+        drop(second);
+        assert_eq!(bridge.emit("resize", &JsonValue::Null), 0);
+        assert_eq!(bridge.emit("missing", &JsonValue::Null), 0);
+    }
+
+    #[test]
+    fn queues_posted_events() {
+        let bridge = EventBridge::new();
+        let host = bridge.clone();
+        std::thread::spawn(move || host.post("click", "button"))
+            .join()
+            .unwrap();
+        assert_eq!(
+            bridge.posted().try_recv().ok(),
+            Some(("click".to_string(), JsonValue::from("button")))
+        );
+    }
+}
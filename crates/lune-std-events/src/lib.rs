@@ -0,0 +1,82 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use mlua::prelude::*;
+use serde_json::Value as JsonValue;
+
+use lune_utils::TableBuilder;
+
+mod bridge;
+mod listeners;
+mod signal;
+
+use self::listeners::{EventConnection, Listeners};
+use self::signal::Signal;
+
+pub use self::bridge::{EventBridge, EventSubscription};
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+// NOTE: These are the same options that are used by the `serde` standard library
+const LUA_DESERIALIZE_OPTIONS: LuaDeserializeOptions = LuaDeserializeOptions::new()
+    .sort_keys(true)
+    .deny_recursive_tables(false)
+    .deny_unsupported_types(true);
+
+/**
+    Returns a string containing type definitions for the `events` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `events` standard library module.
+
+    # Errors
+
+    Errors when out of memory, or if default Lua globals are missing.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    let listeners = Listeners::default();
+    let listen_listeners = listeners.clone();
+    let signal = Signal::create_table(&lua)?;
+    TableBuilder::new(lua)?
+        .with_value("Signal", signal)?
+        .with_function("emit", events_emit)?
+        .with_function("listen", move |lua, (name, callback)| {
+            events_listen(lua, &listen_listeners, name, callback)
+        })?
+        .with_async_function("wait", move |lua, name| {
+            let listeners = listeners.clone();
+            async move { listeners.wait(&lua, name).await }
+        })?
+        .build_readonly()
+}
+
+fn events_emit(lua: &Lua, (name, value): (String, LuaValue)) -> LuaResult<()> {
+    let Some(bridge) = lua.app_data_ref::<EventBridge>().map(|b| b.clone()) else {
+        return Err(LuaError::runtime(
+            "Events can not be emitted - no event bridge was set up by the host",
+        ));
+    };
+    let value: JsonValue = lua
+        .from_value_with(value, LUA_DESERIALIZE_OPTIONS)
+        .map_err(|e| LuaError::runtime(format!("Failed to emit event '{name}' - {e}")))?;
+    bridge.emit(&name, &value);
+    Ok(())
+}
+
+fn events_listen(
+    lua: &Lua,
+    listeners: &Listeners,
+    name: String,
+    callback: LuaFunction,
+) -> LuaResult<EventConnection> {
+    let id = listeners.listen(lua, name.clone(), callback)?;
+    Ok(EventConnection {
+        listeners: listeners.clone(),
+        name,
+        id,
+    })
+}
@@ -0,0 +1,274 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use async_channel::{Receiver, Sender};
+use futures_lite::FutureExt;
+use serde_json::Value as JsonValue;
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, LuaSpawnExt, ShutdownHook};
+
+use lune_utils::shutdown::PRIORITY_EVENTS_LISTEN;
+
+use crate::bridge::{EventBridge, PostedEvent};
+
+// NOTE: These are the same options that are used by the `serde` standard library
+const LUA_SERIALIZE_OPTIONS: LuaSerializeOptions = LuaSerializeOptions::new()
+    .set_array_metatable(false)
+    .serialize_none_to_null(false)
+    .serialize_unit_to_null(false);
+
+#[derive(Debug, Default)]
+struct ListenerState {
+    next_id: u64,
+    listeners: HashMap<String, Vec<(u64, LuaFunction)>>,
+    waiters: HashMap<String, Vec<(u64, Sender<JsonValue>)>>,
+    stop_tx: Option<Sender<()>>,
+    shutdown_hooked: bool,
+}
+
+impl ListenerState {
+    fn is_idle(&self) -> bool {
+        self.listeners.is_empty() && self.waiters.is_empty()
+    }
+
+    /// Stops receiving posted events once nothing is listening for them anymore,
+    /// so that scripts which are done listening for events are able to exit
+    fn stop_if_idle(&mut self) {
+        if self.is_idle()
+            && let Some(stop_tx) = self.stop_tx.take()
+        {
+            stop_tx.close();
+        }
+    }
+}
+
+/**
+    Listeners for events posted by the host, added using `events.listen` and `events.wait`.
+
+    Posted events are only received while anything is listening for them, which keeps
+    the script running - the same as for servers created using `net.serve`.
+*/
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Listeners(Rc<RefCell<ListenerState>>);
+
+impl Listeners {
+    /**
+        Adds a listener, which is called with the value of each event with the given name.
+    */
+    pub fn listen(&self, lua: &Lua, name: String, callback: LuaFunction) -> LuaResult<u64> {
+        self.start_receiving(lua)?;
+        let mut state = self.0.borrow_mut();
+        state.next_id += 1;
+        let id = state.next_id;
+        state
+            .listeners
+            .entry(name)
+            .or_default()
+            .push((id, callback));
+        Ok(id)
+    }
+
+    /**
+        Removes a listener, if it has not already been removed.
+    */
+    pub fn disconnect(&self, name: &str, id: u64) {
+        let mut state = self.0.borrow_mut();
+        if let Some(listeners) = state.listeners.get_mut(name) {
+            listeners.retain(|(listener_id, _)| *listener_id != id);
+            if listeners.is_empty() {
+                state.listeners.remove(name);
+            }
+        }
+        state.stop_if_idle();
+    }
+
+    /**
+        Returns whether the listener with the given id is still connected.
+    */
+    pub fn is_connected(&self, name: &str, id: u64) -> bool {
+        self.0
+            .borrow()
+            .listeners
+            .get(name)
+            .is_some_and(|listeners| listeners.iter().any(|(listener_id, _)| *listener_id == id))
+    }
+
+    /**
+        Waits for the next event with the given name, returning its value.
+    */
+    pub async fn wait(&self, lua: &Lua, name: String) -> LuaResult<LuaValue> {
+        self.start_receiving(lua)?;
+
+        let (tx, rx) = async_channel::bounded(1);
+        let id = {
+            let mut state = self.0.borrow_mut();
+            state.next_id += 1;
+            let id = state.next_id;
+            state
+                .waiters
+                .entry(name.clone())
+                .or_default()
+                .push((id, tx));
+            id
+        };
+
+        // Waiting may be cancelled, such as using `task.cancel`, and the waiter
+        // must then be removed, or it would keep the script running forever
+        let guard = WaiterGuard {
+            listeners: self.clone(),
+            name,
+            id,
+        };
+        let value = rx.recv().await.map_err(|_| {
+            LuaError::runtime("Stopped waiting for event - the runtime is shutting down")
+        })?;
+        drop(guard);
+
+        lua.to_value_with(&value, LUA_SERIALIZE_OPTIONS)
+    }
+
+    /**
+        Starts receiving posted events, if not already receiving them.
+    */
+    fn start_receiving(&self, lua: &Lua) -> LuaResult<()> {
+        let mut state = self.0.borrow_mut();
+        if state.stop_tx.is_some() {
+            return Ok(());
+        }
+
+        let Some(bridge) = lua.app_data_ref::<EventBridge>().map(|b| b.clone()) else {
+            return Err(LuaError::runtime(
+                "Events can not be received - no event bridge was set up by the host",
+            ));
+        };
+
+        let (stop_tx, stop_rx) = async_channel::bounded::<()>(1);
+        state.stop_tx = Some(stop_tx);
+
+        if !state.shutdown_hooked {
+            state.shutdown_hooked = true;
+            lua.on_shutdown(PRIORITY_EVENTS_LISTEN, {
+                let this = self.clone();
+                ShutdownHook::native(move |_| this.disconnect_all())
+            });
+        }
+        drop(state);
+
+        lua.spawn_local({
+            let this = self.clone();
+            let lua = lua.clone();
+            async move {
+                while let Some((name, value)) = next_or_stop(bridge.posted(), &stop_rx).await {
+                    this.deliver(&lua, &name, &value);
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /**
+        Delivers a posted event to all listeners and waiters for its name.
+    */
+    fn deliver(&self, lua: &Lua, name: &str, value: &JsonValue) {
+        let (callbacks, waiters) = {
+            let mut state = self.0.borrow_mut();
+            let callbacks = state
+                .listeners
+                .get(name)
+                .map(|listeners| {
+                    listeners
+                        .iter()
+                        .map(|(_, callback)| callback.clone())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            let waiters = state.waiters.remove(name).unwrap_or_default();
+            state.stop_if_idle();
+            (callbacks, waiters)
+        };
+
+        for (_, tx) in waiters {
+            let _ = tx.try_send(value.clone());
+        }
+
+        if callbacks.is_empty() {
+            return;
+        }
+        // NOTE: JSON values can only fail to convert when out of memory
+        let Ok(lua_value) = lua.to_value_with(value, LUA_SERIALIZE_OPTIONS) else {
+            return;
+        };
+        for callback in callbacks {
+            let _ = lua.push_thread_back(callback, lua_value.clone());
+        }
+    }
+
+    /**
+        Removes all listeners and waiters, and stops receiving posted events.
+    */
+    fn disconnect_all(&self) {
+        let mut state = self.0.borrow_mut();
+        state.listeners.clear();
+        state.waiters.clear();
+        state.stop_if_idle();
+    }
+}
+
+/// Removes a waiter once it is done waiting, or once waiting was cancelled
+struct WaiterGuard {
+    listeners: Listeners,
+    name: String,
+    id: u64,
+}
+
+impl Drop for WaiterGuard {
+    fn drop(&mut self) {
+        let mut state = self.listeners.0.borrow_mut();
+        if let Some(waiters) = state.waiters.get_mut(&self.name) {
+            waiters.retain(|(id, _)| *id != self.id);
+            if waiters.is_empty() {
+                state.waiters.remove(&self.name);
+            }
+        }
+        state.stop_if_idle();
+    }
+}
+
+/// Receives the next posted event, or returns `None` once receiving was stopped
+async fn next_or_stop(
+    posted: &Receiver<PostedEvent>,
+    stop_rx: &Receiver<()>,
+) -> Option<PostedEvent> {
+    let next = async { posted.recv().await.ok() };
+    let stop = async {
+        let _ = stop_rx.recv().await;
+        None
+    };
+    stop.or(next).await
+}
+
+/**
+    A connection to a listener added using `events.listen`.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct EventConnection {
+    pub listeners: Listeners,
+    pub name: String,
+    pub id: u64,
+}
+
+impl LuaUserData for EventConnection {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("connected", |_, this| {
+            Ok(this.listeners.is_connected(&this.name, this.id))
+        });
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("disconnect", |_, this, (): ()| {
+            this.listeners.disconnect(&this.name, this.id);
+            Ok(())
+        });
+    }
+}
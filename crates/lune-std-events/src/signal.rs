@@ -0,0 +1,187 @@
+use std::{cell::RefCell, rc::Rc};
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::Functions;
+
+const WAIT_IMPL_LUA: &str = r"
+local signal = ...
+wait(signal, running())
+return yield()
+";
+
+/**
+    Functions shared by all signals, created once along with the `events` library.
+*/
+#[derive(Debug)]
+struct SignalFunctions {
+    spawn: LuaFunction,
+    wait: LuaFunction,
+}
+
+#[derive(Debug)]
+struct SignalHandler {
+    id: u64,
+    callback: LuaFunction,
+    once: bool,
+}
+
+#[derive(Debug, Default)]
+struct SignalState {
+    next_id: u64,
+    handlers: Vec<SignalHandler>,
+    waiting: Vec<LuaThread>,
+}
+
+/**
+    A signal created using `events.Signal.new`, which calls all of its
+    connected handlers, and resumes all of its waiting threads, when fired.
+
+    Signals are only ever fired from Lua, and do not keep the script running -
+    threads waiting for a signal that is never fired are simply never resumed.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct Signal {
+    fns: Rc<SignalFunctions>,
+    state: Rc<RefCell<SignalState>>,
+}
+
+impl Signal {
+    /**
+        Creates the `Signal` table, containing the `new` constructor for signals.
+    */
+    pub fn create_table(lua: &Lua) -> LuaResult<LuaTable> {
+        let coroutine = lua.globals().get::<LuaTable>("coroutine")?;
+        let wait_env = lua.create_table_from([
+            ("wait", lua.create_function(Self::add_waiting)?),
+            ("running", coroutine.get::<LuaFunction>("running")?),
+            ("yield", coroutine.get::<LuaFunction>("yield")?),
+        ])?;
+        let wait = lua
+            .load(WAIT_IMPL_LUA)
+            .set_name("Signal:Wait")
+            .set_environment(wait_env)
+            .into_function()?;
+
+        let fns = Rc::new(SignalFunctions {
+            spawn: Functions::new(lua.clone())?.spawn,
+            wait,
+        });
+        let new = lua.create_function(move |_, ()| {
+            Ok(Signal {
+                fns: Rc::clone(&fns),
+                state: Rc::default(),
+            })
+        })?;
+
+        let table = lua.create_table_from([("new", new)])?;
+        table.set_readonly(true);
+        Ok(table)
+    }
+
+    fn add_waiting(_: &Lua, (this, thread): (LuaUserDataRef<Self>, LuaThread)) -> LuaResult<()> {
+        this.state.borrow_mut().waiting.push(thread);
+        Ok(())
+    }
+
+    fn connect(&self, callback: LuaFunction, once: bool) -> SignalConnection {
+        let mut state = self.state.borrow_mut();
+        state.next_id += 1;
+        let id = state.next_id;
+        state.handlers.push(SignalHandler { id, callback, once });
+        SignalConnection {
+            signal: self.clone(),
+            id,
+        }
+    }
+
+    fn disconnect(&self, id: u64) -> bool {
+        let mut state = self.state.borrow_mut();
+        let before = state.handlers.len();
+        state.handlers.retain(|handler| handler.id != id);
+        state.handlers.len() != before
+    }
+
+    fn is_connected(&self, id: u64) -> bool {
+        let state = self.state.borrow();
+        state.handlers.iter().any(|handler| handler.id == id)
+    }
+
+    fn fire(&self, args: &LuaMultiValue) -> LuaResult<()> {
+        let (handlers, waiting) = {
+            let mut state = self.state.borrow_mut();
+            let handlers = state
+                .handlers
+                .iter()
+                .map(|handler| (handler.id, handler.callback.clone(), handler.once))
+                .collect::<Vec<_>>();
+            (handlers, std::mem::take(&mut state.waiting))
+        };
+
+        // Handlers are spawned immediately, in the order that they were connected,
+        // and may disconnect other handlers - which are then no longer called
+        for (id, callback, once) in handlers {
+            let connected = if once {
+                self.disconnect(id)
+            } else {
+                self.is_connected(id)
+            };
+            if connected {
+                self.fns.spawn.call::<()>((callback, args.clone()))?;
+            }
+        }
+
+        for thread in waiting {
+            self.fns.spawn.call::<()>((thread, args.clone()))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl LuaUserData for Signal {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        // NOTE: Waiting must yield the calling thread, which is only
+        // possible from Lua, so the method is implemented in Lua
+        fields.add_field_method_get("Wait", |_, this| Ok(this.fns.wait.clone()));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("Fire", |_, this, args: LuaMultiValue| this.fire(&args));
+        methods.add_method("Connect", |_, this, callback: LuaFunction| {
+            Ok(this.connect(callback, false))
+        });
+        methods.add_method("Once", |_, this, callback: LuaFunction| {
+            Ok(this.connect(callback, true))
+        });
+        methods.add_method("DisconnectAll", |_, this, (): ()| {
+            this.state.borrow_mut().handlers.clear();
+            Ok(())
+        });
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, _, (): ()| Ok("Signal"));
+    }
+}
+
+/**
+    A connection to a handler of a signal, returned by `Signal:Connect` and `Signal:Once`.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct SignalConnection {
+    signal: Signal,
+    id: u64,
+}
+
+impl LuaUserData for SignalConnection {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("Connected", |_, this| Ok(this.signal.is_connected(this.id)));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("Disconnect", |_, this, (): ()| {
+            this.signal.disconnect(this.id);
+            Ok(())
+        });
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, _, (): ()| {
+            Ok("SignalConnection")
+        });
+    }
+}
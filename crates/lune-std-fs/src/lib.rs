@@ -7,16 +7,19 @@ use async_fs as fs;
 use bstr::{BString, ByteSlice};
 use futures_lite::prelude::*;
 use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSchedulerExt;
 
 use lune_utils::TableBuilder;
 
 mod copy;
 mod metadata;
+mod mmap;
 mod options;
 
 use self::copy::copy;
 use self::metadata::FsMetadata;
-use self::options::FsWriteOptions;
+use self::mmap::FsMappedBuffer;
+use self::options::{FsMmapOptions, FsWriteOptions};
 
 const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
 
@@ -48,16 +51,24 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
         .with_async_function("isDir", fs_is_dir)?
         .with_async_function("move", fs_move)?
         .with_async_function("copy", fs_copy)?
+        .with_function("mmap", fs_mmap)?
+        .with_function("sharedMemory", fs_shared_memory)?
         .build_readonly()
 }
 
 async fn fs_read_file(lua: Lua, path: String) -> LuaResult<LuaString> {
-    let bytes = fs::read(&path).await.into_lua_err()?;
+    let bytes = lua
+        .with_cancellation(async move { fs::read(&path).await.into_lua_err() })
+        .await?;
 
     lua.create_string(bytes)
 }
 
-async fn fs_read_dir(_: Lua, path: String) -> LuaResult<Vec<String>> {
+async fn fs_read_dir(lua: Lua, path: String) -> LuaResult<Vec<String>> {
+    lua.with_cancellation(read_dir(path)).await
+}
+
+async fn read_dir(path: String) -> LuaResult<Vec<String>> {
     let mut dir_strings = Vec::new();
     let mut dir = fs::read_dir(&path).await.into_lua_err()?;
     while let Some(dir_entry) = dir.try_next().await.into_lua_err()? {
@@ -135,3 +146,11 @@ async fn fs_move(_: Lua, (from, to, options): (String, String, FsWriteOptions))
 async fn fs_copy(_: Lua, (from, to, options): (String, String, FsWriteOptions)) -> LuaResult<()> {
     copy(from, to, options).await
 }
+
+fn fs_mmap(_: &Lua, (path, options): (String, FsMmapOptions)) -> LuaResult<FsMappedBuffer> {
+    FsMappedBuffer::map_file(&path, options.writable)
+}
+
+fn fs_shared_memory(_: &Lua, (name, size): (String, usize)) -> LuaResult<FsMappedBuffer> {
+    FsMappedBuffer::map_shared(&name, size)
+}
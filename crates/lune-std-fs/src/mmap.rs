@@ -0,0 +1,283 @@
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use bstr::BString;
+use memmap2::{Mmap, MmapMut};
+use mlua::{Buffer as LuaBuffer, prelude::*};
+
+/// The memory backing a mapped buffer, which is unmapped once closed
+enum Mapping {
+    ReadOnly(Mmap),
+    Writable(MmapMut),
+}
+
+impl Mapping {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            Self::ReadOnly(map) => map,
+            Self::Writable(map) => map,
+        }
+    }
+}
+
+/// Directory that the files backing named shared memory are created in
+const SHARED_MEMORY_DIR: &str = "lune-shared-memory";
+
+/**
+    A buffer-like object backed by a memory map, created using either
+    `fs.mmap` for files, or `fs.sharedMemory` for named shared memory.
+
+    Clones share the same memory, so a mapped buffer may be handed to other
+    parts of a program to read and write the same bytes without copying them.
+*/
+#[derive(Clone)]
+pub(crate) struct FsMappedBuffer {
+    map: Arc<RwLock<Option<Mapping>>>,
+    path: PathBuf,
+    len: usize,
+    writable: bool,
+}
+
+impl FsMappedBuffer {
+    /**
+        Maps the file at the given path into memory.
+
+        Writable maps write changes back to the file, while read-only maps
+        may see changes made to the file by other processes while mapped.
+
+        Note that if the file is truncated while mapped, accessing bytes past its
+        new end terminates the whole process on Unix, with a `SIGBUS` signal.
+    */
+    pub fn map_file(path: impl AsRef<Path>, writable: bool) -> LuaResult<Self> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(writable)
+            .open(path)
+            .into_lua_err()?;
+        // SAFETY: Bytes are only ever copied in and out of the map, and never borrowed
+        // as Rust values, so other processes writing to the file while it is mapped
+        // can only change the bytes that are read. This can NOT guard against the file
+        // being truncated while mapped, which makes accessing the bytes past its new
+        // end raise `SIGBUS` on Unix - this is documented for `fs.mmap` instead
+        let map = unsafe {
+            if writable {
+                Mapping::Writable(MmapMut::map_mut(&file).into_lua_err()?)
+            } else {
+                Mapping::ReadOnly(Mmap::map(&file).into_lua_err()?)
+            }
+        };
+        Ok(Self::new(map, path.to_path_buf(), writable))
+    }
+
+    /**
+        Maps the shared memory with the given name, creating it with the given size,
+        zeroed, if it does not exist yet. Shared memory is always writable.
+
+        Shared memory is backed by a file in the temporary directory, so that every
+        runtime and process mapping the same name reads and writes the same bytes.
+
+        # Errors
+
+        - If the name contains anything but ASCII letters, digits, `-`, `_` and `.`
+        - If shared memory with the name already exists, but with a different size
+        - If the backing file could not be created or mapped
+    */
+    pub fn map_shared(name: &str, len: usize) -> LuaResult<Self> {
+        let valid_name = !name.is_empty()
+            && !name.starts_with('.')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid_name {
+            return Err(LuaError::runtime(format!(
+                "Invalid shared memory name '{name}' - names may only contain \
+                ASCII letters, digits, '-', '_' and '.', and may not start with '.'"
+            )));
+        }
+
+        let dir = shared_memory_dir();
+        fs::create_dir_all(&dir).into_lua_err()?;
+        let path = dir.join(name);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .into_lua_err()?;
+
+        // NOTE: Newly created files are empty, and are only ever grown here,
+        // so an empty file means that the shared memory was just created
+        let existing = usize::try_from(file.metadata().into_lua_err()?.len()).into_lua_err()?;
+        if existing == 0 {
+            file.set_len(len as u64).into_lua_err()?;
+        } else if existing != len {
+            return Err(LuaError::runtime(format!(
+                "Shared memory '{name}' already exists with a size of {existing} bytes, \
+                which is not the requested size of {len} bytes"
+            )));
+        }
+
+        // SAFETY: See `map_file`, the same applies to the backing file
+        let map = unsafe { MmapMut::map_mut(&file).into_lua_err()? };
+        Ok(Self::new(Mapping::Writable(map), path, true))
+    }
+
+    fn new(map: Mapping, path: PathBuf, writable: bool) -> Self {
+        Self {
+            len: map.bytes().len(),
+            map: Arc::new(RwLock::new(Some(map))),
+            path,
+            writable,
+        }
+    }
+
+    fn range(&self, offset: usize, count: usize) -> LuaResult<Range<usize>> {
+        match offset.checked_add(count) {
+            Some(end) if end <= self.len => Ok(offset..end),
+            _ => Err(LuaError::runtime(format!(
+                "Range {offset}..{} is out of bounds for mapped buffer of size {}",
+                offset.saturating_add(count),
+                self.len
+            ))),
+        }
+    }
+
+    fn read<T>(&self, offset: usize, count: usize, f: impl FnOnce(&[u8]) -> T) -> LuaResult<T> {
+        let range = self.range(offset, count)?;
+        let map = self.map.read().expect("mapped buffer poisoned");
+        let Some(map) = map.as_ref() else {
+            return Err(LuaError::runtime("Mapped buffer is closed"));
+        };
+        Ok(f(&map.bytes()[range]))
+    }
+
+    fn write(&self, offset: usize, count: usize, f: impl FnOnce(&mut [u8])) -> LuaResult<()> {
+        let range = self.range(offset, count)?;
+        let mut map = self.map.write().expect("mapped buffer poisoned");
+        match map.as_mut() {
+            None => Err(LuaError::runtime("Mapped buffer is closed")),
+            Some(Mapping::ReadOnly(_)) => Err(LuaError::runtime(
+                "Mapped buffer is read-only - use the writable option to write to it",
+            )),
+            Some(Mapping::Writable(map)) => {
+                f(&mut map[range]);
+                Ok(())
+            }
+        }
+    }
+
+    fn flush(&self) -> LuaResult<()> {
+        let map = self.map.read().expect("mapped buffer poisoned");
+        match map.as_ref() {
+            None => Err(LuaError::runtime("Mapped buffer is closed")),
+            Some(Mapping::ReadOnly(_)) => Ok(()),
+            Some(Mapping::Writable(map)) => map.flush().into_lua_err(),
+        }
+    }
+
+    /// Unmaps the memory, after writing any changes back to the mapped file
+    fn close(&self) -> LuaResult<()> {
+        let map = self.map.write().expect("mapped buffer poisoned").take();
+        match map {
+            Some(Mapping::Writable(map)) => map.flush().into_lua_err(),
+            _ => Ok(()),
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.map.read().expect("mapped buffer poisoned").is_none()
+    }
+}
+
+/// Prefers memory-backed `/dev/shm` on Linux, so that shared memory is never written to disk
+fn shared_memory_dir() -> PathBuf {
+    let shm = Path::new("/dev/shm");
+    let root = if cfg!(target_os = "linux") && shm.is_dir() {
+        shm.to_path_buf()
+    } else {
+        env::temp_dir()
+    };
+    root.join(SHARED_MEMORY_DIR)
+}
+
+macro_rules! add_number_methods {
+    ($methods:ident, $($ty:ty => $read:literal, $write:literal;)*) => {$(
+        $methods.add_method($read, |_, this, offset: usize| {
+            this.read(offset, size_of::<$ty>(), |bytes| {
+                <$ty>::from_le_bytes(bytes.try_into().expect("range has the size of the type"))
+            })
+        });
+        $methods.add_method($write, |_, this, (offset, value): (usize, $ty)| {
+            let bytes = value.to_le_bytes();
+            this.write(offset, bytes.len(), |dest| dest.copy_from_slice(&bytes))
+        });
+    )*};
+}
+
+impl LuaUserData for FsMappedBuffer {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("len", |_, this| Ok(this.len));
+        fields.add_field_method_get("path", |_, this| {
+            Ok(this.path.to_string_lossy().into_owned())
+        });
+        fields.add_field_method_get("writable", |_, this| Ok(this.writable));
+        fields.add_field_method_get("closed", |_, this| Ok(this.is_closed()));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        add_number_methods!(methods,
+            i8 => "readi8", "writei8";
+            u8 => "readu8", "writeu8";
+            i16 => "readi16", "writei16";
+            u16 => "readu16", "writeu16";
+            i32 => "readi32", "writei32";
+            u32 => "readu32", "writeu32";
+            f32 => "readf32", "writef32";
+            f64 => "readf64", "writef64";
+        );
+
+        methods.add_method(
+            "readstring",
+            |lua, this, (offset, count): (usize, usize)| {
+                this.read(offset, count, |bytes| lua.create_string(bytes))?
+            },
+        );
+        methods.add_method(
+            "writestring",
+            |_, this, (offset, value, count): (usize, BString, Option<usize>)| {
+                let count = count.unwrap_or(value.len()).min(value.len());
+                this.write(offset, count, |dest| dest.copy_from_slice(&value[..count]))
+            },
+        );
+        methods.add_method(
+            "readbuffer",
+            |lua, this, (offset, count): (usize, usize)| {
+                this.read(offset, count, |bytes| lua.create_buffer(bytes))?
+            },
+        );
+        methods.add_method(
+            "writebuffer",
+            |_, this, (offset, value): (usize, LuaBuffer)| {
+                let bytes = value.to_vec();
+                this.write(offset, bytes.len(), |dest| dest.copy_from_slice(&bytes))
+            },
+        );
+        methods.add_method(
+            "fill",
+            |_, this, (offset, value, count): (usize, u8, Option<usize>)| {
+                let count = count.unwrap_or(this.len.saturating_sub(offset));
+                this.write(offset, count, |dest| dest.fill(value))
+            },
+        );
+        methods.add_method("flush", |_, this, (): ()| this.flush());
+        methods.add_method("close", |_, this, (): ()| this.close());
+        methods.add_meta_method(LuaMetaMethod::Len, |_, this, (): ()| Ok(this.len));
+    }
+}
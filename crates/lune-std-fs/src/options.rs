@@ -29,3 +29,32 @@ impl FromLua for FsWriteOptions {
         })
     }
 }
+
+#[derive(Debug, Clone, Copy)]
+pub struct FsMmapOptions {
+    pub(crate) writable: bool,
+}
+
+impl FromLua for FsMmapOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        Ok(match value {
+            LuaValue::Nil => Self { writable: false },
+            LuaValue::Table(t) => {
+                let writable: Option<bool> = t.get("writable")?;
+                Self {
+                    writable: writable.unwrap_or(false),
+                }
+            }
+            _ => {
+                return Err(LuaError::FromLuaConversionError {
+                    from: value.type_name(),
+                    to: "FsMmapOptions".to_string(),
+                    message: Some(format!(
+                        "Invalid mmap options - expected table, got {}",
+                        value.type_name()
+                    )),
+                });
+            }
+        })
+    }
+}
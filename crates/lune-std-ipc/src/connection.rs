@@ -0,0 +1,284 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+};
+
+use async_channel::{Receiver, Sender};
+use async_lock::Mutex as AsyncMutex;
+use futures_lite::AsyncWriteExt;
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, LuaSpawnExt};
+
+use lune_std_serde::{EncodeDecodeFormat, decode, encode};
+
+use crate::endpoint::{IpcReader, IpcWriter};
+use crate::frame::{Frame, FrameKind};
+
+const UNHANDLED_REQUEST_MESSAGE: &str = "requests are not handled by this process";
+
+/// Encodes a value given by a script into the payload of a frame
+fn encode_payload(lua: &Lua, value: LuaValue) -> LuaResult<Vec<u8>> {
+    let encoded = encode(value, lua, EncodeDecodeFormat::Json.into())?;
+    Ok(encoded.as_bytes().to_vec())
+}
+
+/// Decodes the payload of a frame into a value to give to a script
+fn decode_payload(lua: &Lua, payload: &[u8]) -> LuaResult<LuaValue> {
+    decode(payload, lua, EncodeDecodeFormat::Json.into())
+}
+
+/// State of a connection that is shared with the task reading from it
+struct ConnectionInner {
+    /// The writing half of the connection, which is taken once closed
+    writer: AsyncMutex<Option<IpcWriter>>,
+    /// Requests that are waiting for a response: id -> sender of the response
+    pending: Mutex<HashMap<u64, Sender<Frame>>>,
+    next_id: AtomicU64,
+    closed: AtomicBool,
+}
+
+impl ConnectionInner {
+    async fn write(&self, frame: &Frame) -> LuaResult<()> {
+        let mut writer = self.writer.lock().await;
+        let Some(writer) = writer.as_mut() else {
+            return Err(LuaError::runtime("Connection is closed"));
+        };
+        frame.write(writer).await.into_lua_err()
+    }
+
+    /// Closes the connection, which also fails any requests that are waiting for a response
+    async fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        let writer = self.writer.lock().await.take();
+        if let Some(mut writer) = writer {
+            // NOTE: The other process may already have closed the
+            // connection, in which case there is nothing left to close
+            let _ = writer.close().await;
+        }
+        self.pending
+            .lock()
+            .expect("pending requests poisoned")
+            .clear();
+    }
+}
+
+/**
+    A connection between two processes, created using either `ipc.connect`
+    or by a client connecting to an endpoint served using `ipc.serve`.
+
+    Frames are read on a separate task, which gives responses to the requests
+    waiting for them, and passes messages and requests on using channels.
+*/
+#[derive(Clone)]
+pub(crate) struct IpcConnection {
+    inner: Arc<ConnectionInner>,
+    messages: Receiver<Frame>,
+}
+
+impl IpcConnection {
+    /**
+        Creates a connection from the given halves, and starts reading from it.
+
+        Requests from the other process are handled by the given function, if any,
+        with the decoded request and the connection, and its first return value is
+        sent back as the response. Without a function, requests are responded to
+        with an error.
+    */
+    pub fn new(
+        lua: &Lua,
+        mut reader: IpcReader,
+        writer: IpcWriter,
+        handler: Option<LuaFunction>,
+    ) -> Self {
+        let inner = Arc::new(ConnectionInner {
+            writer: AsyncMutex::new(Some(writer)),
+            pending: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            closed: AtomicBool::new(false),
+        });
+        let (messages_tx, messages_rx) = async_channel::unbounded();
+        let (requests_tx, requests_rx) = async_channel::unbounded();
+        let requests_tx = handler.is_some().then_some(requests_tx);
+
+        // NOTE: Frames are read on the main executor, which does not keep
+        // the runtime running, so that open connections never keep it running
+        lua.spawn({
+            let inner = Arc::clone(&inner);
+            async move {
+                while let Ok(Some(frame)) = Frame::read(&mut reader).await {
+                    match frame.kind {
+                        FrameKind::Message => {
+                            let _ = messages_tx.send(frame).await;
+                        }
+                        FrameKind::Request => {
+                            if let Some(requests_tx) = &requests_tx {
+                                let _ = requests_tx.send(frame).await;
+                            } else {
+                                let error = Frame::new(
+                                    FrameKind::Error,
+                                    frame.id,
+                                    UNHANDLED_REQUEST_MESSAGE,
+                                );
+                                let _ = inner.write(&error).await;
+                            }
+                        }
+                        FrameKind::Response | FrameKind::Error => {
+                            let sender = inner
+                                .pending
+                                .lock()
+                                .expect("pending requests poisoned")
+                                .remove(&frame.id);
+                            if let Some(sender) = sender {
+                                let _ = sender.try_send(frame);
+                            }
+                        }
+                    }
+                }
+                inner.close().await;
+            }
+        })
+        .detach();
+
+        let this = Self {
+            inner,
+            messages: messages_rx,
+        };
+
+        if let Some(handler) = handler {
+            let lua_inner = lua.clone();
+            let connection = this.clone();
+            lua.spawn_local(async move {
+                while let Ok(request) = requests_rx.recv().await {
+                    let lua = lua_inner.clone();
+                    let connection = connection.clone();
+                    let handler = handler.clone();
+                    lua_inner.spawn_local(async move {
+                        connection.respond(lua, handler, request).await;
+                    });
+                }
+            });
+        }
+
+        this
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner.closed.load(Ordering::SeqCst)
+    }
+
+    pub async fn close(&self) {
+        self.inner.close().await;
+    }
+
+    async fn send(&self, lua: &Lua, message: LuaValue) -> LuaResult<()> {
+        let payload = encode_payload(lua, message)?;
+        self.inner
+            .write(&Frame::new(FrameKind::Message, 0, payload))
+            .await
+    }
+
+    async fn request(&self, lua: &Lua, request: LuaValue) -> LuaResult<LuaValue> {
+        let payload = encode_payload(lua, request)?;
+        let id = self.inner.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = async_channel::bounded(1);
+        self.inner
+            .pending
+            .lock()
+            .expect("pending requests poisoned")
+            .insert(id, tx);
+
+        if let Err(e) = self
+            .inner
+            .write(&Frame::new(FrameKind::Request, id, payload))
+            .await
+        {
+            self.inner
+                .pending
+                .lock()
+                .expect("pending requests poisoned")
+                .remove(&id);
+            return Err(e);
+        }
+
+        let Ok(response) = rx.recv().await else {
+            return Err(LuaError::runtime(
+                "Connection was closed before the request got a response",
+            ));
+        };
+        match response.kind {
+            FrameKind::Error => Err(LuaError::runtime(format!(
+                "Request failed - {}",
+                String::from_utf8_lossy(&response.payload)
+            ))),
+            _ => decode_payload(lua, &response.payload),
+        }
+    }
+
+    async fn receive(&self, lua: &Lua) -> LuaResult<LuaValue> {
+        match self.messages.recv().await {
+            Ok(message) => decode_payload(lua, &message.payload),
+            Err(_) => Ok(LuaValue::Nil),
+        }
+    }
+
+    /// Handles a request from the other process, and sends back the response
+    async fn respond(&self, lua: Lua, handler: LuaFunction, request: Frame) {
+        let response = match self.call_handler(&lua, handler, &request.payload).await {
+            Ok(payload) => Frame::new(FrameKind::Response, request.id, payload),
+            Err(e) => Frame::new(FrameKind::Error, request.id, e.to_string()),
+        };
+        // NOTE: Responses can only fail to send once the connection
+        // is closed, in which case nobody is waiting for them anymore
+        let _ = self.inner.write(&response).await;
+    }
+
+    async fn call_handler(
+        &self,
+        lua: &Lua,
+        handler: LuaFunction,
+        payload: &[u8],
+    ) -> LuaResult<Vec<u8>> {
+        let request = decode_payload(lua, payload)?;
+        let thread_id = lua.push_thread_back(handler, (request, self.clone()))?;
+        lua.track_thread(thread_id);
+        lua.wait_for_thread(thread_id).await;
+
+        let values = lua
+            .get_thread_result(thread_id)
+            .expect("Missing handler thread result")?;
+        let response = values.into_iter().next().unwrap_or(LuaValue::Nil);
+        encode_payload(lua, response)
+    }
+}
+
+impl LuaUserData for IpcConnection {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("closed", |_, this| Ok(this.is_closed()));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method("send", |lua, this, message: LuaValue| {
+            let this = this.clone();
+            async move { this.send(&lua, message).await }
+        });
+        methods.add_async_method("request", |lua, this, request: LuaValue| {
+            let this = this.clone();
+            async move { this.request(&lua, request).await }
+        });
+        methods.add_async_method("receive", |lua, this, (): ()| {
+            let this = this.clone();
+            async move { this.receive(&lua).await }
+        });
+        methods.add_async_method("close", |_, this, (): ()| {
+            let this = this.clone();
+            async move {
+                this.close().await;
+                Ok(())
+            }
+        });
+    }
+}
@@ -0,0 +1,66 @@
+/*!
+    Endpoints that processes serve and connect to, by name.
+
+    On Unix, endpoints are Unix domain sockets, which are in the temporary
+    directory for plain names, or at the given path for names that are paths.
+
+    On Windows, endpoints are named pipes, such as `\\.\pipe\lune-ipc-<name>`.
+*/
+
+use std::io;
+
+use futures_lite::{AsyncRead, AsyncWrite};
+
+#[cfg(unix)]
+mod unix;
+#[cfg(windows)]
+mod windows;
+
+#[cfg(unix)]
+pub(crate) use self::unix::{IpcListener, connect};
+#[cfg(windows)]
+pub(crate) use self::windows::{IpcListener, connect};
+
+/// The reading half of a connection to an endpoint
+pub(crate) type IpcReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// The writing half of a connection to an endpoint, which closes the connection once closed
+pub(crate) type IpcWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// Checks that the given name of an endpoint is valid
+pub(crate) fn check_name(name: &str) -> io::Result<()> {
+    if name.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "endpoint name must not be empty",
+        ));
+    }
+    if name.contains('\0') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "endpoint name must not contain null characters",
+        ));
+    }
+    Ok(())
+}
+
+/// Endpoints are not supported on other platforms, and can never be served
+#[cfg(not(any(unix, windows)))]
+pub(crate) struct IpcListener;
+
+#[cfg(not(any(unix, windows)))]
+impl IpcListener {
+    pub async fn bind(_: &str) -> io::Result<Self> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    pub async fn accept(&mut self) -> io::Result<(IpcReader, IpcWriter)> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// Endpoints are not supported on other platforms, and can never be connected to
+#[cfg(not(any(unix, windows)))]
+pub(crate) async fn connect(_: &str) -> io::Result<(IpcReader, IpcWriter)> {
+    Err(io::ErrorKind::Unsupported.into())
+}
@@ -0,0 +1,91 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use async_net::unix::{UnixListener, UnixStream};
+
+use super::{IpcReader, IpcWriter, check_name};
+
+/// Gets the path of the socket for the given endpoint name
+fn socket_path(name: &str) -> io::Result<PathBuf> {
+    check_name(name)?;
+    Ok(if name.contains('/') {
+        PathBuf::from(name)
+    } else {
+        env::temp_dir().join(format!("lune-ipc-{name}.sock"))
+    })
+}
+
+fn split(stream: UnixStream) -> (IpcReader, IpcWriter) {
+    (Box::new(stream.clone()), Box::new(stream))
+}
+
+/**
+    A socket that accepts connections to an endpoint, which
+    removes its socket file again once it is dropped.
+*/
+pub(crate) struct IpcListener {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl IpcListener {
+    /**
+        Starts serving the endpoint with the given name.
+
+        Socket files left behind by processes that did not exit cleanly are
+        replaced, but endpoints that are still being served are never taken over.
+    */
+    pub async fn bind(name: &str) -> io::Result<Self> {
+        let path = socket_path(name)?;
+        let listener = match UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+                if UnixStream::connect(&path).await.is_ok() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        format!("another process is already serving '{name}'"),
+                    ));
+                }
+                fs::remove_file(&path)?;
+                UnixListener::bind(&path)?
+            }
+            Err(e) => return Err(e),
+        };
+        Ok(Self { listener, path })
+    }
+
+    pub async fn accept(&mut self) -> io::Result<(IpcReader, IpcWriter)> {
+        let (stream, _) = self.listener.accept().await?;
+        Ok(split(stream))
+    }
+}
+
+impl Drop for IpcListener {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Connects to the endpoint with the given name
+pub(crate) async fn connect(name: &str) -> io::Result<(IpcReader, IpcWriter)> {
+    let path = socket_path(name)?;
+    let stream = UnixStream::connect(&path)
+        .await
+        .map_err(|e| not_served(e, name, &path))?;
+    Ok(split(stream))
+}
+
+fn not_served(e: io::Error, name: &str, path: &Path) -> io::Error {
+    match e.kind() {
+        io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused => io::Error::new(
+            e.kind(),
+            format!(
+                "no process is serving '{name}' (no socket at '{}')",
+                path.display()
+            ),
+        ),
+        _ => e,
+    }
+}
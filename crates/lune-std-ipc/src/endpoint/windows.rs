@@ -0,0 +1,309 @@
+use std::{
+    io::{self, Read, Write},
+    mem,
+    os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle},
+    pin::Pin,
+    ptr,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use blocking::{Unblock, unblock};
+use futures_lite::AsyncWrite;
+
+use windows_sys::Win32::Foundation::{
+    ERROR_ACCESS_DENIED, ERROR_BROKEN_PIPE, ERROR_FILE_NOT_FOUND, ERROR_IO_PENDING,
+    ERROR_OPERATION_ABORTED, ERROR_PIPE_BUSY, ERROR_PIPE_CONNECTED, GENERIC_READ, GENERIC_WRITE,
+    HANDLE, INVALID_HANDLE_VALUE,
+};
+use windows_sys::Win32::Storage::FileSystem::{
+    CreateFileW, FILE_FLAG_FIRST_PIPE_INSTANCE, FILE_FLAG_OVERLAPPED, OPEN_EXISTING,
+    PIPE_ACCESS_DUPLEX, ReadFile, WriteFile,
+};
+use windows_sys::Win32::System::IO::{CancelIoEx, GetOverlappedResult, OVERLAPPED};
+use windows_sys::Win32::System::Pipes::{
+    ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS,
+    PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT, WaitNamedPipeW,
+};
+use windows_sys::Win32::System::Threading::CreateEventW;
+
+use super::{IpcReader, IpcWriter, check_name};
+
+const PIPE_BUFFER_SIZE: u32 = 64 * 1024;
+const PIPE_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Gets the null-terminated name of the pipe for the given endpoint name
+fn pipe_name(name: &str) -> io::Result<Vec<u16>> {
+    check_name(name)?;
+    // NOTE: Backslashes are the only characters that pipe names may not contain
+    let name = format!(r"\\.\pipe\lune-ipc-{}", name.replace('\\', "/"));
+    Ok(name.encode_utf16().chain([0]).collect())
+}
+
+/**
+    Runs an overlapped operation on the given handle, and waits for it to complete,
+    returning the number of bytes transferred.
+
+    Pipes are opened for overlapped operations, so that reads and writes
+    from different threads do not wait for each other to complete.
+*/
+fn overlapped(handle: HANDLE, operation: impl FnOnce(*mut OVERLAPPED) -> i32) -> io::Result<u32> {
+    // SAFETY: The event is manual-reset, unnamed, and has no security attributes
+    let event = unsafe { CreateEventW(ptr::null(), 1, 0, ptr::null()) };
+    if event.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: The event handle is valid, and is closed once dropped
+    let event = unsafe { OwnedHandle::from_raw_handle(event) };
+
+    // SAFETY: An overlapped structure of all zeroes is valid
+    let mut overlapped: OVERLAPPED = unsafe { mem::zeroed() };
+    overlapped.hEvent = event.as_raw_handle();
+
+    if operation(&raw mut overlapped) == 0 {
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() != Some(ERROR_IO_PENDING as i32) {
+            return Err(err);
+        }
+    }
+
+    let mut transferred = 0;
+    // SAFETY: The overlapped structure outlives the operation, since this waits for it
+    if unsafe { GetOverlappedResult(handle, &raw const overlapped, &raw mut transferred, 1) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(transferred)
+}
+
+/// Creates a new instance of the pipe with the given name, to accept a connection with
+fn create_pipe(name: &[u16], first: bool) -> io::Result<OwnedHandle> {
+    let mut open_mode = PIPE_ACCESS_DUPLEX | FILE_FLAG_OVERLAPPED;
+    if first {
+        open_mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+    }
+    // SAFETY: The name is null-terminated, and no security attributes are given
+    let handle = unsafe {
+        CreateNamedPipeW(
+            name.as_ptr(),
+            open_mode,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_UNLIMITED_INSTANCES,
+            PIPE_BUFFER_SIZE,
+            PIPE_BUFFER_SIZE,
+            0,
+            ptr::null(),
+        )
+    };
+    if handle == INVALID_HANDLE_VALUE {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: The handle is valid, and is closed once dropped
+    Ok(unsafe { OwnedHandle::from_raw_handle(handle) })
+}
+
+/// Opens the client end of the pipe with the given name, waiting for it while it is busy
+fn open_pipe(name: &[u16]) -> io::Result<OwnedHandle> {
+    loop {
+        // SAFETY: The name is null-terminated, and no security attributes are given
+        let handle = unsafe {
+            CreateFileW(
+                name.as_ptr(),
+                GENERIC_READ | GENERIC_WRITE,
+                0,
+                ptr::null(),
+                OPEN_EXISTING,
+                FILE_FLAG_OVERLAPPED,
+                ptr::null_mut(),
+            )
+        };
+        if handle != INVALID_HANDLE_VALUE {
+            // SAFETY: The handle is valid, and is closed once dropped
+            return Ok(unsafe { OwnedHandle::from_raw_handle(handle) });
+        }
+
+        // All instances of the pipe are connected, so wait until the server creates another
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() != Some(ERROR_PIPE_BUSY as i32) {
+            return Err(err);
+        }
+        let timeout = u32::try_from(PIPE_BUSY_TIMEOUT.as_millis()).unwrap_or(u32::MAX);
+        // SAFETY: The name is null-terminated
+        if unsafe { WaitNamedPipeW(name.as_ptr(), timeout) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+}
+
+/// One end of a connected pipe, shared by its reading and writing halves
+#[derive(Clone)]
+struct Pipe(Arc<OwnedHandle>);
+
+impl Pipe {
+    fn handle(&self) -> HANDLE {
+        self.0.as_raw_handle()
+    }
+}
+
+impl Read for Pipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let handle = self.handle();
+        let len = u32::try_from(buf.len()).unwrap_or(u32::MAX);
+        // SAFETY: The buffer is valid for writes of the given length
+        let result = overlapped(handle, |overlapped| unsafe {
+            ReadFile(handle, buf.as_mut_ptr(), len, ptr::null_mut(), overlapped)
+        });
+        match result {
+            Ok(read) => Ok(read as usize),
+            // NOTE: Pipes break once the other end is closed, and reads are
+            // aborted once this end is closed, both of which end the stream
+            Err(e)
+                if e.raw_os_error() == Some(ERROR_BROKEN_PIPE as i32)
+                    || e.raw_os_error() == Some(ERROR_OPERATION_ABORTED as i32) =>
+            {
+                Ok(0)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Write for Pipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let handle = self.handle();
+        let len = u32::try_from(buf.len()).unwrap_or(u32::MAX);
+        // SAFETY: The buffer is valid for reads of the given length
+        let written = overlapped(handle, |overlapped| unsafe {
+            WriteFile(handle, buf.as_ptr(), len, ptr::null_mut(), overlapped)
+        })?;
+        Ok(written as usize)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/**
+    The writing half of a pipe, which also aborts any pending read once
+    closed, so that the pipe is closed once both halves are dropped.
+*/
+struct PipeWriter {
+    pipe: Pipe,
+    inner: Unblock<Pipe>,
+}
+
+impl AsyncWrite for PipeWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match Pin::new(&mut self.inner).poll_close(cx) {
+            Poll::Ready(result) => {
+                // SAFETY: The handle is valid, and cancelling has no other effects
+                unsafe { CancelIoEx(self.pipe.handle(), ptr::null()) };
+                Poll::Ready(result)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+fn split(handle: OwnedHandle) -> (IpcReader, IpcWriter) {
+    let pipe = Pipe(Arc::new(handle));
+    let writer = PipeWriter {
+        pipe: pipe.clone(),
+        inner: Unblock::new(pipe.clone()),
+    };
+    (Box::new(Unblock::new(pipe)), Box::new(writer))
+}
+
+/**
+    A named pipe that accepts connections to an endpoint, which always
+    has an instance of the pipe waiting for the next connection.
+*/
+pub(crate) struct IpcListener {
+    name: Vec<u16>,
+    pending: Arc<OwnedHandle>,
+}
+
+impl IpcListener {
+    /**
+        Starts serving the endpoint with the given name.
+
+        Endpoints that are already being served are never taken over.
+    */
+    pub async fn bind(name: &str) -> io::Result<Self> {
+        let pipe = pipe_name(name)?;
+        let pending = create_pipe(&pipe, true).map_err(|e| {
+            if e.raw_os_error() == Some(ERROR_ACCESS_DENIED as i32) {
+                io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("another process is already serving '{name}'"),
+                )
+            } else {
+                e
+            }
+        })?;
+        Ok(Self {
+            name: pipe,
+            pending: Arc::new(pending),
+        })
+    }
+
+    pub async fn accept(&mut self) -> io::Result<(IpcReader, IpcWriter)> {
+        let pending = Arc::clone(&self.pending);
+        unblock(move || {
+            let handle = pending.as_raw_handle();
+            // SAFETY: The handle is a valid pipe instance that is not yet connected
+            let result = overlapped(handle, |overlapped| unsafe {
+                ConnectNamedPipe(handle, overlapped)
+            });
+            match result {
+                Err(e) if e.raw_os_error() != Some(ERROR_PIPE_CONNECTED as i32) => Err(e),
+                _ => Ok(()),
+            }
+        })
+        .await?;
+
+        let next = Arc::new(create_pipe(&self.name, false)?);
+        let connected = mem::replace(&mut self.pending, next);
+        let connected = Arc::try_unwrap(connected)
+            .map_err(|_| io::Error::other("pipe instance is still in use"))?;
+        Ok(split(connected))
+    }
+}
+
+impl Drop for IpcListener {
+    fn drop(&mut self) {
+        // NOTE: Connections may still be waited for on another thread, which
+        // would otherwise keep the pipe instance open until the next connection
+        // SAFETY: The handle is valid, and cancelling has no other effects
+        unsafe { CancelIoEx(self.pending.as_raw_handle(), ptr::null()) };
+    }
+}
+
+/// Connects to the endpoint with the given name
+pub(crate) async fn connect(name: &str) -> io::Result<(IpcReader, IpcWriter)> {
+    let pipe = pipe_name(name)?;
+    let handle = unblock(move || open_pipe(&pipe)).await.map_err(|e| {
+        if e.raw_os_error() == Some(ERROR_FILE_NOT_FOUND as i32) {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no process is serving '{name}'"),
+            )
+        } else {
+            e
+        }
+    })?;
+    Ok(split(handle))
+}
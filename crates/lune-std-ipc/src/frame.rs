@@ -0,0 +1,171 @@
+use std::io;
+
+use futures_lite::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Frames larger than this are rejected, since they can only come from a misbehaving peer
+const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+
+/// Length of the kind and id that every frame starts with
+const HEADER_LEN: usize = 1 + 8;
+
+/**
+    The kind of a frame sent between two connected processes.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FrameKind {
+    /// A message that expects no response
+    Message,
+    /// A request that expects a response with the same id
+    Request,
+    /// A successful response to the request with the same id
+    Response,
+    /// A failed response to the request with the same id, with the error as its payload
+    Error,
+}
+
+impl FrameKind {
+    fn to_byte(self) -> u8 {
+        match self {
+            Self::Message => 0,
+            Self::Request => 1,
+            Self::Response => 2,
+            Self::Error => 3,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        Some(match byte {
+            0 => Self::Message,
+            1 => Self::Request,
+            2 => Self::Response,
+            3 => Self::Error,
+            _ => return None,
+        })
+    }
+}
+
+/**
+    A single frame sent between two connected processes.
+
+    Format: [len: u32][kind: u8][id: u64][payload], where the length covers
+    everything after itself, and the payload is encoded as JSON for messages,
+    requests and responses, or is the message of the error for errors.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Frame {
+    pub kind: FrameKind,
+    /// Correlates requests with their responses, and is zero for messages
+    pub id: u64,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(kind: FrameKind, id: u64, payload: impl Into<Vec<u8>>) -> Self {
+        Self {
+            kind,
+            id,
+            payload: payload.into(),
+        }
+    }
+
+    /**
+        Reads the next frame from the given reader.
+
+        Returns `None` if the reader was closed before the frame started.
+    */
+    pub async fn read(reader: &mut (impl AsyncRead + Unpin)) -> io::Result<Option<Self>> {
+        let mut len = [0; 4];
+        match reader.read_exact(&mut len).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+
+        let len = u32::from_be_bytes(len) as usize;
+        if !(HEADER_LEN..=MAX_FRAME_LEN).contains(&len) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid frame length {len}"),
+            ));
+        }
+
+        let mut bytes = vec![0; len];
+        reader.read_exact(&mut bytes).await?;
+        let Some(kind) = FrameKind::from_byte(bytes[0]) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid frame kind {}", bytes[0]),
+            ));
+        };
+        let id = u64::from_be_bytes(bytes[1..HEADER_LEN].try_into().unwrap());
+        bytes.drain(..HEADER_LEN);
+
+        Ok(Some(Self::new(kind, id, bytes)))
+    }
+
+    /**
+        Writes the frame to the given writer, and flushes it.
+    */
+    pub async fn write(&self, writer: &mut (impl AsyncWrite + Unpin)) -> io::Result<()> {
+        let len = HEADER_LEN + self.payload.len();
+        if len > MAX_FRAME_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("message is too large to send ({len} bytes)"),
+            ));
+        }
+
+        let mut bytes = Vec::with_capacity(4 + len);
+        bytes.extend_from_slice(&u32::try_from(len).unwrap().to_be_bytes());
+        bytes.push(self.kind.to_byte());
+        bytes.extend_from_slice(&self.id.to_be_bytes());
+        bytes.extend_from_slice(&self.payload);
+        writer.write_all(&bytes).await?;
+        writer.flush().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future::block_on;
+    use futures_lite::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn frames_roundtrip() {
+        block_on(async {
+            let frames = [
+                Frame::new(FrameKind::Message, 0, b"{\"hello\":true}".to_vec()),
+                Frame::new(FrameKind::Request, 1, b"[1,2,3]".to_vec()),
+                Frame::new(FrameKind::Response, 1, b"null".to_vec()),
+                Frame::new(FrameKind::Error, u64::MAX, Vec::new()),
+            ];
+
+            let mut buf = Cursor::new(Vec::new());
+            for frame in &frames {
+                frame.write(&mut buf).await.unwrap();
+            }
+
+            let mut buf = Cursor::new(buf.into_inner());
+            for frame in frames {
+                assert_eq!(Frame::read(&mut buf).await.unwrap(), Some(frame));
+            }
+            assert_eq!(Frame::read(&mut buf).await.unwrap(), None);
+        });
+    }
+
+    #[test]
+    fn invalid_frames_are_rejected() {
+        block_on(async {
+            let mut too_short = Cursor::new(vec![0, 0, 0, 1, 0]);
+            assert!(Frame::read(&mut too_short).await.is_err());
+
+            let mut unknown_kind = Cursor::new(vec![0, 0, 0, 9, 7, 0, 0, 0, 0, 0, 0, 0, 0]);
+            assert!(Frame::read(&mut unknown_kind).await.is_err());
+
+            let mut truncated = Cursor::new(vec![0, 0, 0, 12, 0, 0, 0]);
+            assert!(Frame::read(&mut truncated).await.is_err());
+        });
+    }
+}
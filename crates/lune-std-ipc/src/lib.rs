@@ -0,0 +1,53 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSchedulerExt;
+
+use lune_utils::TableBuilder;
+
+mod connection;
+mod endpoint;
+mod frame;
+mod server;
+
+use self::connection::IpcConnection;
+use self::server::{IpcServeConfig, IpcServer, serve};
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `ipc` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `ipc` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_async_function("serve", ipc_serve)?
+        .with_async_function("connect", ipc_connect)?
+        .build_readonly()
+}
+
+async fn ipc_serve(lua: Lua, (name, config): (String, IpcServeConfig)) -> LuaResult<IpcServer> {
+    serve(lua, name, config).await
+}
+
+async fn ipc_connect(lua: Lua, name: String) -> LuaResult<IpcConnection> {
+    let (reader, writer) = lua
+        .with_cancellation(async move {
+            endpoint::connect(&name)
+                .await
+                .map_err(|e| LuaError::runtime(format!("Failed to connect to '{name}' - {e}")))
+        })
+        .await?;
+    Ok(IpcConnection::new(&lua, reader, writer, None))
+}
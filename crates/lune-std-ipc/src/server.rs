@@ -0,0 +1,152 @@
+use std::io;
+
+use async_channel::{Receiver, Sender};
+use futures_lite::FutureExt;
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, LuaSpawnExt, ShutdownHook};
+
+use lune_utils::shutdown::PRIORITY_IPC_SERVE;
+
+use crate::connection::IpcConnection;
+use crate::endpoint::{IpcListener, IpcReader, IpcWriter};
+
+/**
+    Configuration for serving an endpoint using `ipc.serve`,
+    given as either a request handler, or a table of handlers.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct IpcServeConfig {
+    pub handle_request: Option<LuaFunction>,
+    pub handle_connection: Option<LuaFunction>,
+}
+
+impl FromLua for IpcServeConfig {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match &value {
+            LuaValue::Function(f) => Ok(Self {
+                handle_request: Some(f.clone()),
+                handle_connection: None,
+            }),
+            LuaValue::Table(t) => {
+                let handle_request: Option<LuaFunction> = t.get("handleRequest")?;
+                let handle_connection: Option<LuaFunction> = t.get("handleConnection")?;
+                if handle_request.is_none() && handle_connection.is_none() {
+                    return Err(LuaError::FromLuaConversionError {
+                        from: value.type_name(),
+                        to: "IpcServeConfig".to_string(),
+                        message: Some(String::from(
+                            "Invalid serve config - expected table with 'handleRequest' or 'handleConnection' function",
+                        )),
+                    });
+                }
+                Ok(Self {
+                    handle_request,
+                    handle_connection,
+                })
+            }
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "IpcServeConfig".to_string(),
+                message: None,
+            }),
+        }
+    }
+}
+
+/**
+    A handle to an endpoint served using `ipc.serve`, which stops serving
+    it and closes all of its connections once stopped.
+*/
+#[derive(Clone)]
+pub(crate) struct IpcServer {
+    name: String,
+    stop_tx: Sender<()>,
+}
+
+impl IpcServer {
+    /// Stops serving the endpoint, returning `false` if it was already stopped
+    fn stop(&self) -> bool {
+        self.stop_tx.close()
+    }
+}
+
+impl LuaUserData for IpcServer {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("name", |_, this| Ok(this.name.clone()));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("stop", |_, this, (): ()| {
+            if this.stop() {
+                Ok(())
+            } else {
+                Err(LuaError::runtime("Server already stopped"))
+            }
+        });
+    }
+}
+
+/**
+    Starts serving the endpoint with the given name, until the returned server is stopped.
+*/
+pub(crate) async fn serve(lua: Lua, name: String, config: IpcServeConfig) -> LuaResult<IpcServer> {
+    let mut listener = IpcListener::bind(&name)
+        .await
+        .map_err(|e| LuaError::runtime(format!("Failed to serve '{name}' - {e}")))?;
+
+    let (stop_tx, stop_rx) = async_channel::bounded::<()>(1);
+    let server = IpcServer { name, stop_tx };
+
+    lua.on_shutdown(PRIORITY_IPC_SERVE, {
+        let server = server.clone();
+        ShutdownHook::native(move |_| {
+            server.stop();
+        })
+    });
+
+    lua.spawn_local({
+        let lua = lua.clone();
+        async move {
+            let mut connections = Vec::<IpcConnection>::new();
+            while let Some(accepted) = accept_or_stop(&mut listener, &stop_rx).await {
+                let Ok((reader, writer)) = accepted else {
+                    // TODO: Propagate error somehow
+                    continue;
+                };
+                let connection =
+                    IpcConnection::new(&lua, reader, writer, config.handle_request.clone());
+
+                connections.retain(|connection| !connection.is_closed());
+                connections.push(connection.clone());
+
+                if let Some(handler) = &config.handle_connection {
+                    // NOTE: This can only fail when out of memory
+                    let _ = lua.push_thread_back(handler.clone(), connection);
+                }
+            }
+
+            // Stop serving before closing connections, so that no
+            // clients connect to an endpoint that is going away
+            drop(listener);
+            for connection in connections {
+                connection.close().await;
+            }
+        }
+    });
+
+    Ok(server)
+}
+
+/// Accepts the next connection, or returns `None` once the server has been stopped
+async fn accept_or_stop(
+    listener: &mut IpcListener,
+    stop_rx: &Receiver<()>,
+) -> Option<io::Result<(IpcReader, IpcWriter)>> {
+    let accept = async { Some(listener.accept().await) };
+    let stop = async {
+        let _ = stop_rx.recv().await;
+        None
+    };
+    stop.or(accept).await
+}
@@ -1,13 +1,17 @@
 #![allow(clippy::cargo_common_metadata)]
 
+use blocking::unblock;
 use mlua::prelude::*;
 
 use lune_utils::{TableBuilder, jit::JitEnablement};
 
 mod options;
+mod spawn;
 
 use self::options::{LuauCompileOptions, LuauLoadOptions};
 
+pub use self::spawn::{LuauRuntimeSpawner, LuauSpawnOptions, LuauSpawnResult, LuauSpawnSource};
+
 const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
 
 /**
@@ -29,6 +33,7 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
     TableBuilder::new(lua)?
         .with_function("compile", compile_source)?
         .with_function("load", load_source)?
+        .with_async_function("spawnRuntime", spawn_runtime)?
         .build_readonly()
 }
 
@@ -93,3 +98,13 @@ fn load_source(
 
     Ok(function)
 }
+
+async fn spawn_runtime(lua: Lua, options: LuauSpawnOptions) -> LuaResult<LuauSpawnResult> {
+    let spawner = lua
+        .app_data_ref::<LuauRuntimeSpawner>()
+        .ok_or(LuaError::runtime(
+            "Spawning runtimes is not supported in the current environment",
+        ))?
+        .clone();
+    Ok(unblock(move || spawner.spawn(options)).await)
+}
@@ -0,0 +1,120 @@
+use std::{path::PathBuf, sync::Arc};
+
+use mlua::prelude::*;
+
+/**
+    The script to run in a spawned runtime.
+*/
+#[derive(Debug, Clone)]
+pub enum LuauSpawnSource {
+    /// Source code or bytecode, run without file-based require semantics
+    Source(Vec<u8>),
+    /// Path to a file, run with the same semantics as `lune run`
+    Path(PathBuf),
+}
+
+/**
+    Options for spawning a script in a new, isolated runtime.
+*/
+#[derive(Debug, Clone)]
+pub struct LuauSpawnOptions {
+    pub source: LuauSpawnSource,
+    pub args: Vec<String>,
+    pub inherit_stdio: bool,
+}
+
+impl FromLua for LuauSpawnOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        let LuaValue::Table(t) = value else {
+            return Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "SpawnRuntimeOptions".to_string(),
+                message: Some(format!(
+                    "Invalid spawn options - expected table, got {}",
+                    value.type_name()
+                )),
+            });
+        };
+
+        let source = match (
+            t.get::<Option<LuaString>>("source")?,
+            t.get::<Option<String>>("path")?,
+        ) {
+            (Some(source), None) => LuauSpawnSource::Source(source.as_bytes().to_vec()),
+            (None, Some(path)) => LuauSpawnSource::Path(PathBuf::from(path)),
+            (Some(_), Some(_)) => {
+                return Err(LuaError::runtime(
+                    "Invalid spawn options - only one of 'source' or 'path' may be given",
+                ));
+            }
+            (None, None) => {
+                return Err(LuaError::runtime(
+                    "Invalid spawn options - one of 'source' or 'path' must be given",
+                ));
+            }
+        };
+
+        Ok(Self {
+            source,
+            args: t.get::<Option<Vec<String>>>("args")?.unwrap_or_default(),
+            inherit_stdio: t.get::<Option<bool>>("inheritStdio")?.unwrap_or(false),
+        })
+    }
+}
+
+/**
+    The result of running a script in a spawned runtime.
+
+    Output is only captured if the runtime did not inherit stdio,
+    and will otherwise be empty.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct LuauSpawnResult {
+    pub code: u8,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl IntoLua for LuauSpawnResult {
+    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        let result = lua.create_table_with_capacity(0, 4)?;
+        result.set("ok", self.code == 0)?;
+        result.set("code", self.code)?;
+        result.set("stdout", lua.create_string(&self.stdout)?)?;
+        result.set("stderr", lua.create_string(&self.stderr)?)?;
+        result.into_lua(lua)
+    }
+}
+
+type SpawnFn = dyn Fn(LuauSpawnOptions) -> LuauSpawnResult + Send + Sync;
+
+/**
+    Spawns scripts in new, isolated runtimes, for `luau.spawnRuntime`.
+
+    Creating a runtime requires the full set of standard libraries, which
+    this library can not depend on, so a spawner must be stored in Lua app
+    data by the runtime that this library is used in.
+
+    The spawner is called on a separate thread, and must
+    run the script to completion before returning.
+*/
+#[derive(Clone)]
+pub struct LuauRuntimeSpawner {
+    inner: Arc<SpawnFn>,
+}
+
+impl LuauRuntimeSpawner {
+    #[must_use]
+    pub fn new<F>(spawn: F) -> Self
+    where
+        F: Fn(LuauSpawnOptions) -> LuauSpawnResult + Send + Sync + 'static,
+    {
+        Self {
+            inner: Arc::new(spawn),
+        }
+    }
+
+    pub(crate) fn spawn(&self, options: LuauSpawnOptions) -> LuauSpawnResult {
+        (self.inner)(options)
+    }
+}
@@ -25,7 +25,8 @@ use crate::{
     # WARNING
 
     This is an API meant only for private consumption by the main `lune`
-    crate - unlike other functions in *this* crate, it is NOT guaranteed
+    crate and other Lune standard library crates - unlike other functions
+    in *this* crate, it is NOT guaranteed
     to follow semver or be otherwise stable outside of the private usage.
 */
 #[doc(hidden)]
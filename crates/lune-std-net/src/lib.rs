@@ -2,6 +2,7 @@
 
 use lune_utils::TableBuilder;
 use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSchedulerExt;
 
 pub(crate) mod body;
 pub(crate) mod client;
@@ -65,7 +66,8 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
 }
 
 async fn net_http_request(lua: Lua, req: Request) -> LuaResult<Response> {
-    self::client::send(req, lua).await
+    lua.with_cancellation(self::client::send(req, lua.clone()))
+        .await
 }
 
 async fn net_http_serve(lua: Lua, (port, config): (u16, ServeConfig)) -> LuaResult<LuaTable> {
@@ -74,12 +76,16 @@ async fn net_http_serve(lua: Lua, (port, config): (u16, ServeConfig)) -> LuaResu
         .into_lua_table(lua)
 }
 
-async fn net_tcp_connect(_: Lua, (host, port, config): (String, u16, TcpConfig)) -> LuaResult<Tcp> {
-    self::client::connect_tcp(host, port, config).await
+async fn net_tcp_connect(
+    lua: Lua,
+    (host, port, config): (String, u16, TcpConfig),
+) -> LuaResult<Tcp> {
+    lua.with_cancellation(self::client::connect_tcp(host, port, config))
+        .await
 }
 
 async fn net_ws_connect(
-    _: Lua,
+    lua: Lua,
     (url, config): (String, Option<LuaTable>),
 ) -> LuaResult<Websocket<WsStream>> {
     let url = url.parse().into_lua_err()?;
@@ -99,7 +105,8 @@ async fn net_ws_connect(
         None
     };
 
-    self::client::connect_ws(url, headers).await
+    lua.with_cancellation(self::client::connect_ws(url, headers))
+        .await
 }
 
 fn net_url_encode(
@@ -29,22 +29,34 @@ impl ServeHandle {
         (this, receiver)
     }
 
+    /**
+        Stops the server from accepting any new connections, and
+        gracefully shuts down any connections that are still open.
+
+        Returns `false` if the server was already stopped.
+    */
+    pub fn stop(&self) -> bool {
+        if self.shutdown.swap(true, Ordering::SeqCst) {
+            false
+        } else {
+            self.sender.try_send(()).ok();
+            self.sender.close();
+            true
+        }
+    }
+
     // TODO: Remove this in the next major release to use colon/self
     // based call syntax and userdata implementation below instead
     pub fn into_lua_table(self, lua: Lua) -> LuaResult<LuaTable> {
-        let shutdown = self.shutdown.clone();
-        let sender = self.sender.clone();
+        let handle = self.clone();
         TableBuilder::new(lua)?
             .with_value("ip", self.addr.ip().to_string())?
             .with_value("port", self.addr.port())?
             .with_function("stop", move |_, ()| {
-                if shutdown.load(Ordering::SeqCst) {
-                    Err(LuaError::runtime("Server already stopped"))
-                } else {
-                    shutdown.store(true, Ordering::SeqCst);
-                    sender.try_send(()).ok();
-                    sender.close();
+                if handle.stop() {
                     Ok(())
+                } else {
+                    Err(LuaError::runtime("Server already stopped"))
                 }
             })?
             .build()
@@ -59,13 +71,10 @@ impl LuaUserData for ServeHandle {
 
     fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
         methods.add_method("stop", |_, this, ()| {
-            if this.shutdown.load(Ordering::SeqCst) {
-                Err(LuaError::runtime("Server already stopped"))
-            } else {
-                this.shutdown.store(true, Ordering::SeqCst);
-                this.sender.try_send(()).ok();
-                this.sender.close();
+            if this.stop() {
                 Ok(())
+            } else {
+                Err(LuaError::runtime("Server already stopped"))
             }
         });
     }
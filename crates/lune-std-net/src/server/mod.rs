@@ -5,7 +5,9 @@ use futures_lite::pin;
 use hyper::server::conn::http1::Builder as Http1Builder;
 
 use mlua::prelude::*;
-use mlua_luau_scheduler::LuaSpawnExt;
+use mlua_luau_scheduler::{LuaSchedulerExt, LuaSpawnExt, ShutdownHook};
+
+use lune_utils::shutdown::PRIORITY_NET_SERVE;
 
 use crate::{
     server::{config::ServeConfig, handle::ServeHandle, service::Service},
@@ -36,6 +38,15 @@ pub async fn serve(lua: Lua, port: u16, config: ServeConfig) -> LuaResult<ServeH
     let listener = TcpListener::bind(address).await?;
     let (handle, shutdown_rx) = ServeHandle::new(address);
 
+    // Stop accepting connections once the runtime shuts down, this
+    // also makes sure that any open connections are closed gracefully
+    lua.on_shutdown(PRIORITY_NET_SERVE, {
+        let handle = handle.clone();
+        ShutdownHook::native(move |_| {
+            handle.stop();
+        })
+    });
+
     lua.spawn_local({
         let lua = lua.clone();
         async move {
@@ -5,9 +5,9 @@ use async_process::Child as AsyncChild;
 use futures_util::{FutureExt, select};
 
 use mlua::prelude::*;
-use mlua_luau_scheduler::LuaSpawnExt;
+use mlua_luau_scheduler::{LuaSchedulerExt, LuaSpawnExt, ShutdownHook};
 
-use lune_utils::TableBuilder;
+use lune_utils::{TableBuilder, shutdown::PRIORITY_PROCESS_CHILDREN};
 
 use super::{ChildReader, ChildWriter};
 
@@ -32,6 +32,18 @@ impl Child {
         let (status_tx, status_rx) = unbounded();
         lua.spawn(handle_child(child, kill_rx, status_tx)).detach();
 
+        // Kill the child once the runtime shuts down, if it is still running,
+        // and wait for it to be killed so that it does not outlive the runtime
+        lua.on_shutdown(PRIORITY_PROCESS_CHILDREN, {
+            let kill_tx = kill_tx.clone();
+            let status_rx = status_rx.clone();
+            ShutdownHook::future(move |_| async move {
+                if kill_tx.try_send(()).is_ok() {
+                    let _ = status_rx.recv().await;
+                }
+            })
+        });
+
         Self {
             stdin,
             stdout,
@@ -56,8 +68,9 @@ impl LuaUserData for Child {
         });
         methods.add_async_method("status", |lua, this, (): ()| {
             let rx = this.status_rx.clone();
+            let fut = async move { Ok(rx.recv().await.ok().flatten()) };
             async move {
-                let status = rx.recv().await.ok().flatten();
+                let status = lua.with_cancellation(fut).await?;
                 let code = status.and_then(|c| c.code()).unwrap_or(9);
                 TableBuilder::new(lua.clone())?
                     .with_value("ok", code == 0)?
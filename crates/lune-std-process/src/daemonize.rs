@@ -0,0 +1,144 @@
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+};
+
+use mlua::prelude::*;
+
+/**
+    Environment variable set for the daemon started by `process.daemonize`,
+    which tells it that it is already running as the daemon.
+
+    It is never passed on to processes spawned using `process.exec` or
+    `process.create`, so that those may still daemonize themselves.
+*/
+pub(crate) const DAEMON_ENV_VAR: &str = "LUNE_DAEMONIZED";
+
+/**
+    Detaches the current script into a daemon, by spawning the current
+    process again with the same arguments, in the background.
+
+    Everything that the script did before calling `process.daemonize` is done
+    again by the daemon, which is why it should be called as early as possible.
+*/
+pub(crate) const DAEMONIZE_IMPL_LUA: &str = r"
+if not daemonize(...) then
+	exit(0)
+end
+";
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DaemonizeOptions {
+    pub stdout: Option<PathBuf>,
+    pub stderr: Option<PathBuf>,
+    pub pidfile: Option<PathBuf>,
+}
+
+impl FromLua for DaemonizeOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        let value = match value {
+            LuaValue::Nil => return Ok(Self::default()),
+            LuaValue::Table(t) => t,
+            _ => {
+                return Err(LuaError::FromLuaConversionError {
+                    from: value.type_name(),
+                    to: "DaemonizeOptions".to_string(),
+                    message: Some(format!(
+                        "Invalid daemonize options - expected table, got {}",
+                        value.type_name()
+                    )),
+                });
+            }
+        };
+
+        let path = |key: &str| -> LuaResult<Option<PathBuf>> {
+            match value.get::<LuaValue>(key)? {
+                LuaValue::Nil => Ok(None),
+                LuaValue::String(s) => Ok(Some(PathBuf::from(s.to_str()?.to_string()))),
+                value => Err(LuaError::RuntimeError(format!(
+                    "Invalid type for option '{key}' - expected string, got '{}'",
+                    value.type_name()
+                ))),
+            }
+        };
+
+        Ok(Self {
+            stdout: path("stdout")?,
+            stderr: path("stderr")?,
+            pidfile: path("pidfile")?,
+        })
+    }
+}
+
+/**
+    Returns `true` when running as the daemon, once its pidfile has been
+    written, or spawns the daemon and returns `false` otherwise, after
+    which the current process should exit.
+*/
+pub(crate) fn daemonize(_: &Lua, options: DaemonizeOptions) -> LuaResult<bool> {
+    if env::var_os(DAEMON_ENV_VAR).is_some() {
+        if let Some(pidfile) = &options.pidfile {
+            fs::write(pidfile, format!("{}\n", process::id())).map_err(|e| {
+                LuaError::runtime(format!(
+                    "Failed to write pidfile '{}' - {e}",
+                    pidfile.display()
+                ))
+            })?;
+        }
+        return Ok(true);
+    }
+
+    let mut command = Command::new(env::current_exe()?);
+    command
+        .args(env::args_os().skip(1))
+        .env(DAEMON_ENV_VAR, "1")
+        .stdin(Stdio::null())
+        .stdout(open_output(options.stdout.as_deref())?)
+        .stderr(open_output(options.stderr.as_deref())?);
+    detach(&mut command);
+    command.spawn()?;
+
+    Ok(false)
+}
+
+/// Opens the file that an output stream of the daemon is appended to, if any
+fn open_output(path: Option<&Path>) -> LuaResult<Stdio> {
+    let Some(path) = path else {
+        return Ok(Stdio::null());
+    };
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| LuaError::runtime(format!("Failed to open '{}' - {e}", path.display())))?;
+    Ok(Stdio::from(file))
+}
+
+/// Starts the daemon in a new session, without a controlling terminal
+#[cfg(unix)]
+fn detach(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    // SAFETY: setsid is async-signal-safe, and only changes the session of the child
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setsid() == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Starts the daemon in a new process group, without a console
+#[cfg(windows)]
+fn detach(command: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    const DETACHED_PROCESS: u32 = 0x0000_0008;
+    const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+    command.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+}
+
+#[cfg(not(any(unix, windows)))]
+fn detach(_: &mut Command) {}
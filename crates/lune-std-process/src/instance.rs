@@ -0,0 +1,138 @@
+use std::{
+    cell::RefCell,
+    env,
+    fs::{self, File, OpenOptions, TryLockError},
+    path::PathBuf,
+    process,
+    rc::Rc,
+};
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, ShutdownHook};
+
+use lune_utils::{path::append_extension, shutdown::PRIORITY_PROCESS_INSTANCE_LOCKS};
+
+/**
+    A lock acquired using `process.singleInstance`, along with
+    the separate file that the process id of its holder is written to.
+
+    The process id can not be stored in the lock file itself, since
+    locked files can not be read by other processes on Windows.
+*/
+#[derive(Debug)]
+struct InstanceLock {
+    file: File,
+    pid_path: PathBuf,
+}
+
+impl InstanceLock {
+    fn release(self) {
+        let _ = fs::remove_file(&self.pid_path);
+        let _ = self.file.unlock();
+    }
+}
+
+/**
+    The guard returned by `process.singleInstance`.
+
+    Its lock is held until released, or until the runtime shuts down, and
+    never when the guard is garbage collected, since scripts that only check
+    whether they got a guard would otherwise lose their lock at random.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct InstanceGuard(Rc<RefCell<Option<InstanceLock>>>);
+
+impl InstanceGuard {
+    fn release(&self) {
+        if let Some(lock) = self.0.borrow_mut().take() {
+            lock.release();
+        }
+    }
+}
+
+impl LuaUserData for InstanceGuard {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("released", |_, this| Ok(this.0.borrow().is_none()));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("release", |_, this, (): ()| {
+            this.release();
+            Ok(())
+        });
+    }
+}
+
+/**
+    Gets the paths of the lock file and process id file for the given
+    instance name, which are in the temporary directory for plain names,
+    or next to the given path for names that are paths.
+*/
+fn instance_paths(name: &str) -> LuaResult<(PathBuf, PathBuf)> {
+    if name.is_empty() {
+        return Err(LuaError::runtime("Instance name must not be empty"));
+    }
+    let base = if name.contains(['/', '\\']) {
+        PathBuf::from(name)
+    } else {
+        env::temp_dir().join(format!("lune-{name}"))
+    };
+    Ok((
+        append_extension(&base, "lock"),
+        append_extension(&base, "pid"),
+    ))
+}
+
+/**
+    Acquires the lock for the given instance name, returning a guard for it,
+    or the process id of the instance that already holds it, if known.
+*/
+pub(crate) fn single_instance(
+    lua: &Lua,
+    name: String,
+) -> LuaResult<(Option<InstanceGuard>, Option<u32>)> {
+    let (lock_path, pid_path) = instance_paths(&name)?;
+    if let Some(dir) = lock_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&lock_path)
+        .map_err(|e| {
+            LuaError::runtime(format!("Failed to open '{}' - {e}", lock_path.display()))
+        })?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            let pid = fs::read_to_string(&pid_path)
+                .ok()
+                .and_then(|pid| pid.trim().parse().ok());
+            return Ok((None, pid));
+        }
+        Err(TryLockError::Error(e)) => {
+            return Err(LuaError::runtime(format!(
+                "Failed to lock '{}' - {e}",
+                lock_path.display()
+            )));
+        }
+    }
+
+    let lock = InstanceLock { file, pid_path };
+    if let Err(e) = fs::write(&lock.pid_path, format!("{}\n", process::id())) {
+        let message = format!("Failed to write '{}' - {e}", lock.pid_path.display());
+        lock.release();
+        return Err(LuaError::runtime(message));
+    }
+
+    let guard = InstanceGuard(Rc::new(RefCell::new(Some(lock))));
+    lua.on_shutdown(PRIORITY_PROCESS_INSTANCE_LOCKS, {
+        let guard = guard.clone();
+        ShutdownHook::native(move |_| guard.release())
+    });
+
+    Ok((Some(guard), None))
+}
@@ -7,7 +7,7 @@ use std::{
 };
 
 use mlua::prelude::*;
-use mlua_luau_scheduler::Functions;
+use mlua_luau_scheduler::{Functions, LuaSchedulerExt};
 
 use lune_utils::{
     TableBuilder,
@@ -16,9 +16,13 @@ use lune_utils::{
 };
 
 mod create;
+mod daemonize;
 mod exec;
+mod instance;
 mod options;
 
+use self::daemonize::{DAEMONIZE_IMPL_LUA, daemonize};
+use self::instance::single_instance;
 use self::options::ProcessSpawnOptions;
 
 const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
@@ -74,6 +78,18 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
     let fns = Functions::new(lua.clone())?;
     let process_exit = fns.exit;
 
+    // Create our daemonize function, which exits the original process
+    // the same way process.exit does, once the daemon has been spawned
+    let daemonize_env = lua.create_table_from(vec![
+        ("daemonize", lua.create_function(daemonize)?),
+        ("exit", process_exit.clone()),
+    ])?;
+    let process_daemonize = lua
+        .load(DAEMONIZE_IMPL_LUA)
+        .set_name("=process.daemonize")
+        .set_environment(daemonize_env)
+        .into_function()?;
+
     // Create the full process table
     TableBuilder::new(lua)?
         .with_value("os", os)?
@@ -83,8 +99,10 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
         .with_value("cwd", cwd_str)?
         .with_value("env", process_env)?
         .with_value("exit", process_exit)?
+        .with_value("daemonize", process_daemonize)?
         .with_async_function("exec", process_exec)?
         .with_function("create", process_create)?
+        .with_function("singleInstance", single_instance)?
         .build_readonly()
 }
 
@@ -102,14 +120,18 @@ async fn process_exec(
         Stdio::null()
     };
 
+    // NOTE: The child process is owned by the exec future, so if the calling
+    // thread gets cancelled, dropping that future will also kill the process
     let child = options
         .into_command(program, args)
         .stdin(stdin_stdio)
         .stdout(stdout.as_stdio())
         .stderr(stderr.as_stdio())
+        .kill_on_drop(true)
         .spawn()?;
 
-    exec::exec(lua, child, stdin, stdout, stderr).await
+    lua.with_cancellation(exec::exec(lua.clone(), child, stdin, stdout, stderr))
+        .await
 }
 
 fn process_create(
@@ -11,6 +11,8 @@ use mlua::prelude::*;
 use async_process::Command;
 use directories::UserDirs;
 
+use crate::daemonize::DAEMON_ENV_VAR;
+
 mod kind;
 mod stdio;
 
@@ -154,6 +156,9 @@ impl ProcessSpawnOptions {
         if let Some(cwd) = self.cwd {
             cmd.current_dir(cwd);
         }
+        // Child processes must be able to daemonize themselves,
+        // even when they are spawned by a daemon
+        cmd.env_remove(DAEMON_ENV_VAR);
         if !self.envs.is_empty() {
             cmd.envs(self.envs);
         }
@@ -0,0 +1,429 @@
+/*!
+    Helpers for uploading and downloading assets using the Open Cloud assets API.
+
+    Uploading an asset is a multi-step process - the file is sent as a multipart
+    request, which starts a long-running operation, and the operation must then
+    be polled until it is done to find out the id of the new asset. Downloading
+    an asset first requests its location, and then downloads it from there.
+
+    All requests are authenticated using an Open Cloud API key, which is either
+    given in the options, or read from the `ROBLOX_API_KEY` environment variable.
+*/
+
+use std::{
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_io::Timer;
+use mlua::prelude::*;
+use serde_json::{Value as JsonValue, json};
+
+const ASSETS_URL: &str = "https://apis.roblox.com/assets/v1";
+const ASSET_DELIVERY_URL: &str = "https://apis.roblox.com/asset-delivery-api/v1";
+
+const API_KEY_ENV_VAR: &str = "ROBLOX_API_KEY";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const POLL_ATTEMPTS: u32 = 60;
+
+/**
+    The user or group that an uploaded asset will belong to.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Creator {
+    User(u64),
+    Group(u64),
+}
+
+impl Creator {
+    fn to_json(self) -> JsonValue {
+        match self {
+            Self::User(id) => json!({ "userId": id.to_string() }),
+            Self::Group(id) => json!({ "groupId": id.to_string() }),
+        }
+    }
+}
+
+impl FromLua for Creator {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        let LuaValue::Table(table) = value else {
+            return Err(LuaError::runtime(format!(
+                "Expected creator to be a table, got {}",
+                value.type_name()
+            )));
+        };
+        match (
+            table.get::<Option<u64>>("userId")?,
+            table.get::<Option<u64>>("groupId")?,
+        ) {
+            (Some(id), None) => Ok(Self::User(id)),
+            (None, Some(id)) => Ok(Self::Group(id)),
+            _ => Err(LuaError::runtime(
+                "Expected creator to have exactly one of 'userId' or 'groupId'",
+            )),
+        }
+    }
+}
+
+/**
+    Options for `roblox.assets.upload`.
+*/
+#[derive(Debug, Clone)]
+pub struct UploadOptions {
+    asset_type: String,
+    name: String,
+    description: String,
+    creator: Creator,
+    api_key: Option<String>,
+}
+
+impl FromLua for UploadOptions {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
+        let LuaValue::Table(table) = value else {
+            return Err(LuaError::runtime(format!(
+                "Expected upload options to be a table, got {}",
+                value.type_name()
+            )));
+        };
+        Ok(Self {
+            asset_type: table.get("type")?,
+            name: table.get("name")?,
+            description: table
+                .get::<Option<String>>("description")?
+                .unwrap_or_default(),
+            creator: Creator::from_lua(table.get("creator")?, lua)?,
+            api_key: table.get("apiKey")?,
+        })
+    }
+}
+
+/**
+    Options for `roblox.assets.download`.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct DownloadOptions {
+    api_key: Option<String>,
+}
+
+impl FromLua for DownloadOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(table) => Ok(Self {
+                api_key: table.get("apiKey")?,
+            }),
+            value => Err(LuaError::runtime(format!(
+                "Expected download options to be a table, got {}",
+                value.type_name()
+            ))),
+        }
+    }
+}
+
+/**
+    Returns the content type of an asset file, based on its extension.
+*/
+fn content_type(path: &Path) -> LuaResult<&'static str> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    Ok(match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "bmp" => "image/bmp",
+        "tga" => "image/tga",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "flac" => "audio/flac",
+        "fbx" => "model/fbx",
+        "glb" => "model/gltf-binary",
+        "gltf" => "model/gltf+json",
+        "rbxm" | "rbxmx" => "model/x-rbxm",
+        "mp4" => "video/mp4",
+        "mov" => "video/mov",
+        _ => {
+            return Err(LuaError::runtime(format!(
+                "Unsupported asset file extension '{extension}' for '{}'",
+                path.display()
+            )));
+        }
+    })
+}
+
+/**
+    Creates the body of a multipart upload request, with the
+    given request metadata, followed by the contents of the file.
+*/
+fn multipart_body(
+    boundary: &str,
+    request: &JsonValue,
+    file_name: &str,
+    content_type: &str,
+    contents: &[u8],
+) -> Vec<u8> {
+    let mut body = Vec::with_capacity(contents.len() + 512);
+    body.extend_from_slice(
+        format!(
+            "--{boundary}\r\n\
+            Content-Disposition: form-data; name=\"request\"\r\n\
+            Content-Type: application/json\r\n\r\n\
+            {request}\r\n\
+            --{boundary}\r\n\
+            Content-Disposition: form-data; name=\"fileContent\"; filename=\"{file_name}\"\r\n\
+            Content-Type: {content_type}\r\n\r\n"
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(contents);
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+    body
+}
+
+fn api_key(given: Option<String>) -> LuaResult<String> {
+    given
+        .or_else(|| std::env::var(API_KEY_ENV_VAR).ok())
+        .filter(|key| !key.is_empty())
+        .ok_or_else(|| {
+            LuaError::runtime(format!(
+                "Missing Open Cloud API key - pass 'apiKey' in the options \
+                or set the {API_KEY_ENV_VAR} environment variable"
+            ))
+        })
+}
+
+/**
+    Sends a request to an Open Cloud endpoint, returning the body of the response.
+*/
+async fn send(
+    url: &str,
+    method: &str,
+    headers: Vec<(&str, String)>,
+    body: Option<Vec<u8>>,
+) -> LuaResult<Vec<u8>> {
+    let url = url.parse().into_lua_err()?;
+    let method = method.parse().into_lua_err()?;
+    let headers = headers
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect();
+
+    let res = lune_std_net::fetch(url, Some(method), Some(headers), body)
+        .await
+        .map_err(LuaError::runtime)?;
+
+    if !res.status_ok() {
+        let body = String::from_utf8_lossy(res.body());
+        return Err(LuaError::runtime(format!(
+            "Open Cloud request was not successful\
+            \nStatus: {} {}\
+            \nBody: {}",
+            res.status_code(),
+            res.status_message(),
+            body.chars().take(256).collect::<String>(),
+        )));
+    }
+
+    Ok(res.body().to_vec())
+}
+
+async fn send_json(
+    url: &str,
+    method: &str,
+    headers: Vec<(&str, String)>,
+    body: Option<Vec<u8>>,
+) -> LuaResult<JsonValue> {
+    let bytes = send(url, method, headers, body).await?;
+    serde_json::from_slice(&bytes).into_lua_err()
+}
+
+/**
+    Parses the id of an asset from the response of a finished upload operation.
+*/
+fn operation_asset_id(operation: &JsonValue) -> LuaResult<Option<u64>> {
+    if let Some(error) = operation.get("error") {
+        return Err(LuaError::runtime(format!(
+            "Asset upload failed: {}",
+            error
+                .get("message")
+                .and_then(JsonValue::as_str)
+                .map_or_else(|| error.to_string(), ToString::to_string)
+        )));
+    }
+    if !operation
+        .get("done")
+        .and_then(JsonValue::as_bool)
+        .unwrap_or(false)
+    {
+        return Ok(None);
+    }
+    let asset_id = operation
+        .pointer("/response/assetId")
+        .and_then(|id| match id {
+            JsonValue::String(s) => s.parse().ok(),
+            JsonValue::Number(n) => n.as_u64(),
+            _ => None,
+        })
+        .ok_or_else(|| LuaError::runtime("Asset upload finished without an asset id"))?;
+    Ok(Some(asset_id))
+}
+
+/**
+    Implementation of `roblox.assets.upload`, which uploads the file at the
+    given path as a new asset, waits until it has been processed, and returns
+    the id of the new asset.
+*/
+pub async fn upload(_: Lua, (path, options): (String, UploadOptions)) -> LuaResult<u64> {
+    let path = Path::new(&path);
+    let content_type = content_type(path)?;
+    let api_key = api_key(options.api_key)?;
+
+    let contents = async_fs::read(path).await.into_lua_err()?;
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("asset");
+
+    let request = json!({
+        "assetType": options.asset_type,
+        "displayName": options.name,
+        "description": options.description,
+        "creationContext": { "creator": options.creator.to_json() },
+    });
+    let boundary = format!(
+        "lune-{:x}",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos()
+    );
+    let body = multipart_body(&boundary, &request, file_name, content_type, &contents);
+
+    let mut operation = send_json(
+        &format!("{ASSETS_URL}/assets"),
+        "POST",
+        vec![
+            ("x-api-key", api_key.clone()),
+            (
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            ),
+        ],
+        Some(body),
+    )
+    .await?;
+
+    let operation_id = operation
+        .get("operationId")
+        .and_then(JsonValue::as_str)
+        .map(ToString::to_string)
+        .or_else(|| {
+            operation
+                .get("path")
+                .and_then(JsonValue::as_str)
+                .and_then(|path| path.strip_prefix("operations/"))
+                .map(ToString::to_string)
+        });
+
+    for _ in 0..POLL_ATTEMPTS {
+        if let Some(asset_id) = operation_asset_id(&operation)? {
+            return Ok(asset_id);
+        }
+        let Some(operation_id) = &operation_id else {
+            return Err(LuaError::runtime(
+                "Asset upload did not return an operation to wait for",
+            ));
+        };
+        Timer::after(POLL_INTERVAL).await;
+        operation = send_json(
+            &format!("{ASSETS_URL}/operations/{operation_id}"),
+            "GET",
+            vec![("x-api-key", api_key.clone())],
+            None,
+        )
+        .await?;
+    }
+
+    Err(LuaError::runtime(format!(
+        "Timed out waiting for asset upload to finish after {} seconds",
+        POLL_INTERVAL.as_secs() * u64::from(POLL_ATTEMPTS)
+    )))
+}
+
+/**
+    Implementation of `roblox.assets.download`, which downloads the
+    asset with the given id and writes it to the given file path.
+*/
+pub async fn download(
+    _: Lua,
+    (asset_id, dest, options): (u64, String, DownloadOptions),
+) -> LuaResult<()> {
+    let api_key = api_key(options.api_key)?;
+
+    let delivery = send_json(
+        &format!("{ASSET_DELIVERY_URL}/assetId/{asset_id}"),
+        "GET",
+        vec![("x-api-key", api_key)],
+        None,
+    )
+    .await?;
+    let location = delivery
+        .get("location")
+        .and_then(JsonValue::as_str)
+        .ok_or_else(|| {
+            LuaError::runtime(format!(
+                "No download location was found for asset {asset_id}"
+            ))
+        })?;
+
+    let contents = send(location, "GET", Vec::new(), None).await?;
+    async_fs::write(dest, contents).await.into_lua_err()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_types() {
+        assert_eq!(content_type(Path::new("a/b.png")).unwrap(), "image/png");
+        assert_eq!(content_type(Path::new("Sound.MP3")).unwrap(), "audio/mpeg");
+        assert_eq!(
+            content_type(Path::new("model.rbxm")).unwrap(),
+            "model/x-rbxm"
+        );
+        assert!(content_type(Path::new("script.luau")).is_err());
+        assert!(content_type(Path::new("no_extension")).is_err());
+    }
+
+    #[test]
+    fn multipart() {
+        let request = json!({ "assetType": "Decal" });
+        let body = multipart_body("b", &request, "a.png", "image/png", b"data");
+        let expected = "--b\r\n\
+            Content-Disposition: form-data; name=\"request\"\r\n\
+            Content-Type: application/json\r\n\r\n\
+            {\"assetType\":\"Decal\"}\r\n\
+            --b\r\n\
+            Content-Disposition: form-data; name=\"fileContent\"; filename=\"a.png\"\r\n\
+            Content-Type: image/png\r\n\r\n\
+            data\r\n\
+            --b--\r\n";
+        assert_eq!(String::from_utf8(body).unwrap(), expected);
+    }
+
+    #[test]
+    fn operations() {
+        let pending = json!({ "path": "operations/abc", "done": false });
+        assert_eq!(operation_asset_id(&pending).unwrap(), None);
+
+        let done = json!({ "done": true, "response": { "assetId": "12345" } });
+        assert_eq!(operation_asset_id(&done).unwrap(), Some(12345));
+
+        let failed = json!({ "done": true, "error": { "message": "Moderated" } });
+        assert!(operation_asset_id(&failed).is_err());
+    }
+}
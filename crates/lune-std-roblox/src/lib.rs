@@ -5,8 +5,11 @@ use std::sync::OnceLock;
 use mlua::prelude::*;
 use mlua_luau_scheduler::LuaSpawnExt;
 
+use glam::Mat4;
 use lune_roblox::{
+    datatypes::types::{CFrame, Vector3},
     document::{Document, DocumentError, DocumentFormat, DocumentKind},
+    geometry,
     instance::{Instance, registry::InstanceRegistry},
     reflection::Database as ReflectionDatabase,
 };
@@ -16,6 +19,8 @@ static REFLECTION_DATABASE: OnceLock<ReflectionDatabase> = OnceLock::new();
 use lune_utils::TableBuilder;
 use roblox_install::RobloxStudio;
 
+mod assets;
+
 const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
 
 /**
@@ -41,8 +46,14 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
         roblox_constants.push(pair?);
     }
 
+    let submodule_assets = TableBuilder::new(lua.clone())?
+        .with_async_function("upload", assets::upload)?
+        .with_async_function("download", assets::download)?
+        .build_readonly()?;
+
     TableBuilder::new(lua)?
         .with_values(roblox_constants)?
+        .with_value("assets", submodule_assets)?
         .with_async_function("deserializePlace", deserialize_place)?
         .with_async_function("deserializeModel", deserialize_model)?
         .with_async_function("serializePlace", serialize_place)?
@@ -51,6 +62,9 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
         .with_function("getReflectionDatabase", get_reflection_database)?
         .with_function("implementProperty", implement_property)?
         .with_function("implementMethod", implement_method)?
+        .with_function("boundingBox", bounding_box)?
+        .with_function("findOverlapping", find_overlapping)?
+        .with_function("raycast", raycast)?
         .with_function("studioApplicationPath", studio_application_path)?
         .with_function("studioContentPath", studio_content_path)?
         .with_function("studioPluginPath", studio_plugin_path)?
@@ -158,6 +172,57 @@ fn implement_method(
     Ok(())
 }
 
+fn collect_parts(instances: &[LuaUserDataRef<Instance>]) -> Vec<geometry::PartBox> {
+    let instances = instances.iter().map(|i| **i).collect::<Vec<_>>();
+    geometry::collect_parts(&instances)
+}
+
+fn bounding_box(
+    _: &Lua,
+    instances: Vec<LuaUserDataRef<Instance>>,
+) -> LuaResult<(Option<CFrame>, Option<Vector3>)> {
+    let parts = collect_parts(&instances);
+    Ok(match geometry::bounding_box(&parts) {
+        Some(bounds) => (
+            Some(CFrame(Mat4::from_translation(bounds.center()))),
+            Some(Vector3(bounds.size())),
+        ),
+        None => (None, None),
+    })
+}
+
+fn find_overlapping(
+    _: &Lua,
+    (instances, cell_size): (Vec<LuaUserDataRef<Instance>>, Option<f32>),
+) -> LuaResult<Vec<[Instance; 2]>> {
+    let parts = collect_parts(&instances);
+    let pairs = geometry::find_overlapping(&parts, cell_size);
+    Ok(pairs
+        .into_iter()
+        .map(|(a, b)| [parts[a].instance, parts[b].instance])
+        .collect())
+}
+
+fn raycast(
+    lua: &Lua,
+    (origin, direction, instances): (
+        LuaUserDataRef<Vector3>,
+        LuaUserDataRef<Vector3>,
+        Vec<LuaUserDataRef<Instance>>,
+    ),
+) -> LuaResult<Option<LuaTable>> {
+    let parts = collect_parts(&instances);
+    let Some(hit) = geometry::raycast(&parts, origin.0, direction.0) else {
+        return Ok(None);
+    };
+    let result = lua.create_table()?;
+    result.set("Instance", parts[hit.index].instance)?;
+    result.set("Position", Vector3(hit.position))?;
+    result.set("Normal", Vector3(hit.normal))?;
+    result.set("Distance", hit.distance)?;
+    Ok(Some(result))
+}
+
 fn studio_application_path(_: &Lua, _: ()) -> LuaResult<String> {
     RobloxStudio::locate()
         .map(|rs| rs.application_path().display().to_string())
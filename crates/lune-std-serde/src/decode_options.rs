@@ -0,0 +1,627 @@
+use std::{cell::RefCell, collections::HashMap, error::Error, fmt};
+
+use mlua::prelude::*;
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, MapAccess, SeqAccess, Visitor},
+    ser::{SerializeMap, SerializeSeq},
+};
+
+thread_local! {
+    static DECODE_STATE: RefCell<Option<DecodeState>> = const { RefCell::new(None) };
+}
+
+/**
+    What to do when a map or object contains the same key more than once.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// The last value for the key is kept, in the position of the first key.
+    #[default]
+    Last,
+    /// The first value for the key is kept, and all others are ignored.
+    First,
+    /// Decoding fails with a [`DecodeLimit::DuplicateKey`] error.
+    Error,
+}
+
+impl FromLua for DuplicateKeys {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        if let LuaValue::String(s) = &value {
+            match s.to_string_lossy().to_ascii_lowercase().trim() {
+                "last" => Ok(Self::Last),
+                "first" => Ok(Self::First),
+                "error" => Ok(Self::Error),
+                kind => Err(LuaError::FromLuaConversionError {
+                    from: value.type_name(),
+                    to: "DuplicateKeys".to_string(),
+                    message: Some(format!(
+                        "Invalid duplicate key policy '{kind}', valid policies are: last, first, error"
+                    )),
+                }),
+            }
+        } else {
+            Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "DuplicateKeys".to_string(),
+                message: None,
+            })
+        }
+    }
+}
+
+/**
+    Options for decoding untrusted input, limiting the values that it may decode into.
+
+    Limits are checked while decoding, so decoding stops as soon as one is exceeded,
+    instead of after the full input has been decoded into a value.
+*/
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DecodeOptions {
+    /// The maximum nesting depth of arrays and maps, where a top-level array has depth 1.
+    pub max_depth: Option<usize>,
+    /// The maximum length of strings, including keys, in bytes.
+    pub max_string_length: Option<usize>,
+    /// The maximum number of items in a single array or map.
+    pub max_collection_size: Option<usize>,
+    /// What to do when a map contains the same key more than once.
+    pub duplicate_keys: DuplicateKeys,
+}
+
+impl FromLua for DecodeOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => Ok(Self {
+                max_depth: t.get("maxDepth")?,
+                max_string_length: t.get("maxStringLength")?,
+                max_collection_size: t.get("maxCollectionSize")?,
+                duplicate_keys: t
+                    .get::<Option<DuplicateKeys>>("duplicateKeys")?
+                    .unwrap_or_default(),
+            }),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "DecodeOptions".to_string(),
+                message: Some(format!(
+                    "Invalid decode options - expected table, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+/**
+    A limit of [`DecodeOptions`] that was exceeded while decoding.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeLimit {
+    /// Arrays and maps were nested deeper than the max depth.
+    Depth(usize),
+    /// A string was longer than the max string length.
+    StringLength(usize),
+    /// An array or map had more items than the max collection size.
+    CollectionSize(usize),
+    /// A map contained the given key more than once.
+    DuplicateKey(String),
+}
+
+impl fmt::Display for DecodeLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Depth(max) => write!(f, "nesting exceeds the max depth of {max}"),
+            Self::StringLength(max) => {
+                write!(f, "string exceeds the max string length of {max} bytes")
+            }
+            Self::CollectionSize(max) => {
+                write!(
+                    f,
+                    "collection exceeds the max collection size of {max} items"
+                )
+            }
+            Self::DuplicateKey(key) => write!(f, "duplicate key '{key}'"),
+        }
+    }
+}
+
+/**
+    The location in the input of an error while decoding.
+
+    The path points to the value that was being decoded when the error happened,
+    similar to a JSON pointer, such as `/servers/0/port` - it is empty for the
+    top-level value. The byte offset, line, and column are only present if the
+    decoder for the format reports the positions of its errors.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DecodeLocation {
+    /// The byte offset in the input, starting at 0, if known.
+    pub offset: Option<usize>,
+    /// The line in the input, starting at 1, if known.
+    pub line: Option<usize>,
+    /// The column in bytes from the start of the line, starting at 1, if known.
+    pub column: Option<usize>,
+    /// The path to the value that failed to decode.
+    pub path: String,
+}
+
+impl DecodeLocation {
+    /**
+        Creates a location for the given byte offset in the input, computing its line and column.
+    */
+    pub(crate) fn new(bytes: &[u8], offset: Option<usize>, path: String) -> Self {
+        let Some(offset) = offset.map(|offset| offset.min(bytes.len())) else {
+            return Self {
+                path,
+                ..Self::default()
+            };
+        };
+        let before = &bytes[..offset];
+        let line_start = before
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map_or(0, |i| i + 1);
+        Self {
+            offset: Some(offset),
+            line: Some(before.iter().filter(|b| **b == b'\n').count() + 1),
+            column: Some(offset - line_start + 1),
+            path,
+        }
+    }
+}
+
+impl fmt::Display for DecodeLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(offset) = self.offset {
+            write!(f, " at byte offset {offset}")?;
+        }
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, " (line {line}, column {column})")?;
+        }
+        if !self.path.is_empty() {
+            write!(f, " at path '{}'", self.path)?;
+        }
+        Ok(())
+    }
+}
+
+/**
+    An error for input that exceeded a limit of [`DecodeOptions`] while decoding.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeLimitError {
+    /// The limit that was exceeded.
+    pub limit: DecodeLimit,
+    /// The location in the input where the limit was exceeded.
+    pub location: DecodeLocation,
+}
+
+impl fmt::Display for DecodeLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.limit, self.location)
+    }
+}
+
+impl Error for DecodeLimitError {}
+
+/**
+    An error for input that could not be decoded, such as input with invalid syntax.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    /// The message of the decoder for the format, without its location.
+    pub message: String,
+    /// The location in the input of the error.
+    pub location: DecodeLocation,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.message, self.location)
+    }
+}
+
+impl Error for DecodeError {}
+
+struct DecodeState {
+    options: DecodeOptions,
+    depth: usize,
+    path: Vec<String>,
+    exceeded: Option<DecodeLimit>,
+}
+
+/**
+    The outcome of a decoder run using [`with_decode_options`].
+*/
+pub(crate) struct DecodeTrace {
+    /// The limit that was exceeded, if any.
+    pub exceeded: Option<DecodeLimit>,
+    /// The path to the value being decoded when the decoder stopped, which
+    /// is only meaningful if it failed, since it is empty once it succeeds.
+    pub path: String,
+}
+
+/**
+    Runs the given decoder with the given options applied to all [`LimitedValue`]s it decodes,
+    while keeping track of the path to the value that is currently being decoded.
+*/
+pub(crate) fn with_decode_options<T>(
+    options: DecodeOptions,
+    f: impl FnOnce() -> T,
+) -> (T, DecodeTrace) {
+    DECODE_STATE.set(Some(DecodeState {
+        options,
+        depth: 0,
+        path: Vec::new(),
+        exceeded: None,
+    }));
+    let result = f();
+    let state = DECODE_STATE.take();
+    let trace = DecodeTrace {
+        exceeded: state.as_ref().and_then(|state| state.exceeded.clone()),
+        path: state.map(|state| state.path.concat()).unwrap_or_default(),
+    };
+    (result, trace)
+}
+
+fn options() -> DecodeOptions {
+    DECODE_STATE.with_borrow(|state| state.as_ref().map(|s| s.options).unwrap_or_default())
+}
+
+fn exceeded<E: de::Error>(limit: DecodeLimit) -> E {
+    let message = limit.to_string();
+    DECODE_STATE.with_borrow_mut(|state| {
+        if let Some(state) = state {
+            state.exceeded.get_or_insert(limit);
+        }
+    });
+    E::custom(message)
+}
+
+fn enter<E: de::Error>() -> Result<(), E> {
+    let depth = DECODE_STATE.with_borrow_mut(|state| {
+        state.as_mut().map_or(0, |state| {
+            state.depth += 1;
+            state.depth
+        })
+    });
+    match options().max_depth {
+        Some(max) if depth > max => Err(exceeded(DecodeLimit::Depth(max))),
+        _ => Ok(()),
+    }
+}
+
+fn exit() {
+    DECODE_STATE.with_borrow_mut(|state| {
+        if let Some(state) = state {
+            state.depth = state.depth.saturating_sub(1);
+        }
+    });
+}
+
+fn push_path(segment: &str) {
+    DECODE_STATE.with_borrow_mut(|state| {
+        if let Some(state) = state {
+            // Escaped the same way as JSON pointers, '~' as '~0' and '/' as '~1'
+            let segment = segment.replace('~', "~0").replace('/', "~1");
+            state.path.push(format!("/{segment}"));
+        }
+    });
+}
+
+fn pop_path() {
+    DECODE_STATE.with_borrow_mut(|state| {
+        if let Some(state) = state {
+            state.path.pop();
+        }
+    });
+}
+
+fn check_string<E: de::Error>(len: usize) -> Result<(), E> {
+    match options().max_string_length {
+        Some(max) if len > max => Err(exceeded(DecodeLimit::StringLength(max))),
+        _ => Ok(()),
+    }
+}
+
+fn check_collection<E: de::Error>(len: usize) -> Result<(), E> {
+    match options().max_collection_size {
+        Some(max) if len > max => Err(exceeded(DecodeLimit::CollectionSize(max))),
+        _ => Ok(()),
+    }
+}
+
+/**
+    A value decoded from any format, checking the limits of the
+    current [`DecodeOptions`] while it is being deserialized.
+*/
+#[derive(Debug, Clone)]
+pub(crate) enum LimitedValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    Seq(Vec<LimitedValue>),
+    Map(Vec<(LimitedValue, LimitedValue)>),
+}
+
+/// Keys of maps that are compared to find duplicate keys
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum MapKey {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(u64),
+    String(String),
+    Bytes(Vec<u8>),
+}
+
+impl MapKey {
+    fn from_value(value: &LimitedValue) -> Option<Self> {
+        Some(match value {
+            LimitedValue::Null => Self::Null,
+            LimitedValue::Bool(b) => Self::Bool(*b),
+            LimitedValue::Int(i) => Self::Int(*i),
+            LimitedValue::UInt(u) => Self::UInt(*u),
+            LimitedValue::Float(f) => Self::Float(f.to_bits()),
+            LimitedValue::String(s) => Self::String(s.clone()),
+            LimitedValue::Bytes(b) => Self::Bytes(b.clone()),
+            LimitedValue::Seq(_) | LimitedValue::Map(_) => return None,
+        })
+    }
+}
+
+impl fmt::Display for MapKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => write!(f, "null"),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Int(i) => write!(f, "{i}"),
+            Self::UInt(u) => write!(f, "{u}"),
+            Self::Float(bits) => write!(f, "{}", f64::from_bits(*bits)),
+            Self::String(s) => write!(f, "{s}"),
+            Self::Bytes(b) => write!(f, "{}", String::from_utf8_lossy(b)),
+        }
+    }
+}
+
+struct LimitedValueVisitor;
+
+impl<'de> Visitor<'de> for LimitedValueVisitor {
+    type Value = LimitedValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(LimitedValue::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(LimitedValue::Int(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(LimitedValue::UInt(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(LimitedValue::Float(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        check_string(v.len())?;
+        Ok(LimitedValue::String(v.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        check_string(v.len())?;
+        Ok(LimitedValue::String(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        check_string(v.len())?;
+        Ok(LimitedValue::Bytes(v.to_vec()))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(LimitedValue::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(LimitedValue::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        LimitedValue::deserialize(deserializer)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        LimitedValue::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        enter()?;
+        let mut items = Vec::new();
+        loop {
+            // Paths are not popped on errors, so that they point to the failing value
+            push_path(&items.len().to_string());
+            let Some(item) = seq.next_element::<LimitedValue>()? else {
+                pop_path();
+                break;
+            };
+            items.push(item);
+            check_collection(items.len())?;
+            pop_path();
+        }
+        exit();
+        Ok(LimitedValue::Seq(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        enter()?;
+        let duplicate_keys = options().duplicate_keys;
+        let mut entries = Vec::new();
+        let mut indices = HashMap::new();
+        while let Some(key) = map.next_key::<LimitedValue>()? {
+            match MapKey::from_value(&key) {
+                Some(map_key) => push_path(&map_key.to_string()),
+                None => push_path("?"),
+            }
+            let value = map.next_value::<LimitedValue>()?;
+            let existing =
+                MapKey::from_value(&key).and_then(|map_key| match indices.get(&map_key) {
+                    Some(&index) => Some((map_key, index)),
+                    None => {
+                        indices.insert(map_key, entries.len());
+                        None
+                    }
+                });
+            match (existing, duplicate_keys) {
+                (None, _) => {
+                    entries.push((key, value));
+                    check_collection(entries.len())?;
+                }
+                (Some((_, index)), DuplicateKeys::Last) => entries[index].1 = value,
+                (Some(_), DuplicateKeys::First) => {}
+                (Some((map_key, _)), DuplicateKeys::Error) => {
+                    return Err(exceeded(DecodeLimit::DuplicateKey(map_key.to_string())));
+                }
+            }
+            pop_path();
+        }
+        exit();
+        Ok(LimitedValue::Map(entries))
+    }
+}
+
+impl<'de> Deserialize<'de> for LimitedValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(LimitedValueVisitor)
+    }
+}
+
+impl Serialize for LimitedValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Null => serializer.serialize_unit(),
+            Self::Bool(b) => serializer.serialize_bool(*b),
+            Self::Int(i) => serializer.serialize_i64(*i),
+            Self::UInt(u) => serializer.serialize_u64(*u),
+            Self::Float(f) => serializer.serialize_f64(*f),
+            Self::String(s) => serializer.serialize_str(s),
+            Self::Bytes(b) => serializer.serialize_bytes(b),
+            Self::Seq(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Self::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+/**
+    Replaces comments and trailing commas in JSON with comments (JSONC) with
+    whitespace, so that it may be decoded as JSON with the same byte offsets.
+*/
+pub(crate) fn strip_jsonc(bytes: &[u8]) -> Vec<u8> {
+    let mut out = bytes.to_vec();
+    let mut in_string = false;
+    let mut trailing_comma = None;
+    let mut i = 0;
+    while i < out.len() {
+        let byte = out[i];
+        if in_string {
+            match byte {
+                b'\\' => i += 1,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            i += 1;
+            continue;
+        }
+        match (byte, out.get(i + 1)) {
+            (b'/', Some(b'/')) => {
+                while i < out.len() && out[i] != b'\n' {
+                    out[i] = b' ';
+                    i += 1;
+                }
+                continue;
+            }
+            (b'/', Some(b'*')) => {
+                let end = out[i + 2..]
+                    .windows(2)
+                    .position(|w| w == b"*/")
+                    .map_or(out.len(), |pos| i + 2 + pos + 2);
+                for b in &mut out[i..end] {
+                    if *b != b'\n' {
+                        *b = b' ';
+                    }
+                }
+                i = end;
+                continue;
+            }
+            (b'"', _) => {
+                in_string = true;
+                trailing_comma = None;
+            }
+            (b',', _) => trailing_comma = Some(i),
+            (b'}' | b']', _) => {
+                if let Some(comma) = trailing_comma.take() {
+                    out[comma] = b' ';
+                }
+            }
+            (b, _) if b.is_ascii_whitespace() => {}
+            _ => trailing_comma = None,
+        }
+        i += 1;
+    }
+    out
+}
+
+/**
+    Converts the 1-based line and column of an error from `serde_json` into a byte offset.
+*/
+pub(crate) fn json_offset(bytes: &[u8], line: usize, column: usize) -> usize {
+    let line_start = if line > 1 {
+        bytes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| **b == b'\n')
+            .nth(line - 2)
+            .map_or(bytes.len(), |(index, _)| index + 1)
+    } else {
+        0
+    };
+    (line_start + column.saturating_sub(1)).min(bytes.len())
+}
+
+/**
+    Returns the message of an error from `serde_json`, without the line and column at its end.
+*/
+pub(crate) fn json_error_message(error: &serde_json::Error) -> String {
+    let mut message = error.to_string();
+    if error.line() > 0
+        && let Some(index) = message.rfind(" at line ")
+    {
+        message.truncate(index);
+    }
+    message
+}
@@ -4,8 +4,13 @@ use serde_json::Value as JsonValue;
 use serde_yaml2::wrapper::YamlNodeWrapper as YamlValue;
 use toml::Value as TomlValue;
 
+use crate::decode_options::{
+    DecodeError, DecodeLimitError, DecodeLocation, DecodeOptions, LimitedValue, json_error_message,
+    json_offset, strip_jsonc, with_decode_options,
+};
+
 // NOTE: These are options for going from other format -> lua ("serializing" lua values)
-const LUA_SERIALIZE_OPTIONS: LuaSerializeOptions = LuaSerializeOptions::new()
+pub(crate) const LUA_SERIALIZE_OPTIONS: LuaSerializeOptions = LuaSerializeOptions::new()
     .set_array_metatable(false)
     .serialize_none_to_null(false)
     .serialize_unit_to_null(false);
@@ -64,6 +69,7 @@ impl FromLua for EncodeDecodeFormat {
 pub struct EncodeDecodeConfig {
     pub format: EncodeDecodeFormat,
     pub pretty: bool,
+    pub decode_options: Option<DecodeOptions>,
 }
 
 impl From<EncodeDecodeFormat> for EncodeDecodeConfig {
@@ -71,6 +77,7 @@ impl From<EncodeDecodeFormat> for EncodeDecodeConfig {
         Self {
             format,
             pretty: false,
+            decode_options: None,
         }
     }
 }
@@ -80,6 +87,17 @@ impl From<(EncodeDecodeFormat, bool)> for EncodeDecodeConfig {
         Self {
             format: value.0,
             pretty: value.1,
+            decode_options: None,
+        }
+    }
+}
+
+impl From<(EncodeDecodeFormat, DecodeOptions)> for EncodeDecodeConfig {
+    fn from(value: (EncodeDecodeFormat, DecodeOptions)) -> Self {
+        Self {
+            format: value.0,
+            pretty: false,
+            decode_options: Some(value.1),
         }
     }
 }
@@ -125,7 +143,8 @@ pub fn encode(value: LuaValue, lua: &Lua, config: EncodeDecodeConfig) -> LuaResu
 
     # Errors
 
-    Errors when the decoding fails.
+    Errors when the decoding fails, with a [`DecodeError`] or [`DecodeLimitError`]
+    containing the location of the error in the input, if it could be found.
 */
 pub fn decode(
     bytes: impl AsRef<[u8]>,
@@ -133,7 +152,22 @@ pub fn decode(
     config: EncodeDecodeConfig,
 ) -> LuaResult<LuaValue> {
     let bytes = bytes.as_ref();
-    match config.format {
+    if let Some(options) = config.decode_options {
+        let value = decode_limited(bytes, config.format, options)?;
+        return lua.to_value_with(&value, LUA_SERIALIZE_OPTIONS);
+    }
+    decode_value(bytes, lua, config.format).map_err(|e| {
+        // Values are decoded into the value types of each format, which keep details
+        // that generic values do not, so errors are located by decoding again instead
+        match decode_limited(bytes, config.format, DecodeOptions::default()) {
+            Err(located) => located,
+            Ok(_) => e,
+        }
+    })
+}
+
+fn decode_value(bytes: &[u8], lua: &Lua, format: EncodeDecodeFormat) -> LuaResult<LuaValue> {
+    match format {
         EncodeDecodeFormat::Json => {
             let value: JsonValue = serde_json::from_slice(bytes).into_lua_err()?;
             lua.to_value_with(&value, LUA_SERIALIZE_OPTIONS)
@@ -163,3 +197,67 @@ pub fn decode(
         }
     }
 }
+
+/// An error of the decoder for a format, with the byte offset of the error, if known
+struct DecodeFailure {
+    message: String,
+    offset: Option<usize>,
+}
+
+/**
+    Decodes the given bytes while checking the limits of the given options,
+    returning a [`DecodeLimitError`] as soon as any limit is exceeded, or
+    a [`DecodeError`] with the location of any other error.
+
+    JSON with comments is decoded by the JSON decoder, after replacing its comments and
+    trailing commas with whitespace, so that the limits are checked in the same way.
+*/
+fn decode_limited(
+    bytes: &[u8],
+    format: EncodeDecodeFormat,
+    options: DecodeOptions,
+) -> LuaResult<LimitedValue> {
+    let (result, trace) = with_decode_options(options, || match format {
+        EncodeDecodeFormat::Json => decode_json_limited(bytes),
+        EncodeDecodeFormat::JsonC => decode_json_limited(&strip_jsonc(bytes)),
+        EncodeDecodeFormat::Yaml => {
+            let string = std::str::from_utf8(bytes).map_err(|e| DecodeFailure {
+                message: e.to_string(),
+                offset: Some(e.valid_up_to()),
+            })?;
+            serde_yaml2::from_str::<LimitedValue>(string).map_err(|e| DecodeFailure {
+                message: e.to_string(),
+                offset: None,
+            })
+        }
+        EncodeDecodeFormat::Toml => {
+            let string = std::str::from_utf8(bytes).map_err(|e| DecodeFailure {
+                message: "TOML must be valid utf-8".to_string(),
+                offset: Some(e.valid_up_to()),
+            })?;
+            toml::from_str::<LimitedValue>(string).map_err(|e| DecodeFailure {
+                message: e.message().to_string(),
+                offset: e.span().map(|span| span.start),
+            })
+        }
+    });
+    let failure = match result {
+        Ok(value) => return Ok(value),
+        Err(failure) => failure,
+    };
+    let location = DecodeLocation::new(bytes, failure.offset, trace.path);
+    Err(match trace.exceeded {
+        Some(limit) => LuaError::external(DecodeLimitError { limit, location }),
+        None => LuaError::external(DecodeError {
+            message: failure.message,
+            location,
+        }),
+    })
+}
+
+fn decode_json_limited(bytes: &[u8]) -> Result<LimitedValue, DecodeFailure> {
+    serde_json::from_slice::<LimitedValue>(bytes).map_err(|e| DecodeFailure {
+        message: json_error_message(&e),
+        offset: (e.line() > 0).then(|| json_offset(bytes, e.line(), e.column())),
+    })
+}
@@ -0,0 +1,279 @@
+use std::cell::RefCell;
+
+use mlua::prelude::*;
+use serde_json::Value as JsonValue;
+
+use crate::{
+    decode_options::{DecodeError, DecodeLocation, json_error_message, json_offset},
+    encode_decode::LUA_SERIALIZE_OPTIONS,
+};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/**
+    Where values are read from for a [`JsonStream`] - either a function
+    returning chunks, or an object with a `read` method returning chunks,
+    such as a `TcpStream` or a reader of a child process.
+
+    Chunks are strings or buffers, and the end of the stream is `nil` or an empty chunk.
+*/
+#[derive(Debug, Clone)]
+pub enum JsonStreamReader {
+    Function(LuaFunction),
+    Table(LuaTable),
+    UserData(LuaAnyUserData),
+}
+
+impl JsonStreamReader {
+    async fn read(&self) -> LuaResult<Option<Vec<u8>>> {
+        let chunk = match self {
+            Self::Function(f) => f.call_async::<LuaValue>(CHUNK_SIZE).await?,
+            Self::Table(t) => t.call_async_method::<LuaValue>("read", CHUNK_SIZE).await?,
+            Self::UserData(u) => u.call_async_method::<LuaValue>("read", CHUNK_SIZE).await?,
+        };
+        let bytes = match chunk {
+            LuaValue::Nil => Vec::new(),
+            LuaValue::String(s) => s.as_bytes().to_vec(),
+            LuaValue::Buffer(b) => b.to_vec(),
+            value => {
+                return Err(LuaError::runtime(format!(
+                    "Invalid chunk read from json stream - expected string or buffer, got {}",
+                    value.type_name()
+                )));
+            }
+        };
+        Ok((!bytes.is_empty()).then_some(bytes))
+    }
+}
+
+impl FromLua for JsonStreamReader {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Function(f) => Ok(Self::Function(f)),
+            LuaValue::Table(t) => Ok(Self::Table(t)),
+            LuaValue::UserData(u) => Ok(Self::UserData(u)),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "JsonStreamReader".to_string(),
+                message: Some(format!(
+                    "Invalid json stream reader - expected function or reader, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArrayPosition {
+    Start,
+    Value,
+    AfterValue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Start,
+    Values,
+    Array(ArrayPosition),
+    Done,
+}
+
+enum Step {
+    Value(usize, JsonValue),
+    NeedMore,
+    Done,
+}
+
+#[derive(Debug)]
+struct JsonStreamState {
+    buffer: Vec<u8>,
+    position: usize,
+    offset: usize,
+    index: usize,
+    mode: Mode,
+    eof: bool,
+}
+
+impl JsonStreamState {
+    fn remaining(&self) -> &[u8] {
+        &self.buffer[self.position..]
+    }
+
+    fn consume(&mut self, len: usize) {
+        self.position += len;
+    }
+
+    fn push_chunk(&mut self, chunk: Option<Vec<u8>>) {
+        // Values that were already decoded are dropped here, and not as soon as
+        // they are decoded, so that the buffer is only moved once for each chunk
+        self.buffer.drain(..self.position);
+        self.offset += self.position;
+        self.position = 0;
+        match chunk {
+            Some(chunk) => self.buffer.extend(chunk),
+            None => self.eof = true,
+        }
+    }
+
+    fn fail(&mut self, message: String, offset: usize) -> DecodeError {
+        let location = DecodeLocation {
+            offset: Some(self.offset + self.position + offset),
+            path: format!("/{}", self.index),
+            ..DecodeLocation::default()
+        };
+        self.mode = Mode::Done;
+        self.buffer.clear();
+        self.position = 0;
+        DecodeError { message, location }
+    }
+
+    fn advance(&mut self) -> Result<Step, DecodeError> {
+        loop {
+            if self.mode == Mode::Done {
+                return Ok(Step::Done);
+            }
+            let whitespace = self
+                .remaining()
+                .iter()
+                .take_while(|b| matches!(b, b' ' | b'\t' | b'\n' | b'\r'))
+                .count();
+            self.consume(whitespace);
+            let Some(&byte) = self.remaining().first() else {
+                return match (self.eof, self.mode) {
+                    (false, _) => Ok(Step::NeedMore),
+                    (true, Mode::Array(_)) => {
+                        Err(self.fail("EOF while parsing a list".to_string(), 0))
+                    }
+                    (true, _) => {
+                        self.mode = Mode::Done;
+                        Ok(Step::Done)
+                    }
+                };
+            };
+            match (self.mode, byte) {
+                (Mode::Start, b'[') => {
+                    self.consume(1);
+                    self.mode = Mode::Array(ArrayPosition::Start);
+                }
+                (Mode::Start, _) => self.mode = Mode::Values,
+                (Mode::Array(ArrayPosition::Start | ArrayPosition::AfterValue), b']') => {
+                    self.consume(1);
+                    self.mode = Mode::Done;
+                }
+                (Mode::Array(ArrayPosition::AfterValue), b',') => {
+                    self.consume(1);
+                    self.mode = Mode::Array(ArrayPosition::Value);
+                }
+                (Mode::Array(ArrayPosition::AfterValue), _) => {
+                    return Err(self.fail("expected `,` or `]`".to_string(), 0));
+                }
+                (Mode::Array(_) | Mode::Values, _) => {
+                    let Some(value) = self.decode_value()? else {
+                        return Ok(Step::NeedMore);
+                    };
+                    if let Mode::Array(_) = self.mode {
+                        self.mode = Mode::Array(ArrayPosition::AfterValue);
+                    }
+                    self.index += 1;
+                    return Ok(Step::Value(self.index, value));
+                }
+                (Mode::Done, _) => unreachable!(),
+            }
+        }
+    }
+
+    fn decode_value(&mut self) -> Result<Option<JsonValue>, DecodeError> {
+        let mut values =
+            serde_json::Deserializer::from_slice(self.remaining()).into_iter::<JsonValue>();
+        match values.next() {
+            Some(Ok(value)) => {
+                let len = values.byte_offset();
+                // Numbers are not delimited, so a number at the end
+                // of the buffer may continue in the next chunk
+                if value.is_number() && len == self.remaining().len() && !self.eof {
+                    return Ok(None);
+                }
+                self.consume(len);
+                Ok(Some(value))
+            }
+            Some(Err(e)) if e.is_eof() && !self.eof => Ok(None),
+            Some(Err(e)) => {
+                let offset = json_offset(self.remaining(), e.line(), e.column());
+                Err(self.fail(json_error_message(&e), offset))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/**
+    A stream of JSON values, decoded incrementally from the chunks of a [`JsonStreamReader`].
+
+    Streams contain either newline-delimited JSON, where each top-level value is
+    decoded separately, or a single top-level array, where each of its items is
+    decoded separately - so that the whole document never has to be kept in memory.
+*/
+#[derive(Debug)]
+pub struct JsonStream {
+    reader: JsonStreamReader,
+    state: RefCell<JsonStreamState>,
+}
+
+impl JsonStream {
+    /**
+        Creates a new stream reading from the given reader.
+    */
+    #[must_use]
+    pub fn new(reader: JsonStreamReader) -> Self {
+        Self {
+            reader,
+            state: RefCell::new(JsonStreamState {
+                buffer: Vec::new(),
+                position: 0,
+                offset: 0,
+                index: 0,
+                mode: Mode::Start,
+                eof: false,
+            }),
+        }
+    }
+
+    /**
+        Decodes the next value of the stream, reading more chunks as needed.
+
+        Returns the index of the value, starting at 1, along with the value,
+        or `None` once the stream has ended.
+
+        # Errors
+
+        Errors if reading fails, or with a [`DecodeError`] if the stream contains invalid JSON.
+    */
+    pub async fn next(&self) -> LuaResult<Option<(usize, JsonValue)>> {
+        loop {
+            let step = self.state.borrow_mut().advance();
+            match step.map_err(LuaError::external)? {
+                Step::Value(index, value) => return Ok(Some((index, value))),
+                Step::Done => return Ok(None),
+                Step::NeedMore => {
+                    let chunk = self.reader.read().await?;
+                    self.state.borrow_mut().push_chunk(chunk);
+                }
+            }
+        }
+    }
+}
+
+impl LuaUserData for JsonStream {
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method("next", |lua, this, (): ()| async move {
+            Ok(match this.next().await? {
+                Some((index, value)) => (
+                    Some(index),
+                    lua.to_value_with(&value, LUA_SERIALIZE_OPTIONS)?,
+                ),
+                None => (None, LuaValue::Nil),
+            })
+        });
+    }
+}
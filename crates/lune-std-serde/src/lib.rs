@@ -6,12 +6,18 @@ use mlua::prelude::*;
 use lune_utils::TableBuilder;
 
 mod compress_decompress;
+mod decode_options;
 mod encode_decode;
 mod hash;
+mod json_stream;
 
 pub use self::compress_decompress::{CompressDecompressFormat, compress, decompress};
+pub use self::decode_options::{
+    DecodeError, DecodeLimit, DecodeLimitError, DecodeLocation, DecodeOptions, DuplicateKeys,
+};
 pub use self::encode_decode::{EncodeDecodeConfig, EncodeDecodeFormat, decode, encode};
 pub use self::hash::HashOptions;
+pub use self::json_stream::{JsonStream, JsonStreamReader};
 
 const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
 
@@ -34,6 +40,8 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
     TableBuilder::new(lua)?
         .with_function("encode", serde_encode)?
         .with_function("decode", serde_decode)?
+        .with_function("tryDecode", serde_try_decode)?
+        .with_function("jsonStream", serde_json_stream)?
         .with_async_function("compress", serde_compress)?
         .with_async_function("decompress", serde_decompress)?
         .with_function("hash", hash_message)?
@@ -49,11 +57,47 @@ fn serde_encode(
     encode(value, lua, config)
 }
 
-fn serde_decode(lua: &Lua, (format, bs): (EncodeDecodeFormat, BString)) -> LuaResult<LuaValue> {
-    let config = EncodeDecodeConfig::from(format);
+fn serde_decode(
+    lua: &Lua,
+    (format, bs, options): (EncodeDecodeFormat, BString, Option<DecodeOptions>),
+) -> LuaResult<LuaValue> {
+    let config = match options {
+        Some(options) => EncodeDecodeConfig::from((format, options)),
+        None => EncodeDecodeConfig::from(format),
+    };
     decode(bs, lua, config)
 }
 
+fn serde_try_decode(
+    lua: &Lua,
+    (format, bs, options): (EncodeDecodeFormat, BString, Option<DecodeOptions>),
+) -> LuaResult<(bool, LuaValue)> {
+    match serde_decode(lua, (format, bs, options)) {
+        Ok(value) => Ok((true, value)),
+        Err(e) => {
+            let (message, location) = if let Some(e) = e.downcast_ref::<DecodeError>() {
+                (e.message.clone(), e.location.clone())
+            } else if let Some(e) = e.downcast_ref::<DecodeLimitError>() {
+                (e.limit.to_string(), e.location.clone())
+            } else {
+                (e.to_string(), DecodeLocation::default())
+            };
+            let err = TableBuilder::new(lua.clone())?
+                .with_value("message", message)?
+                .with_value("path", location.path)?
+                .with_value("offset", location.offset)?
+                .with_value("line", location.line)?
+                .with_value("column", location.column)?
+                .build()?;
+            Ok((false, LuaValue::Table(err)))
+        }
+    }
+}
+
+fn serde_json_stream(_: &Lua, reader: JsonStreamReader) -> LuaResult<JsonStream> {
+    Ok(JsonStream::new(reader))
+}
+
 async fn serde_compress(
     lua: Lua,
     (format, bs, level): (CompressDecompressFormat, BString, Option<i32>),
@@ -0,0 +1,285 @@
+use std::cmp::Ordering;
+
+use console::{Key, Term, style, truncate_str};
+use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSpawnExt;
+
+use lune_utils::fmt::{FormatLimits, ValueFormatConfig, pretty_format_value};
+
+// Only a summary of each value is shown on its row, nested values are shown when expanded
+const SUMMARY_CONFIG: ValueFormatConfig = ValueFormatConfig::new()
+    .with_max_depth(0)
+    .with_colors_enabled(true)
+    .with_limits(FormatLimits::new());
+
+// Used instead of the tree view when not writing to a terminal
+const FALLBACK_CONFIG: ValueFormatConfig = ValueFormatConfig::new()
+    .with_max_depth(4)
+    .with_colors_enabled(false)
+    .with_limits(FormatLimits::new());
+
+const HELP: &str = "↑/↓ move · →/enter expand · ← collapse · q quit";
+
+/**
+    A single visible row of the tree view - a value,
+    along with the key that it is stored at, if any.
+*/
+struct Row {
+    label: Option<String>,
+    summary: String,
+    value: LuaValue,
+    depth: usize,
+    expanded: bool,
+}
+
+impl Row {
+    fn new(label: Option<String>, value: LuaValue, depth: usize) -> Self {
+        Self {
+            label,
+            summary: summarize(&value),
+            value,
+            depth,
+            expanded: false,
+        }
+    }
+
+    fn render(&self, selected: bool, width: usize) -> String {
+        let marker = if !is_expandable(&self.value) {
+            " "
+        } else if self.expanded {
+            "▾"
+        } else {
+            "▸"
+        };
+        let summary = &self.summary;
+        let line = match &self.label {
+            Some(label) => format!(
+                "{}{marker} {label} {} {summary}",
+                "  ".repeat(self.depth),
+                style("=").dim()
+            ),
+            None => format!("{}{marker} {summary}", "  ".repeat(self.depth)),
+        };
+        let line = truncate_str(&line, width.saturating_sub(2), "…");
+        if selected {
+            format!("{} {line}", style(">").cyan().bold())
+        } else {
+            format!("  {line}")
+        }
+    }
+}
+
+/**
+    An interactive tree view of a value, where tables and
+    userdata are expanded only when navigating into them.
+*/
+struct Inspector {
+    rows: Vec<Row>,
+    cursor: usize,
+    scroll: usize,
+    drawn: usize,
+}
+
+impl Inspector {
+    fn new(value: LuaValue) -> Self {
+        Self {
+            rows: vec![Row::new(None, value, 0)],
+            cursor: 0,
+            scroll: 0,
+            drawn: 0,
+        }
+    }
+
+    fn expand(&mut self, lua: &Lua) -> LuaResult<()> {
+        let row = &self.rows[self.cursor];
+        if row.expanded || !is_expandable(&row.value) {
+            return Ok(());
+        }
+        let depth = row.depth + 1;
+        let children = children_of(lua, &row.value)?
+            .into_iter()
+            .map(|(label, value)| Row::new(Some(label), value, depth))
+            .collect::<Vec<_>>();
+        self.rows[self.cursor].expanded = true;
+        self.rows.splice(self.cursor + 1..self.cursor + 1, children);
+        Ok(())
+    }
+
+    fn collapse(&mut self) {
+        let depth = self.rows[self.cursor].depth;
+        if self.rows[self.cursor].expanded {
+            let end = self.rows[self.cursor + 1..]
+                .iter()
+                .position(|row| row.depth <= depth)
+                .map_or(self.rows.len(), |offset| self.cursor + 1 + offset);
+            self.rows.drain(self.cursor + 1..end);
+            self.rows[self.cursor].expanded = false;
+        } else if let Some(parent) = self.rows[..self.cursor]
+            .iter()
+            .rposition(|row| row.depth < depth)
+        {
+            self.cursor = parent;
+        }
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        let last = self.rows.len() - 1;
+        self.cursor = self.cursor.saturating_add_signed(delta).min(last);
+    }
+
+    fn draw(&mut self, term: &Term) -> std::io::Result<()> {
+        let (height, width) = term.size();
+        let visible = usize::from(height).saturating_sub(2).max(1);
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if self.cursor >= self.scroll + visible {
+            self.scroll = self.cursor + 1 - visible;
+        }
+
+        let mut lines = self
+            .rows
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(visible)
+            .map(|(index, row)| row.render(index == self.cursor, usize::from(width)))
+            .collect::<Vec<_>>();
+        lines.push(style(HELP).dim().to_string());
+
+        term.clear_last_lines(self.drawn)?;
+        for line in &lines {
+            term.write_line(line)?;
+        }
+        self.drawn = lines.len();
+        Ok(())
+    }
+}
+
+/**
+    Opens an interactive tree view of the given value, returning once the user quits it.
+
+    When not writing to a terminal, the value is printed instead.
+*/
+pub async fn inspect(lua: Lua, value: LuaValue) -> LuaResult<()> {
+    let term = Term::stdout();
+    if !term.is_term() {
+        term.write_line(&pretty_format_value(&value, &FALLBACK_CONFIG))?;
+        return Ok(());
+    }
+
+    let mut inspector = Inspector::new(value);
+    term.hide_cursor()?;
+    let result = async {
+        loop {
+            inspector.draw(&term)?;
+            let key = lua.spawn_blocking(|| Term::stdout().read_key()).await?;
+            match key {
+                Key::ArrowUp | Key::Char('k') => inspector.move_by(-1),
+                Key::ArrowDown | Key::Char('j') => inspector.move_by(1),
+                Key::PageUp => inspector.move_by(-10),
+                Key::PageDown => inspector.move_by(10),
+                Key::Home => inspector.cursor = 0,
+                Key::End => inspector.move_by(isize::MAX),
+                Key::ArrowRight | Key::Enter | Key::Char('l' | ' ') => inspector.expand(&lua)?,
+                Key::ArrowLeft | Key::Char('h') => inspector.collapse(),
+                Key::Escape | Key::Char('q') => break,
+                _ => {}
+            }
+        }
+        Ok::<_, LuaError>(())
+    }
+    .await;
+    term.clear_last_lines(inspector.drawn)?;
+    term.show_cursor()?;
+    result
+}
+
+fn is_expandable(value: &LuaValue) -> bool {
+    match value {
+        LuaValue::Table(t) => !t.is_empty() || t.metatable().is_some(),
+        LuaValue::UserData(_) => true,
+        _ => false,
+    }
+}
+
+fn summarize(value: &LuaValue) -> String {
+    match value {
+        // Strings are given as-is at the top level when formatting, so they are quoted here instead
+        LuaValue::String(s) => {
+            let s = s.to_string_lossy();
+            let s = truncate_str(&s, 200, "…");
+            style(format!("{s:?}")).green().to_string()
+        }
+        LuaValue::Table(t) if !t.is_empty() => {
+            let formatted = pretty_format_value(value, &SUMMARY_CONFIG);
+            let count = style(format!(
+                "({} entries)",
+                t.pairs::<LuaValue, LuaValue>().count()
+            ));
+            format!("{formatted} {}", count.dim())
+        }
+        value => pretty_format_value(value, &SUMMARY_CONFIG),
+    }
+}
+
+/**
+    Returns the children of a table or userdata, along with a label for each - the
+    entries of tables, sorted by key, and the metatable of both tables and userdata.
+*/
+fn children_of(lua: &Lua, value: &LuaValue) -> LuaResult<Vec<(String, LuaValue)>> {
+    let mut children = Vec::new();
+    let metatable = match value {
+        LuaValue::Table(t) => {
+            let mut entries = t
+                .pairs::<LuaValue, LuaValue>()
+                .collect::<LuaResult<Vec<_>>>()?;
+            entries.sort_by(|(a, _), (b, _)| compare_keys(a, b));
+            children.extend(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (format_key(&key), value)),
+            );
+            t.metatable().map(LuaValue::Table)
+        }
+        // NOTE: Userdata may protect their metatable using the __metatable
+        // field, which getmetatable respects, so we use it instead of mlua
+        value => {
+            let getmetatable = lua.globals().get::<LuaFunction>("getmetatable")?;
+            Some(getmetatable.call::<LuaValue>(value)?).filter(|mt| !mt.is_nil())
+        }
+    };
+    if let Some(metatable) = metatable {
+        children.push((style("<metatable>").dim().to_string(), metatable));
+    }
+    Ok(children)
+}
+
+fn format_key(key: &LuaValue) -> String {
+    if let LuaValue::String(s) = key
+        && let Ok(s) = s.to_str()
+        && s.chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return s.to_string();
+    }
+    format!("[{}]", summarize(key))
+}
+
+fn compare_keys(a: &LuaValue, b: &LuaValue) -> Ordering {
+    match (a, b) {
+        (LuaValue::Integer(a), LuaValue::Integer(b)) => a.cmp(b),
+        (a, b) if a.is_number() && b.is_number() => {
+            let a = a.as_f64().unwrap_or_default();
+            let b = b.as_f64().unwrap_or_default();
+            a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+        }
+        (LuaValue::String(a), LuaValue::String(b)) => a.as_bytes()[..].cmp(&b.as_bytes()[..]),
+        // Numbers first, then strings, then everything else
+        (a, b) => b
+            .is_number()
+            .cmp(&a.is_number())
+            .then_with(|| b.is_string().cmp(&a.is_string())),
+    }
+}
@@ -1,12 +1,12 @@
 #![allow(clippy::cargo_common_metadata)]
 
 use std::{
-    io::{Stdin, stderr, stdin, stdout},
+    io::{Stdin, Write as _, stderr, stdin, stdout},
     sync::{Arc, LazyLock},
 };
 
 use mlua::prelude::*;
-use mlua_luau_scheduler::LuaSpawnExt;
+use mlua_luau_scheduler::{LuaSchedulerExt, LuaSpawnExt, ShutdownHook};
 
 use async_lock::Mutex as AsyncMutex;
 use blocking::Unblock;
@@ -15,11 +15,14 @@ use futures_lite::{io::BufReader, prelude::*};
 use lune_utils::{
     TableBuilder,
     fmt::{ValueFormatConfig, pretty_format_multi_value},
+    shutdown::PRIORITY_STDIO_FLUSH,
 };
 
+mod inspect;
 mod prompt;
 mod style_and_color;
 
+use self::inspect::inspect;
 use self::prompt::{PromptOptions, PromptResult, prompt};
 use self::style_and_color::{ColorKind, StyleKind};
 
@@ -51,6 +54,14 @@ pub fn typedefs() -> String {
     Errors when out of memory.
 */
 pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    lua.on_shutdown(
+        PRIORITY_STDIO_FLUSH,
+        ShutdownHook::native(|_| {
+            let _ = stdout().lock().flush();
+            let _ = stderr().lock().flush();
+        }),
+    );
+
     TableBuilder::new(lua)?
         .with_function("color", stdio_color)?
         .with_function("style", stdio_style)?
@@ -60,6 +71,7 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
         .with_async_function("readLine", stdio_read_line)?
         .with_async_function("readToEnd", stdio_read_to_end)?
         .with_async_function("prompt", stdio_prompt)?
+        .with_async_function("inspect", stdio_inspect)?
         .build_readonly()
 }
 
@@ -103,6 +115,10 @@ async fn stdio_read_to_end(lua: Lua, (): ()) -> LuaResult<LuaString> {
     lua.create_string(&buffer)
 }
 
+async fn stdio_inspect(lua: Lua, value: LuaValue) -> LuaResult<()> {
+    inspect(lua, value).await
+}
+
 async fn stdio_prompt(lua: Lua, options: PromptOptions) -> LuaResult<PromptResult> {
     lua.spawn_blocking(move || prompt(options))
         .await
@@ -0,0 +1,243 @@
+use std::{
+    future::{Future, poll_fn},
+    pin::Pin,
+    task::Poll,
+};
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, ThreadId};
+
+use lune_utils::TableBuilder;
+
+/*
+    Each branch runs in its own thread, wrapped in a pcall so that errors
+    are given back to us as values, and not also reported by the scheduler
+*/
+const BRANCH_IMPL_LUA: &str = "return pcall(...)";
+
+enum BranchResult {
+    Completed(LuaMultiValue),
+    Failed(LuaValue),
+}
+
+struct Branch {
+    thread: LuaThread,
+    id: ThreadId,
+    finished: bool,
+}
+
+/**
+    A set of functions, each running concurrently in its own thread.
+
+    Any branches that have not finished are cancelled once this is dropped,
+    which also happens if the thread waiting on the branches is cancelled.
+*/
+struct Branches {
+    lua: Lua,
+    branches: Vec<Branch>,
+}
+
+impl Branches {
+    fn spawn(lua: &Lua, name: &str, functions: Vec<LuaFunction>) -> LuaResult<Self> {
+        if functions.is_empty() {
+            return Err(LuaError::runtime(format!(
+                "{name} requires at least one function"
+            )));
+        }
+
+        let runner = lua
+            .load(BRANCH_IMPL_LUA)
+            .set_name(name)
+            .set_environment(
+                TableBuilder::new(lua.clone())?
+                    .with_value("pcall", lua.globals().get::<LuaFunction>("pcall")?)?
+                    .build_readonly()?,
+            )
+            .into_function()?;
+
+        let mut branches = Vec::with_capacity(functions.len());
+        for function in functions {
+            let thread = lua.create_thread(runner.clone())?;
+            let id = lua.push_thread_front(&thread, function)?;
+            lua.track_thread(id);
+            branches.push(Branch {
+                thread,
+                id,
+                finished: false,
+            });
+        }
+
+        Ok(Self {
+            lua: lua.clone(),
+            branches,
+        })
+    }
+
+    fn ids(&self) -> Vec<ThreadId> {
+        self.branches.iter().map(|b| b.id).collect()
+    }
+
+    fn take_result(&mut self, index: usize) -> LuaResult<BranchResult> {
+        let branch = &mut self.branches[index];
+        branch.finished = true;
+        match self.lua.get_thread_result(branch.id) {
+            Some(Ok(mut values)) => {
+                if matches!(values.pop_front(), Some(LuaValue::Boolean(true))) {
+                    Ok(BranchResult::Completed(values))
+                } else {
+                    Ok(BranchResult::Failed(values.pop_front().unwrap_or_default()))
+                }
+            }
+            Some(Err(e)) => Ok(BranchResult::Failed(LuaValue::Error(Box::new(e)))),
+            None => Err(LuaError::runtime("task finished without a result")),
+        }
+    }
+
+    fn cancel_remaining(&mut self) -> LuaResult<()> {
+        for branch in &mut self.branches {
+            if !branch.finished {
+                branch.finished = true;
+                self.lua.cancel_thread(&branch.thread)?;
+                self.lua.get_thread_result(branch.id);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Branches {
+    fn drop(&mut self) {
+        // NOTE: We can not untrack threads here, since this may happen after
+        // the scheduler has stopped, but cancelling is always safe to do
+        for branch in &self.branches {
+            if !branch.finished {
+                let _ = self.lua.cancel_thread(&branch.thread);
+            }
+        }
+    }
+}
+
+/**
+    Waits for the next of the given listeners to resolve, and returns its index.
+
+    Listeners that have already resolved are set to `None`, and must not all be `None`.
+*/
+async fn next_finished<F>(listeners: &mut [Option<Pin<Box<F>>>]) -> usize
+where
+    F: Future<Output = ()>,
+{
+    poll_fn(|cx| {
+        for (index, listener) in listeners.iter_mut().enumerate() {
+            if let Some(fut) = listener
+                && fut.as_mut().poll(cx).is_ready()
+            {
+                *listener = None;
+                return Poll::Ready(index);
+            }
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+fn into_error(value: LuaValue) -> LuaError {
+    match value {
+        LuaValue::Error(e) => *e,
+        LuaValue::String(s) => LuaError::runtime(s.to_string_lossy()),
+        value => LuaError::runtime(
+            value
+                .to_string()
+                .unwrap_or_else(|_| value.type_name().to_string()),
+        ),
+    }
+}
+
+/**
+    Implementation of `task.race`.
+
+    Returns the index of the first function to finish, followed by its return values,
+    or rethrows its error. All other functions are cancelled once the first one finishes.
+*/
+pub async fn race(lua: Lua, functions: LuaVariadic<LuaFunction>) -> LuaResult<LuaMultiValue> {
+    let inner_lua = lua.clone();
+    lua.with_cancellation(async move {
+        let lua = inner_lua;
+        let mut branches = Branches::spawn(&lua, "task.race", functions.into_iter().collect())?;
+
+        let mut listeners = branches
+            .ids()
+            .into_iter()
+            .map(|id| Some(Box::pin(lua.wait_for_thread(id))))
+            .collect::<Vec<_>>();
+
+        let index = next_finished(&mut listeners).await;
+        let result = branches.take_result(index)?;
+        branches.cancel_remaining()?;
+
+        match result {
+            BranchResult::Completed(mut values) => {
+                values.push_front((index + 1).into_lua(&lua)?);
+                Ok(values)
+            }
+            BranchResult::Failed(error) => Err(into_error(error)),
+        }
+    })
+    .await
+}
+
+/**
+    Implementation of `task.all`.
+
+    Waits for all functions to finish, and returns whether all of them completed successfully,
+    together with a table of results, one for each function. If any of the functions fail, all
+    remaining functions are cancelled, and their results will have the status `"cancelled"`.
+*/
+pub async fn all(lua: Lua, functions: LuaVariadic<LuaFunction>) -> LuaResult<(bool, LuaTable)> {
+    let inner_lua = lua.clone();
+    lua.with_cancellation(async move {
+        let lua = inner_lua;
+        let mut branches = Branches::spawn(&lua, "task.all", functions.into_iter().collect())?;
+
+        let mut listeners = branches
+            .ids()
+            .into_iter()
+            .map(|id| Some(Box::pin(lua.wait_for_thread(id))))
+            .collect::<Vec<_>>();
+
+        let mut results = (0..listeners.len()).map(|_| None).collect::<Vec<_>>();
+        let mut success = true;
+        for _ in 0..listeners.len() {
+            let index = next_finished(&mut listeners).await;
+            let result = branches.take_result(index)?;
+            let failed = matches!(result, BranchResult::Failed(_));
+            results[index] = Some(result);
+            if failed {
+                success = false;
+                branches.cancel_remaining()?;
+                break;
+            }
+        }
+
+        let table = lua.create_table_with_capacity(results.len(), 0)?;
+        for result in results {
+            let entry = lua.create_table_with_capacity(0, 2)?;
+            match result {
+                Some(BranchResult::Completed(values)) => {
+                    entry.set("status", "completed")?;
+                    entry.set("values", lua.create_sequence_from(values)?)?;
+                }
+                Some(BranchResult::Failed(error)) => {
+                    entry.set("status", "failed")?;
+                    entry.set("error", error)?;
+                }
+                None => {
+                    entry.set("status", "cancelled")?;
+                }
+            }
+            table.push(entry)?;
+        }
+
+        Ok((success, table))
+    })
+    .await
+}
@@ -6,10 +6,12 @@ use async_io::Timer;
 use futures_lite::future::yield_now;
 
 use mlua::prelude::*;
-use mlua_luau_scheduler::Functions;
+use mlua_luau_scheduler::{Functions, LuaSchedulerExt, ThreadId};
 
 use lune_utils::TableBuilder;
 
+mod combinators;
+
 const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
 
 /**
@@ -45,9 +47,12 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
         .into_function()?;
 
     TableBuilder::new(lua)?
+        .with_async_function("all", combinators::all)?
         .with_value("cancel", fns.cancel)?
         .with_value("defer", fns.defer)?
         .with_value("delay", task_delay)?
+        .with_function("isCancelled", is_cancelled)?
+        .with_async_function("race", combinators::race)?
         .with_value("spawn", fns.spawn)?
         .with_value("wait", task_wait)?
         .build_readonly()
@@ -60,11 +65,16 @@ return defer(function(...)
 end, ...)
 ";
 
+fn is_cancelled(lua: &Lua, thread: Option<LuaThread>) -> LuaResult<bool> {
+    let thread = thread.unwrap_or_else(|| lua.current_thread());
+    Ok(lua.is_thread_cancelled(ThreadId::from(&thread)))
+}
+
 async fn wait(lua: Lua, secs: Option<f64>) -> LuaResult<f64> {
     // NOTE: We must guarantee that the task.wait API always yields
     // from a lua perspective, even if sleep/timer completes instantly
     yield_now().await;
-    wait_inner(lua, secs).await
+    lua.with_cancellation(wait_inner(lua.clone(), secs)).await
 }
 
 async fn wait_inner(_: Lua, secs: Option<f64>) -> LuaResult<f64> {
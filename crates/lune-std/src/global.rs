@@ -7,16 +7,20 @@ use mlua::prelude::*;
 */
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub enum LuneStandardGlobal {
+    Build,
+    Bundle,
     #[cfg(feature = "roblox")]
     CFrame,
     #[cfg(feature = "roblox")]
     Color3,
+    Defines,
     Executable,
     GTable,
     #[cfg(feature = "roblox")]
     NumberRange,
     Print,
     Require,
+    Runtime,
     Script,
     #[cfg(feature = "task")]
     Task,
@@ -32,8 +36,9 @@ impl LuneStandardGlobal {
     /**
         All available standard globals.
 
-        Note: `Executable` is not included here because it needs to be injected
-        after app_data is set (it reads the executable path from app_data).
+        Note: `Build`, `Bundle`, `Defines` and `Executable` are not included here because they need to be
+        injected after app_data is set (they read build information, bundled files, defined values and the
+        executable path from app_data).
     */
     pub const ALL: &'static [Self] = &[
         #[cfg(feature = "roblox")]
@@ -45,6 +50,7 @@ impl LuneStandardGlobal {
         Self::NumberRange,
         Self::Print,
         Self::Require,
+        Self::Runtime,
         Self::Script,
         #[cfg(feature = "task")]
         Self::Task,
@@ -62,16 +68,20 @@ impl LuneStandardGlobal {
     #[must_use]
     pub fn name(&self) -> &'static str {
         match self {
+            Self::Build => "_BUILD",
+            Self::Bundle => "bundle",
             #[cfg(feature = "roblox")]
             Self::CFrame => "CFrame",
             #[cfg(feature = "roblox")]
             Self::Color3 => "Color3",
+            Self::Defines => "_DEFINES",
             Self::Executable => "executable",
             Self::GTable => "_G",
             #[cfg(feature = "roblox")]
             Self::NumberRange => "NumberRange",
             Self::Print => "print",
             Self::Require => "require",
+            Self::Runtime => "runtime",
             Self::Script => "script",
             #[cfg(feature = "task")]
             Self::Task => "task",
@@ -95,16 +105,20 @@ impl LuneStandardGlobal {
     #[allow(unreachable_patterns)]
     pub fn create(&self, lua: Lua) -> LuaResult<LuaValue> {
         let res = match self {
+            Self::Build => crate::globals::build::create(lua),
+            Self::Bundle => crate::globals::bundle::create(lua),
             #[cfg(feature = "roblox")]
             Self::CFrame => crate::globals::roblox_globals::create_cframe(lua),
             #[cfg(feature = "roblox")]
             Self::Color3 => crate::globals::roblox_globals::create_color3(lua),
+            Self::Defines => crate::globals::defines::create(lua),
             Self::Executable => crate::globals::executable::create(lua),
             Self::GTable => crate::globals::g_table::create(lua),
             #[cfg(feature = "roblox")]
             Self::NumberRange => crate::globals::roblox_globals::create_number_range(lua),
             Self::Print => crate::globals::print::create(lua),
             Self::Require => crate::globals::require::create(lua),
+            Self::Runtime => crate::globals::runtime::create(lua),
             Self::Script => crate::globals::script::create(lua),
             #[cfg(feature = "task")]
             Self::Task => crate::globals::task::create(lua),
@@ -130,16 +144,20 @@ impl FromStr for LuneStandardGlobal {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let low = s.trim().to_ascii_lowercase();
         Ok(match low.as_str() {
+            "_build" => Self::Build,
+            "bundle" => Self::Bundle,
             #[cfg(feature = "roblox")]
             "cframe" => Self::CFrame,
             #[cfg(feature = "roblox")]
             "color3" => Self::Color3,
+            "_defines" => Self::Defines,
             "executable" => Self::Executable,
             "_g" => Self::GTable,
             #[cfg(feature = "roblox")]
             "numberrange" => Self::NumberRange,
             "print" => Self::Print,
             "require" => Self::Require,
+            "runtime" => Self::Runtime,
             "script" => Self::Script,
             #[cfg(feature = "task")]
             "task" => Self::Task,
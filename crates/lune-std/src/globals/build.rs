@@ -0,0 +1,17 @@
+use mlua::prelude::*;
+
+use lune_utils::build_info::BuildInfo;
+
+/// Creates the `_BUILD` global.
+///
+/// Gives information about how the standalone executable was built, such as
+/// when, for which target, and from which commit, stored in app data by the
+/// runtime. The table is empty when not running as a standalone executable.
+pub fn create(lua: Lua) -> LuaResult<LuaValue> {
+    let table = match lua.app_data_ref::<BuildInfo>() {
+        Some(info) => info.to_table(&lua)?,
+        None => lua.create_table()?,
+    };
+    table.set_readonly(true);
+    Ok(LuaValue::Table(table))
+}
@@ -0,0 +1,111 @@
+use mlua::prelude::*;
+
+use lune_utils::TableBuilder;
+
+use super::require::{BundledFiles, follow_bundled_link, normalize_to_bundle_key};
+
+/// Creates the `bundle` global.
+///
+/// Gives access to the files bundled into a standalone executable - `bundle.list`
+/// returns the paths of all bundled files, `bundle.exists` checks if a file was
+/// bundled, and `bundle.read` reads one. Paths are relative to the project root,
+/// such as `/templates/page.html`, and a leading `/` or `./` is optional.
+///
+/// When not running as a standalone executable, there are no bundled files.
+pub fn create(lua: Lua) -> LuaResult<LuaValue> {
+    TableBuilder::new(lua)?
+        .with_function("list", bundle_list)?
+        .with_function("exists", bundle_exists)?
+        .with_function("read", bundle_read)?
+        .build_readonly()
+        .map(LuaValue::Table)
+}
+
+/// Converts a path given by a script into the key of a bundled file, following links
+fn bundle_key(lua: &Lua, path: &str) -> String {
+    let normalized = normalize_to_bundle_key(path);
+    let relative = normalized.strip_prefix("./").unwrap_or(&normalized);
+    let key = if relative.starts_with('/') {
+        relative.to_string()
+    } else {
+        format!("/{relative}")
+    };
+    follow_bundled_link(lua, key)
+}
+
+fn bundle_list(lua: &Lua, (): ()) -> LuaResult<Vec<String>> {
+    let mut paths = lua
+        .app_data_ref::<BundledFiles>()
+        .map(|files| files.keys().cloned().collect::<Vec<_>>())
+        .unwrap_or_default();
+    paths.sort();
+    Ok(paths)
+}
+
+fn bundle_exists(lua: &Lua, path: String) -> LuaResult<bool> {
+    let key = bundle_key(lua, &path);
+    Ok(lua
+        .app_data_ref::<BundledFiles>()
+        .is_some_and(|files| files.contains_key(&key)))
+}
+
+fn bundle_read(lua: &Lua, path: String) -> LuaResult<LuaString> {
+    let key = bundle_key(lua, &path);
+    let contents = lua
+        .app_data_ref::<BundledFiles>()
+        .and_then(|files| files.get(&key).cloned());
+    match contents {
+        Some(contents) => lua.create_string(contents),
+        None => Err(LuaError::runtime(format!(
+            "no file was bundled at '{path}'"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn keys_of_script_paths() {
+        let lua = Lua::new();
+        assert_eq!(
+            bundle_key(&lua, "templates/page.html"),
+            "/templates/page.html"
+        );
+        assert_eq!(
+            bundle_key(&lua, "./templates/page.html"),
+            "/templates/page.html"
+        );
+        assert_eq!(
+            bundle_key(&lua, "/templates/page.html"),
+            "/templates/page.html"
+        );
+        assert_eq!(
+            bundle_key(&lua, r"templates\page.html"),
+            "/templates/page.html"
+        );
+    }
+
+    #[test]
+    fn reads_bundled_files() {
+        let lua = Lua::new();
+        lua.set_app_data::<BundledFiles>(HashMap::from([
+            ("/data/b.json".to_string(), b"{}".to_vec().into()),
+            ("/data/a.txt".to_string(), b"hello".to_vec().into()),
+        ]));
+        assert_eq!(
+            bundle_list(&lua, ()).unwrap(),
+            vec!["/data/a.txt", "/data/b.json"]
+        );
+        assert!(bundle_exists(&lua, "data/a.txt".to_string()).unwrap());
+        assert!(!bundle_exists(&lua, "data/c.txt".to_string()).unwrap());
+        assert_eq!(
+            bundle_read(&lua, "./data/a.txt".to_string()).unwrap(),
+            "hello"
+        );
+        assert!(bundle_read(&lua, "data/c.txt".to_string()).is_err());
+    }
+}
@@ -0,0 +1,27 @@
+use std::collections::BTreeMap;
+
+use mlua::prelude::*;
+
+/// Values defined when building a standalone executable,
+/// such as `VERSION=1.2.3`, stored in app data by the runtime.
+#[derive(Debug, Clone, Default)]
+pub struct Defines(pub BTreeMap<String, String>);
+
+/// Creates the `_DEFINES` global.
+///
+/// Values of `true` and `false` are given as booleans, and all other values
+/// as strings. The table is empty when no values have been defined.
+pub fn create(lua: Lua) -> LuaResult<LuaValue> {
+    let table = lua.create_table()?;
+    if let Some(defines) = lua.app_data_ref::<Defines>() {
+        for (key, value) in &defines.0 {
+            match value.as_str() {
+                "true" => table.raw_set(key.as_str(), true)?,
+                "false" => table.raw_set(key.as_str(), false)?,
+                value => table.raw_set(key.as_str(), value)?,
+            }
+        }
+    }
+    table.set_readonly(true);
+    Ok(LuaValue::Table(table))
+}
@@ -0,0 +1,183 @@
+/*!
+    Hot reloading of required modules, for development.
+
+    When enabled, the files of all modules loaded from disk using `require` are
+    watched for changes. Once the file of a module changes, its cache entry is
+    invalidated, and it is required again in a new thread. If the new module
+    is a table with an `__onReload` function, that function is then called
+    with the module that it replaced, which lets modules move state over,
+    or update the previous module in place for scripts that still use it.
+
+    Only the changed modules are run again - modules that required them keep
+    using the values they were given, unless the changed module updates them.
+    If a module errors while being reloaded, the previous module is kept.
+
+    Files are watched by polling their metadata, and the runtime keeps running
+    for as long as any modules are being watched.
+*/
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use async_io::Timer;
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, LuaSpawnExt};
+
+use crate::globals::require::{forget_missing_modules, get_module_cache};
+
+/// Enables hot reloading of required modules, stored in app data by the runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HotReload {
+    /// How often the files of watched modules are checked for changes.
+    pub interval: Duration,
+}
+
+impl Default for HotReload {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(250),
+        }
+    }
+}
+
+/// The state of a file that was last seen, which changes when the file is written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileStamp {
+    fn read(path: &Path) -> Option<Self> {
+        let meta = fs::metadata(path).ok()?;
+        Some(Self {
+            modified: meta.modified().ok(),
+            len: meta.len(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct WatchedModule {
+    cache_key: String,
+    stamp: FileStamp,
+}
+
+/// All modules that are watched, by the paths of their files
+#[derive(Debug, Default)]
+struct WatchedModules(HashMap<PathBuf, WatchedModule>);
+
+/**
+    Starts watching the file of a module that was just loaded, if hot reloading is
+    enabled, so that it is reloaded once the file changes. Bundled modules, which do
+    not exist on disk, are never watched.
+*/
+pub(crate) fn watch_module(lua: &Lua, cache_key: &str, path: &Path) -> LuaResult<()> {
+    let Some(config) = lua.app_data_ref::<HotReload>().map(|config| *config) else {
+        return Ok(());
+    };
+    let Some(stamp) = FileStamp::read(path) else {
+        return Ok(());
+    };
+    let module = WatchedModule {
+        cache_key: cache_key.to_string(),
+        stamp,
+    };
+
+    if let Some(mut watched) = lua.app_data_mut::<WatchedModules>() {
+        watched.0.insert(path.to_path_buf(), module);
+        return Ok(());
+    }
+
+    // This is the first watched module, start polling for changes
+    let mut watched = WatchedModules::default();
+    watched.0.insert(path.to_path_buf(), module);
+    lua.set_app_data(watched);
+
+    let reload = lua.create_async_function(reload_module)?;
+    let inner = lua.clone();
+    lua.spawn_local(async move {
+        loop {
+            Timer::after(config.interval).await;
+            let changed = changed_modules(&inner);
+            // Modules that could not be found before may have been created since
+            if !changed.is_empty() {
+                forget_missing_modules(&inner);
+            }
+            for (cache_key, path) in changed {
+                let path = path.to_string_lossy().to_string();
+                // NOTE: This can only fail when out of memory
+                let _ = inner.push_thread_back(reload.clone(), (cache_key, path));
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Returns the cache keys and paths of all watched modules whose files changed since last checked
+fn changed_modules(lua: &Lua) -> Vec<(String, PathBuf)> {
+    let Some(mut watched) = lua.app_data_mut::<WatchedModules>() else {
+        return Vec::new();
+    };
+    let mut changed = Vec::new();
+    for (path, module) in &mut watched.0 {
+        // Files that were removed, or are being replaced, are checked again later
+        let Some(stamp) = FileStamp::read(path) else {
+            continue;
+        };
+        if stamp != module.stamp {
+            module.stamp = stamp;
+            changed.push((module.cache_key.clone(), path.clone()));
+        }
+    }
+    changed
+}
+
+/// Invalidates and requires a changed module again, calling its `__onReload` function
+async fn reload_module(lua: Lua, (cache_key, path): (String, String)) -> LuaResult<()> {
+    let cache = get_module_cache(&lua)?;
+    let previous = cache.get::<LuaValue>(cache_key.as_str())?;
+    cache.set(cache_key.as_str(), LuaValue::Nil)?;
+
+    let require = lua.globals().get::<LuaFunction>("__lune_async_require")?;
+    let module = match require.call_async::<LuaValue>(path).await {
+        Ok(module) => module,
+        Err(err) => {
+            cache.set(cache_key.as_str(), previous)?;
+            return Err(err);
+        }
+    };
+
+    if let LuaValue::Table(table) = &module
+        && let LuaValue::Function(on_reload) = table.get::<LuaValue>("__onReload")?
+    {
+        on_reload.call_async::<()>(previous).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stamps_change_with_contents() {
+        let dir = std::env::temp_dir().join(format!("lune-hot-reload-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("module.luau");
+
+        fs::write(&path, "return 1").unwrap();
+        let before = FileStamp::read(&path).unwrap();
+        assert_eq!(FileStamp::read(&path), Some(before));
+
+        fs::write(&path, "return 12").unwrap();
+        assert_ne!(FileStamp::read(&path), Some(before));
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(FileStamp::read(&path), None);
+    }
+}
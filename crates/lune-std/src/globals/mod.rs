@@ -1,9 +1,15 @@
+pub mod build;
+pub mod bundle;
+pub mod defines;
 pub mod executable;
 pub mod g_table;
+pub mod hot_reload;
 pub mod print;
+pub mod remote;
 pub mod require;
 #[cfg(feature = "roblox")]
 pub mod roblox_globals;
+pub mod runtime;
 pub mod script;
 #[cfg(feature = "task")]
 pub mod task;
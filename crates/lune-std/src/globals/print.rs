@@ -1,19 +1,43 @@
 use std::io::Write;
 
-use lune_utils::fmt::{ValueFormatConfig, pretty_format_multi_value};
+use lune_utils::fmt::{FormatLimits, ValueFormatConfig, pretty_format_multi_value};
 use mlua::prelude::*;
 
 const FORMAT_CONFIG: ValueFormatConfig = ValueFormatConfig::new()
     .with_max_depth(4)
     .with_colors_enabled(true);
 
+/// Creates the `print` global.
+///
+/// Huge values are elided using the [`FormatLimits`] stored in app
+/// data by the runtime, or the default limits if none are stored, and
+/// `print.full` may be used to print values without any limits.
 pub fn create(lua: Lua) -> LuaResult<LuaValue> {
-    let f = lua.create_function(|_: &Lua, args: LuaMultiValue| {
-        let formatted = format!("{}\n", pretty_format_multi_value(&args, &FORMAT_CONFIG));
-        let mut stdout = std::io::stdout();
-        stdout.write_all(formatted.as_bytes())?;
-        stdout.flush()?;
-        Ok(())
+    let full =
+        lua.create_function(|_, args: LuaMultiValue| write_formatted(&args, &FORMAT_CONFIG))?;
+    let call = lua.create_function(|lua, (_, args): (LuaValue, LuaMultiValue)| {
+        let limits = lua
+            .app_data_ref::<FormatLimits>()
+            .map(|limits| *limits)
+            .unwrap_or_default();
+        write_formatted(&args, &FORMAT_CONFIG.with_limits(limits))
     })?;
-    f.into_lua(&lua)
+
+    let meta = lua.create_table()?;
+    meta.raw_set("__call", call)?;
+    meta.set_readonly(true);
+
+    let print = lua.create_table()?;
+    print.raw_set("full", full)?;
+    print.set_metatable(Some(meta))?;
+    print.set_readonly(true);
+    Ok(LuaValue::Table(print))
+}
+
+fn write_formatted(args: &LuaMultiValue, config: &ValueFormatConfig) -> LuaResult<()> {
+    let formatted = format!("{}\n", pretty_format_multi_value(args, config));
+    let mut stdout = std::io::stdout();
+    stdout.write_all(formatted.as_bytes())?;
+    stdout.flush()?;
+    Ok(())
 }
@@ -0,0 +1,83 @@
+use mlua::prelude::*;
+
+use lune_utils::remote::{Lockfile, RemoteCache, RemoteRequires, hash_contents};
+
+/// Get the source of the remote module with the given URL.
+///
+/// Modules recorded in the lockfile are read from the cache, and only downloaded if missing
+/// from it, in which case they must still match the recorded hash - other modules are always
+/// downloaded, and their hashes recorded, so that later runs are reproducible and offline.
+pub(crate) async fn fetch_remote_module(lua: &Lua, url: &str) -> LuaResult<Vec<u8>> {
+    let Some(options) = lua.app_data_ref::<RemoteRequires>().map(|o| o.clone()) else {
+        return Err(LuaError::runtime(format!(
+            "cannot require remote module '{url}' - requiring modules using \
+            their URLs must be enabled, such as using `lune run --allow-url-requires`"
+        )));
+    };
+
+    let cache = RemoteCache::new(&options.cache_dir);
+    let mut lockfile = Lockfile::read(&options.lockfile).map_err(|e| {
+        LuaError::runtime(format!(
+            "cannot read lockfile '{}': {e}",
+            options.lockfile.display()
+        ))
+    })?;
+
+    let locked = lockfile.get(url).map(ToString::to_string);
+    if let Some(contents) = locked.as_deref().and_then(|hash| cache.read(hash)) {
+        return Ok(contents);
+    }
+
+    let contents = download(url).await?;
+    let hash = hash_contents(&contents);
+    match locked {
+        Some(locked) if locked != hash => {
+            return Err(LuaError::runtime(format!(
+                "remote module '{url}' does not match the hash in '{}'\
+                \nExpected: {locked}\
+                \nReceived: {hash}",
+                options.lockfile.display()
+            )));
+        }
+        Some(_) => {}
+        None => {
+            lockfile.insert(url, hash);
+            lockfile.write(&options.lockfile).map_err(|e| {
+                LuaError::runtime(format!(
+                    "cannot write lockfile '{}': {e}",
+                    options.lockfile.display()
+                ))
+            })?;
+        }
+    }
+
+    // The module was verified, failing to cache it only means downloading it again
+    cache.write(&contents).ok();
+
+    Ok(contents)
+}
+
+#[cfg(feature = "net")]
+async fn download(url: &str) -> LuaResult<Vec<u8>> {
+    let parsed = url
+        .parse()
+        .map_err(|e| LuaError::runtime(format!("invalid remote module url '{url}': {e}")))?;
+    let res = lune_std_net::fetch(parsed, None, None, None)
+        .await
+        .map_err(|e| LuaError::runtime(format!("cannot download '{url}': {e}")))?;
+    if !res.status_ok() {
+        return Err(LuaError::runtime(format!(
+            "cannot download '{url}': request was not successful ({})",
+            res.status_code()
+        )));
+    }
+    Ok(res.body().to_vec())
+}
+
+#[cfg(not(feature = "net"))]
+#[allow(clippy::unused_async)]
+async fn download(url: &str) -> LuaResult<Vec<u8>> {
+    Err(LuaError::runtime(format!(
+        "cannot download '{url}' - the net library is not available in this build of Lune"
+    )))
+}
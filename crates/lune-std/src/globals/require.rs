@@ -5,28 +5,64 @@ use std::rc::Rc;
 
 use async_channel::{Receiver, Sender};
 use async_fs::read as read_file;
+use console::style;
 use mlua::prelude::*;
 use mlua_luau_scheduler::LuaSchedulerExt;
-use serde::Deserialize;
 
+use crate::globals::hot_reload::watch_module;
+use crate::globals::remote::fetch_remote_module;
 use crate::globals::script::{ScriptReference, pop_script_path, push_script_path};
 use crate::require::RequireResolver;
+use lune_utils::bundled::BundledFile;
+use lune_utils::capabilities::{Capabilities, CapabilityMode, parse_requires_directives};
+use lune_utils::luaurc::{LuauConfig, MergedAliases};
+use lune_utils::namespace::{NamespaceModule, Namespaces};
 use lune_utils::path::{
     LuauModulePath, clean_path_and_make_absolute,
     constants::{FILE_CHUNK_PREFIX, FILE_NAME_CONFIG},
-    relative_path_normalize,
+    is_data_file, relative_path_normalize,
 };
+use lune_utils::platform::Platform;
+use lune_utils::remote::{is_remote_path, normalize_remote_url, resolve_remote_relative};
+use lune_utils::require_allow_list::RequireAllowList;
+use lune_utils::require_graph::RequireGraph;
+use lune_utils::require_trace::{RequireTrace, TracedRequire};
 
 type RequireResult = LuaResult<LuaMultiValue>;
 type RequireResultSender = Sender<RequireResult>;
 type RequireResultReceiver = Receiver<RequireResult>;
 
 /// Type for bundled files from standalone executables
-type BundledFiles = HashMap<String, Vec<u8>>;
+pub(crate) type BundledFiles = HashMap<String, BundledFile>;
 
 /// Type for bundled aliases from standalone executables
 type BundledAliases = HashMap<String, String>;
 
+/// Bundled links from standalone executables: path -> path of the bundled file
+#[derive(Debug, Clone, Default)]
+pub struct BundledLinks(pub HashMap<String, String>);
+
+/// Aliases given by the host application, such as using `lune run --alias`,
+/// which take precedence over aliases in .luaurc files: alias name -> path
+#[derive(Debug, Clone, Default)]
+pub struct RequireAliases(pub HashMap<String, PathBuf>);
+
+/// Requires of bundled files from standalone executables, linked when building,
+/// which are looked up instead of resolving their paths, see [`RequireGraph`]
+#[derive(Debug, Clone, Default)]
+pub struct LinkedRequires {
+    graph: RequireGraph,
+    numbers: HashMap<String, usize>,
+}
+
+impl LinkedRequires {
+    #[must_use]
+    pub fn new(graph: RequireGraph) -> Self {
+        let numbers = graph.numbers();
+        Self { graph, numbers }
+    }
+}
+
 /// Normalize path separators to forward slashes for consistent bundled
 /// file lookups. Bundled keys always use forward slashes, but on Windows
 /// path operations produce backslashes.
@@ -41,7 +77,7 @@ fn normalize_separators(path: &str) -> String {
 /// corrupt these virtual paths by prepending drive letters (e.g.,
 /// `D:/Packages/...`). This function strips any drive prefix and
 /// ensures the path starts with `/`.
-fn normalize_to_bundle_key(path: &str) -> String {
+pub(crate) fn normalize_to_bundle_key(path: &str) -> String {
     let normalized = normalize_separators(path);
 
     // Strip Windows drive prefix (e.g., "C:/Packages/..." -> "/Packages/...")
@@ -123,13 +159,23 @@ fn resolve_bundled_relative(caller: &Path, relative: &Path) -> PathBuf {
     PathBuf::from(cleaned)
 }
 
+/// Follow a bundled link for a bundle key, if there is one.
+///
+/// Files that were reached through more than one path when bundling, such as
+/// through symlinks, are only bundled once, and all other paths link to it.
+pub(crate) fn follow_bundled_link(lua: &Lua, key: String) -> String {
+    lua.app_data_ref::<BundledLinks>()
+        .and_then(|links| links.0.get(&key).cloned())
+        .unwrap_or(key)
+}
+
 /// Try to get bundled source for a path from app_data
-fn get_bundled_source(lua: &Lua, path: &Path) -> Option<Vec<u8>> {
+pub(crate) fn get_bundled_source(lua: &Lua, path: &Path) -> Option<BundledFile> {
     let bundled = lua.app_data_ref::<BundledFiles>()?;
 
     // Normalize to bundle key format, stripping any Windows drive prefix
     let key = normalize_to_bundle_key(&path.display().to_string());
-    if let Some(source) = bundled.get(&key) {
+    if let Some(source) = bundled.get(&follow_bundled_link(lua, key)) {
         return Some(source.clone());
     }
 
@@ -146,51 +192,53 @@ fn get_bundled_source(lua: &Lua, path: &Path) -> Option<Vec<u8>> {
 
 /// Try to resolve a module path in bundled files.
 /// Returns the resolved path if found in bundled files.
-/// This handles .luau/.lua extensions and init.luau patterns.
-fn resolve_bundled_module(lua: &Lua, module_path: &Path) -> Option<PathBuf> {
+/// This handles .luau/.lua extensions, init.luau patterns, and bundled links.
+pub(crate) fn resolve_bundled_module(lua: &Lua, module_path: &Path) -> Option<PathBuf> {
     let bundled = lua.app_data_ref::<BundledFiles>()?;
     // Normalize to bundle key format, stripping Windows drive prefixes
     let base = normalize_to_bundle_key(&module_path.display().to_string());
 
-    // Try exact path first
-    if bundled.contains_key(&base) {
-        return Some(PathBuf::from(&base));
-    }
-
-    // Try with .luau extension
-    let with_luau = format!("{}.luau", base);
-    if bundled.contains_key(&with_luau) {
-        return Some(PathBuf::from(&with_luau));
-    }
-
-    // Try with .lua extension
-    let with_lua = format!("{}.lua", base);
-    if bundled.contains_key(&with_lua) {
-        return Some(PathBuf::from(&with_lua));
-    }
-
-    // Try as directory with init.luau
-    let init_luau = format!("{}/init.luau", base);
-    if bundled.contains_key(&init_luau) {
-        return Some(PathBuf::from(&init_luau));
-    }
-
-    // Try as directory with init.lua
-    let init_lua = format!("{}/init.lua", base);
-    if bundled.contains_key(&init_lua) {
-        return Some(PathBuf::from(&init_lua));
-    }
+    // Try the exact path first, then with extensions, then as a directory with an init file
+    let candidates = [
+        base.clone(),
+        format!("{base}.luau"),
+        format!("{base}.lua"),
+        format!("{base}/init.luau"),
+        format!("{base}/init.lua"),
+    ];
+    candidates
+        .into_iter()
+        .map(|candidate| follow_bundled_link(lua, candidate))
+        .find(|candidate| bundled.contains_key(candidate))
+        .map(PathBuf::from)
+}
 
-    None
+/// Look up a require of a bundled module in the requires linked when building, if any.
+///
+/// The exact require path given by the module is looked up, so that linked requires
+/// never need to resolve paths, search for `.luaurc` files, or check for files.
+fn get_linked_require(
+    lua: &Lua,
+    caller_path: Option<&Path>,
+    require_path: &str,
+) -> Option<PathBuf> {
+    let linked = lua.app_data_ref::<LinkedRequires>()?;
+    let caller = normalize_to_bundle_key(&caller_path?.display().to_string());
+    let module = *linked.numbers.get(&caller)?;
+    linked
+        .graph
+        .resolve(module, require_path)
+        .map(PathBuf::from)
 }
 
 /// Try to resolve an alias from bundled aliases
 fn get_bundled_alias(lua: &Lua, alias: &str) -> Option<PathBuf> {
     let bundled = lua.app_data_ref::<BundledAliases>()?;
 
-    // Try exact match first
+    // Try exact match first, following any link so that modules
+    // reached through several paths are always loaded only once
     if let Some(canonical) = bundled.get(alias) {
-        return Some(PathBuf::from(canonical));
+        return Some(PathBuf::from(follow_bundled_link(lua, canonical.clone())));
     }
 
     None
@@ -205,21 +253,43 @@ const MODULE_CACHE_KEY: &str = "__lune_require_cache";
 /// Registry key for the chunk function cache (used by debug.getcoverage)
 pub const CHUNK_CACHE_KEY: &str = "__lune_chunk_cache";
 
+/// Aliases merged from the .luaurc files and package manifests of a directory and its
+/// ancestors, by directory - shared with the app data of the Lua state, so that they
+/// are forgotten along with the [`MissingModules`] whenever files may have changed.
+#[derive(Debug, Clone, Default)]
+struct AliasCache(Rc<RefCell<HashMap<PathBuf, Rc<MergedAliases>>>>);
+
 /// Shared state for tracking pending requires to avoid duplicate loading.
 #[derive(Debug, Clone)]
 struct RequireState {
     tx: Rc<RefCell<HashMap<PathBuf, RequireResultSender>>>,
     rx: Rc<RefCell<HashMap<PathBuf, RequireResultReceiver>>>,
+    aliases: AliasCache,
 }
 
 impl RequireState {
-    fn new() -> Self {
+    fn new(lua: &Lua) -> Self {
+        let aliases = AliasCache::default();
+        lua.set_app_data(aliases.clone());
         Self {
             tx: Rc::new(RefCell::new(HashMap::new())),
             rx: Rc::new(RefCell::new(HashMap::new())),
+            aliases,
         }
     }
 
+    fn get_aliases(&self, dir: &Path) -> Rc<MergedAliases> {
+        if let Some(aliases) = self.aliases.0.borrow().get(dir) {
+            return Rc::clone(aliases);
+        }
+        let aliases = Rc::new(MergedAliases::discover(dir));
+        self.aliases
+            .0
+            .borrow_mut()
+            .insert(dir.to_path_buf(), Rc::clone(&aliases));
+        aliases
+    }
+
     fn get_pending(&self, path: &Path) -> Option<RequireResultReceiver> {
         self.rx.borrow().get(path).cloned()
     }
@@ -237,8 +307,31 @@ impl RequireState {
     }
 }
 
+/// Get the key that a resolved module is cached under, and that concurrent requires of it
+/// wait on, so that a file reached through aliases, relative paths, symlinks, or bundled
+/// links is only loaded once - the key of the linked file for bundled files, and the
+/// canonical path for files on disk.
+fn resolve_module_key(lua: &Lua, path: &Path) -> PathBuf {
+    let key = follow_bundled_link(lua, normalize_to_bundle_key(&path.display().to_string()));
+    let is_bundled = lua
+        .app_data_ref::<BundledFiles>()
+        .is_some_and(|bundled| bundled.contains_key(&key));
+    if is_bundled {
+        return PathBuf::from(key);
+    }
+    if let Ok(canonical) = path.canonicalize() {
+        return canonical;
+    }
+    // Paths that do not exist, such as modules given without their extension
+    // when invalidating them, still need the same parent directory as keys
+    match (path.parent().map(Path::canonicalize), path.file_name()) {
+        (Some(Ok(parent)), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
 /// Get or create the module cache table
-fn get_module_cache(lua: &Lua) -> LuaResult<LuaTable> {
+pub(crate) fn get_module_cache(lua: &Lua) -> LuaResult<LuaTable> {
     match lua.named_registry_value::<LuaTable>(MODULE_CACHE_KEY) {
         Ok(cache) => Ok(cache),
         Err(_) => {
@@ -249,6 +342,87 @@ fn get_module_cache(lua: &Lua) -> LuaResult<LuaTable> {
     }
 }
 
+/// Clear the module cache, so that every module is loaded again the next time it is required
+pub fn clear_module_cache(lua: &Lua) -> LuaResult<()> {
+    forget_missing_modules(lua);
+    lua.set_named_registry_value(MODULE_CACHE_KEY, lua.create_table()?)
+}
+
+/// Find the keys of all cached modules for a path - the path may be given
+/// with or without its extension, or be a directory with an init module
+fn find_cached_modules(lua: &Lua, cache: &LuaTable, path: &Path) -> LuaResult<Vec<String>> {
+    let path = resolve_module_key(lua, path);
+    let path = path.as_path();
+    let init_path = path.join("init");
+    let mut keys = Vec::new();
+    for pair in cache.pairs::<String, LuaValue>() {
+        let (key, _) = pair?;
+        let key_path = Path::new(&key);
+        let key_stem = key_path.with_extension("");
+        if key_path == path || key_stem == path || key_stem == init_path {
+            keys.push(key);
+        }
+    }
+    Ok(keys)
+}
+
+/// Remove a single module from the module cache, so that it is loaded again the next time
+/// it is required - the path may be given with or without its extension, or be a directory
+pub(crate) fn invalidate_module(lua: &Lua, path: &Path) -> LuaResult<()> {
+    forget_missing_modules(lua);
+    let cache = get_module_cache(lua)?;
+    for key in find_cached_modules(lua, &cache, path)? {
+        cache.set(key, LuaValue::Nil)?;
+    }
+    Ok(())
+}
+
+/// Create the `require.cache` table, which is a view of the module cache that modules
+/// can be read from and invalidated through, using paths relative to the calling script
+fn create_cache_view(lua: &Lua) -> LuaResult<LuaTable> {
+    fn view_path(lua: &Lua, path: &str) -> PathBuf {
+        clean_path_and_make_absolute(get_caller_dir(lua).join(path))
+    }
+
+    let index = lua.create_function(|lua, (_, path): (LuaValue, String)| {
+        let cache = get_module_cache(lua)?;
+        match find_cached_modules(lua, &cache, &view_path(lua, &path))?.first() {
+            Some(key) => cache.get::<LuaValue>(key.as_str()),
+            None => Ok(LuaValue::Nil),
+        }
+    })?;
+
+    let newindex = lua.create_function(|lua, (_, path, value): (LuaValue, String, LuaValue)| {
+        if !value.is_nil() {
+            return Err(LuaError::runtime(
+                "modules can only be removed from require.cache, by setting them to nil",
+            ));
+        }
+        invalidate_module(lua, &view_path(lua, &path))
+    })?;
+
+    // Iterate over a copy, so that modules may be invalidated while iterating
+    let iter = lua.create_function(|lua, _: LuaValue| {
+        let copy = lua.create_table()?;
+        for pair in get_module_cache(lua)?.pairs::<LuaValue, LuaValue>() {
+            let (key, value) = pair?;
+            copy.set(key, value)?;
+        }
+        let next = lua.globals().get::<LuaFunction>("next")?;
+        Ok((next, copy))
+    })?;
+
+    let metatable = lua.create_table()?;
+    metatable.set("__index", index)?;
+    metatable.set("__newindex", newindex)?;
+    metatable.set("__iter", iter)?;
+    metatable.set("__metatable", "The metatable is locked")?;
+
+    let view = lua.create_table()?;
+    view.set_metatable(Some(metatable))?;
+    Ok(view)
+}
+
 /// Get or create the chunk function cache table.
 /// Maps chunk names (e.g., "@/path/to/file.luau") to their compiled
 /// chunk functions, enabling file-level coverage via debug.getcoverage.
@@ -311,6 +485,11 @@ fn get_caller_path(lua: &Lua) -> Option<PathBuf> {
     None
 }
 
+/// Get the directory that relative requires of the calling script are resolved from.
+pub(crate) fn get_caller_dir(lua: &Lua) -> PathBuf {
+    caller_dir_of(get_caller_path(lua).as_deref())
+}
+
 /// Convert an absolute target path to a relative path from the current script.
 fn make_relative_path(current_script: &Path, target_path: &Path) -> PathBuf {
     let current_dir = current_script.parent().unwrap_or(current_script);
@@ -359,40 +538,37 @@ enum ResolveResult {
     Alias(String),
 }
 
-/// Structure representing a .luaurc configuration file
-#[derive(Debug, Deserialize, Default)]
-struct LuauConfig {
-    #[serde(default)]
-    aliases: HashMap<String, String>,
-}
-
 /// Read and parse a .luaurc file
 fn read_luaurc(dir: &Path) -> Option<LuauConfig> {
-    let config_path = dir.join(FILE_NAME_CONFIG);
-    if !config_path.exists() {
-        return None;
-    }
-
-    match std::fs::read_to_string(&config_path) {
-        Ok(content) => match serde_json::from_str::<LuauConfig>(&content) {
-            Ok(config) => Some(config),
-            Err(_) => None,
-        },
-        Err(_) => None,
-    }
+    let contents = std::fs::read(dir.join(FILE_NAME_CONFIG)).ok()?;
+    LuauConfig::parse(&contents)
 }
 
-/// Resolve an alias path to an absolute path by searching for .luaurc files
-fn resolve_alias(alias: &str, caller_dir: &Path) -> Option<PathBuf> {
-    // Alias format: @alias/path/to/module or @alias
-    // Strip the leading @
+/// Split an alias path, such as `@alias/path/to/module` or `@alias`,
+/// into the alias name and the rest of the path, if any
+fn split_alias(alias: &str) -> Option<(&str, Option<&str>)> {
     let alias_path = alias.strip_prefix('@')?;
-
-    // Split into alias name and rest of path
-    let (alias_name, rest) = match alias_path.find('/') {
-        Some(idx) => (&alias_path[..idx], Some(&alias_path[idx + 1..])),
+    Some(match alias_path.split_once('/') {
+        Some((name, rest)) => (name, Some(rest)),
         None => (alias_path, None),
-    };
+    })
+}
+
+/// Resolve an alias path using the aliases given by the host application
+fn resolve_given_alias(lua: &Lua, alias: &str) -> Option<PathBuf> {
+    let aliases = lua.app_data_ref::<RequireAliases>()?;
+    let (alias_name, rest) = split_alias(alias)?;
+    let mut resolved = aliases.0.get(alias_name)?.clone();
+    if let Some(rest_path) = rest {
+        resolved = resolved.join(rest_path);
+    }
+    Some(clean_path_and_make_absolute(&resolved))
+}
+
+/// Resolve an alias path to an absolute path by searching for .luaurc files,
+/// and for package manifests that declare a dependency with the alias name
+fn resolve_alias(state: &RequireState, alias: &str, caller_dir: &Path) -> Option<PathBuf> {
+    let (alias_name, rest) = split_alias(alias)?;
 
     // Special case: @lune/* is handled by registered modules, not .luaurc
     if alias_name == "lune" {
@@ -418,24 +594,47 @@ fn resolve_alias(alias: &str, caller_dir: &Path) -> Option<PathBuf> {
         return Some(clean_path_and_make_absolute(&resolved));
     }
 
-    // Search for .luaurc files starting from caller directory going up
-    let mut search_dir = caller_dir.to_path_buf();
+    // Merge the aliases of all .luaurc files and package manifests, starting from the
+    // root directory, so that nested configs inherit and override those of their ancestors
+    let merged = state.get_aliases(caller_dir);
+
+    // The alias path is relative to the directory of the .luaurc file or manifest
+    let mut resolved = merged.get(alias_name)?.to_path_buf();
+    if let Some(rest_path) = rest {
+        resolved = resolved.join(rest_path);
+    }
+    Some(clean_path_and_make_absolute(&resolved))
+}
+
+/// Resolve a bare require path, such as `foo/bar`, by searching the `paths` of
+/// .luaurc files, starting from the caller directory going up. Each path is
+/// relative to the directory of the .luaurc file that it was found in.
+///
+/// Bundled aliases are checked first, since the bundler records bare requires there.
+fn resolve_search_path(lua: &Lua, path: &str, caller_path: Option<&Path>) -> Option<PathBuf> {
+    if let Some(bundled_path) = get_bundled_alias(lua, path) {
+        return Some(bundled_path);
+    }
+
+    let mut search_dir = match caller_path {
+        Some(caller) if caller.is_dir() => caller.to_path_buf(),
+        Some(caller) => caller.parent()?.to_path_buf(),
+        None => std::env::current_dir().ok()?,
+    };
     loop {
         if let Some(config) = read_luaurc(&search_dir) {
-            if let Some(alias_value) = config.aliases.get(alias_name) {
-                // The alias value is a path relative to the .luaurc file's directory
-                let mut resolved = search_dir.join(alias_value);
-
-                // If there's additional path after the alias, append it
-                if let Some(rest_path) = rest {
-                    resolved = resolved.join(rest_path);
+            for search_path in &config.paths {
+                let candidate =
+                    clean_path_and_make_absolute(&search_dir.join(search_path).join(path));
+                let is_file = resolve_data_file(&candidate).is_some()
+                    || LuauModulePath::resolve(&candidate)
+                        .is_ok_and(|resolved| resolved.target().as_file().is_some());
+                if is_file {
+                    return Some(candidate);
                 }
-
-                return Some(clean_path_and_make_absolute(&resolved));
             }
         }
 
-        // Go up to parent directory
         if !search_dir.pop() {
             break;
         }
@@ -445,7 +644,11 @@ fn resolve_alias(alias: &str, caller_dir: &Path) -> Option<PathBuf> {
 }
 
 /// Resolve a require argument to paths or an alias.
-fn resolve_require_arg(arg: &LuaValue, caller_path: Option<&Path>) -> LuaResult<ResolveResult> {
+fn resolve_require_arg(
+    lua: &Lua,
+    arg: &LuaValue,
+    caller_path: Option<&Path>,
+) -> LuaResult<ResolveResult> {
     match arg {
         LuaValue::String(s) => {
             let path_str: String = s.to_str()?.to_string();
@@ -486,9 +689,16 @@ fn resolve_require_arg(arg: &LuaValue, caller_path: Option<&Path>) -> LuaResult<
             } else if path_str.starts_with('@') {
                 // Alias - delegate to built-in require
                 Ok(ResolveResult::Alias(path_str))
+            } else if let Some(abs) = resolve_search_path(lua, &path_str, caller_path) {
+                // Bare path found in the paths of a .luaurc file
+                let rel = caller_path
+                    .map(|cp| make_relative_path(cp, &abs))
+                    .unwrap_or_else(|| PathBuf::from(format!("./{}", abs.display())));
+                Ok(ResolveResult::FilePath(rel, abs))
             } else {
                 Err(LuaError::runtime(format!(
-                    "require path must start with './', '../', '/', or '@': got '{}'",
+                    "require path must start with './', '../', '/', or '@', \
+                    or be found in the paths of a .luaurc file: got '{}'",
                     path_str
                 )))
             }
@@ -512,6 +722,512 @@ fn resolve_require_arg(arg: &LuaValue, caller_path: Option<&Path>) -> LuaResult<
     }
 }
 
+/// Get the directory that requires of the module at the given path are resolved from.
+///
+/// For init.luau modules, the caller path is the directory itself (since the chunk
+/// name strips "/init.luau"), so it is used directly - for regular files, the parent
+/// directory is used, and the current directory if there is no calling module.
+fn caller_dir_of(caller_path: Option<&Path>) -> PathBuf {
+    let cwd = || std::env::current_dir().unwrap_or_default();
+    match caller_path {
+        Some(path) if path.is_dir() => path.to_path_buf(),
+        Some(path) => path.parent().map_or_else(cwd, Path::to_path_buf),
+        None => cwd(),
+    }
+}
+
+/// Resolve an alias, such as `@pkg/foo`, to the path of the module to load.
+fn resolve_alias_module(
+    lua: &Lua,
+    state: &RequireState,
+    alias: &str,
+    caller_path: Option<&Path>,
+) -> LuaResult<PathBuf> {
+    // For standalone executables, try linked requires and bundled aliases first
+    // Both return paths that don't need filesystem resolution
+    if let Some(linked_path) = get_linked_require(lua, caller_path, alias) {
+        return Ok(linked_path);
+    }
+    if let Some(bundled_path) = get_bundled_alias(lua, alias) {
+        return Ok(bundled_path);
+    }
+    // Then aliases given by the host application, which override .luaurc files
+    if let Some(alias_path) = resolve_given_alias(lua, alias) {
+        return resolve_file_module(lua, &alias_path);
+    }
+    // Then .luaurc files, searched from the directory of the calling module
+    let Some(alias_path) = resolve_alias(state, alias, &caller_dir_of(caller_path)) else {
+        return Err(LuaError::runtime(format!("cannot find alias '{}'", alias)));
+    };
+    resolve_file_module(lua, &alias_path)
+}
+
+/// Resolve the path of a module to the path of the file to load.
+fn resolve_file_module(lua: &Lua, path: &Path) -> LuaResult<PathBuf> {
+    // Try bundled files first (for standalone executables with virtual paths)
+    if let Some(bundled_path) = resolve_bundled_module(lua, path) {
+        return Ok(bundled_path);
+    }
+    if let Some(data_path) = resolve_data_file(path) {
+        return Ok(data_path);
+    }
+
+    // Resolve to actual filesystem path (handling .luau/.lua extensions)
+    let resolved = LuauModulePath::resolve(path)
+        .map_err(|e| LuaError::runtime(LuauModulePath::describe_error(path, &e)))?;
+    resolved
+        .target()
+        .as_file()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| LuaError::runtime(format!("cannot require directory '{}'", path.display())))
+}
+
+/// Requires that could not be resolved during this run, by the directory of the
+/// requiring module and the require path - such as optional modules probed using
+/// `pcall` - so that failing again does not walk directories and read .luaurc files.
+///
+/// Forgotten along with the module cache, and whenever watched modules change.
+#[derive(Debug, Default)]
+struct MissingModules(HashMap<(PathBuf, String), LuaError>);
+
+/// Get the error of a require that could not be resolved before, if any.
+///
+/// The directory of the calling module is only looked up once any require has failed.
+fn get_missing_module(
+    lua: &Lua,
+    caller_path: Option<&Path>,
+    require_path: Option<&str>,
+) -> Option<LuaError> {
+    let missing = lua.app_data_ref::<MissingModules>()?;
+    let key = (caller_dir_of(caller_path), require_path?.to_string());
+    missing.0.get(&key).cloned()
+}
+
+/// Remember the error of a require that could not be resolved.
+fn remember_missing_module(
+    lua: &Lua,
+    caller_path: Option<&Path>,
+    require_path: Option<&str>,
+    err: &LuaError,
+) {
+    let Some(require_path) = require_path else {
+        return;
+    };
+    let key = (caller_dir_of(caller_path), require_path.to_string());
+    if let Some(mut missing) = lua.app_data_mut::<MissingModules>() {
+        missing.0.insert(key, err.clone());
+        return;
+    }
+    let mut missing = MissingModules::default();
+    missing.0.insert(key, err.clone());
+    lua.set_app_data(missing);
+}
+
+/// Forget all requires that could not be resolved, since files may have been created,
+/// along with the aliases of directories, since their .luaurc files may have changed.
+pub(crate) fn forget_missing_modules(lua: &Lua) {
+    lua.remove_app_data::<MissingModules>();
+    if let Some(aliases) = lua.app_data_ref::<AliasCache>() {
+        aliases.0.borrow_mut().clear();
+    }
+}
+
+/// Start tracing a require, if requires are being traced.
+fn trace_require(lua: &Lua, arg: &LuaValue, caller_path: Option<&Path>) -> TracedRequire {
+    match lua.app_data_ref::<RequireTrace>() {
+        Some(trace) => trace.start(arg.to_string().unwrap_or_default(), caller_path),
+        None => TracedRequire::disabled(),
+    }
+}
+
+/// Require a module registered in `_REGISTEREDMODULES`, or provided by a namespace
+/// of the host application, in which case it is looked up and registered on first use.
+///
+/// Returns `None` if the alias does not belong to a registered module or namespace,
+/// or if the module is not provided by a namespace that falls back to aliases.
+async fn require_registered(
+    lua: &Lua,
+    state: &RequireState,
+    alias: &str,
+) -> LuaResult<Option<LuaMultiValue>> {
+    let registered: LuaTable = lua.named_registry_value("_REGISTEREDMODULES")?;
+    let value = registered.raw_get::<LuaValue>(alias)?;
+    if !value.is_nil() {
+        return Ok(Some(LuaMultiValue::from_vec(vec![value])));
+    }
+
+    let Some((namespace, name, resolver)) = lua
+        .app_data_ref::<Namespaces>()
+        .and_then(|namespaces| namespaces.find(alias))
+    else {
+        return Ok(None);
+    };
+
+    // Source modules may yield while loading, so concurrent requires wait for the first
+    let pending_path = Path::new(alias);
+    if let Some(rx) = state.get_pending(pending_path) {
+        return rx
+            .recv()
+            .await
+            .into_lua_err()
+            .context("require interrupted")
+            .and_then(|result| result)
+            .map(Some);
+    }
+
+    let Some(module) = resolver(lua, &name)? else {
+        // Resolvers that fall back only intercept some modules, leaving the rest to aliases
+        if lua
+            .app_data_ref::<Namespaces>()
+            .is_some_and(|namespaces| namespaces.falls_back(&namespace))
+        {
+            return Ok(None);
+        }
+        return Err(LuaError::runtime(format!(
+            "cannot find module '{alias}' in namespace '{namespace}'"
+        )));
+    };
+    let value = match module {
+        NamespaceModule::Value(value) => value,
+        NamespaceModule::Source(source) => {
+            let tx = state.create_pending(pending_path);
+
+            let func = lua
+                .load(source)
+                .set_name(format!("={alias}"))
+                .into_function()?;
+            let thread_id = lua.push_thread_back(func, ())?;
+            lua.track_thread(thread_id);
+            lua.wait_for_thread(thread_id).await;
+
+            let result = lua
+                .get_thread_result(thread_id)
+                .expect("thread tracked and waited");
+
+            if tx.receiver_count() > 0 {
+                tx.send(result.clone()).await.ok();
+                tx.close();
+            }
+            state.remove_pending(pending_path);
+
+            result?.into_iter().next().unwrap_or(LuaValue::Nil)
+        }
+    };
+
+    registered.raw_set(alias, value.clone())?;
+    Ok(Some(LuaMultiValue::from_vec(vec![value])))
+}
+
+/// Check if an alias belongs to a module that is not a file, such as a built-in module,
+/// a module registered by the host application, or a module provided by a namespace.
+fn is_registered_alias(lua: &Lua, alias: &str) -> LuaResult<bool> {
+    if alias.starts_with("@lune/") {
+        return Ok(true);
+    }
+    let registered: LuaTable = lua.named_registry_value("_REGISTEREDMODULES")?;
+    Ok(!registered.raw_get::<LuaValue>(alias)?.is_nil()
+        || lua
+            .app_data_ref::<Namespaces>()
+            .is_some_and(|namespaces| namespaces.find(alias).is_some()))
+}
+
+/// Check that a require is allowed by the allow-list given by the host
+/// application, if any - the resolved path is given for modules that are files.
+fn check_allowed(lua: &Lua, require_path: &str, resolved: Option<&Path>) -> LuaResult<()> {
+    let Some(allow_list) = lua.app_data_ref::<RequireAllowList>() else {
+        return Ok(());
+    };
+    allow_list
+        .check(require_path, resolved)
+        .map_err(|violation| LuaError::runtime(violation.to_string()))
+}
+
+/// Check the capabilities declared by a module against those granted to the
+/// requiring module, if the runtime checks capabilities at all. The source is
+/// given when the module is loaded, otherwise its recorded capabilities are used.
+fn check_capabilities(
+    lua: &Lua,
+    caller_path: Option<&Path>,
+    module_path: &Path,
+    source: Option<&[u8]>,
+) -> LuaResult<()> {
+    let Some(capabilities) = lua.app_data_ref::<Capabilities>() else {
+        return Ok(());
+    };
+    let context = caller_path.map(|p| p.display().to_string());
+    let module = module_path.display().to_string();
+    let result = match source {
+        Some(source) => capabilities.check(
+            context.as_deref(),
+            &module,
+            parse_requires_directives(source),
+        ),
+        None => capabilities.check_recorded(context.as_deref(), &module),
+    };
+    match (result, capabilities.mode()) {
+        (Ok(()), _) => Ok(()),
+        (Err(violation), CapabilityMode::Enforce) => Err(LuaError::runtime(violation.to_string())),
+        (Err(violation), CapabilityMode::Audit) => {
+            eprintln!("{}", style(format!("[AUDIT] {violation}")).yellow());
+            Ok(())
+        }
+    }
+}
+
+/// Resolve a data file on the filesystem, such as `config.json`.
+///
+/// Data files are required using their full file name, so unlike modules,
+/// no extensions are appended and they are never resolved as directories.
+fn resolve_data_file(path: &Path) -> Option<PathBuf> {
+    (is_data_file(path) && path.is_file()).then(|| path.to_path_buf())
+}
+
+/// Recursively make all tables in a value readonly.
+fn freeze_value(value: &LuaValue) -> LuaResult<()> {
+    if let LuaValue::Table(table) = value {
+        for pair in table.pairs::<LuaValue, LuaValue>() {
+            let (_, inner) = pair?;
+            freeze_value(&inner)?;
+        }
+        table.set_readonly(true);
+    }
+    Ok(())
+}
+
+/// Decode the contents of a data file, using the format matching its extension.
+fn decode_data_file(lua: &Lua, path: &Path, contents: &[u8]) -> LuaResult<LuaValue> {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "rbxm" | "rbxmx" => decode_model_file(lua, path, contents),
+        _ => decode_serde_file(lua, path, &extension, contents),
+    }
+}
+
+/// Decode the contents of a serialized data file, such as `config.json`.
+#[cfg(feature = "serde")]
+fn decode_serde_file(
+    lua: &Lua,
+    path: &Path,
+    extension: &str,
+    contents: &[u8],
+) -> LuaResult<LuaValue> {
+    use lune_std_serde::{EncodeDecodeFormat, decode};
+
+    let format = match extension {
+        "json" => EncodeDecodeFormat::Json,
+        "jsonc" => EncodeDecodeFormat::JsonC,
+        "toml" => EncodeDecodeFormat::Toml,
+        _ => EncodeDecodeFormat::Yaml,
+    };
+
+    decode(contents, lua, format.into())
+        .map_err(|e| LuaError::runtime(format!("cannot decode '{}': {}", path.display(), e)))
+}
+
+/// Decode the contents of a serialized data file - always an error without the serde library.
+#[cfg(not(feature = "serde"))]
+fn decode_serde_file(
+    _lua: &Lua,
+    path: &Path,
+    _extension: &str,
+    _contents: &[u8],
+) -> LuaResult<LuaValue> {
+    Err(LuaError::runtime(format!(
+        "cannot require data file '{}': the serde library is not enabled",
+        path.display()
+    )))
+}
+
+/// Decode the contents of a model file, such as `Model.rbxm`, into its root instance,
+/// or into an array of its root instances if the model has more than one.
+#[cfg(feature = "roblox")]
+fn decode_model_file(lua: &Lua, path: &Path, contents: &[u8]) -> LuaResult<LuaValue> {
+    use lune_roblox::document::{Document, DocumentKind};
+
+    let instances = Document::from_bytes(contents, DocumentKind::Model)
+        .and_then(Document::into_instance_array)
+        .map_err(|e| LuaError::runtime(format!("cannot decode '{}': {}", path.display(), e)))?;
+
+    match <[_; 1]>::try_from(instances) {
+        Ok([instance]) => instance.into_lua(lua),
+        Err(instances) => instances.into_lua(lua),
+    }
+}
+
+/// Decode the contents of a model file - always an error without the roblox library.
+#[cfg(not(feature = "roblox"))]
+fn decode_model_file(_lua: &Lua, path: &Path, _contents: &[u8]) -> LuaResult<LuaValue> {
+    Err(LuaError::runtime(format!(
+        "cannot require model file '{}': the roblox library is not enabled",
+        path.display()
+    )))
+}
+
+/// Require a data file, decoding it into a frozen value instead of running it.
+///
+/// The value is cached and shared with concurrent requires the same way as the
+/// result of a module, so every require of a data file returns the same value.
+/// Instances decoded from model files are not frozen, only arrays of them are.
+async fn require_data_file(
+    lua: &Lua,
+    state: &RequireState,
+    cache: &LuaTable,
+    path: &Path,
+    key: &Path,
+) -> RequireResult {
+    let tx = state.create_pending(key);
+
+    let contents = match get_bundled_source(lua, path) {
+        Some(bundled) => Ok(bundled),
+        None => read_file(path)
+            .await
+            .map(BundledFile::from)
+            .map_err(|e| LuaError::runtime(format!("cannot read '{}': {}", path.display(), e))),
+    };
+    let result = contents.and_then(|contents| {
+        let value = decode_data_file(lua, path, contents.bytes())?;
+        freeze_value(&value)?;
+        cache.set(key.to_string_lossy().as_ref(), value.clone())?;
+        // The decoded value is cached, so the contents are no longer needed
+        contents.evict();
+        Ok(LuaMultiValue::from_vec(vec![value]))
+    });
+
+    // Notify any waiting requires
+    if tx.receiver_count() > 0 {
+        tx.send(result.clone()).await.ok();
+        tx.close();
+    }
+    state.remove_pending(key);
+
+    result
+}
+
+/// Get the URL of the remote module being required, if any - either a URL given
+/// directly, or a relative path required by a remote module, which is resolved
+/// against the URL of the requiring module.
+fn get_remote_url(arg: &LuaValue, caller_path: Option<&Path>) -> LuaResult<Option<String>> {
+    let LuaValue::String(s) = arg else {
+        return Ok(None);
+    };
+    let path = s.to_str()?;
+    if is_remote_path(&path) {
+        return Ok(Some(normalize_remote_url(&path)));
+    }
+    Ok(caller_path
+        .and_then(Path::to_str)
+        .filter(|caller| is_remote_path(caller))
+        .and_then(|caller| resolve_remote_relative(caller, &path)))
+}
+
+/// Require a remote module using its URL, downloading it unless it is cached.
+///
+/// Remote modules are cached and shared with concurrent requires using their URL,
+/// and run with their URL as their chunk name, so that their own relative requires
+/// are resolved against it.
+async fn require_remote_module(
+    lua: &Lua,
+    state: &RequireState,
+    caller_path: Option<&Path>,
+    url: &str,
+) -> RequireResult {
+    let module_key = PathBuf::from(url);
+
+    let cache = get_module_cache(lua)?;
+    let cached = cache.get::<LuaValue>(url)?;
+    if !cached.is_nil() {
+        check_capabilities(lua, caller_path, &module_key, None)?;
+        return Ok(LuaMultiValue::from_vec(vec![cached]));
+    }
+    if let Some(rx) = state.get_pending(&module_key) {
+        check_capabilities(lua, caller_path, &module_key, None)?;
+        return rx
+            .recv()
+            .await
+            .into_lua_err()
+            .context("require interrupted")?;
+    }
+
+    let tx = state.create_pending(&module_key);
+    let result = async {
+        let source = fetch_remote_module(lua, url).await?;
+        check_capabilities(lua, caller_path, &module_key, Some(&source))?;
+
+        let module_env = lua.create_table()?;
+        module_env.set("script", ScriptReference::new(&module_key))?;
+        let env_mt = lua.create_table()?;
+        env_mt.set("__index", lua.globals())?;
+        env_mt.set("__newindex", lua.globals())?;
+        module_env.set_metatable(Some(env_mt))?;
+
+        let func = lua
+            .load(source)
+            .set_name(format!("{FILE_CHUNK_PREFIX}{url}"))
+            .set_environment(module_env)
+            .into_function()?;
+
+        push_script_path(lua, url)?;
+        let thread_id = lua.push_thread_back(func, ())?;
+        lua.track_thread(thread_id);
+        lua.wait_for_thread(thread_id).await;
+        let result = lua
+            .get_thread_result(thread_id)
+            .expect("thread tracked and waited");
+        pop_script_path(lua)?;
+
+        if let Ok(values) = &result
+            && let Some(first_value) = values.iter().next()
+        {
+            cache.set(url, first_value.clone())?;
+        }
+        result
+    }
+    .await;
+
+    // Notify any waiting requires
+    if tx.receiver_count() > 0 {
+        tx.send(result.clone()).await.ok();
+        tx.close();
+    }
+    state.remove_pending(&module_key);
+
+    result
+}
+
+/// Select the module to require for the current platform, given the arguments of
+/// `require.platform` - either a table of platform names to modules, or platform
+/// names followed by the module to require for them, and optionally for all others.
+fn select_platform_module(
+    _: &Lua,
+    (variants, matching, otherwise): (LuaValue, LuaValue, LuaValue),
+) -> LuaResult<LuaValue> {
+    let platform = Platform::current();
+    match variants {
+        LuaValue::String(names) => Ok(if platform.matches(&names.to_str()?) {
+            matching
+        } else {
+            otherwise
+        }),
+        LuaValue::Table(variants) => {
+            let variants = variants
+                .pairs::<String, LuaValue>()
+                .collect::<LuaResult<Vec<_>>>()?;
+            platform.select(variants).ok_or_else(|| {
+                LuaError::runtime(format!(
+                    "require.platform has no module for platform '{}' and no default",
+                    platform.os()
+                ))
+            })
+        }
+        _ => Err(LuaError::runtime(
+            "require.platform expects platform names or a table of modules",
+        )),
+    }
+}
+
 /// Registry key for storing the caller path temporarily
 const CALLER_PATH_KEY: &str = "__lune_require_caller_path";
 
@@ -520,7 +1236,7 @@ pub fn create(lua: Lua) -> LuaResult<LuaValue> {
     let builtin_require = lua.create_require_function(RequireResolver::new())?;
     lua.set_named_registry_value(BUILTIN_REQUIRE_KEY, builtin_require)?;
 
-    let state = RequireState::new();
+    let state = RequireState::new(&lua);
 
     let require_fn = lua.create_async_function(move |lua, arg: LuaValue| {
         let state = state.clone();
@@ -539,9 +1255,66 @@ pub fn create(lua: Lua) -> LuaResult<LuaValue> {
                 .set_named_registry_value(CALLER_PATH_KEY, LuaValue::Nil)
                 .ok();
 
-            // Resolve the argument to paths
-            match resolve_require_arg(&arg, caller_path.as_deref())? {
+            let mut trace = trace_require(&lua, &arg, caller_path.as_deref());
+
+            // Requires that could not be resolved before fail the same way again
+            let require_path = match &arg {
+                LuaValue::String(s) => Some(s.to_str()?.to_string()),
+                _ => None,
+            };
+            if let Some(err) =
+                get_missing_module(&lua, caller_path.as_deref(), require_path.as_deref())
+            {
+                return Err(err);
+            }
+            let remember_missing = |err: &LuaError| {
+                remember_missing_module(&lua, caller_path.as_deref(), require_path.as_deref(), err);
+            };
+
+            // Requires of bundled modules may have been linked when building - aliases
+            // are only looked up once registered modules have been checked, below
+            let linked_path = match &arg {
+                LuaValue::String(s) if !s.as_bytes().starts_with(b"@") => {
+                    get_linked_require(&lua, caller_path.as_deref(), &s.to_str()?)
+                }
+                _ => None,
+            };
+
+            // Resolve the argument to paths - remote modules are vendored into
+            // standalone executables, and downloaded or read from the cache otherwise
+            let resolved_arg = if let Some(url) = get_remote_url(&arg, caller_path.as_deref())? {
+                match get_bundled_alias(&lua, &url) {
+                    Some(path) => ResolveResult::FilePath(PathBuf::new(), path),
+                    None => {
+                        trace.resolve(&url);
+                        check_allowed(&lua, &url, None)?;
+                        return require_remote_module(&lua, &state, caller_path.as_deref(), &url)
+                            .await;
+                    }
+                }
+            } else {
+                match linked_path {
+                    Some(path) => ResolveResult::FilePath(PathBuf::new(), path),
+                    None => resolve_require_arg(&lua, &arg, caller_path.as_deref())
+                        .inspect_err(&remember_missing)?,
+                }
+            };
+            match resolved_arg {
                 ResolveResult::Alias(alias) => {
+                    // Modules that are not files are only allowed by the prefixes of the allow-list
+                    if lua.app_data_ref::<RequireAllowList>().is_some()
+                        && is_registered_alias(&lua, &alias)?
+                    {
+                        check_allowed(&lua, &alias, None)?;
+                    }
+
+                    // Registered modules, including those provided lazily by the
+                    // namespaces of the host application, take precedence over aliases
+                    if let Some(values) = require_registered(&lua, &state, &alias).await? {
+                        trace.resolve(&alias);
+                        return Ok(values);
+                    }
+
                     // Handle @lune/* built-in modules
                     if alias.starts_with("@lune/") {
                         let module_name = alias.strip_prefix("@lune/").unwrap();
@@ -550,6 +1323,7 @@ pub fn create(lua: Lua) -> LuaResult<LuaValue> {
                         let module_key = format!("@lune/{}", module_name);
                         match registered_modules.get::<LuaValue>(module_key.as_str()) {
                             Ok(value) if !value.is_nil() => {
+                                trace.resolve(&alias);
                                 return Ok(LuaMultiValue::from_vec(vec![value]));
                             }
                             _ => {
@@ -561,70 +1335,29 @@ pub fn create(lua: Lua) -> LuaResult<LuaValue> {
                         }
                     }
 
-                    // Handle custom aliases - check bundled aliases first, then .luaurc files.
-                    // For init.luau modules, the caller_path is the directory itself
-                    // (since the chunk name strips "/init.luau"), so we use it directly.
-                    // For regular files, we use the parent directory.
-                    let caller_dir = caller_path
-                        .as_ref()
-                        .map(|p| {
-                            if p.is_dir() {
-                                p.to_path_buf()
-                            } else {
-                                p.parent()
-                                    .map(|parent| parent.to_path_buf())
-                                    .unwrap_or_else(|| std::env::current_dir().unwrap_or_default())
-                            }
-                        })
-                        .unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+                    let resolved_path =
+                        resolve_alias_module(&lua, &state, &alias, caller_path.as_deref())
+                            .inspect_err(&remember_missing)?;
+                    trace.resolve(&resolved_path);
+                    check_allowed(&lua, &alias, Some(&resolved_path))?;
 
-                    // For standalone executables, try bundled aliases first
-                    // Bundled aliases return paths that don't need filesystem resolution
-                    let resolved_path: PathBuf = if let Some(bundled_path) =
-                        get_bundled_alias(&lua, &alias)
-                    {
-                        bundled_path
-                    } else if let Some(alias_path) = resolve_alias(&alias, &caller_dir) {
-                        // Try bundled files first (for standalone executables with virtual paths)
-                        if let Some(bundled_path) = resolve_bundled_module(&lua, &alias_path) {
-                            bundled_path
-                        } else {
-                            // Fall back to filesystem resolution
-                            let resolved = LuauModulePath::resolve(&alias_path).map_err(|e| {
-                                LuaError::runtime(format!(
-                                    "cannot find module '{}': {:?}",
-                                    alias_path.display(),
-                                    e
-                                ))
-                            })?;
-
-                            resolved
-                                .target()
-                                .as_file()
-                                .ok_or_else(|| {
-                                    LuaError::runtime(format!(
-                                        "cannot require directory '{}'",
-                                        alias_path.display()
-                                    ))
-                                })?
-                                .to_path_buf()
-                        }
-                    } else {
-                        return Err(LuaError::runtime(format!("cannot find alias '{}'", alias)));
-                    };
-
-                    let cache_key = resolved_path.to_string_lossy().to_string();
+                    let module_key = resolve_module_key(&lua, &resolved_path);
+                    let cache_key = module_key.to_string_lossy().to_string();
 
                     // Check cache first
                     let cache = get_module_cache(&lua)?;
                     if let Ok(cached) = cache.get::<LuaValue>(cache_key.as_str()) {
                         if !cached.is_nil() {
+                            trace.cached();
+                            check_capabilities(&lua, caller_path.as_deref(), &resolved_path, None)?;
                             return Ok(LuaMultiValue::from_vec(vec![cached]));
                         }
                     }
 
                     // Check if already being loaded (concurrent require)
-                    if let Some(rx) = state.get_pending(&resolved_path) {
+                    if let Some(rx) = state.get_pending(&module_key) {
+                        trace.cached();
+                        check_capabilities(&lua, caller_path.as_deref(), &resolved_path, None)?;
                         return rx
                             .recv()
                             .await
@@ -632,7 +1365,19 @@ pub fn create(lua: Lua) -> LuaResult<LuaValue> {
                             .context("require interrupted")?;
                     }
 
-                    let tx = state.create_pending(&resolved_path);
+                    // Data files are decoded instead of being loaded as modules
+                    if is_data_file(&resolved_path) {
+                        return require_data_file(
+                            &lua,
+                            &state,
+                            &cache,
+                            &resolved_path,
+                            &module_key,
+                        )
+                        .await;
+                    }
+
+                    let tx = state.create_pending(&module_key);
 
                     // Load and execute the module
                     let chunk_name = format!("{FILE_CHUNK_PREFIX}{}", resolved_path.display());
@@ -642,15 +1387,30 @@ pub fn create(lua: Lua) -> LuaResult<LuaValue> {
                         if let Some(bundled) = get_bundled_source(&lua, &resolved_path) {
                             bundled
                         } else {
-                            read_file(&resolved_path).await.map_err(|e| {
-                                LuaError::runtime(format!(
-                                    "cannot read '{}': {}",
-                                    resolved_path.display(),
-                                    e
-                                ))
-                            })?
+                            read_file(&resolved_path)
+                                .await
+                                .map(BundledFile::from)
+                                .map_err(|e| {
+                                    LuaError::runtime(format!(
+                                        "cannot read '{}': {}",
+                                        resolved_path.display(),
+                                        e
+                                    ))
+                                })?
                         };
 
+                    // Check the capabilities declared by the module before running it
+                    if let Err(err) = check_capabilities(
+                        &lua,
+                        caller_path.as_deref(),
+                        &resolved_path,
+                        Some(chunk_bytes.bytes()),
+                    ) {
+                        tx.close();
+                        state.remove_pending(&module_key);
+                        return Err(err);
+                    }
+
                     // Create a custom environment for this module with a static script reference
                     let module_env = lua.create_table()?;
                     let module_script = ScriptReference::new(&resolved_path);
@@ -663,7 +1423,7 @@ pub fn create(lua: Lua) -> LuaResult<LuaValue> {
                     module_env.set_metatable(Some(env_mt))?;
 
                     let chunk = lua
-                        .load(chunk_bytes)
+                        .load(chunk_bytes.bytes())
                         .set_name(&chunk_name)
                         .set_environment(module_env);
 
@@ -691,6 +1451,9 @@ pub fn create(lua: Lua) -> LuaResult<LuaValue> {
                     if let Ok(ref res) = result {
                         if let Some(first_value) = res.iter().next() {
                             cache.set(cache_key.as_str(), first_value.clone())?;
+                            // The source is only needed again if the cache is cleared
+                            chunk_bytes.evict();
+                            watch_module(&lua, &cache_key, &resolved_path)?;
                         }
                     }
 
@@ -700,51 +1463,37 @@ pub fn create(lua: Lua) -> LuaResult<LuaValue> {
                         tx.close();
                     }
 
-                    state.remove_pending(&resolved_path);
+                    state.remove_pending(&module_key);
 
                     result
                 }
                 ResolveResult::FilePath(_relative_path, absolute_path) => {
-                    // Try bundled files first (for standalone executables with virtual paths)
-                    // Then fall back to filesystem resolution
-                    let resolved_path: PathBuf = if let Some(bundled_path) =
-                        resolve_bundled_module(&lua, &absolute_path)
-                    {
-                        bundled_path
-                    } else {
-                        // Resolve to actual filesystem path (handling .luau/.lua extensions)
-                        let resolved = LuauModulePath::resolve(&absolute_path).map_err(|e| {
-                            LuaError::runtime(format!(
-                                "cannot find module '{}': {:?}",
-                                absolute_path.display(),
-                                e
-                            ))
-                        })?;
-
-                        resolved
-                            .target()
-                            .as_file()
-                            .ok_or_else(|| {
-                                LuaError::runtime(format!(
-                                    "cannot require directory '{}'",
-                                    absolute_path.display()
-                                ))
-                            })?
-                            .to_path_buf()
-                    };
-
-                    let cache_key = resolved_path.to_string_lossy().to_string();
+                    let resolved_path =
+                        resolve_file_module(&lua, &absolute_path).inspect_err(&remember_missing)?;
+                    trace.resolve(&resolved_path);
+                    check_allowed(
+                        &lua,
+                        require_path.as_deref().unwrap_or_default(),
+                        Some(&resolved_path),
+                    )?;
+
+                    let module_key = resolve_module_key(&lua, &resolved_path);
+                    let cache_key = module_key.to_string_lossy().to_string();
 
                     // Check cache first
                     let cache = get_module_cache(&lua)?;
                     if let Ok(cached) = cache.get::<LuaValue>(cache_key.as_str()) {
                         if !cached.is_nil() {
+                            trace.cached();
+                            check_capabilities(&lua, caller_path.as_deref(), &resolved_path, None)?;
                             return Ok(LuaMultiValue::from_vec(vec![cached]));
                         }
                     }
 
                     // Check if already being loaded (concurrent require)
-                    if let Some(rx) = state.get_pending(&resolved_path) {
+                    if let Some(rx) = state.get_pending(&module_key) {
+                        trace.cached();
+                        check_capabilities(&lua, caller_path.as_deref(), &resolved_path, None)?;
                         return rx
                             .recv()
                             .await
@@ -752,7 +1501,19 @@ pub fn create(lua: Lua) -> LuaResult<LuaValue> {
                             .context("require interrupted")?;
                     }
 
-                    let tx = state.create_pending(&resolved_path);
+                    // Data files are decoded instead of being loaded as modules
+                    if is_data_file(&resolved_path) {
+                        return require_data_file(
+                            &lua,
+                            &state,
+                            &cache,
+                            &resolved_path,
+                            &module_key,
+                        )
+                        .await;
+                    }
+
+                    let tx = state.create_pending(&module_key);
 
                     // Load and execute the module
                     // Use absolute path for chunk name so nested requires can resolve correctly
@@ -763,15 +1524,30 @@ pub fn create(lua: Lua) -> LuaResult<LuaValue> {
                         if let Some(bundled) = get_bundled_source(&lua, &resolved_path) {
                             bundled
                         } else {
-                            read_file(&resolved_path).await.map_err(|e| {
-                                LuaError::runtime(format!(
-                                    "cannot read '{}': {}",
-                                    resolved_path.display(),
-                                    e
-                                ))
-                            })?
+                            read_file(&resolved_path)
+                                .await
+                                .map(BundledFile::from)
+                                .map_err(|e| {
+                                    LuaError::runtime(format!(
+                                        "cannot read '{}': {}",
+                                        resolved_path.display(),
+                                        e
+                                    ))
+                                })?
                         };
 
+                    // Check the capabilities declared by the module before running it
+                    if let Err(err) = check_capabilities(
+                        &lua,
+                        caller_path.as_deref(),
+                        &resolved_path,
+                        Some(chunk_bytes.bytes()),
+                    ) {
+                        tx.close();
+                        state.remove_pending(&module_key);
+                        return Err(err);
+                    }
+
                     // Create a custom environment for this module with a static script reference
                     let module_env = lua.create_table()?;
                     let module_script = ScriptReference::new(&resolved_path);
@@ -784,7 +1560,7 @@ pub fn create(lua: Lua) -> LuaResult<LuaValue> {
                     module_env.set_metatable(Some(env_mt))?;
 
                     let chunk = lua
-                        .load(chunk_bytes)
+                        .load(chunk_bytes.bytes())
                         .set_name(&chunk_name)
                         .set_environment(module_env);
 
@@ -812,6 +1588,9 @@ pub fn create(lua: Lua) -> LuaResult<LuaValue> {
                     if let Ok(ref res) = result {
                         if let Some(first_value) = res.iter().next() {
                             cache.set(cache_key.as_str(), first_value.clone())?;
+                            // The source is only needed again if the cache is cleared
+                            chunk_bytes.evict();
+                            watch_module(&lua, &cache_key, &resolved_path)?;
                         }
                     }
 
@@ -821,7 +1600,7 @@ pub fn create(lua: Lua) -> LuaResult<LuaValue> {
                         tx.close();
                     }
 
-                    state.remove_pending(&resolved_path);
+                    state.remove_pending(&module_key);
 
                     result
                 }
@@ -844,6 +1623,10 @@ pub fn create(lua: Lua) -> LuaResult<LuaValue> {
     // Store our async require and preprocessor in globals for the wrapper to access
     lua.globals().set("__lune_async_require", require_fn)?;
     lua.globals().set("__lune_capture_caller", capture_caller)?;
+    lua.globals().set(
+        "__lune_select_platform",
+        lua.create_function(select_platform_module)?,
+    )?;
 
     // Create a Luau wrapper that:
     // 1. Captures the caller path
@@ -852,24 +1635,45 @@ pub fn create(lua: Lua) -> LuaResult<LuaValue> {
     //    - ScriptReference userdata
     //    - Absolute paths (/)
     //    - Relative paths (./ ../)
+    //
+    // The wrapper is a callable table so that it can also hold helpers
+    // such as require.platform, which the bundler knows how to read.
     let wrapper_code = r#"
--- Return a function that wraps require behavior
-return function(arg)
-    -- Capture the caller path first (sync, doesn't yield)
-    __lune_capture_caller()
-    -- All paths go through our async require which handles everything
-    return __lune_async_require(arg)
-end
+-- Return a table that wraps require behavior
+return setmetatable({
+    platform = function(variants, matching, otherwise)
+        -- Capture the caller path first (sync, doesn't yield)
+        __lune_capture_caller()
+        local selected = __lune_select_platform(variants, matching, otherwise)
+        if selected == nil then
+            return nil
+        end
+        return __lune_async_require(selected)
+    end,
+}, {
+    __call = function(_, arg)
+        -- Capture the caller path first (sync, doesn't yield)
+        __lune_capture_caller()
+        -- All paths go through our async require which handles everything
+        return __lune_async_require(arg)
+    end,
+})
 "#;
 
     // Load the wrapper code
     // The wrapper's chunk name doesn't matter since we capture the caller path separately
-    let wrapper: LuaFunction = lua
+    let wrapper: LuaTable = lua
         .load(wrapper_code)
         .set_name("=require_wrapper")
         .call(())?;
 
-    Ok(LuaValue::Function(wrapper))
+    wrapper.set("cache", create_cache_view(&lua)?)?;
+    wrapper.set(
+        "invalidateAll",
+        lua.create_function(|lua, ()| clear_module_cache(lua))?,
+    )?;
+
+    Ok(LuaValue::Table(wrapper))
 }
 
 #[cfg(test)]
@@ -0,0 +1,34 @@
+use std::rc::Rc;
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, ShutdownHook};
+
+use lune_utils::TableBuilder;
+
+mod reload;
+
+use self::reload::ReloadHooks;
+
+/// Creates the `runtime` global.
+///
+/// Contains `runtime.onShutdown`, which registers a function to run when the
+/// runtime shuts down - once the script and all of its threads have completed,
+/// or once `process.exit` has been called.
+///
+/// Also contains `runtime.onReload`, which registers a function to run whenever
+/// a reload is requested, such as by sending `SIGHUP` to the process on Unix.
+pub fn create(lua: Lua) -> LuaResult<LuaValue> {
+    let reload_hooks = Rc::new(ReloadHooks::default());
+    TableBuilder::new(lua)?
+        .with_function("onShutdown", runtime_on_shutdown)?
+        .with_function("onReload", move |lua, args| {
+            reload::on_reload(lua, &reload_hooks, args)
+        })?
+        .build_readonly()
+        .map(LuaValue::Table)
+}
+
+fn runtime_on_shutdown(lua: &Lua, (priority, hook): (i32, LuaFunction)) -> LuaResult<()> {
+    lua.on_shutdown(priority, ShutdownHook::from(hook));
+    Ok(())
+}
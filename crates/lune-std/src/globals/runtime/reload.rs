@@ -0,0 +1,251 @@
+/*!
+    Reloading of long-running scripts, such as services, without restarting them.
+
+    A reload is requested by sending `SIGHUP` to the process on Unix, or by
+    connecting to the named pipe `\\.\pipe\lune-reload-<pid>` on Windows, where
+    `<pid>` is the id of the process, and writing any message to it.
+
+    Every reload first invalidates the modules given to `runtime.onReload`,
+    so that they are loaded again the next time they are required, and
+    then runs each of the registered functions in a new thread.
+
+    Reloads are only listened for while functions are registered, and the
+    runtime keeps running while they are, the same way it does for servers.
+*/
+
+use std::cell::RefCell;
+use std::io;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use async_channel::Sender;
+use futures_lite::FutureExt;
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, LuaSpawnExt};
+
+use lune_utils::path::clean_path_and_make_absolute;
+
+use crate::globals::require::{get_caller_dir, invalidate_module};
+
+/// A function registered using `runtime.onReload`
+struct ReloadHook {
+    id: usize,
+    callback: LuaFunction,
+    modules: Vec<PathBuf>,
+}
+
+#[derive(Default)]
+struct ReloadHooksInner {
+    next_id: usize,
+    hooks: Vec<ReloadHook>,
+    /// Stops listening for reloads once dropped, set while listening
+    listener: Option<Sender<()>>,
+}
+
+/// All functions registered using `runtime.onReload`
+#[derive(Default)]
+pub(super) struct ReloadHooks {
+    inner: RefCell<ReloadHooksInner>,
+}
+
+impl ReloadHooks {
+    fn remove(&self, id: usize) {
+        let mut inner = self.inner.borrow_mut();
+        inner.hooks.retain(|hook| hook.id != id);
+        if inner.hooks.is_empty() {
+            inner.listener = None;
+        }
+    }
+
+    /// Invalidates the modules of all hooks, and then runs each of them in a new thread
+    fn run(&self, lua: &Lua) -> LuaResult<()> {
+        let callbacks = {
+            let inner = self.inner.borrow();
+            for path in inner.hooks.iter().flat_map(|hook| &hook.modules) {
+                invalidate_module(lua, path)?;
+            }
+            inner
+                .hooks
+                .iter()
+                .map(|hook| hook.callback.clone())
+                .collect::<Vec<_>>()
+        };
+        for callback in callbacks {
+            lua.push_thread_back(callback, ())?;
+        }
+        Ok(())
+    }
+}
+
+/**
+    Registers a function to run whenever a reload is requested, along with modules,
+    relative to the calling script, to invalidate before it runs.
+
+    Returns a function that unregisters it again.
+*/
+pub(super) fn on_reload(
+    lua: &Lua,
+    hooks: &Rc<ReloadHooks>,
+    (callback, modules): (LuaFunction, Option<Vec<String>>),
+) -> LuaResult<LuaFunction> {
+    let caller_dir = get_caller_dir(lua);
+    let modules = modules
+        .unwrap_or_default()
+        .into_iter()
+        .map(|module| clean_path_and_make_absolute(caller_dir.join(module)))
+        .collect();
+
+    let id = {
+        let mut inner = hooks.inner.borrow_mut();
+        if inner.listener.is_none() {
+            inner.listener = Some(listen(lua, Rc::clone(hooks))?);
+        }
+        let id = inner.next_id;
+        inner.next_id += 1;
+        inner.hooks.push(ReloadHook {
+            id,
+            callback,
+            modules,
+        });
+        id
+    };
+
+    let hooks = Rc::clone(hooks);
+    lua.create_function(move |_, ()| {
+        hooks.remove(id);
+        Ok(())
+    })
+}
+
+/// Starts listening for reloads, until the returned sender is dropped
+fn listen(lua: &Lua, hooks: Rc<ReloadHooks>) -> LuaResult<Sender<()>> {
+    let mut signals = ReloadSignals::new()
+        .into_lua_err()
+        .context("failed to listen for reloads")?;
+    let (stop_tx, stop_rx) = async_channel::bounded::<()>(1);
+    let (reload_tx, reload_rx) = async_channel::unbounded::<()>();
+
+    // NOTE: Signals are received on the main executor, which does not keep
+    // the runtime running, and the local task below stops along with them
+    lua.spawn(async move {
+        let forward = async {
+            while signals.next().await.is_ok() {
+                if reload_tx.send(()).await.is_err() {
+                    break;
+                }
+            }
+        };
+        let stop = async {
+            stop_rx.recv().await.ok();
+        };
+        forward.or(stop).await;
+    })
+    .detach();
+
+    let inner = lua.clone();
+    lua.spawn_local(async move {
+        while reload_rx.recv().await.is_ok() {
+            // NOTE: This can only fail when out of memory
+            let _ = hooks.run(&inner);
+        }
+    });
+
+    Ok(stop_tx)
+}
+
+/// Reload requests sent to the process using `SIGHUP`
+#[cfg(unix)]
+struct ReloadSignals(async_signal::Signals);
+
+#[cfg(unix)]
+impl ReloadSignals {
+    fn new() -> io::Result<Self> {
+        async_signal::Signals::new([async_signal::Signal::Hup]).map(Self)
+    }
+
+    async fn next(&mut self) -> io::Result<()> {
+        use futures_lite::StreamExt;
+        match self.0.next().await {
+            Some(signal) => signal.map(|_| ()),
+            None => Err(io::ErrorKind::UnexpectedEof.into()),
+        }
+    }
+}
+
+/// Reload requests sent to the process using its named pipe, by its null-terminated name
+#[cfg(windows)]
+struct ReloadSignals(Vec<u16>);
+
+#[cfg(windows)]
+impl ReloadSignals {
+    fn new() -> io::Result<Self> {
+        let name = format!(r"\\.\pipe\lune-reload-{}", std::process::id());
+        Ok(Self(name.encode_utf16().chain([0]).collect()))
+    }
+
+    async fn next(&mut self) -> io::Result<()> {
+        let name = self.0.clone();
+        blocking::unblock(move || wait_for_pipe_message(&name)).await
+    }
+}
+
+/// Creates the named pipe, and waits until a client has connected to it and written a message
+#[cfg(windows)]
+fn wait_for_pipe_message(name: &[u16]) -> io::Result<()> {
+    use std::fs::File;
+    use std::io::Read;
+    use std::os::windows::io::FromRawHandle;
+
+    use windows_sys::Win32::Foundation::{ERROR_PIPE_CONNECTED, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::Storage::FileSystem::PIPE_ACCESS_INBOUND;
+    use windows_sys::Win32::System::Pipes::{
+        ConnectNamedPipe, CreateNamedPipeW, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+    };
+
+    // SAFETY: The name is null-terminated, and no security attributes are given
+    let handle = unsafe {
+        CreateNamedPipeW(
+            name.as_ptr(),
+            PIPE_ACCESS_INBOUND,
+            PIPE_TYPE_BYTE | PIPE_WAIT,
+            PIPE_UNLIMITED_INSTANCES,
+            0,
+            512,
+            0,
+            std::ptr::null(),
+        )
+    };
+    if handle == INVALID_HANDLE_VALUE {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: The handle is valid, and is closed once the file is dropped
+    let mut pipe = unsafe { File::from_raw_handle(handle) };
+
+    // SAFETY: The handle is valid, and was not opened for overlapped operations
+    if unsafe { ConnectNamedPipe(handle, std::ptr::null_mut()) } == 0 {
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() != Some(ERROR_PIPE_CONNECTED as i32) {
+            return Err(err);
+        }
+    }
+
+    // NOTE: Reading fails with a broken pipe once the client disconnects,
+    // which is expected, since the contents of the message do not matter
+    let _ = pipe.read_to_end(&mut Vec::new());
+    Ok(())
+}
+
+/// Reload requests are not supported on other platforms, and never received
+#[cfg(not(any(unix, windows)))]
+struct ReloadSignals;
+
+#[cfg(not(any(unix, windows)))]
+impl ReloadSignals {
+    fn new() -> io::Result<Self> {
+        Ok(Self)
+    }
+
+    async fn next(&mut self) -> io::Result<()> {
+        std::future::pending().await
+    }
+}
@@ -1,10 +1,14 @@
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use lune_utils::path::{LuauModulePath, clean_path, find_project_file};
 use mlua::UserData;
 use mlua::prelude::*;
 
+use crate::globals::require::{get_bundled_source, resolve_bundled_module};
+
 /// Registry key for storing the current script path stack
 const SCRIPT_PATH_STACK_KEY: &str = "__lune_script_path_stack";
 
@@ -66,8 +70,9 @@ impl ScriptReference {
     }
 
     /// Get a child by name from a path
-    /// First checks default.project.json for path mappings, then falls back to direct child
-    fn child_from_path(path: &Path, name: &str) -> ScriptReference {
+    /// First checks the project file for path mappings, then falls back to direct child.
+    /// Returns `None` if the project file says that no such child can exist.
+    fn child_from_path(path: &Path, name: &str) -> LuaResult<Option<ScriptReference>> {
         let base_dir = if path.is_file() {
             path.parent().map(|p| p.to_path_buf())
         } else {
@@ -75,38 +80,103 @@ impl ScriptReference {
         };
 
         if let Some(base) = &base_dir {
-            // Try to resolve through project.json first
-            if let Some(resolved) = resolve_through_project(base, name) {
-                return ScriptReference::new(resolved);
+            // Try to resolve through the project file first
+            if let Some((project_root, node)) = find_project_node(base)? {
+                if let Some(child) = node.children.get(name) {
+                    let location = child.location(&project_root, base, name);
+                    return Ok(Some(ScriptReference::new(location)));
+                }
+                if !node.allows_unknown_children() {
+                    return Ok(None);
+                }
             }
         }
 
         // Fall back to direct child lookup
         let mut child_path = base_dir.unwrap_or_else(|| path.to_path_buf());
         child_path.push(name);
-        ScriptReference::new(child_path)
+        Ok(Some(ScriptReference::new(child_path)))
     }
+
+    /// Get all children of a path as (name, reference) pairs, sorted by name.
+    /// Uses the same base directory rules as `child_from_path`, and lets
+    /// project file mappings take precedence over direct children.
+    fn children_from_path(path: &Path) -> LuaResult<Vec<(String, ScriptReference)>> {
+        let base_dir = if path.is_file() {
+            path.parent().map(|p| p.to_path_buf())
+        } else {
+            Some(path.to_path_buf())
+        };
+        let Some(base) = base_dir else {
+            return Ok(Vec::new());
+        };
+
+        let mut children = HashMap::new();
+
+        // Direct children on the filesystem - source files are named
+        // without their extension, same as they would be when required
+        if let Ok(entries) = fs::read_dir(&base) {
+            for entry in entries.flatten() {
+                let entry_path = entry.path();
+                let Some(file_name) = entry_path.file_name().and_then(|n| n.to_str()) else {
+                    continue;
+                };
+                if file_name.starts_with('.') {
+                    continue;
+                }
+                let name = if entry_path.is_file() && is_source_file(&entry_path) {
+                    let stem = entry_path
+                        .file_stem()
+                        .map(|s| s.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    // Init files represent their parent directory, not a child
+                    if stem == "init" {
+                        continue;
+                    }
+                    stem
+                } else {
+                    file_name.to_string()
+                };
+                let child_path = base.join(&name);
+                children.insert(name, ScriptReference::new(child_path));
+            }
+        }
+
+        // Project mappings override any direct children with the same name
+        if let Some((project_root, node)) = find_project_node(&base)? {
+            for (name, child) in &node.children {
+                let location = child.location(&project_root, &base, name);
+                children.insert(name.clone(), ScriptReference::new(location));
+            }
+        }
+
+        let mut children = children.into_iter().collect::<Vec<_>>();
+        children.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(children)
+    }
+}
+
+/// Check if a path has a Luau source file extension
+fn is_source_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == "luau" || ext == "lua")
 }
 
-/// Find the project root by searching up from the given path for default.project.json
-fn find_project_root(start_path: &Path) -> Option<PathBuf> {
-    let mut current = if start_path.is_file() {
-        start_path.parent()?.to_path_buf()
+/// Find the project root by searching up from the given path for a project file.
+/// Returns both the project root directory and the project file that was found in it.
+fn find_project_root(start_path: &Path) -> LuaResult<Option<(PathBuf, PathBuf)>> {
+    let start_dir = if start_path.is_file() {
+        start_path.parent()
     } else {
-        start_path.to_path_buf()
+        Some(start_path)
     };
 
-    loop {
-        let project_file = current.join("default.project.json");
-        if project_file.exists() {
-            return Some(current);
-        }
-
-        match current.parent() {
-            Some(parent) => current = parent.to_path_buf(),
-            None => return None,
+    for dir in start_dir.into_iter().flat_map(Path::ancestors) {
+        if let Some(project_file) = find_project_file(dir).into_lua_err()? {
+            return Ok(Some((dir.to_path_buf(), project_file)));
         }
     }
+    Ok(None)
 }
 
 /// Represents a node in the project tree
@@ -114,6 +184,8 @@ fn find_project_root(start_path: &Path) -> Option<PathBuf> {
 struct ProjectNode {
     /// The $path if specified
     path: Option<String>,
+    /// The $ignoreUnknownInstances flag if specified
+    ignore_unknown_instances: Option<bool>,
     /// Child nodes
     children: HashMap<String, ProjectNode>,
 }
@@ -122,6 +194,7 @@ impl ProjectNode {
     fn new() -> Self {
         Self {
             path: None,
+            ignore_unknown_instances: None,
             children: HashMap::new(),
         }
     }
@@ -130,12 +203,20 @@ impl ProjectNode {
         let obj = value.as_object()?;
         let mut node = ProjectNode::new();
 
-        // Check for $path
+        // Check for $path, which can be either a string or { "optional": "path" }
         if let Some(path_val) = obj.get("$path") {
-            node.path = path_val.as_str().map(|s| s.to_string());
+            node.path = path_val
+                .as_str()
+                .or_else(|| path_val.get("optional").and_then(|v| v.as_str()))
+                .map(|s| s.to_string());
         }
 
-        // Process children (skip $ prefixed keys)
+        // Check for $ignoreUnknownInstances
+        if let Some(ignore_val) = obj.get("$ignoreUnknownInstances") {
+            node.ignore_unknown_instances = ignore_val.as_bool();
+        }
+
+        // Process children (skip $ prefixed keys, such as $className)
         for (key, child_value) in obj.iter() {
             if !key.starts_with('$') {
                 if let Some(child_node) = ProjectNode::from_json(child_value) {
@@ -146,12 +227,49 @@ impl ProjectNode {
 
         Some(node)
     }
+
+    /// Get the location of this node, given the location of its parent.
+    ///
+    /// Nodes with a $path are located at that path, relative to the project root.
+    /// Nodes without one (`$className`-only containers) only exist in the project
+    /// tree, and get a virtual location inside of their parent instead.
+    fn location(&self, project_root: &Path, parent_location: &Path, name: &str) -> PathBuf {
+        match &self.path {
+            Some(path) => clean_path(project_root.join(path)),
+            None => clean_path(parent_location.join(name)),
+        }
+    }
+
+    /// Check if children that are not declared in the project tree may exist in this node.
+    ///
+    /// Nodes with a $path may contain anything on the filesystem. For containers without
+    /// one, Rojo defaults to ignoring unknown instances, unless explicitly disabled.
+    fn allows_unknown_children(&self) -> bool {
+        self.path.is_some() || self.ignore_unknown_instances != Some(false)
+    }
+
+    /// Recursively find the node located at `target`, given the location of this node
+    fn find_at(&self, project_root: &Path, location: &Path, target: &Path) -> Option<&Self> {
+        if location == target {
+            return Some(self);
+        }
+        for (name, child) in &self.children {
+            let child_location = child.location(project_root, location, name);
+            if let Some(found) = child.find_at(project_root, &child_location, target) {
+                return Some(found);
+            }
+            // Nodes with a $path can also be reached through their name in the tree
+            if child.path.is_some() && clean_path(location.join(name)) == target {
+                return Some(child);
+            }
+        }
+        None
+    }
 }
 
-/// Parse a default.project.json file and return the tree
-fn parse_project_json(project_root: &Path) -> Option<ProjectNode> {
-    let project_file = project_root.join("default.project.json");
-    let content = fs::read_to_string(&project_file).ok()?;
+/// Parse a project file and return the tree
+fn parse_project_json(project_file: &Path) -> Option<ProjectNode> {
+    let content = fs::read_to_string(project_file).ok()?;
     let json: serde_json::Value = serde_json::from_str(&content).ok()?;
 
     // Get the tree from the JSON
@@ -159,58 +277,23 @@ fn parse_project_json(project_root: &Path) -> Option<ProjectNode> {
     ProjectNode::from_json(tree)
 }
 
-/// Try to resolve a child name through the project.json tree
-fn resolve_through_project(base_path: &Path, child_name: &str) -> Option<PathBuf> {
-    // Find project root
-    let project_root = find_project_root(base_path)?;
-
-    // Parse project.json
-    let tree = parse_project_json(&project_root)?;
-
-    // Calculate relative path from project root to base_path
-    let relative_to_root = base_path.strip_prefix(&project_root).ok()?;
-
-    // Navigate the tree to find current position
-    let mut current_node = &tree;
-
-    // Navigate through the relative path to find current node
-    for component in relative_to_root.components() {
-        let name = component.as_os_str().to_string_lossy();
-
-        // First, check if there's a direct child with this name
-        if let Some(child) = current_node.children.get(name.as_ref()) {
-            current_node = child;
-            continue;
-        }
-
-        // Check if any child has a $path that matches
-        let mut found = false;
-        for (_, child) in &current_node.children {
-            if let Some(ref path) = child.path {
-                let resolved_path = project_root.join(path);
-                if resolved_path == base_path.join(component.as_os_str()) {
-                    current_node = child;
-                    found = true;
-                    break;
-                }
-            }
-        }
-
-        if !found {
-            // Can't navigate further in the tree
-            return None;
-        }
-    }
+/// Find the project tree node that corresponds to the given directory,
+/// along with the project root that any `$path` values are relative to
+fn find_project_node(base_path: &Path) -> LuaResult<Option<(PathBuf, ProjectNode)>> {
+    let Some((project_root, project_file)) = find_project_root(base_path)? else {
+        return Ok(None);
+    };
+    let Some(tree) = parse_project_json(&project_file) else {
+        return Ok(None);
+    };
 
-    // Now look for the child_name in the current node
-    if let Some(child_node) = current_node.children.get(child_name) {
-        if let Some(ref path) = child_node.path {
-            // Return the resolved path
-            return Some(project_root.join(path));
-        }
-    }
+    let root_location = tree.location(&project_root, &project_root, "");
+    let target = clean_path(base_path);
+    let node = tree
+        .find_at(&project_root, &root_location, &target)
+        .cloned();
 
-    None
+    Ok(node.map(|node| (project_root, node)))
 }
 
 impl UserData for ScriptReference {
@@ -278,12 +361,79 @@ impl UserData for ScriptReference {
                 },
                 _ => {
                     // Treat as child lookup
-                    let child = ScriptReference::child_from_path(&path, &key);
-                    Ok(LuaValue::UserData(lua.create_userdata(child)?))
+                    match ScriptReference::child_from_path(&path, &key)? {
+                        Some(child) => Ok(LuaValue::UserData(lua.create_userdata(child)?)),
+                        None => Ok(LuaValue::Nil),
+                    }
                 }
             }
         });
 
+        // Generalized iteration over children as (name, ScriptReference) pairs
+        // Usage: for name, child in script.Parent do ... end
+        methods.add_meta_method(LuaMetaMethod::Iter, |lua, this, ()| {
+            let path = this.resolve_path(lua)?;
+            let children = ScriptReference::children_from_path(&path)?;
+            let index = Cell::new(0);
+            lua.create_function(move |lua, ()| {
+                let current = index.get();
+                index.set(current + 1);
+                match children.get(current) {
+                    Some((name, child)) => Ok((
+                        LuaValue::String(lua.create_string(name)?),
+                        LuaValue::UserData(lua.create_userdata(child.clone())?),
+                    )),
+                    None => Ok((LuaValue::Nil, LuaValue::Nil)),
+                }
+            })
+        });
+
+        // GetChildren returns an array of child references (like Roblox's Instance:GetChildren())
+        methods.add_method("GetChildren", |lua, this, ()| {
+            let path = this.resolve_path(lua)?;
+            let children = ScriptReference::children_from_path(&path)?;
+            let table = lua.create_table_with_capacity(children.len(), 0)?;
+            for (_, child) in children {
+                table.push(child)?;
+            }
+            Ok(table)
+        });
+
+        // Path returns the real path of the file this reference points to, which may
+        // be used with other APIs such as @lune/fs instead of relying on tostring
+        // Usage: fs.readDir(script.Parent.Assets:Path())
+        methods.add_method("Path", |lua, this, ()| {
+            let path = this.resolve_path(lua)?;
+            let resolved = match resolve_file(lua, &path) {
+                Some(ResolvedFile::Bundled(key)) => key,
+                Some(ResolvedFile::Filesystem(file)) => file,
+                None => path,
+            };
+            Ok(resolved.display().to_string())
+        });
+
+        // Read returns the contents of the file this reference points to, reading
+        // from bundled files first so that it also works in standalone binaries
+        // Usage: local template = script.Parent["template.html"]:Read()
+        methods.add_method("Read", |lua, this, ()| {
+            let path = this.resolve_path(lua)?;
+            let contents = read_file_contents(lua, &path)?;
+            lua.create_string(contents)
+        });
+
+        // ReadJson reads the file this reference points to and decodes it as JSON
+        // Usage: local config = script.Parent["config.json"]:ReadJson()
+        #[cfg(feature = "serde")]
+        methods.add_method("ReadJson", |lua, this, ()| {
+            let path = this.resolve_path(lua)?;
+            let contents = read_file_contents(lua, &path)?;
+            lune_std_serde::decode(
+                contents,
+                lua,
+                lune_std_serde::EncodeDecodeFormat::Json.into(),
+            )
+        });
+
         // GetFullName returns the full path (like Roblox's Instance:GetFullName())
         methods.add_method("GetFullName", |lua, this, ()| {
             let path = this.resolve_path(lua)?;
@@ -307,6 +457,61 @@ impl UserData for ScriptReference {
     }
 }
 
+/// A file that a script reference was resolved to
+enum ResolvedFile {
+    /// A file bundled into a standalone binary, by its bundle path
+    Bundled(PathBuf),
+    /// A file on the real filesystem
+    Filesystem(PathBuf),
+}
+
+/// Resolve the file that a script reference points to, trying bundled files first.
+///
+/// Paths that do not point directly to a file are resolved like modules, meaning
+/// `script.Parent.Module` can be resolved to either `Module.luau` or `Module/init.luau`.
+fn resolve_file(lua: &Lua, path: &Path) -> Option<ResolvedFile> {
+    if let Some(bundled) = resolve_bundled_module(lua, path) {
+        return Some(ResolvedFile::Bundled(bundled));
+    }
+    if path.is_file() {
+        return Some(ResolvedFile::Filesystem(path.to_path_buf()));
+    }
+    let module = LuauModulePath::resolve(path).ok()?;
+    let file = module.target().as_file()?;
+    Some(ResolvedFile::Filesystem(file.to_path_buf()))
+}
+
+/// Read the contents of the file that a script reference points to
+fn read_file_contents(lua: &Lua, path: &Path) -> LuaResult<Vec<u8>> {
+    match resolve_file(lua, path) {
+        Some(ResolvedFile::Bundled(key)) => match get_bundled_source(lua, &key) {
+            // Binaries built with --strip-source only contain bytecode,
+            // which must never be exposed as if it were the source text
+            Some(source) if is_bytecode(source.bytes()) => Err(LuaError::runtime(format!(
+                "cannot read bundled file '{}': source code was stripped from this binary",
+                key.display()
+            ))),
+            Some(source) => Ok(source.bytes().to_vec()),
+            None => Err(LuaError::runtime(format!(
+                "cannot read bundled file '{}'",
+                key.display()
+            ))),
+        },
+        Some(ResolvedFile::Filesystem(file)) => fs::read(&file)
+            .map_err(|e| LuaError::runtime(format!("cannot read '{}': {e}", file.display()))),
+        None => Err(LuaError::runtime(format!(
+            "cannot read '{}': no such file",
+            path.display()
+        ))),
+    }
+}
+
+/// Check if bundled file contents are compiled Luau bytecode rather than source text.
+/// Bytecode always starts with its version number, which is never valid source text.
+fn is_bytecode(contents: &[u8]) -> bool {
+    contents.first().is_some_and(|b| *b < b'\t')
+}
+
 /// Convert an absolute target path to a relative path from the current script.
 /// Returns a path starting with "./" or "../" as required by the require system.
 fn make_relative_path(current_script: &str, target_path: &str) -> String {
@@ -8,12 +8,24 @@ mod library;
 mod require;
 
 pub use self::global::LuneStandardGlobal;
+pub use self::globals::build::create as create_build_global;
+pub use self::globals::bundle::create as create_bundle_global;
+pub use self::globals::defines::{Defines, create as create_defines_global};
 pub use self::globals::executable::create as create_executable_global;
-pub use self::globals::require::{CHUNK_CACHE_KEY, get_chunk_cache};
+pub use self::globals::hot_reload::HotReload;
+pub use self::globals::require::{
+    BundledLinks, CHUNK_CACHE_KEY, LinkedRequires, RequireAliases, clear_module_cache,
+    get_chunk_cache,
+};
 pub use self::globals::script::{ScriptReference, pop_script_path, push_script_path};
 pub use self::globals::version::set_global_version;
 pub use self::library::LuneStandardLibrary;
 
+#[cfg(feature = "events")]
+pub use lune_std_events::{EventBridge, EventSubscription};
+#[cfg(feature = "luau")]
+pub use lune_std_luau::{LuauRuntimeSpawner, LuauSpawnOptions, LuauSpawnResult, LuauSpawnSource};
+
 /**
     Injects all standard globals into the given Lua state / VM.
 
@@ -8,8 +8,11 @@ use mlua::prelude::*;
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 #[rustfmt::skip]
 pub enum LuneStandardLibrary {
+    #[cfg(feature = "buffer")]   Buffer,
     #[cfg(feature = "datetime")] DateTime,
+    #[cfg(feature = "events")]   Events,
     #[cfg(feature = "fs")]       Fs,
+    #[cfg(feature = "ipc")]      Ipc,
     #[cfg(feature = "luau")]     Luau,
     #[cfg(feature = "net")]      Net,
     #[cfg(feature = "task")]     Task,
@@ -26,8 +29,11 @@ impl LuneStandardLibrary {
     */
     #[rustfmt::skip]
     pub const ALL: &'static [Self] = &[
+        #[cfg(feature = "buffer")]   Self::Buffer,
         #[cfg(feature = "datetime")] Self::DateTime,
+        #[cfg(feature = "events")]   Self::Events,
         #[cfg(feature = "fs")]       Self::Fs,
+        #[cfg(feature = "ipc")]      Self::Ipc,
         #[cfg(feature = "luau")]     Self::Luau,
         #[cfg(feature = "net")]      Self::Net,
         #[cfg(feature = "task")]     Self::Task,
@@ -46,8 +52,11 @@ impl LuneStandardLibrary {
     #[allow(unreachable_patterns)]
     pub fn name(&self) -> &'static str {
         match self {
+            #[cfg(feature = "buffer")]   Self::Buffer   => "buffer",
             #[cfg(feature = "datetime")] Self::DateTime => "datetime",
+            #[cfg(feature = "events")]   Self::Events   => "events",
             #[cfg(feature = "fs")]       Self::Fs       => "fs",
+            #[cfg(feature = "ipc")]      Self::Ipc      => "ipc",
             #[cfg(feature = "luau")]     Self::Luau     => "luau",
             #[cfg(feature = "net")]      Self::Net      => "net",
             #[cfg(feature = "task")]     Self::Task     => "task",
@@ -69,8 +78,11 @@ impl LuneStandardLibrary {
     #[allow(unreachable_patterns)]
     pub fn typedefs(&self) -> String {
     	match self {
+            #[cfg(feature = "buffer")]   Self::Buffer   => lune_std_buffer::typedefs(),
             #[cfg(feature = "datetime")] Self::DateTime => lune_std_datetime::typedefs(),
+            #[cfg(feature = "events")]   Self::Events   => lune_std_events::typedefs(),
             #[cfg(feature = "fs")]       Self::Fs       => lune_std_fs::typedefs(),
+            #[cfg(feature = "ipc")]      Self::Ipc      => lune_std_ipc::typedefs(),
             #[cfg(feature = "luau")]     Self::Luau     => lune_std_luau::typedefs(),
             #[cfg(feature = "net")]      Self::Net      => lune_std_net::typedefs(),
             #[cfg(feature = "task")]     Self::Task     => lune_std_task::typedefs(),
@@ -96,8 +108,11 @@ impl LuneStandardLibrary {
     pub fn module(&self, lua: Lua) -> LuaResult<LuaTable> {
         let mod_lua = lua.clone();
         let res: LuaResult<LuaTable> = match self {
+            #[cfg(feature = "buffer")]   Self::Buffer   => lune_std_buffer::module(mod_lua),
             #[cfg(feature = "datetime")] Self::DateTime => lune_std_datetime::module(mod_lua),
+            #[cfg(feature = "events")]   Self::Events   => lune_std_events::module(mod_lua),
             #[cfg(feature = "fs")]       Self::Fs       => lune_std_fs::module(mod_lua),
+            #[cfg(feature = "ipc")]      Self::Ipc      => lune_std_ipc::module(mod_lua),
             #[cfg(feature = "luau")]     Self::Luau     => lune_std_luau::module(mod_lua),
             #[cfg(feature = "net")]      Self::Net      => lune_std_net::module(mod_lua),
             #[cfg(feature = "task")]     Self::Task     => lune_std_task::module(mod_lua),
@@ -125,8 +140,11 @@ impl FromStr for LuneStandardLibrary {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let low = s.trim().to_ascii_lowercase();
         Ok(match low.as_str() {
+            #[cfg(feature = "buffer")]   "buffer"   => Self::Buffer,
             #[cfg(feature = "datetime")] "datetime" => Self::DateTime,
+            #[cfg(feature = "events")]   "events"   => Self::Events,
             #[cfg(feature = "fs")]       "fs"       => Self::Fs,
+            #[cfg(feature = "ipc")]      "ipc"      => Self::Ipc,
             #[cfg(feature = "luau")]     "luau"     => Self::Luau,
             #[cfg(feature = "net")]      "net"      => Self::Net,
             #[cfg(feature = "task")]     "task"     => Self::Task,
@@ -0,0 +1,73 @@
+use std::collections::BTreeMap;
+
+use mlua::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/**
+    Information about how a standalone binary was built, embedded
+    into it when building, and given to scripts in the `_BUILD` global.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    /// When the binary was built, in seconds since the Unix epoch
+    pub timestamp: u64,
+    /// The version of Lune that built the binary
+    pub lune_version: String,
+    /// The target that the binary was built for, such as `linux-x86_64`
+    pub target: String,
+    /// The commit of the git repository that the binary was built from, if any
+    pub commit: Option<String>,
+    /// Additional fields given using `--build-info`
+    pub fields: BTreeMap<String, String>,
+}
+
+impl BuildInfo {
+    /**
+        Creates a table with the build information, with keys in camel case.
+
+        # Errors
+
+        - If the table could not be created
+    */
+    pub fn to_table(&self, lua: &Lua) -> LuaResult<LuaTable> {
+        let fields = lua.create_table_from(
+            self.fields
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str())),
+        )?;
+        fields.set_readonly(true);
+
+        let table = lua.create_table()?;
+        table.raw_set("timestamp", self.timestamp)?;
+        table.raw_set("luneVersion", self.lune_version.as_str())?;
+        table.raw_set("target", self.target.as_str())?;
+        table.raw_set("commit", self.commit.as_deref())?;
+        table.raw_set("fields", fields)?;
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_info_table() {
+        let lua = Lua::new();
+        let info = BuildInfo {
+            timestamp: 1_700_000_000,
+            lune_version: "0.10.0".to_string(),
+            target: "linux-x86_64".to_string(),
+            commit: None,
+            fields: BTreeMap::from([("channel".to_string(), "beta".to_string())]),
+        };
+        let table = info.to_table(&lua).unwrap();
+        assert_eq!(table.get::<u64>("timestamp").unwrap(), 1_700_000_000);
+        assert_eq!(table.get::<String>("luneVersion").unwrap(), "0.10.0");
+        assert_eq!(table.get::<String>("target").unwrap(), "linux-x86_64");
+        assert!(table.get::<Option<String>>("commit").unwrap().is_none());
+        let fields = table.get::<LuaTable>("fields").unwrap();
+        assert_eq!(fields.get::<String>("channel").unwrap(), "beta");
+        assert!(fields.is_readonly());
+    }
+}
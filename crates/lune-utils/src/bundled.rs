@@ -0,0 +1,105 @@
+use std::{fmt, ops::Range, sync::Arc};
+
+/**
+    Memory that the contents of bundled files are stored in, such as
+    the bytes of a standalone binary that was mapped into memory.
+*/
+pub trait BundledStorage: Send + Sync + 'static {
+    /// Returns all bytes of the storage
+    fn bytes(&self) -> &[u8];
+
+    /**
+        Releases the memory used by the given range of bytes, if possible,
+        which is loaded again from the storage when it is next read.
+
+        Called for the source of a module once its result has been cached,
+        since the source is then only needed again if the cache is cleared.
+    */
+    fn evict(&self, _range: Range<usize>) {}
+}
+
+impl BundledStorage for Vec<u8> {
+    fn bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+/**
+    The contents of a bundled file, as a range of the storage that it is read
+    from, so that files are never copied when they are required or read.
+
+    Clones share the same storage.
+*/
+#[derive(Clone)]
+pub struct BundledFile {
+    storage: Arc<dyn BundledStorage>,
+    range: Range<usize>,
+}
+
+impl BundledFile {
+    /**
+        Creates a bundled file from the given range of the given storage.
+
+        # Panics
+
+        Panics if the range is out of bounds for the storage.
+    */
+    #[must_use]
+    pub fn new(storage: Arc<dyn BundledStorage>, range: Range<usize>) -> Self {
+        assert!(
+            range.start <= range.end && range.end <= storage.bytes().len(),
+            "bundled file range is out of bounds for its storage"
+        );
+        Self { storage, range }
+    }
+
+    /// Returns the contents of the file
+    #[must_use]
+    pub fn bytes(&self) -> &[u8] {
+        &self.storage.bytes()[self.range.clone()]
+    }
+
+    /// Releases the memory used by the contents of the file, if possible
+    pub fn evict(&self) {
+        self.storage.evict(self.range.clone());
+    }
+}
+
+impl From<Vec<u8>> for BundledFile {
+    fn from(contents: Vec<u8>) -> Self {
+        let range = 0..contents.len();
+        Self {
+            storage: Arc::new(contents),
+            range,
+        }
+    }
+}
+
+impl AsRef<[u8]> for BundledFile {
+    fn as_ref(&self) -> &[u8] {
+        self.bytes()
+    }
+}
+
+impl fmt::Debug for BundledFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BundledFile")
+            .field("range", &self.range)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_are_ranges_of_shared_storage() {
+        let storage: Arc<dyn BundledStorage> = Arc::new(b"return 1return 2".to_vec());
+        let first = BundledFile::new(Arc::clone(&storage), 0..8);
+        let second = BundledFile::new(Arc::clone(&storage), 8..16);
+        assert_eq!(first.bytes(), b"return 1");
+        assert_eq!(second.bytes(), b"return 2");
+        assert_eq!(BundledFile::from(b"data".to_vec()).bytes(), b"data");
+    }
+}
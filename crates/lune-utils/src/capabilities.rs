@@ -0,0 +1,284 @@
+/*!
+    Capabilities declared by modules using `--!requires` directives.
+
+    A module may declare the capabilities it needs at the top of its source,
+    such as `--!requires net, fs-read`, and when a runtime checks capabilities,
+    a module may only be required from a context that has been granted all of
+    the capabilities it declares. The script being run is granted the
+    capabilities given to the runtime, and every required module is granted
+    exactly the capabilities that it declares, meaning that modules must
+    declare the capabilities of all modules that they require in turn.
+
+    This is intended for hygiene in plugin ecosystems, and not as a security
+    boundary - directives are read from source code, so modules precompiled
+    to bytecode, or provided by the host application, declare none.
+*/
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashMap},
+    fmt,
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+const DIRECTIVE_PREFIX: &str = "--!requires";
+
+/**
+    How capabilities declared by modules are checked.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CapabilityMode {
+    /// Requiring a module without its capabilities being granted is an error.
+    #[default]
+    Enforce,
+    /// Requiring a module without its capabilities being granted prints a warning.
+    Audit,
+}
+
+impl FromStr for CapabilityMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "enforce" => Ok(Self::Enforce),
+            "audit" => Ok(Self::Audit),
+            _ => Err(format!(
+                "invalid capability mode '{s}', expected 'enforce' or 'audit'"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for CapabilityMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Enforce => write!(f, "enforce"),
+            Self::Audit => write!(f, "audit"),
+        }
+    }
+}
+
+/**
+    A module that was required without all of its capabilities being granted.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapabilityViolation {
+    /// Path of the module that was required.
+    pub module: String,
+    /// Path of the module that required it, or `None` for the script being run.
+    pub context: Option<String>,
+    /// Capabilities declared by the module that were not granted.
+    pub missing: BTreeSet<String>,
+}
+
+impl fmt::Display for CapabilityViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let missing = self.missing.iter().cloned().collect::<Vec<_>>().join(", ");
+        match &self.context {
+            Some(context) => write!(
+                f,
+                "module '{}' requires capabilities that are not granted to '{context}': {missing}",
+                self.module
+            ),
+            None => write!(
+                f,
+                "module '{}' requires capabilities that are not granted to the runtime: {missing}",
+                self.module
+            ),
+        }
+    }
+}
+
+/**
+    Capabilities granted to a runtime, and those declared by
+    each module that has been required so far, stored as app data.
+
+    Only present when the runtime checks capabilities.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct Capabilities {
+    mode: CapabilityMode,
+    granted: BTreeSet<String>,
+    modules: RefCell<HashMap<String, BTreeSet<String>>>,
+}
+
+impl Capabilities {
+    /**
+        Creates a new set of capabilities granted to the script being run.
+    */
+    #[must_use]
+    pub fn new<I, S>(mode: CapabilityMode, granted: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            mode,
+            granted: granted.into_iter().map(Into::into).collect(),
+            modules: RefCell::default(),
+        }
+    }
+
+    /**
+        Returns how capabilities declared by modules are checked.
+    */
+    #[must_use]
+    pub fn mode(&self) -> CapabilityMode {
+        self.mode
+    }
+
+    /**
+        Returns the capabilities granted to the script being run.
+    */
+    #[must_use]
+    pub fn granted(&self) -> &BTreeSet<String> {
+        &self.granted
+    }
+
+    /**
+        Checks that the given context, which is the path of the requiring module
+        or `None` for the script being run, has been granted all of the capabilities
+        that are declared by the module at the given path, and records them as the
+        capabilities granted to that module, for any requires it makes in turn.
+
+        Contexts that are not modules required so far are treated as the
+        script being run, and are granted the capabilities of the runtime.
+
+        # Errors
+
+        - If any declared capabilities have not been granted to the context
+    */
+    pub fn check(
+        &self,
+        context: Option<&str>,
+        module: &str,
+        declared: BTreeSet<String>,
+    ) -> Result<(), CapabilityViolation> {
+        let missing = {
+            let modules = self.modules.borrow();
+            let (context, granted) = match context.and_then(|c| modules.get_key_value(c)) {
+                Some((context, granted)) => (Some(context.clone()), granted),
+                None => (None, &self.granted),
+            };
+            let missing = declared
+                .difference(granted)
+                .cloned()
+                .collect::<BTreeSet<_>>();
+            (!missing.is_empty()).then_some((context, missing))
+        };
+
+        self.modules
+            .borrow_mut()
+            .insert(module.to_string(), declared);
+
+        match missing {
+            None => Ok(()),
+            Some((context, missing)) => Err(CapabilityViolation {
+                module: module.to_string(),
+                context,
+                missing,
+            }),
+        }
+    }
+
+    /**
+        Checks that the given context has been granted all of the capabilities
+        declared by a module that has already been required, and recorded using
+        [`Capabilities::check`], such as when it is cached.
+
+        # Errors
+
+        - If any declared capabilities have not been granted to the context
+    */
+    pub fn check_recorded(
+        &self,
+        context: Option<&str>,
+        module: &str,
+    ) -> Result<(), CapabilityViolation> {
+        let declared = self.modules.borrow().get(module).cloned();
+        match declared {
+            Some(declared) => self.check(context, module, declared),
+            None => Ok(()),
+        }
+    }
+}
+
+/**
+    Parses all `--!requires` directives at the top of the given source,
+    before any code, returning the names of the declared capabilities.
+
+    Capabilities may be separated by commas and / or whitespace, and
+    multiple directives may be given, such as `--!requires net, fs-read`.
+*/
+#[must_use]
+pub fn parse_requires_directives(source: &[u8]) -> BTreeSet<String> {
+    let mut capabilities = BTreeSet::new();
+    for (index, line) in source.split(|b| *b == b'\n').enumerate() {
+        let Ok(line) = std::str::from_utf8(line) else {
+            break;
+        };
+        let line = line.trim();
+        if line.is_empty() || (index == 0 && line.starts_with("#!")) {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix(DIRECTIVE_PREFIX)
+            && (rest.is_empty() || rest.starts_with(char::is_whitespace))
+        {
+            capabilities.extend(
+                rest.split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|name| !name.is_empty())
+                    .map(ToString::to_string),
+            );
+        } else if !line.starts_with("--") || line.starts_with("--[") {
+            // Directives must come before any code or block comments
+            break;
+        }
+    }
+    capabilities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn parses_directives() {
+        let source = b"#!/usr/bin/env lune\n--!strict\n--!requires net, fs-read\n--!requires  process\n\nreturn {}";
+        assert_eq!(
+            parse_requires_directives(source),
+            names(&["fs-read", "net", "process"])
+        );
+    }
+
+    #[test]
+    fn ignores_directives_after_code() {
+        let source = b"local x = 1\n--!requires net\nreturn x";
+        assert!(parse_requires_directives(source).is_empty());
+        let source = b"--!requiresnet\nreturn {}";
+        assert!(parse_requires_directives(source).is_empty());
+    }
+
+    #[test]
+    fn checks_granted_capabilities() {
+        let caps = Capabilities::new(CapabilityMode::Enforce, ["net", "fs-read"]);
+        assert!(caps.check(None, "/a.luau", names(&["net"])).is_ok());
+
+        // The module is only granted what it declares
+        let violation = caps
+            .check(Some("/a.luau"), "/b.luau", names(&["net", "fs-read"]))
+            .unwrap_err();
+        assert_eq!(violation.context.as_deref(), Some("/a.luau"));
+        assert_eq!(violation.missing, names(&["fs-read"]));
+
+        // Unknown contexts are the script being run
+        assert!(caps.check_recorded(Some("main"), "/b.luau").is_ok());
+        assert!(caps.check_recorded(Some("/a.luau"), "/b.luau").is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use std::io::ErrorKind;
+
+use mlua::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// NOTE: These are matched against the messages of errors thrown by `require`,
+// both from our own implementation and from the built-in require function
+const REQUIRE_ERROR_MESSAGES: &[&str] = &[
+    "cannot find module",
+    "cannot find built-in module",
+    "cannot find alias",
+    "ambiguous module",
+    "cannot require directory",
+    "require expects a string",
+];
+
+// NOTE: Luau uses the first message for writes to readonly tables, which
+// includes the global table and all standard libraries once sandboxed,
+// and the second is used when a module declares capabilities using
+// `--!requires` directives that the requiring module was not granted
+const SANDBOX_VIOLATION_MESSAGES: &[&str] = &[
+    "attempt to modify a readonly table",
+    "requires capabilities that are not granted",
+];
+
+const TIMEOUT_MESSAGES: &[&str] = &["timed out", "timeout"];
+
+/**
+    The class of failure that caused a Lune runtime to exit unsuccessfully.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RuntimeFailure {
+    /// An error was thrown while running the script.
+    RuntimeError,
+    /// A call to `require` could not be resolved.
+    RequireError,
+    /// The script tried to modify a sandboxed global or standard library,
+    /// or required a module without the capabilities that it declares.
+    SandboxViolation,
+    /// An operation in the script timed out.
+    Timeout,
+}
+
+impl RuntimeFailure {
+    /**
+        Classifies the given error into a class of failure.
+
+        Errors that are not recognized as any other class are runtime errors.
+    */
+    #[must_use]
+    pub fn classify(error: &LuaError) -> Self {
+        if is_timeout(error) {
+            return Self::Timeout;
+        }
+        let message = error.to_string();
+        if REQUIRE_ERROR_MESSAGES.iter().any(|m| message.contains(m))
+            || (message.contains("module '") && message.contains("' not found"))
+        {
+            Self::RequireError
+        } else if SANDBOX_VIOLATION_MESSAGES
+            .iter()
+            .any(|m| message.contains(m))
+        {
+            Self::SandboxViolation
+        } else if TIMEOUT_MESSAGES
+            .iter()
+            .any(|m| message.to_ascii_lowercase().contains(m))
+        {
+            Self::Timeout
+        } else {
+            Self::RuntimeError
+        }
+    }
+}
+
+/**
+    Process exit codes to use for each class of failure.
+
+    Each exit code defaults to `1`, the exit code used for any failure
+    when no exit codes have been configured. An exit code set by the
+    script itself, using `process.exit`, always takes precedence.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ExitCodes {
+    /// Exit code for errors thrown while running the script.
+    pub runtime_error: u8,
+    /// Exit code for calls to `require` that could not be resolved.
+    pub require_error: u8,
+    /// Exit code for modifications of sandboxed globals and standard libraries,
+    /// and requires of modules without the capabilities that they declare.
+    pub sandbox_violation: u8,
+    /// Exit code for operations that timed out.
+    pub timeout: u8,
+}
+
+impl ExitCodes {
+    /**
+        Returns the exit code to use for the given class of failure.
+    */
+    #[must_use]
+    pub fn get(&self, failure: RuntimeFailure) -> u8 {
+        match failure {
+            RuntimeFailure::RuntimeError => self.runtime_error,
+            RuntimeFailure::RequireError => self.require_error,
+            RuntimeFailure::SandboxViolation => self.sandbox_violation,
+            RuntimeFailure::Timeout => self.timeout,
+        }
+    }
+
+    /**
+        Returns whether all exit codes are the default exit code.
+    */
+    #[must_use]
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Default for ExitCodes {
+    fn default() -> Self {
+        Self {
+            runtime_error: 1,
+            require_error: 1,
+            sandbox_violation: 1,
+            timeout: 1,
+        }
+    }
+}
+
+fn is_timeout(error: &LuaError) -> bool {
+    match error {
+        LuaError::CallbackError { cause, .. } => is_timeout(cause),
+        LuaError::WithContext { cause, .. } => is_timeout(cause),
+        LuaError::ExternalError(e) => e
+            .downcast_ref::<std::io::Error>()
+            .is_some_and(|e| e.kind() == ErrorKind::TimedOut),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_errors() {
+        let classify = |message: &str| RuntimeFailure::classify(&LuaError::runtime(message));
+        assert_eq!(
+            classify("cannot find module './missing': NotFound"),
+            RuntimeFailure::RequireError
+        );
+        assert_eq!(
+            classify("cannot find alias 'pkg'"),
+            RuntimeFailure::RequireError
+        );
+        assert_eq!(
+            classify("ambiguous module './utils', it matches 'utils.luau' and 'utils/init.luau'"),
+            RuntimeFailure::RequireError
+        );
+        assert_eq!(
+            classify("[string \"main\"]:1: attempt to modify a readonly table"),
+            RuntimeFailure::SandboxViolation
+        );
+        assert_eq!(
+            classify(
+                "module '/plugin.luau' requires capabilities that are not granted to '/main.luau': net"
+            ),
+            RuntimeFailure::SandboxViolation
+        );
+        assert_eq!(classify("request timed out"), RuntimeFailure::Timeout);
+        assert_eq!(
+            classify("attempt to index nil"),
+            RuntimeFailure::RuntimeError
+        );
+    }
+
+    #[test]
+    fn classify_io_timeout() {
+        let error = LuaError::external(std::io::Error::from(ErrorKind::TimedOut));
+        assert_eq!(RuntimeFailure::classify(&error), RuntimeFailure::Timeout);
+    }
+
+    #[test]
+    fn exit_codes_deserialize() {
+        let codes: ExitCodes =
+            serde_json::from_str(r#"{ "require-error": 3, "timeout": 124 }"#).unwrap();
+        assert_eq!(codes.get(RuntimeFailure::RequireError), 3);
+        assert_eq!(codes.get(RuntimeFailure::Timeout), 124);
+        assert_eq!(codes.get(RuntimeFailure::RuntimeError), 1);
+        assert!(!codes.is_default());
+        assert!(serde_json::from_str::<ExitCodes>(r#"{ "unknown": 2 }"#).is_err());
+    }
+}
@@ -4,4 +4,4 @@ mod value;
 
 pub use self::error::{ErrorComponents, StackTrace, StackTraceLine, StackTraceSource};
 pub use self::label::Label;
-pub use self::value::{ValueFormatConfig, pretty_format_multi_value, pretty_format_value};
+pub use self::value::{FormatLimits, ValueFormatConfig, pretty_format_multi_value, pretty_format_value};
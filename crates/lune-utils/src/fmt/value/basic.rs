@@ -46,16 +46,7 @@ pub(crate) fn format_value_styled(value: &LuaValue, prefer_plain: bool) -> Strin
         LuaValue::Boolean(false) => COLOR_YELLOW.apply_to("false").to_string(),
         LuaValue::Number(n) => COLOR_CYAN.apply_to(n).to_string(),
         LuaValue::Integer(i) => COLOR_CYAN.apply_to(i).to_string(),
-        LuaValue::String(s) if prefer_plain => s.to_string_lossy().to_string(),
-        LuaValue::String(s) => COLOR_GREEN
-            .apply_to({
-                let mut s = s.to_string_lossy().to_string();
-                for (from, to) in STRING_REPLACEMENTS {
-                    s = s.replace(from, to);
-                }
-                format!(r#""{s}""#)
-            })
-            .to_string(),
+        LuaValue::String(s) => format_string_styled(&s.to_string_lossy(), prefer_plain),
         LuaValue::Other(_) => COLOR_MAGENTA.apply_to("<unknown>").to_string(),
         LuaValue::Buffer(_) => COLOR_MAGENTA.apply_to("<buffer>").to_string(),
         LuaValue::Vector(_) => COLOR_MAGENTA.apply_to("<vector>").to_string(),
@@ -87,6 +78,20 @@ pub(crate) fn format_value_styled(value: &LuaValue, prefer_plain: bool) -> Strin
     }
 }
 
+/**
+    Formats a string into a pretty string, quoting and escaping it unless `prefer_plain` is set.
+*/
+pub(crate) fn format_string_styled(s: &str, prefer_plain: bool) -> String {
+    if prefer_plain {
+        return s.to_string();
+    }
+    let mut s = s.to_string();
+    for (from, to) in STRING_REPLACEMENTS {
+        s = s.replace(from, to);
+    }
+    COLOR_GREEN.apply_to(format!(r#""{s}""#)).to_string()
+}
+
 fn format_typename_and_tostringed(
     fallback: &'static str,
     typename: Option<String>,
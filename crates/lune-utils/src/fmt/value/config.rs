@@ -1,3 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+/**
+    Limits for formatting values, which keep huge values from
+    freezing terminals and flooding logs when they are formatted.
+
+    Each limit is disabled when set to zero.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct FormatLimits {
+    /// Maximum number of entries to format for each table.
+    pub max_items: usize,
+    /// Maximum number of bytes to format for each string inside of a table.
+    pub max_string_length: usize,
+    /// Maximum number of bytes to format for all values, across a single call.
+    pub max_output_length: usize,
+}
+
+impl FormatLimits {
+    /// Limits that never elide anything.
+    pub const UNLIMITED: Self = Self {
+        max_items: 0,
+        max_string_length: 0,
+        max_output_length: 0,
+    };
+
+    /**
+        Creates the default limits, which are generous enough
+        to never elide values that are reasonable to print.
+    */
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            max_items: 1_000,
+            max_string_length: 10_000,
+            max_output_length: 1_000_000,
+        }
+    }
+}
+
+impl Default for FormatLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /**
     Configuration for formatting values.
 */
@@ -5,6 +52,7 @@
 pub struct ValueFormatConfig {
     pub(super) max_depth: usize,
     pub(super) colors_enabled: bool,
+    pub(super) limits: FormatLimits,
 }
 
 impl ValueFormatConfig {
@@ -16,6 +64,7 @@ impl ValueFormatConfig {
         Self {
             max_depth: 3,
             colors_enabled: false,
+            limits: FormatLimits::UNLIMITED,
         }
     }
 
@@ -39,6 +88,16 @@ impl ValueFormatConfig {
             ..self
         }
     }
+
+    /**
+        Sets the limits for formatting large values.
+
+        Values are not limited by default.
+    */
+    #[must_use]
+    pub const fn with_limits(self, limits: FormatLimits) -> Self {
+        Self { limits, ..self }
+    }
 }
 
 impl Default for ValueFormatConfig {
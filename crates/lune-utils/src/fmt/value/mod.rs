@@ -1,7 +1,4 @@
-use std::{
-    collections::HashSet,
-    sync::{Arc, LazyLock},
-};
+use std::sync::{Arc, LazyLock};
 
 use console::{colors_enabled as get_colors_enabled, set_colors_enabled};
 use mlua::prelude::*;
@@ -13,9 +10,9 @@ mod metamethods;
 mod recursive;
 mod style;
 
-use self::recursive::format_value_recursive;
+use self::recursive::{FormatState, format_value_recursive};
 
-pub use self::config::ValueFormatConfig;
+pub use self::config::{FormatLimits, ValueFormatConfig};
 
 // NOTE: Since the setting for colors being enabled is global,
 // and these functions may be called in parallel, we use this global
@@ -34,8 +31,8 @@ pub fn pretty_format_value(value: &LuaValue, config: &ValueFormatConfig) -> Stri
     let were_colors_enabled = get_colors_enabled();
     set_colors_enabled(were_colors_enabled && config.colors_enabled);
 
-    let mut visited = HashSet::new();
-    let res = format_value_recursive(value, config, &mut visited, 0);
+    let mut state = FormatState::default();
+    let res = format_value_recursive(value, config, &mut state, 0);
 
     set_colors_enabled(were_colors_enabled);
     res.expect("using fmt for writing into strings should never fail")
@@ -54,13 +51,16 @@ pub fn pretty_format_multi_value(values: &LuaMultiValue, config: &ValueFormatCon
     let were_colors_enabled = get_colors_enabled();
     set_colors_enabled(were_colors_enabled && config.colors_enabled);
 
-    let mut visited = HashSet::new();
+    let mut state = FormatState::default();
     let res = values
         .into_iter()
-        .map(|value| format_value_recursive(value, config, &mut visited, 0))
+        .map(|value| format_value_recursive(value, config, &mut state, 0))
         .collect::<Result<Vec<_>, _>>();
 
     set_colors_enabled(were_colors_enabled);
     res.expect("using fmt for writing into strings should never fail")
         .join(" ")
 }
+
+#[cfg(test)]
+mod tests;
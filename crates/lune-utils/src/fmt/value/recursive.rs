@@ -6,7 +6,7 @@ use mlua::prelude::*;
 
 use super::metamethods::{call_table_tostring_metamethod, get_table_type_metavalue};
 use super::{
-    basic::{format_value_styled, lua_value_as_plain_string_key},
+    basic::{format_string_styled, format_value_styled, lua_value_as_plain_string_key},
     config::ValueFormatConfig,
     style::STYLE_DIM,
 };
@@ -31,6 +31,23 @@ impl From<&LuaTable> for LuaValueId {
     }
 }
 
+/**
+    State shared between all values formatted in a single call - the tables
+    currently being formatted, and the number of bytes formatted so far.
+*/
+#[derive(Debug, Default)]
+pub(crate) struct FormatState {
+    visited: HashSet<LuaValueId>,
+    written: usize,
+}
+
+impl FormatState {
+    fn is_exhausted(&self, config: &ValueFormatConfig) -> bool {
+        let limit = config.limits.max_output_length;
+        limit > 0 && self.written >= limit
+    }
+}
+
 /**
     Formats the given value, recursively formatting tables
     up to the maximum depth specified in the config.
@@ -41,12 +58,14 @@ impl From<&LuaTable> for LuaValueId {
 pub(crate) fn format_value_recursive(
     value: &LuaValue,
     config: &ValueFormatConfig,
-    visited: &mut HashSet<LuaValueId>,
+    state: &mut FormatState,
     depth: usize,
 ) -> Result<String, fmt::Error> {
     let mut buffer = String::new();
 
-    if let LuaValue::Table(t) = value {
+    if state.is_exhausted(config) {
+        write!(buffer, "{}", STYLE_DIM.apply_to("..."))?;
+    } else if let LuaValue::Table(t) = value {
         if let Some(formatted) = format_typename_and_tostringed(
             get_table_type_metavalue(t),
             call_table_tostring_metamethod(t),
@@ -54,7 +73,7 @@ pub(crate) fn format_value_recursive(
             write!(buffer, "{formatted}")?;
         } else if depth >= config.max_depth {
             write!(buffer, "{}", STYLE_DIM.apply_to("{ ... }"))?;
-        } else if !visited.insert(LuaValueId::from(t)) {
+        } else if !state.visited.insert(LuaValueId::from(t)) {
             write!(buffer, "{}", STYLE_DIM.apply_to("{ recursive }"))?;
         } else {
             write!(buffer, "{}", STYLE_DIM.apply_to("{"))?;
@@ -72,13 +91,27 @@ pub(crate) fn format_value_recursive(
                 .enumerate()
                 .all(|(i, (key, _))| key.as_integer().is_some_and(|x| x == (i as i64) + 1));
 
-            let formatted_values = if is_array {
-                format_array(values, config, visited, depth)?
+            let total = values.len();
+            let limit = config.limits.max_items;
+            if limit > 0 {
+                values.truncate(limit);
+            }
+
+            let mut formatted_values = if is_array {
+                format_array(values, config, state, depth)?
             } else {
-                format_table(values, config, visited, depth)?
+                format_table(values, config, state, depth)?
             };
+            if formatted_values.len() < total {
+                let elided = format!("... {} more items", total - formatted_values.len());
+                formatted_values.push(format!(
+                    "{}{}",
+                    INDENT.repeat(1 + depth),
+                    STYLE_DIM.apply_to(elided)
+                ));
+            }
 
-            visited.remove(&LuaValueId::from(t));
+            state.visited.remove(&LuaValueId::from(t));
 
             if is_empty {
                 write!(buffer, " {}", STYLE_DIM.apply_to("}"))?;
@@ -94,7 +127,12 @@ pub(crate) fn format_value_recursive(
         }
     } else {
         let prefer_plain = depth == 0;
-        write!(buffer, "{}", format_value_styled(value, prefer_plain))?;
+        let formatted = match value {
+            LuaValue::String(s) => format_string_limited(s, config, prefer_plain),
+            value => format_value_styled(value, prefer_plain),
+        };
+        state.written += formatted.len();
+        write!(buffer, "{formatted}")?;
     }
 
     Ok(buffer)
@@ -122,63 +160,77 @@ fn sort_for_formatting(values: &mut [(LuaValue, LuaValue)]) {
 fn format_array(
     values: Vec<(LuaValue, LuaValue)>,
     config: &ValueFormatConfig,
-    visited: &mut HashSet<LuaValueId>,
+    state: &mut FormatState,
     depth: usize,
 ) -> Result<Vec<String>, fmt::Error> {
-    values
-        .into_iter()
-        .map(|(_, value)| {
-            Ok(format!(
-                "{}{}{}",
-                INDENT.repeat(1 + depth),
-                format_value_recursive(&value, config, visited, depth + 1)?,
-                STYLE_DIM.apply_to(","),
-            ))
-        })
-        .collect()
+    let mut formatted = Vec::with_capacity(values.len());
+    for (_, value) in values {
+        if state.is_exhausted(config) {
+            break;
+        }
+        formatted.push(format!(
+            "{}{}{}",
+            INDENT.repeat(1 + depth),
+            format_value_recursive(&value, config, state, depth + 1)?,
+            STYLE_DIM.apply_to(","),
+        ));
+    }
+    Ok(formatted)
 }
 
 fn format_table(
     values: Vec<(LuaValue, LuaValue)>,
     config: &ValueFormatConfig,
-    visited: &mut HashSet<LuaValueId>,
+    state: &mut FormatState,
     depth: usize,
 ) -> Result<Vec<String>, fmt::Error> {
-    values
-        .into_iter()
-        .map(|(key, value)| {
+    let mut formatted = Vec::with_capacity(values.len());
+    for (key, value) in values {
+        if state.is_exhausted(config) {
+            break;
+        }
+        formatted.push(
             if let Some(plain_key) = lua_value_as_plain_string_key(&key) {
-                Ok(format!(
+                format!(
                     "{}{plain_key} {} {}{}",
                     INDENT.repeat(1 + depth),
                     STYLE_DIM.apply_to("="),
-                    format_value_recursive(&value, config, visited, depth + 1)?,
+                    format_value_recursive(&value, config, state, depth + 1)?,
                     STYLE_DIM.apply_to(","),
-                ))
+                )
             } else {
-                Ok(format!(
+                format!(
                     "{}{}{}{} {} {}{}",
                     INDENT.repeat(1 + depth),
                     STYLE_DIM.apply_to("["),
-                    format_value_recursive(&key, config, visited, depth + 1)?,
+                    format_value_recursive(&key, config, state, depth + 1)?,
                     STYLE_DIM.apply_to("]"),
                     STYLE_DIM.apply_to("="),
-                    format_value_recursive(&value, config, visited, depth + 1)?,
+                    format_value_recursive(&value, config, state, depth + 1)?,
                     STYLE_DIM.apply_to(","),
-                ))
-            }
-        })
-        .collect()
+                )
+            },
+        );
+    }
+    Ok(formatted)
 }
 
-fn format_typename_and_tostringed(
-    typename: Option<String>,
-    tostringed: Option<String>,
-) -> Option<String> {
-    match (typename, tostringed) {
-        (Some(typename), Some(tostringed)) => Some(format!("<{typename}({tostringed})>")),
-        (Some(typename), None) => Some(format!("<{typename}>")),
-        (None, Some(tostringed)) => Some(tostringed),
-        (None, None) => None,
+/**
+    Formats the given string, eliding anything past the maximum string length.
+
+    Plain strings, which are given directly instead of inside of tables, are never elided,
+    since printing them is a common way to write output that should stay intact.
+*/
+fn format_string_limited(s: &LuaString, config: &ValueFormatConfig, prefer_plain: bool) -> String {
+    let bytes = s.as_bytes();
+    let limit = config.limits.max_string_length;
+    if limit == 0 || prefer_plain || bytes.len() <= limit {
+        return format_string_styled(&String::from_utf8_lossy(&bytes), prefer_plain);
     }
+    let elided = format!("... {} more bytes", bytes.len() - limit);
+    format!(
+        "{} {}",
+        format_string_styled(&String::from_utf8_lossy(&bytes[..limit]), prefer_plain),
+        STYLE_DIM.apply_to(elided)
+    )
 }
@@ -0,0 +1,62 @@
+use mlua::prelude::*;
+
+use crate::fmt::{FormatLimits, ValueFormatConfig, pretty_format_multi_value, pretty_format_value};
+
+const LIMITS: FormatLimits = FormatLimits {
+    max_items: 3,
+    max_string_length: 5,
+    max_output_length: 0,
+};
+
+fn eval(lua: &Lua, source: &str) -> LuaValue {
+    lua.load(source).eval().unwrap()
+}
+
+#[test]
+fn unlimited_by_default() {
+    let lua = Lua::new();
+    let value = eval(&lua, "local t = {} for i = 1, 10 do t[i] = i end return t");
+    let formatted = pretty_format_value(&value, &ValueFormatConfig::new());
+    assert!(formatted.contains("10,"));
+    assert!(!formatted.contains("more items"));
+}
+
+#[test]
+fn elides_table_items() {
+    let lua = Lua::new();
+    let value = eval(&lua, "local t = {} for i = 1, 10 do t[i] = i end return t");
+    let config = ValueFormatConfig::new().with_limits(LIMITS);
+    let formatted = pretty_format_value(&value, &config);
+    assert!(formatted.contains("3,"));
+    assert!(!formatted.contains("4,"));
+    assert!(formatted.contains("... 7 more items"));
+}
+
+#[test]
+fn elides_long_strings() {
+    let lua = Lua::new();
+    let value = eval(&lua, "return { 'abcdefghij' }");
+    let config = ValueFormatConfig::new().with_limits(LIMITS);
+    let formatted = pretty_format_value(&value, &config);
+    assert!(formatted.contains("\"abcde\" ... 5 more bytes"));
+
+    let value = eval(&lua, "return 'abcdefghij'");
+    assert_eq!(pretty_format_value(&value, &config), "abcdefghij");
+}
+
+#[test]
+fn stops_at_output_length() {
+    let lua = Lua::new();
+    let values = lua
+        .load("local t = {} for i = 1, 100000 do t[i] = i end return t, 'after'")
+        .eval::<LuaMultiValue>()
+        .unwrap();
+    let config = ValueFormatConfig::new().with_limits(FormatLimits {
+        max_output_length: 100,
+        ..FormatLimits::UNLIMITED
+    });
+    let formatted = pretty_format_multi_value(&values, &config);
+    assert!(formatted.len() < 2_000);
+    assert!(formatted.contains("more items"));
+    assert!(!formatted.contains("after"));
+}
@@ -3,9 +3,22 @@
 mod table_builder;
 mod version_string;
 
+pub mod build_info;
+pub mod bundled;
+pub mod capabilities;
+pub mod exit_codes;
 pub mod fmt;
+pub mod luaurc;
+pub mod namespace;
+pub mod packages;
 pub mod path;
+pub mod platform;
 pub mod process;
+pub mod remote;
+pub mod require_allow_list;
+pub mod require_graph;
+pub mod require_trace;
+pub mod shutdown;
 
 pub use self::table_builder::TableBuilder;
 pub use self::version_string::get_version_string;
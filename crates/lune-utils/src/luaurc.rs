@@ -0,0 +1,206 @@
+/*!
+    Luau configuration files, `.luaurc`, and the aliases they define for `require`.
+
+    Aliases follow the semantics of Luau configs: a config inherits all aliases of the
+    configs in its ancestor directories, and may override any of them. Each alias is
+    relative to the directory of the config that defines it, and alias names are
+    matched case-insensitively.
+*/
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::packages::{PACKAGE_MANIFESTS, PackageAliases};
+use crate::path::constants::FILE_NAME_CONFIG;
+
+/**
+    A Luau configuration file, `.luaurc`.
+*/
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LuauConfig {
+    /// Aliases for `require`, such as `pkg` for `@pkg/module` - alias name -> path
+    #[serde(default)]
+    pub aliases: HashMap<String, String>,
+    /// Paths that bare requires, such as `utils/strings`, are searched in
+    #[serde(default)]
+    pub paths: Vec<String>,
+}
+
+impl LuauConfig {
+    /**
+        Parses the contents of a configuration file, returning `None` if they are invalid.
+    */
+    #[must_use]
+    pub fn parse(contents: &[u8]) -> Option<Self> {
+        serde_json::from_slice(contents).ok()
+    }
+}
+
+/**
+    Aliases merged from all configs and package manifests in a directory and its ancestors.
+
+    Configs must be added in order from the root directory, so that configs in
+    nested directories override the aliases defined by those in their ancestors.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergedAliases {
+    aliases: HashMap<String, PathBuf>,
+}
+
+impl MergedAliases {
+    /**
+        Discovers and merges the aliases of all configs and package manifests
+        in the given directory and its ancestors, read from the file system.
+    */
+    #[must_use]
+    pub fn discover(dir: &Path) -> Self {
+        Self::discover_with(dir, |path| fs::read(path).ok())
+    }
+
+    /**
+        Discovers and merges the aliases of all configs and package manifests
+        in the given directory and its ancestors, using the given function to
+        read files - it should return `None` for files that do not exist.
+    */
+    #[must_use]
+    pub fn discover_with(dir: &Path, mut read: impl FnMut(&Path) -> Option<Vec<u8>>) -> Self {
+        let mut merged = Self::default();
+        for ancestor in dir.ancestors().collect::<Vec<_>>().into_iter().rev() {
+            let packages = PACKAGE_MANIFESTS.iter().find_map(|file_name| {
+                let contents = read(&ancestor.join(file_name))?;
+                PackageAliases::from_manifest(file_name, &contents)
+            });
+            if let Some(packages) = packages {
+                merged.add_packages(ancestor, &packages);
+            }
+            let config = read(&ancestor.join(FILE_NAME_CONFIG));
+            if let Some(config) = config.as_deref().and_then(LuauConfig::parse) {
+                merged.add_config(ancestor, &config);
+            }
+        }
+        merged
+    }
+
+    /**
+        Merges the aliases of a config found in the given directory, overriding existing ones.
+    */
+    pub fn add_config(&mut self, dir: &Path, config: &LuauConfig) {
+        for (name, path) in &config.aliases {
+            self.aliases.insert(name.to_lowercase(), dir.join(path));
+        }
+    }
+
+    /**
+        Merges the aliases of a package manifest found in the given directory, overriding
+        existing ones - a config in the same directory should be added after its manifest.
+    */
+    pub fn add_packages(&mut self, dir: &Path, packages: &PackageAliases) {
+        for (name, path) in packages.iter() {
+            self.aliases.insert(name.to_string(), dir.join(path));
+        }
+    }
+
+    /**
+        Gets the path that the alias with the given name resolves to.
+    */
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Path> {
+        self.aliases.get(&name.to_lowercase()).map(PathBuf::as_path)
+    }
+
+    /**
+        Returns an iterator over all aliases, as lowercase alias names and paths.
+    */
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Path)> {
+        self.aliases
+            .iter()
+            .map(|(name, path)| (name.as_str(), path.as_path()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(aliases: &[(&str, &str)]) -> LuauConfig {
+        LuauConfig {
+            aliases: aliases
+                .iter()
+                .map(|(name, path)| ((*name).to_string(), (*path).to_string()))
+                .collect(),
+            paths: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn nested_configs_inherit_and_override() {
+        let mut merged = MergedAliases::default();
+        merged.add_config(
+            Path::new("/project"),
+            &config(&[("pkg", "./Packages"), ("Utils", "./utils")]),
+        );
+        merged.add_config(Path::new("/project/src"), &config(&[("pkg", "./vendor")]));
+
+        assert_eq!(merged.get("pkg"), Some(Path::new("/project/src/vendor")));
+        assert_eq!(merged.get("utils"), Some(Path::new("/project/utils")));
+        assert_eq!(merged.get("UTILS"), Some(Path::new("/project/utils")));
+        assert_eq!(merged.get("missing"), None);
+    }
+
+    #[test]
+    fn configs_override_package_manifests() {
+        let packages =
+            PackageAliases::from_manifest("wally.toml", b"[dependencies]\nRoact = \"a/b@1.0.0\"")
+                .unwrap();
+        let mut merged = MergedAliases::default();
+        merged.add_packages(Path::new("/project"), &packages);
+        assert_eq!(
+            merged.get("roact"),
+            Some(Path::new("/project/Packages/Roact"))
+        );
+        merged.add_config(Path::new("/project"), &config(&[("roact", "./roact")]));
+        assert_eq!(merged.get("roact"), Some(Path::new("/project/roact")));
+    }
+
+    #[test]
+    fn discovers_aliases_in_ancestors() {
+        let files = HashMap::from([
+            (
+                "/project/.luaurc",
+                r#"{"aliases":{"pkg":"./Packages","utils":"./utils"}}"#,
+            ),
+            (
+                "/project/wally.toml",
+                "[dependencies]\nRoact = \"a/b@1.0.0\"",
+            ),
+            ("/project/src/.luaurc", r#"{"aliases":{"pkg":"./vendor"}}"#),
+        ]);
+        let merged = MergedAliases::discover_with(Path::new("/project/src/nested"), |path| {
+            let path = path.to_str()?.replace('\\', "/");
+            files
+                .get(path.as_str())
+                .map(|contents| contents.as_bytes().to_vec())
+        });
+
+        assert_eq!(merged.get("pkg"), Some(Path::new("/project/src/vendor")));
+        assert_eq!(merged.get("utils"), Some(Path::new("/project/utils")));
+        assert_eq!(
+            merged.get("roact"),
+            Some(Path::new("/project/Packages/Roact"))
+        );
+    }
+
+    #[test]
+    fn parses_configs() {
+        let parsed =
+            LuauConfig::parse(br#"{"aliases":{"pkg":"./Packages"},"paths":["./lib"]}"#).unwrap();
+        assert_eq!(parsed.aliases["pkg"], "./Packages");
+        assert_eq!(parsed.paths, ["./lib"]);
+        assert!(LuauConfig::parse(b"{").is_none());
+    }
+}
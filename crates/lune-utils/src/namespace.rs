@@ -0,0 +1,195 @@
+/*!
+    Namespaces of modules provided by the application embedding Lune.
+
+    A namespace, such as `@mycompany`, covers all requires starting with it, such as
+    `require("@mycompany/http")`. Modules in a namespace are not known up front, and
+    are instead looked up lazily the first time they are required, by calling back
+    into the host application, which may provide either a value or Luau source code.
+
+    Namespaces may also be registered to fall back, which lets a [`RequireResolver`]
+    intercept only some modules of an alias - any module that the resolver does
+    not provide then uses the default resolution of aliases and files instead.
+*/
+
+use std::{fmt, rc::Rc};
+
+use mlua::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/**
+    A module provided by a namespace.
+*/
+#[derive(Debug, Clone)]
+pub enum NamespaceModule {
+    /// A value to return directly from `require`.
+    Value(LuaValue),
+    /// Luau source code, or bytecode, to run as a module - the
+    /// first value returned by the module is returned from `require`.
+    Source(Vec<u8>),
+}
+
+/// Function that looks up a module by its name inside of a namespace.
+pub type NamespaceResolver = Rc<dyn Fn(&Lua, &str) -> LuaResult<Option<NamespaceModule>>>;
+
+/**
+    A resolver for `require`, which may provide modules for a prefix such as `@myapp`.
+
+    Implemented for all functions with the same signature as [`RequireResolver::resolve`].
+*/
+pub trait RequireResolver: 'static {
+    /**
+        Looks up the module with the given name, relative to the prefix of the
+        resolver, such as `http` for `require("@myapp/http")`, returning `None`
+        if the module is not provided by this resolver.
+
+        # Errors
+
+        - If the module exists, but could not be provided
+    */
+    fn resolve(&self, lua: &Lua, name: &str) -> LuaResult<Option<NamespaceModule>>;
+}
+
+impl<F> RequireResolver for F
+where
+    F: Fn(&Lua, &str) -> LuaResult<Option<NamespaceModule>> + 'static,
+{
+    fn resolve(&self, lua: &Lua, name: &str) -> LuaResult<Option<NamespaceModule>> {
+        self(lua, name)
+    }
+}
+
+#[derive(Clone)]
+struct Namespace {
+    name: String,
+    resolver: NamespaceResolver,
+    fallback: bool,
+}
+
+/**
+    All namespaces registered with a runtime, stored as app data.
+*/
+#[derive(Clone, Default)]
+pub struct Namespaces {
+    entries: Vec<Namespace>,
+}
+
+impl Namespaces {
+    /**
+        Registers a namespace, replacing the resolver of any
+        existing namespace with the same name.
+
+        Modules not provided by the resolver of a namespace that falls back use the
+        default resolution of aliases and files, instead of failing to be required.
+
+        The name must have been validated using [`parse_namespace_name`].
+    */
+    pub fn insert(&mut self, name: String, resolver: NamespaceResolver, fallback: bool) {
+        let namespace = Namespace {
+            name,
+            resolver,
+            fallback,
+        };
+        match self.entries.iter_mut().find(|n| n.name == namespace.name) {
+            Some(entry) => *entry = namespace,
+            None => self.entries.push(namespace),
+        }
+    }
+
+    /**
+        Returns whether the namespace with the given name falls
+        back to the default resolution of aliases and files.
+    */
+    #[must_use]
+    pub fn falls_back(&self, name: &str) -> bool {
+        self.entries.iter().any(|n| n.name == name && n.fallback)
+    }
+
+    /**
+        Finds the namespace that a require path belongs to, returning
+        the name of the namespace, the name of the module inside of
+        it, and the resolver of the namespace.
+    */
+    #[must_use]
+    pub fn find(&self, require_path: &str) -> Option<(String, String, NamespaceResolver)> {
+        let (namespace, module) = require_path.split_once('/')?;
+        if module.is_empty() {
+            return None;
+        }
+        self.entries
+            .iter()
+            .find(|n| n.name == namespace)
+            .map(|n| (n.name.clone(), module.to_string(), Rc::clone(&n.resolver)))
+    }
+
+    /**
+        Creates a manifest listing the names of all registered namespaces.
+    */
+    #[must_use]
+    pub fn manifest(&self) -> NamespaceManifest {
+        NamespaceManifest {
+            namespaces: self.entries.iter().map(|n| n.name.clone()).collect(),
+        }
+    }
+}
+
+impl fmt::Debug for Namespaces {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.entries.iter().map(|n| &n.name))
+            .finish()
+    }
+}
+
+/**
+    A manifest of namespaces provided by an application, which
+    may be written to a JSON file for tools such as the bundler,
+    so that they know which requires are provided by the application.
+
+    ```json
+    { "namespaces": ["@mycompany"] }
+    ```
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NamespaceManifest {
+    pub namespaces: Vec<String>,
+}
+
+impl NamespaceManifest {
+    /**
+        Returns whether the given require path belongs to any of the namespaces in this manifest.
+    */
+    #[must_use]
+    pub fn contains(&self, require_path: &str) -> bool {
+        require_path
+            .split_once('/')
+            .is_some_and(|(namespace, module)| {
+                !module.is_empty() && self.namespaces.iter().any(|n| n == namespace)
+            })
+    }
+}
+
+/**
+    Parses and validates the name of a namespace, given as `@name`,
+    optionally followed by a `*` wildcard segment, returning the
+    name in the form `@name`.
+
+    # Errors
+
+    - If the name does not start with `@`, or is empty
+    - If the name contains a `/`, other than before a trailing wildcard
+    - If the name is one of the reserved `lune` or `self` aliases
+*/
+pub fn parse_namespace_name(name: &str) -> Result<String, &'static str> {
+    let name = name.trim();
+    let name = name.strip_suffix("/*").unwrap_or(name);
+    let Some(alias) = name.strip_prefix('@') else {
+        return Err("Namespace names must start with '@'");
+    };
+    if alias.is_empty() || alias.contains('/') {
+        return Err("Namespace names must be in the form '@name'");
+    }
+    if alias == "lune" || alias == "self" {
+        return Err("Namespace names must not be '@lune' or '@self'");
+    }
+    Ok(name.to_string())
+}
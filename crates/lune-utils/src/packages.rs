@@ -0,0 +1,231 @@
+/*!
+    Require aliases for the dependencies declared in package manifests.
+
+    Projects using wally or pesde declare their dependencies in a `wally.toml`
+    or `pesde.toml` manifest, and the package manager installs each of them as
+    a module next to the manifest, named after the dependency. Every dependency
+    is exposed as an alias with the same name, such as `@roact` for a dependency
+    named `Roact`, so that these projects do not need to keep a `.luaurc` file
+    in sync with their manifest. Alias names are matched case-insensitively.
+*/
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, de::IgnoredAny};
+
+/// Manifest files of package managers, found next to the package directories they install
+pub const PACKAGE_MANIFESTS: &[&str] = &[FILE_NAME_WALLY, FILE_NAME_PESDE];
+
+const FILE_NAME_WALLY: &str = "wally.toml";
+const FILE_NAME_PESDE: &str = "pesde.toml";
+
+type Dependencies = BTreeMap<String, IgnoredAny>;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct WallyManifest {
+    #[serde(default)]
+    dependencies: Dependencies,
+    #[serde(default)]
+    server_dependencies: Dependencies,
+    #[serde(default)]
+    dev_dependencies: Dependencies,
+}
+
+#[derive(Debug, Deserialize)]
+struct PesdeManifest {
+    target: Option<PesdeTarget>,
+    #[serde(default)]
+    dependencies: Dependencies,
+    #[serde(default)]
+    peer_dependencies: Dependencies,
+    #[serde(default)]
+    dev_dependencies: Dependencies,
+}
+
+#[derive(Debug, Deserialize)]
+struct PesdeTarget {
+    environment: String,
+}
+
+/**
+    Aliases for the dependencies declared in a single package manifest.
+
+    Paths of aliases are relative to the directory that the manifest is in.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackageAliases {
+    aliases: HashMap<String, PathBuf>,
+}
+
+impl PackageAliases {
+    /**
+        Reads the aliases of the package manifest in the given directory, if any.
+
+        Manifests that can not be read or parsed are ignored.
+    */
+    #[must_use]
+    pub fn read(dir: impl AsRef<Path>) -> Option<Self> {
+        let dir = dir.as_ref();
+        PACKAGE_MANIFESTS.iter().find_map(|file_name| {
+            let contents = fs::read(dir.join(file_name)).ok()?;
+            Self::from_manifest(file_name, &contents)
+        })
+    }
+
+    /**
+        Parses the aliases of a package manifest with the given file name and contents.
+
+        Returns `None` if the file is not a known package manifest, or could not be parsed.
+    */
+    #[must_use]
+    pub fn from_manifest(file_name: &str, contents: &[u8]) -> Option<Self> {
+        let contents = std::str::from_utf8(contents).ok()?;
+        match file_name {
+            FILE_NAME_WALLY => Self::from_wally(contents),
+            FILE_NAME_PESDE => Self::from_pesde(contents),
+            _ => None,
+        }
+    }
+
+    fn from_wally(contents: &str) -> Option<Self> {
+        let manifest = toml::from_str::<WallyManifest>(contents).ok()?;
+        let mut this = Self::default();
+        this.extend("Packages", manifest.dependencies);
+        this.extend("ServerPackages", manifest.server_dependencies);
+        this.extend("DevPackages", manifest.dev_dependencies);
+        Some(this)
+    }
+
+    fn from_pesde(contents: &str) -> Option<Self> {
+        let manifest = toml::from_str::<PesdeManifest>(contents).ok()?;
+        // Packages are installed into a directory named after the target environment,
+        // such as `lune_packages`, and there is nothing to alias without a target
+        let dir = format!("{}_packages", manifest.target?.environment);
+        let mut this = Self::default();
+        this.extend(&dir, manifest.dependencies);
+        this.extend(&dir, manifest.peer_dependencies);
+        this.extend(&dir, manifest.dev_dependencies);
+        Some(this)
+    }
+
+    fn extend(&mut self, dir: &str, dependencies: Dependencies) {
+        for name in dependencies.into_keys() {
+            let path = Path::new(dir).join(&name);
+            self.aliases.entry(name.to_lowercase()).or_insert(path);
+        }
+    }
+
+    /**
+        Gets the path of the dependency with the given alias name, relative to the manifest.
+    */
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Path> {
+        self.aliases.get(&name.to_lowercase()).map(PathBuf::as_path)
+    }
+
+    /**
+        Returns an iterator over all aliases, as lowercase alias names and paths.
+    */
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Path)> {
+        self.aliases
+            .iter()
+            .map(|(name, path)| (name.as_str(), path.as_path()))
+    }
+
+    /**
+        Returns `true` if the manifest does not declare any dependencies.
+    */
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wally_dependencies() {
+        let aliases = PackageAliases::from_manifest(
+            "wally.toml",
+            br#"
+                [package]
+                name = "test/project"
+                version = "0.1.0"
+                registry = "https://github.com/UpliftGames/wally-index"
+                realm = "shared"
+
+                [dependencies]
+                Roact = "roblox/roact@1.4.0"
+
+                [server-dependencies]
+                DataStore = "test/datastore@1.0.0"
+
+                [dev-dependencies]
+                TestEZ = "roblox/testez@0.4.1"
+                Roact = "roblox/roact@1.4.4"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(aliases.get("roact"), Some(Path::new("Packages/Roact")));
+        assert_eq!(aliases.get("Roact"), Some(Path::new("Packages/Roact")));
+        assert_eq!(
+            aliases.get("datastore"),
+            Some(Path::new("ServerPackages/DataStore"))
+        );
+        assert_eq!(aliases.get("testez"), Some(Path::new("DevPackages/TestEZ")));
+        assert_eq!(aliases.get("missing"), None);
+    }
+
+    #[test]
+    fn pesde_dependencies() {
+        let aliases = PackageAliases::from_manifest(
+            "pesde.toml",
+            br#"
+                name = "test/project"
+                version = "0.1.0"
+
+                [target]
+                environment = "lune"
+
+                [dependencies]
+                greeter = { name = "test/greeter", version = "^1.0.0" }
+
+                [dev_dependencies]
+                frktest = { name = "itsfrank/frktest", version = "^0.0.2" }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            aliases.get("greeter"),
+            Some(Path::new("lune_packages/greeter"))
+        );
+        assert_eq!(
+            aliases.get("frktest"),
+            Some(Path::new("lune_packages/frktest"))
+        );
+    }
+
+    #[test]
+    fn invalid_manifests() {
+        assert_eq!(PackageAliases::from_manifest("wally.toml", b"[[["), None);
+        assert_eq!(
+            PackageAliases::from_manifest("pesde.toml", b"name = \"a\""),
+            None
+        );
+        assert_eq!(PackageAliases::from_manifest("Cargo.toml", b""), None);
+        assert!(
+            PackageAliases::from_manifest("wally.toml", b"")
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -12,43 +12,102 @@ use mlua::prelude::*;
 use serde::Deserialize;
 
 use super::constants::{FILE_EXTENSIONS, FILE_NAME_INIT};
+use super::project::find_project_file;
 use super::std::append_extension;
 
-/// Name of the project file used for path mapping
-const PROJECT_FILE_NAME: &str = "default.project.json";
-
-/// Structure representing a minimal default.project.json tree
+/// Structure representing a minimal project file tree
 #[derive(Debug, Deserialize)]
 struct ProjectTree {
     #[serde(rename = "$path")]
     path: Option<String>,
 }
 
-/// Structure representing a minimal default.project.json file
+/// Structure representing a minimal project file, such as `default.project.json`
 #[derive(Debug, Deserialize)]
 struct ProjectJson {
     tree: Option<ProjectTree>,
 }
 
-/// Read and parse a default.project.json file to get the root $path mapping
-fn read_project_json_path(dir: &Path) -> Option<PathBuf> {
-    let project_file = dir.join(PROJECT_FILE_NAME);
-    if !project_file.exists() {
-        return None;
-    }
+/// Read and parse the project file of a directory to get the root $path mapping
+fn read_project_json_path(dir: &Path) -> Result<Option<PathBuf>, LuaNavigateError> {
+    let Some(project_file) = find_project_file(dir)
+        .map_err(|e| LuaNavigateError::Other(LuaError::runtime(e.to_string())))?
+    else {
+        return Ok(None);
+    };
+
+    let Ok(content) = fs::read_to_string(&project_file) else {
+        return Ok(None);
+    };
+    let Ok(project) = serde_json::from_str::<ProjectJson>(&content) else {
+        return Ok(None);
+    };
+
+    // Resolve the root $path mapping relative to the project file's directory
+    Ok(project
+        .tree
+        .and_then(|tree| tree.path)
+        .map(|path| dir.join(path)))
+}
 
-    let content = fs::read_to_string(&project_file).ok()?;
-    let project: ProjectJson = serde_json::from_str(&content).ok()?;
+/// Files that a module path may resolve to, in the order they are tried
+fn module_candidates(module: &Path) -> Vec<PathBuf> {
+    let init = Path::new(FILE_NAME_INIT);
+    let files = FILE_EXTENSIONS
+        .iter()
+        .map(|ext| append_extension(module, ext));
+    let init_files = FILE_EXTENSIONS
+        .iter()
+        .map(|ext| module.join(append_extension(init, ext)));
+    files.chain(init_files).collect()
+}
 
-    // Check if the root tree has a $path mapping
-    if let Some(tree) = project.tree {
-        if let Some(path) = tree.path {
-            // Resolve the path relative to the project file's directory
-            return Some(dir.join(path));
-        }
-    }
+/// Names of files and directories next to a module path that were likely meant to be
+/// required, since their names only differ from it in casing or extension
+fn similar_files(module: &Path) -> Vec<String> {
+    const MAX_SIMILAR: usize = 3;
+
+    let (Some(dir), Some(name)) = (module.parent(), module.file_name()) else {
+        return Vec::new();
+    };
+    let name = name.to_string_lossy();
+    let wanted = [
+        name.to_lowercase(),
+        Path::new(name.as_ref())
+            .with_extension("")
+            .to_string_lossy()
+            .to_lowercase(),
+    ];
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut similar = entries
+        .filter_map(|entry| {
+            let entry_name = entry.ok()?.file_name().to_string_lossy().to_string();
+            let entry_stem = Path::new(&entry_name).with_extension("");
+            let is_similar = wanted.contains(&entry_name.to_lowercase())
+                || wanted.contains(&entry_stem.to_string_lossy().to_lowercase());
+            (is_similar && entry_name != name).then_some(entry_name)
+        })
+        .collect::<Vec<_>>();
+    similar.sort();
+    similar.truncate(MAX_SIMILAR);
+    similar
+}
 
-    None
+/// Joins the given items in quotes, such as `'a', 'b', and 'c'` or `'a' or 'b'`
+fn join_quoted(items: &[String], conjunction: &str) -> String {
+    let quoted = items
+        .iter()
+        .map(|item| format!("'{item}'"))
+        .collect::<Vec<_>>();
+    match quoted.as_slice() {
+        [] => String::new(),
+        [only] => only.clone(),
+        [first, second] => format!("{first} {conjunction} {second}"),
+        [rest @ .., last] => format!("{}, {conjunction} {last}", rest.join(", ")),
+    }
 }
 
 /**
@@ -91,10 +150,10 @@ impl LuauFilePath {
 
         // Try directories with init files in them
         if module.is_dir() {
-            // Check for default.project.json with $path mapping
+            // Check for a project file, such as default.project.json, with a $path mapping
             // This supports Roblox-style project files used by Wally packages
             if check_project_json {
-                if let Some(mapped_path) = read_project_json_path(module) {
+                if let Some(mapped_path) = read_project_json_path(module)? {
                     // If the mapped path is already an existing file, return it directly
                     // This handles cases like $path: "init.luau" where the path points
                     // to a file rather than a directory
@@ -246,6 +305,66 @@ impl LuauModulePath {
         Ok(Self { source, target })
     }
 
+    /**
+        Describes why the given *module* path could not be resolved, for use in error messages.
+
+        For modules that were not found, this lists the files that were tried, and suggests
+        files next to them with names that only differ in casing or extension. For ambiguous
+        modules, this lists the files that the module matched, instead of picking one of them.
+    */
+    #[must_use]
+    pub fn describe_error(module: impl AsRef<Path>, error: &LuaNavigateError) -> String {
+        let module = module.as_ref();
+        let dir = module.parent().unwrap_or(Path::new(""));
+        let relative = |path: &Path| path.strip_prefix(dir).unwrap_or(path).display().to_string();
+        match error {
+            LuaNavigateError::NotFound => {
+                let tried = module_candidates(module)
+                    .iter()
+                    .map(|candidate| relative(candidate))
+                    .collect::<Vec<_>>();
+                let mut message = format!(
+                    "cannot find module '{}', tried {}",
+                    module.display(),
+                    join_quoted(&tried, "and")
+                );
+                let similar = similar_files(module);
+                if !similar.is_empty() {
+                    message.push_str(&format!(" - did you mean {}?", join_quoted(&similar, "or")));
+                }
+                message
+            }
+            LuaNavigateError::Ambiguous => {
+                if module
+                    .file_name()
+                    .is_some_and(|n| n == OsStr::new(FILE_NAME_INIT))
+                {
+                    return format!(
+                        "ambiguous module '{}', require its directory '{}' instead",
+                        module.display(),
+                        dir.display()
+                    );
+                }
+                let matched = module_candidates(module)
+                    .into_iter()
+                    .filter(|candidate| candidate.is_file())
+                    .map(|candidate| relative(&candidate))
+                    .collect::<Vec<_>>();
+                if matched.len() < 2 {
+                    return format!("ambiguous module '{}'", module.display());
+                }
+                format!(
+                    "ambiguous module '{}', it matches {} - rename or remove all but one of them",
+                    module.display(),
+                    join_quoted(&matched, "and")
+                )
+            }
+            LuaNavigateError::Other(e) => {
+                format!("cannot find module '{}': {e}", module.display())
+            }
+        }
+    }
+
     /**
         Returns the source Luau module path.
     */
@@ -268,3 +387,54 @@ impl fmt::Display for LuauModulePath {
         self.source().display().fmt(f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_missing_modules() {
+        let dir = std::env::temp_dir().join(format!("lune-luau-missing-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("Helper.luau"), "").unwrap();
+        fs::write(dir.join("helper.txt"), "").unwrap();
+
+        let module = dir.join("helper");
+        let message = LuauModulePath::describe_error(&module, &LuaNavigateError::NotFound);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            message,
+            format!(
+                "cannot find module '{}', tried 'helper.luau', 'helper.lua', '{}', and '{}' \
+                - did you mean 'Helper.luau' or 'helper.txt'?",
+                module.display(),
+                Path::new("helper").join("init.luau").display(),
+                Path::new("helper").join("init.lua").display(),
+            )
+        );
+    }
+
+    #[test]
+    fn describes_ambiguous_modules() {
+        let dir = std::env::temp_dir().join(format!("lune-luau-ambiguous-{}", std::process::id()));
+        fs::create_dir_all(dir.join("module")).unwrap();
+        fs::write(dir.join("module.luau"), "").unwrap();
+        fs::write(dir.join("module").join("init.luau"), "").unwrap();
+
+        let module = dir.join("module");
+        let error = LuauModulePath::resolve(&module).unwrap_err();
+        let message = LuauModulePath::describe_error(&module, &error);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            message,
+            format!(
+                "ambiguous module '{}', it matches 'module.luau' and '{}' \
+                - rename or remove all but one of them",
+                module.display(),
+                Path::new("module").join("init.luau").display(),
+            )
+        );
+    }
+}
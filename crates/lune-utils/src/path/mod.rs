@@ -1,11 +1,16 @@
 mod luau;
+mod project;
 mod std;
 
 pub mod constants;
 
 pub use self::std::{
     append_extension, clean_path, clean_path_and_make_absolute, get_current_dir, get_current_exe,
-    relative_path_normalize, relative_path_parent,
+    is_data_file, relative_path_normalize, relative_path_parent,
 };
 
 pub use self::luau::{LuauFilePath, LuauModulePath};
+pub use self::project::{
+    AmbiguousProjectFiles, DEFAULT_PROJECT_FILE_NAME, PROJECT_FILE_SUFFIX, find_project_file,
+    set_project_file_name,
+};
@@ -0,0 +1,146 @@
+/*!
+    Utilities for finding Rojo project files, such as `default.project.json`.
+*/
+
+use std::{
+    ffi::OsStr,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use parking_lot::RwLock;
+
+/// Name of the project file that is used when no other project file is configured
+pub const DEFAULT_PROJECT_FILE_NAME: &str = "default.project.json";
+
+/// Suffix shared by all project file names, such as `game.project.json`
+pub const PROJECT_FILE_SUFFIX: &str = ".project.json";
+
+static PROJECT_FILE_NAME: RwLock<Option<String>> = RwLock::new(None);
+
+/**
+    Sets the name of the project file to use in directories with several project
+    files, such as `game.project.json`, or `None` to only use default ones.
+
+    This applies to all runtimes in the process, the same as the current directory.
+*/
+pub fn set_project_file_name(name: Option<String>) {
+    *PROJECT_FILE_NAME.write() = name;
+}
+
+/**
+    Finds the project file in a directory, if any.
+
+    Uses the project file with the name given to [`set_project_file_name`] if it exists,
+    then `default.project.json`, and finally any other `*.project.json` file.
+
+    # Errors
+
+    - If the directory contains several `*.project.json` files and none
+      of them is `default.project.json` or has the configured name.
+*/
+pub fn find_project_file(dir: &Path) -> Result<Option<PathBuf>, AmbiguousProjectFiles> {
+    find_project_file_named(dir, PROJECT_FILE_NAME.read().as_deref())
+}
+
+fn find_project_file_named(
+    dir: &Path,
+    configured: Option<&str>,
+) -> Result<Option<PathBuf>, AmbiguousProjectFiles> {
+    let names = configured.into_iter().chain([DEFAULT_PROJECT_FILE_NAME]);
+    for name in names {
+        let path = dir.join(name);
+        if path.is_file() {
+            return Ok(Some(path));
+        }
+    }
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(None);
+    };
+    let mut found = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(OsStr::to_str)
+                .is_some_and(|n| n.ends_with(PROJECT_FILE_SUFFIX))
+                && path.is_file()
+        })
+        .collect::<Vec<_>>();
+
+    if found.len() > 1 {
+        found.sort();
+        return Err(AmbiguousProjectFiles {
+            dir: dir.to_path_buf(),
+            files: found,
+        });
+    }
+    Ok(found.pop())
+}
+
+/**
+    A directory with several project files, none of which could be chosen.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AmbiguousProjectFiles {
+    /// The directory that the project files were found in.
+    pub dir: PathBuf,
+    /// The paths of all project files in the directory, sorted by name.
+    pub files: Vec<PathBuf>,
+}
+
+impl fmt::Display for AmbiguousProjectFiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = self
+            .files
+            .iter()
+            .filter_map(|path| path.file_name())
+            .map(|name| format!("'{}'", name.to_string_lossy()))
+            .collect::<Vec<_>>();
+        write!(
+            f,
+            "ambiguous project files in '{}', found {} - set `project-file` \
+            in the `[rojo]` table of lune.toml to choose one",
+            self.dir.display(),
+            names.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for AmbiguousProjectFiles {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_project_files() {
+        let dir = std::env::temp_dir().join(format!("lune-project-files-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        assert_eq!(find_project_file_named(&dir, None), Ok(None));
+
+        fs::write(dir.join("game.project.json"), "{}").unwrap();
+        let game = Some(dir.join("game.project.json"));
+        assert_eq!(find_project_file_named(&dir, None), Ok(game.clone()));
+
+        fs::write(dir.join("place.project.json"), "{}").unwrap();
+        let ambiguous = find_project_file_named(&dir, None);
+        let configured = find_project_file_named(&dir, Some("game.project.json"));
+
+        fs::write(dir.join(DEFAULT_PROJECT_FILE_NAME), "{}").unwrap();
+        let default = find_project_file_named(&dir, None);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            ambiguous.unwrap_err().files,
+            [
+                dir.join("game.project.json"),
+                dir.join("place.project.json")
+            ]
+        );
+        assert_eq!(configured, Ok(game));
+        assert_eq!(default, Ok(Some(dir.join(DEFAULT_PROJECT_FILE_NAME))));
+    }
+}
@@ -11,6 +11,8 @@ use std::{
 
 use path_clean::PathClean;
 
+use super::constants::FILE_EXTENSIONS_DATA;
+
 static CWD: LazyLock<Arc<Path>> = LazyLock::new(create_cwd);
 static EXE: LazyLock<Arc<Path>> = LazyLock::new(create_exe);
 
@@ -116,6 +118,24 @@ pub fn append_extension(path: impl AsRef<Path>, ext: impl AsRef<OsStr>) -> PathB
     }
 }
 
+/**
+    Checks if the given path is a data file, such as `config.json` or
+    `Model.rbxm`, which is decoded into a value when required instead of being run.
+
+    See [`FILE_EXTENSIONS_DATA`] for the extensions of data files.
+*/
+#[must_use]
+pub fn is_data_file(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|ext| {
+            FILE_EXTENSIONS_DATA
+                .iter()
+                .any(|data_ext| ext.eq_ignore_ascii_case(data_ext))
+        })
+}
+
 /**
     Normalizes the given relative path.
 
@@ -0,0 +1,115 @@
+/*!
+    Platforms that modules may be conditionally required for, using `require.platform`.
+
+    Platforms are named the same as the operating systems and families in
+    [`std::env::consts`], such as `windows`, `linux`, `macos`, and `unix`.
+    Several platforms may be given at once, separated by commas.
+*/
+
+/// Name of the variant used when no other variant matches the platform.
+pub const PLATFORM_DEFAULT: &str = "default";
+
+/**
+    A platform that code runs on, or is built for.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Platform {
+    os: &'static str,
+    family: &'static str,
+}
+
+impl Platform {
+    /**
+        Creates a new platform from its operating system and family names.
+    */
+    #[must_use]
+    pub const fn new(os: &'static str, family: &'static str) -> Self {
+        Self { os, family }
+    }
+
+    /**
+        Returns the platform that the current process is running on.
+    */
+    #[must_use]
+    pub const fn current() -> Self {
+        Self::new(std::env::consts::OS, std::env::consts::FAMILY)
+    }
+
+    /**
+        Returns the name of the operating system, such as `windows` or `linux`.
+    */
+    #[must_use]
+    pub const fn os(&self) -> &'static str {
+        self.os
+    }
+
+    /**
+        Checks if any of the given platform names, separated by commas, match this
+        platform - either by the name of its operating system, or of its family.
+    */
+    #[must_use]
+    pub fn matches(&self, names: &str) -> bool {
+        contains_name(names, self.os)
+            || (!self.family.is_empty() && contains_name(names, self.family))
+    }
+
+    /**
+        Selects the variant for this platform, given pairs of platform names and variants.
+
+        Variants naming the operating system are preferred over those naming its family,
+        and a variant named [`PLATFORM_DEFAULT`] is used when no other variant matches.
+    */
+    #[must_use]
+    pub fn select<K, V>(&self, variants: impl IntoIterator<Item = (K, V)>) -> Option<V>
+    where
+        K: AsRef<str>,
+    {
+        let mut for_family = None;
+        let mut for_default = None;
+        for (names, variant) in variants {
+            let names = names.as_ref();
+            if contains_name(names, self.os) {
+                return Some(variant);
+            } else if !self.family.is_empty() && contains_name(names, self.family) {
+                for_family.get_or_insert(variant);
+            } else if contains_name(names, PLATFORM_DEFAULT) {
+                for_default.get_or_insert(variant);
+            }
+        }
+        for_family.or(for_default)
+    }
+}
+
+fn contains_name(names: &str, name: &str) -> bool {
+    names
+        .split(',')
+        .any(|candidate| candidate.trim().eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINUX: Platform = Platform::new("linux", "unix");
+    const WINDOWS: Platform = Platform::new("windows", "windows");
+
+    #[test]
+    fn matches_os_and_family() {
+        assert!(LINUX.matches("linux"));
+        assert!(LINUX.matches("unix"));
+        assert!(LINUX.matches("windows, Linux"));
+        assert!(!LINUX.matches("windows"));
+        assert!(WINDOWS.matches("windows"));
+        assert!(!WINDOWS.matches("unix, macos"));
+    }
+
+    #[test]
+    fn selects_most_specific_variant() {
+        let variants = [("default", 0), ("unix", 1), ("linux", 2), ("windows", 3)];
+        assert_eq!(LINUX.select(variants), Some(2));
+        assert_eq!(WINDOWS.select(variants), Some(3));
+        assert_eq!(Platform::new("macos", "unix").select(variants), Some(1));
+        assert_eq!(Platform::new("wasi", "wasm").select(variants), Some(0));
+        assert_eq!(Platform::new("wasi", "wasm").select([("unix", 1)]), None);
+    }
+}
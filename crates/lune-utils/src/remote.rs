@@ -0,0 +1,277 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Name of the lockfile that hashes of remote modules are recorded in
+pub const LOCKFILE_NAME: &str = "lune.lock";
+
+/// Prefix of the bundle paths that remote modules are vendored under in standalone binaries
+pub const REMOTE_BUNDLE_PREFIX: &str = "/@remote/";
+
+static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/**
+    Options for requiring remote modules using their URLs, such as
+    `require("https://example.com/module.luau")`, which must be opted into.
+
+    Downloaded modules are stored in a content-addressed cache, and their
+    hashes recorded in a lockfile, so that running a script again is both
+    reproducible and possible without network access.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteRequires {
+    /// Path to the lockfile that hashes of remote modules are recorded in
+    pub lockfile: PathBuf,
+    /// Directory that downloaded remote modules are cached in
+    pub cache_dir: PathBuf,
+}
+
+/**
+    Returns whether the given require path is the URL of a remote module.
+*/
+#[must_use]
+pub fn is_remote_path(path: &str) -> bool {
+    path.starts_with("https://")
+}
+
+/**
+    Returns the URL of a remote module, adding the `.luau` extension
+    if the URL does not already end with a file extension.
+*/
+#[must_use]
+pub fn normalize_remote_url(url: &str) -> String {
+    let name = url.rsplit('/').next().unwrap_or_default();
+    if name.contains('.') {
+        url.to_string()
+    } else {
+        format!("{url}.luau")
+    }
+}
+
+/**
+    Resolves a relative require path, such as `./util` or `../shared/types`,
+    required by the remote module with the given URL, to the URL of a module.
+
+    Returns `None` if the path is not relative, or if it leaves the host.
+*/
+#[must_use]
+pub fn resolve_remote_relative(base_url: &str, relative: &str) -> Option<String> {
+    if !relative.starts_with("./") && !relative.starts_with("../") {
+        return None;
+    }
+    let rest = base_url.strip_prefix("https://")?;
+    let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+
+    let mut segments = path.split('/').collect::<Vec<_>>();
+    segments.pop(); // The name of the requiring module
+    for segment in relative.split('/') {
+        match segment {
+            "." | "" => {}
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    Some(normalize_remote_url(&format!(
+        "https://{host}/{}",
+        segments.join("/")
+    )))
+}
+
+/**
+    Returns the bundle path that a remote module is vendored under in standalone binaries.
+*/
+#[must_use]
+pub fn remote_bundle_path(url: &str) -> String {
+    let rest = url.strip_prefix("https://").unwrap_or(url);
+    format!("{REMOTE_BUNDLE_PREFIX}{rest}")
+}
+
+/**
+    Returns the SHA-256 hash of the given contents, as lowercase hex.
+*/
+#[must_use]
+pub fn hash_contents(contents: &[u8]) -> String {
+    use std::fmt::Write;
+
+    Sha256::digest(contents)
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+/**
+    A lockfile, recording the hash of every remote module that has been required.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    /// Hashes of remote modules: URL -> SHA-256 hash of the module
+    pub modules: BTreeMap<String, String>,
+}
+
+impl Lockfile {
+    /**
+        Reads the lockfile at the given path, or an empty lockfile if it does not exist.
+
+        # Errors
+
+        Errors if the lockfile exists but could not be read, or is not a valid lockfile.
+    */
+    pub fn read(path: &Path) -> io::Result<Self> {
+        match fs::read(path) {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /**
+        Writes the lockfile to the given path.
+
+        # Errors
+
+        Errors if the lockfile could not be written.
+    */
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut contents = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        contents.push('\n');
+        fs::write(path, contents)
+    }
+
+    /**
+        Returns the recorded hash of the remote module with the given URL, if any.
+    */
+    #[must_use]
+    pub fn get(&self, url: &str) -> Option<&str> {
+        self.modules.get(url).map(String::as_str)
+    }
+
+    /**
+        Records the hash of the remote module with the given URL.
+    */
+    pub fn insert(&mut self, url: impl Into<String>, hash: impl Into<String>) {
+        self.modules.insert(url.into(), hash.into());
+    }
+}
+
+/**
+    A content-addressed cache of downloaded remote modules, stored by their hashes.
+*/
+#[derive(Debug, Clone)]
+pub struct RemoteCache {
+    dir: PathBuf,
+}
+
+impl RemoteCache {
+    /**
+        Creates a cache stored in the given directory.
+    */
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn entry_path(&self, hash: &str) -> PathBuf {
+        self.dir.join(hash.get(..2).unwrap_or(hash)).join(hash)
+    }
+
+    /**
+        Reads the remote module with the given hash, if it is cached and
+        its contents still match the hash.
+    */
+    #[must_use]
+    pub fn read(&self, hash: &str) -> Option<Vec<u8>> {
+        let contents = fs::read(self.entry_path(hash)).ok()?;
+        (hash_contents(&contents) == hash).then_some(contents)
+    }
+
+    /**
+        Stores the given remote module in the cache, returning its hash.
+
+        # Errors
+
+        Errors if the module could not be written to the cache directory.
+    */
+    pub fn write(&self, contents: &[u8]) -> io::Result<String> {
+        let hash = hash_contents(contents);
+        let path = self.entry_path(&hash);
+        let dir = path.parent().unwrap_or(&self.dir);
+        fs::create_dir_all(dir)?;
+
+        // Write to a temporary file first, so that other processes
+        // never read a module that has only been partially written
+        let temp = dir.join(format!(
+            "tmp-{}-{}",
+            std::process::id(),
+            TEMP_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&temp, contents)?;
+        fs::rename(&temp, &path)?;
+
+        Ok(hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_urls_are_resolved() {
+        let base = "https://example.com/lib/mod.luau";
+        assert_eq!(
+            resolve_remote_relative(base, "./util").as_deref(),
+            Some("https://example.com/lib/util.luau")
+        );
+        assert_eq!(
+            resolve_remote_relative(base, "../shared/types.lua").as_deref(),
+            Some("https://example.com/shared/types.lua")
+        );
+        assert_eq!(resolve_remote_relative(base, "../../escape"), None);
+        assert_eq!(resolve_remote_relative(base, "@lune/fs"), None);
+    }
+
+    #[test]
+    fn bundle_paths_keep_hosts() {
+        assert_eq!(
+            remote_bundle_path("https://example.com/lib/mod.luau"),
+            "/@remote/example.com/lib/mod.luau"
+        );
+        assert_eq!(
+            normalize_remote_url("https://example.com/lib/mod"),
+            "https://example.com/lib/mod.luau"
+        );
+    }
+
+    #[test]
+    fn lockfile_and_cache_round_trip() {
+        let dir = std::env::temp_dir().join(format!("lune-remote-test-{}", std::process::id()));
+        let cache = RemoteCache::new(dir.join("cache"));
+
+        let hash = cache.write(b"return 1").unwrap();
+        assert_eq!(hash, hash_contents(b"return 1"));
+        assert_eq!(cache.read(&hash).as_deref(), Some(&b"return 1"[..]));
+        assert_eq!(cache.read(&hash_contents(b"return 2")), None);
+
+        let path = dir.join(LOCKFILE_NAME);
+        let mut lockfile = Lockfile::read(&path).unwrap();
+        assert!(lockfile.modules.is_empty());
+        lockfile.insert("https://example.com/mod.luau", hash.clone());
+        lockfile.write(&path).unwrap();
+        let lockfile = Lockfile::read(&path).unwrap();
+        assert_eq!(lockfile.get("https://example.com/mod.luau"), Some(&*hash));
+
+        fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,150 @@
+/*!
+    Restricting `require` to an allow-list of directories and prefixes.
+
+    Host scripts that run untrusted scripts, such as plugins, use an allow-list
+    so that those scripts are only able to require modules from the directories
+    they are given, and built-in or remote modules with the prefixes they are given.
+*/
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use crate::{path::clean_path_and_make_absolute, remote::is_remote_path};
+
+/**
+    Directories and prefixes that modules may be required from, stored as app data.
+
+    Only present when requires are restricted.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequireAllowList {
+    dirs: Vec<PathBuf>,
+    prefixes: Vec<String>,
+}
+
+impl RequireAllowList {
+    /**
+        Creates a new allow-list from the given entries.
+
+        Entries starting with `@`, such as `@lune/` or `@pkg`, and URLs, such as
+        `https://example.com/`, are prefixes of require paths. All other entries are
+        directories, where relative paths are resolved from the current directory.
+    */
+    #[must_use]
+    pub fn new<I, S>(entries: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut this = Self::default();
+        for entry in entries {
+            let entry = entry.as_ref().trim();
+            if entry.starts_with('@') || is_remote_path(entry) {
+                this.prefixes.push(entry.to_string());
+            } else {
+                this.dirs
+                    .push(clean_path_and_make_absolute(Path::new(entry)));
+            }
+        }
+        this
+    }
+
+    /**
+        Checks if a require is allowed - either its require path starts with an
+        allowed prefix, or the module it resolved to is inside an allowed directory.
+
+        Modules that are not files, such as built-in modules, are only allowed by prefixes.
+
+        # Errors
+
+        Returns an error describing the require if it is not allowed.
+    */
+    pub fn check(
+        &self,
+        require_path: &str,
+        resolved: Option<&Path>,
+    ) -> Result<(), RequireNotAllowed> {
+        let allowed_prefix = self
+            .prefixes
+            .iter()
+            .any(|prefix| require_path.starts_with(prefix.as_str()));
+        let allowed_dir =
+            resolved.is_some_and(|path| self.dirs.iter().any(|dir| path.starts_with(dir)));
+        if allowed_prefix || allowed_dir {
+            Ok(())
+        } else {
+            Err(RequireNotAllowed {
+                require_path: require_path.to_string(),
+                resolved: resolved.map(Path::to_path_buf),
+            })
+        }
+    }
+}
+
+/**
+    A require that was not allowed by a [`RequireAllowList`].
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequireNotAllowed {
+    /// The path given to `require`.
+    pub require_path: String,
+    /// The path of the module it resolved to, if it resolved to a file.
+    pub resolved: Option<PathBuf>,
+}
+
+impl fmt::Display for RequireNotAllowed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.resolved {
+            Some(resolved) => write!(
+                f,
+                "require of '{}' is not allowed: '{}' is outside of the require allow-list",
+                self.require_path,
+                resolved.display()
+            ),
+            None => write!(
+                f,
+                "require of '{}' is not allowed: it does not match the require allow-list",
+                self.require_path
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allows_prefixes_and_dirs() {
+        let plugins = clean_path_and_make_absolute(Path::new("plugins"));
+        let list = RequireAllowList::new(["@lune/", "https://example.com/", "plugins"]);
+
+        assert!(list.check("@lune/fs", None).is_ok());
+        assert!(list.check("https://example.com/mod.luau", None).is_ok());
+        assert!(list.check("./a", Some(&plugins.join("foo/a.luau"))).is_ok());
+
+        assert!(list.check("@pkg/foo", None).is_err());
+        assert!(list.check("https://example.org/mod.luau", None).is_err());
+        assert!(
+            list.check("../secret", Some(&plugins.with_file_name("secret.luau")))
+                .is_err()
+        );
+        assert!(
+            list.check("./a", Some(&plugins.with_file_name("plugins-other/a.luau")))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn empty_list_allows_nothing() {
+        let list = RequireAllowList::new(Vec::<String>::new());
+        assert!(list.check("@lune/fs", None).is_err());
+        let err = list.check("./a", Some(Path::new("a.luau"))).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "require of './a' is not allowed: 'a.luau' is outside of the require allow-list"
+        );
+    }
+}
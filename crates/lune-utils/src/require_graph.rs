@@ -0,0 +1,176 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+/**
+    The requires of all bundled modules, resolved when building a standalone binary.
+
+    Modules are numbered in dependency order - every module comes after the modules
+    that it requires, except in cycles - starting from the entry files, and each
+    require maps the exact string given to `require` to the number of a module.
+
+    Standalone binaries built with this graph resolve requires by looking them up,
+    instead of resolving paths, searching for `.luaurc` files, and checking files.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequireGraph {
+    /// Bundle paths of all modules, in dependency order
+    pub modules: Vec<String>,
+    /// Requires of each module, in the same order: require path -> module number
+    pub requires: Vec<BTreeMap<String, u32>>,
+}
+
+impl RequireGraph {
+    /**
+        Links the given requires into a graph, numbering the modules
+        in dependency order, starting from the given entry modules.
+
+        Requires are given as bundle path of the requiring module ->
+        require path -> bundle path of the required module.
+    */
+    #[must_use]
+    pub fn link(entries: &[String], requires: &HashMap<String, BTreeMap<String, String>>) -> Self {
+        let mut order = Vec::new();
+        let mut visited = BTreeSet::new();
+
+        let mut roots = entries.to_vec();
+        let mut others = requires.keys().cloned().collect::<Vec<_>>();
+        others.sort();
+        roots.extend(others);
+
+        for root in roots {
+            // Depth-first, adding modules once all modules they require were added
+            let mut stack = vec![(root, false)];
+            while let Some((module, expanded)) = stack.pop() {
+                if expanded {
+                    order.push(module);
+                    continue;
+                }
+                if !visited.insert(module.clone()) {
+                    continue;
+                }
+                stack.push((module.clone(), true));
+                if let Some(required) = requires.get(&module) {
+                    for path in required.values().rev() {
+                        if !visited.contains(path) {
+                            stack.push((path.clone(), false));
+                        }
+                    }
+                }
+            }
+        }
+
+        let numbers = order
+            .iter()
+            .enumerate()
+            .map(|(index, module)| (module.as_str(), index_to_u32(index)))
+            .collect::<HashMap<_, _>>();
+        let linked = order
+            .iter()
+            .map(|module| {
+                requires
+                    .get(module)
+                    .into_iter()
+                    .flatten()
+                    .map(|(require, path)| (require.clone(), numbers[path.as_str()]))
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            modules: order,
+            requires: linked,
+        }
+    }
+
+    /**
+        Returns whether the graph contains no modules.
+    */
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    /**
+        Returns the number of each module, by its bundle path, for
+        looking up the requires of modules using [`RequireGraph::resolve`].
+    */
+    #[must_use]
+    pub fn numbers(&self) -> HashMap<String, usize> {
+        self.modules
+            .iter()
+            .enumerate()
+            .map(|(index, module)| (module.clone(), index))
+            .collect()
+    }
+
+    /**
+        Returns the bundle path of the module required by the module
+        with the given number, using the given require path, if linked.
+    */
+    #[must_use]
+    pub fn resolve(&self, module: usize, require: &str) -> Option<&str> {
+        let required = *self.requires.get(module)?.get(require)?;
+        self.modules
+            .get(usize::try_from(required).ok()?)
+            .map(String::as_str)
+    }
+}
+
+fn index_to_u32(index: usize) -> u32 {
+    u32::try_from(index).expect("more than u32::MAX bundled modules")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requires(pairs: &[(&str, &[(&str, &str)])]) -> HashMap<String, BTreeMap<String, String>> {
+        pairs
+            .iter()
+            .map(|(module, required)| {
+                let required = required
+                    .iter()
+                    .map(|(require, path)| ((*require).to_string(), (*path).to_string()))
+                    .collect();
+                ((*module).to_string(), required)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn dependencies_come_first() {
+        let graph = RequireGraph::link(
+            &["/main.luau".to_string()],
+            &requires(&[
+                (
+                    "/main.luau",
+                    &[("./a", "/a.luau"), ("@pkg/b", "/Packages/b.luau")],
+                ),
+                ("/a.luau", &[("../Packages/b", "/Packages/b.luau")]),
+            ]),
+        );
+        assert_eq!(graph.modules, ["/Packages/b.luau", "/a.luau", "/main.luau"]);
+
+        let main = graph.numbers()["/main.luau"];
+        assert_eq!(graph.resolve(main, "./a"), Some("/a.luau"));
+        assert_eq!(graph.resolve(main, "@pkg/b"), Some("/Packages/b.luau"));
+        assert_eq!(graph.resolve(main, "./missing"), None);
+    }
+
+    #[test]
+    fn cycles_are_linked() {
+        let graph = RequireGraph::link(
+            &["/main.luau".to_string()],
+            &requires(&[
+                ("/main.luau", &[("./a", "/a.luau")]),
+                ("/a.luau", &[("./b", "/b.luau")]),
+                ("/b.luau", &[("./a", "/a.luau")]),
+            ]),
+        );
+        assert_eq!(graph.modules, ["/b.luau", "/a.luau", "/main.luau"]);
+        let numbers = graph.numbers();
+        assert_eq!(graph.resolve(numbers["/b.luau"], "./a"), Some("/a.luau"));
+        assert_eq!(graph.resolve(numbers["/a.luau"], "./b"), Some("/b.luau"));
+    }
+}
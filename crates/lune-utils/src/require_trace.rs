@@ -0,0 +1,299 @@
+use std::{
+    ffi::OsStr,
+    fmt::Write as _,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use serde::{Serialize, Serializer};
+
+/**
+    A trace of every require made during a run, for diagnosing slow
+    startup and finding out why modules end up being required.
+
+    Traces are cheap to clone, and all clones record into the same trace.
+*/
+#[derive(Debug, Clone)]
+pub struct RequireTrace {
+    started: Instant,
+    entries: Arc<Mutex<Vec<RequireTraceEntry>>>,
+}
+
+/**
+    A single require, recorded in a [`RequireTrace`].
+*/
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RequireTraceEntry {
+    /// The string given to `require`, such as `./module` or `@lune/fs`
+    pub module: String,
+    /// The resolved path of the module, or `None` if the require could not be resolved
+    pub path: Option<String>,
+    /// Path of the module that made the require, if known
+    pub parent: Option<String>,
+    /// Time that the require was made at, since the trace was created
+    #[serde(rename = "start_ms", serialize_with = "serialize_millis")]
+    pub start: Duration,
+    /// Time spent on the require, including running the module and any modules it requires
+    #[serde(rename = "duration_ms", serialize_with = "serialize_millis")]
+    pub duration: Duration,
+    /// Whether the module had already been loaded, and was not run again
+    pub cached: bool,
+}
+
+impl RequireTrace {
+    /**
+        Creates a new, empty trace.
+    */
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            entries: Arc::default(),
+        }
+    }
+
+    /**
+        Starts tracing a require of the given module, made by the module at the given
+        path - the require is recorded once the returned [`TracedRequire`] is dropped.
+    */
+    #[must_use]
+    pub fn start(&self, module: impl Into<String>, parent: Option<&Path>) -> TracedRequire {
+        let started = Instant::now();
+        TracedRequire {
+            trace: Some(self.clone()),
+            started,
+            entry: RequireTraceEntry {
+                module: module.into(),
+                path: None,
+                parent: parent.map(|p| p.display().to_string()),
+                start: started.duration_since(self.started),
+                duration: Duration::ZERO,
+                cached: false,
+            },
+        }
+    }
+
+    /**
+        Returns all requires recorded so far, in the order that they were made.
+    */
+    #[must_use]
+    pub fn entries(&self) -> Vec<RequireTraceEntry> {
+        let mut entries = self.entries.lock().clone();
+        entries.sort_by_key(|entry| entry.start);
+        entries
+    }
+
+    /**
+        Formats all requires recorded so far as JSON.
+    */
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.entries()).expect("trace is serializable")
+    }
+
+    /**
+        Formats all requires recorded so far as a tree, with the requires made by
+        each module nested under it, and the time spent on each require.
+
+        Modules that had already been loaded are marked as cached, and
+        the requires they made when first loaded are not repeated.
+    */
+    #[must_use]
+    pub fn format_tree(&self) -> String {
+        let entries = self.entries();
+
+        // Each require belongs to the load of its parent module that was running when it was
+        // made - this keeps requires apart for modules that were loaded more than once
+        let owners = entries
+            .iter()
+            .map(|entry| {
+                entries.iter().rposition(|owner| {
+                    !owner.cached
+                        && owner.path.is_some()
+                        && owner.path == entry.parent
+                        && owner.start <= entry.start
+                        && entry.start <= owner.start + owner.duration
+                        && !std::ptr::eq(owner, entry)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let loaded = entries.iter().filter(|e| !e.cached && e.path.is_some());
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "Require trace ({} requires, {} modules loaded)",
+            entries.len(),
+            loaded.count()
+        );
+
+        let mut roots = Vec::<Option<&str>>::new();
+        for (entry, owner) in entries.iter().zip(&owners) {
+            if owner.is_none() && !roots.contains(&entry.parent.as_deref()) {
+                roots.push(entry.parent.as_deref());
+            }
+        }
+        for root in roots {
+            let _ = writeln!(out, "  {}", root.unwrap_or("<unknown>"));
+            for (index, (entry, owner)) in entries.iter().zip(&owners).enumerate() {
+                if owner.is_none() && entry.parent.as_deref() == root {
+                    format_tree_entry(&mut out, &entries, &owners, index, 2);
+                }
+            }
+        }
+
+        out
+    }
+}
+
+impl Default for RequireTrace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn format_tree_entry(
+    out: &mut String,
+    entries: &[RequireTraceEntry],
+    owners: &[Option<usize>],
+    index: usize,
+    depth: usize,
+) {
+    let entry = &entries[index];
+    let indent = "  ".repeat(depth);
+    let path = entry.path.as_deref().unwrap_or("<unresolved>");
+    if entry.cached {
+        let _ = writeln!(out, "{indent}{} -> {path} (cached)", entry.module);
+    } else {
+        let millis = entry.duration.as_secs_f64() * 1000.0;
+        let _ = writeln!(out, "{indent}{} -> {path} ({millis:.2}ms)", entry.module);
+    }
+    for (child, owner) in owners.iter().enumerate() {
+        if *owner == Some(index) {
+            format_tree_entry(out, entries, owners, child, depth + 1);
+        }
+    }
+}
+
+fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
+
+/**
+    A require that is being traced, recorded in its [`RequireTrace`] once dropped.
+
+    Requires that are not being traced may use [`TracedRequire::disabled`],
+    which records nothing, instead of keeping an optional [`TracedRequire`].
+*/
+#[derive(Debug)]
+pub struct TracedRequire {
+    trace: Option<RequireTrace>,
+    started: Instant,
+    entry: RequireTraceEntry,
+}
+
+impl TracedRequire {
+    /**
+        Creates a traced require that records nothing.
+    */
+    #[must_use]
+    pub fn disabled() -> Self {
+        Self {
+            trace: None,
+            started: Instant::now(),
+            entry: RequireTraceEntry::default(),
+        }
+    }
+
+    /**
+        Records the path, or other name, that the require was resolved to.
+    */
+    pub fn resolve(&mut self, path: impl AsRef<OsStr>) {
+        if self.trace.is_some() {
+            self.entry.path = Some(Path::new(path.as_ref()).display().to_string());
+        }
+    }
+
+    /**
+        Records that the required module had already been loaded.
+    */
+    pub fn cached(&mut self) {
+        self.entry.cached = true;
+    }
+}
+
+impl Drop for TracedRequire {
+    fn drop(&mut self) {
+        if let Some(trace) = self.trace.take() {
+            self.entry.duration = self.started.elapsed();
+            trace.entries.lock().push(std::mem::take(&mut self.entry));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(
+        module: &str,
+        path: &str,
+        parent: &str,
+        start: u64,
+        duration: u64,
+    ) -> RequireTraceEntry {
+        RequireTraceEntry {
+            module: module.to_string(),
+            path: Some(path.to_string()),
+            parent: Some(parent.to_string()),
+            start: Duration::from_millis(start),
+            duration: Duration::from_millis(duration),
+            cached: false,
+        }
+    }
+
+    #[test]
+    fn formats_nested_requires() {
+        let trace = RequireTrace::new();
+        trace.entries.lock().extend([
+            entry("./util", "/util.luau", "/lib.luau", 2, 1),
+            entry("./lib", "/lib.luau", "/main.luau", 1, 5),
+            RequireTraceEntry {
+                cached: true,
+                ..entry("./util", "/util.luau", "/main.luau", 7, 0)
+            },
+            RequireTraceEntry {
+                path: None,
+                ..entry("./missing", "", "/main.luau", 8, 0)
+            },
+        ]);
+
+        assert_eq!(
+            trace.format_tree(),
+            "Require trace (4 requires, 2 modules loaded)\
+            \n  /main.luau\
+            \n    ./lib -> /lib.luau (5.00ms)\
+            \n      ./util -> /util.luau (1.00ms)\
+            \n    ./util -> /util.luau (cached)\
+            \n    ./missing -> <unresolved> (0.00ms)\
+            \n"
+        );
+    }
+
+    #[test]
+    fn records_dropped_requires() {
+        let trace = RequireTrace::new();
+        {
+            let mut traced = trace.start("./lib", Some(Path::new("/main.luau")));
+            traced.resolve("/lib.luau");
+            let _ = TracedRequire::disabled();
+        }
+        let entries = trace.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path.as_deref(), Some("/lib.luau"));
+        assert_eq!(entries[0].parent.as_deref(), Some("/main.luau"));
+        assert!(trace.to_json().contains("\"duration_ms\""));
+    }
+}
@@ -0,0 +1,24 @@
+/*!
+    Priorities of the shutdown hooks registered by the standard libraries.
+
+    Hooks with a higher priority run first - hooks registered by users, using
+    `runtime.onShutdown`, may use any priority to run before or after these.
+*/
+
+/// Stops any servers created using `net.serve` from accepting new connections
+pub const PRIORITY_NET_SERVE: i32 = 300;
+
+/// Stops any endpoints served using `ipc.serve`, and closes their connections
+pub const PRIORITY_IPC_SERVE: i32 = 300;
+
+/// Disconnects all listeners for events posted by the host, added using `events.listen`
+pub const PRIORITY_EVENTS_LISTEN: i32 = 300;
+
+/// Kills any child processes created using `process.create` that are still running
+pub const PRIORITY_PROCESS_CHILDREN: i32 = 200;
+
+/// Flushes stdout and stderr
+pub const PRIORITY_STDIO_FLUSH: i32 = 100;
+
+/// Releases any locks acquired using `process.singleInstance` that are still held
+pub const PRIORITY_PROCESS_INSTANCE_LOCKS: i32 = 50;
@@ -0,0 +1,146 @@
+/*!
+    Minimal writers for the archive formats used when packaging standalone binaries.
+
+    All archives use a fixed owner and modification time for every entry,
+    so that packaging the same binary always produces the exact same bytes.
+*/
+
+use std::io::Write;
+
+use anyhow::{Context, Result};
+use flate2::{Compression, write::GzEncoder};
+
+pub const TAR_BLOCK_SIZE: usize = 512;
+
+const AR_MAGIC: &[u8] = b"!<arch>\n";
+const AR_HEADER_SIZE: usize = 60;
+
+/**
+    Appends a regular file to a tar archive, using the `ustar` format.
+
+    Paths must be at most 100 bytes long, which all of our paths are.
+*/
+pub fn append_tar_file(archive: &mut Vec<u8>, path: &str, contents: &[u8], mode: u32) {
+    append_tar_header(archive, path, contents.len(), mode, b'0');
+    archive.extend_from_slice(contents);
+    archive.resize(archive.len().next_multiple_of(TAR_BLOCK_SIZE), 0);
+}
+
+/**
+    Appends a directory to a tar archive, using the `ustar` format.
+*/
+pub fn append_tar_dir(archive: &mut Vec<u8>, path: &str, mode: u32) {
+    append_tar_header(archive, path, 0, mode, b'5');
+}
+
+/**
+    Finishes a tar archive, marking its end with two empty blocks.
+*/
+pub fn finish_tar(archive: &mut Vec<u8>) {
+    archive.resize(archive.len() + TAR_BLOCK_SIZE * 2, 0);
+}
+
+fn append_tar_header(archive: &mut Vec<u8>, path: &str, size: usize, mode: u32, kind: u8) {
+    assert!(
+        path.len() <= 100,
+        "tar entry paths must be at most 100 bytes"
+    );
+
+    let mut header = [0u8; TAR_BLOCK_SIZE];
+    header[..path.len()].copy_from_slice(path.as_bytes());
+    write_octal(&mut header[100..108], mode as usize);
+    write_octal(&mut header[108..116], 0);
+    write_octal(&mut header[116..124], 0);
+    write_octal(&mut header[124..136], size);
+    write_octal(&mut header[136..148], 0);
+    header[156] = kind;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // The checksum is calculated with the checksum field itself as spaces
+    header[148..156].fill(b' ');
+    let checksum = header.iter().map(|b| *b as usize).sum::<usize>();
+    write_octal(&mut header[148..155], checksum);
+
+    archive.extend_from_slice(&header);
+}
+
+fn write_octal(field: &mut [u8], value: usize) {
+    let digits = field.len() - 1;
+    let octal = format!("{value:0digits$o}");
+    field[..digits].copy_from_slice(octal.as_bytes());
+    field[digits] = 0;
+}
+
+/**
+    Creates an `ar` archive, as used for Debian packages, from the given files.
+
+    File names must be at most 16 bytes long, which all of our names are.
+*/
+pub fn create_ar(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut archive = AR_MAGIC.to_vec();
+    for (name, contents) in files {
+        assert!(name.len() <= 16, "ar entry names must be at most 16 bytes");
+        let header = format!(
+            "{name:<16}{mtime:<12}{uid:<6}{gid:<6}{mode:<8o}{size:<10}`\n",
+            mtime = 0,
+            uid = 0,
+            gid = 0,
+            mode = 0o100_644,
+            size = contents.len(),
+        );
+        debug_assert_eq!(header.len(), AR_HEADER_SIZE);
+        archive.extend_from_slice(header.as_bytes());
+        archive.extend_from_slice(contents);
+        if archive.len() % 2 != 0 {
+            archive.push(b'\n');
+        }
+    }
+    archive
+}
+
+/**
+    Compresses the given data using gzip.
+
+    # Errors
+
+    - If the data could not be compressed
+*/
+pub fn gzip(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish().context("failed to compress archive")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tar_entries_are_valid() {
+        let mut archive = Vec::new();
+        append_tar_file(&mut archive, "hello", b"world", 0o644);
+        finish_tar(&mut archive);
+
+        assert_eq!(archive.len(), TAR_BLOCK_SIZE * 4);
+        assert_eq!(&archive[..5], b"hello");
+        assert_eq!(&archive[124..135], b"00000000005");
+        assert_eq!(&archive[TAR_BLOCK_SIZE..TAR_BLOCK_SIZE + 5], b"world");
+
+        let mut header = archive[..TAR_BLOCK_SIZE].to_vec();
+        let stored = usize::from_str_radix(std::str::from_utf8(&header[148..155]).unwrap(), 8);
+        header[148..156].fill(b' ');
+        let expected = header.iter().map(|b| *b as usize).sum::<usize>();
+        assert_eq!(stored, Ok(expected));
+    }
+
+    #[test]
+    fn ar_entries_are_valid() {
+        let archive = create_ar(&[("debian-binary", b"2.0\n"), ("odd", b"abc")]);
+        assert!(archive.starts_with(AR_MAGIC));
+        let header = &archive[AR_MAGIC.len()..AR_MAGIC.len() + AR_HEADER_SIZE];
+        assert!(header.starts_with(b"debian-binary   0 "));
+        assert!(header.ends_with(b"4         `\n"));
+        assert_eq!(archive.len() % 2, 0);
+    }
+}
@@ -9,11 +9,14 @@ use blocking::unblock;
 use crate::standalone::metadata::CURRENT_EXE;
 
 use super::{
+    features::{BaseFeatures, build_base_executable, is_cargo_available},
     files::write_executable_file_to,
+    output::progress,
     result::{BuildError, BuildResult},
     target::{BuildTarget, CACHE_DIR},
 };
 
+#[cfg(feature = "std-net")]
 const RELEASE_REQUEST_HEADERS: &[(&str, &str)] = &[
     (
         "User-Agent",
@@ -29,6 +32,9 @@ const RELEASE_REQUEST_HEADERS: &[(&str, &str)] = &[
     // ("Accept-Encoding", "gzip"),
 ];
 
+/// Name of the base shared library built from the `lune-ffi` crate
+const BASE_LIBRARY_NAME: &str = "lune_ffi";
+
 /**
     Discovers the path to the base executable to use for cross-compilation.
 
@@ -40,23 +46,123 @@ pub async fn get_or_download_base_executable(target: BuildTarget) -> BuildResult
     if target.is_current_system() {
         return Ok(CURRENT_EXE.to_path_buf());
     }
-    if target.cache_path().exists() {
-        return Ok(target.cache_path());
+    let file_name = format!("lune{}", target.exe_suffix());
+    let cache_path = target.cache_path();
+    let asset_name = format!("lune-{}-{target}", env!("CARGO_PKG_VERSION"));
+    get_or_download(target, asset_name, file_name, cache_path).await
+}
+
+/**
+    Discovers the path to a base executable that only contains the given
+    standard libraries, for `lune build --base-features`.
+
+    If no binary exists at the target path, it will attempt to download a pre-built
+    one from the internet, and if there is none, to build one locally using cargo.
+*/
+pub async fn get_or_build_trimmed_base_executable(
+    target: BuildTarget,
+    features: &BaseFeatures,
+) -> BuildResult<PathBuf> {
+    let label = features.label();
+    let file_name = format!("lune{}", target.exe_suffix());
+    let cache_path = target.trimmed_cache_path(&label);
+    let asset_name = format!("lune-{}-{target}-{label}", env!("CARGO_PKG_VERSION"));
+    match get_or_download(target.clone(), asset_name, file_name, cache_path.clone()).await {
+        Err(BuildError::ReleaseTargetNotFound(_)) if is_cargo_available().await => {
+            progress(format_args!(
+                "No pre-built base executable exists for these features, building one instead"
+            ));
+            build_base_executable(&target, features, &cache_path).await?;
+            Ok(cache_path)
+        }
+        result => result,
+    }
+}
+
+/**
+    Discovers the path to the base shared library to use for `lune build --lib`.
+
+    If the target is the same as the current system, and the library exists next
+    to the current executable, as it does in release archives, that library is used.
+
+    If no library exists at the target path, it will attempt to download it from the internet.
+*/
+pub async fn get_or_download_base_library(target: BuildTarget) -> BuildResult<PathBuf> {
+    let file_name = target.lib_file_name(BASE_LIBRARY_NAME);
+    if target.is_current_system() {
+        let local_path = CURRENT_EXE.with_file_name(&file_name);
+        if local_path.exists() {
+            return Ok(local_path);
+        }
+    }
+    let cache_path = target.lib_cache_path();
+    let asset_name = format!("lune-{}-{target}", env!("CARGO_PKG_VERSION"));
+    get_or_download(target, asset_name, file_name, cache_path).await
+}
+
+async fn get_or_download(
+    target: BuildTarget,
+    asset_name: String,
+    binary_file_name: String,
+    cache_path: PathBuf,
+) -> BuildResult<PathBuf> {
+    if cache_path.exists() {
+        return Ok(cache_path);
     }
 
     // The target is not cached, we must download it
-    println!("Requested target '{target}' does not exist in cache");
+    progress(format_args!(
+        "Requested target '{target}' does not exist in cache"
+    ));
     let version = env!("CARGO_PKG_VERSION");
-    let target_triple = format!("lune-{version}-{target}");
 
     let release_url = format!(
-        "{base_url}/v{version}/{target_triple}.zip",
+        "{base_url}/v{version}/{asset_name}.zip",
         base_url = "https://github.com/horsenuggets/lune/releases/download",
     );
 
     // NOTE: This is not entirely accurate, but it is clearer for a user
-    println!("Downloading {target_triple}{}...", target.exe_suffix());
+    progress(format_args!(
+        "Downloading {asset_name} ({binary_file_name})..."
+    ));
+
+    // Start reading the zip file
+    let zip_file = Cursor::new(download_release(&release_url, target).await?);
+
+    // Look for and extract the binary file from the zip file
+    // NOTE: We use spawn_blocking here since reading a zip
+    // archive is a somewhat slow / blocking operation
+    let binary_file_handle = unblock(move || {
+        let mut archive = zip::ZipArchive::new(zip_file)?;
+
+        let mut binary = Vec::new();
+        archive
+            .by_name(&binary_file_name)
+            .or(Err(BuildError::ZippedBinaryNotFound(binary_file_name)))?
+            .read_to_end(&mut binary)?;
+
+        Ok::<_, BuildError>(binary)
+    });
+    let binary_file_contents = binary_file_handle.await?;
+
+    // Finally, write the extracted binary to the cache
+    if !CACHE_DIR.exists() {
+        fs::create_dir_all(CACHE_DIR.as_path()).await?;
+    }
+    write_executable_file_to(&cache_path, binary_file_contents).await?;
+    progress(format_args!("Downloaded successfully and added to cache"));
+
+    Ok(cache_path)
+}
 
+/**
+    Downloads the release asset at the given url, returning its contents.
+
+    Downloading uses the networking of the `std-net` feature, so that
+    feature-trimmed builds without it do not include any networking.
+*/
+#[cfg(feature = "std-net")]
+async fn download_release(release_url: &str, target: BuildTarget) -> BuildResult<Vec<u8>> {
     // Try to request to download the zip file from the target url,
     // making sure transient errors are handled gracefully and
     // with a different error message than "not found"
@@ -88,32 +194,13 @@ pub async fn get_or_download_base_executable(target: BuildTarget) -> BuildResult
         )));
     }
 
-    // Start reading the zip file
-    let zip_file = Cursor::new(body.into_bytes());
-
-    // Look for and extract the binary file from the zip file
-    // NOTE: We use spawn_blocking here since reading a zip
-    // archive is a somewhat slow / blocking operation
-    let binary_file_name = format!("lune{}", target.exe_suffix());
-    let binary_file_handle = unblock(move || {
-        let mut archive = zip::ZipArchive::new(zip_file)?;
-
-        let mut binary = Vec::new();
-        archive
-            .by_name(&binary_file_name)
-            .or(Err(BuildError::ZippedBinaryNotFound(binary_file_name)))?
-            .read_to_end(&mut binary)?;
-
-        Ok::<_, BuildError>(binary)
-    });
-    let binary_file_contents = binary_file_handle.await?;
-
-    // Finally, write the extracted binary to the cache
-    if !CACHE_DIR.exists() {
-        fs::create_dir_all(CACHE_DIR.as_path()).await?;
-    }
-    write_executable_file_to(target.cache_path(), binary_file_contents).await?;
-    println!("Downloaded successfully and added to cache");
+    Ok(Vec::from(body.into_bytes()))
+}
 
-    Ok(target.cache_path())
+#[cfg(not(feature = "std-net"))]
+#[allow(clippy::unused_async, clippy::needless_pass_by_value)]
+async fn download_release(_: &str, _: BuildTarget) -> BuildResult<Vec<u8>> {
+    Err(BuildError::Download(String::from(
+        "this build of Lune does not include the std-net feature, which is needed to download base executables",
+    )))
 }
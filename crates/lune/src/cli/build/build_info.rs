@@ -0,0 +1,91 @@
+/*!
+    Collection of the build information embedded into standalone binaries,
+    which is given to the bundled script in the `_BUILD` global.
+
+    The build timestamp is read from the `SOURCE_DATE_EPOCH` environment
+    variable when it is set, so that builds can be made reproducible.
+*/
+
+use std::{
+    collections::BTreeMap,
+    env,
+    path::Path,
+    process::Command,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use blocking::unblock;
+
+use lune_utils::build_info::BuildInfo;
+
+use super::target::BuildTarget;
+
+/**
+    Collects the build information for a binary built for the given target,
+    from the project in the given directory, with the given additional fields.
+*/
+pub async fn collect_build_info(
+    target: &BuildTarget,
+    project_dir: &Path,
+    fields: BTreeMap<String, String>,
+) -> BuildInfo {
+    BuildInfo {
+        timestamp: build_timestamp(env::var("SOURCE_DATE_EPOCH").ok().as_deref()),
+        lune_version: env!("CARGO_PKG_VERSION").to_string(),
+        target: target.to_string(),
+        commit: git_commit(project_dir).await,
+        fields,
+    }
+}
+
+/// Gets the build timestamp from the given value of `SOURCE_DATE_EPOCH`, or the current time
+fn build_timestamp(source_date_epoch: Option<&str>) -> u64 {
+    source_date_epoch
+        .and_then(|epoch| epoch.trim().parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .as_ref()
+                .map_or(0, Duration::as_secs)
+        })
+}
+
+/// Gets the current commit of the git repository that the given directory is in, if any
+async fn git_commit(dir: &Path) -> Option<String> {
+    let dir = dir.to_path_buf();
+    let output = unblock(move || {
+        Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .current_dir(dir)
+            .output()
+    })
+    .await
+    .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let commit = String::from_utf8(output.stdout).ok()?;
+    let commit = commit.trim();
+    (!commit.is_empty()).then(|| commit.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamp_from_source_date_epoch() {
+        assert_eq!(build_timestamp(Some("1700000000")), 1_700_000_000);
+        assert_eq!(build_timestamp(Some(" 1700000000\n")), 1_700_000_000);
+    }
+
+    #[test]
+    fn timestamp_defaults_to_now() {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        assert!(build_timestamp(None) >= now);
+        assert!(build_timestamp(Some("not a number")) >= now);
+    }
+}
@@ -0,0 +1,117 @@
+/*!
+    Parsing of `.env` files, for `lune build --env-file`.
+
+    Each line of a file is either empty, a comment starting with `#`, or a
+    `KEY=VALUE` pair, optionally prefixed with `export`. Values may be quoted -
+    escape sequences such as `\n` are only expanded in double-quoted values,
+    single-quoted values are used as-is, and unquoted values end at a comment.
+*/
+
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{Context, Result, bail};
+use async_fs as fs;
+
+/**
+    Reads the variables of the `.env` file at the given path.
+
+    # Errors
+
+    - If the file could not be read
+    - If the file contains a line that is not a valid variable
+*/
+pub async fn read_env_file(path: &Path) -> Result<BTreeMap<String, String>> {
+    let contents = fs::read_to_string(path)
+        .await
+        .with_context(|| format!("failed to read env file {}", path.display()))?;
+    parse_env_file(&contents).with_context(|| format!("invalid env file {}", path.display()))
+}
+
+fn parse_env_file(contents: &str) -> Result<BTreeMap<String, String>> {
+    let mut vars = BTreeMap::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            bail!("expected KEY=VALUE on line {}", index + 1);
+        };
+        let key = key.trim();
+        if key.is_empty()
+            || !key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        {
+            bail!("invalid variable name '{key}' on line {}", index + 1);
+        }
+        let value = parse_value(value.trim())
+            .with_context(|| format!("invalid value for '{key}' on line {}", index + 1))?;
+        vars.insert(key.to_string(), value);
+    }
+    Ok(vars)
+}
+
+fn parse_value(value: &str) -> Result<String> {
+    if let Some(quoted) = value.strip_prefix('\'') {
+        let Some((inner, _)) = quoted.split_once('\'') else {
+            bail!("missing closing quote");
+        };
+        return Ok(inner.to_string());
+    }
+    if let Some(quoted) = value.strip_prefix('"') {
+        let mut result = String::new();
+        let mut chars = quoted.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Ok(result),
+                '\\' => match chars.next() {
+                    Some('n') => result.push('\n'),
+                    Some('r') => result.push('\r'),
+                    Some('t') => result.push('\t'),
+                    Some(other) => result.push(other),
+                    None => break,
+                },
+                c => result.push(c),
+            }
+        }
+        bail!("missing closing quote");
+    }
+    // Unquoted values end at a comment, which must be preceded by whitespace
+    let end = value.find(" #").unwrap_or(value.len());
+    Ok(value[..end].trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_variables() {
+        let vars = parse_env_file(
+            "# comment\n\
+            \n\
+            PLAIN=value # trailing comment\n\
+            export EXPORTED = spaced\n\
+            SINGLE='no \\n escapes'\n\
+            DOUBLE=\"line\\nbreak \\\"quoted\\\"\"\n\
+            EMPTY=\n\
+            URL=http://host/#fragment\n",
+        )
+        .unwrap();
+        assert_eq!(vars["PLAIN"], "value");
+        assert_eq!(vars["EXPORTED"], "spaced");
+        assert_eq!(vars["SINGLE"], "no \\n escapes");
+        assert_eq!(vars["DOUBLE"], "line\nbreak \"quoted\"");
+        assert_eq!(vars["EMPTY"], "");
+        assert_eq!(vars["URL"], "http://host/#fragment");
+    }
+
+    #[test]
+    fn rejects_invalid_lines() {
+        assert!(parse_env_file("NO_VALUE").is_err());
+        assert!(parse_env_file("BAD KEY=1").is_err());
+        assert!(parse_env_file("OPEN=\"unterminated").is_err());
+    }
+}
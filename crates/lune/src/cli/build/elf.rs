@@ -0,0 +1,266 @@
+/*!
+    Inspection and modification of Linux ELF executables.
+
+    Used to make sure that base executables for musl targets are fully static,
+    since a musl executable that is dynamically linked needs the musl loader at
+    runtime, and fails to run on distros without it, the same as for glibc.
+
+    Also used to add sections to executables, for storing the metadata
+    of standalone binaries in a section of their own, see [`super::section`].
+*/
+
+use anyhow::{Context, Result, bail};
+
+const ELF_MAGIC: &[u8; 4] = b"\x7fELF";
+const ELF_CLASS_64: u8 = 2;
+const ELF_DATA_LITTLE: u8 = 1;
+
+const PT_INTERP: u32 = 3;
+
+const SHT_PROGBITS: u32 = 1;
+const SECTION_HEADER_SIZE: usize = 64;
+/// Section indices from this one and up are reserved, and can not be used
+const SHN_LORESERVE: usize = 0xff00;
+
+fn read_u16_le(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64_le(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+fn write_u16_le(buf: &mut [u8], offset: usize, val: u16) {
+    buf[offset..offset + 2].copy_from_slice(&val.to_le_bytes());
+}
+
+fn write_u32_le(buf: &mut [u8], offset: usize, val: u32) {
+    buf[offset..offset + 4].copy_from_slice(&val.to_le_bytes());
+}
+
+fn write_u64_le(buf: &mut [u8], offset: usize, val: u64) {
+    buf[offset..offset + 8].copy_from_slice(&val.to_le_bytes());
+}
+
+fn is_elf64_le(data: &[u8]) -> bool {
+    data.starts_with(ELF_MAGIC)
+        && data.get(4) == Some(&ELF_CLASS_64)
+        && data.get(5) == Some(&ELF_DATA_LITTLE)
+}
+
+/**
+    Returns whether the given 64-bit little-endian ELF executable is statically
+    linked, meaning that it does not request a program interpreter (dynamic loader).
+
+    Static PIE executables, which relocate themselves, are also statically linked.
+
+    Returns `None` if the given bytes are not a 64-bit little-endian ELF executable.
+*/
+pub fn is_statically_linked(data: &[u8]) -> Option<bool> {
+    if !is_elf64_le(data) {
+        return None;
+    }
+
+    let phoff = usize::try_from(read_u64_le(data, 0x20)?).ok()?;
+    let phentsize = usize::from(read_u16_le(data, 0x36)?);
+    let phnum = usize::from(read_u16_le(data, 0x38)?);
+
+    for index in 0..phnum {
+        let p_type = read_u32_le(data, phoff + index * phentsize)?;
+        if p_type == PT_INTERP {
+            return Some(false);
+        }
+    }
+    Some(true)
+}
+
+/// Location of the section header table of an ELF executable
+struct SectionTable {
+    offset: usize,
+    count: usize,
+    names_index: usize,
+}
+
+fn read_section_table(data: &[u8]) -> Option<SectionTable> {
+    let offset = usize::try_from(read_u64_le(data, 0x28)?).ok()?;
+    let entry_size = usize::from(read_u16_le(data, 0x3A)?);
+    let count = usize::from(read_u16_le(data, 0x3C)?);
+    let names_index = usize::from(read_u16_le(data, 0x3E)?);
+    if offset == 0 || count == 0 || entry_size != SECTION_HEADER_SIZE || names_index >= count {
+        return None;
+    }
+    Some(SectionTable {
+        offset,
+        count,
+        names_index,
+    })
+}
+
+/**
+    Adds a new section with the given name and contents to the given
+    64-bit little-endian ELF executable, in place.
+
+    The section is not allocated, meaning it is not loaded into memory, so
+    its contents, a new section name table, and a new section header table
+    are all appended to the end of the executable, and none of the existing
+    contents of the executable need to be moved.
+
+    # Errors
+
+    - If the given data is not a 64-bit little-endian ELF executable
+    - If the executable has no section headers, such as when it is packed
+*/
+pub fn add_section(data: &mut Vec<u8>, name: &[u8], contents: &[u8]) -> Result<()> {
+    if !is_elf64_le(data) {
+        bail!("base executable is not a valid Linux executable");
+    }
+    let table = read_section_table(data).context("base executable has no section headers")?;
+    if table.count + 1 >= SHN_LORESERVE {
+        bail!("base executable has too many sections to add another");
+    }
+
+    let mut headers = table
+        .offset
+        .checked_add(table.count * SECTION_HEADER_SIZE)
+        .and_then(|end| data.get(table.offset..end))
+        .context("base executable has truncated section headers")?
+        .to_vec();
+    let names_header = table.names_index * SECTION_HEADER_SIZE;
+    let mut names = read_u64_le(&headers, names_header + 0x18)
+        .zip(read_u64_le(&headers, names_header + 0x20))
+        .and_then(|(offset, size)| {
+            let offset = usize::try_from(offset).ok()?;
+            data.get(offset..offset.checked_add(usize::try_from(size).ok()?)?)
+        })
+        .context("base executable has a truncated section name table")?
+        .to_vec();
+    let name_offset = names.len();
+    names.extend_from_slice(name);
+    names.push(0);
+
+    // Append the contents, the names including the new one, and then
+    // the headers including the new one, which must be 8-byte aligned
+    let contents_offset = data.len();
+    data.extend_from_slice(contents);
+    let names_offset = data.len();
+    data.extend_from_slice(&names);
+    data.resize(data.len().next_multiple_of(8), 0);
+    let headers_offset = data.len();
+
+    write_u64_le(&mut headers, names_header + 0x18, names_offset as u64);
+    write_u64_le(&mut headers, names_header + 0x20, names.len() as u64);
+
+    let mut header = [0u8; SECTION_HEADER_SIZE];
+    write_u32_le(&mut header, 0x00, u32::try_from(name_offset)?);
+    write_u32_le(&mut header, 0x04, SHT_PROGBITS);
+    write_u64_le(&mut header, 0x18, contents_offset as u64);
+    write_u64_le(&mut header, 0x20, contents.len() as u64);
+    write_u64_le(&mut header, 0x30, 1); // Alignment
+    headers.extend_from_slice(&header);
+    data.extend_from_slice(&headers);
+
+    write_u64_le(data, 0x28, headers_offset as u64);
+    write_u16_le(data, 0x3C, u16::try_from(table.count + 1)?);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::standalone::section::{ELF_SECTION_NAME, find_metadata_section};
+
+    use super::*;
+
+    const PHOFF: usize = 64;
+    const PHENTSIZE: usize = 56;
+
+    fn build_test_elf(program_headers: &[u32]) -> Vec<u8> {
+        let mut data = vec![0u8; PHOFF + PHENTSIZE * program_headers.len()];
+        data[..4].copy_from_slice(ELF_MAGIC);
+        data[4] = ELF_CLASS_64;
+        data[5] = ELF_DATA_LITTLE;
+        data[0x20..0x28].copy_from_slice(&(PHOFF as u64).to_le_bytes());
+        data[0x36..0x38].copy_from_slice(&(PHENTSIZE as u16).to_le_bytes());
+        data[0x38..0x3a].copy_from_slice(&(program_headers.len() as u16).to_le_bytes());
+        for (index, p_type) in program_headers.iter().enumerate() {
+            let offset = PHOFF + index * PHENTSIZE;
+            data[offset..offset + 4].copy_from_slice(&p_type.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn static_executables() {
+        // PT_PHDR, PT_LOAD, PT_DYNAMIC (static PIE), PT_GNU_STACK
+        let elf = build_test_elf(&[6, 1, 1, 2, 0x6474_e551]);
+        assert_eq!(is_statically_linked(&elf), Some(true));
+    }
+
+    #[test]
+    fn dynamic_executables() {
+        // PT_PHDR, PT_INTERP, PT_LOAD, PT_DYNAMIC
+        let elf = build_test_elf(&[6, PT_INTERP, 1, 2]);
+        assert_eq!(is_statically_linked(&elf), Some(false));
+    }
+
+    /// Build an ELF executable with a null section and a section name table
+    fn build_test_elf_with_sections() -> Vec<u8> {
+        let mut elf = build_test_elf(&[1]);
+        let names_offset = elf.len();
+        elf.extend_from_slice(b"\0.shstrtab\0");
+        elf.resize(elf.len().next_multiple_of(8), 0);
+        let headers_offset = elf.len();
+        elf.resize(headers_offset + SECTION_HEADER_SIZE * 2, 0);
+
+        let names_header = headers_offset + SECTION_HEADER_SIZE;
+        write_u32_le(&mut elf, names_header, 1);
+        write_u32_le(&mut elf, names_header + 0x04, 3); // SHT_STRTAB
+        write_u64_le(&mut elf, names_header + 0x18, names_offset as u64);
+        write_u64_le(&mut elf, names_header + 0x20, 11);
+
+        write_u64_le(&mut elf, 0x28, headers_offset as u64);
+        write_u16_le(&mut elf, 0x3A, SECTION_HEADER_SIZE as u16);
+        write_u16_le(&mut elf, 0x3C, 2);
+        write_u16_le(&mut elf, 0x3E, 1);
+        elf
+    }
+
+    #[test]
+    fn added_sections() {
+        let mut elf = build_test_elf_with_sections();
+        add_section(&mut elf, ELF_SECTION_NAME, b"payload").unwrap();
+
+        let table = read_section_table(&elf).unwrap();
+        assert_eq!(table.count, 3);
+        assert_eq!(find_metadata_section(&elf), Some(&b"payload"[..]));
+        // Existing sections are kept, and executables are still recognized
+        assert_eq!(is_statically_linked(&elf), Some(true));
+    }
+
+    #[test]
+    fn added_sections_need_section_headers() {
+        let mut elf = build_test_elf(&[1]);
+        assert!(add_section(&mut elf, ELF_SECTION_NAME, b"payload").is_err());
+        assert!(add_section(&mut b"MZ".to_vec(), ELF_SECTION_NAME, b"payload").is_err());
+    }
+
+    #[test]
+    fn not_elf_executables() {
+        assert_eq!(is_statically_linked(b"MZ\x90\x00"), None);
+        assert_eq!(is_statically_linked(b"\x7fELF"), None);
+        // Truncated program headers
+        let mut elf = build_test_elf(&[6, 1]);
+        elf.truncate(PHOFF + 8);
+        assert_eq!(is_statically_linked(&elf), None);
+    }
+}
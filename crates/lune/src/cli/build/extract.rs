@@ -0,0 +1,178 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use async_fs as fs;
+use console::style;
+use lune_utils::path::LuauModulePath;
+
+use crate::standalone::metadata::Metadata;
+
+use super::output::progress;
+
+/**
+    Extracts all of the bundled files from the standalone binary at `input`
+    into the directory at `output`, and reconstructs a `.luaurc` file at
+    the root of the output directory from the bundled alias mappings.
+
+    Returns the number of files that were written, excluding the `.luaurc`.
+*/
+pub async fn extract_standalone_binary(input: &Path, output: &Path) -> Result<usize> {
+    let contents = fs::read(input)
+        .await
+        .with_context(|| format!("failed to read binary at {}", input.display()))?;
+    let mut meta = Metadata::from_bytes(contents)
+        .with_context(|| format!("{} is not a standalone binary", input.display()))?;
+    meta.decrypt()
+        .with_context(|| format!("failed to decrypt {}", input.display()))?;
+    if meta.source_stripped {
+        bail!(
+            "{} was built with --strip-source and does not contain any source code",
+            input.display()
+        );
+    }
+
+    // The entry point is usually also part of the bundled files,
+    // but older binaries and single-file builds may only have it
+    // stored as the main source, so make sure we always extract it
+    let mut files = meta.files.into_iter().collect::<BTreeMap<_, _>>();
+    files.entry(meta.entry_path.clone()).or_insert(meta.source);
+
+    fs::create_dir_all(output).await?;
+    for (bundle_path, source) in &files {
+        let file_path = output.join(bundle_path_to_relative(bundle_path)?);
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(&file_path, source)
+            .await
+            .with_context(|| format!("failed to write file {}", file_path.display()))?;
+        progress(format_args!("Extracted {}", style(bundle_path).green()));
+    }
+
+    let aliases = reconstruct_aliases(&meta.aliases);
+    if !aliases.is_empty() {
+        let config = serde_json::json!({ "aliases": aliases });
+        let config_path = output.join(".luaurc");
+        fs::write(&config_path, serde_json::to_string_pretty(&config)?).await?;
+        progress(format_args!(
+            "Reconstructed {} aliases in {}",
+            style(aliases.len()).cyan(),
+            style(config_path.display()).blue()
+        ));
+    }
+
+    progress(format_args!(
+        "Entry point for the extracted files is {}",
+        style(&meta.entry_path).green()
+    ));
+
+    Ok(files.len())
+}
+
+/**
+    Converts a bundle key such as `/src/main.luau` into a relative
+    path that is safe to join onto the extraction output directory.
+*/
+fn bundle_path_to_relative(bundle_path: &str) -> Result<PathBuf> {
+    let mut relative = PathBuf::new();
+    for segment in bundle_path.split(['/', '\\']) {
+        match segment {
+            "" | "." => {}
+            ".." => bail!("refusing to extract file outside of output directory: {bundle_path}"),
+            // Files bundled from outside of the project root on Windows
+            // keep their drive letter, which we can't use as a directory
+            s if s.ends_with(':') => relative.push(s.trim_end_matches(':')),
+            s => relative.push(s),
+        }
+    }
+    if relative.as_os_str().is_empty() {
+        bail!("invalid bundled file path: {bundle_path}");
+    }
+    Ok(relative)
+}
+
+/**
+    Reconstructs `.luaurc` aliases from bundled alias mappings.
+
+    Bundled aliases map full require paths (`@pkg/Foo`) to bundled files
+    (`/Packages/Foo.luau`), so the alias directory can be recovered by
+    stripping the required sub-path from the module path of the file.
+*/
+fn reconstruct_aliases(bundled: &HashMap<String, String>) -> BTreeMap<String, String> {
+    let mut sorted = bundled.iter().collect::<Vec<_>>();
+    sorted.sort();
+
+    let mut aliases = BTreeMap::new();
+    for (require_path, file_path) in sorted {
+        let Some(alias_path) = require_path.strip_prefix('@') else {
+            continue;
+        };
+        let (alias_name, rest) = match alias_path.split_once('/') {
+            Some((name, rest)) => (name, Some(rest)),
+            None => (alias_path, None),
+        };
+
+        let module_path = LuauModulePath::strip(file_path.as_str());
+        let module_path = module_path.to_string_lossy().replace('\\', "/");
+        let alias_dir = match rest {
+            None => Some(module_path.as_str()),
+            Some(rest) => module_path.strip_suffix(&format!("/{rest}")),
+        };
+
+        if let Some(alias_dir) = alias_dir {
+            aliases
+                .entry(alias_name.to_string())
+                .or_insert_with(|| format!(".{alias_dir}"));
+        }
+    }
+    aliases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_path_strips_leading_slash() {
+        let path = bundle_path_to_relative("/src/main.luau").unwrap();
+        assert_eq!(path, Path::new("src").join("main.luau"));
+    }
+
+    #[test]
+    fn relative_path_rejects_parent_components() {
+        assert!(bundle_path_to_relative("/src/../../etc/passwd").is_err());
+    }
+
+    #[test]
+    fn relative_path_strips_drive_letters() {
+        let path = bundle_path_to_relative("C:/Users/dev/lib.luau").unwrap();
+        assert_eq!(
+            path,
+            Path::new("C").join("Users").join("dev").join("lib.luau")
+        );
+    }
+
+    #[test]
+    fn aliases_with_sub_paths() {
+        let mut bundled = HashMap::new();
+        bundled.insert("@pkg/Foo".to_string(), "/Packages/Foo.luau".to_string());
+        bundled.insert(
+            "@pkg/Bar".to_string(),
+            "/Packages/Bar/init.luau".to_string(),
+        );
+        let aliases = reconstruct_aliases(&bundled);
+        assert_eq!(aliases.len(), 1);
+        assert_eq!(aliases["pkg"], "./Packages");
+    }
+
+    #[test]
+    fn aliases_without_sub_paths() {
+        let mut bundled = HashMap::new();
+        bundled.insert("@utils".to_string(), "/src/utils/init.luau".to_string());
+        let aliases = reconstruct_aliases(&bundled);
+        assert_eq!(aliases["utils"], "./src/utils");
+    }
+}
@@ -0,0 +1,182 @@
+use std::{collections::BTreeSet, fmt, path::Path, process::Command, str::FromStr};
+
+use async_fs as fs;
+use blocking::unblock;
+
+use super::{
+    output::progress,
+    result::{BuildError, BuildResult},
+    target::BuildTarget,
+};
+
+/// Standard libraries that may be included in feature-trimmed base executables,
+/// each of which is enabled using the `std-<name>` cargo feature of `lune`
+const STD_LIBRARIES: &[&str] = &[
+    "buffer", "datetime", "events", "fs", "ipc", "luau", "net", "process", "regex", "roblox",
+    "serde", "stdio", "task",
+];
+
+/// Repository to build feature-trimmed base executables from, using `cargo install`
+const SOURCE_REPOSITORY: &str = "https://github.com/horsenuggets/lune";
+
+/**
+    The standard libraries to include in a feature-trimmed base
+    executable, for `lune build --base-features net,fs,task`.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BaseFeatures(BTreeSet<&'static str>);
+
+impl BaseFeatures {
+    /**
+        Returns a stable label for this set of features, such as `fs+net+task`,
+        used in the names of cached and pre-built base executables.
+    */
+    pub fn label(&self) -> String {
+        if self.0.is_empty() {
+            return String::from("none");
+        }
+        self.0.iter().copied().collect::<Vec<_>>().join("+")
+    }
+
+    /**
+        Returns the cargo features to build `lune` with for this set of features.
+    */
+    pub fn cargo_features(&self) -> String {
+        std::iter::once(String::from("cli"))
+            .chain(self.0.iter().map(|name| format!("std-{name}")))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+impl FromStr for BaseFeatures {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut features = BTreeSet::new();
+        for name in s.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let name = name.to_ascii_lowercase();
+            let name = name.strip_prefix("std-").unwrap_or(&name);
+            let Some(library) = STD_LIBRARIES.iter().find(|library| **library == name) else {
+                return Err(format!(
+                    "unknown standard library '{name}', expected any of {}",
+                    STD_LIBRARIES.join(", ")
+                ));
+            };
+            features.insert(*library);
+        }
+        Ok(Self(features))
+    }
+}
+
+impl fmt::Display for BaseFeatures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.0.iter().copied().collect::<Vec<_>>().join(",")
+        )
+    }
+}
+
+/**
+    Checks if a Rust toolchain with `cargo` is available on the current system.
+*/
+pub async fn is_cargo_available() -> bool {
+    unblock(|| Command::new("cargo").arg("--version").output())
+        .await
+        .is_ok_and(|output| output.status.success())
+}
+
+/**
+    Builds a feature-trimmed base executable for the given target using
+    `cargo install`, from the sources of the current version of Lune,
+    and writes it to the given cache path.
+
+    # Errors
+
+    - If cargo fails to build the executable, such as when the Rust
+      toolchain for the target, or a linker for it, is not installed
+*/
+pub async fn build_base_executable(
+    target: &BuildTarget,
+    features: &BaseFeatures,
+    cache_path: &Path,
+) -> BuildResult<()> {
+    let version = env!("CARGO_PKG_VERSION");
+    let root = std::env::temp_dir().join(format!(
+        "lune-base-{target}-{}-{}",
+        features.label(),
+        std::process::id()
+    ));
+
+    progress(format_args!(
+        "Building base executable for '{target}' with features {features} using cargo, \
+        this may take a while..."
+    ));
+    let args = [
+        "install".to_string(),
+        "lune".to_string(),
+        "--git".to_string(),
+        SOURCE_REPOSITORY.to_string(),
+        "--tag".to_string(),
+        format!("v{version}"),
+        "--locked".to_string(),
+        "--no-default-features".to_string(),
+        "--features".to_string(),
+        features.cargo_features(),
+        "--target".to_string(),
+        target.rust_triple().to_string(),
+        "--root".to_string(),
+        root.display().to_string(),
+    ];
+    let status = unblock(move || Command::new("cargo").args(args).status()).await?;
+    if !status.success() {
+        let _ = fs::remove_dir_all(&root).await;
+        return Err(BuildError::Cargo(format!(
+            "build exited with {status} - make sure that the Rust toolchain \
+            for '{}' is installed, using `rustup target add`",
+            target.rust_triple()
+        )));
+    }
+
+    let built_path = root
+        .join("bin")
+        .join(format!("lune{}", target.exe_suffix()));
+    if let Some(parent) = cache_path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    let result = fs::copy(&built_path, cache_path).await;
+    let _ = fs::remove_dir_all(&root).await;
+    result.map_err(|e| {
+        BuildError::Cargo(format!(
+            "failed to copy built executable {}: {e}",
+            built_path.display()
+        ))
+    })?;
+
+    progress(format_args!("Built successfully and added to cache"));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_features() {
+        let features: BaseFeatures = "net, fs,std-task,, fs".parse().unwrap();
+        assert_eq!(features.label(), "fs+net+task");
+        assert_eq!(features.cargo_features(), "cli,std-fs,std-net,std-task");
+        assert_eq!(features.to_string(), "fs,net,task");
+
+        let features: BaseFeatures = "".parse().unwrap();
+        assert_eq!(features.label(), "none");
+        assert_eq!(features.cargo_features(), "cli");
+    }
+
+    #[test]
+    fn rejects_unknown_features() {
+        let error = "fs,gui".parse::<BaseFeatures>().unwrap_err();
+        assert!(error.contains("'gui'"));
+    }
+}
@@ -0,0 +1,137 @@
+/*!
+    Merging of thin macOS Mach-O binaries into a single universal binary.
+
+    This does the same as `lipo -create` from Apple's developer tools, but
+    works on any host system. A universal binary, also known as a fat binary,
+    is a small header listing the architecture, offset and size of each thin
+    binary, followed by all of the thin binaries, each aligned to a page.
+
+    Each thin binary keeps its own appended metadata and code signature, which
+    means that the universal binary ends with the metadata of its last slice,
+    and that the standalone runtime finds it the same way as for thin binaries.
+*/
+
+use anyhow::{Result, bail};
+
+const FAT_MAGIC: u32 = 0xcafe_babe;
+const MH_MAGIC_64: u32 = 0xfeed_facf;
+
+const FAT_HEADER_SIZE: usize = 8;
+const FAT_ARCH_SIZE: usize = 20;
+
+// NOTE: Apple's lipo aligns both x86_64 and arm64 slices to 16 KiB pages
+const SLICE_ALIGN_BITS: u32 = 14;
+const SLICE_ALIGN: usize = 1 << SLICE_ALIGN_BITS;
+
+fn read_u32_le(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn align_up(value: usize, align: usize) -> usize {
+    value.div_ceil(align) * align
+}
+
+/**
+    Merges the given thin 64-bit Mach-O binaries into a single universal binary.
+
+    # Errors
+
+    - If no binaries were given
+    - If any of the binaries is not a thin 64-bit Mach-O binary
+    - If more than one binary is for the same architecture
+    - If the universal binary would be larger than 4 GiB
+*/
+pub fn create_universal_binary(slices: &[Vec<u8>]) -> Result<Vec<u8>> {
+    if slices.is_empty() {
+        bail!("no binaries were given to merge into a universal binary");
+    }
+
+    let mut cpu_types = Vec::with_capacity(slices.len());
+    for slice in slices {
+        if slice.len() < 12 || read_u32_le(slice, 0) != MH_MAGIC_64 {
+            bail!("only thin 64-bit Mach-O binaries can be merged into a universal binary");
+        }
+        let cpu_type = read_u32_le(slice, 4);
+        let cpu_subtype = read_u32_le(slice, 8);
+        if cpu_types.iter().any(|(t, _)| *t == cpu_type) {
+            bail!("more than one binary was given for the same architecture");
+        }
+        cpu_types.push((cpu_type, cpu_subtype));
+    }
+
+    let header_size = FAT_HEADER_SIZE + FAT_ARCH_SIZE * slices.len();
+    let mut offsets = Vec::with_capacity(slices.len());
+    let mut end = header_size;
+    for slice in slices {
+        let offset = align_up(end, SLICE_ALIGN);
+        offsets.push(offset);
+        end = offset + slice.len();
+    }
+    if u32::try_from(end).is_err() {
+        bail!("universal binaries larger than 4 GiB are not supported");
+    }
+
+    let mut bytes = Vec::with_capacity(end);
+    bytes.extend_from_slice(&FAT_MAGIC.to_be_bytes());
+    bytes.extend_from_slice(&u32::try_from(slices.len())?.to_be_bytes());
+    for ((slice, offset), (cpu_type, cpu_subtype)) in slices.iter().zip(&offsets).zip(&cpu_types) {
+        bytes.extend_from_slice(&cpu_type.to_be_bytes());
+        bytes.extend_from_slice(&cpu_subtype.to_be_bytes());
+        bytes.extend_from_slice(&u32::try_from(*offset)?.to_be_bytes());
+        bytes.extend_from_slice(&u32::try_from(slice.len())?.to_be_bytes());
+        bytes.extend_from_slice(&SLICE_ALIGN_BITS.to_be_bytes());
+    }
+    for (slice, offset) in slices.iter().zip(&offsets) {
+        bytes.resize(*offset, 0);
+        bytes.extend_from_slice(slice);
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CPU_TYPE_X86_64: u32 = 0x0100_0007;
+    const CPU_TYPE_ARM64: u32 = 0x0100_000c;
+
+    fn read_u32_be(data: &[u8], offset: usize) -> u32 {
+        u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn thin(cpu_type: u32, len: usize) -> Vec<u8> {
+        let mut data = vec![0xab; len];
+        data[0..4].copy_from_slice(&MH_MAGIC_64.to_le_bytes());
+        data[4..8].copy_from_slice(&cpu_type.to_le_bytes());
+        data[8..12].copy_from_slice(&3u32.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn merges_slices() {
+        let x86 = thin(CPU_TYPE_X86_64, 5000);
+        let arm = thin(CPU_TYPE_ARM64, 20000);
+        let fat = create_universal_binary(&[x86.clone(), arm.clone()]).unwrap();
+
+        assert_eq!(read_u32_be(&fat, 0), FAT_MAGIC);
+        assert_eq!(read_u32_be(&fat, 4), 2);
+
+        let x86_offset = read_u32_be(&fat, 16) as usize;
+        let arm_offset = read_u32_be(&fat, 36) as usize;
+        assert_eq!(read_u32_be(&fat, 8), CPU_TYPE_X86_64);
+        assert_eq!(read_u32_be(&fat, 28), CPU_TYPE_ARM64);
+        assert_eq!(x86_offset % SLICE_ALIGN, 0);
+        assert_eq!(arm_offset % SLICE_ALIGN, 0);
+        assert_eq!(&fat[x86_offset..x86_offset + x86.len()], &x86[..]);
+        assert_eq!(&fat[arm_offset..], &arm[..]);
+    }
+
+    #[test]
+    fn rejects_invalid_slices() {
+        assert!(create_universal_binary(&[]).is_err());
+        assert!(create_universal_binary(&[vec![0; 64]]).is_err());
+        let arm = thin(CPU_TYPE_ARM64, 64);
+        assert!(create_universal_binary(&[arm.clone(), arm]).is_err());
+    }
+}
@@ -1,37 +1,82 @@
 use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    io::stdin,
+    num::NonZeroUsize,
     path::{Path, PathBuf},
     process::ExitCode,
 };
 
 use anyhow::{Context, Result, bail};
 use async_fs as fs;
+use blocking::{Unblock, unblock};
 use clap::Parser;
 use console::style;
+use futures_lite::AsyncReadExt;
 
-use crate::standalone::metadata::Metadata;
+use lune_bundler::{Bundler, bundler::create_source_map, module_cache::ModuleCache, strip_shebang};
+use lune_utils::namespace::NamespaceManifest;
+use lune_utils::remote::{Lockfile, RemoteCache};
 
+use crate::standalone::{
+    metadata::Metadata,
+    signature::{MetadataSigningKey, encode_hex},
+};
+
+use super::run::remote_requires;
+use super::utils::config::{PackageConfig, ProjectConfig};
+
+mod archive;
 mod base_exe;
-mod bundler;
+mod build_info;
 mod codesign;
+mod dotenv;
+mod elf;
+mod extract;
+mod features;
 mod files;
+mod lipo;
+mod oci;
+mod output;
+mod pack;
+mod package;
+mod pe_resources;
+mod report;
 mod result;
+mod section;
+mod signing;
+mod strip;
 mod target;
+mod validate;
+mod wasm;
+mod workers;
 
-use self::base_exe::get_or_download_base_executable;
-use self::bundler::{Bundler, normalize_bundle_path};
+use self::base_exe::{
+    get_or_build_trimmed_base_executable, get_or_download_base_executable,
+    get_or_download_base_library,
+};
+use self::build_info::collect_build_info;
+use self::dotenv::read_env_file;
+use self::elf::is_statically_linked;
+use self::extract::extract_standalone_binary;
+use self::features::BaseFeatures;
 use self::files::{remove_source_file_ext, write_executable_file_to};
-use self::target::BuildTarget;
-
-/// Strip shebang line from source code if present
-fn strip_shebang(mut contents: Vec<u8>) -> Vec<u8> {
-    if contents.starts_with(b"#!") {
-        if let Some(idx) = contents.iter().position(|&c| c == b'\n') {
-            // Keep the newline to preserve line numbers
-            contents.drain(..idx);
-        }
-    }
-    contents
-}
+use self::lipo::create_universal_binary;
+use self::oci::{ImageReference, create_image_archive};
+use self::output::{
+    BuildSummary, ExtractSummary, OutputFormat, print_json, progress, set_quiet, warning,
+};
+use self::pack::pack_executable;
+use self::package::{PackageFormat, PackageInfo, create_package};
+use self::pe_resources::{WindowsResources, embed_resources, parse_version};
+use self::report::BundleReport;
+use self::section::embed_metadata_section;
+use self::signing::{SigningMode, run_notarize_hook, sign_with_identity};
+use self::strip::{compile_bytecode_cached, strip_bundled_files};
+use self::target::{BUNDLE_CACHE_DIR, BuildTarget, MODULE_CACHE_DIR};
+use self::validate::validate_bundled_files;
+use self::wasm::embed_payload;
+use self::workers::Workers;
 
 /// Find the actual source file for a given path.
 /// If the path is a directory containing init.luau or init.lua, return that file.
@@ -50,41 +95,368 @@ fn resolve_entry_file(path: &Path) -> PathBuf {
     path.to_path_buf()
 }
 
+/// Parse a `KEY=VALUE` pair for version strings and defines
+pub(crate) fn parse_key_value(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .filter(|(key, _)| !key.trim().is_empty())
+        .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{s}'"))
+}
+
+/// Read a manifest of namespaces provided by the application that runs the bundle
+async fn read_namespace_manifest(path: &Path) -> Result<NamespaceManifest> {
+    let contents = fs::read(path)
+        .await
+        .with_context(|| format!("failed to read namespace manifest {}", path.display()))?;
+    serde_json::from_slice(&contents)
+        .with_context(|| format!("invalid namespace manifest {}", path.display()))
+}
+
+/// Package the binary at the given path into an OCI image archive next to it,
+/// returning the path of the written archive
+async fn write_oci_image(
+    binary_path: &Path,
+    target: &BuildTarget,
+    reference: &ImageReference,
+) -> Result<PathBuf> {
+    let binary = fs::read(binary_path)
+        .await
+        .context("failed to read standalone binary")?;
+    let binary_name = binary_path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("lune")
+        .to_string();
+    let image_path = binary_path.with_file_name(format!("{binary_name}.oci.tar"));
+
+    progress(format_args!(
+        "Packaging standalone binary as image {}",
+        style(reference).green()
+    ));
+    let (target, reference) = (target.clone(), reference.clone());
+    let archive =
+        unblock(move || create_image_archive(&binary, &binary_name, &target, &reference)).await?;
+    fs::write(&image_path, archive)
+        .await
+        .context("failed to write image archive")?;
+
+    progress(format_args!(
+        "Wrote image archive to {}",
+        style(image_path.display()).blue()
+    ));
+    Ok(image_path)
+}
+
+/// An entry script given through stdin or `--eval`, instead of an input file
+struct InlineSource {
+    /// Name of the entry file in the bundle, in the current directory
+    file_name: &'static str,
+    /// Description of where the script came from, for progress messages
+    description: &'static str,
+    source: Vec<u8>,
+}
+
 /// Build a standalone executable
 #[derive(Debug, Clone, Parser)]
 pub struct BuildCommand {
-    /// The path to the input file
-    pub input: PathBuf,
+    /// The path to the input file, or `-` to read the
+    /// entry script from stdin, which requires `--output`
+    #[clap(required_unless_present = "eval")]
+    pub input: Option<PathBuf>,
+
+    /// The source code of the entry script to build, instead of reading it
+    /// from an input file - requires given in it are resolved relative to
+    /// the current directory, and `--output` is required
+    #[clap(long, value_name = "CODE", conflicts_with_all = ["input", "extract"])]
+    pub eval: Option<String>,
+
+    /// Additional named entry scripts to bundle, such as `fmt=tools/fmt.luau` -
+    /// the binary runs the entry script named after the name it is invoked as,
+    /// such as through a symlink, or the one given using `--lune-entry <name>`,
+    /// and runs the input script otherwise - may be given multiple times
+    #[clap(long = "entry", value_name = "NAME=PATH", value_parser = parse_key_value)]
+    pub entries: Vec<(String, String)>,
 
     /// The path to the output file - defaults to the
     /// input file path with an executable extension
     #[clap(short, long)]
     pub output: Option<PathBuf>,
 
-    /// The target to compile for in the format `os-arch` -
-    /// defaults to the os and arch of the current system
+    /// The target to compile for in the format `os-arch`, or `os-arch-musl`
+    /// for Linux systems without glibc - defaults to the current system,
+    /// `macos-universal` builds a single binary for all Mac systems,
+    /// and `wasm32-wasi` builds a WASI module for WebAssembly runtimes
     #[clap(short, long)]
     pub target: Option<BuildTarget>,
+
+    /// Extract the bundled files of an existing standalone binary
+    /// given as the input, instead of building a new one - the output
+    /// path is the directory to extract to, defaulting to the input
+    /// file path with an `-extracted` suffix
+    #[clap(long)]
+    pub extract: bool,
+
+    /// Path to an `.ico` file to embed as the icon of
+    /// the executable - only supported for Windows targets
+    #[clap(long)]
+    pub icon: Option<PathBuf>,
+
+    /// The file and product version to embed in the
+    /// executable - only supported for Windows targets
+    #[clap(long)]
+    pub file_version: Option<String>,
+
+    /// Additional version information to embed in the executable, such as
+    /// `CompanyName=Acme` or `ProductName=Tool` - may be given multiple
+    /// times, and is only supported for Windows targets
+    #[clap(long = "version-string", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub version_strings: Vec<(String, String)>,
+
+    /// Values to expose to the bundled script through the `_DEFINES`
+    /// global, such as `VERSION=1.2.3` or `FEATURE_X=true` - may be
+    /// given multiple times, and `true` or `false` become booleans
+    #[clap(long = "define", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub defines: Vec<(String, String)>,
+
+    /// Additional fields to expose to the bundled script through the
+    /// `fields` table of the `_BUILD` global, along with the build
+    /// timestamp, target and git commit - may be given multiple times
+    #[clap(long = "build-info", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub build_info: Vec<(String, String)>,
+
+    /// Path to a `.env` file with default environment variables for the
+    /// binary, given to the bundled script in `process.env` unless the
+    /// same variables are set when the binary runs - may be given multiple
+    /// times, with later files taking precedence, and values are stored
+    /// in the binary as-is, so they should not contain any secrets
+    #[clap(long = "env-file", value_name = "PATH")]
+    pub env_files: Vec<PathBuf>,
+
+    /// A default environment variable for the binary, such as `LOG_LEVEL=info`,
+    /// used unless the same variable is set when the binary runs - may be given
+    /// multiple times, and takes precedence over variables from `--env-file`
+    #[clap(long = "env", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub env_vars: Vec<(String, String)>,
+
+    /// A default argument for the binary, such as a subcommand or the path to
+    /// a config file, passed to the bundled script before the arguments given
+    /// when the binary runs - may be given multiple times, in order
+    #[clap(long = "arg", value_name = "ARG", allow_hyphen_values = true)]
+    pub default_args: Vec<String>,
+
+    /// The identity to sign macOS binaries with, using Apple's
+    /// `codesign` tool - defaults to `-`, which signs ad-hoc using
+    /// the built-in signer, and works on any host system
+    #[clap(long, value_name = "IDENTITY")]
+    pub codesign_identity: Option<String>,
+
+    /// Path to an entitlements file to use when
+    /// signing macOS binaries with an identity
+    #[clap(long)]
+    pub entitlements: Option<PathBuf>,
+
+    /// Skip re-signing macOS binaries after building
+    #[clap(long)]
+    pub no_codesign: bool,
+
+    /// A shell command to run after the binary has been written and signed,
+    /// such as a call to `xcrun notarytool` - the output path and target are
+    /// given in the `LUNE_BUILD_OUTPUT` and `LUNE_BUILD_TARGET` variables
+    #[clap(long, value_name = "COMMAND")]
+    pub notarize_hook: Option<String>,
+
+    /// Write a crash report to the given directory when the standalone
+    /// binary fails with an uncaught error - a leading `~` is expanded
+    /// to the home directory of the user running the binary
+    #[clap(long, value_name = "DIR")]
+    pub crash_report_dir: Option<String>,
+
+    /// Embed a source map in the binary, so that errors show the original
+    /// paths of bundled files relative to the current directory, instead
+    /// of their paths inside of the bundle
+    #[clap(long)]
+    pub source_map: bool,
+
+    /// Store only compiled bytecode in the binary, without any of the
+    /// original source code - line numbers are kept for error messages
+    #[clap(long)]
+    pub strip_source: bool,
+
+    /// Link the requires of all bundled modules when building, so that the
+    /// binary looks them up instead of resolving their paths when it runs,
+    /// which speeds up starting binaries that require many modules
+    #[clap(long)]
+    pub link_requires: bool,
+
+    /// Opt in to reporting, allowing any error and usage reporters
+    /// registered with the runtime to be called - users of the binary
+    /// may still opt out using `DO_NOT_TRACK` or `LUNE_NO_REPORTING`
+    #[clap(long)]
+    pub reporting_consent: bool,
+
+    /// Encrypt the source code and bundled files in the binary using the
+    /// given key - the binary then needs the same key in the
+    /// `LUNE_STANDALONE_KEY` environment variable to run, unless
+    /// it was built with `--embed-key`
+    #[clap(long, value_name = "KEY")]
+    pub encrypt_key: Option<String>,
+
+    /// Embed an obfuscated copy of the key given to `--encrypt-key` in
+    /// the binary, so that it runs without any key being given - this
+    /// only offers basic tamper resistance, and is not fully secure
+    #[clap(long, requires = "encrypt_key")]
+    pub embed_key: bool,
+
+    /// Compress the base executable using UPX to produce a smaller
+    /// binary - UPX must be installed, either in `PATH` or at the
+    /// path given by the `LUNE_UPX_PATH` environment variable
+    #[clap(long)]
+    pub pack: bool,
+
+    /// Store the metadata of the binary in a dedicated section of the
+    /// executable, instead of appending it to the end, which keeps code
+    /// signatures valid, and keeps the metadata when using tools such as
+    /// `strip` - WASI modules always store it in a data segment
+    #[clap(long, conflicts_with = "pack")]
+    pub section: bool,
+
+    /// Do not switch Windows consoles to UTF-8 and enable ANSI escape
+    /// sequences when the binary starts, leaving the console as-is
+    #[clap(long)]
+    pub no_console_setup: bool,
+
+    /// Show a breakdown of every bundled module, its size, and which
+    /// modules required it, along with the total payload size - if a
+    /// path is given, the breakdown is written to it as JSON instead
+    #[clap(long, value_name = "PATH", num_args = 0..=1)]
+    pub report: Option<Option<PathBuf>>,
+
+    /// Package the binary into an OCI container image with the given name
+    /// and tag, such as `tool:1.0`, written next to the binary as a tar
+    /// archive - this does not need Docker, and is only supported for
+    /// Linux targets, preferably `linux-*-musl` since the image has no libc
+    #[clap(long, value_name = "IMAGE:TAG")]
+    pub oci: Option<ImageReference>,
+
+    /// Do not use the on-disk caches of bundled files and compiled modules,
+    /// and parse and compile every file again, even if it has not changed
+    #[clap(long)]
+    pub no_cache: bool,
+
+    /// Maximum number of threads used to validate and compile
+    /// bundled files, defaults to the number of CPUs
+    #[clap(long, short = 'j', value_name = "N")]
+    pub jobs: Option<NonZeroUsize>,
+
+    /// Create a packaging manifest next to the binary, with its version,
+    /// checksum and install path - one of `formula` for Homebrew, `scoop`
+    /// for Scoop, or `deb` for Debian packages, using the metadata from the
+    /// `[package]` table in `lune.toml` - may be given multiple times
+    #[clap(long = "package", value_name = "FORMAT")]
+    pub packages: Vec<PackageFormat>,
+
+    /// Build a shared library instead of an executable, for embedding the
+    /// project into other applications - the library exports a C function
+    /// `int lune_run(int argc, const char *const *argv)` that runs the
+    /// bundled project and returns its exit code, and the output path
+    /// defaults to a library name such as `libmain.so` or `main.dll`
+    #[clap(long)]
+    pub lib: bool,
+
+    /// Build on a base executable that only contains the given standard
+    /// libraries, separated by commas, such as `net,fs,task` - smaller
+    /// pre-built base executables are downloaded when they exist, and
+    /// are otherwise built locally, which requires a Rust toolchain
+    #[clap(long, value_name = "FEATURES")]
+    pub base_features: Option<BaseFeatures>,
+
+    /// Path to a JSON manifest of module namespaces provided by the
+    /// application that runs the bundle, as created by the application
+    /// using `Runtime::namespace_manifest` - requires of modules in
+    /// these namespaces are left to the application, and not bundled
+    #[clap(long, value_name = "MANIFEST")]
+    pub namespaces: Option<PathBuf>,
+
+    /// Do not show any progress messages or warnings -
+    /// errors are still written to stderr
+    #[clap(short, long)]
+    pub quiet: bool,
+
+    /// The format to write build results to stdout in - `text`, or
+    /// `json` for a single machine-readable summary of the build, with
+    /// its output path, size, bundled file count, and any warnings -
+    /// progress messages are always written to stderr
+    #[clap(long, value_name = "FORMAT", default_value = "text")]
+    pub output_format: OutputFormat,
 }
 
 impl BuildCommand {
     pub async fn run(self) -> Result<ExitCode> {
+        set_quiet(self.quiet);
+        if self.extract {
+            return self.run_extract().await;
+        }
+
         // Derive target spec to use, or default to the current host system
-        let target = self.target.unwrap_or_else(BuildTarget::current_system);
+        let target = self
+            .target
+            .clone()
+            .unwrap_or_else(BuildTarget::current_system);
+
+        // Gather any Windows resources to embed, before doing any real work
+        let resources = self.windows_resources().await?;
+        if !resources.is_empty() && target.os != target::BuildTargetOS::Windows {
+            bail!(
+                "--icon, --file-version and --version-string are only supported for Windows targets"
+            );
+        }
+        if self.lib {
+            self.check_lib_supported(&target, &resources)?;
+        }
+        if self.pack {
+            pack::check_target_supported(&target)?;
+        }
+        if self.oci.is_some() {
+            oci::check_target_supported(&target)?;
+        }
+        for format in &self.packages {
+            format.check_target_supported(&target)?;
+        }
+        let signing = SigningMode::from_options(
+            self.codesign_identity.clone(),
+            self.entitlements.clone(),
+            self.no_codesign,
+        )?;
+        if signing != SigningMode::AdHoc && target.os != target::BuildTargetOS::MacOS {
+            bail!(
+                "--codesign-identity, --entitlements and --no-codesign are only supported for macOS targets"
+            );
+        }
+
+        // Scripts from stdin or --eval have no file of their own,
+        // and are bundled as if they were in the current directory
+        let inline_source = self.read_inline_source().await?;
+        let input = match (&inline_source, &self.input) {
+            (Some(inline), _) => env::current_dir()
+                .context("failed to get current directory")?
+                .join(inline.file_name),
+            (None, Some(input)) => input.clone(),
+            (None, None) => unreachable!("clap requires an input when not using --eval"),
+        };
 
         // Resolve the entry file (handles directories with init.luau)
-        let entry_file = resolve_entry_file(&self.input);
-        let is_directory_module = entry_file != self.input;
+        let entry_file = resolve_entry_file(&input);
+        let is_directory_module = entry_file != input;
 
         // Verify the entry file exists
-        if !entry_file.is_file() {
-            if self.input.is_dir() {
+        if inline_source.is_none() && !entry_file.is_file() {
+            if input.is_dir() {
                 bail!(
                     "directory {} does not contain an init.luau or init.lua file",
-                    self.input.display()
+                    input.display()
                 );
             }
-            bail!("input file {} does not exist", self.input.display());
+            bail!("input file {} does not exist", input.display());
         }
 
         // Derive paths to use, and make sure the output path is
@@ -94,16 +466,26 @@ impl BuildCommand {
             if is_directory_module {
                 // For directory modules, use the directory name in the current directory
                 // This avoids conflicts where output would equal the input directory
-                self.input
+                input
                     .file_name()
                     .map(PathBuf::from)
-                    .unwrap_or_else(|| self.input.clone())
+                    .unwrap_or_else(|| input.clone())
             } else {
-                remove_source_file_ext(&self.input)
+                remove_source_file_ext(&input)
             }
         });
-        let output_path = output_path.with_extension(target.exe_extension());
-        if output_path == self.input || output_path == entry_file {
+        let output_path = match (self.lib, &self.output) {
+            (false, _) => output_path.with_extension(target.exe_extension()),
+            (true, Some(_)) => output_path,
+            (true, None) => {
+                let name = output_path.file_name().map_or_else(
+                    || "lune".to_string(),
+                    |name| name.to_string_lossy().to_string(),
+                );
+                output_path.with_file_name(target.lib_file_name(&name))
+            }
+        };
+        if output_path == input || output_path == entry_file {
             if self.output.is_some() {
                 bail!("output path cannot be the same as input path");
             }
@@ -113,71 +495,592 @@ impl BuildCommand {
         }
 
         // Try to read the given input file and strip shebang
-        let source_code = strip_shebang(
-            fs::read(&entry_file)
+        let source_code = strip_shebang(match &inline_source {
+            Some(inline) => inline.source.clone(),
+            None => fs::read(&entry_file)
                 .await
                 .context("failed to read input file")?,
-        );
+        });
+
+        // Resolve the named entry files, which are bundled along with the input file
+        let mut entry_files = Vec::with_capacity(self.entries.len());
+        for (name, path) in &self.entries {
+            if name.contains(['/', '\\']) {
+                bail!("entry name '{name}' can not contain path separators");
+            }
+            let file = resolve_entry_file(Path::new(path));
+            if !file.is_file() {
+                bail!("entry file {path} for entry '{name}' does not exist");
+            }
+            entry_files.push(file);
+        }
+
+        // Read the project config closest to the entry file, to embed into the binary
+        let config_dir = entry_file.parent().unwrap_or(Path::new("."));
+        let config = ProjectConfig::discover(config_dir).await?;
 
         // Bundle all dependencies
-        let display_path = if is_directory_module {
-            format!("{} (init.luau)", self.input.display())
+        let display_path = if let Some(inline) = &inline_source {
+            inline.description.to_string()
+        } else if is_directory_module {
+            format!("{} (init.luau)", input.display())
         } else {
-            self.input.display().to_string()
+            input.display().to_string()
         };
-        println!("Bundling dependencies for {}", style(&display_path).green());
-        let mut bundler = Bundler::new(&entry_file).context("failed to initialize bundler")?;
-        let bundle_result = bundler
+        progress(format_args!(
+            "Bundling dependencies for {}",
+            style(&display_path).green()
+        ));
+        let mut bundler = Bundler::new(&entry_file)
+            .context("failed to initialize bundler")?
+            .with_platform(target.platform())
+            .with_extra_entries(entry_files);
+        if !self.no_cache {
+            bundler = bundler.with_cache(BUNDLE_CACHE_DIR.as_path());
+        }
+        if inline_source.is_some() {
+            bundler = bundler.with_entry_source(source_code.clone());
+        }
+        if let Some(path) = &self.namespaces {
+            bundler = bundler.with_namespaces(read_namespace_manifest(path).await?);
+        }
+        // Remote modules are vendored from those recorded when running the script
+        let remote = remote_requires()?;
+        let lockfile = Lockfile::read(&remote.lockfile)
+            .with_context(|| format!("failed to read {}", remote.lockfile.display()))?;
+        bundler = bundler.with_remote_modules(lockfile, RemoteCache::new(remote.cache_dir));
+        let mut bundle_result = bundler
             .bundle(&entry_file)
             .context("failed to bundle dependencies")?;
-        println!(
-            "Bundled {} files, {} aliases",
+        progress(format_args!(
+            "Bundled {} files ({} unchanged), {} aliases",
             style(bundle_result.files.len()).cyan(),
+            style(bundler.cache_hits()).cyan(),
             style(bundle_result.aliases.len()).cyan()
-        );
+        ));
 
-        // Derive the base executable path based on the arguments provided
-        let base_exe_path = get_or_download_base_executable(target.clone()).await?;
+        let mut warnings = Vec::new();
+        if !bundle_result.namespace_requires.is_empty() {
+            warnings.push(format!(
+                "left {} requires to namespaces of the application, which must provide them: {}",
+                bundle_result.namespace_requires.len(),
+                bundle_result
+                    .namespace_requires
+                    .iter()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        for message in &warnings {
+            warning(message);
+        }
+        let bundled_files = bundle_result.files.len();
+        let aliases = bundle_result.aliases.len();
+
+        // Compile every bundled file, so that syntax errors in any of them,
+        // including transitive dependencies, fail the build instead of the binary
+        let root = env::current_dir().context("failed to get current directory")?;
+        let workers = Workers::new(self.jobs);
+        let validated =
+            validate_bundled_files(&bundle_result.files, &bundle_result.sources, &root, workers)?;
+        progress(format_args!("Validated {}", style(validated).cyan()));
 
         // Read the contents of the lune interpreter as our starting point
-        println!(
+        progress(format_args!(
             "Compiling standalone binary from {}",
             style(&display_path).green()
-        );
-        // Use relative path from project root for portability
-        let canonical_entry = entry_file
-            .canonicalize()
-            .unwrap_or_else(|_| entry_file.clone());
-        let entry_path = normalize_bundle_path(&canonical_entry, bundler.base_dir());
-        let patched_bin = Metadata::create_env_patched_bin(
-            base_exe_path,
-            source_code,
-            entry_path,
-            bundle_result.files,
-            bundle_result.aliases,
+        ));
+        // Use relative path from project root for portability, keeping
+        // any symlinks as-is, the same as for all other bundled files
+        let entry_path = bundle_result.entry.clone();
+
+        // Compile all sources to bytecode if requested, which is safe to do
+        // after bundling since requires have already been found and resolved
+        let source_code = if self.strip_source {
+            // Bytecode for identical sources is shared across all projects
+            let module_cache =
+                (!self.no_cache).then(|| ModuleCache::new(MODULE_CACHE_DIR.as_path()));
+            let compiled =
+                strip_bundled_files(&mut bundle_result.files, module_cache.as_ref(), workers)?;
+            progress(format_args!(
+                "Compiled {} to bytecode ({} cached)",
+                style(compiled).cyan(),
+                style(module_cache.as_ref().map_or(0, ModuleCache::hits)).cyan()
+            ));
+            progress(format_args!(
+                "Processed {} in total",
+                style(validated.merge(compiled)).cyan()
+            ));
+            compile_bytecode_cached(module_cache.as_ref(), &entry_path, source_code)?
+        } else {
+            source_code
+        };
+
+        // Measure modules as they are stored, after any compilation
+        let report = self.report.is_some().then(|| {
+            BundleReport::new(
+                &entry_path,
+                &source_code,
+                &bundle_result.files,
+                &bundle_result.required_by,
+            )
+        });
+
+        let source_map = if self.source_map {
+            create_source_map(&bundle_result.sources, &root)
+        } else {
+            HashMap::new()
+        };
+
+        let project_dir = entry_file.parent().unwrap_or(&root);
+        let build_info = collect_build_info(
+            &target,
+            project_dir,
+            self.build_info.iter().cloned().collect(),
         )
-        .await
-        .context("failed to create patched binary")?;
-
-        // Ad-hoc sign macOS binaries to prevent SIGKILL on Apple Silicon.
-        // Appending metadata to the base executable invalidates its original
-        // code signature, so we re-sign with our built-in cross-platform
-        // signer. This works on any host OS (Linux, Windows, macOS).
-        let mut patched_bin = patched_bin;
-        if target.os == target::BuildTargetOS::MacOS {
-            let bin_name = output_path
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("lune");
-            codesign::sign_macho(&mut patched_bin, bin_name);
+        .await;
+
+        let require_graph = self.link_requires.then(|| bundle_result.require_graph());
+        if let Some(graph) = &require_graph {
+            progress(format_args!(
+                "Linked {} requires between {} modules",
+                style(graph.requires.iter().map(BTreeMap::len).sum::<usize>()).cyan(),
+                style(graph.modules.len()).cyan()
+            ));
         }
 
+        let mut meta = Metadata {
+            files: bundle_result.files,
+            aliases: bundle_result.aliases,
+            links: bundle_result.links,
+            crash_report_dir: self.crash_report_dir.clone(),
+            reporting_consent: self.reporting_consent,
+            source_map,
+            source_stripped: self.strip_source,
+            defines: self.defines.iter().cloned().collect(),
+            env: self.read_default_env().await?,
+            args: self.default_args.clone(),
+            exit_codes: config.exit_codes,
+            no_console_setup: self.no_console_setup,
+            build: Some(build_info),
+            entries: self
+                .entries
+                .iter()
+                .map(|(name, _)| name.clone())
+                .zip(bundle_result.extra_entries)
+                .collect(),
+            require_graph,
+            ..Metadata::new(source_code, entry_path)
+        };
+        if let Some(key) = &self.encrypt_key {
+            meta.encrypt(key, self.embed_key)
+                .context("failed to encrypt bundled files")?;
+        }
+        let signing_key = self.read_signing_key().await?;
+        let (base_size, patched_bin) = self
+            .create_patched_bin(
+                &meta,
+                signing_key.as_ref(),
+                &target,
+                &resources,
+                &signing,
+                &output_path,
+            )
+            .await?;
+
+        // Reports without a path are part of the results, and are
+        // included in the summary instead when writing it as JSON
+        let mut artifacts = Vec::new();
+        let report = report.map(|report| report.with_sizes(base_size, patched_bin.len()));
+        let summary_report = match (report, self.report.as_ref().and_then(Option::as_deref)) {
+            (Some(report), Some(path)) => {
+                report.write_to(path).await?;
+                artifacts.push(path.to_path_buf());
+                None
+            }
+            (Some(report), None) if self.output_format == OutputFormat::Text => {
+                report.print();
+                None
+            }
+            (report, _) => report,
+        };
+
+        self.sign_and_write(&output_path, patched_bin, &target, &signing)
+            .await?;
+
+        if let Some(reference) = &self.oci {
+            artifacts.push(write_oci_image(&output_path, &target, reference).await?);
+        }
+        if !self.packages.is_empty() {
+            artifacts.extend(
+                self.write_packages(&output_path, &target, &config.package)
+                    .await?,
+            );
+        }
+
+        if self.output_format == OutputFormat::Json {
+            let size = fs::metadata(&output_path)
+                .await
+                .context("failed to read standalone binary")?
+                .len();
+            print_json(&BuildSummary {
+                output: output_path,
+                target: target.to_string(),
+                size,
+                bundled_files,
+                unchanged_files: bundler.cache_hits(),
+                aliases,
+                artifacts,
+                warnings,
+                report: summary_report,
+            })?;
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+
+    async fn create_patched_bin(
+        &self,
+        meta: &Metadata,
+        signing_key: Option<&MetadataSigningKey>,
+        target: &BuildTarget,
+        resources: &WindowsResources,
+        signing: &SigningMode,
+        output_path: &Path,
+    ) -> Result<(usize, Vec<u8>)> {
+        let mut base_size = 0;
+        let mut slices = Vec::new();
+        for slice_target in target.slices() {
+            let base_exe = self.prepare_base_exe(&slice_target, resources).await?;
+            base_size += base_exe.len();
+            // WASI modules can not read their own file, so the metadata is
+            // embedded into the module itself, instead of being appended to it
+            let mut patched_bin = if slice_target.is_wasi() {
+                embed_payload(&base_exe, &meta.to_bytes(signing_key)?)
+                    .context("failed to embed metadata into WASI module")?
+            } else if self.section {
+                let mut patched_bin = base_exe;
+                embed_metadata_section(&mut patched_bin, &meta.to_bytes(signing_key)?)
+                    .context("failed to store metadata in a section")?;
+                patched_bin
+            } else {
+                meta.create_env_patched_bin(base_exe, signing_key)
+                    .context("failed to create patched binary")?
+            };
+
+            // Ad-hoc sign macOS binaries to prevent SIGKILL on Apple Silicon.
+            // Appending metadata to the base executable invalidates its original
+            // code signature, so we re-sign with our built-in cross-platform
+            // signer. This works on any host OS (Linux, Windows, macOS).
+            // Signing with an identity happens after writing the output file,
+            // since Apple's codesign tool only operates on files
+            if slice_target.os == target::BuildTargetOS::MacOS && *signing == SigningMode::AdHoc {
+                let bin_name = output_path
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or("lune");
+                codesign::sign_macho(&mut patched_bin, bin_name);
+            }
+
+            slices.push(patched_bin);
+        }
+
+        // Universal binaries are merged only after signing, since
+        // each architecture needs its own separate code signature
+        let patched_bin = if target.is_universal() {
+            progress(format_args!(
+                "Merging {} architectures into a universal binary",
+                style(slices.len()).cyan()
+            ));
+            create_universal_binary(&slices).context("failed to create universal binary")?
+        } else {
+            slices
+                .pop()
+                .expect("non-universal targets have exactly one slice")
+        };
+
+        Ok((base_size, patched_bin))
+    }
+
+    async fn prepare_base_exe(
+        &self,
+        target: &BuildTarget,
+        resources: &WindowsResources,
+    ) -> Result<Vec<u8>> {
+        // Derive the base executable path based on the arguments provided
+        let base_exe_path = if self.lib {
+            get_or_download_base_library(target.clone()).await?
+        } else if let Some(features) = &self.base_features {
+            get_or_build_trimmed_base_executable(target.clone(), features).await?
+        } else {
+            get_or_download_base_executable(target.clone()).await?
+        };
+        let mut base_exe = fs::read(&base_exe_path)
+            .await
+            .context("failed to read base executable")?;
+
+        // Binaries for musl targets are meant to run on any Linux system, which
+        // they can only do if they do not need a dynamic loader, such as ld-musl
+        if target.libc == target::BuildTargetLibc::Musl
+            && is_statically_linked(&base_exe) == Some(false)
+        {
+            bail!(
+                "base executable {} for target '{target}' is dynamically linked, \
+                and would not run on systems without the musl dynamic loader",
+                base_exe_path.display()
+            );
+        }
+
+        // Embed Windows resources before appending metadata, since
+        // the metadata must stay at the very end of the executable
+        if !resources.is_empty() {
+            embed_resources(&mut base_exe, resources)
+                .context("failed to embed Windows resources")?;
+        }
+
+        // Pack before appending metadata too, so that the trailer stays intact
+        if self.pack {
+            let unpacked_size = base_exe.len();
+            base_exe = pack_executable(base_exe, target).await?;
+            progress(format_args!(
+                "Packed base executable from {} to {} bytes",
+                style(unpacked_size).cyan(),
+                style(base_exe.len()).cyan()
+            ));
+        }
+
+        Ok(base_exe)
+    }
+
+    async fn read_default_env(&self) -> Result<BTreeMap<String, String>> {
+        let mut env = BTreeMap::new();
+        for path in &self.env_files {
+            env.extend(read_env_file(path).await?);
+        }
+        env.extend(self.env_vars.iter().cloned());
+        if !env.is_empty() {
+            progress(format_args!(
+                "Embedding {} default environment variables",
+                style(env.len()).cyan()
+            ));
+        }
+        Ok(env)
+    }
+
+    async fn read_signing_key(&self) -> Result<Option<MetadataSigningKey>> {
+        let Some(path) = &self.sign_metadata else {
+            return Ok(None);
+        };
+        let contents = fs::read(path)
+            .await
+            .with_context(|| format!("failed to read signing key {}", path.display()))?;
+        let key = MetadataSigningKey::from_pkcs8(&contents)
+            .with_context(|| format!("invalid signing key {}", path.display()))?;
+        progress(format_args!(
+            "Signing metadata with public key {}",
+            style(encode_hex(&key.public_key())).cyan()
+        ));
+        Ok(Some(key))
+    }
+
+    fn check_lib_supported(
+        &self,
+        target: &BuildTarget,
+        resources: &WindowsResources,
+    ) -> Result<()> {
+        // NOTE: Rust can not build shared libraries that link statically against musl
+        if target.libc == target::BuildTargetLibc::Musl {
+            bail!("--lib is not supported for musl targets");
+        }
+        if target.is_wasi() {
+            bail!("--lib is not supported for WASI targets");
+        }
+        if !resources.is_empty() || self.pack || self.oci.is_some() || !self.packages.is_empty() {
+            bail!(
+                "--icon, --file-version, --version-string, --pack, --oci and --package are not supported with --lib"
+            );
+        }
+        if self.base_features.is_some() {
+            bail!("--base-features is not supported with --lib");
+        }
+        Ok(())
+    }
+
+    async fn sign_and_write(
+        &self,
+        output_path: &Path,
+        patched_bin: Vec<u8>,
+        target: &BuildTarget,
+        signing: &SigningMode,
+    ) -> Result<()> {
         // And finally write the patched binary to the output file
-        println!(
+        progress(format_args!(
             "Writing standalone binary to {}",
             style(output_path.display()).blue()
-        );
-        write_executable_file_to(&output_path, patched_bin).await?;
+        ));
+        write_executable_file_to(output_path, patched_bin).await?;
+
+        if let SigningMode::Identity {
+            identity,
+            entitlements,
+        } = signing
+        {
+            progress(format_args!(
+                "Signing standalone binary as {}",
+                style(identity).green()
+            ));
+            sign_with_identity(output_path, identity, entitlements.as_deref()).await?;
+        }
+
+        if let Some(command) = &self.notarize_hook {
+            progress(format_args!(
+                "Running notarization hook {}",
+                style(command).green()
+            ));
+            run_notarize_hook(command, output_path, target).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn write_packages(
+        &self,
+        binary_path: &Path,
+        target: &BuildTarget,
+        config: &PackageConfig,
+    ) -> Result<Vec<PathBuf>> {
+        let binary = fs::read(binary_path)
+            .await
+            .context("failed to read standalone binary")?;
+        let file_name = binary_path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("lune");
+        let binary_name = binary_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("lune");
+
+        let version = self.file_version.as_deref().or_else(|| {
+            self.defines
+                .iter()
+                .rev()
+                .find(|(key, _)| key == "VERSION")
+                .map(|(_, value)| value.as_str())
+        });
+        let info = PackageInfo::resolve(config, binary_name, version)?;
+
+        let mut package_paths = Vec::new();
+        for format in &self.packages {
+            let (package_name, contents) =
+                create_package(*format, &info, &binary, file_name, target)?;
+            let package_path = binary_path.with_file_name(package_name);
+            fs::write(&package_path, contents)
+                .await
+                .with_context(|| format!("failed to write {}", package_path.display()))?;
+            progress(format_args!(
+                "Wrote {format} package to {}",
+                style(package_path.display()).blue()
+            ));
+            package_paths.push(package_path);
+        }
+
+        Ok(package_paths)
+    }
+
+    async fn windows_resources(&self) -> Result<WindowsResources> {
+        let icon = match &self.icon {
+            Some(path) => Some(
+                fs::read(path)
+                    .await
+                    .with_context(|| format!("failed to read icon file {}", path.display()))?,
+            ),
+            None => None,
+        };
+        let version = self
+            .file_version
+            .as_deref()
+            .map(parse_version)
+            .transpose()
+            .context("invalid file version")?;
+        let strings = self
+            .version_strings
+            .iter()
+            .cloned()
+            .collect::<BTreeMap<_, _>>();
+        Ok(WindowsResources {
+            icon,
+            version,
+            strings,
+        })
+    }
+
+    async fn read_inline_source(&self) -> Result<Option<InlineSource>> {
+        let is_stdin = self.input.as_deref() == Some(Path::new("-"));
+        if (is_stdin || self.eval.is_some()) && self.output.is_none() {
+            bail!("--output is required when building from stdin or --eval");
+        }
+        if let Some(code) = &self.eval {
+            return Ok(Some(InlineSource {
+                file_name: "eval.luau",
+                description: "--eval script",
+                source: code.clone().into_bytes(),
+            }));
+        }
+        if !is_stdin {
+            return Ok(None);
+        }
+        let mut source = Vec::new();
+        Unblock::new(stdin())
+            .read_to_end(&mut source)
+            .await
+            .context("failed to read script contents from stdin")?;
+        Ok(Some(InlineSource {
+            file_name: "stdin.luau",
+            description: "stdin",
+            source,
+        }))
+    }
+
+    async fn run_extract(self) -> Result<ExitCode> {
+        let Some(input) = &self.input else {
+            bail!("--extract requires the path to a standalone binary");
+        };
+        if !input.is_file() {
+            bail!("input binary {} does not exist", input.display());
+        }
+
+        let output_dir = self.output.clone().unwrap_or_else(|| {
+            let stem = input
+                .file_stem()
+                .map_or_else(|| "lune".to_string(), |s| s.to_string_lossy().to_string());
+            input.with_file_name(format!("{stem}-extracted"))
+        });
+        if output_dir.is_file() {
+            bail!(
+                "output path {} is a file, please specify a directory",
+                output_dir.display()
+            );
+        }
+
+        progress(format_args!(
+            "Extracting standalone binary {}",
+            style(input.display()).green()
+        ));
+        let count = extract_standalone_binary(input, &output_dir).await?;
+        progress(format_args!(
+            "Extracted {} files to {}",
+            style(count).cyan(),
+            style(output_dir.display()).blue()
+        ));
+
+        if self.output_format == OutputFormat::Json {
+            print_json(&ExtractSummary {
+                output: output_dir,
+                files: count,
+            })?;
+        }
 
         Ok(ExitCode::SUCCESS)
     }
@@ -0,0 +1,230 @@
+/*!
+    Packaging of standalone binaries into OCI container images.
+
+    Images are written as an OCI image layout in a single tar archive, without
+    needing Docker or any other container tooling installed. The image is based
+    on `scratch`, and contains nothing but the standalone binary, which is also
+    the entry point of the image. The archive may then be loaded or pushed using
+    `docker load`, `podman load`, or `skopeo copy oci-archive:<path> <dest>`.
+
+    Since a `scratch` image has no C library, binaries should be built for a
+    `linux-*-musl` target, which links statically against musl.
+*/
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{Result, bail};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+
+use super::archive::{append_tar_file, finish_tar, gzip};
+use super::target::{BuildTarget, BuildTargetArch, BuildTargetOS};
+
+const MEDIA_TYPE_INDEX: &str = "application/vnd.oci.image.index.v1+json";
+const MEDIA_TYPE_MANIFEST: &str = "application/vnd.oci.image.manifest.v1+json";
+const MEDIA_TYPE_CONFIG: &str = "application/vnd.oci.image.config.v1+json";
+const MEDIA_TYPE_LAYER: &str = "application/vnd.oci.image.layer.v1.tar+gzip";
+
+/**
+    A reference to an image, in the form `name` or `name:tag`.
+
+    The name may include a registry, such as `ghcr.io/user/tool`,
+    and the tag defaults to `latest` if not given.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageReference {
+    pub name: String,
+    pub tag: String,
+}
+
+impl fmt::Display for ImageReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.name, self.tag)
+    }
+}
+
+impl FromStr for ImageReference {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // NOTE: A colon before the last slash is a registry port, not a tag
+        let name_start = s.rfind('/').map_or(0, |i| i + 1);
+        let (name, tag) = match s[name_start..].rfind(':') {
+            Some(i) => (&s[..name_start + i], &s[name_start + i + 1..]),
+            None => (s, "latest"),
+        };
+        if name.is_empty() || name.ends_with('/') {
+            return Err("image name must not be empty");
+        }
+        if tag.is_empty() {
+            return Err("image tag must not be empty");
+        }
+        Ok(Self {
+            name: name.to_string(),
+            tag: tag.to_string(),
+        })
+    }
+}
+
+/**
+    Checks that container images can be created for the given target.
+
+    # Errors
+
+    - If the target is not a Linux target
+*/
+pub fn check_target_supported(target: &BuildTarget) -> Result<()> {
+    if target.os != BuildTargetOS::Linux {
+        bail!("--oci is only supported for linux targets");
+    }
+    Ok(())
+}
+
+/**
+    Creates a tar archive containing an OCI image layout, with a
+    single image that runs the given binary as its entry point.
+
+    # Errors
+
+    - If the target is not supported, see [`check_target_supported`]
+    - If the image layer could not be compressed
+*/
+pub fn create_image_archive(
+    binary: &[u8],
+    binary_name: &str,
+    target: &BuildTarget,
+    reference: &ImageReference,
+) -> Result<Vec<u8>> {
+    check_target_supported(target)?;
+
+    let mut layer_tar = Vec::new();
+    append_tar_file(&mut layer_tar, binary_name, binary, 0o755);
+    finish_tar(&mut layer_tar);
+
+    let layer = gzip(&layer_tar)?;
+
+    let config = to_json(&json!({
+        "architecture": oci_arch(target.arch),
+        "os": "linux",
+        "config": {
+            "Entrypoint": [format!("/{binary_name}")],
+            "Env": ["PATH=/"],
+        },
+        "rootfs": {
+            "type": "layers",
+            "diff_ids": [digest(&layer_tar)],
+        },
+        "history": [{ "created_by": concat!("lune build ", env!("CARGO_PKG_VERSION")) }],
+    }));
+
+    let manifest = to_json(&json!({
+        "schemaVersion": 2,
+        "mediaType": MEDIA_TYPE_MANIFEST,
+        "config": descriptor(MEDIA_TYPE_CONFIG, &config),
+        "layers": [descriptor(MEDIA_TYPE_LAYER, &layer)],
+    }));
+
+    let mut manifest_descriptor = descriptor(MEDIA_TYPE_MANIFEST, &manifest);
+    manifest_descriptor["annotations"] = json!({
+        "org.opencontainers.image.ref.name": reference.tag,
+        "io.containerd.image.name": reference.to_string(),
+    });
+    let index = to_json(&json!({
+        "schemaVersion": 2,
+        "mediaType": MEDIA_TYPE_INDEX,
+        "manifests": [manifest_descriptor],
+    }));
+
+    let mut archive = Vec::new();
+    append_tar_file(
+        &mut archive,
+        "oci-layout",
+        br#"{"imageLayoutVersion":"1.0.0"}"#,
+        0o644,
+    );
+    append_tar_file(&mut archive, "index.json", &index, 0o644);
+    for blob in [&config, &layer, &manifest] {
+        let path = format!("blobs/{}", digest(blob).replace(':', "/"));
+        append_tar_file(&mut archive, &path, blob, 0o644);
+    }
+    finish_tar(&mut archive);
+
+    Ok(archive)
+}
+
+fn oci_arch(arch: BuildTargetArch) -> &'static str {
+    match arch {
+        BuildTargetArch::X86_64 => "amd64",
+        BuildTargetArch::Aarch64 => "arm64",
+        BuildTargetArch::Universal => unreachable!("universal targets are macos only"),
+        BuildTargetArch::Wasm32 => unreachable!("wasm32 targets are wasi only"),
+    }
+}
+
+fn to_json(value: &Value) -> Vec<u8> {
+    serde_json::to_vec(value).expect("json values always serialize")
+}
+
+fn digest(data: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(data))
+}
+
+fn descriptor(media_type: &str, data: &[u8]) -> Value {
+    json!({
+        "mediaType": media_type,
+        "digest": digest(data),
+        "size": data.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::build::archive::TAR_BLOCK_SIZE;
+
+    fn linux_target() -> BuildTarget {
+        "linux-x86_64-musl".parse().unwrap()
+    }
+
+    #[test]
+    fn parse_references() {
+        let parse = |s: &str| s.parse::<ImageReference>().map(|r| r.to_string());
+        assert_eq!(parse("tool"), Ok("tool:latest".to_string()));
+        assert_eq!(parse("tool:1.2.3"), Ok("tool:1.2.3".to_string()));
+        assert_eq!(
+            parse("localhost:5000/user/tool"),
+            Ok("localhost:5000/user/tool:latest".to_string())
+        );
+        assert_eq!(
+            parse("ghcr.io/user/tool:edge"),
+            Ok("ghcr.io/user/tool:edge".to_string())
+        );
+        assert!(parse(":tag").is_err());
+        assert!(parse("tool:").is_err());
+    }
+
+    #[test]
+    fn image_archive_contains_layout() {
+        let reference = "tool:1.0".parse().unwrap();
+        let archive = create_image_archive(b"binary", "tool", &linux_target(), &reference).unwrap();
+
+        let paths = archive
+            .chunks(TAR_BLOCK_SIZE)
+            .filter(|block| block[257..262] == *b"ustar")
+            .map(|block| {
+                let end = block.iter().position(|b| *b == 0).unwrap_or(100);
+                String::from_utf8(block[..end].to_vec()).unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(paths[0], "oci-layout");
+        assert_eq!(paths[1], "index.json");
+        assert_eq!(paths.len(), 5);
+        assert!(paths[2..].iter().all(|p| p.starts_with("blobs/sha256/")));
+    }
+
+    #[test]
+    fn image_archive_requires_linux() {
+        let reference = "tool".parse().unwrap();
+        let target = "macos-aarch64".parse().unwrap();
+        assert!(create_image_archive(b"binary", "tool", &target, &reference).is_err());
+    }
+}
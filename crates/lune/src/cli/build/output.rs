@@ -0,0 +1,161 @@
+/*!
+    Output of `lune build` - progress messages, and the summary of a build.
+
+    Progress messages are diagnostics, and are written to stderr, so that
+    stdout only ever contains results, such as the summary of a build
+    when using `--output-format json`, which tools can then parse.
+*/
+
+use std::{
+    fmt,
+    path::PathBuf,
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::Result;
+use console::style;
+use serde::Serialize;
+
+use super::report::BundleReport;
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/**
+    Sets whether progress messages and warnings are hidden, for `--quiet`.
+*/
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/**
+    Writes a progress message to stderr, unless `--quiet` was given.
+*/
+pub fn progress(message: impl fmt::Display) {
+    if !QUIET.load(Ordering::Relaxed) {
+        eprintln!("{message}");
+    }
+}
+
+/**
+    Writes a warning to stderr, unless `--quiet` was given.
+*/
+pub fn warning(message: impl fmt::Display) {
+    progress(format_args!(
+        "{} {message}",
+        style("warning:").yellow().bold()
+    ));
+}
+
+/**
+    The format that the results of `lune build` are written to stdout in.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err("invalid output format, expected one of text, json"),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text => write!(f, "text"),
+            Self::Json => write!(f, "json"),
+        }
+    }
+}
+
+/**
+    A summary of a finished build, written to stdout as JSON
+    when using `--output-format json`.
+*/
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildSummary {
+    /// Path of the written binary or library
+    pub output: PathBuf,
+    /// The target that was built for, such as `linux-x86_64`
+    pub target: String,
+    /// Size of the written binary or library, in bytes
+    pub size: u64,
+    /// Number of files bundled into the binary, excluding the entry point
+    pub bundled_files: usize,
+    /// Number of bundled files that were unchanged since the previous build
+    pub unchanged_files: usize,
+    /// Number of bundled aliases
+    pub aliases: usize,
+    /// Paths of any other files that were written, such as packaging manifests
+    pub artifacts: Vec<PathBuf>,
+    /// Warnings that were emitted during the build
+    pub warnings: Vec<String>,
+    /// The bundle report, if `--report` was given without a path
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report: Option<BundleReport>,
+}
+
+/**
+    A summary of an extracted binary, written to stdout as JSON
+    when using `--extract` together with `--output-format json`.
+*/
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtractSummary {
+    /// Path of the directory that files were extracted to
+    pub output: PathBuf,
+    /// Number of extracted files, including the entry point
+    pub files: usize,
+}
+
+/**
+    Writes the given summary to stdout as JSON.
+
+    # Errors
+
+    - If the summary could not be serialized
+*/
+pub fn print_json(summary: &impl Serialize) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(summary)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_output_formats() {
+        assert_eq!("text".parse(), Ok(OutputFormat::Text));
+        assert_eq!(" JSON ".parse(), Ok(OutputFormat::Json));
+        assert!("yaml".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn summary_omits_missing_report() {
+        let summary = BuildSummary {
+            output: PathBuf::from("main"),
+            target: "linux-x86_64".to_string(),
+            size: 1024,
+            bundled_files: 2,
+            unchanged_files: 1,
+            aliases: 0,
+            artifacts: Vec::new(),
+            warnings: vec!["something".to_string()],
+            report: None,
+        };
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["output"], "main");
+        assert_eq!(json["size"], 1024);
+        assert_eq!(json["warnings"][0], "something");
+        assert!(json.get("report").is_none());
+    }
+}
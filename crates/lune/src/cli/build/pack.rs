@@ -0,0 +1,148 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::{self, Command},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, bail};
+use async_fs as fs;
+use blocking::unblock;
+
+use super::target::{BuildTarget, BuildTargetOS};
+
+/// Environment variable that may be set to the path of a specific UPX executable
+const UPX_PATH_ENV_VAR: &str = "LUNE_UPX_PATH";
+
+fn upx_program() -> PathBuf {
+    env::var_os(UPX_PATH_ENV_VAR).map_or_else(|| PathBuf::from("upx"), PathBuf::from)
+}
+
+fn upx_args(input: &Path, output: &Path) -> Vec<&std::ffi::OsStr> {
+    vec![
+        "--best".as_ref(),
+        "--no-progress".as_ref(),
+        "-q".as_ref(),
+        "-o".as_ref(),
+        output.as_os_str(),
+        input.as_os_str(),
+    ]
+}
+
+/**
+    Returns an error if executables for the given target can not be packed.
+
+    Packed macOS executables are not supported by UPX on recent versions
+    of macOS, and would also have their code signature invalidated, and
+    UPX does not support WASI modules at all.
+*/
+pub fn check_target_supported(target: &BuildTarget) -> Result<()> {
+    if target.os == BuildTargetOS::MacOS {
+        bail!("--pack is not supported for macOS targets");
+    }
+    if target.os == BuildTargetOS::Wasi {
+        bail!("--pack is not supported for WASI targets");
+    }
+    Ok(())
+}
+
+/**
+    Compresses the given base executable using UPX, which must be installed.
+
+    This must be done *before* appending the standalone metadata trailer,
+    since UPX refuses to pack executables with extra data at the end, and
+    would otherwise compress the trailer so that it can no longer be found.
+
+    # Errors
+
+    - If UPX could not be found, or fails to pack the executable
+    - If the temporary files used for packing could not be written or read
+*/
+pub async fn pack_executable(base_exe: Vec<u8>, target: &BuildTarget) -> Result<Vec<u8>> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let temp_dir = env::temp_dir();
+    let input = temp_dir.join(format!(
+        "lune-pack-{}-{nanos}-in{}",
+        process::id(),
+        target.exe_suffix()
+    ));
+    let output = temp_dir.join(format!(
+        "lune-pack-{}-{nanos}-out{}",
+        process::id(),
+        target.exe_suffix()
+    ));
+
+    fs::write(&input, &base_exe)
+        .await
+        .context("failed to write temporary file for packing")?;
+
+    let result = run_upx(&input, &output).await;
+    let packed = match result {
+        Ok(()) => fs::read(&output)
+            .await
+            .context("failed to read packed executable"),
+        Err(e) => Err(e),
+    };
+
+    // Always clean up, even if packing failed
+    let _ = fs::remove_file(&input).await;
+    let _ = fs::remove_file(&output).await;
+
+    packed
+}
+
+async fn run_upx(input: &Path, output: &Path) -> Result<()> {
+    let program = upx_program();
+    let input = input.to_path_buf();
+    let output = output.to_path_buf();
+    let display = program.display().to_string();
+
+    let result = unblock(move || {
+        Command::new(&program)
+            .args(upx_args(&input, &output))
+            .output()
+    })
+    .await;
+
+    let output = result.with_context(|| {
+        format!(
+            "failed to run '{display}' - is UPX installed? \
+            The path to UPX may also be set using {UPX_PATH_ENV_VAR}"
+        )
+    })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(
+            "UPX failed to pack the executable ({}): {}",
+            output.status,
+            stderr.trim()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn macos_is_not_supported() {
+        let macos = "macos-aarch64".parse::<BuildTarget>().unwrap();
+        let linux = "linux-x86_64".parse::<BuildTarget>().unwrap();
+        let windows = "windows-x86_64".parse::<BuildTarget>().unwrap();
+        assert!(check_target_supported(&macos).is_err());
+        assert!(check_target_supported(&linux).is_ok());
+        assert!(check_target_supported(&windows).is_ok());
+    }
+
+    #[test]
+    fn args_end_with_input() {
+        let args = upx_args(Path::new("in.exe"), Path::new("out.exe"));
+        assert_eq!(args.last(), Some(&Path::new("in.exe").as_os_str()));
+        assert!(args.contains(&Path::new("out.exe").as_os_str()));
+    }
+}
@@ -0,0 +1,422 @@
+/*!
+    Packaging manifests for standalone binaries.
+
+    Manifests are created next to the binary, using metadata from the
+    `[package]` table of the closest `lune.toml` file, and checksums of
+    the final binary, so that they can be published as-is:
+
+    - `formula` - a Homebrew formula, for macOS and Linux targets
+    - `scoop` - a Scoop manifest, for Windows targets
+    - `deb` - a Debian package containing the binary, for Linux targets
+
+    Homebrew formulae and Scoop manifests download the binary, and need
+    the `url` that it will be published at to be set in the `[package]` table.
+*/
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{Result, bail};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::cli::utils::config::PackageConfig;
+
+use super::archive::{append_tar_dir, append_tar_file, create_ar, finish_tar, gzip};
+use super::target::{BuildTarget, BuildTargetArch, BuildTargetOS};
+
+/// Default description for packages that do not have one
+const DEFAULT_DESCRIPTION: &str = "A standalone binary built with Lune";
+
+/**
+    A packaging manifest format that `lune build --package` can create
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageFormat {
+    Formula,
+    Scoop,
+    Deb,
+}
+
+impl PackageFormat {
+    /**
+        Checks that this format supports the given target.
+
+        # Errors
+
+        - If the format does not support the operating system of the target
+    */
+    pub fn check_target_supported(self, target: &BuildTarget) -> Result<()> {
+        let supported = match self {
+            Self::Formula => matches!(target.os, BuildTargetOS::MacOS | BuildTargetOS::Linux),
+            Self::Scoop => target.os == BuildTargetOS::Windows,
+            Self::Deb => target.os == BuildTargetOS::Linux,
+        };
+        if !supported {
+            bail!(
+                "--package {self} is not supported for {} targets",
+                target.os
+            );
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PackageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Formula => write!(f, "formula"),
+            Self::Scoop => write!(f, "scoop"),
+            Self::Deb => write!(f, "deb"),
+        }
+    }
+}
+
+impl FromStr for PackageFormat {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "formula" | "brew" | "homebrew" => Ok(Self::Formula),
+            "scoop" => Ok(Self::Scoop),
+            "deb" | "debian" => Ok(Self::Deb),
+            _ => Err("invalid package format, expected one of formula, scoop, deb"),
+        }
+    }
+}
+
+/**
+    Metadata of a package, resolved from the package
+    config and the options given to `lune build`.
+*/
+#[derive(Debug, Clone)]
+pub struct PackageInfo {
+    pub name: String,
+    pub version: String,
+    pub config: PackageConfig,
+}
+
+impl PackageInfo {
+    /**
+        Resolves the metadata of a package, using the package config if set,
+        otherwise the given binary name and version.
+
+        # Errors
+
+        - If no version was set in the package config, and none was given
+    */
+    pub fn resolve(
+        config: &PackageConfig,
+        binary_name: &str,
+        version: Option<&str>,
+    ) -> Result<Self> {
+        let name = config.name.as_deref().unwrap_or(binary_name).to_string();
+        let Some(version) = config.version.as_deref().or(version) else {
+            bail!(
+                "--package needs a version, set using `version` in the [package] table of lune.toml, \
+                or using --file-version or --define VERSION=..."
+            );
+        };
+        Ok(Self {
+            name,
+            version: version.to_string(),
+            config: config.clone(),
+        })
+    }
+
+    fn description(&self) -> &str {
+        self.config
+            .description
+            .as_deref()
+            .unwrap_or(DEFAULT_DESCRIPTION)
+    }
+
+    /**
+        Returns the URL that the binary will be published at,
+        with all placeholders replaced using the given values.
+    */
+    fn url(&self, format: PackageFormat, file_name: &str, target: &BuildTarget) -> Result<String> {
+        let Some(url) = &self.config.url else {
+            bail!(
+                "--package {format} needs the url of the binary, set using `url` in the [package] table of lune.toml"
+            );
+        };
+        Ok(url
+            .replace("{name}", &self.name)
+            .replace("{version}", &self.version)
+            .replace("{target}", &target.to_string())
+            .replace("{file}", file_name))
+    }
+}
+
+/**
+    Creates a packaging manifest in the given format, returning
+    the file name to write the manifest to and its contents.
+
+    # Errors
+
+    - If the format does not support the target
+    - If any metadata needed for the format is missing
+*/
+pub fn create_package(
+    format: PackageFormat,
+    info: &PackageInfo,
+    binary: &[u8],
+    file_name: &str,
+    target: &BuildTarget,
+) -> Result<(String, Vec<u8>)> {
+    format.check_target_supported(target)?;
+    let sha256 = format!("{:x}", Sha256::digest(binary));
+    match format {
+        PackageFormat::Formula => {
+            let url = info.url(format, file_name, target)?;
+            let manifest = create_formula(info, &url, &sha256);
+            Ok((format!("{}.rb", info.name), manifest.into_bytes()))
+        }
+        PackageFormat::Scoop => {
+            let url = info.url(format, file_name, target)?;
+            let manifest = create_scoop_manifest(info, &url, &sha256, target)?;
+            Ok((format!("{}.json", info.name), manifest.into_bytes()))
+        }
+        PackageFormat::Deb => {
+            let arch = debian_arch(target.arch);
+            let package = create_deb(info, binary, arch)?;
+            Ok((
+                format!("{}_{}_{arch}.deb", info.name, info.version),
+                package,
+            ))
+        }
+    }
+}
+
+fn url_file_name(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+fn formula_class_name(name: &str) -> String {
+    name.split(['-', '_', '.'])
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map_or_else(String::new, |first| {
+                first.to_ascii_uppercase().to_string() + chars.as_str()
+            })
+        })
+        .collect()
+}
+
+fn ruby_string(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("#{", "\\#{");
+    format!("\"{escaped}\"")
+}
+
+fn create_formula(info: &PackageInfo, url: &str, sha256: &str) -> String {
+    let mut lines = vec![
+        format!("class {} < Formula", formula_class_name(&info.name)),
+        format!("  desc {}", ruby_string(info.description())),
+    ];
+    if let Some(homepage) = &info.config.homepage {
+        lines.push(format!("  homepage {}", ruby_string(homepage)));
+    }
+    lines.push(format!("  url {}", ruby_string(url)));
+    lines.push(format!("  version {}", ruby_string(&info.version)));
+    lines.push(format!("  sha256 {}", ruby_string(sha256)));
+    if let Some(license) = &info.config.license {
+        lines.push(format!("  license {}", ruby_string(license)));
+    }
+    lines.push(String::new());
+    lines.push("  def install".to_string());
+    lines.push(format!(
+        "    bin.install {} => {}",
+        ruby_string(url_file_name(url)),
+        ruby_string(&info.name)
+    ));
+    lines.push("  end".to_string());
+    lines.push(String::new());
+    lines.push("  test do".to_string());
+    lines.push(format!(
+        "    assert_predicate bin/{}, :executable?",
+        ruby_string(&info.name)
+    ));
+    lines.push("  end".to_string());
+    lines.push("end".to_string());
+    lines.join("\n") + "\n"
+}
+
+fn create_scoop_manifest(
+    info: &PackageInfo,
+    url: &str,
+    sha256: &str,
+    target: &BuildTarget,
+) -> Result<String> {
+    let arch = match target.arch {
+        BuildTargetArch::X86_64 => "64bit",
+        BuildTargetArch::Aarch64 => "arm64",
+        BuildTargetArch::Universal => unreachable!("universal targets are macos only"),
+        BuildTargetArch::Wasm32 => unreachable!("wasm32 targets are wasi only"),
+    };
+    let file_name = url_file_name(url);
+    let exe_name = format!("{}.exe", info.name);
+    let bin = if file_name.eq_ignore_ascii_case(&exe_name) {
+        json!(file_name)
+    } else {
+        json!([[file_name, info.name]])
+    };
+
+    let mut manifest = json!({
+        "version": info.version,
+        "description": info.description(),
+        "architecture": {
+            arch: {
+                "url": url,
+                "hash": sha256,
+            }
+        },
+        "bin": bin,
+    });
+    if let Some(homepage) = &info.config.homepage {
+        manifest["homepage"] = json!(homepage);
+    }
+    if let Some(license) = &info.config.license {
+        manifest["license"] = json!(license);
+    }
+
+    Ok(serde_json::to_string_pretty(&manifest)? + "\n")
+}
+
+fn debian_arch(arch: BuildTargetArch) -> &'static str {
+    match arch {
+        BuildTargetArch::X86_64 => "amd64",
+        BuildTargetArch::Aarch64 => "arm64",
+        BuildTargetArch::Universal => unreachable!("universal targets are macos only"),
+        BuildTargetArch::Wasm32 => unreachable!("wasm32 targets are wasi only"),
+    }
+}
+
+fn create_deb(info: &PackageInfo, binary: &[u8], arch: &str) -> Result<Vec<u8>> {
+    let Some(maintainer) = &info.config.maintainer else {
+        bail!(
+            "--package deb needs a maintainer, set using `maintainer` in the [package] table of lune.toml"
+        );
+    };
+
+    let mut control = vec![
+        format!("Package: {}", info.name.to_ascii_lowercase()),
+        format!("Version: {}", info.version),
+        format!("Architecture: {arch}"),
+        format!("Maintainer: {maintainer}"),
+        format!("Installed-Size: {}", binary.len().div_ceil(1024)),
+        "Section: utils".to_string(),
+        "Priority: optional".to_string(),
+    ];
+    if let Some(homepage) = &info.config.homepage {
+        control.push(format!("Homepage: {homepage}"));
+    }
+    control.push(format!("Description: {}", info.description()));
+    let control = control.join("\n") + "\n";
+
+    let mut control_tar = Vec::new();
+    append_tar_dir(&mut control_tar, "./", 0o755);
+    append_tar_file(&mut control_tar, "./control", control.as_bytes(), 0o644);
+    finish_tar(&mut control_tar);
+
+    let mut data_tar = Vec::new();
+    append_tar_dir(&mut data_tar, "./", 0o755);
+    append_tar_dir(&mut data_tar, "./usr/", 0o755);
+    append_tar_dir(&mut data_tar, "./usr/bin/", 0o755);
+    let bin_path = format!("./usr/bin/{}", info.name);
+    append_tar_file(&mut data_tar, &bin_path, binary, 0o755);
+    finish_tar(&mut data_tar);
+
+    Ok(create_ar(&[
+        ("debian-binary", b"2.0\n"),
+        ("control.tar.gz", &gzip(&control_tar)?),
+        ("data.tar.gz", &gzip(&data_tar)?),
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info() -> PackageInfo {
+        let config = PackageConfig {
+            description: Some("Does things".to_string()),
+            maintainer: Some("Someone <someone@example.com>".to_string()),
+            url: Some("https://example.com/v{version}/{name}-{target}".to_string()),
+            ..PackageConfig::default()
+        };
+        PackageInfo::resolve(&config, "my-tool", Some("1.2.3")).unwrap()
+    }
+
+    #[test]
+    fn version_is_required() {
+        assert!(PackageInfo::resolve(&PackageConfig::default(), "tool", None).is_err());
+    }
+
+    #[test]
+    fn formula_has_checksum_and_url() {
+        let target = "macos-aarch64".parse().unwrap();
+        let (name, formula) =
+            create_package(PackageFormat::Formula, &info(), b"bin", "my-tool", &target).unwrap();
+        let formula = String::from_utf8(formula).unwrap();
+
+        assert_eq!(name, "my-tool.rb");
+        assert!(formula.starts_with("class MyTool < Formula\n"));
+        assert!(formula.contains("url \"https://example.com/v1.2.3/my-tool-macos-aarch64\""));
+        assert!(formula.contains(&format!("sha256 \"{:x}\"", Sha256::digest(b"bin"))));
+        assert!(formula.contains("bin.install \"my-tool-macos-aarch64\" => \"my-tool\""));
+    }
+
+    #[test]
+    fn scoop_manifest_has_architecture() {
+        let target = "windows-x86_64".parse().unwrap();
+        let (name, manifest) = create_package(
+            PackageFormat::Scoop,
+            &info(),
+            b"bin",
+            "my-tool.exe",
+            &target,
+        )
+        .unwrap();
+        let manifest: serde_json::Value = serde_json::from_slice(&manifest).unwrap();
+
+        assert_eq!(name, "my-tool.json");
+        assert_eq!(manifest["version"], "1.2.3");
+        assert_eq!(
+            manifest["architecture"]["64bit"]["url"],
+            "https://example.com/v1.2.3/my-tool-windows-x86_64"
+        );
+        assert_eq!(
+            manifest["bin"],
+            json!([["my-tool-windows-x86_64", "my-tool"]])
+        );
+    }
+
+    #[test]
+    fn deb_is_an_ar_archive() {
+        let target = "linux-x86_64".parse().unwrap();
+        let (name, package) =
+            create_package(PackageFormat::Deb, &info(), b"bin", "my-tool", &target).unwrap();
+        assert_eq!(name, "my-tool_1.2.3_amd64.deb");
+        assert!(package.starts_with(b"!<arch>\ndebian-binary"));
+    }
+
+    #[test]
+    fn formats_check_targets() {
+        let windows = "windows-x86_64".parse().unwrap();
+        assert!(PackageFormat::Deb.check_target_supported(&windows).is_err());
+        assert!(
+            PackageFormat::Formula
+                .check_target_supported(&windows)
+                .is_err()
+        );
+        assert!(
+            PackageFormat::Scoop
+                .check_target_supported(&windows)
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,978 @@
+/*!
+    Icon and version resource embedding for Windows PE executables.
+
+    Standalone binaries built by `lune build` inherit the resources of the base
+    executable, which has no icon or version information describing the tool.
+    This module rebuilds the resource tree of a PE executable with a new icon
+    group and / or version resource, and stores it in a newly added section,
+    without requiring any Windows-only tooling such as `rc.exe` or `rcedit`.
+
+    The original resource section is left in place (but unreferenced), since
+    moving or resizing existing sections would require relocating their data.
+
+    Adding sections is also used for storing the metadata of standalone
+    binaries in a section of their own, see [`super::section`].
+*/
+
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result, bail};
+
+// PE constants
+const PE_SIGNATURE: &[u8; 4] = b"PE\0\0";
+const PE32_MAGIC: u16 = 0x10b;
+const PE32_PLUS_MAGIC: u16 = 0x20b;
+const SECTION_HEADER_SIZE: usize = 40;
+const DIRECTORY_ENTRY_RESOURCE: usize = 2;
+const DIRECTORY_ENTRY_SECURITY: usize = 4;
+const SECTION_CHARACTERISTICS: u32 = 0x4000_0040; // Initialized data, readable
+const SECTION_NAME: &[u8; 8] = b".lrsrc\0\0";
+
+// Resource constants
+const RT_ICON: u16 = 3;
+const RT_GROUP_ICON: u16 = 14;
+const RT_VERSION: u16 = 16;
+const LANG_EN_US: u16 = 0x0409;
+const CODEPAGE_UNICODE: u16 = 0x04B0;
+const RESOURCE_DIRECTORY_SIZE: usize = 16;
+const RESOURCE_ENTRY_SIZE: usize = 8;
+const RESOURCE_DATA_ENTRY_SIZE: usize = 16;
+const SUBDIRECTORY_FLAG: u32 = 0x8000_0000;
+
+/**
+    Resources to embed into a Windows executable.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct WindowsResources {
+    /// Contents of an `.ico` file to use as the executable icon
+    pub icon: Option<Vec<u8>>,
+    /// Version number, in the form `major.minor.patch.build`
+    pub version: Option<[u16; 4]>,
+    /// Version information strings, such as `CompanyName` or `ProductName`
+    pub strings: BTreeMap<String, String>,
+}
+
+impl WindowsResources {
+    /// Returns `true` if there are no resources to embed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.icon.is_none() && self.version.is_none() && self.strings.is_empty()
+    }
+}
+
+/**
+    Parses a version string such as `1.2.3` into the four
+    numeric components used by Windows version resources.
+
+    # Errors
+
+    - If the version has more than four components
+    - If any component is not a valid 16-bit number
+*/
+pub fn parse_version(version: &str) -> Result<[u16; 4]> {
+    let mut parts = [0u16; 4];
+    // Ignore any pre-release or build metadata, such as 1.2.3-beta.1
+    let numeric = version.trim().split(['-', '+']).next().unwrap_or_default();
+    for (index, part) in numeric.split('.').enumerate() {
+        if index >= 4 {
+            bail!("version '{version}' has more than four components");
+        }
+        parts[index] = part
+            .parse()
+            .with_context(|| format!("invalid version component '{part}' in '{version}'"))?;
+    }
+    Ok(parts)
+}
+
+/// A name or numeric id of a resource type or resource entry.
+///
+/// Named entries are ordered before numeric ids, which is
+/// the order required for entries in a resource directory.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum ResourceName {
+    Name(Vec<u16>),
+    Id(u16),
+}
+
+#[derive(Debug, Clone)]
+struct ResourceData {
+    data: Vec<u8>,
+    codepage: u32,
+}
+
+/// Resource tree in the standard three levels: type -> name -> language
+type ResourceTree = BTreeMap<ResourceName, BTreeMap<ResourceName, BTreeMap<u16, ResourceData>>>;
+
+struct PeSection {
+    virtual_size: u32,
+    virtual_address: u32,
+    raw_size: u32,
+    raw_pointer: u32,
+}
+
+struct PeInfo {
+    optional_header_offset: usize,
+    data_directory_offset: usize,
+    number_of_rva_and_sizes: usize,
+    section_count_offset: usize,
+    section_table_offset: usize,
+    section_alignment: u32,
+    file_alignment: u32,
+    size_of_headers: u32,
+    sections: Vec<PeSection>,
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn write_u16(buf: &mut [u8], offset: usize, val: u16) {
+    buf[offset..offset + 2].copy_from_slice(&val.to_le_bytes());
+}
+
+fn write_u32(buf: &mut [u8], offset: usize, val: u32) {
+    buf[offset..offset + 4].copy_from_slice(&val.to_le_bytes());
+}
+
+fn align_up(value: usize, alignment: usize) -> usize {
+    if alignment == 0 {
+        value
+    } else {
+        value.div_ceil(alignment) * alignment
+    }
+}
+
+fn count_named<'a>(names: impl Iterator<Item = &'a ResourceName>) -> usize {
+    names
+        .filter(|name| matches!(name, ResourceName::Name(_)))
+        .count()
+}
+
+/// Parse the headers of a PE executable.
+fn parse_pe(data: &[u8]) -> Option<PeInfo> {
+    if !data.starts_with(b"MZ") {
+        return None;
+    }
+
+    let pe_offset = read_u32(data, 0x3C)? as usize;
+    if data.get(pe_offset..pe_offset + 4)? != PE_SIGNATURE {
+        return None;
+    }
+
+    // COFF header follows the signature
+    let coff_offset = pe_offset + 4;
+    let section_count = read_u16(data, coff_offset + 2)? as usize;
+    let optional_header_size = read_u16(data, coff_offset + 16)? as usize;
+
+    // The optional header differs slightly between PE32 and PE32+
+    let optional_header_offset = coff_offset + 20;
+    let (rva_count_offset, data_directory_offset) = match read_u16(data, optional_header_offset)? {
+        PE32_MAGIC => (optional_header_offset + 92, optional_header_offset + 96),
+        PE32_PLUS_MAGIC => (optional_header_offset + 108, optional_header_offset + 112),
+        _ => return None,
+    };
+
+    let section_table_offset = optional_header_offset + optional_header_size;
+    let mut sections = Vec::with_capacity(section_count);
+    for index in 0..section_count {
+        let offset = section_table_offset + index * SECTION_HEADER_SIZE;
+        sections.push(PeSection {
+            virtual_size: read_u32(data, offset + 8)?,
+            virtual_address: read_u32(data, offset + 12)?,
+            raw_size: read_u32(data, offset + 16)?,
+            raw_pointer: read_u32(data, offset + 20)?,
+        });
+    }
+
+    Some(PeInfo {
+        optional_header_offset,
+        data_directory_offset,
+        number_of_rva_and_sizes: read_u32(data, rva_count_offset)? as usize,
+        section_count_offset: coff_offset + 2,
+        section_table_offset,
+        section_alignment: read_u32(data, optional_header_offset + 32)?,
+        file_alignment: read_u32(data, optional_header_offset + 36)?,
+        size_of_headers: read_u32(data, optional_header_offset + 60)?,
+        sections,
+    })
+}
+
+impl PeInfo {
+    fn data_directory(&self, data: &[u8], index: usize) -> Option<(u32, u32)> {
+        if index >= self.number_of_rva_and_sizes {
+            return None;
+        }
+        let offset = self.data_directory_offset + index * 8;
+        Some((read_u32(data, offset)?, read_u32(data, offset + 4)?))
+    }
+
+    fn rva_to_offset(&self, rva: u32) -> Option<usize> {
+        self.sections.iter().find_map(|section| {
+            // Only the part of a section that is backed by raw data has a file
+            // offset, the rest of it is filled with zeros once it is loaded
+            let delta = rva.checked_sub(section.virtual_address)?;
+            if delta >= section.raw_size {
+                return None;
+            }
+            let offset = section.raw_pointer.checked_add(delta)?;
+            Some(offset as usize)
+        })
+    }
+}
+
+/// Read the existing resource tree of a PE executable, if it has one.
+fn read_resource_tree(data: &[u8], info: &PeInfo) -> Option<ResourceTree> {
+    let (rva, size) = info.data_directory(data, DIRECTORY_ENTRY_RESOURCE)?;
+    if rva == 0 || size == 0 {
+        return None;
+    }
+    let root = info.rva_to_offset(rva)?;
+
+    let read_name = |name: u32| -> Option<ResourceName> {
+        if name & SUBDIRECTORY_FLAG == 0 {
+            return Some(ResourceName::Id(name as u16));
+        }
+        let offset = root + (name & !SUBDIRECTORY_FLAG) as usize;
+        let len = read_u16(data, offset)? as usize;
+        let chars = (0..len)
+            .map(|i| read_u16(data, offset + 2 + i * 2))
+            .collect::<Option<Vec<_>>>()?;
+        Some(ResourceName::Name(chars))
+    };
+
+    let read_entries = |dir_offset: usize| -> Option<Vec<(u32, u32)>> {
+        let named = read_u16(data, dir_offset + 12)? as usize;
+        let ids = read_u16(data, dir_offset + 14)? as usize;
+        (0..named + ids)
+            .map(|i| {
+                let offset = dir_offset + RESOURCE_DIRECTORY_SIZE + i * RESOURCE_ENTRY_SIZE;
+                Some((read_u32(data, offset)?, read_u32(data, offset + 4)?))
+            })
+            .collect()
+    };
+
+    let subdirectory = |target: u32| -> Option<usize> {
+        if target & SUBDIRECTORY_FLAG == 0 {
+            return None;
+        }
+        Some(root + (target & !SUBDIRECTORY_FLAG) as usize)
+    };
+
+    let mut tree = ResourceTree::new();
+    for (type_name, type_target) in read_entries(root)? {
+        let type_dir = subdirectory(type_target)?;
+        let names = tree.entry(read_name(type_name)?).or_default();
+        for (entry_name, entry_target) in read_entries(type_dir)? {
+            let entry_dir = subdirectory(entry_target)?;
+            let langs = names.entry(read_name(entry_name)?).or_default();
+            for (lang, data_target) in read_entries(entry_dir)? {
+                // Leaf entries point to data entries, which contain RVAs
+                let data_entry = root + data_target as usize;
+                let data_rva = read_u32(data, data_entry)?;
+                let data_size = read_u32(data, data_entry + 4)? as usize;
+                let codepage = read_u32(data, data_entry + 8)?;
+                let data_offset = info.rva_to_offset(data_rva)?;
+                let bytes = data.get(data_offset..data_offset + data_size)?.to_vec();
+                langs.insert(
+                    lang as u16,
+                    ResourceData {
+                        data: bytes,
+                        codepage,
+                    },
+                );
+            }
+        }
+    }
+
+    Some(tree)
+}
+
+/// Offsets of every part of a serialized resource tree, relative to the start of the section.
+struct ResourceLayout {
+    type_offsets: Vec<usize>,
+    name_offsets: Vec<usize>,
+    data_entry_offsets: Vec<usize>,
+    string_offsets: BTreeMap<Vec<u16>, usize>,
+    data_offsets: Vec<usize>,
+    size: usize,
+}
+
+impl ResourceLayout {
+    fn new(tree: &ResourceTree) -> Self {
+        let dir_size = |count: usize| RESOURCE_DIRECTORY_SIZE + count * RESOURCE_ENTRY_SIZE;
+
+        // Directories are laid out breadth-first, followed by data
+        // entries, then name strings, and finally the resource data
+        let mut offset = dir_size(tree.len());
+        let mut type_offsets = Vec::new();
+        for names in tree.values() {
+            type_offsets.push(offset);
+            offset += dir_size(names.len());
+        }
+        let mut name_offsets = Vec::new();
+        for langs in tree.values().flat_map(BTreeMap::values) {
+            name_offsets.push(offset);
+            offset += dir_size(langs.len());
+        }
+        let mut data_entry_offsets = Vec::new();
+        for _ in tree
+            .values()
+            .flat_map(BTreeMap::values)
+            .flat_map(BTreeMap::values)
+        {
+            data_entry_offsets.push(offset);
+            offset += RESOURCE_DATA_ENTRY_SIZE;
+        }
+        let mut string_offsets = BTreeMap::new();
+        for (type_name, names) in tree {
+            for name in std::iter::once(type_name).chain(names.keys()) {
+                if let ResourceName::Name(chars) = name {
+                    string_offsets.entry(chars.clone()).or_insert_with(|| {
+                        let string_offset = offset;
+                        offset += 2 + chars.len() * 2;
+                        string_offset
+                    });
+                }
+            }
+        }
+        offset = align_up(offset, 8);
+        let mut data_offsets = Vec::new();
+        for resource in tree
+            .values()
+            .flat_map(BTreeMap::values)
+            .flat_map(BTreeMap::values)
+        {
+            data_offsets.push(offset);
+            offset = align_up(offset + resource.data.len(), 8);
+        }
+
+        Self {
+            type_offsets,
+            name_offsets,
+            data_entry_offsets,
+            string_offsets,
+            data_offsets,
+            size: offset,
+        }
+    }
+
+    fn name_field(&self, name: &ResourceName) -> u32 {
+        match name {
+            ResourceName::Id(id) => u32::from(*id),
+            ResourceName::Name(chars) => SUBDIRECTORY_FLAG | self.string_offsets[chars] as u32,
+        }
+    }
+}
+
+/// Write a resource directory with the given `(name, target)` entries.
+fn write_directory(buf: &mut [u8], offset: usize, entries: &[(u32, u32)], named: usize) {
+    write_u16(buf, offset + 12, named as u16);
+    write_u16(buf, offset + 14, (entries.len() - named) as u16);
+    for (index, (name, target)) in entries.iter().enumerate() {
+        let entry_offset = offset + RESOURCE_DIRECTORY_SIZE + index * RESOURCE_ENTRY_SIZE;
+        write_u32(buf, entry_offset, *name);
+        write_u32(buf, entry_offset + 4, *target);
+    }
+}
+
+/// Serialize a resource tree into the contents of a resource section at the given RVA.
+fn write_resource_tree(tree: &ResourceTree, section_rva: u32) -> Vec<u8> {
+    let layout = ResourceLayout::new(tree);
+    let mut buf = vec![0u8; layout.size];
+
+    // Root directory
+    let root_entries = tree
+        .keys()
+        .zip(&layout.type_offsets)
+        .map(|(name, target)| (layout.name_field(name), SUBDIRECTORY_FLAG | *target as u32))
+        .collect::<Vec<_>>();
+    write_directory(&mut buf, 0, &root_entries, count_named(tree.keys()));
+
+    // Type and name directories, followed by the data itself
+    let mut name_index = 0;
+    let mut leaf_index = 0;
+    for (names, type_offset) in tree.values().zip(&layout.type_offsets) {
+        let type_entries = names
+            .keys()
+            .zip(&layout.name_offsets[name_index..])
+            .map(|(name, target)| (layout.name_field(name), SUBDIRECTORY_FLAG | *target as u32))
+            .collect::<Vec<_>>();
+        write_directory(
+            &mut buf,
+            *type_offset,
+            &type_entries,
+            count_named(names.keys()),
+        );
+
+        for langs in names.values() {
+            let lang_entries = langs
+                .keys()
+                .zip(&layout.data_entry_offsets[leaf_index..])
+                .map(|(lang, target)| (u32::from(*lang), *target as u32))
+                .collect::<Vec<_>>();
+            write_directory(&mut buf, layout.name_offsets[name_index], &lang_entries, 0);
+            name_index += 1;
+
+            for resource in langs.values() {
+                let entry_offset = layout.data_entry_offsets[leaf_index];
+                let data_offset = layout.data_offsets[leaf_index];
+                let data_end = data_offset + resource.data.len();
+                write_u32(&mut buf, entry_offset, section_rva + data_offset as u32);
+                write_u32(&mut buf, entry_offset + 4, resource.data.len() as u32);
+                write_u32(&mut buf, entry_offset + 8, resource.codepage);
+                buf[data_offset..data_end].copy_from_slice(&resource.data);
+                leaf_index += 1;
+            }
+        }
+    }
+
+    for (chars, string_offset) in &layout.string_offsets {
+        write_u16(&mut buf, *string_offset, chars.len() as u16);
+        for (index, c) in chars.iter().enumerate() {
+            write_u16(&mut buf, string_offset + 2 + index * 2, *c);
+        }
+    }
+
+    buf
+}
+
+/// Convert an `.ico` file into individual `RT_ICON` images and an `RT_GROUP_ICON` directory.
+fn parse_icon(ico: &[u8]) -> Result<(Vec<Vec<u8>>, Vec<u8>)> {
+    let invalid = || anyhow::anyhow!("icon file is not a valid .ico file");
+    if read_u16(ico, 0) != Some(0) || read_u16(ico, 2) != Some(1) {
+        return Err(invalid());
+    }
+    let count = read_u16(ico, 4).ok_or_else(invalid)? as usize;
+    if count == 0 {
+        bail!("icon file does not contain any images");
+    }
+
+    let mut images = Vec::with_capacity(count);
+    let mut group = Vec::with_capacity(6 + count * 14);
+    group.extend_from_slice(&[0, 0, 1, 0]);
+    group.extend_from_slice(&(count as u16).to_le_bytes());
+
+    for index in 0..count {
+        // ICONDIRENTRY is 16 bytes, GRPICONDIRENTRY replaces the
+        // trailing image offset with a 16-bit id of the RT_ICON entry
+        let entry = ico
+            .get(6 + index * 16..6 + (index + 1) * 16)
+            .ok_or_else(invalid)?;
+        let size = read_u32(entry, 8).ok_or_else(invalid)? as usize;
+        let offset = read_u32(entry, 12).ok_or_else(invalid)? as usize;
+        let image = ico.get(offset..offset + size).ok_or_else(invalid)?;
+        images.push(image.to_vec());
+        group.extend_from_slice(&entry[..12]);
+        group.extend_from_slice(&(index as u16 + 1).to_le_bytes());
+    }
+
+    Ok((images, group))
+}
+
+/// Value of a block in a version resource.
+enum VersionValue<'a> {
+    None,
+    Binary(&'a [u8]),
+    Text(&'a str),
+}
+
+/// Write a single (nested) block of a version resource, with its children.
+fn write_version_block(
+    buf: &mut Vec<u8>,
+    key: &str,
+    value: &VersionValue,
+    children: impl FnOnce(&mut Vec<u8>),
+) {
+    buf.resize(align_up(buf.len(), 4), 0);
+    let start = buf.len();
+
+    let (value_len, value_type) = match value {
+        VersionValue::None => (0, 1),
+        VersionValue::Binary(bytes) => (bytes.len(), 0),
+        // Text lengths are in characters, including the null terminator
+        VersionValue::Text(text) => (text.encode_utf16().count() + 1, 1),
+    };
+    buf.extend_from_slice(&[0, 0]); // Length, written at the end
+    buf.extend_from_slice(&(value_len as u16).to_le_bytes());
+    buf.extend_from_slice(&(value_type as u16).to_le_bytes());
+    for c in key.encode_utf16().chain(std::iter::once(0)) {
+        buf.extend_from_slice(&c.to_le_bytes());
+    }
+
+    buf.resize(align_up(buf.len(), 4), 0);
+    match value {
+        VersionValue::None => {}
+        VersionValue::Binary(bytes) => buf.extend_from_slice(bytes),
+        VersionValue::Text(text) => {
+            for c in text.encode_utf16().chain(std::iter::once(0)) {
+                buf.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+    }
+
+    children(buf);
+
+    let len = (buf.len() - start) as u16;
+    write_u16(buf, start, len);
+}
+
+/// Build a `VS_VERSIONINFO` resource from the given version and strings.
+fn build_version_info(version: [u16; 4], strings: &BTreeMap<String, String>) -> Vec<u8> {
+    let ms = (u32::from(version[0]) << 16) | u32::from(version[1]);
+    let ls = (u32::from(version[2]) << 16) | u32::from(version[3]);
+
+    // VS_FIXEDFILEINFO
+    let mut fixed = Vec::with_capacity(52);
+    for value in [
+        0xFEEF_04BD, // Signature
+        0x0001_0000, // Structure version
+        ms,          // File version
+        ls,
+        ms, // Product version
+        ls,
+        0x3F,        // File flags mask
+        0,           // File flags
+        0x0004_0004, // VOS_NT_WINDOWS32
+        1,           // VFT_APP
+        0,           // File subtype
+        0,           // File date
+        0,
+    ] {
+        fixed.extend_from_slice(&u32::to_le_bytes(value));
+    }
+
+    let translation = [LANG_EN_US.to_le_bytes(), CODEPAGE_UNICODE.to_le_bytes()].concat();
+    let table_key = format!("{LANG_EN_US:04X}{CODEPAGE_UNICODE:04X}");
+
+    let mut buf = Vec::new();
+    write_version_block(
+        &mut buf,
+        "VS_VERSION_INFO",
+        &VersionValue::Binary(&fixed),
+        |buf| {
+            write_version_block(buf, "StringFileInfo", &VersionValue::None, |buf| {
+                write_version_block(buf, &table_key, &VersionValue::None, |buf| {
+                    for (key, value) in strings {
+                        write_version_block(buf, key, &VersionValue::Text(value), |_| {});
+                    }
+                });
+            });
+            write_version_block(buf, "VarFileInfo", &VersionValue::None, |buf| {
+                write_version_block(
+                    buf,
+                    "Translation",
+                    &VersionValue::Binary(&translation),
+                    |_| {},
+                );
+            });
+        },
+    );
+    buf
+}
+
+/// Replace the icon and / or version resources in a resource tree.
+fn update_resource_tree(tree: &mut ResourceTree, resources: &WindowsResources) -> Result<()> {
+    let language = |data: Vec<u8>| {
+        let mut langs = BTreeMap::new();
+        langs.insert(LANG_EN_US, ResourceData { data, codepage: 0 });
+        langs
+    };
+
+    if let Some(icon) = &resources.icon {
+        let (images, group) = parse_icon(icon)?;
+        let icons = tree.entry(ResourceName::Id(RT_ICON)).or_default();
+        icons.clear();
+        for (index, image) in images.into_iter().enumerate() {
+            icons.insert(ResourceName::Id(index as u16 + 1), language(image));
+        }
+        let groups = tree.entry(ResourceName::Id(RT_GROUP_ICON)).or_default();
+        groups.clear();
+        groups.insert(ResourceName::Id(1), language(group));
+    }
+
+    if resources.version.is_some() || !resources.strings.is_empty() {
+        let version = resources.version.unwrap_or_default();
+        let mut strings = resources.strings.clone();
+        if resources.version.is_some() {
+            let version_string = version.map(|v| v.to_string()).join(".");
+            strings
+                .entry("FileVersion".to_string())
+                .or_insert_with(|| version_string.clone());
+            strings
+                .entry("ProductVersion".to_string())
+                .or_insert(version_string);
+        }
+        let versions = tree.entry(ResourceName::Id(RT_VERSION)).or_default();
+        versions.clear();
+        versions.insert(
+            ResourceName::Id(1),
+            language(build_version_info(version, &strings)),
+        );
+    }
+
+    Ok(())
+}
+
+/**
+    Embeds the given resources into a Windows PE executable, in place.
+
+    Any existing icons are replaced when an icon is given, and any existing
+    version resource is replaced when version information is given, other
+    resources in the executable (such as the manifest) are preserved.
+
+    # Errors
+
+    - If the given data is not a valid PE executable
+    - If the icon is not a valid `.ico` file
+    - If there is no room for an additional section header
+*/
+pub fn embed_resources(data: &mut Vec<u8>, resources: &WindowsResources) -> Result<()> {
+    let info = parse_pe(data).context("base executable is not a valid Windows executable")?;
+    if info.number_of_rva_and_sizes <= DIRECTORY_ENTRY_RESOURCE {
+        bail!("base executable has no resource data directory");
+    }
+    let mut tree = read_resource_tree(data, &info).unwrap_or_default();
+
+    update_resource_tree(&mut tree, resources)?;
+
+    let (section_rva, section_size) = add_section(data, SECTION_NAME, |section_rva| {
+        write_resource_tree(&tree, section_rva)
+    })?;
+
+    let dir_offset = info.data_directory_offset + DIRECTORY_ENTRY_RESOURCE * 8;
+    write_u32(data, dir_offset, section_rva);
+    write_u32(data, dir_offset + 4, section_size);
+
+    Ok(())
+}
+
+/**
+    Adds a new section with the given name to a Windows PE executable, in place,
+    after all of the existing sections, both in memory and on disk.
+
+    The contents of the section are created using the RVA that the section
+    will be placed at, and the RVA and size of the contents are returned.
+
+    # Errors
+
+    - If the given data is not a valid PE executable
+    - If there is no room for an additional section header
+*/
+pub fn add_section(
+    data: &mut Vec<u8>,
+    name: &[u8; 8],
+    make_contents: impl FnOnce(u32) -> Vec<u8>,
+) -> Result<(u32, u32)> {
+    let info = parse_pe(data).context("base executable is not a valid Windows executable")?;
+
+    // Make sure we have room for another section header before any section data
+    let header_end = info.section_table_offset + (info.sections.len() + 1) * SECTION_HEADER_SIZE;
+    let first_section_data = info
+        .sections
+        .iter()
+        .map(|s| s.raw_pointer as usize)
+        .filter(|p| *p > 0)
+        .min()
+        .unwrap_or(usize::MAX);
+    if header_end > info.size_of_headers as usize || header_end > first_section_data {
+        bail!("base executable has no room for an additional section");
+    }
+
+    // Any existing signature is invalidated by modifying the executable,
+    // and it would otherwise end up in the middle of the file, so strip it
+    let certificate = info
+        .data_directory(data, DIRECTORY_ENTRY_SECURITY)
+        .filter(|(offset, size)| *offset > 0 && *size > 0);
+    if let Some((cert_offset, _)) = certificate {
+        data.truncate(cert_offset as usize);
+        let dir_offset = info.data_directory_offset + DIRECTORY_ENTRY_SECURITY * 8;
+        write_u32(data, dir_offset, 0);
+        write_u32(data, dir_offset + 4, 0);
+    }
+
+    // The new section goes after all of the existing ones, both in memory and on disk
+    let section_rva = info
+        .sections
+        .iter()
+        .map(|s| {
+            s.virtual_address
+                .saturating_add(s.virtual_size.max(s.raw_size))
+        })
+        .max()
+        .unwrap_or_default();
+    let section_rva = align_up(section_rva as usize, info.section_alignment as usize) as u32;
+    let contents = make_contents(section_rva);
+    let raw_pointer = align_up(data.len(), info.file_alignment as usize);
+    let raw_size = align_up(contents.len(), info.file_alignment as usize);
+
+    data.resize(raw_pointer, 0);
+    data.extend_from_slice(&contents);
+    data.resize(raw_pointer + raw_size, 0);
+
+    // Write the section header and update the PE headers to reference it
+    let header_offset = info.section_table_offset + info.sections.len() * SECTION_HEADER_SIZE;
+    data[header_offset..header_offset + SECTION_HEADER_SIZE].fill(0);
+    data[header_offset..header_offset + 8].copy_from_slice(name);
+    write_u32(data, header_offset + 8, contents.len() as u32);
+    write_u32(data, header_offset + 12, section_rva);
+    write_u32(data, header_offset + 16, raw_size as u32);
+    write_u32(data, header_offset + 20, raw_pointer as u32);
+    write_u32(data, header_offset + 36, SECTION_CHARACTERISTICS);
+
+    write_u16(
+        data,
+        info.section_count_offset,
+        info.sections.len() as u16 + 1,
+    );
+
+    let size_of_image = align_up(
+        section_rva as usize + contents.len(),
+        info.section_alignment as usize,
+    );
+    write_u32(data, info.optional_header_offset + 56, size_of_image as u32);
+
+    // The checksum is not validated for regular executables, clear it
+    write_u32(data, info.optional_header_offset + 64, 0);
+
+    Ok((section_rva, contents.len() as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::standalone::section::{PE_SECTION_NAME, find_metadata_section};
+
+    use super::*;
+
+    fn utf16(s: &str) -> Vec<u16> {
+        s.encode_utf16().collect()
+    }
+
+    /// Build a minimal PE32+ executable with a single section.
+    fn build_test_pe() -> Vec<u8> {
+        let mut data = vec![0u8; 0x400];
+        data[0..2].copy_from_slice(b"MZ");
+        write_u32(&mut data, 0x3C, 0x80);
+        data[0x80..0x84].copy_from_slice(PE_SIGNATURE);
+
+        // COFF header
+        write_u16(&mut data, 0x84, 0x8664); // Machine
+        write_u16(&mut data, 0x86, 1); // Number of sections
+        write_u16(&mut data, 0x94, 240); // Size of optional header
+
+        // Optional header
+        let opt = 0x98;
+        write_u16(&mut data, opt, PE32_PLUS_MAGIC);
+        write_u32(&mut data, opt + 32, 0x1000); // Section alignment
+        write_u32(&mut data, opt + 36, 0x200); // File alignment
+        write_u32(&mut data, opt + 56, 0x2000); // Size of image
+        write_u32(&mut data, opt + 60, 0x200); // Size of headers
+        write_u32(&mut data, opt + 108, 16); // Number of data directories
+
+        // Single .text section
+        let section = opt + 240;
+        data[section..section + 5].copy_from_slice(b".text");
+        write_u32(&mut data, section + 8, 0x100);
+        write_u32(&mut data, section + 12, 0x1000);
+        write_u32(&mut data, section + 16, 0x200);
+        write_u32(&mut data, section + 20, 0x200);
+
+        data
+    }
+
+    /// Build a minimal .ico file with the given number of fake images.
+    fn build_test_ico(images: usize) -> Vec<u8> {
+        let mut ico = vec![0, 0, 1, 0];
+        ico.extend_from_slice(&(images as u16).to_le_bytes());
+        let data_start = 6 + images * 16;
+        for i in 0..images {
+            let size = 16 * (i as u8 + 1);
+            ico.extend_from_slice(&[size, size, 0, 0, 1, 0, 32, 0]);
+            ico.extend_from_slice(&8u32.to_le_bytes());
+            ico.extend_from_slice(&((data_start + i * 8) as u32).to_le_bytes());
+        }
+        for i in 0..images {
+            ico.extend_from_slice(&[i as u8; 8]);
+        }
+        ico
+    }
+
+    #[test]
+    fn parse_version_components() {
+        assert_eq!(parse_version("1.2.3").unwrap(), [1, 2, 3, 0]);
+        assert_eq!(parse_version("1.2.3.4").unwrap(), [1, 2, 3, 4]);
+        assert_eq!(parse_version("  2  ").unwrap(), [2, 0, 0, 0]);
+        assert_eq!(parse_version("0.10.4-horse.14").unwrap(), [0, 10, 4, 0]);
+    }
+
+    #[test]
+    fn parse_version_invalid() {
+        assert!(parse_version("1.2.3.4.5").is_err());
+        assert!(parse_version("one.two").is_err());
+        assert!(parse_version("70000").is_err());
+    }
+
+    #[test]
+    fn parse_pe_valid() {
+        let info = parse_pe(&build_test_pe()).expect("should parse");
+        assert_eq!(info.sections.len(), 1);
+        assert_eq!(info.file_alignment, 0x200);
+        assert_eq!(info.section_alignment, 0x1000);
+    }
+
+    #[test]
+    fn rva_to_offset_bounds() {
+        let mut info = parse_pe(&build_test_pe()).expect("should parse");
+        assert_eq!(info.rva_to_offset(0x1000), Some(0x200));
+        assert_eq!(info.rva_to_offset(0x11FF), Some(0x3FF));
+        assert_eq!(info.rva_to_offset(0x1200), None);
+        assert_eq!(info.rva_to_offset(0x0FFF), None);
+
+        // Sections with more virtual than raw data, or that would overflow, have no offset
+        info.sections[0].virtual_size = 0x1000;
+        assert_eq!(info.rva_to_offset(0x1400), None);
+        info.sections[0].raw_pointer = u32::MAX;
+        assert_eq!(info.rva_to_offset(0x1001), None);
+    }
+
+    #[test]
+    fn parse_pe_invalid() {
+        assert!(parse_pe(b"not an executable").is_none());
+        assert!(parse_pe(&[0u8; 0x100]).is_none());
+    }
+
+    #[test]
+    fn parse_icon_group() {
+        let (images, group) = parse_icon(&build_test_ico(2)).unwrap();
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[1], vec![1u8; 8]);
+        assert_eq!(group.len(), 6 + 2 * 14);
+        assert_eq!(read_u16(&group, 4), Some(2));
+        // Second entry should reference the second RT_ICON id
+        assert_eq!(read_u16(&group, 6 + 14 + 12), Some(2));
+    }
+
+    #[test]
+    fn parse_icon_invalid() {
+        assert!(parse_icon(b"nope").is_err());
+        assert!(parse_icon(&[0, 0, 1, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn version_info_structure() {
+        let mut strings = BTreeMap::new();
+        strings.insert("ProductName".to_string(), "Tool".to_string());
+        let info = build_version_info([1, 2, 3, 4], &strings);
+        assert_eq!(read_u16(&info, 0), Some(info.len() as u16));
+        assert_eq!(read_u16(&info, 2), Some(52));
+        // Key is followed by padding, then the fixed file info signature
+        let key_end = 6 + ("VS_VERSION_INFO".len() + 1) * 2;
+        let fixed = align_up(key_end, 4);
+        assert_eq!(read_u32(&info, fixed), Some(0xFEEF_04BD));
+        assert_eq!(read_u32(&info, fixed + 8), Some(0x0001_0002));
+        assert_eq!(read_u32(&info, fixed + 12), Some(0x0003_0004));
+    }
+
+    #[test]
+    fn resource_tree_roundtrip() {
+        let mut data = build_test_pe();
+        let resources = WindowsResources {
+            icon: Some(build_test_ico(3)),
+            version: Some([1, 0, 0, 0]),
+            strings: BTreeMap::new(),
+        };
+        embed_resources(&mut data, &resources).unwrap();
+
+        let info = parse_pe(&data).expect("should still parse");
+        assert_eq!(info.sections.len(), 2);
+        let tree = read_resource_tree(&data, &info).expect("should have resources");
+        assert_eq!(tree[&ResourceName::Id(RT_ICON)].len(), 3);
+        assert_eq!(tree[&ResourceName::Id(RT_GROUP_ICON)].len(), 1);
+        assert_eq!(tree[&ResourceName::Id(RT_VERSION)].len(), 1);
+    }
+
+    #[test]
+    fn resource_tree_preserves_existing() {
+        let mut data = build_test_pe();
+        let version_only = WindowsResources {
+            version: Some([1, 0, 0, 0]),
+            ..Default::default()
+        };
+        embed_resources(&mut data, &version_only).unwrap();
+
+        let icon_only = WindowsResources {
+            icon: Some(build_test_ico(1)),
+            ..Default::default()
+        };
+        embed_resources(&mut data, &icon_only).unwrap();
+
+        let info = parse_pe(&data).unwrap();
+        let tree = read_resource_tree(&data, &info).unwrap();
+        assert!(tree.contains_key(&ResourceName::Id(RT_VERSION)));
+        assert!(tree.contains_key(&ResourceName::Id(RT_ICON)));
+    }
+
+    #[test]
+    fn resource_tree_named_entries() {
+        let mut tree = ResourceTree::new();
+        let mut langs = BTreeMap::new();
+        langs.insert(
+            LANG_EN_US,
+            ResourceData {
+                data: b"hello".to_vec(),
+                codepage: 0,
+            },
+        );
+        let mut names = BTreeMap::new();
+        names.insert(ResourceName::Name(utf16("CUSTOM")), langs);
+        tree.insert(ResourceName::Name(utf16("DATA")), names);
+
+        let mut data = build_test_pe();
+        let info = parse_pe(&data).unwrap();
+        let contents = write_resource_tree(&tree, 0x2000);
+        // Put the resources in place of the section data to read them back
+        data.resize(0x200 + contents.len().max(0x200), 0);
+        data[0x200..0x200 + contents.len()].copy_from_slice(&contents);
+        let dir_offset = info.data_directory_offset + DIRECTORY_ENTRY_RESOURCE * 8;
+        write_u32(&mut data, dir_offset, 0x2000);
+        write_u32(&mut data, dir_offset + 4, contents.len() as u32);
+        let section = info.section_table_offset;
+        write_u32(&mut data, section + 12, 0x2000);
+        write_u32(&mut data, section + 8, contents.len() as u32);
+
+        let info = parse_pe(&data).unwrap();
+        let read = read_resource_tree(&data, &info).unwrap();
+        let names = &read[&ResourceName::Name(utf16("DATA"))];
+        let langs = &names[&ResourceName::Name(utf16("CUSTOM"))];
+        assert_eq!(langs[&LANG_EN_US].data, b"hello");
+    }
+
+    #[test]
+    fn add_section_after_existing() {
+        let mut data = build_test_pe();
+        let (rva, size) = add_section(&mut data, PE_SECTION_NAME, |_| b"payload".to_vec()).unwrap();
+        assert_eq!((rva, size), (0x2000, 7));
+
+        let info = parse_pe(&data).unwrap();
+        assert_eq!(info.sections.len(), 2);
+        let section = &info.sections[1];
+        assert_eq!(section.virtual_address, 0x2000);
+        assert_eq!(section.raw_pointer, 0x400);
+        assert_eq!(&data[0x400..0x407], b"payload");
+        assert_eq!(
+            read_u32(&data, info.optional_header_offset + 56),
+            Some(0x3000)
+        );
+    }
+
+    #[test]
+    fn embed_rejects_non_pe() {
+        let mut data = b"definitely not a PE file".to_vec();
+        assert!(embed_resources(&mut data, &WindowsResources::default()).is_err());
+    }
+}
@@ -0,0 +1,212 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{Context, Result};
+use async_fs as fs;
+use console::style;
+use serde::Serialize;
+
+use super::output::progress;
+
+/**
+    A single module stored in a standalone binary.
+*/
+#[derive(Debug, Clone, Serialize)]
+pub struct ModuleReport {
+    /// Path of the module inside of the bundle
+    pub path: String,
+    /// Size of the module as stored in the binary, in bytes
+    pub size: usize,
+    /// Paths of the modules that required this module, sorted
+    pub required_by: Vec<String>,
+}
+
+/**
+    A breakdown of what makes up a standalone binary, and how large each part is.
+*/
+#[derive(Debug, Clone, Serialize)]
+pub struct BundleReport {
+    /// Path of the entry point inside of the bundle
+    pub entry: String,
+    /// Size of the entry point as stored in the binary, in bytes
+    pub entry_size: usize,
+    /// All bundled modules, largest first
+    pub modules: Vec<ModuleReport>,
+    /// Combined size of the entry point and all bundled modules, in bytes
+    pub total_source_size: usize,
+    /// Size of everything appended to the base executable, in bytes,
+    /// including metadata, aliases, and any encryption overhead
+    pub payload_size: usize,
+    /// Size of the base executable, in bytes
+    pub base_size: usize,
+    /// Size of the final binary, in bytes, before any signing
+    pub binary_size: usize,
+}
+
+impl BundleReport {
+    /**
+        Creates a new report for the given entry point and bundled files.
+
+        Binary sizes are left as zero until set using [`BundleReport::with_sizes`].
+    */
+    pub fn new(
+        entry: &str,
+        entry_source: &[u8],
+        files: &HashMap<String, Vec<u8>>,
+        required_by: &HashMap<String, Vec<String>>,
+    ) -> Self {
+        let mut modules = files
+            .iter()
+            .map(|(path, contents)| ModuleReport {
+                path: path.clone(),
+                size: contents.len(),
+                required_by: required_by.get(path).cloned().unwrap_or_default(),
+            })
+            .collect::<Vec<_>>();
+        modules.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+
+        let total_source_size = entry_source.len() + modules.iter().map(|m| m.size).sum::<usize>();
+
+        Self {
+            entry: entry.to_string(),
+            entry_size: entry_source.len(),
+            modules,
+            total_source_size,
+            payload_size: 0,
+            base_size: 0,
+            binary_size: 0,
+        }
+    }
+
+    /**
+        Sets the sizes of the base executable and the final binary.
+    */
+    #[must_use]
+    pub fn with_sizes(mut self, base_size: usize, binary_size: usize) -> Self {
+        self.base_size = base_size;
+        self.binary_size = binary_size;
+        self.payload_size = binary_size.saturating_sub(base_size);
+        self
+    }
+
+    /**
+        Prints the report to stdout, in a human-readable format.
+    */
+    pub fn print(&self) {
+        let width = self
+            .modules
+            .iter()
+            .map(|m| m.path.len())
+            .chain([self.entry.len()])
+            .max()
+            .unwrap_or_default();
+
+        println!("\n{}", style("Bundle report").bold());
+        println!(
+            "  {:<width$}  {:>10}  {}",
+            style(&self.entry).green(),
+            format_size(self.entry_size),
+            style("(entry point)").dim()
+        );
+        for module in &self.modules {
+            let required_by = if module.required_by.is_empty() {
+                String::new()
+            } else {
+                format!("required by {}", module.required_by.join(", "))
+            };
+            println!(
+                "  {:<width$}  {:>10}  {}",
+                module.path,
+                format_size(module.size),
+                style(required_by).dim()
+            );
+        }
+
+        println!();
+        println!(
+            "  {:<20} {:>10}",
+            "Total source size",
+            format_size(self.total_source_size)
+        );
+        println!(
+            "  {:<20} {:>10}",
+            "Payload size",
+            format_size(self.payload_size)
+        );
+        println!(
+            "  {:<20} {:>10}",
+            "Base executable",
+            format_size(self.base_size)
+        );
+        println!(
+            "  {:<20} {:>10}\n",
+            "Binary size",
+            style(format_size(self.binary_size)).cyan()
+        );
+    }
+
+    /**
+        Writes the report to the given path, as JSON.
+
+        # Errors
+
+        - If the report could not be serialized or written
+    */
+    pub async fn write_to(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_vec_pretty(self).context("failed to serialize bundle report")?;
+        fs::write(path, json)
+            .await
+            .with_context(|| format!("failed to write bundle report to {}", path.display()))?;
+        progress(format_args!(
+            "Wrote bundle report to {}",
+            style(path.display()).blue()
+        ));
+        Ok(())
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+pub fn format_size(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modules_sorted_by_size() {
+        let files = HashMap::from([
+            ("small.luau".to_string(), vec![0; 10]),
+            ("large.luau".to_string(), vec![0; 100]),
+        ]);
+        let required_by = HashMap::from([(
+            "small.luau".to_string(),
+            vec!["large.luau".to_string(), "main.luau".to_string()],
+        )]);
+        let report =
+            BundleReport::new("main.luau", &[0; 5], &files, &required_by).with_sizes(1000, 1500);
+
+        assert_eq!(report.modules[0].path, "large.luau");
+        assert_eq!(report.modules[1].required_by.len(), 2);
+        assert_eq!(report.total_source_size, 115);
+        assert_eq!(report.payload_size, 500);
+    }
+
+    #[test]
+    fn sizes_formatted() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(2048), "2.0 KiB");
+        assert_eq!(format_size(40 * 1024 * 1024), "40.0 MiB");
+    }
+}
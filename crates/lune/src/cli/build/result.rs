@@ -13,6 +13,8 @@ pub enum BuildError {
     ZippedBinaryNotFound(String),
     #[error("failed to download lune binary: {0}")]
     Download(String),
+    #[error("failed to build lune binary with cargo: {0}")]
+    Cargo(String),
     #[error("failed to unzip lune binary: {0}")]
     Unzip(#[from] zip::result::ZipError),
     #[error("io error: {0}")]
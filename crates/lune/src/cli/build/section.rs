@@ -0,0 +1,376 @@
+/*!
+    Storing the metadata of standalone binaries in a dedicated section.
+
+    By default, metadata is appended to the end of the base executable, which
+    works for any executable format, but leaves data that is not part of the
+    executable itself - this invalidates code signatures, trips up some virus
+    scanners, and gets removed by tools such as `strip`. When building using
+    `--section`, metadata is instead stored in a section of its own, which
+    standalone binaries find through the headers of their executable, see
+    [`crate::standalone::section`]:
+
+    - ELF: a `.lune` section, which is not loaded into memory
+    - PE: a `.lune` section, added after all of the existing sections
+    - Mach-O: a `__LUNE` segment with a single `__lune` section, inserted
+      right before the `__LINKEDIT` segment, since the code signature
+      must stay at the end of `__LINKEDIT`, and cover all other segments
+*/
+
+use anyhow::{Result, bail};
+
+use crate::standalone::section::{
+    ELF_SECTION_NAME, MACHO_SECTION_NAME, MACHO_SEGMENT_NAME, PE_SECTION_NAME,
+};
+
+use super::{elf, pe_resources};
+
+const MH_MAGIC_64: u32 = 0xfeed_facf;
+const MACHO_HEADER_SIZE: usize = 32;
+
+const LC_SYMTAB: u32 = 0x2;
+const LC_DYSYMTAB: u32 = 0xb;
+const LC_SEGMENT_64: u32 = 0x19;
+const LC_DYLD_INFO: u32 = 0x22;
+const LC_DYLD_INFO_ONLY: u32 = 0x8000_0022;
+
+/// Load commands that only contain an offset and size of data in `__LINKEDIT`
+const LINKEDIT_DATA_COMMANDS: &[u32] = &[
+    0x1d,        // LC_CODE_SIGNATURE
+    0x1e,        // LC_SEGMENT_SPLIT_INFO
+    0x26,        // LC_FUNCTION_STARTS
+    0x29,        // LC_DATA_IN_CODE
+    0x2b,        // LC_DYLIB_CODE_SIGN_DRS
+    0x2e,        // LC_LINKER_OPTIMIZATION_HINT
+    0x36,        // LC_ATOM_INFO
+    0x8000_0033, // LC_DYLD_EXPORTS_TRIE
+    0x8000_0034, // LC_DYLD_CHAINED_FIXUPS
+];
+
+const SEGMENT_COMMAND_SIZE: usize = 72;
+const SECTION_SIZE: usize = 80;
+const VM_PROT_READ: u32 = 0x1;
+
+/// Segments are aligned to 16 KiB, the page size of both Intel and Apple Silicon Macs
+const SEGMENT_ALIGNMENT: usize = 0x4000;
+
+/**
+    Stores the given metadata in a dedicated section of the given base executable.
+
+    # Errors
+
+    - If the executable is not an ELF, PE or Mach-O executable
+    - If the executable has no room for the headers of another section
+*/
+pub fn embed_metadata_section(base_exe: &mut Vec<u8>, metadata: &[u8]) -> Result<()> {
+    if base_exe.starts_with(b"\x7fELF") {
+        elf::add_section(base_exe, ELF_SECTION_NAME, metadata)
+    } else if base_exe.starts_with(b"MZ") {
+        pe_resources::add_section(base_exe, PE_SECTION_NAME, |_| metadata.to_vec())?;
+        Ok(())
+    } else if read_u32(base_exe, 0) == Some(MH_MAGIC_64) {
+        add_macho_segment(base_exe, metadata)
+    } else {
+        bail!("base executable does not support storing metadata in a section")
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+fn write_u32(buf: &mut [u8], offset: usize, val: u32) {
+    buf[offset..offset + 4].copy_from_slice(&val.to_le_bytes());
+}
+
+fn write_u64(buf: &mut [u8], offset: usize, val: u64) {
+    buf[offset..offset + 8].copy_from_slice(&val.to_le_bytes());
+}
+
+fn write_name(buf: &mut [u8], offset: usize, name: &[u8]) {
+    buf[offset..offset + 16].fill(0);
+    buf[offset..offset + name.len()].copy_from_slice(name);
+}
+
+/// A load command of a Mach-O executable, at the given offset
+struct LoadCommand {
+    offset: usize,
+    cmd: u32,
+    segment_name: Option<[u8; 16]>,
+}
+
+fn read_load_commands(data: &[u8]) -> Option<Vec<LoadCommand>> {
+    let ncmds = read_u32(data, 16)?;
+    let mut commands = Vec::new();
+    let mut offset = MACHO_HEADER_SIZE;
+    for _ in 0..ncmds {
+        let cmd = read_u32(data, offset)?;
+        let cmdsize = usize::try_from(read_u32(data, offset + 4)?).ok()?;
+        if cmdsize < 8 {
+            return None;
+        }
+        let segment_name = if cmd == LC_SEGMENT_64 {
+            Some(data.get(offset + 8..offset + 24)?.try_into().ok()?)
+        } else {
+            None
+        };
+        commands.push(LoadCommand {
+            offset,
+            cmd,
+            segment_name,
+        });
+        offset = offset.checked_add(cmdsize)?;
+    }
+    Some(commands)
+}
+
+fn is_segment(command: &LoadCommand, name: &[u8]) -> bool {
+    command.segment_name.is_some_and(|segment_name| {
+        segment_name.starts_with(name) && segment_name[name.len()..].iter().all(|b| *b == 0)
+    })
+}
+
+/// Returns the lowest file offset of any section, which load commands must end before
+fn first_section_offset(data: &[u8], commands: &[LoadCommand]) -> Option<usize> {
+    let mut first = usize::MAX;
+    for command in commands.iter().filter(|c| c.segment_name.is_some()) {
+        let nsects = usize::try_from(read_u32(data, command.offset + 64)?).ok()?;
+        for index in 0..nsects {
+            let section = command.offset + SEGMENT_COMMAND_SIZE + index * SECTION_SIZE;
+            // NOTE: Zero-filled sections have no data in the file, and an offset of zero
+            let offset = usize::try_from(read_u32(data, section + 48)?).ok()?;
+            if offset > 0 {
+                first = first.min(offset);
+            }
+        }
+    }
+    Some(first)
+}
+
+/// Shifts the file offset stored at the given position, if it points into `__LINKEDIT`
+fn shift_u32(data: &mut [u8], offset: usize, linkedit_offset: u64, shift: u64) -> Result<()> {
+    let Some(value) = read_u32(data, offset) else {
+        bail!("base executable has truncated load commands");
+    };
+    if u64::from(value) >= linkedit_offset && value > 0 {
+        let Ok(value) = u32::try_from(u64::from(value) + shift) else {
+            bail!("base executable is too large to store metadata in a section");
+        };
+        write_u32(data, offset, value);
+    }
+    Ok(())
+}
+
+/**
+    Inserts a new `__LUNE` segment with the given contents into a 64-bit Mach-O
+    executable, right before its `__LINKEDIT` segment, moving `__LINKEDIT`
+    and all of the load commands that point into it further into the file.
+*/
+fn add_macho_segment(data: &mut Vec<u8>, contents: &[u8]) -> Result<()> {
+    let Some(commands) = read_load_commands(data) else {
+        bail!("base executable has truncated load commands");
+    };
+    if commands.iter().any(|c| is_segment(c, MACHO_SEGMENT_NAME)) {
+        bail!("base executable already has a metadata segment");
+    }
+    let Some(linkedit) = commands.iter().find(|c| is_segment(c, b"__LINKEDIT")) else {
+        bail!("base executable has no __LINKEDIT segment");
+    };
+    let linkedit_command = linkedit.offset;
+    let (Some(linkedit_vmaddr), Some(linkedit_fileoff)) = (
+        read_u64(data, linkedit_command + 24),
+        read_u64(data, linkedit_command + 40),
+    ) else {
+        bail!("base executable has truncated load commands");
+    };
+
+    // The new load command must fit between the existing ones and the first section
+    let sizeofcmds = usize::try_from(read_u32(data, 20).unwrap_or_default())?;
+    let commands_end = MACHO_HEADER_SIZE + sizeofcmds;
+    let new_command_size = SEGMENT_COMMAND_SIZE + SECTION_SIZE;
+    let first_section = first_section_offset(data, &commands).unwrap_or_default();
+    if commands_end + new_command_size > first_section {
+        bail!("base executable has no room for an additional segment");
+    }
+
+    // Move __LINKEDIT, and everything that points into it, to make room for the segment
+    let segment_size = contents.len().next_multiple_of(SEGMENT_ALIGNMENT);
+    let shift = segment_size as u64;
+    for command in &commands {
+        let offset = command.offset;
+        match command.cmd {
+            LC_SEGMENT_64 if offset == linkedit_command => {
+                write_u64(data, offset + 24, linkedit_vmaddr + shift);
+                write_u64(data, offset + 40, linkedit_fileoff + shift);
+            }
+            LC_SYMTAB => {
+                // symoff, stroff
+                for field in [8, 16] {
+                    shift_u32(data, offset + field, linkedit_fileoff, shift)?;
+                }
+            }
+            LC_DYSYMTAB => {
+                // tocoff, modtaboff, extrefsymoff, indirectsymoff, extreloff, locreloff
+                for field in [32, 40, 48, 56, 64, 72] {
+                    shift_u32(data, offset + field, linkedit_fileoff, shift)?;
+                }
+            }
+            LC_DYLD_INFO | LC_DYLD_INFO_ONLY => {
+                // rebase_off, bind_off, weak_bind_off, lazy_bind_off, export_off
+                for field in [8, 16, 24, 32, 40] {
+                    shift_u32(data, offset + field, linkedit_fileoff, shift)?;
+                }
+            }
+            cmd if LINKEDIT_DATA_COMMANDS.contains(&cmd) => {
+                // dataoff
+                shift_u32(data, offset + 8, linkedit_fileoff, shift)?;
+            }
+            _ => {}
+        }
+    }
+
+    let Ok(segment_start) = usize::try_from(linkedit_fileoff) else {
+        bail!("base executable has an invalid __LINKEDIT segment");
+    };
+    if segment_start > data.len() {
+        bail!("base executable has an invalid __LINKEDIT segment");
+    }
+    let mut segment = contents.to_vec();
+    segment.resize(segment_size, 0);
+    data.splice(segment_start..segment_start, segment);
+
+    // Write the new segment command in place of the __LINKEDIT
+    // segment command, with all of the following commands after it
+    let mut command = vec![0u8; new_command_size];
+    write_u32(&mut command, 0, LC_SEGMENT_64);
+    write_u32(&mut command, 4, new_command_size as u32);
+    write_name(&mut command, 8, MACHO_SEGMENT_NAME);
+    write_u64(&mut command, 24, linkedit_vmaddr); // vmaddr
+    write_u64(&mut command, 32, shift); // vmsize
+    write_u64(&mut command, 40, linkedit_fileoff); // fileoff
+    write_u64(&mut command, 48, shift); // filesize
+    write_u32(&mut command, 56, VM_PROT_READ); // maxprot
+    write_u32(&mut command, 60, VM_PROT_READ); // initprot
+    write_u32(&mut command, 64, 1); // nsects
+
+    let section = SEGMENT_COMMAND_SIZE;
+    write_name(&mut command, section, MACHO_SECTION_NAME);
+    write_name(&mut command, section + 16, MACHO_SEGMENT_NAME);
+    write_u64(&mut command, section + 32, linkedit_vmaddr); // addr
+    write_u64(&mut command, section + 40, contents.len() as u64); // size
+    write_u32(&mut command, section + 48, u32::try_from(linkedit_fileoff)?); // offset
+
+    data.copy_within(
+        linkedit_command..commands_end,
+        linkedit_command + new_command_size,
+    );
+    data[linkedit_command..linkedit_command + new_command_size].copy_from_slice(&command);
+
+    let ncmds = read_u32(data, 16).unwrap_or_default();
+    write_u32(data, 16, ncmds + 1);
+    write_u32(data, 20, u32::try_from(sizeofcmds + new_command_size)?);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::standalone::section::find_metadata_section;
+
+    use super::*;
+
+    const LC_CODE_SIGNATURE: u32 = 0x1d;
+
+    /// Build a Mach-O executable with a `__TEXT` segment containing a single
+    /// section, and a `__LINKEDIT` segment containing a code signature
+    fn build_test_macho() -> Vec<u8> {
+        let mut data = vec![0u8; SEGMENT_ALIGNMENT * 2];
+        write_u32(&mut data, 0, MH_MAGIC_64);
+        write_u32(&mut data, 12, 2); // MH_EXECUTE
+        write_u32(&mut data, 16, 3);
+        write_u32(
+            &mut data,
+            20,
+            (SEGMENT_COMMAND_SIZE + SECTION_SIZE + SEGMENT_COMMAND_SIZE + 16) as u32,
+        );
+
+        let text = MACHO_HEADER_SIZE;
+        write_u32(&mut data, text, LC_SEGMENT_64);
+        write_u32(
+            &mut data,
+            text + 4,
+            (SEGMENT_COMMAND_SIZE + SECTION_SIZE) as u32,
+        );
+        write_name(&mut data, text + 8, b"__TEXT");
+        write_u64(&mut data, text + 32, SEGMENT_ALIGNMENT as u64);
+        write_u64(&mut data, text + 48, SEGMENT_ALIGNMENT as u64);
+        write_u32(&mut data, text + 64, 1);
+        write_name(&mut data, text + SEGMENT_COMMAND_SIZE, b"__text");
+        write_u32(&mut data, text + SEGMENT_COMMAND_SIZE + 48, 0x1000);
+
+        let linkedit = text + SEGMENT_COMMAND_SIZE + SECTION_SIZE;
+        write_u32(&mut data, linkedit, LC_SEGMENT_64);
+        write_u32(&mut data, linkedit + 4, SEGMENT_COMMAND_SIZE as u32);
+        write_name(&mut data, linkedit + 8, b"__LINKEDIT");
+        write_u64(&mut data, linkedit + 24, SEGMENT_ALIGNMENT as u64);
+        write_u64(&mut data, linkedit + 32, SEGMENT_ALIGNMENT as u64);
+        write_u64(&mut data, linkedit + 40, SEGMENT_ALIGNMENT as u64);
+        write_u64(&mut data, linkedit + 48, SEGMENT_ALIGNMENT as u64);
+
+        let codesig = linkedit + SEGMENT_COMMAND_SIZE;
+        write_u32(&mut data, codesig, LC_CODE_SIGNATURE);
+        write_u32(&mut data, codesig + 4, 16);
+        write_u32(&mut data, codesig + 8, SEGMENT_ALIGNMENT as u32 + 0x10);
+        write_u32(&mut data, codesig + 12, 0x100);
+
+        data
+    }
+
+    #[test]
+    fn macho_segment_is_inserted_before_linkedit() {
+        let mut data = build_test_macho();
+        embed_metadata_section(&mut data, b"payload").unwrap();
+        assert_eq!(data.len(), SEGMENT_ALIGNMENT * 3);
+        assert_eq!(find_metadata_section(&data), Some(&b"payload"[..]));
+
+        let commands = read_load_commands(&data).unwrap();
+        assert_eq!(commands.len(), 4);
+        assert!(is_segment(&commands[1], MACHO_SEGMENT_NAME));
+        assert!(is_segment(&commands[2], b"__LINKEDIT"));
+
+        // __LINKEDIT, and the code signature inside of it, are moved after the new segment
+        let linkedit = commands[2].offset;
+        let shifted = 2 * SEGMENT_ALIGNMENT as u64;
+        assert_eq!(read_u64(&data, linkedit + 24), Some(shifted));
+        assert_eq!(read_u64(&data, linkedit + 40), Some(shifted));
+        assert_eq!(
+            read_u32(&data, commands[3].offset + 8),
+            Some(shifted as u32 + 0x10)
+        );
+    }
+
+    #[test]
+    fn macho_segment_needs_room() {
+        let mut data = build_test_macho();
+        // Move the text section right after the load commands
+        write_u32(
+            &mut data,
+            MACHO_HEADER_SIZE + SEGMENT_COMMAND_SIZE + 48,
+            0x150,
+        );
+        assert!(embed_metadata_section(&mut data, b"payload").is_err());
+    }
+
+    #[test]
+    fn unsupported_executables() {
+        let mut data = b"\0asm\x01\0\0\0".to_vec();
+        assert!(embed_metadata_section(&mut data, b"payload").is_err());
+    }
+}
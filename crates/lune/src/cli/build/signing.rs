@@ -0,0 +1,195 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{Context, Result, bail};
+use blocking::unblock;
+
+use super::target::BuildTarget;
+
+/// Identity that signs ad-hoc, same as with Apple's `codesign` tool
+pub const AD_HOC_IDENTITY: &str = "-";
+
+/**
+    How to sign a standalone macOS binary after it has been patched.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SigningMode {
+    /// Sign ad-hoc using the built-in, cross-platform signer
+    AdHoc,
+    /// Sign with an identity from the keychain, using Apple's `codesign` tool
+    Identity {
+        identity: String,
+        entitlements: Option<PathBuf>,
+    },
+    /// Do not sign the binary at all
+    Disabled,
+}
+
+impl SigningMode {
+    /**
+        Creates a signing mode from the build options given by the user.
+    */
+    pub fn from_options(
+        identity: Option<String>,
+        entitlements: Option<PathBuf>,
+        disabled: bool,
+    ) -> Result<Self> {
+        if disabled {
+            if identity.is_some() || entitlements.is_some() {
+                bail!(
+                    "--no-codesign can not be used together with --codesign-identity or --entitlements"
+                );
+            }
+            return Ok(Self::Disabled);
+        }
+        match identity {
+            Some(identity) if identity.trim() != AD_HOC_IDENTITY => Ok(Self::Identity {
+                identity: identity.trim().to_string(),
+                entitlements,
+            }),
+            _ if entitlements.is_some() => {
+                bail!("--entitlements requires a signing identity, given using --codesign-identity")
+            }
+            _ => Ok(Self::AdHoc),
+        }
+    }
+}
+
+fn codesign_args(path: &Path, identity: &str, entitlements: Option<&Path>) -> Vec<OsString> {
+    let mut args: Vec<OsString> = vec![
+        "--force".into(),
+        "--sign".into(),
+        identity.into(),
+        // Notarization requires both the hardened runtime and a secure timestamp
+        "--options".into(),
+        "runtime".into(),
+        "--timestamp".into(),
+    ];
+    if let Some(entitlements) = entitlements {
+        args.push("--entitlements".into());
+        args.push(entitlements.into());
+    }
+    args.push(path.into());
+    args
+}
+
+/**
+    Signs the binary at the given path with an identity from the
+    keychain, using Apple's `codesign` tool, which must be available.
+
+    # Errors
+
+    - If the current system is not macOS
+    - If the `codesign` tool could not be run, or fails
+*/
+pub async fn sign_with_identity(
+    path: &Path,
+    identity: &str,
+    entitlements: Option<&Path>,
+) -> Result<()> {
+    if !cfg!(target_os = "macos") {
+        bail!("signing with an identity requires building on macOS, use ad-hoc signing instead");
+    }
+
+    let args = codesign_args(path, identity, entitlements);
+    let status = unblock(move || Command::new("codesign").args(args).status())
+        .await
+        .context("failed to run codesign - is Xcode or the Command Line Tools installed?")?;
+    if !status.success() {
+        bail!("codesign failed with {status}");
+    }
+
+    Ok(())
+}
+
+/**
+    Runs a user-provided notarization hook for the binary at the given path.
+
+    The hook is a shell command, which receives the path to the binary and its
+    target using the `LUNE_BUILD_OUTPUT` and `LUNE_BUILD_TARGET` environment
+    variables, making it possible to invoke tools such as `xcrun notarytool`.
+
+    # Errors
+
+    - If the hook could not be run, or exits with a non-zero status
+*/
+pub async fn run_notarize_hook(command: &str, path: &Path, target: &BuildTarget) -> Result<()> {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+    cmd.env("LUNE_BUILD_OUTPUT", path)
+        .env("LUNE_BUILD_TARGET", target.to_string());
+
+    let status = unblock(move || cmd.status())
+        .await
+        .context("failed to run notarization hook")?;
+    if !status.success() {
+        bail!("notarization hook failed with {status}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mode_defaults_to_ad_hoc() {
+        let mode = SigningMode::from_options(None, None, false).unwrap();
+        assert_eq!(mode, SigningMode::AdHoc);
+        let mode = SigningMode::from_options(Some("-".to_string()), None, false).unwrap();
+        assert_eq!(mode, SigningMode::AdHoc);
+    }
+
+    #[test]
+    fn mode_with_identity() {
+        let mode = SigningMode::from_options(
+            Some("Developer ID Application: Someone".to_string()),
+            Some(PathBuf::from("app.entitlements")),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            mode,
+            SigningMode::Identity {
+                identity: "Developer ID Application: Someone".to_string(),
+                entitlements: Some(PathBuf::from("app.entitlements")),
+            }
+        );
+    }
+
+    #[test]
+    fn mode_disabled() {
+        let mode = SigningMode::from_options(None, None, true).unwrap();
+        assert_eq!(mode, SigningMode::Disabled);
+        assert!(SigningMode::from_options(Some("ID".to_string()), None, true).is_err());
+    }
+
+    #[test]
+    fn entitlements_require_identity() {
+        assert!(SigningMode::from_options(None, Some(PathBuf::from("e.plist")), false).is_err());
+    }
+
+    #[test]
+    fn codesign_args_order() {
+        let args = codesign_args(
+            Path::new("out/tool"),
+            "ID",
+            Some(Path::new("app.entitlements")),
+        );
+        assert_eq!(args.first(), Some(&OsString::from("--force")));
+        assert_eq!(args.last(), Some(&OsString::from("out/tool")));
+        assert!(args.contains(&OsString::from("--entitlements")));
+        assert!(args.contains(&OsString::from("runtime")));
+    }
+}
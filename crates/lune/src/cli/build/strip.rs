@@ -0,0 +1,164 @@
+use std::{collections::HashMap, time::Instant};
+
+use anyhow::{Result, anyhow};
+use lune_bundler::{module_cache::ModuleCache, strip_shebang};
+use lune_utils::path::is_data_file;
+use mlua::Compiler;
+
+use super::workers::{Throughput, Workers};
+
+/// The kind of processing stored in the module cache, including all compiler options
+const BYTECODE_CACHE_KIND: &str = "bytecode-O1-D1";
+
+/**
+    Compiles the given Luau source code into bytecode.
+
+    Bytecode keeps line information, so that errors still show
+    correct line numbers, but does not contain any of the source text.
+
+    # Errors
+
+    - If the source code is not valid Luau
+*/
+pub fn compile_bytecode(bundle_path: &str, source: Vec<u8>) -> Result<Vec<u8>> {
+    Compiler::new()
+        .set_optimization_level(1)
+        .set_debug_level(1)
+        .compile(strip_shebang(source))
+        .map_err(|e| anyhow!("failed to compile {bundle_path}: {e}"))
+}
+
+/**
+    Compiles the given Luau source code into bytecode, the same as [`compile_bytecode`],
+    reusing bytecode for identical source code from the given module cache, if any.
+
+    # Errors
+
+    - If the source code is not valid Luau
+*/
+pub fn compile_bytecode_cached(
+    cache: Option<&ModuleCache>,
+    bundle_path: &str,
+    source: Vec<u8>,
+) -> Result<Vec<u8>> {
+    let Some(cache) = cache else {
+        return compile_bytecode(bundle_path, source);
+    };
+    let source = strip_shebang(source);
+    cache.get_or_insert_with(BYTECODE_CACHE_KIND, &source, || {
+        compile_bytecode(bundle_path, source.clone())
+    })
+}
+
+/**
+    Compiles all of the given bundled files into bytecode, in place, using the given workers.
+
+    Data files, such as `config.json`, are kept as-is.
+
+    # Errors
+
+    - If any of the bundled files are not valid Luau
+*/
+pub fn strip_bundled_files(
+    files: &mut HashMap<String, Vec<u8>>,
+    cache: Option<&ModuleCache>,
+    workers: Workers,
+) -> Result<Throughput> {
+    let start = Instant::now();
+    let sources = files
+        .iter_mut()
+        .filter(|(bundle_path, _)| !is_data_file(bundle_path))
+        .collect::<Vec<_>>();
+    let count = sources.len();
+    let bytes = sources.iter().map(|(_, source)| source.len()).sum();
+    let threads = workers.count_for(count);
+
+    let results = workers.map(sources, |(bundle_path, source)| {
+        *source = compile_bytecode_cached(cache, bundle_path, std::mem::take(source))?;
+        Ok(())
+    });
+    results.into_iter().collect::<Result<()>>()?;
+
+    Ok(Throughput::since(start, count, bytes, threads))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use super::*;
+
+    #[test]
+    fn compiles_to_bytecode() {
+        let source = b"local value = 'secret text'\nreturn value".to_vec();
+        let bytecode = compile_bytecode("/main.luau", source).unwrap();
+        assert!(bytecode.first().is_some_and(|b| *b != 0));
+        assert!(!bytecode.starts_with(b"local"));
+    }
+
+    #[test]
+    fn compiles_with_shebang() {
+        let source = b"#!/usr/bin/env lune\nreturn 1".to_vec();
+        assert!(compile_bytecode("/main.luau", source).is_ok());
+    }
+
+    #[test]
+    fn invalid_source_errors() {
+        let error = compile_bytecode("/broken.luau", b"local = ".to_vec()).unwrap_err();
+        assert!(error.to_string().contains("/broken.luau"));
+    }
+
+    #[test]
+    fn data_files_are_kept() {
+        let mut files = HashMap::from([
+            ("/main.luau".to_string(), b"return 1".to_vec()),
+            ("/config.toml".to_string(), b"name = 'lune'".to_vec()),
+        ]);
+        strip_bundled_files(&mut files, None, Workers::new(None)).unwrap();
+        assert_eq!(files["/config.toml"], b"name = 'lune'");
+        assert_ne!(files["/main.luau"], b"return 1");
+    }
+
+    #[test]
+    fn files_are_compiled_in_parallel() {
+        let mut files = (0..32)
+            .map(|n| {
+                (
+                    format!("/module{n}.luau"),
+                    format!("return {n}").into_bytes(),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        let expected = files
+            .iter()
+            .map(|(path, source)| {
+                (
+                    path.clone(),
+                    compile_bytecode(path, source.clone()).unwrap(),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        let throughput =
+            strip_bundled_files(&mut files, None, Workers::new(NonZeroUsize::new(4))).unwrap();
+        assert_eq!(files, expected);
+        assert_eq!(throughput.files, 32);
+        assert_eq!(throughput.workers, 4);
+    }
+
+    #[test]
+    fn cached_bytecode_matches() {
+        let dir = std::env::temp_dir().join(format!("lune-strip-cache-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let cache = ModuleCache::new(&dir);
+
+        let source = b"#!/usr/bin/env lune\nreturn 1".to_vec();
+        let expected = compile_bytecode("/main.luau", source.clone()).unwrap();
+        let first = compile_bytecode_cached(Some(&cache), "/a.luau", source.clone()).unwrap();
+        let second = compile_bytecode_cached(Some(&cache), "/b.luau", source).unwrap();
+        assert_eq!(first, expected);
+        assert_eq!(second, expected);
+        assert_eq!(cache.hits(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
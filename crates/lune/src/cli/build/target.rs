@@ -1,8 +1,9 @@
 use std::{env::consts::ARCH, fmt, path::PathBuf, str::FromStr, sync::LazyLock};
 
 use directories::BaseDirs;
+use lune_utils::platform::Platform;
 
-static HOME_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
+pub static HOME_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
     BaseDirs::new()
         .expect("could not find home directory")
         .home_dir()
@@ -10,6 +11,11 @@ static HOME_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
 });
 
 pub static CACHE_DIR: LazyLock<PathBuf> = LazyLock::new(|| HOME_DIR.join(".lune").join("target"));
+pub static BUNDLE_CACHE_DIR: LazyLock<PathBuf> =
+    LazyLock::new(|| HOME_DIR.join(".lune").join("cache").join("bundle"));
+// Kept in ~/.lune/cache next to the other caches, instead of the XDG cache directory
+pub static MODULE_CACHE_DIR: LazyLock<PathBuf> =
+    LazyLock::new(|| HOME_DIR.join(".lune").join("cache").join("modules"));
 
 /**
     A target operating system supported by Lune
@@ -19,6 +25,7 @@ pub enum BuildTargetOS {
     Windows,
     Linux,
     MacOS,
+    Wasi,
 }
 
 impl BuildTargetOS {
@@ -27,6 +34,7 @@ impl BuildTargetOS {
             "windows" => Self::Windows,
             "linux" => Self::Linux,
             "macos" => Self::MacOS,
+            "wasi" => Self::Wasi,
             _ => panic!("unsupported target OS"),
         }
     }
@@ -36,6 +44,7 @@ impl BuildTargetOS {
         // they are only accessible for the current target
         match self {
             Self::Windows => "exe",
+            Self::Wasi => "wasm",
             _ => "",
         }
     }
@@ -43,9 +52,36 @@ impl BuildTargetOS {
     fn exe_suffix(self) -> &'static str {
         match self {
             Self::Windows => ".exe",
+            Self::Wasi => ".wasm",
             _ => "",
         }
     }
+
+    fn lib_extension(self) -> &'static str {
+        match self {
+            Self::Windows => "dll",
+            Self::Linux => "so",
+            Self::MacOS => "dylib",
+            Self::Wasi => "wasm",
+        }
+    }
+
+    fn lib_prefix(self) -> &'static str {
+        match self {
+            Self::Windows => "",
+            _ => "lib",
+        }
+    }
+
+    fn platform(self) -> Platform {
+        // NOTE: Same as for extensions, these match the constants from std
+        match self {
+            Self::Windows => Platform::new("windows", "windows"),
+            Self::Linux => Platform::new("linux", "unix"),
+            Self::MacOS => Platform::new("macos", "unix"),
+            Self::Wasi => Platform::new("wasi", "wasm"),
+        }
+    }
 }
 
 impl fmt::Display for BuildTargetOS {
@@ -54,6 +90,7 @@ impl fmt::Display for BuildTargetOS {
             Self::Windows => write!(f, "windows"),
             Self::Linux => write!(f, "linux"),
             Self::MacOS => write!(f, "macos"),
+            Self::Wasi => write!(f, "wasi"),
         }
     }
 }
@@ -65,6 +102,7 @@ impl FromStr for BuildTargetOS {
             "win" | "windows" => Ok(Self::Windows),
             "linux" => Ok(Self::Linux),
             "mac" | "macos" | "darwin" => Ok(Self::MacOS),
+            "wasi" => Ok(Self::Wasi),
             _ => Err("invalid target OS"),
         }
     }
@@ -72,11 +110,18 @@ impl FromStr for BuildTargetOS {
 
 /**
     A target architecture supported by Lune
+
+    The universal architecture is only supported for macOS, and
+    stands for a single binary that contains all other architectures.
+
+    The wasm32 architecture is only supported for WASI.
 */
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BuildTargetArch {
     X86_64,
     Aarch64,
+    Universal,
+    Wasm32,
 }
 
 impl BuildTargetArch {
@@ -84,6 +129,7 @@ impl BuildTargetArch {
         match ARCH {
             "x86_64" => Self::X86_64,
             "aarch64" => Self::Aarch64,
+            "wasm32" => Self::Wasm32,
             _ => panic!("unsupported target architecture"),
         }
     }
@@ -94,6 +140,8 @@ impl fmt::Display for BuildTargetArch {
         match self {
             Self::X86_64 => write!(f, "x86_64"),
             Self::Aarch64 => write!(f, "aarch64"),
+            Self::Universal => write!(f, "universal"),
+            Self::Wasm32 => write!(f, "wasm32"),
         }
     }
 }
@@ -104,30 +152,82 @@ impl FromStr for BuildTargetArch {
         match s.trim().to_ascii_lowercase().as_str() {
             "x86_64" | "x64" => Ok(Self::X86_64),
             "aarch64" | "arm64" => Ok(Self::Aarch64),
+            "universal" => Ok(Self::Universal),
+            "wasm" | "wasm32" => Ok(Self::Wasm32),
             _ => Err("invalid target architecture"),
         }
     }
 }
 
 /**
-    A full target description that Lune supports (OS + Arch)
+    A target C library supported by Lune, only used for Linux targets
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BuildTargetLibc {
+    #[default]
+    Gnu,
+    Musl,
+}
+
+impl BuildTargetLibc {
+    fn current_system() -> Self {
+        if cfg!(target_env = "musl") {
+            Self::Musl
+        } else {
+            Self::Gnu
+        }
+    }
+
+    fn target_suffix(self) -> &'static str {
+        // NOTE: Gnu is the default, and has no suffix, to keep
+        // target names and base executable names backwards compatible
+        match self {
+            Self::Gnu => "",
+            Self::Musl => "-musl",
+        }
+    }
+}
+
+impl FromStr for BuildTargetLibc {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "gnu" | "glibc" => Ok(Self::Gnu),
+            "musl" => Ok(Self::Musl),
+            _ => Err("invalid target libc"),
+        }
+    }
+}
+
+/**
+    A full target description that Lune supports (OS + Arch + Libc)
 
     This is used to determine the target to build for standalone binaries,
     and to download the correct base executable for cross-compilation.
 
-    The target may be parsed from and displayed in the form `os-arch`.
-    Examples of valid targets are:
+    The target may be parsed from and displayed in the form `os-arch`, or
+    `os-arch-libc` for Linux targets that do not use glibc, such as the
+    ones for Alpine containers. Examples of valid targets are:
 
     - `linux-aarch64`
+    - `linux-aarch64-musl`
     - `linux-x86_64`
+    - `linux-x86_64-musl`
     - `macos-aarch64`
     - `macos-x86_64`
+    - `macos-universal`
     - `windows-x86_64`
+    - `wasm32-wasi`
+
+    WASI targets are the exception to the `os-arch` form, and are displayed
+    as `wasm32-wasi` to match Rust target names, but may also be given as
+    `wasm-wasi` or `wasi-wasm32`.
 */
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BuildTarget {
     pub os: BuildTargetOS,
     pub arch: BuildTargetArch,
+    pub libc: BuildTargetLibc,
 }
 
 impl BuildTarget {
@@ -135,11 +235,12 @@ impl BuildTarget {
         Self {
             os: BuildTargetOS::current_system(),
             arch: BuildTargetArch::current_system(),
+            libc: BuildTargetLibc::current_system(),
         }
     }
 
     pub fn is_current_system(&self) -> bool {
-        self.os == BuildTargetOS::current_system() && self.arch == BuildTargetArch::current_system()
+        *self == Self::current_system()
     }
 
     pub fn exe_extension(&self) -> &'static str {
@@ -150,27 +251,131 @@ impl BuildTarget {
         self.os.exe_suffix()
     }
 
+    /**
+        Returns the platform of this target, as used by `require.platform`.
+    */
+    pub fn platform(&self) -> Platform {
+        self.os.platform()
+    }
+
+    /**
+        Returns the file name of a shared library with the given name for
+        this target, such as `libname.so` on Linux and `name.dll` on Windows.
+    */
+    pub fn lib_file_name(&self, name: &str) -> String {
+        format!("{}{name}.{}", self.os.lib_prefix(), self.os.lib_extension())
+    }
+
+    pub fn is_universal(&self) -> bool {
+        self.arch == BuildTargetArch::Universal
+    }
+
+    pub fn is_wasi(&self) -> bool {
+        self.os == BuildTargetOS::Wasi
+    }
+
+    /**
+        Returns the targets for each of the binaries that make up a
+        binary for this target - one for each architecture for universal
+        targets, and only this target itself for all others.
+    */
+    pub fn slices(&self) -> Vec<Self> {
+        if self.is_universal() {
+            [BuildTargetArch::X86_64, BuildTargetArch::Aarch64]
+                .into_iter()
+                .map(|arch| Self {
+                    arch,
+                    ..self.clone()
+                })
+                .collect()
+        } else {
+            vec![self.clone()]
+        }
+    }
+
     pub fn cache_path(&self) -> PathBuf {
         CACHE_DIR.join(format!("{self}{}", self.os.exe_extension()))
     }
+
+    /**
+        Returns the path that a base executable with only the standard
+        libraries with the given label is cached at, for `--base-features`.
+    */
+    pub fn trimmed_cache_path(&self, label: &str) -> PathBuf {
+        CACHE_DIR.join(format!("{self}-{label}{}", self.os.exe_suffix()))
+    }
+
+    /**
+        Returns the Rust target triple for this target, such as `x86_64-unknown-linux-gnu`.
+
+        Universal targets have no triple of their own, and must be built per slice.
+    */
+    pub fn rust_triple(&self) -> &'static str {
+        match (self.os, self.arch, self.libc) {
+            (BuildTargetOS::Windows, BuildTargetArch::Aarch64, _) => "aarch64-pc-windows-msvc",
+            (BuildTargetOS::Windows, _, _) => "x86_64-pc-windows-msvc",
+            (BuildTargetOS::Linux, BuildTargetArch::Aarch64, BuildTargetLibc::Gnu) => {
+                "aarch64-unknown-linux-gnu"
+            }
+            (BuildTargetOS::Linux, BuildTargetArch::Aarch64, BuildTargetLibc::Musl) => {
+                "aarch64-unknown-linux-musl"
+            }
+            (BuildTargetOS::Linux, _, BuildTargetLibc::Gnu) => "x86_64-unknown-linux-gnu",
+            (BuildTargetOS::Linux, _, BuildTargetLibc::Musl) => "x86_64-unknown-linux-musl",
+            (BuildTargetOS::MacOS, BuildTargetArch::Aarch64, _) => "aarch64-apple-darwin",
+            (BuildTargetOS::MacOS, _, _) => "x86_64-apple-darwin",
+            (BuildTargetOS::Wasi, _, _) => "wasm32-wasip1",
+        }
+    }
+
+    pub fn lib_cache_path(&self) -> PathBuf {
+        CACHE_DIR.join(format!("{self}-lib.{}", self.os.lib_extension()))
+    }
 }
 
 impl fmt::Display for BuildTarget {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}-{}", self.os, self.arch)
+        if self.is_wasi() {
+            return write!(f, "{}-{}", self.arch, self.os);
+        }
+        write!(f, "{}-{}{}", self.os, self.arch, self.libc.target_suffix())
     }
 }
 
 impl FromStr for BuildTarget {
     type Err = &'static str;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (left, right) = s
-            .split_once('-')
-            .ok_or("target must be in the form `os-arch`")?;
+        let mut parts = s.split('-');
+        let (Some(os), Some(arch)) = (parts.next(), parts.next()) else {
+            return Err("target must be in the form `os-arch` or `os-arch-libc`");
+        };
+        // WASI targets are usually given in the form `arch-os`, like in Rust target names
+        let (os, arch) = if arch.trim().eq_ignore_ascii_case("wasi") {
+            (arch, os)
+        } else {
+            (os, arch)
+        };
+        let libc = parts.next();
+        if parts.next().is_some() {
+            return Err("target must be in the form `os-arch` or `os-arch-libc`");
+        }
 
-        let os = left.parse()?;
-        let arch = right.parse()?;
+        let os = os.parse()?;
+        let arch = arch.parse()?;
+        let libc = match libc {
+            None => BuildTargetLibc::Gnu,
+            Some(libc) => libc.parse()?,
+        };
+        if libc == BuildTargetLibc::Musl && os != BuildTargetOS::Linux {
+            return Err("musl targets are only supported for linux");
+        }
+        if arch == BuildTargetArch::Universal && os != BuildTargetOS::MacOS {
+            return Err("universal targets are only supported for macos");
+        }
+        if (arch == BuildTargetArch::Wasm32) != (os == BuildTargetOS::Wasi) {
+            return Err("wasm32 targets are only supported for wasi, and wasi only for wasm32");
+        }
 
-        Ok(Self { os, arch })
+        Ok(Self { os, arch, libc })
     }
 }
@@ -0,0 +1,153 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use anyhow::{Result, bail};
+use lune_bundler::strip_shebang;
+use lune_utils::path::is_data_file;
+use mlua::{Compiler, Error as LuaError};
+
+use super::workers::{Throughput, Workers};
+
+/**
+    Compiles every bundled file with the Luau compiler, using the given workers,
+    failing with diagnostics for all files that contain syntax errors.
+
+    Data files, such as `config.json`, are not Luau and are skipped.
+
+    Paths in diagnostics are the original paths of the files, relative
+    to the given root directory, or absolute if outside of the root.
+
+    # Errors
+
+    - If any of the bundled files are not valid Luau
+*/
+pub fn validate_bundled_files(
+    files: &HashMap<String, Vec<u8>>,
+    sources: &HashMap<String, PathBuf>,
+    root: &Path,
+    workers: Workers,
+) -> Result<Throughput> {
+    let start = Instant::now();
+    let luau_files = files
+        .iter()
+        .filter(|(bundle_path, _)| !is_data_file(bundle_path))
+        .collect::<Vec<_>>();
+    let count = luau_files.len();
+    let bytes = luau_files.iter().map(|(_, source)| source.len()).sum();
+    let threads = workers.count_for(count);
+
+    let mut diagnostics = workers
+        .map(luau_files, |(bundle_path, source)| {
+            let error = Compiler::new()
+                .compile(strip_shebang(source.clone()))
+                .err()?;
+            let path = sources
+                .get(bundle_path)
+                .map_or_else(|| bundle_path.clone(), |path| display_path(path, root));
+            Some(format_diagnostic(&path, &error))
+        })
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+    if diagnostics.is_empty() {
+        return Ok(Throughput::since(start, count, bytes, threads));
+    }
+
+    diagnostics.sort();
+    bail!(
+        "found syntax errors in {} bundled file{}:\n{}",
+        diagnostics.len(),
+        if diagnostics.len() == 1 { "" } else { "s" },
+        diagnostics.join("\n")
+    );
+}
+
+fn display_path(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+        .replace('\\', "/")
+}
+
+fn format_diagnostic(path: &str, error: &LuaError) -> String {
+    let message = match error {
+        LuaError::SyntaxError { message, .. } => message.trim().to_string(),
+        other => other.to_string(),
+    };
+    // NOTE: Luau compile errors are in the form `:line: message`,
+    // without a chunk name, so the path is prepended directly
+    if message.starts_with(':') {
+        format!("  {path}{message}")
+    } else {
+        format!("  {path}: {message}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle(files: &[(&str, &str)]) -> (HashMap<String, Vec<u8>>, HashMap<String, PathBuf>) {
+        let sources = files
+            .iter()
+            .map(|(path, _)| {
+                (
+                    (*path).to_string(),
+                    PathBuf::from(format!("/project{path}")),
+                )
+            })
+            .collect();
+        let files = files
+            .iter()
+            .map(|(path, source)| ((*path).to_string(), source.as_bytes().to_vec()))
+            .collect();
+        (files, sources)
+    }
+
+    #[test]
+    fn valid_files_pass() {
+        let (files, sources) = bundle(&[
+            (
+                "/main.luau",
+                "#!/usr/bin/env lune\nreturn require(\"./lib\")",
+            ),
+            ("/lib.luau", "return 1"),
+        ]);
+        assert!(
+            validate_bundled_files(&files, &sources, Path::new("/project"), Workers::new(None))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn invalid_files_have_diagnostics() {
+        let (files, sources) = bundle(&[
+            ("/main.luau", "return 1"),
+            ("/deps/broken.luau", "local x = 1\nlocal = 2"),
+        ]);
+        let error =
+            validate_bundled_files(&files, &sources, Path::new("/project"), Workers::new(None))
+                .unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("1 bundled file:"));
+        assert!(message.contains("deps/broken.luau:2:"), "{message}");
+        assert!(!message.contains("main.luau"));
+    }
+
+    #[test]
+    fn data_files_are_skipped() {
+        let (files, sources) = bundle(&[
+            ("/main.luau", "return require(\"./config.json\")"),
+            ("/config.json", "{ \"name\": \"lune\" }"),
+        ]);
+        assert!(
+            validate_bundled_files(&files, &sources, Path::new("/project"), Workers::new(None))
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,443 @@
+/*!
+    Embedding of the standalone payload into WASI modules.
+
+    A WASI module can not read its own file at runtime, and a WebAssembly module
+    can not have any extra data appended to it, so the payload is instead added
+    to the base module as a new passive data segment. The bodies of the exported
+    `lune_payload_len` and `lune_payload_copy` functions of the base module are
+    then replaced, so that they return the length of the segment, and copy the
+    segment into memory, which is how the standalone runtime finds the payload.
+
+    Replacing function bodies keeps all function indices intact, meaning that
+    no other part of the module needs to be rewritten, except for the data
+    count section, which must match the number of data segments.
+*/
+
+use anyhow::{Context, Result, bail};
+
+const WASM_MAGIC: &[u8; 4] = b"\0asm";
+const WASM_VERSION: &[u8; 4] = &[1, 0, 0, 0];
+
+const SECTION_IMPORT: u8 = 2;
+const SECTION_EXPORT: u8 = 7;
+const SECTION_CODE: u8 = 10;
+const SECTION_DATA: u8 = 11;
+const SECTION_DATA_COUNT: u8 = 12;
+
+const EXPORT_PAYLOAD_LEN: &str = "lune_payload_len";
+const EXPORT_PAYLOAD_COPY: &str = "lune_payload_copy";
+
+/**
+    Embeds the given payload into the given base WASI module, returning the new module.
+
+    # Errors
+
+    - If the base module is not a valid WebAssembly module
+    - If the base module does not export the payload functions
+    - If the payload is larger than 2 GiB
+*/
+pub fn embed_payload(base_module: &[u8], payload: &[u8]) -> Result<Vec<u8>> {
+    if base_module.get(0..4) != Some(WASM_MAGIC) || base_module.get(4..8) != Some(WASM_VERSION) {
+        bail!("base executable is not a WebAssembly module");
+    }
+    let payload_len = i32::try_from(payload.len())
+        .context("payload is too large to embed into a WebAssembly module")?;
+
+    let sections = read_sections(&base_module[8..])?;
+
+    // Find the functions to replace, which are indexed after all imported functions
+    let imported_funcs = match find_section(&sections, SECTION_IMPORT) {
+        Some(contents) => count_imported_funcs(contents)?,
+        None => 0,
+    };
+    let exports = find_section(&sections, SECTION_EXPORT)
+        .context("base module does not export any functions")?;
+    let find_func = |name: &str| -> Result<usize> {
+        let index = find_exported_func(exports, name)?
+            .with_context(|| format!("base module does not export '{name}'"))?;
+        index
+            .checked_sub(imported_funcs)
+            .with_context(|| format!("'{name}' is an imported function in the base module"))
+    };
+    let len_func = find_func(EXPORT_PAYLOAD_LEN)?;
+    let copy_func = find_func(EXPORT_PAYLOAD_COPY)?;
+
+    // The new passive data segment goes after all existing ones
+    let segment_count = match find_section(&sections, SECTION_DATA) {
+        Some(contents) => Reader::new(contents).u32()?,
+        None => 0,
+    };
+
+    let mut len_body = vec![0x00]; // No locals
+    len_body.push(0x41); // i32.const
+    write_i32(&mut len_body, payload_len);
+    len_body.push(0x0b); // end
+
+    let mut copy_body = vec![0x00]; // No locals
+    copy_body.extend_from_slice(&[0x20, 0x00]); // local.get 0 (destination)
+    copy_body.extend_from_slice(&[0x41, 0x00]); // i32.const 0 (offset in segment)
+    copy_body.push(0x41); // i32.const
+    write_i32(&mut copy_body, payload_len); // (length of segment)
+    copy_body.extend_from_slice(&[0xfc, 0x08]); // memory.init
+    write_u32(&mut copy_body, segment_count);
+    copy_body.push(0x00); // (memory 0)
+    copy_body.push(0x0b); // end
+
+    let mut module = Vec::with_capacity(base_module.len() + payload.len() + 64);
+    module.extend_from_slice(WASM_MAGIC);
+    module.extend_from_slice(WASM_VERSION);
+
+    let mut wrote_data = false;
+    for (id, contents) in &sections {
+        match *id {
+            // Dropped here, and written again right before the code section
+            SECTION_DATA_COUNT => {}
+            SECTION_CODE => {
+                let mut count = Vec::new();
+                write_u32(&mut count, segment_count + 1);
+                write_section(&mut module, SECTION_DATA_COUNT, &count);
+                let code = replace_func_bodies(
+                    contents,
+                    &[
+                        (len_func, len_body.as_slice()),
+                        (copy_func, copy_body.as_slice()),
+                    ],
+                )?;
+                write_section(&mut module, SECTION_CODE, &code);
+            }
+            SECTION_DATA => {
+                let data = append_passive_segment(contents, payload)?;
+                write_section(&mut module, SECTION_DATA, &data);
+                wrote_data = true;
+            }
+            _ => write_section(&mut module, *id, contents),
+        }
+    }
+    if !wrote_data {
+        let mut data = Vec::new();
+        write_u32(&mut data, 1);
+        write_passive_segment(&mut data, payload);
+        // NOTE: Custom sections may come after the data section, but
+        // the code section must have come before, since we found it
+        write_section(&mut module, SECTION_DATA, &data);
+    }
+
+    Ok(module)
+}
+
+fn read_sections(mut bytes: &[u8]) -> Result<Vec<(u8, &[u8])>> {
+    let mut sections = Vec::new();
+    while !bytes.is_empty() {
+        let mut reader = Reader::new(bytes);
+        let id = reader.byte()?;
+        let size = reader.u32()? as usize;
+        let contents = reader.take(size)?;
+        sections.push((id, contents));
+        bytes = reader.rest();
+    }
+    if !sections.iter().any(|(id, _)| *id == SECTION_CODE) {
+        bail!("base module has no code section");
+    }
+    Ok(sections)
+}
+
+fn find_section<'a>(sections: &[(u8, &'a [u8])], id: u8) -> Option<&'a [u8]> {
+    sections
+        .iter()
+        .find(|(section_id, _)| *section_id == id)
+        .map(|(_, contents)| *contents)
+}
+
+fn count_imported_funcs(contents: &[u8]) -> Result<usize> {
+    let mut reader = Reader::new(contents);
+    let mut funcs = 0;
+    for _ in 0..reader.u32()? {
+        reader.name()?; // Module
+        reader.name()?; // Field
+        match reader.byte()? {
+            0x00 => {
+                reader.u32()?; // Type index
+                funcs += 1;
+            }
+            0x01 => {
+                reader.byte()?; // Reference type
+                reader.limits()?;
+            }
+            0x02 => reader.limits()?,
+            0x03 => {
+                reader.byte()?; // Value type
+                reader.byte()?; // Mutability
+            }
+            0x04 => {
+                reader.byte()?; // Attribute
+                reader.u32()?; // Type index
+            }
+            kind => bail!("base module has an import of unknown kind {kind:#x}"),
+        }
+    }
+    Ok(funcs)
+}
+
+fn find_exported_func(contents: &[u8], name: &str) -> Result<Option<usize>> {
+    let mut reader = Reader::new(contents);
+    for _ in 0..reader.u32()? {
+        let export_name = reader.name()?;
+        let kind = reader.byte()?;
+        let index = reader.u32()? as usize;
+        if kind == 0x00 && export_name == name.as_bytes() {
+            return Ok(Some(index));
+        }
+    }
+    Ok(None)
+}
+
+fn replace_func_bodies(contents: &[u8], replacements: &[(usize, &[u8])]) -> Result<Vec<u8>> {
+    let mut reader = Reader::new(contents);
+    let count = reader.u32()?;
+    let mut code = Vec::with_capacity(contents.len());
+    write_u32(&mut code, count);
+    for index in 0..count as usize {
+        let size = reader.u32()? as usize;
+        let body = reader.take(size)?;
+        let body = replacements
+            .iter()
+            .find(|(i, _)| *i == index)
+            .map_or(body, |(_, replacement)| *replacement);
+        write_u32(&mut code, u32::try_from(body.len())?);
+        code.extend_from_slice(body);
+    }
+    if replacements.iter().any(|(i, _)| *i >= count as usize) {
+        bail!("base module has fewer function bodies than functions");
+    }
+    Ok(code)
+}
+
+fn append_passive_segment(contents: &[u8], payload: &[u8]) -> Result<Vec<u8>> {
+    let mut reader = Reader::new(contents);
+    let count = reader.u32()?;
+    let mut data = Vec::with_capacity(contents.len() + payload.len() + 16);
+    write_u32(&mut data, count + 1);
+    data.extend_from_slice(reader.rest());
+    write_passive_segment(&mut data, payload);
+    Ok(data)
+}
+
+fn write_passive_segment(data: &mut Vec<u8>, payload: &[u8]) {
+    data.push(0x01); // Passive
+    write_u32(data, payload.len() as u32);
+    data.extend_from_slice(payload);
+}
+
+fn write_section(module: &mut Vec<u8>, id: u8, contents: &[u8]) {
+    module.push(id);
+    write_u32(module, contents.len() as u32);
+    module.extend_from_slice(contents);
+}
+
+fn write_u32(buf: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf.push(byte);
+            return;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+fn write_i32(buf: &mut Vec<u8>, mut value: i32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        let done = (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0);
+        if done {
+            buf.push(byte);
+            return;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, offset: 0 }
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.bytes[self.offset..]
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        let byte = *self
+            .bytes
+            .get(self.offset)
+            .context("unexpected end of WebAssembly module")?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .context("unexpected end of WebAssembly module")?;
+        let bytes = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        bail!("invalid integer in WebAssembly module")
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        u32::try_from(self.u64()?).context("invalid integer in WebAssembly module")
+    }
+
+    fn name(&mut self) -> Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    fn limits(&mut self) -> Result<()> {
+        let flags = self.byte()?;
+        self.u64()?; // Minimum
+        if flags & 0x01 != 0 {
+            self.u64()?; // Maximum
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A module that imports one function, and exports the
+    /// two payload functions, along with a single data segment
+    fn base_module() -> Vec<u8> {
+        let mut module = Vec::new();
+        module.extend_from_slice(WASM_MAGIC);
+        module.extend_from_slice(WASM_VERSION);
+
+        // Types: () -> i32, (i32) -> ()
+        write_section(&mut module, 1, &[2, 0x60, 0, 1, 0x7f, 0x60, 1, 0x7f, 0]);
+        // Imports: env.f as type 1
+        write_section(
+            &mut module,
+            SECTION_IMPORT,
+            &[1, 3, b'e', b'n', b'v', 1, b'f', 0x00, 1],
+        );
+        // Functions: two, of types 0 and 1
+        write_section(&mut module, 3, &[2, 0, 1]);
+        // Memory: one page
+        write_section(&mut module, 5, &[1, 0x00, 1]);
+        // Exports: both payload functions, after the imported function
+        let mut exports = vec![2];
+        for (name, index) in [(EXPORT_PAYLOAD_LEN, 1), (EXPORT_PAYLOAD_COPY, 2)] {
+            exports.push(name.len() as u8);
+            exports.extend_from_slice(name.as_bytes());
+            exports.extend_from_slice(&[0x00, index]);
+        }
+        write_section(&mut module, SECTION_EXPORT, &exports);
+        // Code: return 0, and do nothing
+        write_section(
+            &mut module,
+            SECTION_CODE,
+            &[2, 4, 0, 0x41, 0, 0x0b, 2, 0, 0x0b],
+        );
+        // Data: one active segment at offset 0
+        write_section(
+            &mut module,
+            SECTION_DATA,
+            &[1, 0x00, 0x41, 0, 0x0b, 2, b'h', b'i'],
+        );
+        module
+    }
+
+    #[test]
+    fn leb128_integers() {
+        let mut buf = Vec::new();
+        write_u32(&mut buf, 624_485);
+        assert_eq!(buf, [0xe5, 0x8e, 0x26]);
+        assert_eq!(Reader::new(&buf).u32().unwrap(), 624_485);
+
+        let mut buf = Vec::new();
+        write_i32(&mut buf, 64);
+        assert_eq!(buf, [0xc0, 0x00]);
+        let mut buf = Vec::new();
+        write_i32(&mut buf, -123_456);
+        assert_eq!(buf, [0xc0, 0xbb, 0x78]);
+    }
+
+    #[test]
+    fn embeds_payload() {
+        let payload = b"payload bytes";
+        let module = embed_payload(&base_module(), payload).unwrap();
+        let sections = read_sections(&module[8..]).unwrap();
+
+        // The data count section must come right before the code section
+        let ids = sections.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        assert_eq!(ids, [1, 2, 3, 5, 7, 12, 10, 11]);
+        assert_eq!(
+            find_section(&sections, SECTION_DATA_COUNT),
+            Some(&[2u8][..])
+        );
+
+        // Function bodies are replaced, while the count stays the same
+        let code = find_section(&sections, SECTION_CODE).unwrap();
+        assert_eq!(&code[..3], &[2, 4, 0]);
+        assert_eq!(&code[3..6], &[0x41, payload.len() as u8, 0x0b]);
+        assert_eq!(
+            &code[6..],
+            &[
+                12,
+                0,
+                0x20,
+                0,
+                0x41,
+                0,
+                0x41,
+                payload.len() as u8,
+                0xfc,
+                0x08,
+                1,
+                0,
+                0x0b
+            ]
+        );
+
+        // The payload is appended as a passive segment
+        let data = find_section(&sections, SECTION_DATA).unwrap();
+        assert_eq!(data[0], 2);
+        assert!(data.ends_with(payload));
+        assert_eq!(data[data.len() - payload.len() - 2], 0x01);
+    }
+
+    #[test]
+    fn rejects_modules_without_payload_functions() {
+        let mut module = base_module();
+        let position = module
+            .windows(EXPORT_PAYLOAD_COPY.len())
+            .position(|w| w == EXPORT_PAYLOAD_COPY.as_bytes())
+            .unwrap();
+        module[position] = b'x';
+        assert!(embed_payload(&module, b"payload").is_err());
+        assert!(embed_payload(b"not a module", b"payload").is_err());
+    }
+}
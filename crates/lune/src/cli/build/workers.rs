@@ -0,0 +1,220 @@
+/*!
+    Processing of bundled files across a bounded pool of worker threads.
+
+    Validating and compiling bundled files is independent for every file, and
+    takes most of the time of a build for projects with thousands of modules,
+    so files are processed by as many threads as there are CPUs, unless limited
+    using `--jobs`. Workers take files from a shared queue one at a time, so that
+    a few large files, such as embedded assets, do not leave other workers idle.
+
+    Note that the checksum and encryption of the standalone payload are not done by
+    workers, since each of them is a single hash or seal over the whole payload, which
+    can not be split across threads without changing the format of standalone binaries.
+*/
+
+use std::{
+    fmt,
+    num::NonZeroUsize,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::report::format_size;
+
+/**
+    A bounded pool of worker threads, which are started for each batch of work.
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct Workers {
+    limit: NonZeroUsize,
+}
+
+impl Workers {
+    /**
+        Creates a pool of at most the given number of workers,
+        or of as many workers as there are CPUs if not given.
+    */
+    pub fn new(limit: Option<NonZeroUsize>) -> Self {
+        let limit = limit
+            .or_else(|| thread::available_parallelism().ok())
+            .unwrap_or(NonZeroUsize::MIN);
+        Self { limit }
+    }
+
+    /**
+        Returns the number of workers used for the given number of items.
+    */
+    pub fn count_for(self, items: usize) -> usize {
+        self.limit.get().min(items).max(1)
+    }
+
+    /**
+        Calls the given function for every item, across all workers,
+        and returns the results in the same order as the items.
+
+        Work is done on the current thread if there is only one worker.
+    */
+    pub fn map<T, R>(self, items: Vec<T>, f: impl Fn(T) -> R + Sync) -> Vec<R>
+    where
+        T: Send,
+        R: Send,
+    {
+        let count = self.count_for(items.len());
+        if count == 1 {
+            return items.into_iter().map(f).collect();
+        }
+
+        let len = items.len();
+        let queue = Mutex::new(items.into_iter().enumerate());
+        let next = || queue.lock().expect("worker panicked").next();
+
+        let mut results = thread::scope(|scope| {
+            let handles = (0..count)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut done = Vec::new();
+                        while let Some((index, item)) = next() {
+                            done.push((index, f(item)));
+                        }
+                        done
+                    })
+                })
+                .collect::<Vec<_>>();
+            let mut results = Vec::with_capacity(len);
+            for handle in handles {
+                match handle.join() {
+                    Ok(done) => results.extend(done),
+                    Err(panic) => std::panic::resume_unwind(panic),
+                }
+            }
+            results
+        });
+
+        results.sort_unstable_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+}
+
+/**
+    The amount of files and bytes processed by workers, and how long it took.
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct Throughput {
+    pub files: usize,
+    pub bytes: usize,
+    pub workers: usize,
+    pub elapsed: Duration,
+}
+
+impl Throughput {
+    /**
+        Measures processing the given number of files and bytes, starting at the given instant.
+    */
+    pub fn since(start: Instant, files: usize, bytes: usize, workers: usize) -> Self {
+        Self {
+            files,
+            bytes,
+            workers,
+            elapsed: start.elapsed(),
+        }
+    }
+
+    /**
+        Combines the throughput of two steps that ran one after another,
+        such as validating and then compiling the same files.
+    */
+    #[must_use]
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            files: self.files + other.files,
+            bytes: self.bytes + other.bytes,
+            workers: self.workers.max(other.workers),
+            elapsed: self.elapsed + other.elapsed,
+        }
+    }
+
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    fn bytes_per_second(&self) -> usize {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            (self.bytes as f64 / secs) as usize
+        } else {
+            self.bytes
+        }
+    }
+}
+
+impl fmt::Display for Throughput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} files, {} in {}ms on {} thread{} ({}/s)",
+            self.files,
+            format_size(self.bytes),
+            self.elapsed.as_millis(),
+            self.workers,
+            if self.workers == 1 { "" } else { "s" },
+            format_size(self.bytes_per_second()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_keep_order() {
+        let workers = Workers::new(NonZeroUsize::new(4));
+        let items = (0..100).collect::<Vec<u32>>();
+        let results = workers.map(items, |n| n * 2);
+        assert_eq!(results, (0..100).map(|n| n * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn workers_are_bounded() {
+        let workers = Workers::new(NonZeroUsize::new(3));
+        assert_eq!(workers.count_for(100), 3);
+        assert_eq!(workers.count_for(2), 2);
+        assert_eq!(workers.count_for(0), 1);
+    }
+
+    #[test]
+    fn throughput_is_displayed() {
+        let throughput = Throughput {
+            files: 2,
+            bytes: 4096,
+            workers: 1,
+            elapsed: Duration::from_secs(2),
+        };
+        assert_eq!(
+            throughput.to_string(),
+            "2 files, 4.0 KiB in 2000ms on 1 thread (2.0 KiB/s)"
+        );
+    }
+
+    #[test]
+    fn throughput_is_merged() {
+        let validated = Throughput {
+            files: 2,
+            bytes: 2048,
+            workers: 2,
+            elapsed: Duration::from_secs(1),
+        };
+        let compiled = Throughput {
+            files: 2,
+            bytes: 2048,
+            workers: 1,
+            elapsed: Duration::from_secs(1),
+        };
+        assert_eq!(
+            validated.merge(compiled).to_string(),
+            "4 files, 4.0 KiB in 2000ms on 2 threads (2.0 KiB/s)"
+        );
+    }
+}
@@ -0,0 +1,201 @@
+/*!
+    Rich display of values in notebooks, as MIME bundles.
+
+    Values are displayed as the formats that suit them best - all values as
+    `text/plain`, tables also as `application/json`, and buffers containing
+    PNG, JPEG or GIF images as those images. Scripts may also give a MIME
+    type explicitly, such as to display HTML from a string.
+*/
+
+use lune_utils::fmt::{ValueFormatConfig, pretty_format_value};
+use mlua::prelude::*;
+use serde_json::{Map, Number, Value};
+
+// Displayed values are not written to a terminal, so they should not be colored
+const FORMAT_CONFIG: ValueFormatConfig = ValueFormatConfig::new()
+    .with_max_depth(4)
+    .with_colors_enabled(false);
+
+/// Tables nested deeper than this are assumed to contain cycles
+const MAX_JSON_DEPTH: usize = 64;
+
+const IMAGE_SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xFF\xD8\xFF", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+];
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/**
+    Creates a MIME bundle for displaying the given value, either as the given
+    MIME type, or as all of the MIME types that suit the value if none is given.
+
+    # Errors
+
+    - If the value can not be displayed as the given MIME type
+*/
+pub fn mime_bundle(value: &LuaValue, mime: Option<&str>) -> LuaResult<Map<String, Value>> {
+    let mut bundle = Map::new();
+    match mime {
+        Some(mime) => {
+            bundle.insert(mime.to_string(), mime_data(value, mime)?);
+        }
+        None => {
+            if let LuaValue::Buffer(buffer) = value {
+                let bytes = buffer.to_vec();
+                let image = IMAGE_SIGNATURES
+                    .iter()
+                    .find(|(signature, _)| bytes.starts_with(signature));
+                if let Some((_, mime)) = image {
+                    bundle.insert((*mime).to_string(), Value::String(encode_base64(&bytes)));
+                }
+            }
+            if let LuaValue::Table(_) = value
+                && let Ok(json) = to_json(value, 0)
+            {
+                bundle.insert("application/json".to_string(), json);
+            }
+            let text = match value {
+                LuaValue::String(s) => s.to_string_lossy(),
+                _ => pretty_format_value(value, &FORMAT_CONFIG),
+            };
+            bundle.insert("text/plain".to_string(), Value::String(text));
+        }
+    }
+    Ok(bundle)
+}
+
+fn mime_data(value: &LuaValue, mime: &str) -> LuaResult<Value> {
+    let is_json = mime == "application/json" || mime.ends_with("+json");
+    let is_text = mime.starts_with("text/") || mime.ends_with("+xml");
+
+    let bytes = match value {
+        LuaValue::String(s) => s.as_bytes().to_vec(),
+        LuaValue::Buffer(buffer) => buffer.to_vec(),
+        LuaValue::Table(_) if is_json => return to_json(value, 0),
+        _ => {
+            return Err(LuaError::runtime(format!(
+                "cannot display a value of type '{}' as '{mime}', expected a string or buffer",
+                value.type_name()
+            )));
+        }
+    };
+
+    Ok(if is_json {
+        serde_json::from_slice(&bytes)
+            .map_err(|e| LuaError::runtime(format!("invalid JSON to display: {e}")))?
+    } else if is_text {
+        Value::String(String::from_utf8_lossy(&bytes).into_owned())
+    } else {
+        Value::String(encode_base64(&bytes))
+    })
+}
+
+fn to_json(value: &LuaValue, depth: usize) -> LuaResult<Value> {
+    if depth > MAX_JSON_DEPTH {
+        return Err(LuaError::runtime(
+            "table is nested too deeply, or is cyclic",
+        ));
+    }
+    Ok(match value {
+        LuaValue::Nil => Value::Null,
+        LuaValue::Boolean(b) => Value::Bool(*b),
+        LuaValue::Integer(i) => Value::Number((*i).into()),
+        // NOTE: Luau only has floats, but integers should be displayed without a fraction
+        LuaValue::Number(n) if n.fract() == 0.0 && n.abs() < 2f64.powi(53) => {
+            Value::Number((*n as i64).into())
+        }
+        LuaValue::Number(n) => Number::from_f64(*n).map_or(Value::Null, Value::Number),
+        LuaValue::String(s) => Value::String(s.to_string_lossy()),
+        LuaValue::Table(table) => {
+            let pairs = table
+                .pairs::<LuaValue, LuaValue>()
+                .collect::<LuaResult<Vec<_>>>()?;
+            let len = table.raw_len();
+            if len > 0 && pairs.len() == len {
+                let values = (1..=len)
+                    .map(|index| to_json(&table.raw_get(index)?, depth + 1))
+                    .collect::<LuaResult<Vec<_>>>()?;
+                Value::Array(values)
+            } else {
+                let mut object = Map::new();
+                for (key, value) in pairs {
+                    let key = match key {
+                        LuaValue::String(s) => s.to_string_lossy(),
+                        other => pretty_format_value(&other, &FORMAT_CONFIG),
+                    };
+                    object.insert(key, to_json(&value, depth + 1)?);
+                }
+                Value::Object(object)
+            }
+        }
+        other => Value::String(pretty_format_value(other, &FORMAT_CONFIG)),
+    })
+}
+
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut output = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let buffer = chunk.iter().enumerate().fold(0u32, |buffer, (i, &byte)| {
+            buffer | (u32::from(byte) << (16 - i * 8))
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (buffer >> (18 - i * 6)) & 0x3F;
+                output.push(char::from(BASE64_ALPHABET[index as usize]));
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_base64() {
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"f"), "Zg==");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert_eq!(encode_base64(b"foo"), "Zm9v");
+        assert_eq!(encode_base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn tables_display_as_json() {
+        let lua = Lua::new();
+        let value = lua
+            .load("{ name = \"lune\", list = { 1, 2, 3 } }")
+            .eval::<LuaValue>()
+            .unwrap();
+        let bundle = mime_bundle(&value, None).unwrap();
+        assert_eq!(
+            bundle["application/json"],
+            serde_json::json!({ "name": "lune", "list": [1, 2, 3] })
+        );
+        assert!(bundle.contains_key("text/plain"));
+    }
+
+    #[test]
+    fn images_display_as_base64() {
+        let lua = Lua::new();
+        let png = lua.create_buffer(b"\x89PNG\r\n\x1a\n").unwrap();
+        let bundle = mime_bundle(&LuaValue::Buffer(png), None).unwrap();
+        assert_eq!(bundle["image/png"], "iVBORw0KGgo=");
+    }
+
+    #[test]
+    fn explicit_mime_types() {
+        let lua = Lua::new();
+        let html = LuaValue::String(lua.create_string("<b>hi</b>").unwrap());
+        let bundle = mime_bundle(&html, Some("text/html")).unwrap();
+        assert_eq!(bundle["text/html"], "<b>hi</b>");
+        assert!(mime_bundle(&LuaValue::Boolean(true), Some("text/html")).is_err());
+    }
+}
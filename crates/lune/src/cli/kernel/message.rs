@@ -0,0 +1,218 @@
+/*!
+    Messages of the Jupyter messaging protocol, and their signed wire format.
+
+    See <https://jupyter-client.readthedocs.io/en/latest/messaging.html>
+*/
+
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use ring::{
+    hmac,
+    rand::{SecureRandom, SystemRandom},
+};
+use serde_json::{Value, json};
+
+use crate::standalone::signature::{decode_hex, encode_hex};
+
+/// Frame separating routing identities from the rest of a message
+const DELIMITER: &[u8] = b"<IDS|MSG>";
+
+/// Version of the messaging protocol that the kernel implements
+pub const PROTOCOL_VERSION: &str = "5.3";
+
+/**
+    A message of the Jupyter messaging protocol.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub header: Value,
+    pub parent_header: Value,
+    pub metadata: Value,
+    pub content: Value,
+}
+
+impl Message {
+    /**
+        Creates a new message of the given type, in reply to the given parent header.
+    */
+    pub fn new(session: &str, msg_type: &str, parent_header: Value, content: Value) -> Self {
+        Self {
+            header: json!({
+                "msg_id": new_id(),
+                "session": session,
+                "username": "lune",
+                "date": Utc::now().to_rfc3339(),
+                "msg_type": msg_type,
+                "version": PROTOCOL_VERSION,
+            }),
+            parent_header,
+            metadata: json!({}),
+            content,
+        }
+    }
+
+    /**
+        Returns the type of this message, such as `execute_request`.
+    */
+    pub fn msg_type(&self) -> &str {
+        self.header["msg_type"].as_str().unwrap_or_default()
+    }
+}
+
+/**
+    Signs and verifies messages using the key from the connection file.
+
+    Messages are not signed if the key is empty.
+*/
+#[derive(Debug)]
+pub struct Signer {
+    key: Option<hmac::Key>,
+}
+
+impl Signer {
+    /**
+        Creates a signer for the given key and signature scheme.
+
+        # Errors
+
+        - If the signature scheme is not `hmac-sha256`
+    */
+    pub fn new(key: &str, scheme: &str) -> Result<Self> {
+        if key.is_empty() {
+            return Ok(Self { key: None });
+        }
+        if scheme != "hmac-sha256" {
+            bail!("unsupported signature scheme '{scheme}', expected 'hmac-sha256'");
+        }
+        Ok(Self {
+            key: Some(hmac::Key::new(hmac::HMAC_SHA256, key.as_bytes())),
+        })
+    }
+
+    /**
+        Decodes and verifies a message from the frames it was received as.
+
+        # Errors
+
+        - If the message is missing any of its parts
+        - If the signature of the message is invalid
+        - If any of the parts are not valid JSON
+    */
+    pub fn decode(&self, frames: &[Vec<u8>]) -> Result<Message> {
+        let start = frames
+            .iter()
+            .position(|frame| frame == DELIMITER)
+            .context("message is missing its delimiter")?;
+        let [signature, header, parent_header, metadata, content, ..] = &frames[start + 1..] else {
+            bail!("message is missing some of its parts");
+        };
+
+        if let Some(key) = &self.key {
+            let signature = std::str::from_utf8(signature)
+                .ok()
+                .and_then(|signature| decode_hex(signature).ok())
+                .context("message has an invalid signature")?;
+            let signed = [header, parent_header, metadata, content].map(Vec::as_slice);
+            hmac::verify(key, &signed.concat(), &signature)
+                .ok()
+                .context("message has an invalid signature")?;
+        }
+
+        Ok(Message {
+            header: serde_json::from_slice(header).context("invalid message header")?,
+            parent_header: serde_json::from_slice(parent_header)
+                .context("invalid message parent header")?,
+            metadata: serde_json::from_slice(metadata).context("invalid message metadata")?,
+            content: serde_json::from_slice(content).context("invalid message content")?,
+        })
+    }
+
+    /**
+        Encodes and signs a message into the frames to send it as.
+    */
+    pub fn encode(&self, message: &Message) -> Vec<Vec<u8>> {
+        let parts = [
+            &message.header,
+            &message.parent_header,
+            &message.metadata,
+            &message.content,
+        ]
+        .map(|part| serde_json::to_vec(part).expect("JSON values always serialize"));
+
+        let signature = match &self.key {
+            Some(key) => {
+                let mut context = hmac::Context::with_key(key);
+                for part in &parts {
+                    context.update(part);
+                }
+                encode_hex(context.sign().as_ref())
+            }
+            None => String::new(),
+        };
+
+        let mut frames = vec![DELIMITER.to_vec(), signature.into_bytes()];
+        frames.extend(parts);
+        frames
+    }
+}
+
+/// Creates a random UUID (v4) for identifying messages and sessions
+pub fn new_id() -> String {
+    let mut bytes = [0u8; 16];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .expect("system random number generator is available");
+    bytes[6] = (bytes[6] & 0x0F) | 0x40;
+    bytes[8] = (bytes[8] & 0x3F) | 0x80;
+    let hex = encode_hex(&bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message() -> Message {
+        Message::new(
+            "session",
+            "execute_request",
+            json!({}),
+            json!({ "code": "print(1)" }),
+        )
+    }
+
+    #[test]
+    fn signed_messages_roundtrip() {
+        let signer = Signer::new("secret", "hmac-sha256").unwrap();
+        let message = message();
+        let mut frames = vec![b"identity".to_vec()];
+        frames.extend(signer.encode(&message));
+        assert_eq!(signer.decode(&frames).unwrap(), message);
+    }
+
+    #[test]
+    fn tampered_messages_are_rejected() {
+        let signer = Signer::new("secret", "hmac-sha256").unwrap();
+        let mut frames = signer.encode(&message());
+        frames[5] = br#"{"code":"os.exit()"}"#.to_vec();
+        assert!(signer.decode(&frames).is_err());
+
+        let other = Signer::new("other", "hmac-sha256").unwrap();
+        assert!(other.decode(&signer.encode(&message())).is_err());
+    }
+
+    #[test]
+    fn ids_are_uuids() {
+        let id = new_id();
+        assert_eq!(id.len(), 36);
+        assert_eq!(id.as_bytes()[14], b'4');
+        assert_ne!(id, new_id());
+    }
+}
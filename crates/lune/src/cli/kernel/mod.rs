@@ -0,0 +1,437 @@
+use std::{
+    cell::RefCell,
+    env,
+    path::PathBuf,
+    process::ExitCode,
+    rc::Rc,
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver},
+    },
+};
+
+use anyhow::{Context, Result, bail};
+use async_fs as fs;
+use blocking::unblock;
+use clap::Parser;
+use console::style;
+use directories::BaseDirs;
+use mlua::{Compiler, prelude::*};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use lune::{OutputBuffer, Runtime};
+use lune_utils::fmt::{ValueFormatConfig, pretty_format_multi_value};
+
+mod display;
+mod message;
+mod zmtp;
+
+use self::display::mime_bundle;
+use self::message::{Message, PROTOCOL_VERSION, Signer, new_id};
+use self::zmtp::{Incoming, Socket, SocketType};
+
+const CELL_CHUNK_NAME: &str = "cell";
+
+// Results are not written to a terminal, so they should not be colored
+const FORMAT_CONFIG: ValueFormatConfig = ValueFormatConfig::new()
+    .with_max_depth(4)
+    .with_colors_enabled(false);
+
+/// Run a Jupyter kernel, executing notebook cells using Lune
+#[derive(Debug, Clone, Parser)]
+pub struct KernelCommand {
+    /// Path to the connection file given by Jupyter,
+    /// with the ports and key for the kernel to use
+    #[clap(long, value_name = "PATH", required_unless_present = "install")]
+    pub connection_file: Option<PathBuf>,
+
+    /// Install a kernel spec for Lune into the Jupyter data directory of
+    /// the current user, instead of running a kernel, so that notebooks can
+    /// use Lune - the directory may be changed using `JUPYTER_DATA_DIR`
+    #[clap(long, conflicts_with = "connection_file")]
+    pub install: bool,
+}
+
+impl KernelCommand {
+    pub async fn run(self) -> Result<ExitCode> {
+        let Some(connection_file) = self.connection_file else {
+            return install_kernel_spec().await;
+        };
+
+        let contents = fs::read(&connection_file).await.with_context(|| {
+            format!(
+                "failed to read connection file {}",
+                connection_file.display()
+            )
+        })?;
+        let info: ConnectionInfo =
+            serde_json::from_slice(&contents).context("invalid connection file")?;
+
+        let (sender, receiver) = mpsc::channel();
+        let sockets = Sockets::bind(&info, sender)?;
+        let signer = Signer::new(&info.key, &info.signature_scheme)?;
+        let mut kernel = Kernel::new(sockets, signer)?;
+
+        let receiver = Arc::new(Mutex::new(receiver));
+        loop {
+            let Some(incoming) = next_message(&receiver).await else {
+                break;
+            };
+            if !kernel.handle(incoming).await {
+                break;
+            }
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+async fn next_message(
+    receiver: &Arc<Mutex<Receiver<Incoming<Channel>>>>,
+) -> Option<Incoming<Channel>> {
+    let receiver = Arc::clone(receiver);
+    unblock(move || receiver.lock().unwrap().recv().ok()).await
+}
+
+/**
+    Contents of the connection file that Jupyter starts kernels with.
+*/
+#[derive(Debug, Deserialize)]
+struct ConnectionInfo {
+    transport: String,
+    ip: String,
+    key: String,
+    signature_scheme: String,
+    shell_port: u16,
+    iopub_port: u16,
+    stdin_port: u16,
+    control_port: u16,
+    hb_port: u16,
+}
+
+impl ConnectionInfo {
+    fn address(&self, port: u16) -> String {
+        format!("{}:{port}", self.ip)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Channel {
+    Shell,
+    Control,
+    Stdin,
+}
+
+/**
+    All sockets of a kernel - input requests are not supported, but the `stdin`
+    and heartbeat sockets must still accept connections for clients to connect.
+*/
+struct Sockets {
+    shell: Socket,
+    control: Socket,
+    iopub: Socket,
+    _stdin: Socket,
+    _heartbeat: Socket,
+}
+
+impl Sockets {
+    fn bind(info: &ConnectionInfo, sender: mpsc::Sender<Incoming<Channel>>) -> Result<Self> {
+        if info.transport != "tcp" {
+            bail!("unsupported transport '{}', expected 'tcp'", info.transport);
+        }
+        let bind = |kind, port, channel| {
+            Socket::bind(kind, &info.address(port), channel, sender.clone())
+                .with_context(|| format!("failed to bind to {}", info.address(port)))
+        };
+        Ok(Self {
+            shell: bind(SocketType::Router, info.shell_port, Channel::Shell)?,
+            control: bind(SocketType::Router, info.control_port, Channel::Control)?,
+            iopub: bind(SocketType::Pub, info.iopub_port, Channel::Shell)?,
+            _stdin: bind(SocketType::Router, info.stdin_port, Channel::Stdin)?,
+            _heartbeat: bind(SocketType::Rep, info.hb_port, Channel::Shell)?,
+        })
+    }
+}
+
+/**
+    Publishes messages on the `iopub` socket, in reply to the request being handled.
+*/
+struct Publisher {
+    socket: Socket,
+    signer: Rc<Signer>,
+    session: String,
+    parent_header: RefCell<Value>,
+    stdout: OutputBuffer,
+    stderr: OutputBuffer,
+}
+
+impl Publisher {
+    fn publish(&self, msg_type: &str, content: Value) {
+        let parent_header = self.parent_header.borrow().clone();
+        let message = Message::new(&self.session, msg_type, parent_header, content);
+        let mut frames = vec![msg_type.as_bytes().to_vec()];
+        frames.extend(self.signer.encode(&message));
+        self.socket.broadcast(&frames);
+    }
+
+    /**
+        Publishes all output captured so far as streams, so
+        that it is shown before anything else that is published.
+    */
+    fn flush_output(&self) {
+        for (name, output) in [("stdout", &self.stdout), ("stderr", &self.stderr)] {
+            let bytes = std::mem::take(&mut *output.lock().unwrap());
+            if !bytes.is_empty() {
+                let text = String::from_utf8_lossy(&bytes);
+                self.publish("stream", json!({ "name": name, "text": text }));
+            }
+        }
+    }
+}
+
+/**
+    A Jupyter kernel, running cells in a single Lune runtime,
+    so that globals persist between cells, same as in the REPL.
+*/
+struct Kernel {
+    runtime: Runtime,
+    shell: Socket,
+    control: Socket,
+    publisher: Rc<Publisher>,
+    signer: Rc<Signer>,
+    session: String,
+    execution_count: u64,
+}
+
+impl Kernel {
+    fn new(sockets: Sockets, signer: Signer) -> Result<Self> {
+        let signer = Rc::new(signer);
+        let session = new_id();
+        let stdout = OutputBuffer::default();
+        let stderr = OutputBuffer::default();
+        let publisher = Rc::new(Publisher {
+            socket: sockets.iopub,
+            signer: Rc::clone(&signer),
+            session: session.clone(),
+            parent_header: RefCell::new(json!({})),
+            stdout: Arc::clone(&stdout),
+            stderr: Arc::clone(&stderr),
+        });
+
+        let display_publisher = Rc::clone(&publisher);
+        let runtime = Runtime::new()?
+            .with_output_capture(stdout, stderr)?
+            .with_lib("@jupyter", move |lua| {
+                create_jupyter_lib(lua, display_publisher)
+            })?;
+
+        Ok(Self {
+            runtime,
+            shell: sockets.shell,
+            control: sockets.control,
+            publisher,
+            signer,
+            session,
+            execution_count: 0,
+        })
+    }
+
+    /**
+        Handles a single request, returning `false` once the kernel should shut down.
+    */
+    async fn handle(&mut self, incoming: Incoming<Channel>) -> bool {
+        let request = match self.signer.decode(&incoming.frames) {
+            Ok(request) => request,
+            Err(e) => {
+                eprintln!("{}", style(format!("Ignoring message: {e:#}")).yellow());
+                return true;
+            }
+        };
+        if incoming.channel == Channel::Stdin {
+            return true;
+        }
+
+        *self.publisher.parent_header.borrow_mut() = request.header.clone();
+        self.publisher
+            .publish("status", json!({ "execution_state": "busy" }));
+
+        let mut keep_running = true;
+        let reply = match request.msg_type() {
+            "kernel_info_request" => Some(("kernel_info_reply", kernel_info())),
+            "execute_request" => Some(("execute_reply", self.execute(&request.content).await)),
+            "is_complete_request" => Some(("is_complete_reply", is_complete(&request.content))),
+            "comm_info_request" => {
+                Some(("comm_info_reply", json!({ "status": "ok", "comms": {} })))
+            }
+            "shutdown_request" => {
+                keep_running = false;
+                Some((
+                    "shutdown_reply",
+                    json!({ "status": "ok", "restart": request.content["restart"] }),
+                ))
+            }
+            _ => None,
+        };
+
+        if let Some((msg_type, content)) = reply {
+            let message = Message::new(&self.session, msg_type, request.header, content);
+            let socket = match incoming.channel {
+                Channel::Control => &self.control,
+                _ => &self.shell,
+            };
+            if let Err(e) = socket.send_to(incoming.peer, &self.signer.encode(&message)) {
+                eprintln!("{}", style(format!("Failed to send reply: {e}")).yellow());
+            }
+        }
+
+        self.publisher
+            .publish("status", json!({ "execution_state": "idle" }));
+        keep_running
+    }
+
+    async fn execute(&mut self, content: &Value) -> Value {
+        let code = content["code"].as_str().unwrap_or_default();
+        let silent = content["silent"].as_bool().unwrap_or(false);
+        if !silent {
+            self.execution_count += 1;
+        }
+        let execution_count = self.execution_count;
+
+        self.publisher.publish(
+            "execute_input",
+            json!({ "code": code, "execution_count": execution_count }),
+        );
+
+        let result = self.runtime.run_custom(CELL_CHUNK_NAME, code).await;
+        self.publisher.flush_output();
+
+        let (ename, evalue) = match result {
+            Ok(values) if values.success() => {
+                if !silent && !values.values.is_empty() {
+                    let text = pretty_format_multi_value(&values.values, &FORMAT_CONFIG);
+                    self.publisher.publish(
+                        "execute_result",
+                        json!({
+                            "execution_count": execution_count,
+                            "data": { "text/plain": text },
+                            "metadata": {},
+                        }),
+                    );
+                }
+                return json!({
+                    "status": "ok",
+                    "execution_count": execution_count,
+                    "user_expressions": {},
+                });
+            }
+            // NOTE: Errors thrown while running have already been
+            // captured and published as stderr, so they are not repeated
+            Ok(_) => ("RuntimeError", "the cell threw an error".to_string()),
+            Err(e) => {
+                let message = e.disable_colors().to_string();
+                self.publisher.publish(
+                    "error",
+                    json!({
+                        "ename": "SyntaxError",
+                        "evalue": message,
+                        "traceback": [message],
+                    }),
+                );
+                ("SyntaxError", message)
+            }
+        };
+
+        json!({
+            "status": "error",
+            "execution_count": execution_count,
+            "ename": ename,
+            "evalue": evalue,
+            "traceback": [],
+        })
+    }
+}
+
+fn kernel_info() -> Value {
+    json!({
+        "status": "ok",
+        "protocol_version": PROTOCOL_VERSION,
+        "implementation": "lune",
+        "implementation_version": env!("CARGO_PKG_VERSION"),
+        "language_info": {
+            "name": "luau",
+            "mimetype": "text/x-luau",
+            "file_extension": ".luau",
+        },
+        "banner": concat!("Lune v", env!("CARGO_PKG_VERSION")),
+        "help_links": [],
+    })
+}
+
+fn is_complete(content: &Value) -> Value {
+    let code = content["code"].as_str().unwrap_or_default();
+    let status = match Compiler::new().compile(code) {
+        Ok(_) => "complete",
+        Err(LuaError::SyntaxError {
+            incomplete_input: true,
+            ..
+        }) => "incomplete",
+        Err(_) => "invalid",
+    };
+    json!({ "status": status, "indent": "" })
+}
+
+/**
+    Creates the `@jupyter` library, with a `display` function
+    for showing values in notebooks in their richest format.
+*/
+fn create_jupyter_lib(lua: &Lua, publisher: Rc<Publisher>) -> LuaResult<LuaValue> {
+    let display = lua.create_function(move |_, (value, mime): (LuaValue, Option<String>)| {
+        let data = mime_bundle(&value, mime.as_deref())?;
+        publisher.flush_output();
+        publisher.publish(
+            "display_data",
+            json!({ "data": data, "metadata": {}, "transient": {} }),
+        );
+        Ok(())
+    })?;
+    let lib = lua.create_table_from([("display", display)])?;
+    lib.set_readonly(true);
+    Ok(LuaValue::Table(lib))
+}
+
+/**
+    Writes a kernel spec that runs the current executable as a kernel
+    into the Jupyter data directory, so that Jupyter can find it.
+*/
+async fn install_kernel_spec() -> Result<ExitCode> {
+    let exe = env::current_exe().context("failed to get current executable")?;
+    let dir = jupyter_data_dir()?.join("kernels").join("lune");
+    let spec = json!({
+        "argv": [exe.to_string_lossy(), "kernel", "--connection-file", "{connection_file}"],
+        "display_name": "Lune (Luau)",
+        "language": "luau",
+    });
+
+    fs::create_dir_all(&dir)
+        .await
+        .with_context(|| format!("failed to create directory {}", dir.display()))?;
+    fs::write(dir.join("kernel.json"), serde_json::to_vec_pretty(&spec)?)
+        .await
+        .context("failed to write kernel spec")?;
+
+    println!("Installed kernel spec to {}", style(dir.display()).blue());
+    Ok(ExitCode::SUCCESS)
+}
+
+fn jupyter_data_dir() -> Result<PathBuf> {
+    if let Some(dir) = env::var_os("JUPYTER_DATA_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    let dirs = BaseDirs::new().context("failed to find user home directory")?;
+    Ok(if cfg!(target_os = "macos") {
+        dirs.home_dir().join("Library").join("Jupyter")
+    } else {
+        dirs.data_dir().join("jupyter")
+    })
+}
@@ -0,0 +1,298 @@
+/*!
+    A minimal implementation of ZMTP 3.0, the wire protocol of `ZeroMQ`, for Jupyter kernels.
+
+    Only what Jupyter needs is implemented - TCP transports, the `NULL` security
+    mechanism, and the `ROUTER`, `PUB` and `REP` socket types, which only bind
+    and accept connections from clients. Replies are routed to the connection
+    that the request was received from, instead of using identity frames, and
+    `PUB` sockets send every message to all of their peers, regardless of topic,
+    since Jupyter clients always subscribe to all topics.
+*/
+
+use std::{
+    io::{Error as IoError, ErrorKind, Read, Result as IoResult, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex, mpsc::Sender},
+    thread,
+};
+
+const GREETING_LEN: usize = 64;
+const MECHANISM_NULL: &[u8] = b"NULL";
+
+const FLAG_MORE: u8 = 0x01;
+const FLAG_LONG: u8 = 0x02;
+const FLAG_COMMAND: u8 = 0x04;
+
+/// Messages larger than this are rejected, instead of allocating for them
+const MAX_FRAME_LEN: u64 = 512 * 1024 * 1024;
+
+type Peers = Arc<Mutex<Vec<(u64, TcpStream)>>>;
+
+/**
+    The type of a socket, which decides what happens to messages it receives.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SocketType {
+    /// Messages are given to the kernel, along with the peer they came from
+    Router,
+    /// Messages are ignored, since they are only subscriptions
+    Pub,
+    /// Messages are echoed back to their peer right away, for heartbeats
+    Rep,
+}
+
+impl SocketType {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Router => "ROUTER",
+            Self::Pub => "PUB",
+            Self::Rep => "REP",
+        }
+    }
+}
+
+/**
+    A message received by a `ROUTER` socket.
+*/
+#[derive(Debug)]
+pub struct Incoming<C> {
+    /// The channel of the socket that received the message
+    pub channel: C,
+    /// The connection that the message was received from
+    pub peer: u64,
+    pub frames: Vec<Vec<u8>>,
+}
+
+/**
+    A bound socket, accepting connections on background threads.
+*/
+#[derive(Debug)]
+pub struct Socket {
+    peers: Peers,
+}
+
+impl Socket {
+    /**
+        Binds a socket of the given type to the given address.
+
+        Messages received by `ROUTER` sockets are sent to `incoming`,
+        tagged with the given channel.
+
+        # Errors
+
+        - If the address could not be bound to
+    */
+    pub fn bind<C>(
+        kind: SocketType,
+        address: &str,
+        channel: C,
+        incoming: Sender<Incoming<C>>,
+    ) -> IoResult<Self>
+    where
+        C: Copy + Send + 'static,
+    {
+        let listener = TcpListener::bind(address)?;
+        let peers = Peers::default();
+        let peers_inner = Arc::clone(&peers);
+        thread::spawn(move || {
+            for (id, stream) in (0..).zip(listener.incoming()) {
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let peers = Arc::clone(&peers_inner);
+                let incoming = incoming.clone();
+                thread::spawn(move || {
+                    // NOTE: Peers disconnecting is not an error, they may reconnect
+                    serve_peer(kind, id, stream, &peers, channel, &incoming).ok();
+                    peers.lock().unwrap().retain(|(peer, _)| *peer != id);
+                });
+            }
+        });
+        Ok(Self { peers })
+    }
+
+    /**
+        Sends a message to the given peer, if it is still connected.
+
+        # Errors
+
+        - If the message could not be written to the peer
+    */
+    pub fn send_to(&self, peer: u64, frames: &[Vec<u8>]) -> IoResult<()> {
+        let mut peers = self.peers.lock().unwrap();
+        match peers.iter_mut().find(|(id, _)| *id == peer) {
+            Some((_, stream)) => stream.write_all(&encode_message(frames)),
+            None => Ok(()),
+        }
+    }
+
+    /**
+        Sends a message to all connected peers, ignoring any that fail to receive it.
+    */
+    pub fn broadcast(&self, frames: &[Vec<u8>]) {
+        let message = encode_message(frames);
+        for (_, stream) in self.peers.lock().unwrap().iter_mut() {
+            stream.write_all(&message).ok();
+        }
+    }
+}
+
+fn serve_peer<C: Copy>(
+    kind: SocketType,
+    id: u64,
+    mut stream: TcpStream,
+    peers: &Peers,
+    channel: C,
+    incoming: &Sender<Incoming<C>>,
+) -> IoResult<()> {
+    stream.set_nodelay(true)?;
+    handshake(&mut stream, kind)?;
+    peers.lock().unwrap().push((id, stream.try_clone()?));
+
+    loop {
+        let frames = read_message(&mut stream)?;
+        match kind {
+            SocketType::Router => {
+                let message = Incoming {
+                    channel,
+                    peer: id,
+                    frames,
+                };
+                if incoming.send(message).is_err() {
+                    return Ok(());
+                }
+            }
+            SocketType::Pub => {}
+            SocketType::Rep => stream.write_all(&encode_message(&frames))?,
+        }
+    }
+}
+
+fn handshake(stream: &mut TcpStream, kind: SocketType) -> IoResult<()> {
+    stream.write_all(&greeting())?;
+
+    let mut peer = [0; GREETING_LEN];
+    stream.read_exact(&mut peer)?;
+    if peer[0] != 0xFF || peer[9] != 0x7F || peer[10] < 3 {
+        return Err(invalid_data("peer does not speak ZMTP 3"));
+    }
+    if !peer[12..32].starts_with(MECHANISM_NULL) || peer[12 + MECHANISM_NULL.len()] != 0 {
+        return Err(invalid_data("peer uses an unsupported security mechanism"));
+    }
+
+    let mut ready = Vec::new();
+    push_short_string(&mut ready, b"READY");
+    push_short_string(&mut ready, b"Socket-Type");
+    ready.extend_from_slice(&(kind.name().len() as u32).to_be_bytes());
+    ready.extend_from_slice(kind.name().as_bytes());
+    let mut frame = Vec::new();
+    encode_frame(&mut frame, FLAG_COMMAND, &ready);
+    stream.write_all(&frame)?;
+
+    let (flags, body) = read_frame(stream)?;
+    if flags & FLAG_COMMAND == 0 || !body.starts_with(b"\x05READY") {
+        return Err(invalid_data("peer did not send a READY command"));
+    }
+    Ok(())
+}
+
+fn greeting() -> [u8; GREETING_LEN] {
+    let mut greeting = [0; GREETING_LEN];
+    greeting[0] = 0xFF;
+    greeting[9] = 0x7F;
+    greeting[10] = 3;
+    greeting[12..12 + MECHANISM_NULL.len()].copy_from_slice(MECHANISM_NULL);
+    greeting[32] = 1; // as-server
+    greeting
+}
+
+fn push_short_string(buf: &mut Vec<u8>, s: &[u8]) {
+    buf.push(u8::try_from(s.len()).expect("short strings are at most 255 bytes"));
+    buf.extend_from_slice(s);
+}
+
+fn read_frame(stream: &mut impl Read) -> IoResult<(u8, Vec<u8>)> {
+    let mut flags = [0; 1];
+    stream.read_exact(&mut flags)?;
+    let len = if flags[0] & FLAG_LONG == 0 {
+        let mut len = [0; 1];
+        stream.read_exact(&mut len)?;
+        u64::from(len[0])
+    } else {
+        let mut len = [0; 8];
+        stream.read_exact(&mut len)?;
+        u64::from_be_bytes(len)
+    };
+    if len > MAX_FRAME_LEN {
+        return Err(invalid_data("peer sent a frame that is too large"));
+    }
+    let mut body = vec![0; usize::try_from(len).map_err(IoError::other)?];
+    stream.read_exact(&mut body)?;
+    Ok((flags[0], body))
+}
+
+fn read_message(stream: &mut impl Read) -> IoResult<Vec<Vec<u8>>> {
+    let mut frames = Vec::new();
+    loop {
+        let (flags, body) = read_frame(stream)?;
+        // NOTE: Commands after the handshake, such as heartbeats
+        // of newer protocol versions, have no meaning for us
+        if flags & FLAG_COMMAND != 0 {
+            continue;
+        }
+        frames.push(body);
+        if flags & FLAG_MORE == 0 {
+            return Ok(frames);
+        }
+    }
+}
+
+fn encode_frame(buf: &mut Vec<u8>, flags: u8, body: &[u8]) {
+    if let Ok(len) = u8::try_from(body.len()) {
+        buf.push(flags);
+        buf.push(len);
+    } else {
+        buf.push(flags | FLAG_LONG);
+        buf.extend_from_slice(&(body.len() as u64).to_be_bytes());
+    }
+    buf.extend_from_slice(body);
+}
+
+fn encode_message(frames: &[Vec<u8>]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for (index, frame) in frames.iter().enumerate() {
+        let more = index + 1 < frames.len();
+        encode_frame(&mut buf, if more { FLAG_MORE } else { 0 }, frame);
+    }
+    buf
+}
+
+fn invalid_data(message: &str) -> IoError {
+    IoError::new(ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn messages_roundtrip() {
+        let frames = vec![b"topic".to_vec(), Vec::new(), vec![7; 300]];
+        let encoded = encode_message(&frames);
+        // Short frames use a single byte for their length, long frames eight
+        assert_eq!(&encoded[..7], b"\x01\x05topic");
+        assert_eq!(encoded[9], FLAG_LONG);
+        assert_eq!(read_message(&mut Cursor::new(encoded)).unwrap(), frames);
+    }
+
+    #[test]
+    fn commands_are_skipped() {
+        let mut encoded = Vec::new();
+        encode_frame(&mut encoded, FLAG_COMMAND, b"\x04PING");
+        encode_frame(&mut encoded, 0, b"hello");
+        let frames = read_message(&mut Cursor::new(encoded)).unwrap();
+        assert_eq!(frames, vec![b"hello".to_vec()]);
+    }
+}
@@ -0,0 +1,372 @@
+use std::{
+    io::{Write, stdin, stdout},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
+};
+
+use anyhow::Result;
+use blocking::Unblock;
+use clap::Parser;
+use futures_lite::{io::BufReader, prelude::*};
+use serde_json::{Value, json};
+
+use lune::{CapabilityMode, OutputBuffer, Runtime};
+use lune_utils::path::set_project_file_name;
+
+use super::utils::{
+    config::ProjectConfig,
+    files::discover_script_path,
+    listing::{find_lune_scripts, sort_lune_scripts},
+};
+
+/// Versions of the Model Context Protocol that are supported, latest first
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+const ERROR_PARSE: i64 = -32700;
+const ERROR_INVALID_REQUEST: i64 = -32600;
+const ERROR_METHOD_NOT_FOUND: i64 = -32601;
+const ERROR_INVALID_PARAMS: i64 = -32602;
+
+/// Serve the scripts available to run as tools, using the Model Context Protocol over stdio
+#[derive(Debug, Clone, Parser)]
+pub struct McpCommand {}
+
+impl McpCommand {
+    pub async fn run(self) -> Result<ExitCode> {
+        let server = Server::new(discover_tools().await);
+
+        let mut lines = BufReader::new(Unblock::new(stdin())).lines();
+        while let Some(line) = lines.next().await {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = server.handle(&line).await {
+                let mut stdout = stdout().lock();
+                writeln!(stdout, "{response}")?;
+                stdout.flush()?;
+            }
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/**
+    A script that is served as a tool, found in a `lune` or `.lune` directory.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Tool {
+    /// Name of the tool, which is the name of the script
+    name: String,
+    /// Description of the script, from its leading `-->` comments
+    description: String,
+    /// Whether the script is in the home directory, instead of the current directory
+    global: bool,
+}
+
+impl Tool {
+    fn to_json(&self) -> Value {
+        let description = if self.description.is_empty() {
+            format!("Runs the Lune script '{}'", self.name)
+        } else {
+            self.description.clone()
+        };
+        json!({
+            "name": self.name,
+            "description": description,
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "args": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Arguments to pass to the script, stored in process.args",
+                    },
+                },
+            },
+        })
+    }
+
+    /**
+        Finds the file of the script, in the same `lune` or `.lune`
+        directory that it was discovered in when listing tools.
+    */
+    fn script_path(&self) -> Result<PathBuf> {
+        discover_script_path(Path::new("lune").join(&self.name), self.global)
+            .or_else(|_| discover_script_path(Path::new(".lune").join(&self.name), self.global))
+    }
+}
+
+/**
+    Finds the scripts that `lune list` shows, preferring scripts in
+    the current directory over global scripts with the same name.
+*/
+async fn discover_tools() -> Vec<Tool> {
+    let mut tools = Vec::new();
+    for global in [false, true] {
+        let Ok(scripts) = find_lune_scripts(global).await else {
+            continue;
+        };
+        for (name, description) in sort_lune_scripts(scripts) {
+            if is_tool_name(&name) && !tools.iter().any(|tool: &Tool| tool.name == name) {
+                tools.push(Tool {
+                    name,
+                    description,
+                    global,
+                });
+            }
+        }
+    }
+    tools
+}
+
+/// Tool names may only contain ASCII letters, digits, underscores and dashes
+fn is_tool_name(name: &str) -> bool {
+    (1..=64).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/**
+    Handles JSON-RPC messages of the Model Context Protocol.
+
+    Every tool call runs its script in a new runtime, with its output
+    captured, and with the capabilities granted by the `lune.toml` file
+    closest to the script always being checked, even without one.
+*/
+struct Server {
+    tools: Vec<Tool>,
+}
+
+impl Server {
+    fn new(tools: Vec<Tool>) -> Self {
+        Self { tools }
+    }
+
+    /**
+        Handles a single line of input, returning the response to it, if any.
+    */
+    async fn handle(&self, line: &str) -> Option<Value> {
+        let message: Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(e) => return Some(error_response(&Value::Null, ERROR_PARSE, &e.to_string())),
+        };
+        if !message.is_object() {
+            let message = "expected a JSON object";
+            return Some(error_response(&Value::Null, ERROR_INVALID_REQUEST, message));
+        }
+        let Some(method) = message["method"].as_str() else {
+            // NOTE: Responses from the client are ignored, since no requests are ever sent to it
+            return None;
+        };
+        // Notifications have no id, and are never responded to
+        let id = message.get("id")?;
+        let params = &message["params"];
+
+        let result = match method {
+            "initialize" => Ok(initialize(params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({
+                "tools": self.tools.iter().map(Tool::to_json).collect::<Vec<_>>(),
+            })),
+            "tools/call" => self.call_tool(params).await,
+            _ => Err((ERROR_METHOD_NOT_FOUND, format!("unknown method '{method}'"))),
+        };
+
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+
+    async fn call_tool(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let name = params["name"].as_str().unwrap_or_default();
+        let Some(tool) = self.tools.iter().find(|tool| tool.name == name) else {
+            return Err((ERROR_INVALID_PARAMS, format!("unknown tool '{name}'")));
+        };
+        let args = match &params["arguments"]["args"] {
+            Value::Null => Vec::new(),
+            Value::Array(args) => args
+                .iter()
+                .map(|arg| arg.as_str().map(String::from))
+                .collect::<Option<Vec<_>>>()
+                .ok_or((
+                    ERROR_INVALID_PARAMS,
+                    "all arguments must be strings".to_string(),
+                ))?,
+            _ => {
+                return Err((
+                    ERROR_INVALID_PARAMS,
+                    "arguments must be an array of strings".to_string(),
+                ));
+            }
+        };
+
+        Ok(match run_tool(tool, args).await {
+            Ok(result) => result,
+            Err(e) => tool_result("", &format!("{e:#}"), true),
+        })
+    }
+}
+
+fn initialize(params: &Value) -> Value {
+    let requested = params["protocolVersion"].as_str().unwrap_or_default();
+    let version = PROTOCOL_VERSIONS
+        .iter()
+        .find(|version| **version == requested)
+        .unwrap_or(&PROTOCOL_VERSIONS[0]);
+    json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": {} },
+        "serverInfo": {
+            "name": "lune",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+    })
+}
+
+async fn run_tool(tool: &Tool, args: Vec<String>) -> Result<Value> {
+    let script_path = tool.script_path()?;
+    let config_dir = script_path.parent().unwrap_or(Path::new("."));
+    let config = ProjectConfig::discover(config_dir).await?;
+    set_project_file_name(config.rojo.project_file);
+    let (mode, grant) = config
+        .capabilities
+        .map_or((CapabilityMode::Enforce, Vec::new()), |capabilities| {
+            (capabilities.mode, capabilities.grant)
+        });
+
+    let stdout = OutputBuffer::default();
+    let stderr = OutputBuffer::default();
+    let mut rt = Runtime::new()?
+        .with_args(args)
+        .with_exit_codes(config.exit_codes)
+        .with_print_limits(config.print)
+        .with_capabilities(mode, grant)
+        .with_output_capture(Arc::clone(&stdout), Arc::clone(&stderr))?;
+
+    let result = rt.run_file(script_path).await;
+    let stdout = String::from_utf8_lossy(&stdout.lock().unwrap()).into_owned();
+    let mut stderr = String::from_utf8_lossy(&stderr.lock().unwrap()).into_owned();
+
+    let is_error = match result {
+        Ok(values) => values.status() != 0,
+        Err(e) => {
+            stderr.push_str(&e.disable_colors().to_string());
+            true
+        }
+    };
+    Ok(tool_result(&stdout, &stderr, is_error))
+}
+
+/**
+    Creates the result of a tool call, with the output of the
+    script as text content, and errors as separate content.
+*/
+fn tool_result(stdout: &str, stderr: &str, is_error: bool) -> Value {
+    let mut content = Vec::new();
+    if !stdout.is_empty() || stderr.is_empty() {
+        content.push(json!({ "type": "text", "text": stdout }));
+    }
+    if !stderr.is_empty() {
+        content.push(json!({ "type": "text", "text": stderr }));
+    }
+    json!({ "content": content, "isError": is_error })
+}
+
+fn error_response(id: &Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_lite::future::block_on;
+
+    use super::*;
+
+    fn server() -> Server {
+        Server::new(vec![Tool {
+            name: "deploy".to_string(),
+            description: "Deploys the project".to_string(),
+            global: false,
+        }])
+    }
+
+    #[test]
+    fn negotiates_protocol_version() {
+        let response = block_on(server().handle(
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2024-11-05"}}"#,
+        ))
+        .unwrap();
+        assert_eq!(response["result"]["protocolVersion"], "2024-11-05");
+        assert_eq!(response["result"]["serverInfo"]["name"], "lune");
+
+        let response = block_on(server().handle(
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"1999-01-01"}}"#,
+        ))
+        .unwrap();
+        assert_eq!(response["result"]["protocolVersion"], PROTOCOL_VERSIONS[0]);
+    }
+
+    #[test]
+    fn lists_tools() {
+        let response =
+            block_on(server().handle(r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#)).unwrap();
+        let tools = response["result"]["tools"].as_array().unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0]["name"], "deploy");
+        assert_eq!(tools[0]["description"], "Deploys the project");
+        assert_eq!(
+            tools[0]["inputSchema"]["properties"]["args"]["type"],
+            "array"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_messages() {
+        let server = server();
+        let response = block_on(server.handle("{not json")).unwrap();
+        assert_eq!(response["error"]["code"], ERROR_PARSE);
+
+        let response = block_on(server.handle("[]")).unwrap();
+        assert_eq!(response["error"]["code"], ERROR_INVALID_REQUEST);
+
+        let response =
+            block_on(server.handle(r#"{"jsonrpc":"2.0","id":3,"method":"nope"}"#)).unwrap();
+        assert_eq!(response["error"]["code"], ERROR_METHOD_NOT_FOUND);
+
+        let response = block_on(server.handle(
+            r#"{"jsonrpc":"2.0","id":4,"method":"tools/call","params":{"name":"missing"}}"#,
+        ))
+        .unwrap();
+        assert_eq!(response["error"]["code"], ERROR_INVALID_PARAMS);
+
+        let response = block_on(server.handle(
+            r#"{"jsonrpc":"2.0","id":5,"method":"tools/call","params":{"name":"deploy","arguments":{"args":[1]}}}"#,
+        ))
+        .unwrap();
+        assert_eq!(response["error"]["code"], ERROR_INVALID_PARAMS);
+    }
+
+    #[test]
+    fn notifications_are_not_answered() {
+        let response =
+            block_on(server().handle(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#));
+        assert!(response.is_none());
+    }
+
+    #[test]
+    fn validates_tool_names() {
+        assert!(is_tool_name("build-release_2"));
+        assert!(!is_tool_name("with space"));
+        assert!(!is_tool_name("dotted.name"));
+        assert!(!is_tool_name(""));
+    }
+}
@@ -1,17 +1,23 @@
-use std::{env::args_os, process::ExitCode};
+use std::{env::args_os, iter::once, process::ExitCode};
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
 
 pub(crate) mod build;
+pub(crate) mod kernel;
 pub(crate) mod list;
+pub(crate) mod mcp;
+pub(crate) mod release;
 pub(crate) mod repl;
 pub(crate) mod run;
+pub(crate) mod service;
 pub(crate) mod setup;
 pub(crate) mod utils;
 
 pub use self::{
-    build::BuildCommand, list::ListCommand, repl::ReplCommand, run::RunCommand, setup::SetupCommand,
+    build::BuildCommand, kernel::KernelCommand, list::ListCommand, mcp::McpCommand,
+    release::ReleaseCommand, repl::ReplCommand, run::RunCommand, service::ServiceCommand,
+    setup::SetupCommand,
 };
 
 #[derive(Debug, Clone, Subcommand)]
@@ -20,7 +26,11 @@ pub enum CliSubcommand {
     List(ListCommand),
     Setup(SetupCommand),
     Build(BuildCommand),
+    Release(ReleaseCommand),
     Repl(ReplCommand),
+    Kernel(KernelCommand),
+    Mcp(McpCommand),
+    Service(ServiceCommand),
 }
 
 impl Default for CliSubcommand {
@@ -45,23 +55,28 @@ impl Cli {
             .nth(1)
             .is_some_and(|arg| arg.eq_ignore_ascii_case("run"))
         {
-            let Some(script_path) = args_os()
-                .nth(2)
-                .and_then(|arg| arg.to_str().map(String::from))
-            else {
+            // Options of the run command itself must come before the script path,
+            // so that all arguments after it are passed to the script as-is
+            let args = args_os()
+                .skip(2)
+                .filter_map(|arg| arg.to_str().map(String::from))
+                .collect::<Vec<_>>();
+            let options_len = run_options_len(&args);
+            let Some(script_path) = args.get(options_len).cloned() else {
                 return Self::parse(); // Will fail and return the help message
             };
 
-            let script_args = args_os()
-                .skip(3)
-                .filter_map(|arg| arg.to_str().map(String::from))
-                .collect::<Vec<_>>();
+            let options = args[..options_len].iter().cloned();
+            let mut command = RunCommand::try_parse_from(
+                once(String::from("run"))
+                    .chain(options)
+                    .chain(once(script_path)),
+            )
+            .unwrap_or_else(|err| err.exit());
+            command.script_args = args[options_len + 1..].to_vec();
 
             Self {
-                subcommand: Some(CliSubcommand::Run(RunCommand {
-                    script_path,
-                    script_args,
-                })),
+                subcommand: Some(CliSubcommand::Run(command)),
             }
         } else {
             Self::parse()
@@ -74,7 +89,29 @@ impl Cli {
             CliSubcommand::List(cmd) => cmd.run().await,
             CliSubcommand::Setup(cmd) => cmd.run().await,
             CliSubcommand::Build(cmd) => cmd.run().await,
+            CliSubcommand::Release(cmd) => cmd.run().await,
             CliSubcommand::Repl(cmd) => cmd.run().await,
+            CliSubcommand::Kernel(cmd) => cmd.run().await,
+            CliSubcommand::Mcp(cmd) => cmd.run().await,
+            CliSubcommand::Service(cmd) => cmd.run().await,
+        }
+    }
+}
+
+/// Counts the leading arguments given to `lune run` that are
+/// options of the run command, including any of their values
+fn run_options_len(args: &[String]) -> usize {
+    let command = RunCommand::command();
+    let mut len = 0;
+    while let Some(name) = args.get(len).and_then(|arg| arg.strip_prefix("--")) {
+        if name.is_empty() {
+            break;
         }
+        let takes_value = !name.contains('=')
+            && command
+                .get_arguments()
+                .any(|arg| arg.get_long() == Some(name) && arg.get_action().takes_values());
+        len += if takes_value { 2 } else { 1 };
     }
+    len.min(args.len())
 }
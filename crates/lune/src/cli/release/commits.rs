@@ -0,0 +1,214 @@
+use std::{fmt, str::FromStr, sync::LazyLock};
+
+use regex::Regex;
+use semver::Version;
+
+static CONVENTIONAL_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?P<kind>[A-Za-z]+)(?:\((?P<scope>[^)]*)\))?(?P<breaking>!)?:\s*(?P<desc>.+)$")
+        .expect("conventional commit regex is valid")
+});
+
+/**
+    A kind of version bump, following semantic versioning.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum VersionBump {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl VersionBump {
+    /**
+        Applies the bump to the given version, resetting any
+        lower version numbers, pre-release and build metadata.
+    */
+    pub fn apply(self, version: &Version) -> Version {
+        match self {
+            Self::Major => Version::new(version.major + 1, 0, 0),
+            Self::Minor => Version::new(version.major, version.minor + 1, 0),
+            Self::Patch => Version::new(version.major, version.minor, version.patch + 1),
+        }
+    }
+}
+
+impl fmt::Display for VersionBump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Major => write!(f, "major"),
+            Self::Minor => write!(f, "minor"),
+            Self::Patch => write!(f, "patch"),
+        }
+    }
+}
+
+impl FromStr for VersionBump {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "major" => Ok(Self::Major),
+            "minor" => Ok(Self::Minor),
+            "patch" => Ok(Self::Patch),
+            _ => Err("invalid version bump"),
+        }
+    }
+}
+
+/**
+    The section of a changelog that a commit belongs in.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChangelogSection {
+    Breaking,
+    Added,
+    Changed,
+    Fixed,
+}
+
+impl ChangelogSection {
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Breaking => "Breaking Changes",
+            Self::Added => "Added",
+            Self::Changed => "Changed",
+            Self::Fixed => "Fixed",
+        }
+    }
+}
+
+/**
+    A commit message parsed using the conventional commits format,
+    such as `feat(net): add websocket server` or `fix!: drop old api`.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConventionalCommit {
+    pub kind: String,
+    pub scope: Option<String>,
+    pub description: String,
+    pub breaking: bool,
+}
+
+impl ConventionalCommit {
+    /**
+        Parses a commit from its subject and body, returning `None`
+        if the subject does not follow the conventional commits format.
+    */
+    pub fn parse(subject: &str, body: &str) -> Option<Self> {
+        let caps = CONVENTIONAL_REGEX.captures(subject.trim())?;
+        let breaking = caps.name("breaking").is_some()
+            || body.lines().any(|line| {
+                line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:")
+            });
+        Some(Self {
+            kind: caps["kind"].to_ascii_lowercase(),
+            scope: caps
+                .name("scope")
+                .map(|m| m.as_str().trim().to_string())
+                .filter(|s| !s.is_empty()),
+            description: caps["desc"].trim().to_string(),
+            breaking,
+        })
+    }
+
+    /**
+        Returns the version bump that this commit requires, if any.
+
+        Only breaking changes, features and fixes require a release, other
+        kinds of commits such as `docs` or `chore` do not bump the version.
+    */
+    pub fn bump(&self) -> Option<VersionBump> {
+        if self.breaking {
+            return Some(VersionBump::Major);
+        }
+        match self.kind.as_str() {
+            "feat" => Some(VersionBump::Minor),
+            "fix" | "perf" => Some(VersionBump::Patch),
+            _ => None,
+        }
+    }
+
+    /**
+        Returns the changelog section that this commit belongs in, if any.
+    */
+    pub fn section(&self) -> Option<ChangelogSection> {
+        if self.breaking {
+            return Some(ChangelogSection::Breaking);
+        }
+        match self.kind.as_str() {
+            "feat" => Some(ChangelogSection::Added),
+            "fix" => Some(ChangelogSection::Fixed),
+            "perf" | "refactor" => Some(ChangelogSection::Changed),
+            _ => None,
+        }
+    }
+
+    /**
+        Returns the changelog entry for this commit, with its
+        scope as a prefix, and the first letter capitalized.
+    */
+    pub fn entry(&self) -> String {
+        let mut chars = self.description.chars();
+        let description = chars.next().map_or_else(String::new, |first| {
+            first.to_uppercase().collect::<String>() + chars.as_str()
+        });
+        match &self.scope {
+            Some(scope) => format!("`{scope}`: {description}"),
+            None => description,
+        }
+    }
+}
+
+/**
+    Returns the largest version bump required by any of the given commits.
+*/
+pub fn required_bump(commits: &[ConventionalCommit]) -> Option<VersionBump> {
+    commits.iter().filter_map(ConventionalCommit::bump).max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commits() {
+        let commit = ConventionalCommit::parse("feat(net): add websocket server", "").unwrap();
+        assert_eq!(commit.kind, "feat");
+        assert_eq!(commit.scope.as_deref(), Some("net"));
+        assert_eq!(commit.entry(), "`net`: Add websocket server");
+        assert!(!commit.breaking);
+
+        assert!(
+            ConventionalCommit::parse("fix!: drop old api", "")
+                .unwrap()
+                .breaking
+        );
+        assert!(
+            ConventionalCommit::parse("refactor: rework", "BREAKING CHANGE: it is gone")
+                .unwrap()
+                .breaking
+        );
+        assert!(ConventionalCommit::parse("Merge branch 'main'", "").is_none());
+    }
+
+    #[test]
+    fn bumps_from_commits() {
+        let parse = |subject| ConventionalCommit::parse(subject, "").unwrap();
+        assert_eq!(required_bump(&[parse("docs: typo")]), None);
+        assert_eq!(
+            required_bump(&[parse("fix: a"), parse("feat: b")]),
+            Some(VersionBump::Minor)
+        );
+        assert_eq!(
+            required_bump(&[parse("fix: a"), parse("feat!: b")]),
+            Some(VersionBump::Major)
+        );
+    }
+
+    #[test]
+    fn apply_bumps() {
+        let version = Version::parse("1.2.3-rc.1").unwrap();
+        assert_eq!(VersionBump::Major.apply(&version).to_string(), "2.0.0");
+        assert_eq!(VersionBump::Minor.apply(&version).to_string(), "1.3.0");
+        assert_eq!(VersionBump::Patch.apply(&version).to_string(), "1.2.4");
+    }
+}
@@ -0,0 +1,216 @@
+use std::{collections::BTreeMap, sync::LazyLock};
+
+use chrono::{Datelike, NaiveDate};
+use regex::Regex;
+
+use super::commits::{ChangelogSection, ConventionalCommit};
+
+static VERSION_FIELD_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^(?P<key>\s*version\s*=\s*)(?P<quote>["'])[^"']*["']"#)
+        .expect("version field regex is valid")
+});
+
+/**
+    Reads the `version` field of the `[package]` table in the given TOML manifest.
+*/
+pub fn read_package_version(contents: &str) -> Option<String> {
+    let manifest = toml::from_str::<toml::Table>(contents).ok()?;
+    manifest
+        .get("package")?
+        .get("version")?
+        .as_str()
+        .map(ToString::to_string)
+}
+
+/**
+    Replaces the `version` field of the `[package]` table in the given TOML
+    manifest, keeping all other formatting and comments in the file as-is.
+
+    Returns `None` if the manifest has no `version` field in a `[package]` table.
+*/
+pub fn set_package_version(contents: &str, version: &str) -> Option<String> {
+    let mut in_package = false;
+    let mut replaced = false;
+    let mut output = String::with_capacity(contents.len());
+    for line in contents.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            in_package = trimmed.split('#').next().unwrap_or_default().trim() == "[package]";
+        } else if in_package && !replaced && VERSION_FIELD_REGEX.is_match(line) {
+            let line = VERSION_FIELD_REGEX.replace(line, |caps: &regex::Captures| {
+                format!(
+                    "{}{quote}{version}{quote}",
+                    &caps["key"],
+                    quote = &caps["quote"]
+                )
+            });
+            output.push_str(&line);
+            replaced = true;
+            continue;
+        }
+        output.push_str(line);
+    }
+    replaced.then_some(output)
+}
+
+/**
+    Formats a date the same way as the headings of Lune's own changelog, such as `April 1st, 2026`.
+*/
+pub fn format_changelog_date(date: NaiveDate) -> String {
+    let day = date.day();
+    let suffix = match (day % 10, day % 100) {
+        (1, 11) | (2, 12) | (3, 13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{} {day}{suffix}, {}", date.format("%B"), date.year())
+}
+
+/**
+    Creates the entries of a changelog section from the given commits, grouped by kind.
+
+    Returns an empty string if none of the commits belong in a changelog.
+*/
+pub fn changelog_entries(commits: &[ConventionalCommit]) -> String {
+    let mut sections = BTreeMap::<ChangelogSection, Vec<String>>::new();
+    for commit in commits {
+        if let Some(section) = commit.section() {
+            sections.entry(section).or_default().push(commit.entry());
+        }
+    }
+
+    let mut entries = String::new();
+    for (section, lines) in sections {
+        entries.push_str(&format!("### {}\n\n", section.title()));
+        for line in lines {
+            entries.push_str(&format!("- {line}\n"));
+        }
+        entries.push('\n');
+    }
+    entries
+}
+
+/**
+    Adds a section for a new release to the given changelog.
+
+    If the changelog has an `## Unreleased` section, it is turned into the
+    section for the release, and the given entries are only added to it if
+    it has no entries of its own. Otherwise, a new section is added before
+    the section of the latest release, or at the end of the changelog.
+*/
+pub fn add_changelog_release(contents: &str, version: &str, date: &str, entries: &str) -> String {
+    let heading = format!("## `{version}` - {date}");
+
+    let lines = contents.split_inclusive('\n').collect::<Vec<_>>();
+    let is_heading = |line: &str| line.starts_with("## ");
+    let unreleased = lines
+        .iter()
+        .position(|line| is_heading(line) && line[3..].trim().eq_ignore_ascii_case("unreleased"));
+
+    if let Some(start) = unreleased {
+        let end = lines[start + 1..]
+            .iter()
+            .position(|line| is_heading(line))
+            .map_or(lines.len(), |i| start + 1 + i);
+        let is_empty = lines[start + 1..end]
+            .iter()
+            .all(|line| line.trim().is_empty());
+
+        let mut output = lines[..start].concat();
+        output.push_str(&heading);
+        output.push_str("\n\n");
+        if is_empty {
+            output.push_str(entries);
+        } else {
+            output.push_str(lines[start + 1..end].concat().trim_start_matches('\n'));
+            if !output.ends_with("\n\n") {
+                output.push('\n');
+            }
+        }
+        output.push_str(&lines[end..].concat());
+        return output;
+    }
+
+    let section = format!("{heading}\n\n{entries}");
+    match lines.iter().position(|line| is_heading(line)) {
+        Some(index) => lines[..index].concat() + &section + &lines[index..].concat(),
+        None => {
+            let mut output = contents.trim_end().to_string();
+            if !output.is_empty() {
+                output.push_str("\n\n");
+            }
+            output + section.trim_end() + "\n"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_version_in_package_table() {
+        let manifest = "[dependencies]\nversion = \"x\"\n\n[package]\nname = \"tool\"\nversion = \"1.0.0\" # current\n";
+        let updated = set_package_version(manifest, "1.1.0").unwrap();
+        assert_eq!(
+            updated,
+            "[dependencies]\nversion = \"x\"\n\n[package]\nname = \"tool\"\nversion = \"1.1.0\" # current\n"
+        );
+        assert_eq!(read_package_version(&updated).as_deref(), Some("1.1.0"));
+        assert!(set_package_version("[package]\nname = \"tool\"\n", "1.0.0").is_none());
+    }
+
+    #[test]
+    fn format_dates() {
+        let date = |d| format_changelog_date(NaiveDate::from_ymd_opt(2026, 4, d).unwrap());
+        assert_eq!(date(1), "April 1st, 2026");
+        assert_eq!(date(12), "April 12th, 2026");
+        assert_eq!(date(22), "April 22nd, 2026");
+        assert_eq!(date(23), "April 23rd, 2026");
+    }
+
+    #[test]
+    fn entries_are_grouped() {
+        let commits = ["feat: one", "fix: two", "docs: three", "feat!: four"]
+            .iter()
+            .filter_map(|s| ConventionalCommit::parse(s, ""))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changelog_entries(&commits),
+            "### Breaking Changes\n\n- Four\n\n### Added\n\n- One\n\n### Fixed\n\n- Two\n\n"
+        );
+    }
+
+    #[test]
+    fn release_replaces_empty_unreleased() {
+        let changelog = "# Changelog\n\n## Unreleased\n\n## `1.0.0` - April 1st, 2026\n\n- Old\n";
+        let updated = add_changelog_release(changelog, "1.1.0", "May 1st, 2026", "- New\n\n");
+        assert_eq!(
+            updated,
+            "# Changelog\n\n## `1.1.0` - May 1st, 2026\n\n- New\n\n## `1.0.0` - April 1st, 2026\n\n- Old\n"
+        );
+    }
+
+    #[test]
+    fn release_keeps_curated_unreleased() {
+        let changelog =
+            "# Changelog\n\n## Unreleased\n\n- Curated\n\n## `1.0.0` - April 1st, 2026\n";
+        let updated = add_changelog_release(changelog, "1.1.0", "May 1st, 2026", "- New\n\n");
+        assert_eq!(
+            updated,
+            "# Changelog\n\n## `1.1.0` - May 1st, 2026\n\n- Curated\n\n## `1.0.0` - April 1st, 2026\n"
+        );
+    }
+
+    #[test]
+    fn release_inserted_before_latest() {
+        let changelog = "# Changelog\n\n## `1.0.0` - April 1st, 2026\n";
+        let updated = add_changelog_release(changelog, "1.1.0", "May 1st, 2026", "- New\n\n");
+        assert_eq!(
+            updated,
+            "# Changelog\n\n## `1.1.0` - May 1st, 2026\n\n- New\n\n## `1.0.0` - April 1st, 2026\n"
+        );
+    }
+}
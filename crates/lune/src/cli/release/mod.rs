@@ -0,0 +1,269 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::{Command, ExitCode},
+};
+
+use anyhow::{Context, Result, bail};
+use async_fs as fs;
+use blocking::unblock;
+use chrono::Local;
+use clap::Parser;
+use console::style;
+use semver::Version;
+
+use super::build::BuildCommand;
+use super::utils::config::{FILE_NAME_PROJECT_CONFIG, ProjectConfig};
+
+mod commits;
+mod files;
+
+use self::commits::{ConventionalCommit, VersionBump, required_bump};
+use self::files::{
+    add_changelog_release, changelog_entries, format_changelog_date, read_package_version,
+    set_package_version,
+};
+
+/// Manifests with a `[package]` table that has a version to bump
+const VERSIONED_MANIFESTS: &[&str] = &[FILE_NAME_PROJECT_CONFIG, "wally.toml"];
+
+/// Release a new version of the project in the current directory
+#[derive(Debug, Clone, Parser)]
+pub struct ReleaseCommand {
+    /// The version to release, or `major`, `minor` or `patch` to bump the
+    /// current version - defaults to the bump needed by the conventional
+    /// commits made since the latest release tag
+    pub version: Option<String>,
+
+    /// Path to the changelog to add the release to
+    #[clap(long, default_value = "CHANGELOG.md")]
+    pub changelog: PathBuf,
+
+    /// Prefix for the names of release tags
+    #[clap(long, default_value = "v")]
+    pub tag_prefix: String,
+
+    /// Only update files, without creating a release commit and tag
+    #[clap(long)]
+    pub no_git: bool,
+
+    /// Build standalone binaries of the given entry point once
+    /// the release has been tagged, using `lune build`
+    #[clap(long, value_name = "ENTRY")]
+    pub build: Option<PathBuf>,
+
+    /// Targets to build standalone binaries for - may be given
+    /// multiple times, and defaults to the current system
+    #[clap(long = "target", value_name = "TARGET", requires = "build")]
+    pub targets: Vec<String>,
+
+    /// Directory to write standalone binaries to
+    #[clap(long, default_value = "dist", requires = "build")]
+    pub dist_dir: PathBuf,
+
+    /// Show the release that would be made, without changing anything
+    #[clap(long)]
+    pub dry_run: bool,
+}
+
+impl ReleaseCommand {
+    pub async fn run(self) -> Result<ExitCode> {
+        let cwd = env::current_dir().context("failed to get current directory")?;
+
+        let mut manifests = Vec::new();
+        for name in VERSIONED_MANIFESTS {
+            let path = cwd.join(name);
+            if let Ok(contents) = fs::read_to_string(&path).await
+                && let Some(version) = read_package_version(&contents)
+            {
+                manifests.push((path, contents, version));
+            }
+        }
+
+        let latest_tag = if self.no_git {
+            None
+        } else {
+            let pattern = format!("{}*", self.tag_prefix);
+            git(&["describe", "--tags", "--abbrev=0", "--match", &pattern])
+                .await
+                .ok()
+                .map(|tag| tag.trim().to_string())
+                .filter(|tag| !tag.is_empty())
+        };
+
+        let current = manifests
+            .first()
+            .map(|(_, _, version)| version.clone())
+            .or_else(|| {
+                latest_tag
+                    .as_deref()
+                    .and_then(|tag| tag.strip_prefix(&self.tag_prefix))
+                    .map(ToString::to_string)
+            })
+            .unwrap_or_else(|| "0.0.0".to_string());
+        let current = Version::parse(&current)
+            .with_context(|| format!("current version '{current}' is not a valid version"))?;
+
+        let commits = if self.no_git {
+            Vec::new()
+        } else {
+            commits_since(latest_tag.as_deref()).await?
+        };
+        let version = self.next_version(&current, &commits, latest_tag.as_deref())?;
+        let tag = format!("{}{version}", self.tag_prefix);
+
+        let date = format_changelog_date(Local::now().date_naive());
+        let entries = changelog_entries(&commits);
+
+        println!(
+            "Releasing {} (previously {})",
+            style(&version).green(),
+            style(&current).dim()
+        );
+        if self.dry_run {
+            if !entries.is_empty() {
+                println!("\n{}", entries.trim_end());
+            }
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        if !self.no_git && !git(&["status", "--porcelain"]).await?.trim().is_empty() {
+            bail!(
+                "the working tree has uncommitted changes, commit or stash them before releasing"
+            );
+        }
+
+        let mut changed = Vec::new();
+        for (path, contents, _) in &manifests {
+            if let Some(updated) = set_package_version(contents, &version.to_string()) {
+                fs::write(path, updated)
+                    .await
+                    .with_context(|| format!("failed to write {}", path.display()))?;
+                println!("Updated version in {}", style(path.display()).blue());
+                changed.push(path.clone());
+            }
+        }
+
+        let changelog = fs::read_to_string(&self.changelog)
+            .await
+            .unwrap_or_else(|_| "# Changelog\n".to_string());
+        let changelog = add_changelog_release(&changelog, &version.to_string(), &date, &entries);
+        fs::write(&self.changelog, changelog)
+            .await
+            .with_context(|| format!("failed to write {}", self.changelog.display()))?;
+        println!("Updated {}", style(self.changelog.display()).blue());
+        changed.push(self.changelog.clone());
+
+        if !self.no_git {
+            let mut add = vec!["add".to_string(), "--".to_string()];
+            add.extend(changed.iter().map(|path| path.display().to_string()));
+            git(&add.iter().map(String::as_str).collect::<Vec<_>>()).await?;
+            let message = format!("Release {tag}");
+            git(&["commit", "-m", &message]).await?;
+            git(&["tag", "-a", &tag, "-m", &message]).await?;
+            println!("Created release commit and tag {}", style(&tag).green());
+        }
+
+        if let Some(entry) = &self.build {
+            self.build_binaries(entry, &version).await?;
+        }
+
+        if !self.no_git {
+            println!(
+                "Released {} - push it using {}",
+                style(&version).green(),
+                style("git push --follow-tags").cyan()
+            );
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+
+    fn next_version(
+        &self,
+        current: &Version,
+        commits: &[ConventionalCommit],
+        latest_tag: Option<&str>,
+    ) -> Result<Version> {
+        if let Some(version) = &self.version {
+            if let Ok(bump) = version.parse::<VersionBump>() {
+                return Ok(bump.apply(current));
+            }
+            let version = version.strip_prefix(&self.tag_prefix).unwrap_or(version);
+            return Version::parse(version)
+                .with_context(|| format!("'{version}' is not a valid version or version bump"));
+        }
+        match required_bump(commits) {
+            Some(bump) => Ok(bump.apply(current)),
+            None => bail!(
+                "no features, fixes or breaking changes have been committed since {} - \
+                give a version or version bump to release anyway",
+                latest_tag.unwrap_or("the first commit")
+            ),
+        }
+    }
+
+    async fn build_binaries(&self, entry: &Path, version: &Version) -> Result<()> {
+        let config_dir = entry.parent().unwrap_or(Path::new("."));
+        let config = ProjectConfig::discover(config_dir).await?;
+        let name = config.package.name.clone().unwrap_or_else(|| {
+            entry
+                .file_stem()
+                .map_or_else(|| "lune".to_string(), |s| s.to_string_lossy().to_string())
+        });
+
+        let targets = if self.targets.is_empty() {
+            vec![None]
+        } else {
+            self.targets.iter().map(Some).collect()
+        };
+        for target in targets {
+            let suffix = target.map_or_else(String::new, |target| format!("-{target}"));
+            let output = self.dist_dir.join(format!("{name}-{version}{suffix}"));
+
+            let mut args = vec![
+                "build".into(),
+                entry.as_os_str().to_owned(),
+                "--output".into(),
+                output.into_os_string(),
+            ];
+            if let Some(target) = target {
+                args.push("--target".into());
+                args.push(target.into());
+            }
+            let command = BuildCommand::try_parse_from(args)?;
+            Box::pin(command.run()).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Run git with the given arguments in the current directory, returning its output
+async fn git(args: &[&str]) -> Result<String> {
+    let args = args.iter().map(ToString::to_string).collect::<Vec<_>>();
+    let description = args.join(" ");
+    let output = unblock(move || Command::new("git").args(args).output())
+        .await
+        .context("failed to run git - is it installed?")?;
+    if !output.status.success() {
+        bail!(
+            "git {description} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Get all conventional commits made after the given tag, or all commits if there is no tag
+async fn commits_since(tag: Option<&str>) -> Result<Vec<ConventionalCommit>> {
+    let range = tag.map_or_else(|| "HEAD".to_string(), |tag| format!("{tag}..HEAD"));
+    let log = git(&["log", "--reverse", "--format=%s%x1f%b%x1e", &range]).await?;
+    Ok(log
+        .split('\x1e')
+        .filter_map(|commit| {
+            let (subject, body) = commit.trim_start().split_once('\x1f')?;
+            ConventionalCommit::parse(subject, body)
+        })
+        .collect())
+}
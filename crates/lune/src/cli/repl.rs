@@ -1,12 +1,20 @@
-use std::{path::PathBuf, process::ExitCode};
+use std::{
+    collections::BTreeMap,
+    env,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use async_fs as fs;
+use blocking::unblock;
 use clap::Parser;
 use directories::UserDirs;
 use rustyline::{DefaultEditor, error::ReadlineError};
 
 use lune::Runtime;
+use lune_utils::luaurc::MergedAliases;
+use lune_utils::path::clean_path;
 
 const MESSAGE_WELCOME: &str = concat!("Lune v", env!("CARGO_PKG_VERSION"));
 const MESSAGE_INTERRUPT: &str = "Interrupt: ^C again to exit";
@@ -16,6 +24,160 @@ enum PromptState {
     Continuation,
 }
 
+const MESSAGE_COMMANDS: &str = "expected :inspect, :load, :reload, :aliases, :save or :restore";
+
+/**
+    A command given to the REPL instead of code, starting with a colon.
+*/
+#[derive(Debug, PartialEq, Eq)]
+enum MetaCommand<'a> {
+    /// Opens the interactive inspector for the value of an expression
+    Inspect(&'a str),
+    /// Requires a module into a global with the given name
+    Load { name: &'a str, path: &'a str },
+    /// Requires all loaded modules again, or only the one with the given name
+    Reload(Option<&'a str>),
+    /// Shows the aliases that requires in the current directory resolve
+    Aliases,
+    /// Saves all code that ran successfully in this session to a file
+    Save(&'a str),
+    /// Runs the code in a file, such as one written using `:save`
+    Restore(&'a str),
+}
+
+impl<'a> MetaCommand<'a> {
+    /**
+        Parses a meta command from a line of input, if it starts with a colon.
+    */
+    fn parse(line: &'a str) -> Option<Result<Self, String>> {
+        let line = line.trim().strip_prefix(':')?;
+        let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let args = rest.split_whitespace().collect::<Vec<_>>();
+        Some(match (name, args.as_slice()) {
+            ("inspect", []) => Err("usage: :inspect <expression>".to_string()),
+            ("inspect", _) => Ok(Self::Inspect(rest)),
+            ("load", [name, path]) if is_identifier(name) => Ok(Self::Load { name, path }),
+            ("load", _) => Err("usage: :load <name> <path>".to_string()),
+            ("reload", []) => Ok(Self::Reload(None)),
+            ("reload", [name]) => Ok(Self::Reload(Some(name))),
+            ("reload", _) => Err("usage: :reload [name]".to_string()),
+            ("aliases", []) => Ok(Self::Aliases),
+            ("aliases", _) => Err("usage: :aliases".to_string()),
+            ("save", []) => Err("usage: :save <path>".to_string()),
+            ("save", _) => Ok(Self::Save(rest)),
+            ("restore", []) => Err("usage: :restore <path>".to_string()),
+            ("restore", _) => Ok(Self::Restore(rest)),
+            _ => Err(format!("unknown command ':{name}', {MESSAGE_COMMANDS}")),
+        })
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/**
+    Returns the source code that requires the module at `path` into the global `name`.
+*/
+fn load_source(name: &str, path: &str) -> String {
+    format!("{name} = require({path:?})")
+}
+
+/**
+    State of a REPL session - the modules loaded into it using `:load`,
+    and all of the code that ran successfully, for saving it using `:save`.
+*/
+#[derive(Debug, Default)]
+struct Session {
+    modules: BTreeMap<String, String>,
+    script: Vec<String>,
+}
+
+impl Session {
+    /**
+        Runs the given meta command, returning the source code to run for it, if any.
+    */
+    async fn run_command(
+        &mut self,
+        runtime: &Runtime,
+        command: MetaCommand<'_>,
+    ) -> Result<Option<String>> {
+        Ok(match command {
+            MetaCommand::Inspect(expression) => {
+                Some(format!("require(\"@lune/stdio\").inspect(({expression}))"))
+            }
+            MetaCommand::Load { name, path } => {
+                self.modules.insert(name.to_string(), path.to_string());
+                Some(load_source(name, path))
+            }
+            MetaCommand::Reload(name) => {
+                let modules = match name {
+                    None => self.modules.iter().collect::<Vec<_>>(),
+                    Some(name) => match self.modules.get_key_value(name) {
+                        Some(module) => vec![module],
+                        None => bail!("no module named '{name}' was loaded using :load"),
+                    },
+                };
+                if modules.is_empty() {
+                    bail!("no modules were loaded using :load");
+                }
+                // Modules that a reloaded module requires may also have been
+                // edited, so the whole cache is cleared instead of single entries
+                runtime.clear_module_cache()?;
+                let source = modules
+                    .into_iter()
+                    .map(|(name, path)| load_source(name, path))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                Some(source)
+            }
+            MetaCommand::Aliases => {
+                let cwd = env::current_dir().context("failed to get current directory")?;
+                let aliases = discover_aliases(&cwd).await;
+                if aliases.is_empty() {
+                    println!("No aliases found in .luaurc files or package manifests");
+                }
+                for (alias, path) in aliases {
+                    println!("@{alias} -> {}", path.display());
+                }
+                None
+            }
+            MetaCommand::Save(path) => {
+                let mut contents = self.script.join("\n\n");
+                contents.push('\n');
+                fs::write(path, contents)
+                    .await
+                    .with_context(|| format!("failed to write session to {path}"))?;
+                println!("Saved {} chunks to {path}", self.script.len());
+                None
+            }
+            MetaCommand::Restore(path) => Some(
+                fs::read_to_string(path)
+                    .await
+                    .with_context(|| format!("failed to read session from {path}"))?,
+            ),
+        })
+    }
+}
+
+/**
+    Finds all aliases in `.luaurc` files and package manifests in the given directory and
+    its ancestors, resolved to paths - merged the same way as in `require`, where nested
+    `.luaurc` files inherit the aliases of their ancestors and may override them.
+*/
+async fn discover_aliases(dir: &Path) -> BTreeMap<String, PathBuf> {
+    let dir = dir.to_path_buf();
+    let merged = unblock(move || MergedAliases::discover(&dir)).await;
+    merged
+        .iter()
+        .map(|(alias, path)| (alias.to_string(), clean_path(path)))
+        .collect()
+}
+
 /// Launch an interactive REPL (default)
 #[derive(Debug, Clone, Default, Parser)]
 pub struct ReplCommand {}
@@ -40,6 +202,7 @@ impl ReplCommand {
         let mut source_code = String::new();
 
         let mut lune_instance = Runtime::new()?;
+        let mut session = Session::default();
 
         loop {
             let prompt = match prompt_state {
@@ -58,7 +221,23 @@ impl ReplCommand {
                     repl.save_history(history_file_path)?;
 
                     match prompt_state {
-                        PromptState::Regular => source_code = code,
+                        PromptState::Regular => match MetaCommand::parse(&code) {
+                            Some(Ok(command)) => {
+                                match session.run_command(&lune_instance, command).await {
+                                    Ok(Some(source)) => source_code = source,
+                                    Ok(None) => continue,
+                                    Err(err) => {
+                                        eprintln!("{err:#}");
+                                        continue;
+                                    }
+                                }
+                            }
+                            Some(Err(message)) => {
+                                eprintln!("{message}");
+                                continue;
+                            }
+                            None => source_code = code,
+                        },
                         PromptState::Continuation => source_code.push_str(&code),
                     }
                 }
@@ -85,7 +264,10 @@ impl ReplCommand {
             }
 
             match lune_instance.run_custom("REPL", &source_code).await {
-                Ok(_) => prompt_state = PromptState::Regular,
+                Ok(_) => {
+                    prompt_state = PromptState::Regular;
+                    session.script.push(source_code.clone());
+                }
 
                 Err(err) => {
                     if err.is_incomplete_input() {
@@ -103,3 +285,56 @@ impl ReplCommand {
         Ok(ExitCode::SUCCESS)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_meta_commands() {
+        assert_eq!(MetaCommand::parse("print(1)"), None);
+        assert_eq!(
+            MetaCommand::parse(" :inspect  { a = 1 } "),
+            Some(Ok(MetaCommand::Inspect("{ a = 1 }")))
+        );
+        assert!(matches!(MetaCommand::parse(":inspect"), Some(Err(_))));
+        assert!(matches!(MetaCommand::parse(":unknown"), Some(Err(_))));
+    }
+
+    #[test]
+    fn parses_workspace_commands() {
+        assert_eq!(
+            MetaCommand::parse(":load utils ./src/utils"),
+            Some(Ok(MetaCommand::Load {
+                name: "utils",
+                path: "./src/utils"
+            }))
+        );
+        assert!(matches!(MetaCommand::parse(":load 1x ./a"), Some(Err(_))));
+        assert!(matches!(MetaCommand::parse(":load utils"), Some(Err(_))));
+        assert_eq!(
+            MetaCommand::parse(":reload"),
+            Some(Ok(MetaCommand::Reload(None)))
+        );
+        assert_eq!(
+            MetaCommand::parse(":reload utils"),
+            Some(Ok(MetaCommand::Reload(Some("utils"))))
+        );
+        assert_eq!(
+            MetaCommand::parse(":aliases"),
+            Some(Ok(MetaCommand::Aliases))
+        );
+        assert_eq!(
+            MetaCommand::parse(":save session.luau"),
+            Some(Ok(MetaCommand::Save("session.luau")))
+        );
+    }
+
+    #[test]
+    fn load_source_quotes_paths() {
+        assert_eq!(
+            load_source("utils", "@project/utils"),
+            "utils = require(\"@project/utils\")"
+        );
+    }
+}
@@ -1,13 +1,26 @@
-use std::{env, io::stdin, process::ExitCode};
+use std::{env, io::stdin, path::PathBuf, process::ExitCode};
 
 use anyhow::{Context, Result};
 use blocking::Unblock;
 use clap::Parser;
 use futures_lite::prelude::*;
 
+use directories::BaseDirs;
 use lune::Runtime;
+use lune_utils::{
+    path::{get_current_dir, set_project_file_name},
+    remote::{LOCKFILE_NAME, RemoteRequires},
+    require_trace::RequireTrace,
+};
 
-use super::utils::files::discover_script_path_including_lune_dirs;
+use crate::require_trace::TraceOutput;
+
+use super::{
+    build::parse_key_value,
+    utils::{
+        api_usage::ApiUsage, config::ProjectConfig, files::discover_script_path_including_lune_dirs,
+    },
+};
 
 /// Run a script
 #[derive(Debug, Clone, Parser)]
@@ -16,6 +29,37 @@ pub struct RunCommand {
     pub(super) script_path: String,
     /// Arguments to pass to the script, stored in process.args
     pub(super) script_args: Vec<String>,
+    /// Record which functions of the standard libraries the script calls, and
+    /// write them to the given path as JSON once it finishes, along with the
+    /// cargo features needed for a trimmed build of Lune that can run it
+    #[clap(long, value_name = "PATH")]
+    pub(super) api_usage: Option<PathBuf>,
+    /// Watch the files of required modules, and reload modules once their files
+    /// change, calling their `__onReload` function if any - the script keeps
+    /// running for as long as modules are being watched
+    #[clap(long)]
+    pub(super) hot_reload: bool,
+    /// Allow requiring remote modules using their URLs, such as
+    /// `require("https://example.com/module.luau")` - downloaded modules
+    /// are cached, and their hashes recorded in `lune.lock` in the current
+    /// directory, so that later runs are reproducible and work offline
+    #[clap(long)]
+    pub(super) allow_url_requires: bool,
+    /// Record every require made by the script, with the path it was resolved to,
+    /// the module that made it, and the time spent on it - printed to stderr as a
+    /// tree once the script exits, or written to the given path as JSON
+    #[clap(long, value_name = "PATH", num_args = 0..=1, require_equals = true)]
+    pub(super) trace_requires: Option<Option<PathBuf>>,
+    /// Add an alias for `require`, such as `pkg=./vendor/pkg` for `require("@pkg/module")`,
+    /// without needing a `.luaurc` file - relative paths are resolved from the current
+    /// directory, and these aliases take precedence over those in `.luaurc` files
+    #[clap(long = "alias", value_name = "NAME=PATH", value_parser = parse_key_value)]
+    pub(super) aliases: Vec<(String, String)>,
+    /// Only allow requiring modules inside of the given directory, or with the given
+    /// prefix, such as `@lune/` or `https://example.com/` - may be given more than once,
+    /// and requiring anything else fails, for running untrusted scripts such as plugins
+    #[clap(long = "allow-require", value_name = "DIR|PREFIX")]
+    pub(super) allowed_requires: Option<Vec<String>>,
 }
 
 impl RunCommand {
@@ -25,32 +69,90 @@ impl RunCommand {
             .ok()
             .is_some_and(|s| matches!(s.as_str(), "0" | "false" | "off"));
 
+        // Figure out if we should run stdin or run a file,
+        // reading from stdin is marked by passing a single "-"
+        // (dash) as the script name to run to the cli
+        let file_path = if &self.script_path == "-" {
+            None
+        } else {
+            Some(discover_script_path_including_lune_dirs(&self.script_path)?)
+        };
+
+        // Read the project config closest to the script, or the current directory for stdin
+        let config_dir = file_path
+            .as_ref()
+            .and_then(|path| path.parent())
+            .map_or_else(|| get_current_dir().to_path_buf(), Into::into);
+        let config = ProjectConfig::discover(&config_dir).await?;
+        set_project_file_name(config.rojo.project_file);
+
         // Create a new lune runtime with all globals & run the script
         let mut rt = Runtime::new()?
             .with_args(self.script_args)
-            .with_jit(!jit_disabled);
+            .with_jit(!jit_disabled)
+            .with_exit_codes(config.exit_codes)
+            .with_print_limits(config.print)
+            .with_hot_reload(self.hot_reload)
+            .with_aliases(self.aliases)?;
+        if self.allow_url_requires {
+            rt = rt.with_remote_requires(remote_requires()?);
+        }
+        if let Some(allowed) = self.allowed_requires {
+            rt = rt.with_require_allow_list(allowed);
+        }
+        if let Some(capabilities) = config.capabilities {
+            rt = rt.with_capabilities(capabilities.mode, capabilities.grant);
+        }
+        let trace_output = match self.trace_requires.clone() {
+            Some(Some(path)) => Some(TraceOutput::Json(path)),
+            Some(None) => Some(TraceOutput::Tree),
+            None => TraceOutput::from_env(),
+        };
+        let trace = trace_output.as_ref().map(|_| RequireTrace::new());
+        if let Some(trace) = &trace {
+            rt = rt.with_require_trace(trace.clone());
+        }
+        let api_usage = self.api_usage.as_ref().map(|_| ApiUsage::default());
+        if let Some(api_usage) = &api_usage {
+            rt = api_usage.instrument(rt);
+        }
 
-        // Figure out if we should run stdin or run a file,
-        // reading from stdin is marked by passing a single "-"
-        // (dash) as the script name to run to the cli
-        let result = if &self.script_path == "-" {
+        let result = if let Some(file_path) = file_path {
+            rt.run_file(file_path).await
+        } else {
             let mut stdin_contents = Vec::new();
             Unblock::new(stdin())
                 .read_to_end(&mut stdin_contents)
                 .await
                 .context("Failed to read script contents from stdin")?;
             rt.run_custom("stdin", stdin_contents).await
-        } else {
-            let file_path = discover_script_path_including_lune_dirs(&self.script_path)?;
-            rt.run_file(file_path).await
         };
 
+        if let (Some(api_usage), Some(path)) = (&api_usage, &self.api_usage) {
+            api_usage.write_to(path).await?;
+        }
+        if let (Some(output), Some(trace)) = (&trace_output, &trace) {
+            output.write(trace).await?;
+        }
+
         Ok(match result {
             Err(err) => {
                 eprintln!("{err}");
-                ExitCode::FAILURE
+                ExitCode::from(config.exit_codes.runtime_error)
             }
             Ok(values) => ExitCode::from(values.status()),
         })
     }
 }
+
+/**
+    Options for requiring remote modules, using the lockfile in the current
+    directory and the cache of remote modules in the user's home directory.
+*/
+pub(crate) fn remote_requires() -> Result<RemoteRequires> {
+    let dirs = BaseDirs::new().context("failed to find user home directory")?;
+    Ok(RemoteRequires {
+        lockfile: get_current_dir().join(LOCKFILE_NAME),
+        cache_dir: dirs.home_dir().join(".lune").join("cache").join("remote"),
+    })
+}
@@ -0,0 +1,81 @@
+use super::{ServiceDefinition, escape_xml};
+
+/**
+    Generates a launchd property list for the given service, which
+    runs it once loaded, and restarts it whenever it fails.
+*/
+pub(super) fn plist(service: &ServiceDefinition) -> String {
+    let string = |text: &str| format!("<string>{}</string>", escape_xml(text));
+
+    let mut lines = vec![
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#.to_string(),
+        r#"<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">"#.to_string(),
+        r#"<plist version="1.0">"#.to_string(),
+        "<dict>".to_string(),
+        "\t<key>Label</key>".to_string(),
+        format!("\t{}", string(&service.name)),
+        "\t<key>ProgramArguments</key>".to_string(),
+        "\t<array>".to_string(),
+        format!("\t\t{}", string(&service.program.to_string_lossy())),
+    ];
+    for arg in &service.args {
+        lines.push(format!("\t\t{}", string(arg)));
+    }
+    lines.extend([
+        "\t</array>".to_string(),
+        "\t<key>WorkingDirectory</key>".to_string(),
+        format!("\t{}", string(&service.working_dir.to_string_lossy())),
+    ]);
+    if !service.env.is_empty() {
+        lines.push("\t<key>EnvironmentVariables</key>".to_string());
+        lines.push("\t<dict>".to_string());
+        for (key, value) in &service.env {
+            lines.push(format!("\t\t<key>{}</key>", escape_xml(key)));
+            lines.push(format!("\t\t{}", string(value)));
+        }
+        lines.push("\t</dict>".to_string());
+    }
+    lines.extend([
+        "\t<key>RunAtLoad</key>".to_string(),
+        "\t<true/>".to_string(),
+        "\t<key>KeepAlive</key>".to_string(),
+        "\t<dict>".to_string(),
+        "\t\t<key>SuccessfulExit</key>".to_string(),
+        "\t\t<false/>".to_string(),
+        "\t</dict>".to_string(),
+        "</dict>".to_string(),
+        "</plist>".to_string(),
+    ]);
+
+    let mut plist = lines.join("\n");
+    plist.push('\n');
+    plist
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_service;
+    use super::*;
+
+    #[test]
+    fn generates_plist() {
+        let plist = plist(&test_service());
+        assert!(plist.contains("\t<key>Label</key>\n\t<string>my-daemon</string>\n"));
+        assert!(plist.contains(
+            "\t<array>\n\
+            \t\t<string>/opt/my daemon/my-daemon</string>\n\
+            \t\t<string>--port</string>\n\
+            \t\t<string>8080</string>\n\
+            \t</array>\n"
+        ));
+        assert!(plist.contains("\t\t<key>LOG_LEVEL</key>\n\t\t<string>debug 100%</string>\n"));
+        assert!(plist.ends_with("</dict>\n</plist>\n"));
+    }
+
+    #[test]
+    fn omits_empty_environment() {
+        let mut service = test_service();
+        service.env.clear();
+        assert!(!plist(&service).contains("EnvironmentVariables"));
+    }
+}
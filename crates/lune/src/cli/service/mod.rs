@@ -0,0 +1,385 @@
+use std::{
+    env::consts::OS,
+    path::{Path, PathBuf},
+    process::{Command, ExitCode},
+};
+
+use anyhow::{Context, Result, bail};
+use async_fs as fs;
+use blocking::unblock;
+use clap::{Parser, Subcommand};
+use console::style;
+use directories::BaseDirs;
+
+use lune_bundler::Metadata;
+use lune_utils::path::clean_path_and_make_absolute;
+
+use super::build::parse_key_value;
+
+mod launchd;
+mod systemd;
+mod winsw;
+
+/// Manage standalone binaries as services of the operating system
+#[derive(Debug, Clone, Parser)]
+pub struct ServiceCommand {
+    #[clap(subcommand)]
+    subcommand: ServiceSubcommand,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum ServiceSubcommand {
+    Install(ServiceInstallCommand),
+}
+
+impl ServiceCommand {
+    pub async fn run(self) -> Result<ExitCode> {
+        match self.subcommand {
+            ServiceSubcommand::Install(cmd) => cmd.run().await,
+        }
+    }
+}
+
+/// Install a standalone binary as a service that starts with the system - a systemd
+/// unit on Linux, a launchd job on macOS, or a Windows service wrapped by WinSW
+#[derive(Debug, Clone, Parser)]
+struct ServiceInstallCommand {
+    /// The standalone binary to install, built using `lune build`
+    binary: PathBuf,
+
+    /// Name of the service - defaults to the file name of the binary
+    #[clap(long)]
+    name: Option<String>,
+
+    /// Description of the service
+    #[clap(long)]
+    description: Option<String>,
+
+    /// Install the service for the current user only, instead of the whole system
+    #[clap(long)]
+    user: bool,
+
+    /// Argument to pass to the binary - may be given multiple times
+    #[clap(long = "arg", value_name = "ARG", allow_hyphen_values = true)]
+    args: Vec<String>,
+
+    /// Environment variable to set for the binary - may be given multiple times
+    #[clap(long = "env", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    env: Vec<(String, String)>,
+
+    /// Directory to run the binary in - defaults to the directory of the binary
+    #[clap(long)]
+    working_dir: Option<PathBuf>,
+
+    /// Path to the WinSW executable that wraps the binary as a Windows service
+    #[clap(long, value_name = "PATH")]
+    winsw: Option<PathBuf>,
+
+    /// Register the service without starting it
+    #[clap(long)]
+    no_start: bool,
+
+    /// Print the service definition, without installing anything
+    #[clap(long)]
+    print: bool,
+}
+
+impl ServiceInstallCommand {
+    async fn run(self) -> Result<ExitCode> {
+        let binary = clean_path_and_make_absolute(&self.binary);
+        let contents = fs::read(&binary)
+            .await
+            .with_context(|| format!("failed to read {}", binary.display()))?;
+        if !Metadata::is_standalone(&contents) {
+            bail!(
+                "{} is not a standalone binary - build one using `lune build` first",
+                binary.display()
+            );
+        }
+
+        let service = self.service(binary)?;
+        let platform = ServicePlatform::current()?;
+        if self.user && platform == ServicePlatform::Windows {
+            bail!("services can only be installed for the whole system on Windows");
+        }
+
+        if self.print {
+            print!("{}", platform.definition(&service));
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        let path = match platform {
+            ServicePlatform::Systemd => self.install_systemd(&service).await?,
+            ServicePlatform::Launchd => self.install_launchd(&service).await?,
+            ServicePlatform::Windows => self.install_winsw(&service).await?,
+        };
+
+        println!(
+            "Installed service {} at {}{}",
+            style(&service.name).green(),
+            style(path.display()).dim(),
+            if self.no_start {
+                ""
+            } else {
+                ", and started it"
+            }
+        );
+        Ok(ExitCode::SUCCESS)
+    }
+
+    fn service(&self, binary: PathBuf) -> Result<ServiceDefinition> {
+        let name = match &self.name {
+            Some(name) => name.clone(),
+            None => binary
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .context("binary has no file name")?
+                .to_string(),
+        };
+        if !is_service_name(&name) {
+            bail!(
+                "invalid service name '{name}' - names may only contain \
+                ASCII letters, digits, dashes, underscores, dots and @"
+            );
+        }
+
+        let working_dir = match &self.working_dir {
+            Some(dir) => clean_path_and_make_absolute(dir),
+            None => binary
+                .parent()
+                .map(Path::to_path_buf)
+                .context("binary has no parent directory")?,
+        };
+
+        Ok(ServiceDefinition {
+            description: self.description.clone().unwrap_or_else(|| name.clone()),
+            name,
+            program: binary,
+            args: self.args.clone(),
+            env: self.env.clone(),
+            working_dir,
+            user: self.user,
+        })
+    }
+
+    async fn install_systemd(&self, service: &ServiceDefinition) -> Result<PathBuf> {
+        let dir = if self.user {
+            BaseDirs::new()
+                .context("failed to find the home directory")?
+                .config_dir()
+                .join("systemd")
+                .join("user")
+        } else {
+            PathBuf::from("/etc/systemd/system")
+        };
+        let path = dir.join(format!("{}.service", service.name));
+        write_definition(&path, &systemd::unit(service)).await?;
+
+        let mut reload = Vec::new();
+        let mut enable = Vec::new();
+        if self.user {
+            reload.push("--user");
+            enable.push("--user");
+        }
+        reload.push("daemon-reload");
+        enable.push("enable");
+        if !self.no_start {
+            enable.push("--now");
+        }
+        enable.push(&service.name);
+        run_command("systemctl", &reload).await?;
+        run_command("systemctl", &enable).await?;
+
+        Ok(path)
+    }
+
+    async fn install_launchd(&self, service: &ServiceDefinition) -> Result<PathBuf> {
+        let dir = if self.user {
+            BaseDirs::new()
+                .context("failed to find the home directory")?
+                .home_dir()
+                .join("Library")
+                .join("LaunchAgents")
+        } else {
+            PathBuf::from("/Library/LaunchDaemons")
+        };
+        let path = dir.join(format!("{}.plist", service.name));
+        write_definition(&path, &launchd::plist(service)).await?;
+
+        // NOTE: Jobs are started as soon as they are loaded, since they
+        // run at load, and any job that is not loaded now is loaded on boot
+        if !self.no_start {
+            let path = path.to_string_lossy();
+            run_command("launchctl", &["load", "-w", &path]).await?;
+        }
+
+        Ok(path)
+    }
+
+    async fn install_winsw(&self, service: &ServiceDefinition) -> Result<PathBuf> {
+        let Some(winsw) = &self.winsw else {
+            bail!(
+                "installing Windows services requires WinSW, which runs the binary as a service - \
+                download it from https://github.com/winsw/winsw/releases and pass its path using --winsw"
+            );
+        };
+
+        // WinSW reads its configuration from the file next
+        // to its executable, which has the same file name
+        let dir = service
+            .program
+            .parent()
+            .context("binary has no parent directory")?;
+        let wrapper = dir.join(format!("{}-service.exe", service.name));
+        let path = dir.join(format!("{}-service.xml", service.name));
+        fs::copy(winsw, &wrapper)
+            .await
+            .with_context(|| format!("failed to copy WinSW to {}", wrapper.display()))?;
+        write_definition(&path, &winsw::config(service)).await?;
+
+        let wrapper = wrapper.to_string_lossy();
+        run_command(&wrapper, &["install"]).await?;
+        if !self.no_start {
+            run_command(&wrapper, &["start"]).await?;
+        }
+
+        Ok(path)
+    }
+}
+
+/**
+    A service that runs a standalone binary, which the definition
+    files of each service manager are generated from.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ServiceDefinition {
+    name: String,
+    description: String,
+    /// Absolute path to the standalone binary
+    program: PathBuf,
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+    working_dir: PathBuf,
+    /// Whether the service is installed for the current user only
+    user: bool,
+}
+
+/// The service manager of the current system
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ServicePlatform {
+    Systemd,
+    Launchd,
+    Windows,
+}
+
+impl ServicePlatform {
+    fn current() -> Result<Self> {
+        Ok(match OS {
+            "linux" => Self::Systemd,
+            "macos" => Self::Launchd,
+            "windows" => Self::Windows,
+            os => bail!("installing services is not supported on {os}"),
+        })
+    }
+
+    fn definition(self, service: &ServiceDefinition) -> String {
+        match self {
+            Self::Systemd => systemd::unit(service),
+            Self::Launchd => launchd::plist(service),
+            Self::Windows => winsw::config(service),
+        }
+    }
+}
+
+/// Service names may only contain characters that every service manager accepts
+fn is_service_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@'))
+}
+
+/// Escapes the given text for use in the contents of an XML element or attribute
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+async fn write_definition(path: &Path, contents: &str) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .await
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    fs::write(path, contents).await.with_context(|| {
+        format!(
+            "failed to write {} - installing services for the whole \
+            system may require elevated permissions, or use --user",
+            path.display()
+        )
+    })
+}
+
+async fn run_command(program: &str, args: &[&str]) -> Result<()> {
+    let program = program.to_string();
+    let args = args.iter().map(ToString::to_string).collect::<Vec<_>>();
+    let description = format!("{program} {}", args.join(" "));
+    let output = unblock(move || Command::new(program).args(args).output())
+        .await
+        .with_context(|| format!("failed to run {description}"))?;
+    if !output.status.success() {
+        bail!(
+            "{description} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+fn test_service() -> ServiceDefinition {
+    ServiceDefinition {
+        name: "my-daemon".to_string(),
+        description: "My \"daemon\" & friends".to_string(),
+        program: PathBuf::from("/opt/my daemon/my-daemon"),
+        args: vec!["--port".to_string(), "8080".to_string()],
+        env: vec![("LOG_LEVEL".to_string(), "debug 100%".to_string())],
+        working_dir: PathBuf::from("/opt/my daemon"),
+        user: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_service_names() {
+        assert!(is_service_name("my-daemon"));
+        assert!(is_service_name("com.example.daemon"));
+        assert!(is_service_name("worker@1"));
+        assert!(!is_service_name(""));
+        assert!(!is_service_name(".hidden"));
+        assert!(!is_service_name("my daemon"));
+        assert!(!is_service_name("../escape"));
+    }
+
+    #[test]
+    fn escapes_xml() {
+        assert_eq!(
+            escape_xml(r#"<a href="x">Tom & 'Jerry'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &apos;Jerry&apos;&lt;/a&gt;"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use super::ServiceDefinition;
+
+/**
+    Generates a systemd unit for the given service.
+
+    Reloading the unit sends `SIGHUP` to the binary, which
+    scripts can handle using `runtime.onReload`.
+*/
+pub(super) fn unit(service: &ServiceDefinition) -> String {
+    let mut exec_start = quote_word(&service.program.to_string_lossy());
+    for arg in &service.args {
+        exec_start.push(' ');
+        exec_start.push_str(&quote_word(arg));
+    }
+
+    let mut lines = vec![
+        "[Unit]".to_string(),
+        format!("Description={}", escape_specifiers(&service.description)),
+        "After=network.target".to_string(),
+        String::new(),
+        "[Service]".to_string(),
+        "Type=simple".to_string(),
+        format!("ExecStart={exec_start}"),
+        "ExecReload=/bin/kill -HUP $MAINPID".to_string(),
+        format!(
+            "WorkingDirectory={}",
+            escape_specifiers(&service.working_dir.to_string_lossy())
+        ),
+    ];
+    for (key, value) in &service.env {
+        let assignment = format!("{key}={value}");
+        lines.push(format!("Environment={}", quote_string(&assignment)));
+    }
+    lines.extend([
+        "Restart=on-failure".to_string(),
+        "RestartSec=5".to_string(),
+        String::new(),
+        "[Install]".to_string(),
+        format!(
+            "WantedBy={}",
+            if service.user {
+                "default.target"
+            } else {
+                "multi-user.target"
+            }
+        ),
+    ]);
+
+    let mut unit = lines.join("\n");
+    unit.push('\n');
+    unit
+}
+
+/// Escapes specifiers such as `%h`, which systemd expands in most settings
+fn escape_specifiers(text: &str) -> String {
+    text.replace('%', "%%")
+}
+
+/// Quotes the given text, escaping quotes, backslashes and specifiers
+fn quote_string(text: &str) -> String {
+    let escaped = escape_specifiers(text)
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"");
+    format!("\"{escaped}\"")
+}
+
+/// Quotes a single word of a command line, if needed, which also
+/// escapes variables such as `$HOME`, since systemd expands those too
+fn quote_word(word: &str) -> String {
+    let needs_quotes = word.is_empty()
+        || word
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | ';'));
+    let word = word.replace('$', "$$");
+    if needs_quotes {
+        quote_string(&word)
+    } else {
+        escape_specifiers(&word)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_service;
+    use super::*;
+
+    #[test]
+    fn generates_unit() {
+        let unit = unit(&test_service());
+        assert!(unit.starts_with("[Unit]\nDescription=My \"daemon\" & friends\n"));
+        assert!(unit.contains("\nExecStart=\"/opt/my daemon/my-daemon\" --port 8080\n"));
+        assert!(unit.contains("\nExecReload=/bin/kill -HUP $MAINPID\n"));
+        assert!(unit.contains("\nWorkingDirectory=/opt/my daemon\n"));
+        assert!(unit.contains("\nEnvironment=\"LOG_LEVEL=debug 100%%\"\n"));
+        assert!(unit.ends_with("\n[Install]\nWantedBy=multi-user.target\n"));
+    }
+
+    #[test]
+    fn user_units_are_wanted_by_default_target() {
+        let mut service = test_service();
+        service.user = true;
+        assert!(unit(&service).ends_with("\nWantedBy=default.target\n"));
+    }
+
+    #[test]
+    fn quotes_words() {
+        assert_eq!(quote_word("plain"), "plain");
+        assert_eq!(quote_word(""), "\"\"");
+        assert_eq!(quote_word("two words"), "\"two words\"");
+        assert_eq!(quote_word(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(quote_word("$HOME/100%"), "$$HOME/100%%");
+    }
+}
@@ -0,0 +1,109 @@
+use std::iter::repeat_n;
+
+use super::{ServiceDefinition, escape_xml};
+
+/**
+    Generates a WinSW configuration for the given service, which
+    starts it with the system, and restarts it whenever it fails.
+
+    WinSW is what runs the binary as a Windows service, since
+    Windows services must respond to the service control manager.
+*/
+pub(super) fn config(service: &ServiceDefinition) -> String {
+    let element = |name: &str, text: &str| format!("\t<{name}>{}</{name}>", escape_xml(text));
+
+    let arguments = service
+        .args
+        .iter()
+        .map(|arg| quote_arg(arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut lines = vec![
+        "<service>".to_string(),
+        element("id", &service.name),
+        element("name", &service.name),
+        element("description", &service.description),
+        element("executable", &service.program.to_string_lossy()),
+    ];
+    if !arguments.is_empty() {
+        lines.push(element("arguments", &arguments));
+    }
+    lines.push(element(
+        "workingdirectory",
+        &service.working_dir.to_string_lossy(),
+    ));
+    for (key, value) in &service.env {
+        lines.push(format!(
+            "\t<env name=\"{}\" value=\"{}\"/>",
+            escape_xml(key),
+            escape_xml(value)
+        ));
+    }
+    lines.extend([
+        "\t<startmode>Automatic</startmode>".to_string(),
+        "\t<onfailure action=\"restart\" delay=\"5 sec\"/>".to_string(),
+        "\t<log mode=\"roll\"/>".to_string(),
+        "</service>".to_string(),
+    ]);
+
+    let mut config = lines.join("\n");
+    config.push('\n');
+    config
+}
+
+/// Quotes a single argument of a command line, if needed, the way
+/// that Windows programs split their command lines into arguments
+fn quote_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+        return arg.to_string();
+    }
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        if c == '\\' {
+            backslashes += 1;
+            continue;
+        }
+        // Backslashes are only escapes when followed by a quote
+        let escapes = if c == '"' {
+            backslashes * 2 + 1
+        } else {
+            backslashes
+        };
+        quoted.extend(repeat_n('\\', escapes));
+        quoted.push(c);
+        backslashes = 0;
+    }
+    quoted.extend(repeat_n('\\', backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_service;
+    use super::*;
+
+    #[test]
+    fn generates_config() {
+        let config = config(&test_service());
+        assert!(config.starts_with("<service>\n\t<id>my-daemon</id>\n"));
+        assert!(
+            config.contains("\t<description>My &quot;daemon&quot; &amp; friends</description>\n")
+        );
+        assert!(config.contains("\t<executable>/opt/my daemon/my-daemon</executable>\n"));
+        assert!(config.contains("\t<arguments>--port 8080</arguments>\n"));
+        assert!(config.contains("\t<env name=\"LOG_LEVEL\" value=\"debug 100%\"/>\n"));
+        assert!(config.ends_with("</service>\n"));
+    }
+
+    #[test]
+    fn quotes_args() {
+        assert_eq!(quote_arg("plain"), "plain");
+        assert_eq!(quote_arg(""), "\"\"");
+        assert_eq!(quote_arg(r"C:\Program Files\"), r#""C:\Program Files\\""#);
+        assert_eq!(quote_arg(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(quote_arg(r#"a\"b"#), r#""a\\\"b""#);
+    }
+}
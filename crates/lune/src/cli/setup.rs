@@ -1,21 +1,43 @@
-use std::{io::ErrorKind, process::ExitCode};
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::LazyLock,
+};
 
 use anyhow::{Context, Result};
 use async_fs as fs;
+use blocking::unblock;
 use clap::Parser;
 use directories::UserDirs;
+use lune_utils::path::{append_extension, clean_path, get_current_dir, is_data_file};
+use regex::Regex;
 use thiserror::Error;
 
 use serde_json::Value as JsonValue;
 
 const LUAURC_PATH: &str = ".luaurc";
 
+/// First line of type definitions generated for data files, which
+/// are only ever overwritten if they still start with this line
+const DATA_TYPEDEF_HEADER: &str = "-- Type definitions for a data file, generated by `lune setup`";
+
+/// Directories that are never searched for requires of data files
+const DATA_TYPEDEF_SKIPPED_DIRS: &[&str] = &["Packages", "node_modules", "target"];
+
+static RELATIVE_REQUIRE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"require\s*\(?\s*["'](\.\.?/[^"']+)["']"#).expect("valid require regex")
+});
+
 /// Set up type definitions for your editor
 #[derive(Debug, Clone, Parser)]
 pub struct SetupCommand {
     // Skip updating the .luaurc file
     #[arg(long = "no-update-luaurc")]
     pub no_update_luaurc: bool,
+    // Skip generating type definitions for required data files
+    #[arg(long = "no-data-typedefs")]
+    pub no_data_typedefs: bool,
 }
 
 impl SetupCommand {
@@ -32,6 +54,17 @@ impl SetupCommand {
             write_luaurc(luaurc).await?;
         }
 
+        if !self.no_data_typedefs {
+            let cwd = get_current_dir();
+            let count = unblock(move || generate_data_file_typedefs(&cwd)).await?;
+            if count > 0 {
+                println!(
+                    "Generated type definitions for {count} required data file{}.",
+                    if count == 1 { "" } else { "s" }
+                );
+            }
+        }
+
         println!(
             "Type definitions for Lune v{} have been set up successfully.\
             \nYou may need to restart your editor for the changes to take effect.",
@@ -137,3 +170,107 @@ async fn generate_typedef_files_from_definitions() -> Result<String> {
     }
     Ok(version_string.to_string())
 }
+
+/**
+    Finds all data files, such as `config.json`, that are required using relative
+    paths from Luau files in the given directory, and writes a type definition file
+    next to each of them, typing the decoded value as `unknown` for editors.
+
+    Editors resolve `require("./config.json")` to `config.json.luau`, which is
+    never loaded by Lune itself, since data files are always resolved first.
+
+    Returns the number of type definition files that were written.
+*/
+fn generate_data_file_typedefs(dir: &Path) -> Result<usize> {
+    let mut luau_files = Vec::new();
+    find_luau_files(dir, &mut luau_files)?;
+
+    let mut data_files = luau_files
+        .iter()
+        .filter_map(|path| Some((path.parent()?, std::fs::read_to_string(path).ok()?)))
+        .flat_map(|(parent, source)| {
+            required_data_files(&source)
+                .into_iter()
+                .map(|required| clean_path(parent.join(required)))
+                .collect::<Vec<_>>()
+        })
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    data_files.sort();
+    data_files.dedup();
+
+    let mut count = 0;
+    for data_file in data_files {
+        let typedef_path = append_extension(&data_file, "luau");
+        let is_generated = match std::fs::read_to_string(&typedef_path) {
+            Ok(existing) => existing.starts_with(DATA_TYPEDEF_HEADER),
+            Err(e) => e.kind() == ErrorKind::NotFound,
+        };
+        if !is_generated {
+            continue;
+        }
+        let file_name = data_file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let contents = format!(
+            "{DATA_TYPEDEF_HEADER}\n\
+            -- The value is decoded from '{file_name}' when required\n\
+            return (nil :: any) :: unknown\n"
+        );
+        std::fs::write(&typedef_path, contents)
+            .with_context(|| format!("Failed to write {}", typedef_path.display()))?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn find_luau_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if !name.starts_with('.') && !DATA_TYPEDEF_SKIPPED_DIRS.contains(&name.as_str()) {
+                find_luau_files(&path, files)?;
+            }
+        } else if file_type.is_file()
+            && path
+                .extension()
+                .is_some_and(|ext| ext == "luau" || ext == "lua")
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Finds the relative paths of all data files required in the given source
+fn required_data_files(source: &str) -> Vec<&str> {
+    RELATIVE_REQUIRE_REGEX
+        .captures_iter(source)
+        .filter_map(|captures| captures.get(1))
+        .map(|found| found.as_str())
+        .filter(|path| is_data_file(path))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_required_data_files() {
+        let source = r#"
+            local config = require("./config.json")
+            local settings = require "../settings.yaml"
+            local module = require("./module")
+            local absolute = require("/data.toml")
+        "#;
+        assert_eq!(
+            required_data_files(source),
+            vec!["./config.json", "../settings.yaml"]
+        );
+    }
+}
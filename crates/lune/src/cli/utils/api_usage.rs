@@ -0,0 +1,170 @@
+use std::{cell::RefCell, collections::BTreeMap, path::Path, rc::Rc};
+
+use anyhow::{Context, Result};
+use async_fs as fs;
+use mlua::prelude::*;
+use serde::Serialize;
+
+use lune::Runtime;
+
+/// Luau code creating wrappers that record calls - these must be written
+/// in Luau, and not Rust, so that wrapped functions are still able to yield
+const WRAPPER_FACTORY: &str = r"
+local record = ...
+return function(name, inner)
+    return function(...)
+        record(name)
+        return inner(...)
+    end
+end
+";
+
+/// Nested tables of standard libraries deeper than this are not instrumented
+const MAX_DEPTH: usize = 2;
+
+type CallCounts = BTreeMap<String, BTreeMap<String, u64>>;
+
+/**
+    Usage of standard library functions during a single run, for `lune run --api-usage`.
+
+    Only functions stored in library tables, or in tables nested in them, are recorded,
+    such as `fs.readFile` - methods of the values they return are not, and neither are
+    libraries that are required without calling any of their functions.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct ApiUsage {
+    calls: Rc<RefCell<CallCounts>>,
+}
+
+#[derive(Debug, Serialize)]
+struct ApiUsageReport<'a> {
+    /// Cargo features of `lune` needed for the libraries that were used
+    features: Vec<String>,
+    /// Number of calls to each function, by library
+    libraries: &'a CallCounts,
+}
+
+impl ApiUsage {
+    /**
+        Instruments all standard libraries of the given runtime, recording calls to them.
+    */
+    #[must_use]
+    pub fn instrument(&self, rt: Runtime) -> Runtime {
+        let calls = Rc::clone(&self.calls);
+        rt.with_std_module_hook(move |lua, library, module| {
+            instrument_module(lua, &calls, library, &module)
+        })
+    }
+
+    /**
+        Writes the recorded usage to the given path, as JSON.
+
+        # Errors
+
+        - If the usage could not be serialized or written
+    */
+    pub async fn write_to(&self, path: &Path) -> Result<()> {
+        let calls = self.calls.borrow();
+        let report = ApiUsageReport {
+            features: calls
+                .keys()
+                .map(|library| format!("std-{library}"))
+                .collect(),
+            libraries: &calls,
+        };
+        let json = serde_json::to_vec_pretty(&report).context("failed to serialize API usage")?;
+        drop(calls);
+        fs::write(path, json)
+            .await
+            .with_context(|| format!("failed to write API usage to {}", path.display()))
+    }
+}
+
+/// Instruments the module table of a single standard library
+fn instrument_module(
+    lua: &Lua,
+    calls: &Rc<RefCell<CallCounts>>,
+    library: &str,
+    module: &LuaTable,
+) -> LuaResult<LuaTable> {
+    let calls = Rc::clone(calls);
+    let library = library.to_string();
+    let record = lua.create_function(move |_, name: String| {
+        *calls
+            .borrow_mut()
+            .entry(library.clone())
+            .or_default()
+            .entry(name)
+            .or_default() += 1;
+        Ok(())
+    })?;
+    let factory = lua
+        .load(WRAPPER_FACTORY)
+        .set_name("=api_usage")
+        .call::<LuaFunction>(record)?;
+    instrument_table(lua, &factory, module, "", 0)
+}
+
+/// Copies the given table, wrapping all of its functions, and those of
+/// any nested tables, to record calls - standard library tables are
+/// usually readonly, so they can not be instrumented in place
+fn instrument_table(
+    lua: &Lua,
+    factory: &LuaFunction,
+    table: &LuaTable,
+    prefix: &str,
+    depth: usize,
+) -> LuaResult<LuaTable> {
+    let instrumented = lua.create_table()?;
+    for pair in table.pairs::<LuaValue, LuaValue>() {
+        let (key, value) = pair?;
+        let value = match (&key, value) {
+            (LuaValue::String(name), LuaValue::Function(inner)) => {
+                let name = format!("{prefix}{}", name.to_string_lossy());
+                LuaValue::Function(factory.call::<LuaFunction>((name, inner))?)
+            }
+            (LuaValue::String(name), LuaValue::Table(inner)) if depth < MAX_DEPTH => {
+                let prefix = format!("{prefix}{}.", name.to_string_lossy());
+                LuaValue::Table(instrument_table(lua, factory, &inner, &prefix, depth + 1)?)
+            }
+            (_, value) => value,
+        };
+        instrumented.raw_set(key, value)?;
+    }
+    instrumented.set_metatable(table.metatable())?;
+    instrumented.set_readonly(table.is_readonly());
+    Ok(instrumented)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_calls() {
+        let lua = Lua::new();
+        let module = lua
+            .load("return { read = function(x) return x end, nested = { write = function() end } }")
+            .eval::<LuaTable>()
+            .unwrap();
+        module.set_readonly(true);
+
+        let usage = ApiUsage::default();
+        let module = instrument_module(&lua, &usage.calls, "fs", &module).unwrap();
+        assert!(module.is_readonly());
+
+        let read = module.get::<LuaFunction>("read").unwrap();
+        assert_eq!(read.call::<i32>(5).unwrap(), 5);
+        read.call::<()>(()).unwrap();
+        let nested = module.get::<LuaTable>("nested").unwrap();
+        nested
+            .get::<LuaFunction>("write")
+            .unwrap()
+            .call::<()>(())
+            .unwrap();
+
+        let calls = usage.calls.borrow();
+        assert_eq!(calls["fs"]["read"], 2);
+        assert_eq!(calls["fs"]["nested.write"], 1);
+    }
+}
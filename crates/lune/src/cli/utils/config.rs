@@ -0,0 +1,138 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use async_fs as fs;
+use serde::Deserialize;
+
+use lune::{CapabilityMode, ExitCodes};
+use lune_utils::fmt::FormatLimits;
+
+/// Name of the project configuration file, searched for in the directory
+/// of the script being run or built, and all of its ancestor directories
+pub const FILE_NAME_PROJECT_CONFIG: &str = "lune.toml";
+
+/**
+    Project configuration, read from a `lune.toml` file.
+
+    ```toml
+    [exit-codes]
+    runtime-error = 1
+    require-error = 3
+    sandbox-violation = 4
+    timeout = 124
+
+    [capabilities]
+    mode = "enforce"
+    grant = ["net", "fs-read"]
+
+    [print]
+    max-items = 1000
+    max-string-length = 10000
+    max-output-length = 1000000
+
+    [rojo]
+    project-file = "game.project.json"
+
+    [package]
+    name = "tool"
+    version = "1.2.3"
+    description = "A tool that does things"
+    url = "https://example.com/releases/v{version}/{file}"
+    ```
+*/
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Process exit codes to use for each class of failure
+    pub exit_codes: ExitCodes,
+    /// Capabilities granted to scripts, checked against the `--!requires`
+    /// directives of required modules - not checked if the table is missing
+    pub capabilities: Option<CapabilitiesConfig>,
+    /// Limits for values given to `print`, past which they are elided - each
+    /// limit may be set to `0` to disable it, and `print.full` ignores them
+    pub print: FormatLimits,
+    /// Settings for Rojo project files, used by `require` and the `script` global
+    pub rojo: RojoConfig,
+    /// Metadata for packaging manifests created using `lune build --package`
+    pub package: PackageConfig,
+}
+
+/**
+    Capabilities granted to scripts that are run, read from
+    the `[capabilities]` table of a `lune.toml` file.
+*/
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct CapabilitiesConfig {
+    /// Whether missing capabilities are errors (`enforce`) or warnings (`audit`)
+    pub mode: CapabilityMode,
+    /// Names of the capabilities granted to the script being run
+    pub grant: Vec<String>,
+}
+
+/**
+    Settings for Rojo project files, such as `default.project.json`,
+    read from the `[rojo]` table of a `lune.toml` file.
+*/
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RojoConfig {
+    /// Name of the project file to use in directories with several project
+    /// files - `default.project.json` or a single project file is used otherwise
+    pub project_file: Option<String>,
+}
+
+/**
+    Metadata for packaging manifests, such as Homebrew formulae,
+    Scoop manifests and Debian packages, read from the `[package]`
+    table of a `lune.toml` file.
+*/
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PackageConfig {
+    /// Name of the package - defaults to the name of the binary
+    pub name: Option<String>,
+    /// Version of the package - defaults to the version given using
+    /// `--file-version`, or the `VERSION` value given using `--define`
+    pub version: Option<String>,
+    /// Short, single line description of the package
+    pub description: Option<String>,
+    /// URL of the homepage of the package
+    pub homepage: Option<String>,
+    /// License of the package, as an SPDX identifier
+    pub license: Option<String>,
+    /// Maintainer of the package, such as `Name <name@example.com>`
+    pub maintainer: Option<String>,
+    /// URL that the binary will be published at, where `{name}`, `{version}`,
+    /// `{target}` and `{file}` are replaced with the values for the build
+    pub url: Option<String>,
+}
+
+impl ProjectConfig {
+    /**
+        Finds the closest `lune.toml` file, starting from the given
+        directory, and reads the project configuration from it.
+
+        Returns the default configuration if no `lune.toml` file was found.
+
+        # Errors
+
+        - If a `lune.toml` file was found, but could not be read or is invalid
+    */
+    pub async fn discover(start_dir: &Path) -> Result<Self> {
+        let Some(path) = find_config_file(start_dir) else {
+            return Ok(Self::default());
+        };
+        let contents = fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("invalid {}", path.display()))
+    }
+}
+
+fn find_config_file(start_dir: &Path) -> Option<PathBuf> {
+    start_dir
+        .ancestors()
+        .map(|dir| dir.join(FILE_NAME_PROJECT_CONFIG))
+        .find(|path| path.is_file())
+}
@@ -1,2 +1,4 @@
+pub mod api_usage;
+pub mod config;
 pub mod files;
 pub mod listing;
@@ -5,4 +5,13 @@ mod rt;
 #[cfg(test)]
 mod tests;
 
-pub use crate::rt::{Runtime, RuntimeError, RuntimeResult, RuntimeReturnValues};
+pub use crate::rt::{
+    BuildInfo, BundledFile, BundledStorage, CapabilityMode, ExitCodes, NamespaceManifest,
+    NamespaceModule, OutputBuffer, RequireResolver, Runtime, RuntimeError, RuntimeErrorFrame,
+    RuntimeErrorReport, RuntimeFailure, RuntimeReport, RuntimeResult, RuntimeReturnValues,
+};
+
+#[cfg(feature = "std-events")]
+pub use crate::rt::RuntimeHandle;
+#[cfg(feature = "std-events")]
+pub use lune_std::EventSubscription;
@@ -5,7 +5,9 @@ use std::{io::stderr, process::ExitCode};
 #[cfg(feature = "cli")]
 pub(crate) mod cli;
 
+pub(crate) mod require_trace;
 pub(crate) mod standalone;
+pub(crate) mod terminal;
 
 use lune_utils::fmt::Label;
 
@@ -20,12 +22,19 @@ fn main() -> ExitCode {
         .init();
 
     async_io::block_on(async {
-        if let Some(bin) = standalone::check().await {
-            return standalone::run(bin).await.unwrap();
+        if let Some(bin) = standalone::check() {
+            return match standalone::run(bin).await {
+                Ok(code) => code,
+                Err(err) => {
+                    eprintln!("{}\n{err:?}", Label::Error);
+                    ExitCode::FAILURE
+                }
+            };
         }
 
         #[cfg(feature = "cli")]
         {
+            let _console = terminal::setup(true);
             match cli::Cli::new().run().await {
                 Ok(code) => code,
                 Err(err) => {
@@ -0,0 +1,82 @@
+/*!
+    Tracing of requires, for diagnosing slow startup and unexpected dependencies.
+
+    Tracing is enabled using `lune run --trace-requires`, or by setting the
+    `LUNE_TRACE_REQUIRE` environment variable, which also works for standalone
+    binaries. Once the script exits, every require that it made is printed to
+    stderr as a tree, or written to a file as JSON when a path is given, such
+    as using `--trace-requires=trace.json` or `LUNE_TRACE_REQUIRE=trace.json`.
+*/
+
+use std::{env, path::PathBuf};
+
+use anyhow::{Context, Result};
+use async_fs as fs;
+
+use lune_utils::require_trace::RequireTrace;
+
+/// Environment variable that enables tracing, either when set to a truthy value, or to a path
+const TRACE_ENV_VAR: &str = "LUNE_TRACE_REQUIRE";
+
+/**
+    Where a trace of requires is written to once the script exits.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum TraceOutput {
+    /// Printed to stderr, as a tree
+    Tree,
+    /// Written to the given path, as JSON
+    Json(PathBuf),
+}
+
+impl TraceOutput {
+    /**
+        Returns where to write a trace of requires to, if enabled using the environment.
+    */
+    pub(crate) fn from_env() -> Option<Self> {
+        Self::parse(&env::var(TRACE_ENV_VAR).ok()?)
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "" | "0" | "false" | "off" => None,
+            "1" | "true" | "on" => Some(Self::Tree),
+            path => Some(Self::Json(PathBuf::from(path))),
+        }
+    }
+
+    /**
+        Writes the requires recorded in the given trace.
+
+        # Errors
+
+        - If the trace should be written to a file, and it could not be written
+    */
+    pub(crate) async fn write(&self, trace: &RequireTrace) -> Result<()> {
+        match self {
+            Self::Tree => {
+                eprint!("{}", trace.format_tree());
+                Ok(())
+            }
+            Self::Json(path) => fs::write(path, trace.to_json())
+                .await
+                .with_context(|| format!("failed to write require trace to {}", path.display())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_env_values() {
+        assert_eq!(TraceOutput::parse("0"), None);
+        assert_eq!(TraceOutput::parse(""), None);
+        assert_eq!(TraceOutput::parse("1"), Some(TraceOutput::Tree));
+        assert_eq!(
+            TraceOutput::parse("trace.json"),
+            Some(TraceOutput::Json(PathBuf::from("trace.json")))
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use std::sync::{Arc, Mutex};
+
+use lune_utils::fmt::{FormatLimits, ValueFormatConfig, pretty_format_multi_value};
+use mlua::prelude::*;
+
+/// Buffer that captured output is written to, instead of stdout or stderr
+pub type OutputBuffer = Arc<Mutex<Vec<u8>>>;
+
+// Captured output is not written to a terminal, so it should not be colored
+const FORMAT_CONFIG: ValueFormatConfig = ValueFormatConfig::new()
+    .with_max_depth(4)
+    .with_colors_enabled(false);
+
+/**
+    Replaces the `print` and `warn` globals with
+    versions that write to the given output buffer.
+
+    Like the `print` global it replaces, `print` is a callable
+    table, with a `full` function that ignores any print limits.
+*/
+pub(super) fn capture_globals(lua: &Lua, stdout: &OutputBuffer) -> LuaResult<()> {
+    let print_output = Arc::clone(stdout);
+    let print_call = lua.create_function(move |lua, (_, args): (LuaValue, LuaMultiValue)| {
+        let limits = lua
+            .app_data_ref::<FormatLimits>()
+            .map(|limits| *limits)
+            .unwrap_or_default();
+        let config = FORMAT_CONFIG.with_limits(limits);
+        write_line(&print_output, &pretty_format_multi_value(&args, &config));
+        Ok(())
+    })?;
+    let print_output = Arc::clone(stdout);
+    let print_full = lua.create_function(move |_, args: LuaMultiValue| {
+        write_line(
+            &print_output,
+            &pretty_format_multi_value(&args, &FORMAT_CONFIG),
+        );
+        Ok(())
+    })?;
+    let print = lua.create_table_from([("full", print_full)])?;
+    print.set_metatable(Some(lua.create_table_from([("__call", print_call)])?))?;
+    print.set_readonly(true);
+
+    let warn_output = Arc::clone(stdout);
+    let warn = lua.create_function(move |_, args: LuaMultiValue| {
+        let message = pretty_format_multi_value(&args, &FORMAT_CONFIG);
+        write_line(&warn_output, &format!("[WARN] {message}"));
+        Ok(())
+    })?;
+
+    lua.globals().set("print", print)?;
+    lua.globals().set("warn", warn)?;
+    Ok(())
+}
+
+/**
+    Replaces the `write` and `ewrite` functions of the `stdio`
+    standard library with versions that write to the given buffers.
+
+    Other libraries are returned unchanged.
+*/
+pub(super) fn capture_std_module(
+    lua: &Lua,
+    library: &str,
+    module: LuaTable,
+    stdout: &OutputBuffer,
+    stderr: &OutputBuffer,
+) -> LuaResult<LuaTable> {
+    if library != "stdio" {
+        return Ok(module);
+    }
+
+    let captured = lua.create_table()?;
+    for pair in module.pairs::<LuaValue, LuaValue>() {
+        let (key, value) = pair?;
+        captured.raw_set(key, value)?;
+    }
+
+    for (name, output) in [("write", stdout), ("ewrite", stderr)] {
+        let output = Arc::clone(output);
+        let func = lua.create_function(move |_, s: LuaString| {
+            output.lock().unwrap().extend_from_slice(&s.as_bytes());
+            Ok(())
+        })?;
+        captured.raw_set(name, func)?;
+    }
+
+    if module.is_readonly() {
+        captured.set_readonly(true);
+    }
+
+    Ok(captured)
+}
+
+pub(super) fn write_line(output: &OutputBuffer, line: &str) {
+    let mut output = output.lock().unwrap();
+    output.extend_from_slice(line.as_bytes());
+    output.push(b'\n');
+}
@@ -0,0 +1,44 @@
+use lune_std::{EventBridge, EventSubscription};
+use serde_json::Value as JsonValue;
+
+/**
+    A handle to a [`Runtime`](super::Runtime), for exchanging events with
+    scripts that use the `events` standard library, from any thread.
+
+    Handles are cheap to clone, and may be sent to other threads, such as
+    the threads of a game engine or GUI application that Lune is embedded in.
+*/
+#[derive(Debug, Clone)]
+pub struct RuntimeHandle {
+    bridge: EventBridge,
+}
+
+impl RuntimeHandle {
+    pub(super) fn new(bridge: EventBridge) -> Self {
+        Self { bridge }
+    }
+
+    /**
+        Posts an event with the given name and value into the runtime.
+
+        The event is delivered to listeners added using `events.listen` and `events.wait`
+        in the script - events are queued until the script listens for them, and then
+        discarded if nothing is listening for the given name.
+
+        This never blocks, and may be called from any thread.
+    */
+    pub fn post(&self, name: impl Into<String>, value: impl Into<JsonValue>) {
+        self.bridge.post(name, value);
+    }
+
+    /**
+        Subscribes to events with the given name, emitted by the script using `events.emit`.
+
+        Only events emitted after subscribing are received, and the subscription
+        ends once the returned [`EventSubscription`] is dropped.
+    */
+    #[must_use]
+    pub fn subscribe(&self, name: impl Into<String>) -> EventSubscription {
+        self.bridge.subscribe(name)
+    }
+}
@@ -1,5 +1,21 @@
+mod capture;
+#[cfg(feature = "std-events")]
+mod handle;
+mod report;
 mod result;
 mod runtime;
+#[cfg(feature = "std-luau")]
+mod spawn;
 
+pub use self::capture::OutputBuffer;
+#[cfg(feature = "std-events")]
+pub use self::handle::RuntimeHandle;
+pub use self::report::{RuntimeErrorFrame, RuntimeErrorReport, RuntimeReport};
 pub use self::result::{RuntimeError, RuntimeResult};
 pub use self::runtime::{Runtime, RuntimeReturnValues};
+
+pub use lune_utils::build_info::BuildInfo;
+pub use lune_utils::bundled::{BundledFile, BundledStorage};
+pub use lune_utils::capabilities::CapabilityMode;
+pub use lune_utils::exit_codes::{ExitCodes, RuntimeFailure};
+pub use lune_utils::namespace::{NamespaceManifest, NamespaceModule, RequireResolver};
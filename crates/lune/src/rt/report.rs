@@ -0,0 +1,95 @@
+use std::{env, time::Duration};
+
+use mlua::prelude::*;
+use serde::Serialize;
+
+use lune_utils::fmt::ErrorComponents;
+
+/**
+    Environment variables that, when set to a truthy value, opt out of
+    any reporting - even if the tool being run has asked for consent.
+
+    `DO_NOT_TRACK` is a common convention across many command line tools.
+*/
+const OPT_OUT_ENV_VARS: &[&str] = &["DO_NOT_TRACK", "LUNE_NO_REPORTING"];
+
+/**
+    A single frame of a stack trace in a [`RuntimeErrorReport`].
+*/
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct RuntimeErrorFrame {
+    /// Whether this frame is from a native (Rust / C) function.
+    pub native: bool,
+    /// The path of the script, if this frame is from a script.
+    pub path: Option<String>,
+    /// The line number in the script, if known.
+    pub line: Option<usize>,
+    /// The name of the function, if known.
+    pub function: Option<String>,
+}
+
+/**
+    Structured data for an error thrown from a thread in a Lune runtime.
+*/
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct RuntimeErrorReport {
+    /// The error message(s), including any additional context messages.
+    pub messages: Vec<String>,
+    /// The stack trace of the error, innermost frame first.
+    pub frames: Vec<RuntimeErrorFrame>,
+}
+
+impl From<&LuaError> for RuntimeErrorReport {
+    fn from(error: &LuaError) -> Self {
+        let components = ErrorComponents::from(error.clone());
+        let frames = components
+            .trace()
+            .map(|trace| {
+                trace
+                    .lines()
+                    .iter()
+                    .map(|line| RuntimeErrorFrame {
+                        native: line.source().is_c(),
+                        path: line.path().map(ToString::to_string),
+                        line: line.line_number(),
+                        function: line.function_name().map(ToString::to_string),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            messages: components.messages().to_vec(),
+            frames,
+        }
+    }
+}
+
+/**
+    A report of a completed run of a Lune runtime, given to reporters.
+
+    See [`Runtime::with_reporter`](crate::Runtime::with_reporter) for more information.
+*/
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct RuntimeReport {
+    /// The final exit code of the run.
+    pub exit_code: u8,
+    /// How long the run took, from start to finish.
+    pub duration: Duration,
+    /// All errors thrown from threads during the run, in order.
+    pub errors: Vec<RuntimeErrorReport>,
+}
+
+/**
+    Returns `true` if the user running the current process has opted out of reporting.
+*/
+pub(crate) fn reporting_opted_out() -> bool {
+    OPT_OUT_ENV_VARS.iter().any(|var| {
+        env::var(var).is_ok_and(|value| {
+            let value = value.trim();
+            !value.is_empty() && value != "0" && !value.eq_ignore_ascii_case("false")
+        })
+    })
+}
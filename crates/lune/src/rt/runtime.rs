@@ -1,24 +1,40 @@
 #![allow(clippy::missing_panics_doc)]
 
 use std::{
-    collections::HashMap,
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet, HashMap},
     ffi::OsString,
     path::PathBuf,
+    rc::Rc,
     sync::{
         Arc,
         atomic::{AtomicBool, Ordering},
     },
+    time::Instant,
 };
 
 use async_fs as fs;
+use console::style;
 use lune_utils::{
-    path::{LuauModulePath, constants::FILE_CHUNK_PREFIX},
+    capabilities::{Capabilities, CapabilityMode, CapabilityViolation, parse_requires_directives},
+    fmt::FormatLimits,
+    namespace::{
+        NamespaceManifest, NamespaceModule, Namespaces, RequireResolver, parse_namespace_name,
+    },
+    path::{LuauModulePath, clean_path_and_make_absolute, constants::FILE_CHUNK_PREFIX},
     process::{ProcessArgs, ProcessEnv, ProcessJitEnablement},
+    remote::RemoteRequires,
+    require_allow_list::RequireAllowList,
+    require_graph::RequireGraph,
+    require_trace::RequireTrace,
 };
 use mlua::prelude::*;
-use mlua_luau_scheduler::{Functions, Scheduler};
+use mlua_luau_scheduler::{Functions, Scheduler, ThreadId};
 
-use super::{RuntimeError, RuntimeResult};
+use super::{
+    BuildInfo, BundledFile, ExitCodes, OutputBuffer, RuntimeError, RuntimeErrorReport,
+    RuntimeFailure, RuntimeReport, RuntimeResult, report::reporting_opted_out,
+};
 
 /**
     Values returned by running a Lune runtime until completion.
@@ -31,8 +47,11 @@ pub struct RuntimeReturnValues {
     /// Whether any errors were thrown from threads
     /// that were not the main thread, or not.
     pub errored: bool,
+    /// The class of failure of the first error thrown from any thread, if any.
+    pub failure: Option<RuntimeFailure>,
     /// The final values returned by the main thread.
     pub values: LuaMultiValue,
+    exit_codes: ExitCodes,
 }
 
 impl RuntimeReturnValues {
@@ -43,11 +62,15 @@ impl RuntimeReturnValues {
         or any threads it may have spawned, the status will be either:
 
         - `0` if no threads errored
-        - `1` if any threads errored
+        - The exit code for the class of the first error, if any threads errored,
+          which is `1` unless configured using [`Runtime::with_exit_codes`]
     */
     #[must_use]
     pub fn status(&self) -> u8 {
-        self.code.unwrap_or(u8::from(self.errored))
+        self.code.unwrap_or_else(|| match self.failure {
+            Some(failure) => self.exit_codes.get(failure),
+            None => u8::from(self.errored),
+        })
     }
 
     /**
@@ -62,14 +85,45 @@ impl RuntimeReturnValues {
 }
 
 /// Bundled files for standalone executables: path -> source
-pub type BundledFiles = HashMap<String, Vec<u8>>;
+pub type BundledFiles = HashMap<String, BundledFile>;
 
 /// Bundled aliases for standalone executables: alias -> canonical path
 pub type BundledAliases = HashMap<String, String>;
 
+/// Bundled links for standalone executables: path -> path of the bundled file
+pub type BundledLinks = HashMap<String, String>;
+
 /// Path to the current executable for standalone binaries
 pub type ExecutablePath = Option<PathBuf>;
 
+/// Values defined for standalone executables: name -> value
+pub type DefinedValues = BTreeMap<String, String>;
+
+/// Hook called with the name and module of each standard library as it is injected
+type StdModuleHook = Box<dyn Fn(&Lua, &str, LuaTable) -> LuaResult<LuaTable>>;
+
+/// Hook called with each error thrown from a thread before it is reported
+type ErrorHook = Rc<dyn Fn(&Lua, LuaError) -> LuaError>;
+
+/// Hook called with a report of each completed run
+type Reporter = Box<dyn Fn(&RuntimeReport)>;
+
+/// State shared with the error callback: whether any error was thrown,
+/// the class of failure of the first error, and the collected error reports
+type ErrorCallbackState = (
+    Arc<AtomicBool>,
+    Rc<Cell<Option<RuntimeFailure>>>,
+    Rc<RefCell<Vec<RuntimeErrorReport>>>,
+);
+
+/// State of a script that was started, kept until the scheduler completes running it
+struct RunState {
+    started: Instant,
+    reporting: bool,
+    errors: ErrorCallbackState,
+    main_thread_id: ThreadId,
+}
+
 /**
     A Lune runtime.
 */
@@ -81,7 +135,28 @@ pub struct Runtime {
     jit: ProcessJitEnablement,
     bundled_files: BundledFiles,
     bundled_aliases: BundledAliases,
+    aliases: HashMap<String, PathBuf>,
+    bundled_links: BundledLinks,
+    require_graph: Option<RequireGraph>,
     executable_path: ExecutablePath,
+    defines: DefinedValues,
+    build_info: Option<BuildInfo>,
+    namespaces: Namespaces,
+    capabilities: Option<Capabilities>,
+    hot_reload: bool,
+    remote_requires: Option<RemoteRequires>,
+    require_trace: Option<RequireTrace>,
+    require_allow_list: Option<RequireAllowList>,
+    stepping: Option<RunState>,
+    #[cfg(feature = "std-events")]
+    events: lune_std::EventBridge,
+    std_module_hook: Option<StdModuleHook>,
+    error_hook: Option<ErrorHook>,
+    reporter: Option<Reporter>,
+    reporting_consent: bool,
+    exit_codes: ExitCodes,
+    print_limits: FormatLimits,
+    stderr_capture: Option<OutputBuffer>,
 }
 
 impl Runtime {
@@ -217,8 +292,11 @@ impl Runtime {
 
         // Inject all the globals that are enabled
         #[cfg(any(
+            feature = "std-buffer",
             feature = "std-datetime",
+            feature = "std-events",
             feature = "std-fs",
+            feature = "std-ipc",
             feature = "std-luau",
             feature = "std-net",
             feature = "std-process",
@@ -239,8 +317,11 @@ impl Runtime {
         // _G table needs to be injected again after sandboxing,
         // otherwise it will be read-only and completely unusable
         #[cfg(any(
+            feature = "std-buffer",
             feature = "std-datetime",
+            feature = "std-events",
             feature = "std-fs",
+            feature = "std-ipc",
             feature = "std-luau",
             feature = "std-net",
             feature = "std-process",
@@ -268,7 +349,28 @@ impl Runtime {
             jit,
             bundled_files: HashMap::new(),
             bundled_aliases: HashMap::new(),
+            aliases: HashMap::new(),
+            bundled_links: HashMap::new(),
+            require_graph: None,
             executable_path: None,
+            defines: BTreeMap::new(),
+            build_info: None,
+            namespaces: Namespaces::default(),
+            capabilities: None,
+            hot_reload: false,
+            remote_requires: None,
+            require_trace: None,
+            require_allow_list: None,
+            stepping: None,
+            #[cfg(feature = "std-events")]
+            events: lune_std::EventBridge::new(),
+            std_module_hook: None,
+            error_hook: None,
+            reporter: None,
+            reporting_consent: false,
+            exit_codes: ExitCodes::default(),
+            print_limits: FormatLimits::default(),
+            stderr_capture: None,
         })
     }
 
@@ -319,10 +421,19 @@ impl Runtime {
         Sets bundled files for standalone executables.
 
         These files will be available to require without reading from the filesystem.
+        Files may be given as byte vectors, or as [`BundledFile`]s that are read in place
+        from shared storage, such as the mapped bytes of a standalone binary, which
+        are never copied when required, and may be evicted once they have been.
     */
     #[must_use]
-    pub fn with_bundled_files(mut self, files: BundledFiles) -> Self {
-        self.bundled_files = files;
+    pub fn with_bundled_files<F>(mut self, files: impl IntoIterator<Item = (String, F)>) -> Self
+    where
+        F: Into<BundledFile>,
+    {
+        self.bundled_files = files
+            .into_iter()
+            .map(|(path, contents)| (path, contents.into()))
+            .collect();
         self
     }
 
@@ -337,6 +448,72 @@ impl Runtime {
         self
     }
 
+    /**
+        Adds aliases for `require`, such as `pkg` to `./vendor/pkg` for
+        `require("@pkg/module")`, without needing any `.luaurc` files.
+
+        Aliases may be given with or without their leading `@`, and relative paths are
+        resolved from the current directory. These aliases take precedence over aliases
+        defined in `.luaurc` files, and may be used together with them.
+
+        # Errors
+
+        Returns an error if:
+
+        - An alias name is empty, or contains a `/`
+        - An alias uses the reserved `lune` or `self` aliases
+    */
+    pub fn with_aliases<I, K, V>(mut self, aliases: I) -> RuntimeResult<Self>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Into<PathBuf>,
+    {
+        for (name, path) in aliases {
+            let name = name.as_ref().trim();
+            let name = name.strip_prefix('@').unwrap_or(name);
+            if name.is_empty() || name.contains('/') {
+                return Err(LuaError::runtime(format!(
+                    "alias names must be in the form 'name' or '@name', got '{name}'"
+                ))
+                .into());
+            }
+            if name == "lune" || name == "self" {
+                return Err(LuaError::runtime(format!(
+                    "alias names must not be 'lune' or 'self', got '{name}'"
+                ))
+                .into());
+            }
+            let path = clean_path_and_make_absolute(&path.into());
+            self.aliases.insert(name.to_string(), path);
+        }
+        Ok(self)
+    }
+
+    /**
+        Sets bundled links for standalone executables.
+
+        These map paths of files that were reached through more than one path when
+        bundling, such as through symlinks, to the path of the single bundled file.
+    */
+    #[must_use]
+    pub fn with_bundled_links(mut self, links: BundledLinks) -> Self {
+        self.bundled_links = links;
+        self
+    }
+
+    /**
+        Sets the requires of bundled files, linked when building standalone executables.
+
+        Requires in this graph are looked up by the exact require path given by
+        each bundled module, instead of resolving paths and checking for files.
+    */
+    #[must_use]
+    pub fn with_require_graph(mut self, graph: RequireGraph) -> Self {
+        self.require_graph = Some(graph);
+        self
+    }
+
     /**
         Sets the executable path for standalone executables.
 
@@ -349,6 +526,228 @@ impl Runtime {
         self
     }
 
+    /**
+        Sets values to give in the `_DEFINES` global, such as a version or feature flags.
+
+        Values of `true` and `false` are given to scripts as booleans, all other values as strings.
+    */
+    #[must_use]
+    pub fn with_defines(mut self, defines: DefinedValues) -> Self {
+        self.defines = defines;
+        self
+    }
+
+    /**
+        Sets information about how the standalone executable was built, to give in the `_BUILD` global.
+
+        The `_BUILD` global is an empty table when no build information is set.
+    */
+    #[must_use]
+    pub fn with_build_info(mut self, build_info: BuildInfo) -> Self {
+        self.build_info = Some(build_info);
+        self
+    }
+
+    /**
+        Sets a hook to call for each standard library module as it is injected.
+
+        The hook receives the name of the library, such as `fs`, along with
+        its module table, and returns the module table to make available
+        through `require` - this can be used to wrap or instrument libraries.
+    */
+    #[must_use]
+    pub fn with_std_module_hook<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Lua, &str, LuaTable) -> LuaResult<LuaTable> + 'static,
+    {
+        self.std_module_hook = Some(Box::new(hook));
+        self
+    }
+
+    /**
+        Sets a hook to call for each error thrown from a thread, before it is reported.
+
+        The hook receives the Luau VM and the error, and returns the error to report in
+        its place - this can be used to add more context to errors, rewrite their
+        tracebacks, or to inspect the state of the VM at the time of the error.
+    */
+    #[must_use]
+    pub fn with_error_hook<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Lua, LuaError) -> LuaError + 'static,
+    {
+        self.error_hook = Some(Rc::new(hook));
+        self
+    }
+
+    /**
+        Sets a reporter to call with a [`RuntimeReport`] once a run completes, right before
+        the run returns - this can be used to integrate error and usage reporting services.
+
+        Reporting is strictly opt-in, and reporters are **only** called if consent
+        has been given using [`Runtime::with_reporting_consent`]. Users running the
+        process may also opt out by setting `DO_NOT_TRACK` or `LUNE_NO_REPORTING`,
+        which always takes precedence over any consent given here.
+    */
+    #[must_use]
+    pub fn with_reporter<F>(mut self, reporter: F) -> Self
+    where
+        F: Fn(&RuntimeReport) + 'static,
+    {
+        self.reporter = Some(Box::new(reporter));
+        self
+    }
+
+    /**
+        Sets whether consent has been given to call the reporter set using
+        [`Runtime::with_reporter`]. Consent is not given by default.
+    */
+    #[must_use]
+    pub fn with_reporting_consent(mut self, consent: bool) -> Self {
+        self.reporting_consent = consent;
+        self
+    }
+
+    /**
+        Sets the exit codes to use for each class of failure, such as a
+        `require` that could not be resolved, when the runtime errors.
+
+        By default, the exit code for all failures is `1`.
+    */
+    #[must_use]
+    pub fn with_exit_codes(mut self, exit_codes: ExitCodes) -> Self {
+        self.exit_codes = exit_codes;
+        self
+    }
+
+    /**
+        Sets the limits for values given to `print`, such as the maximum number
+        of table entries to print, past which the rest of the value is elided.
+
+        By default, limits that are generous enough for all reasonable values are used.
+        Scripts may always print values without any limits, using `print.full`.
+    */
+    #[must_use]
+    pub fn with_print_limits(mut self, limits: FormatLimits) -> Self {
+        self.print_limits = limits;
+        self
+    }
+
+    /**
+        Enables checking the capabilities that modules declare using
+        `--!requires` directives, such as `--!requires net, fs-read`,
+        granting the given capabilities to the script being run.
+
+        A module may only be required from a script or module that has been
+        granted all of the capabilities it declares, and every required module
+        is granted exactly the capabilities that it declares. Depending on the
+        mode, requiring a module without its capabilities errors, or only warns.
+
+        Capabilities are not checked by default.
+    */
+    #[must_use]
+    pub fn with_capabilities<I, S>(mut self, mode: CapabilityMode, granted: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.capabilities = Some(Capabilities::new(mode, granted));
+        self
+    }
+
+    /**
+        Enables or disables hot reloading of required modules, for development.
+
+        When enabled, the files of modules loaded from disk are watched, and once a
+        file changes, its module is invalidated and required again. If the new module
+        is a table with an `__onReload` function, it is called with the previous module.
+
+        The runtime keeps running for as long as any modules are being watched.
+
+        Hot reloading is disabled by default.
+    */
+    #[must_use]
+    pub fn with_hot_reload(mut self, enabled: bool) -> Self {
+        self.hot_reload = enabled;
+        self
+    }
+
+    /**
+        Allows requiring remote modules using their URLs, such as
+        `require("https://example.com/module.luau")`.
+
+        Remote modules are downloaded once, into the given cache directory, and the
+        hash of each is recorded in the given lockfile - once recorded, modules are
+        read from the cache, and must match their hash if downloaded again.
+
+        Remote modules can not be required by default.
+    */
+    #[must_use]
+    pub fn with_remote_requires(mut self, options: RemoteRequires) -> Self {
+        self.remote_requires = Some(options);
+        self
+    }
+
+    /**
+        Records every require made by scripts in the given trace, along with the
+        path that it was resolved to, the module that made it, and the time spent
+        on it - useful for diagnosing slow startup and unexpected dependencies.
+
+        Clones of the trace record into the same trace, so a clone may be kept
+        to format the recorded requires once the script has finished running.
+    */
+    #[must_use]
+    pub fn with_require_trace(mut self, trace: RequireTrace) -> Self {
+        self.require_trace = Some(trace);
+        self
+    }
+
+    /**
+        Restricts `require` to the given directories and prefixes, such as
+        for running untrusted plugin scripts from a trusted host script.
+
+        Entries starting with `@`, such as `@lune/`, and URLs, such as `https://example.com/`,
+        are prefixes that require paths must start with - all other entries are directories,
+        where relative paths are resolved from the current directory, that required modules
+        must be inside of. Requiring anything else fails with an error.
+
+        Requires are not restricted by default.
+    */
+    #[must_use]
+    pub fn with_require_allow_list<I, S>(mut self, allowed: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.require_allow_list = Some(RequireAllowList::new(allowed));
+        self
+    }
+
+    /**
+        Captures all output of the runtime in the given buffers, instead of writing it
+        to stdout and stderr - this includes `print`, `warn`, `stdio.write` and
+        `stdio.ewrite`, along with any errors, which are written without colors.
+
+        This replaces any standard library module hook.
+
+        # Errors
+
+        - If out of memory
+    */
+    pub fn with_output_capture(
+        mut self,
+        stdout: OutputBuffer,
+        stderr: OutputBuffer,
+    ) -> LuaResult<Self> {
+        super::capture::capture_globals(&self.lua, &stdout)?;
+        let stderr_inner = Arc::clone(&stderr);
+        self.std_module_hook = Some(Box::new(move |lua, library, module| {
+            super::capture::capture_std_module(lua, library, module, &stdout, &stderr_inner)
+        }));
+        self.stderr_capture = Some(stderr);
+        Ok(self)
+    }
+
     /**
         Adds a custom library to the runtime, making it available through `require`.
 
@@ -416,12 +815,135 @@ impl Runtime {
         Ok(self)
     }
 
+    /**
+        Adds a namespace of modules to the runtime, such as `@mycompany`, making
+        all modules inside of it, such as `@mycompany/http`, available through
+        `require` - the name may also end with a `*` wildcard segment.
+
+        Modules are looked up lazily, the first time they are required, by calling
+        the given resolver with the name of the module inside of the namespace, such
+        as `http`. The resolver may return a value, or Luau source code to run as the
+        module, or `None` if there is no such module. Modules are then stored in the
+        `_REGISTEREDMODULES` registry table, the same as modules added using
+        [`Runtime::with_lib`], and the resolver is not called for them again.
+
+        Source modules can not use relative requires, and should instead
+        require other modules in the namespace using their full names.
+
+        # Example Usage
+
+        ```rs
+        Runtime::new().with_namespace("@mycompany", |lua, name| {
+            Ok(match name {
+                "version" => Some(NamespaceModule::Value(lua.create_string("1.0")?.into_lua(lua)?)),
+                "greet" => Some(NamespaceModule::Source(b"return function() print('hi') end".to_vec())),
+                _ => None,
+            })
+        });
+        ```
+
+        # Errors
+
+        Returns an error if:
+
+        - The namespace name does not start with `@`, or contains a `/`
+        - The namespace uses the reserved `lune` or `self` aliases
+    */
+    pub fn with_namespace<S, F>(mut self, namespace: S, resolver: F) -> RuntimeResult<Self>
+    where
+        S: AsRef<str>,
+        F: Fn(&Lua, &str) -> LuaResult<Option<NamespaceModule>> + 'static,
+    {
+        let name = parse_namespace_name(namespace.as_ref())
+            .map_err(|e| RuntimeError::from(LuaError::external(e)))?;
+        self.namespaces.insert(name, Rc::new(resolver), false);
+        Ok(self)
+    }
+
+    /**
+        Adds a resolver for `require` that intercepts modules with the given prefix,
+        such as `@myapp` for `require("@myapp/config")`, the same as a namespace added
+        using [`Runtime::with_namespace`], except that modules not provided by the
+        resolver fall back to the default resolution of aliases and files.
+
+        This makes it possible to override or extend only some modules of an alias
+        that is also defined in a `.luaurc` file, such as for prebuilt tables of
+        configuration, while every other module is still loaded from the filesystem.
+
+        # Example Usage
+
+        ```rs
+        Runtime::new().with_require_resolver("@myapp", |lua, name| {
+            Ok(match name {
+                "config" => Some(NamespaceModule::Value(lua.create_table()?.into_lua(lua)?)),
+                _ => None, // Any other @myapp module is resolved using .luaurc aliases
+            })
+        });
+        ```
+
+        # Errors
+
+        Returns an error if:
+
+        - The prefix does not start with `@`, or contains a `/`
+        - The prefix uses the reserved `lune` or `self` aliases
+    */
+    pub fn with_require_resolver<S, R>(mut self, prefix: S, resolver: R) -> RuntimeResult<Self>
+    where
+        S: AsRef<str>,
+        R: RequireResolver,
+    {
+        let name = parse_namespace_name(prefix.as_ref())
+            .map_err(|e| RuntimeError::from(LuaError::external(e)))?;
+        let resolver = move |lua: &Lua, name: &str| resolver.resolve(lua, name);
+        self.namespaces.insert(name, Rc::new(resolver), true);
+        Ok(self)
+    }
+
+    /**
+        Returns a manifest of all namespaces added using [`Runtime::with_namespace`].
+
+        The manifest may be serialized to JSON and given to `lune build --namespaces`,
+        so that requires of modules in these namespaces are left to the application.
+    */
+    #[must_use]
+    pub fn namespace_manifest(&self) -> NamespaceManifest {
+        self.namespaces.manifest()
+    }
+
+    /**
+        Returns a handle to this runtime, for posting events into scripts and
+        subscribing to events that scripts emit, using the `events` standard library.
+
+        Handles may be cloned and sent to other threads, and keep working
+        across runs of scripts in this runtime.
+    */
+    #[cfg(feature = "std-events")]
+    #[must_use]
+    pub fn handle(&self) -> super::RuntimeHandle {
+        super::RuntimeHandle::new(self.events.clone())
+    }
+
+    /**
+        Clears the cache of required modules, so that every module is loaded again
+        the next time it is required, such as after the files of modules were edited.
+
+        # Errors
+
+        - If out of memory
+    */
+    pub fn clear_module_cache(&self) -> RuntimeResult<()> {
+        lune_std::clear_module_cache(&self.lua)?;
+        Ok(())
+    }
+
     /**
         Runs some kind of custom input, inside of the current runtime.
 
         For any input that is a real module or file path, [`run_file`] should
-        be used instead, since when using this method, any file requires will
-        fail. Requires for standard libraries and custom libraries will work.
+        be used instead. Since custom input is not a file, its file requires are
+        resolved from the current working directory, as are its aliases, using
+        the `.luaurc` files in the current working directory and its ancestors.
 
         # Errors
 
@@ -512,19 +1034,178 @@ impl Runtime {
         self.run_inner(module_name, module_contents).await
     }
 
-    async fn run_inner(
+    /**
+        Starts running source code with file-based semantics, the same as
+        [`Runtime::run_source`], without running the scheduler of the runtime.
+
+        The script must then be driven forward using [`Runtime::step`], which lets
+        host applications with their own event loop, such as game engines or GUI
+        applications, run the script one tick at a time, such as once per frame.
+
+        # Errors
+
+        Returns an error if:
+
+        - The script fails to load (not if the script itself errors)
+        - A script is already running in this runtime
+    */
+    pub fn start_source(
+        &mut self,
+        module_path: impl AsRef<str>,
+        source: impl AsRef<[u8]>,
+    ) -> RuntimeResult<()> {
+        let module_name = format!("{FILE_CHUNK_PREFIX}{}", module_path.as_ref());
+        self.start_stepping(module_name, source)
+    }
+
+    /**
+        Starts running some kind of custom input, the same as
+        [`Runtime::run_custom`], without running the scheduler of the runtime.
+
+        See [`Runtime::start_source`] for more information.
+
+        # Errors
+
+        Returns an error if:
+
+        - The script fails to load (not if the script itself errors)
+        - A script is already running in this runtime
+    */
+    pub fn start_custom(
         &mut self,
         chunk_name: impl AsRef<str>,
         chunk_contents: impl AsRef<[u8]>,
-    ) -> RuntimeResult<RuntimeReturnValues> {
-        // Add error callback to format errors nicely + store status
+    ) -> RuntimeResult<()> {
+        let chunk_name = format!("={}", chunk_name.as_ref());
+        self.start_stepping(chunk_name, chunk_contents)
+    }
+
+    /**
+        Runs a single tick of the script started using [`Runtime::start_source`]
+        or [`Runtime::start_custom`], without blocking.
+
+        Every thread and task that is ready runs until it yields, and anything still
+        waiting, such as for timers or network requests, is left for following ticks.
+
+        Returns `None` while the script is still running, and the values returned
+        by running it once it completes, the same as running it until completion.
+
+        # Errors
+
+        Returns an error if no script was started, or if it has already completed.
+    */
+    pub fn step(&mut self) -> RuntimeResult<Option<RuntimeReturnValues>> {
+        if self.stepping.is_none() {
+            return Err(LuaError::runtime("no script has been started in this runtime").into());
+        }
+        if self.sched.step().is_running() {
+            return Ok(None);
+        }
+        let state = self.stepping.take().expect("script was started");
+        Ok(Some(self.finish_inner(state)))
+    }
+
+    /**
+        Returns whether a script started using [`Runtime::start_source`]
+        or [`Runtime::start_custom`] is still running, or not.
+    */
+    #[must_use]
+    pub fn is_running(&self) -> bool {
+        self.stepping.is_some()
+    }
+
+    fn start_stepping(
+        &mut self,
+        chunk_name: impl AsRef<str>,
+        chunk_contents: impl AsRef<[u8]>,
+    ) -> RuntimeResult<()> {
+        let state = self.start_inner(chunk_name, chunk_contents)?;
+        self.sched.start();
+        self.stepping = Some(state);
+        Ok(())
+    }
+
+    /**
+        Replaces each registered standard library module with
+        the result of the standard library module hook, if any.
+    */
+    #[cfg(any(
+        feature = "std-buffer",
+        feature = "std-datetime",
+        feature = "std-events",
+        feature = "std-fs",
+        feature = "std-ipc",
+        feature = "std-luau",
+        feature = "std-net",
+        feature = "std-process",
+        feature = "std-regex",
+        feature = "std-roblox",
+        feature = "std-serde",
+        feature = "std-stdio",
+        feature = "std-task",
+    ))]
+    fn apply_std_module_hook(&self) -> LuaResult<()> {
+        let Some(hook) = &self.std_module_hook else {
+            return Ok(());
+        };
+        let registered: LuaTable = self.lua.named_registry_value("_REGISTEREDMODULES")?;
+        for library in lune_std::LuneStandardLibrary::ALL {
+            let alias = format!("@lune/{}", library.name());
+            if let LuaValue::Table(module) = registered.raw_get(alias.as_str())? {
+                registered.raw_set(alias, hook(&self.lua, library.name(), module)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    /**
+        Sets the error callback of the scheduler, which calls the error hook, stores
+        error reports if reporting, and then prints the error (or captures it).
+
+        Returns a flag that is set once any error is thrown, the class of failure
+        of the first error, along with the collected reports.
+    */
+    fn set_error_callback(&self, reporting: bool) -> ErrorCallbackState {
         let got_any_error = Arc::new(AtomicBool::new(false));
         let got_any_inner = Arc::clone(&got_any_error);
+        let failure = Rc::new(Cell::new(None));
+        let failure_inner = Rc::clone(&failure);
+        let error_hook = self.error_hook.clone();
+        let error_lua = self.lua.weak();
+        let error_reports = Rc::new(RefCell::new(Vec::new()));
+        let error_reports_inner = Rc::clone(&error_reports);
+        let stderr_capture = self.stderr_capture.clone();
         self.sched.set_error_callback(move |e| {
             got_any_inner.store(true, Ordering::SeqCst);
-            eprintln!("{}", RuntimeError::from(e));
+            if failure_inner.get().is_none() {
+                failure_inner.set(Some(RuntimeFailure::classify(&e)));
+            }
+            let e = match (&error_hook, error_lua.try_upgrade()) {
+                (Some(hook), Some(lua)) => hook(&lua, e),
+                _ => e,
+            };
+            if reporting {
+                error_reports_inner
+                    .borrow_mut()
+                    .push(RuntimeErrorReport::from(&e));
+            }
+            if let Some(stderr) = &stderr_capture {
+                let message = RuntimeError::from(e).disable_colors();
+                let mut stderr = stderr.lock().unwrap();
+                stderr.extend_from_slice(format!("{message}\n").as_bytes());
+            } else {
+                eprintln!("{}", RuntimeError::from(e));
+            }
         });
 
+        (got_any_error, failure, error_reports)
+    }
+
+    /**
+        Stores all app data for the runtime, and injects the globals
+        and standard libraries that need app data to be created.
+    */
+    fn inject_app_data(&self) -> LuaResult<()> {
         // Store the provided args, environment variables, jit enablement, bundled files/aliases, and executable path as AppData
         self.lua.set_app_data(self.args.clone());
         self.lua.set_app_data(self.env.clone());
@@ -532,11 +1213,25 @@ impl Runtime {
         self.lua.set_app_data(self.bundled_files.clone());
         self.lua.set_app_data(self.bundled_aliases.clone());
         self.lua.set_app_data(self.executable_path.clone());
-
-        // Inject the executable global now that app_data is set
+        self.lua.set_app_data(self.namespaces.clone());
+        self.lua.set_app_data(self.print_limits);
+        if let Some(capabilities) = &self.capabilities {
+            self.lua.set_app_data(capabilities.clone());
+        }
+        #[cfg(feature = "std-luau")]
+        self.lua.set_app_data(lune_std::LuauRuntimeSpawner::new(
+            super::spawn::spawn_runtime,
+        ));
+        #[cfg(feature = "std-events")]
+        self.lua.set_app_data(self.events.clone());
+
+        // Inject the executable and bundle globals now that app_data is set
         #[cfg(any(
+            feature = "std-buffer",
             feature = "std-datetime",
+            feature = "std-events",
             feature = "std-fs",
+            feature = "std-ipc",
             feature = "std-luau",
             feature = "std-net",
             feature = "std-process",
@@ -549,13 +1244,48 @@ impl Runtime {
         {
             let executable_value = lune_std::create_executable_global(self.lua.clone())?;
             self.lua.globals().set("executable", executable_value)?;
+            let bundle_value = lune_std::create_bundle_global(self.lua.clone())?;
+            self.lua.globals().set("bundle", bundle_value)?;
+
+            self.lua
+                .set_app_data(lune_std::BundledLinks(self.bundled_links.clone()));
+            if let Some(graph) = &self.require_graph {
+                self.lua
+                    .set_app_data(lune_std::LinkedRequires::new(graph.clone()));
+            }
+            self.lua
+                .set_app_data(lune_std::Defines(self.defines.clone()));
+            if self.hot_reload {
+                self.lua.set_app_data(lune_std::HotReload::default());
+            }
+            if let Some(options) = &self.remote_requires {
+                self.lua.set_app_data(options.clone());
+            }
+            if let Some(trace) = &self.require_trace {
+                self.lua.set_app_data(trace.clone());
+            }
+            if let Some(allow_list) = &self.require_allow_list {
+                self.lua.set_app_data(allow_list.clone());
+            }
+            self.lua
+                .set_app_data(lune_std::RequireAliases(self.aliases.clone()));
+            let defines_value = lune_std::create_defines_global(self.lua.clone())?;
+            self.lua.globals().set("_DEFINES", defines_value)?;
+            if let Some(build_info) = &self.build_info {
+                self.lua.set_app_data(build_info.clone());
+            }
+            let build_value = lune_std::create_build_global(self.lua.clone())?;
+            self.lua.globals().set("_BUILD", build_value)?;
         }
 
         // Inject all the standard libraries that are enabled - this needs to be done after
         // storing the args/env, since some standard libraries use those during initialization
         #[cfg(any(
+            feature = "std-buffer",
             feature = "std-datetime",
+            feature = "std-events",
             feature = "std-fs",
+            feature = "std-ipc",
             feature = "std-luau",
             feature = "std-net",
             feature = "std-process",
@@ -567,15 +1297,86 @@ impl Runtime {
         ))]
         {
             lune_std::inject_std(self.lua.clone())?;
+            self.apply_std_module_hook()?;
+        }
+
+        Ok(())
+    }
+
+    /**
+        Checks that the capabilities declared by the script being
+        run have been granted to the runtime, if checking them.
+    */
+    fn check_capabilities(&self, chunk_name: &str, chunk_contents: &[u8]) -> LuaResult<()> {
+        let Some(capabilities) = self.lua.app_data_ref::<Capabilities>() else {
+            return Ok(());
+        };
+        // NOTE: The script is not recorded as a module, so that
+        // it keeps all of the capabilities granted to the runtime
+        let missing = parse_requires_directives(chunk_contents)
+            .difference(capabilities.granted())
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        let violation = CapabilityViolation {
+            module: chunk_name.trim_start_matches(['@', '=']).to_string(),
+            context: None,
+            missing,
+        };
+        if capabilities.mode() == CapabilityMode::Audit {
+            eprintln!("{}", style(format!("[AUDIT] {violation}")).yellow());
+            Ok(())
+        } else {
+            Err(LuaError::runtime(violation.to_string()))
+        }
+    }
+
+    async fn run_inner(
+        &mut self,
+        chunk_name: impl AsRef<str>,
+        chunk_contents: impl AsRef<[u8]>,
+    ) -> RuntimeResult<RuntimeReturnValues> {
+        let state = self.start_inner(chunk_name, chunk_contents)?;
+
+        // Run it on our scheduler until it and any other spawned threads complete
+        self.sched.run().await;
+
+        Ok(self.finish_inner(state))
+    }
+
+    /**
+        Loads the given script, and pushes its main thread to the scheduler, without running it.
+    */
+    fn start_inner(
+        &mut self,
+        chunk_name: impl AsRef<str>,
+        chunk_contents: impl AsRef<[u8]>,
+    ) -> RuntimeResult<RunState> {
+        if self.stepping.is_some() {
+            return Err(LuaError::runtime("a script is already running in this runtime").into());
         }
 
+        let started = Instant::now();
+        let reporting = self.reporter.is_some() && self.reporting_consent && !reporting_opted_out();
+
+        // Add error callback to format errors nicely + store status
+        let errors = self.set_error_callback(reporting);
+
+        // Store app data, and inject any globals and libraries that depend on it
+        self.inject_app_data()?;
+
         // Enable / disable the JIT as requested, before loading anything
         self.lua.enable_jit(self.jit.enabled());
 
         // Set the current script path for the script global
         #[cfg(any(
+            feature = "std-buffer",
             feature = "std-datetime",
+            feature = "std-events",
             feature = "std-fs",
+            feature = "std-ipc",
             feature = "std-luau",
             feature = "std-net",
             feature = "std-process",
@@ -596,6 +1397,9 @@ impl Runtime {
             lune_std::push_script_path(&self.lua, script_path)?;
         }
 
+        // Check the capabilities declared by the script itself, if enabled
+        self.check_capabilities(chunk_name.as_ref(), chunk_contents.as_ref())?;
+
         // Load our "main" thread
         let main = self
             .lua
@@ -609,9 +1413,26 @@ impl Runtime {
             chunk_cache.set(chunk_name.as_ref(), main_func.clone())?;
         }
 
-        // Run it on our scheduler until it and any other spawned threads complete
         let main_thread_id = self.sched.push_thread_back(main_func, ())?;
-        self.sched.run().await;
+
+        Ok(RunState {
+            started,
+            reporting,
+            errors,
+            main_thread_id,
+        })
+    }
+
+    /**
+        Collects the values of a script, once the scheduler has completed running it.
+    */
+    fn finish_inner(&self, state: RunState) -> RuntimeReturnValues {
+        let RunState {
+            started,
+            reporting,
+            errors: (got_any_error, failure, error_reports),
+            main_thread_id,
+        } = state;
 
         let main_thread_values = self
             .sched
@@ -619,11 +1440,23 @@ impl Runtime {
             .unwrap_or_else(|| Ok(LuaMultiValue::new())) // Ignore missing result (interruption), we just want to extract values
             .unwrap_or_default(); // Ignore any errors from the script, we just want to extract values
 
-        Ok(RuntimeReturnValues {
+        let values = RuntimeReturnValues {
             code: self.sched.get_exit_code(),
             errored: got_any_error.load(Ordering::SeqCst),
+            failure: failure.get(),
             values: main_thread_values,
-        })
+            exit_codes: self.exit_codes,
+        };
+
+        if reporting && let Some(reporter) = &self.reporter {
+            reporter(&RuntimeReport {
+                exit_code: values.status(),
+                duration: started.elapsed(),
+                errors: error_reports.take(),
+            });
+        }
+
+        values
     }
 }
 
@@ -0,0 +1,63 @@
+use std::sync::Arc;
+
+use lune_std::{LuauSpawnOptions, LuauSpawnResult, LuauSpawnSource};
+
+use super::{
+    Runtime, RuntimeResult,
+    capture::{OutputBuffer, write_line},
+};
+
+const SPAWNED_CHUNK_NAME: &str = "luau.spawnRuntime";
+
+/**
+    Runs a script in a new, isolated runtime, for `luau.spawnRuntime`.
+
+    The runtime gets its own Luau VM and scheduler, and is run to
+    completion on the current thread, which must not be the thread
+    that the runtime spawning it is running on.
+*/
+pub(crate) fn spawn_runtime(options: LuauSpawnOptions) -> LuauSpawnResult {
+    let stdout = OutputBuffer::default();
+    let stderr = OutputBuffer::default();
+    let inherit_stdio = options.inherit_stdio;
+    let capture = (!inherit_stdio).then(|| (Arc::clone(&stdout), Arc::clone(&stderr)));
+
+    let code = match async_io::block_on(run_spawned(options, capture)) {
+        Ok(code) => code,
+        Err(e) => {
+            if inherit_stdio {
+                eprintln!("{e}");
+            } else {
+                write_line(&stderr, &e.disable_colors().to_string());
+            }
+            1
+        }
+    };
+
+    LuauSpawnResult {
+        code,
+        stdout: take_output(&stdout),
+        stderr: take_output(&stderr),
+    }
+}
+
+async fn run_spawned(
+    options: LuauSpawnOptions,
+    capture: Option<(OutputBuffer, OutputBuffer)>,
+) -> RuntimeResult<u8> {
+    let mut rt = Runtime::new()?.with_args(options.args);
+    if let Some((stdout, stderr)) = capture {
+        rt = rt.with_output_capture(stdout, stderr)?;
+    }
+
+    let values = match options.source {
+        LuauSpawnSource::Source(source) => rt.run_custom(SPAWNED_CHUNK_NAME, source).await?,
+        LuauSpawnSource::Path(path) => rt.run_file(path).await?,
+    };
+
+    Ok(values.status())
+}
+
+fn take_output(output: &OutputBuffer) -> Vec<u8> {
+    std::mem::take(&mut *output.lock().unwrap())
+}
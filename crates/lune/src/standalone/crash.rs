@@ -0,0 +1,280 @@
+/*!
+    Crash reports for standalone binaries.
+
+    When a crash report directory is baked into a standalone binary using
+    `lune build --crash-report-dir`, the first uncaught error of a run writes
+    a compact, plain-text report to that directory, containing:
+
+    - The error message and its stack trace
+    - Stack traces of all other tasks that were still alive at the time
+    - Metadata about the host system and the executable itself
+
+    This lets developers shipping compiled tools to end users ask for
+    these reports instead of relying on screenshots of terminal output.
+*/
+
+use std::{
+    cell::Cell,
+    env,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use directories::UserDirs;
+use mlua::prelude::*;
+
+use lune::RuntimeError;
+
+use super::metadata::CURRENT_EXE;
+
+const TRACKED_TASKS_KEY: &str = "__lune_crash_tracked_tasks";
+const TRACKED_TASK_FUNCTIONS: &[&str] = &["spawn", "defer", "delay"];
+
+/**
+    Writes crash reports for uncaught errors to a directory.
+
+    Only the first error of a run is reported, since any following
+    errors are very often caused by the first one.
+*/
+#[derive(Debug)]
+pub struct CrashReporter {
+    dir: PathBuf,
+    reported: Cell<bool>,
+}
+
+impl CrashReporter {
+    /**
+        Creates a new crash reporter for the given directory.
+
+        A leading `~` in the directory is expanded to the home directory
+        of the current user, and relative directories are resolved from
+        the current working directory at the time of the crash.
+    */
+    pub fn new(dir: &str) -> Self {
+        Self {
+            dir: expand_home_dir(dir),
+            reported: Cell::new(false),
+        }
+    }
+
+    /**
+        Writes a crash report for the given error, unless one has already been written.
+
+        Failing to write the report never affects the error itself, but is
+        mentioned in the output so that the missing report is not a surprise.
+    */
+    pub fn report(&self, lua: &Lua, error: &LuaError) {
+        if self.reported.replace(true) {
+            return;
+        }
+
+        let contents = create_report(lua, error);
+        match write_report(&self.dir, &contents) {
+            Ok(path) => eprintln!("A crash report was written to {}", path.display()),
+            Err(e) => eprintln!(
+                "Failed to write crash report to {}: {e}",
+                self.dir.display()
+            ),
+        }
+    }
+}
+
+fn expand_home_dir(dir: &str) -> PathBuf {
+    let home = || UserDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+    if dir == "~"
+        && let Some(home) = home()
+    {
+        return home;
+    }
+    if let Some(rest) = dir.strip_prefix("~/").or_else(|| dir.strip_prefix("~\\"))
+        && let Some(home) = home()
+    {
+        return home.join(rest);
+    }
+    PathBuf::from(dir)
+}
+
+/**
+    Wraps the task spawning functions of the `task` standard library so
+    that all tasks they create are tracked, and can have their stack
+    traces included in crash reports.
+
+    Tasks are tracked using a table with weak keys, meaning tracking
+    them does not prevent them from being garbage collected.
+*/
+pub fn track_tasks(lua: &Lua, library: &str, module: LuaTable) -> LuaResult<LuaTable> {
+    if library != "task" {
+        return Ok(module);
+    }
+
+    let tracked = lua.create_table()?;
+    tracked.set_metatable(Some(lua.create_table_from([("__mode", "k")])?))?;
+    lua.set_named_registry_value(TRACKED_TASKS_KEY, tracked)?;
+
+    let wrapped = lua.create_table()?;
+    for pair in module.pairs::<LuaValue, LuaValue>() {
+        let (key, value) = pair?;
+        let value = match (&key, value) {
+            (LuaValue::String(name), LuaValue::Function(func))
+                if TRACKED_TASK_FUNCTIONS.contains(&name.to_string_lossy().as_str()) =>
+            {
+                let func = lua.create_function(move |lua, args: LuaMultiValue| {
+                    let values = func.call::<LuaMultiValue>(args)?;
+                    if let Some(LuaValue::Thread(thread)) = values.front() {
+                        let tracked: LuaTable = lua.named_registry_value(TRACKED_TASKS_KEY)?;
+                        tracked.raw_set(thread.clone(), true)?;
+                    }
+                    Ok(values)
+                })?;
+                LuaValue::Function(func)
+            }
+            (_, value) => value,
+        };
+        wrapped.raw_set(key, value)?;
+    }
+
+    if module.is_readonly() {
+        wrapped.set_readonly(true);
+    }
+
+    Ok(wrapped)
+}
+
+/**
+    Gets stack traces for all tracked tasks that have not yet finished.
+*/
+fn live_task_tracebacks(lua: &Lua) -> LuaResult<Vec<String>> {
+    let Ok(tracked) = lua.named_registry_value::<LuaTable>(TRACKED_TASKS_KEY) else {
+        return Ok(Vec::new());
+    };
+
+    let traceback = lua
+        .globals()
+        .get::<LuaTable>("debug")?
+        .get::<LuaFunction>("traceback")?;
+
+    let mut tracebacks = Vec::new();
+    for pair in tracked.pairs::<LuaThread, LuaValue>() {
+        let (thread, _) = pair?;
+        if thread.status() == LuaThreadStatus::Resumable {
+            tracebacks.push(traceback.call::<String>(thread)?);
+        }
+    }
+    Ok(tracebacks)
+}
+
+fn create_report(lua: &Lua, error: &LuaError) -> String {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    let mut report = String::new();
+    let _ = writeln!(report, "Lune crash report");
+    let _ = writeln!(report);
+    let _ = writeln!(report, "Timestamp: {timestamp}");
+    let _ = writeln!(report, "Lune version: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(report, "Executable: {}", CURRENT_EXE.display());
+    let _ = writeln!(
+        report,
+        "Host: {}-{} ({})",
+        env::consts::OS,
+        env::consts::ARCH,
+        env::consts::FAMILY
+    );
+    let _ = writeln!(report, "Process id: {}", process::id());
+    if let Ok(cwd) = env::current_dir() {
+        let _ = writeln!(report, "Working directory: {}", cwd.display());
+    }
+    let _ = writeln!(
+        report,
+        "Arguments: {:?}",
+        env::args().skip(1).collect::<Vec<_>>()
+    );
+
+    let _ = writeln!(report);
+    let _ = writeln!(report, "Error:");
+    let error = console::strip_ansi_codes(&RuntimeError::from(error).to_string()).to_string();
+    let _ = writeln!(report, "{}", error.trim_end());
+
+    match live_task_tracebacks(lua) {
+        Ok(tracebacks) => {
+            for (index, traceback) in tracebacks.iter().enumerate() {
+                let _ = writeln!(report);
+                let _ = writeln!(report, "Task {}:", index + 1);
+                let _ = writeln!(report, "{}", traceback.trim_end());
+            }
+        }
+        Err(e) => {
+            let _ = writeln!(report);
+            let _ = writeln!(report, "Failed to collect task stack traces: {e}");
+        }
+    }
+
+    report
+}
+
+fn write_report(dir: &Path, contents: &str) -> std::io::Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let name = CURRENT_EXE
+        .file_stem()
+        .map_or_else(|| "lune".to_string(), |s| s.to_string_lossy().to_string());
+
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{name}-crash-{timestamp}-{}.txt", process::id()));
+    fs::write(&path, contents)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_home() {
+        let home = UserDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+        if let Some(home) = home {
+            assert_eq!(expand_home_dir("~"), home);
+            assert_eq!(expand_home_dir("~/crashes"), home.join("crashes"));
+        }
+        assert_eq!(expand_home_dir("crashes"), PathBuf::from("crashes"));
+        assert_eq!(expand_home_dir("/tmp/~"), PathBuf::from("/tmp/~"));
+    }
+
+    #[test]
+    fn report_contains_error_and_host() {
+        let lua = Lua::new();
+        let report = create_report(&lua, &LuaError::runtime("something broke"));
+        assert!(report.contains("something broke"));
+        assert!(report.contains(env::consts::OS));
+        assert!(report.contains(env!("CARGO_PKG_VERSION")));
+    }
+
+    #[test]
+    fn report_contains_live_tasks() {
+        let lua = Lua::new();
+        let task = lua.create_table().unwrap();
+        let module = track_tasks(&lua, "task", task).unwrap();
+        assert!(module.is_empty());
+
+        let thread = lua
+            .create_thread(
+                lua.load("coroutine.yield() error('unreachable')")
+                    .into_function()
+                    .unwrap(),
+            )
+            .unwrap();
+        thread.resume::<()>(()).unwrap();
+        let tracked: LuaTable = lua.named_registry_value(TRACKED_TASKS_KEY).unwrap();
+        tracked.raw_set(thread, true).unwrap();
+
+        let report = create_report(&lua, &LuaError::runtime("oops"));
+        assert!(report.contains("Task 1:"));
+    }
+}
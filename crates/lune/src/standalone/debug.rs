@@ -0,0 +1,179 @@
+/*!
+    Introspection of standalone binaries, for diagnosing them on machines
+    other than the one that built them.
+
+    When the `LUNE_STANDALONE_DEBUG` environment variable is set, a standalone
+    binary prints a description of its metadata instead of running, including
+    its entry point, bundled files, aliases and the sizes of its payload, and
+    then exits. Values of default environment variables are never printed,
+    since they may contain secrets, and files of encrypted binaries are only
+    listed when the key to decrypt them is available.
+*/
+
+use std::{collections::BTreeMap, env, fmt::Write as _};
+
+use super::metadata::Metadata;
+
+/// Environment variable that, when set to a truthy value, enables introspection
+const DEBUG_ENV_VAR: &str = "LUNE_STANDALONE_DEBUG";
+
+/**
+    Returns whether introspection was enabled using the environment.
+*/
+pub(crate) fn is_enabled() -> bool {
+    env::var(DEBUG_ENV_VAR)
+        .is_ok_and(|v| !v.is_empty() && !matches!(v.as_str(), "0" | "false" | "off"))
+}
+
+/**
+    Prints a description of the metadata of the given standalone binary.
+*/
+pub(crate) fn print(patched_bin: &[u8]) -> anyhow::Result<()> {
+    let mut meta = Metadata::from_bytes(patched_bin)?;
+    let encrypted = meta.encrypted.is_some();
+    let decrypt_error = meta.decrypt().err().map(|e| e.to_string());
+    print!(
+        "{}",
+        describe(
+            &meta,
+            patched_bin.len(),
+            encrypted,
+            decrypt_error.as_deref()
+        )
+    );
+    Ok(())
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
+
+/**
+    Describes the given metadata, which was read from a binary of the given size,
+    as plain text, with lists of files, aliases and similar sorted by name.
+*/
+fn describe(
+    meta: &Metadata,
+    binary_len: usize,
+    encrypted: bool,
+    decrypt_error: Option<&str>,
+) -> String {
+    let mut out = String::new();
+    let files = meta
+        .files
+        .iter()
+        .map(|(path, contents)| (path.as_str(), contents.len()))
+        .collect::<BTreeMap<_, _>>();
+    let files_len = files.values().sum::<usize>();
+
+    let _ = writeln!(out, "Standalone binary");
+    let _ = writeln!(out, "  Lune version: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(out, "  Binary size: {binary_len} bytes");
+    let _ = writeln!(out, "  Entry path: {}", meta.entry_path);
+    let _ = writeln!(out, "  Entry size: {} bytes", meta.source.len());
+    let _ = writeln!(out, "  Bundled size: {files_len} bytes");
+    let _ = writeln!(out, "  Source stripped: {}", yes_no(meta.source_stripped));
+    match (encrypted, decrypt_error) {
+        (false, _) => {
+            let _ = writeln!(out, "  Encrypted: no");
+        }
+        (true, None) => {
+            let _ = writeln!(out, "  Encrypted: yes (decrypted)");
+        }
+        (true, Some(e)) => {
+            let _ = writeln!(out, "  Encrypted: yes (could not decrypt - {e})");
+        }
+    }
+    if let Some(dir) = &meta.crash_report_dir {
+        let _ = writeln!(out, "  Crash report directory: {dir}");
+    }
+    let _ = writeln!(
+        out,
+        "  Reporting consent: {}",
+        yes_no(meta.reporting_consent)
+    );
+    let _ = writeln!(out, "  Console setup: {}", yes_no(!meta.no_console_setup));
+
+    if let Some(build) = &meta.build {
+        let _ = writeln!(out, "Build");
+        let _ = writeln!(out, "  Timestamp: {}", build.timestamp);
+        let _ = writeln!(out, "  Lune version: {}", build.lune_version);
+        let _ = writeln!(out, "  Target: {}", build.target);
+        if let Some(commit) = &build.commit {
+            let _ = writeln!(out, "  Commit: {commit}");
+        }
+        for (key, value) in &build.fields {
+            let _ = writeln!(out, "  {key}: {value}");
+        }
+    }
+
+    let _ = writeln!(out, "Entries ({})", meta.entries.len());
+    for (name, path) in &meta.entries {
+        let _ = writeln!(out, "  {name} -> {path}");
+    }
+
+    let _ = writeln!(out, "Files ({})", files.len());
+    for (path, len) in &files {
+        let _ = writeln!(out, "  {path} ({len} bytes)");
+    }
+
+    let aliases = meta.aliases.iter().collect::<BTreeMap<_, _>>();
+    let _ = writeln!(out, "Aliases ({})", aliases.len());
+    for (alias, path) in aliases {
+        let _ = writeln!(out, "  {alias} -> {path}");
+    }
+
+    let links = meta.links.iter().collect::<BTreeMap<_, _>>();
+    let _ = writeln!(out, "Links ({})", links.len());
+    for (link, path) in links {
+        let _ = writeln!(out, "  {link} -> {path}");
+    }
+
+    let _ = writeln!(out, "Defines ({})", meta.defines.len());
+    for (key, value) in &meta.defines {
+        let _ = writeln!(out, "  {key}={value}");
+    }
+
+    let _ = writeln!(out, "Environment ({})", meta.env.len());
+    for key in meta.env.keys() {
+        let _ = writeln!(out, "  {key}");
+    }
+
+    let _ = writeln!(out, "Arguments ({})", meta.args.len());
+    for arg in &meta.args {
+        let _ = writeln!(out, "  {arg}");
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_metadata_without_env_values() {
+        let mut meta = Metadata::new("return 1", "/main.luau");
+        meta.files
+            .insert("/lib.luau".to_string(), b"return 2".to_vec());
+        meta.aliases
+            .insert("@lib".to_string(), "/lib.luau".to_string());
+        meta.env
+            .insert("API_TOKEN".to_string(), "secret".to_string());
+
+        let description = describe(&meta, 1024, false, None);
+        assert!(description.contains("Binary size: 1024 bytes"));
+        assert!(description.contains("Entry path: /main.luau"));
+        assert!(description.contains("/lib.luau (8 bytes)"));
+        assert!(description.contains("@lib -> /lib.luau"));
+        assert!(description.contains("API_TOKEN"));
+        assert!(!description.contains("secret"));
+    }
+
+    #[test]
+    fn describes_undecryptable_metadata() {
+        let meta = Metadata::new("", "/main.luau");
+        let description = describe(&meta, 0, true, Some("missing key"));
+        assert!(description.contains("Encrypted: yes (could not decrypt - missing key)"));
+    }
+}
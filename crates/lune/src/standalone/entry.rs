@@ -0,0 +1,211 @@
+/*!
+    Entry point selection for standalone binaries with several entry points.
+
+    Binaries built using `lune build --entry NAME=PATH` work like busybox: the
+    same binary may be installed under several names, usually using symlinks,
+    and runs the entry point named after the name that it was invoked as.
+
+    When the binary can not be invoked under another name, such as on systems
+    without symlinks, the entry point may be given as the first argument
+    instead, using `--lune-entry <name>` or `--lune-entry=<name>`.
+
+    Binaries invoked under any other name run their default entry point.
+
+    Any standalone binary may also run a script from the filesystem instead of its
+    entry point, for debugging, using `--lune-run <path>` or `--lune-run=<path>`. The
+    script runs with the same runtime, and may require bundled files using aliases.
+*/
+
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result, bail};
+
+use super::metadata::Metadata;
+
+const ENTRY_FLAG: &str = "--lune-entry";
+const RUN_FLAG: &str = "--lune-run";
+
+/**
+    Returns the name that the binary was invoked as, given the
+    first argument of the process, without any directories or extension.
+*/
+fn invoked_name(arg0: &str) -> Option<&str> {
+    Path::new(arg0).file_stem()?.to_str()
+}
+
+/**
+    Takes the value of the given flag out of the given arguments, which is only
+    recognized as the first argument, so that scripts may still receive
+    the flag themselves after any other argument.
+*/
+fn take_flag(args: &mut Vec<String>, flag: &str) -> Result<Option<String>> {
+    let Some(first) = args.first() else {
+        return Ok(None);
+    };
+    if let Some(value) = first.strip_prefix(flag).and_then(|s| s.strip_prefix('=')) {
+        let value = value.to_string();
+        args.remove(0);
+        return Ok(Some(value));
+    }
+    if first != flag {
+        return Ok(None);
+    }
+    if args.len() < 2 {
+        bail!("{flag} requires a value");
+    }
+    let mut taken = args.drain(..2);
+    Ok(taken.nth(1))
+}
+
+/**
+    Makes the script at the given path the one that runs, instead of the entry point.
+*/
+fn run_script<F>(meta: &mut Metadata<F>, path: &str) -> Result<()> {
+    let source = fs::read(path).with_context(|| format!("failed to read script {path}"))?;
+    meta.source = lune_bundler::strip_shebang(source);
+    meta.entry_path = path.to_string();
+    Ok(())
+}
+
+/**
+    Takes a script given using `--lune-run` out of the given arguments, which must
+    be done before decrypting the given metadata, since encrypted binaries may
+    not run other scripts, which could otherwise read their decrypted files.
+
+    # Errors
+
+    - If a script was given using `--lune-run` for an encrypted binary
+*/
+pub(crate) fn take_script<F>(meta: &Metadata<F>, args: &mut Vec<String>) -> Result<Option<String>> {
+    let script = take_flag(args, RUN_FLAG)?;
+    if script.is_some() && meta.encrypted.is_some() {
+        bail!("{RUN_FLAG} is not supported for encrypted binaries");
+    }
+    Ok(script)
+}
+
+/**
+    Selects the entry point to run for the given metadata, and returns the
+    arguments to pass to it, without any entry point given using `--lune-entry`.
+
+    Binaries without named entry points always run their only entry point,
+    and receive all of their arguments as-is, unless given a script to run.
+
+    # Errors
+
+    - If an entry point was given using `--lune-entry`, but there is no entry point with its name
+    - If the given script could not be read
+*/
+pub(crate) fn select<F: AsRef<[u8]> + From<Vec<u8>>>(
+    meta: &mut Metadata<F>,
+    arg0: Option<&str>,
+    script: Option<&str>,
+    mut args: Vec<String>,
+) -> Result<Vec<String>> {
+    if let Some(path) = script {
+        run_script(meta, path)?;
+        return Ok(args);
+    }
+    if meta.entries.is_empty() {
+        return Ok(args);
+    }
+    if let Some(name) = take_flag(&mut args, ENTRY_FLAG)? {
+        meta.select_entry(&name)?;
+    } else if let Some(name) = arg0.and_then(invoked_name)
+        && meta.entries.contains_key(name)
+    {
+        meta.select_entry(name)?;
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> Metadata {
+        let mut meta = Metadata::new("return 'main'", "/main.luau");
+        for name in ["fmt", "lint"] {
+            let path = format!("/tools/{name}.luau");
+            meta.files
+                .insert(path.clone(), format!("return '{name}'").into_bytes());
+            meta.entries.insert(name.to_string(), path);
+        }
+        meta
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn selects_by_invoked_name() {
+        let mut meta = metadata();
+        let rest = select(&mut meta, Some("/usr/local/bin/lint"), None, args(&["a"])).unwrap();
+        assert_eq!(meta.entry_path, "/tools/lint.luau");
+        assert_eq!(rest, ["a"]);
+
+        let mut meta = metadata();
+        select(&mut meta, Some("fmt.exe"), None, args(&[])).unwrap();
+        assert_eq!(meta.entry_path, "/tools/fmt.luau");
+    }
+
+    #[test]
+    fn falls_back_to_default_entry() {
+        let mut meta = metadata();
+        let rest = select(&mut meta, Some("./toolbox"), None, args(&["lint"])).unwrap();
+        assert_eq!(meta.entry_path, "/main.luau");
+        assert_eq!(rest, ["lint"]);
+    }
+
+    #[test]
+    fn selects_by_flag() {
+        let mut meta = metadata();
+        let rest = select(
+            &mut meta,
+            Some("fmt"),
+            None,
+            args(&["--lune-entry", "lint", "--lune-entry", "x"]),
+        )
+        .unwrap();
+        assert_eq!(meta.entry_path, "/tools/lint.luau");
+        assert_eq!(rest, ["--lune-entry", "x"]);
+
+        let mut meta = metadata();
+        let rest = select(&mut meta, None, None, args(&["--lune-entry=fmt", "b"])).unwrap();
+        assert_eq!(meta.entry_path, "/tools/fmt.luau");
+        assert_eq!(rest, ["b"]);
+    }
+
+    #[test]
+    fn rejects_unknown_or_missing_flag_names() {
+        assert!(select(&mut metadata(), None, None, args(&["--lune-entry", "nope"])).is_err());
+        assert!(select(&mut metadata(), None, None, args(&["--lune-entry"])).is_err());
+    }
+
+    #[test]
+    fn runs_given_script() {
+        let dir = std::env::temp_dir().join(format!("lune-entry-run-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("debug.luau");
+        fs::write(&path, "#!/usr/bin/env lune\nreturn 'debug'").unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let mut meta = metadata();
+        let mut args = args(&[RUN_FLAG, path.as_str(), "--lune-entry", "fmt"]);
+        let script = take_script(&meta, &mut args).unwrap();
+        let rest = select(&mut meta, Some("lint"), script.as_deref(), args).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(meta.entry_path, path);
+        assert_eq!(meta.source, b"\nreturn 'debug'");
+        assert_eq!(rest, ["--lune-entry", "fmt"]);
+    }
+
+    #[test]
+    fn ignores_flag_without_entries() {
+        let mut meta = Metadata::new("return 1", "/main.luau");
+        let rest = select(&mut meta, None, None, args(&["--lune-entry", "lint"])).unwrap();
+        assert_eq!(rest, ["--lune-entry", "lint"]);
+    }
+}
@@ -1,104 +1,90 @@
-use std::collections::HashMap;
+#[cfg(unix)]
+use std::ops::Range;
+#[cfg(not(target_os = "wasi"))]
+use std::path::Path;
 use std::{env, path::PathBuf, sync::LazyLock};
 
-use anyhow::{Result, bail};
-use async_fs as fs;
-use serde::{Deserialize, Serialize};
+use lune::BundledStorage;
 
-pub static CURRENT_EXE: LazyLock<PathBuf> =
-    LazyLock::new(|| env::current_exe().expect("failed to get current exe"));
-const MAGIC: &[u8; 8] = b"cr3sc3nt";
+pub use lune_bundler::Metadata;
+
+pub static CURRENT_EXE: LazyLock<PathBuf> = LazyLock::new(|| {
+    // NOTE: WASI has no concept of a current executable, so
+    // the name the module was run with is used instead, if any
+    if cfg!(target_os = "wasi") {
+        env::args().next().map(PathBuf::from).unwrap_or_default()
+    } else {
+        env::current_exe().expect("failed to get current exe")
+    }
+});
 
 /**
-    Metadata for a standalone Lune executable. Can be used to
-    discover and load the source code contained in a standalone binary.
+    The bytes of a standalone binary.
 
-    Stores the entry point source, its path, and all bundled dependencies.
+    Binaries are mapped into memory instead of being read fully, so that
+    only the parts needed to find and read their metadata are ever loaded,
+    while the payload of WASI modules is always copied out of the module.
 */
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Metadata {
-    /// The entry point source code
-    pub source: Vec<u8>,
-    /// The entry point path (for chunk naming)
-    pub entry_path: String,
-    /// Bundled module files: canonical path -> source
-    #[serde(default)]
-    pub files: HashMap<String, Vec<u8>>,
-    /// Alias mappings: alias (e.g., "@packages/Foo") -> canonical path
-    #[serde(default)]
-    pub aliases: HashMap<String, String>,
-}
+#[cfg(not(target_os = "wasi"))]
+pub type StandaloneBytes = memmap2::Mmap;
+#[cfg(target_os = "wasi")]
+pub type StandaloneBytes = Vec<u8>;
 
-impl Metadata {
-    /**
-        Returns whether or not the currently executing Lune binary
-        is a standalone binary, and if so, the bytes of the binary.
-    */
-    pub async fn check_env() -> (bool, Vec<u8>) {
-        let contents = fs::read(CURRENT_EXE.to_path_buf())
-            .await
-            .unwrap_or_default();
-        let is_standalone = contents.ends_with(MAGIC);
-        (is_standalone, contents)
-    }
+/**
+    Storage for bundled files that reads them in place from the bytes of
+    a standalone binary, so that files are never copied out of the binary,
+    and only those that are actually required are ever loaded into memory.
+*/
+pub struct StandaloneStorage(pub StandaloneBytes);
 
-    /**
-        Creates a patched standalone binary from the given script contents.
-    */
-    pub async fn create_env_patched_bin(
-        base_exe_path: PathBuf,
-        script_contents: impl Into<Vec<u8>>,
-        entry_path: impl Into<String>,
-        files: HashMap<String, Vec<u8>>,
-        aliases: HashMap<String, String>,
-    ) -> Result<Vec<u8>> {
-        let mut patched_bin = fs::read(base_exe_path).await?;
+impl BundledStorage for StandaloneStorage {
+    fn bytes(&self) -> &[u8] {
+        &self.0
+    }
 
-        let meta = Self {
-            source: script_contents.into(),
-            entry_path: entry_path.into(),
-            files,
-            aliases,
+    #[cfg(unix)]
+    fn evict(&self, range: Range<usize>) {
+        // SAFETY: The mapping is read-only, so dropping its pages only means that they
+        // are read from the binary again when next used, and no changes can be lost -
+        // the range is rounded to whole pages, which may also drop parts of other files
+        let _ = unsafe {
+            self.0.unchecked_advise_range(
+                memmap2::UncheckedAdvice::DontNeed,
+                range.start,
+                range.len(),
+            )
         };
-        patched_bin.extend_from_slice(&meta.to_bytes()?);
-
-        Ok(patched_bin)
     }
+}
 
-    /**
-        Tries to read a standalone binary from the given bytes.
-    */
-    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Result<Self> {
-        let bytes = bytes.as_ref();
-        // Minimum size: 8 (magic) + 8 (json_size) = 16
-        if bytes.len() < 16 || !bytes.ends_with(MAGIC) {
-            bail!("not a standalone binary")
-        }
-
-        // Extract JSON size (8 bytes before magic)
-        let json_size_bytes = &bytes[bytes.len() - 16..bytes.len() - 8];
-        let json_size = usize::try_from(u64::from_be_bytes(json_size_bytes.try_into().unwrap()))?;
-
-        // Extract JSON data
-        let json_start = bytes.len() - 16 - json_size;
-        let json_bytes = &bytes[json_start..json_start + json_size];
-
-        // Deserialize
-        let meta: Self = serde_json::from_slice(json_bytes)?;
-        Ok(meta)
+/**
+    Returns the bytes of the currently executing Lune binary,
+    if it is a standalone binary, or `None` otherwise.
+*/
+pub fn check_env() -> Option<StandaloneBytes> {
+    #[cfg(target_os = "wasi")]
+    {
+        let payload = super::wasi::read_payload();
+        Metadata::is_standalone(&payload).then_some(payload)
+    }
+    #[cfg(not(target_os = "wasi"))]
+    {
+        check_path(&CURRENT_EXE)
     }
+}
 
-    /**
-        Writes the metadata chunk to a byte vector, to later be read using `from_bytes`.
+/**
+    Returns the bytes of the binary or library at the given
+    path, if it is a standalone binary, or `None` otherwise.
 
-        Format: [json_data][json_size: u64][MAGIC: 8 bytes]
-    */
-    pub fn to_bytes(&self) -> Result<Vec<u8>> {
-        let json_bytes = serde_json::to_vec(self)?;
-        let mut bytes = Vec::new();
-        bytes.extend_from_slice(&json_bytes);
-        bytes.extend_from_slice(&(json_bytes.len() as u64).to_be_bytes());
-        bytes.extend_from_slice(MAGIC);
-        Ok(bytes)
-    }
+    Only the end and headers of the binary are read for binaries that
+    are not standalone, which keeps startup fast for the Lune binary.
+*/
+#[cfg(not(target_os = "wasi"))]
+pub fn check_path(path: &Path) -> Option<StandaloneBytes> {
+    let file = std::fs::File::open(path).ok()?;
+    // SAFETY: The mapping is read-only, and the binary is expected to not be
+    // modified while it is running, which most systems also prevent entirely
+    let bytes = unsafe { memmap2::Mmap::map(&file) }.ok()?;
+    Metadata::is_standalone(&bytes).then_some(bytes)
 }
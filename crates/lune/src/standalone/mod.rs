@@ -1,49 +1,159 @@
-use std::{env, process::ExitCode};
+use std::{collections::BTreeMap, env, ffi::OsString, process::ExitCode, sync::Arc};
 
 use anyhow::Result;
-use lune::Runtime;
+use lune::{BundledFile, BundledStorage, Runtime};
+use lune_utils::require_trace::RequireTrace;
 
+use crate::{require_trace::TraceOutput, terminal};
+
+pub(crate) mod crash;
+pub(crate) mod debug;
+pub(crate) mod entry;
 pub(crate) mod metadata;
 pub(crate) mod tracer;
+#[cfg(target_os = "wasi")]
+pub(crate) mod wasi;
+
+pub(crate) use lune_bundler::{section, signature};
 
-use self::metadata::Metadata;
+use self::crash::CrashReporter;
+use self::metadata::{Metadata, StandaloneBytes, StandaloneStorage};
+use self::tracer::SourceMapper;
 
 /**
-    Returns whether or not the currently executing Lune binary
-    is a standalone binary, and if so, the bytes of the binary.
+    Returns the currently executing Lune binary mapped into memory, if it
+    is a standalone binary, or `None` otherwise. See [`StandaloneBytes`].
+
+    Metadata and bundled files are read in place from the mapping, so
+    it must be kept alive for as long as anything borrowed from it.
 */
-pub async fn check() -> Option<Vec<u8>> {
-    let (is_standalone, patched_bin) = Metadata::check_env().await;
-    if is_standalone {
-        Some(patched_bin)
-    } else {
-        None
-    }
+pub fn check() -> Option<StandaloneBytes> {
+    metadata::check_env()
 }
 
 /**
     Discovers, loads and executes the bytecode contained in a standalone binary.
 */
-pub async fn run(patched_bin: impl AsRef<[u8]>) -> Result<ExitCode> {
-    // The first argument is the path to the current executable
-    let args = env::args().skip(1).collect::<Vec<_>>();
-    let meta = Metadata::from_bytes(patched_bin).expect("must be a standalone binary");
+pub async fn run(patched_bin: StandaloneBytes) -> Result<ExitCode> {
+    if debug::is_enabled() {
+        debug::print(&patched_bin)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    // The first argument is the name the current executable was invoked as,
+    // which selects the entry point to run for binaries with several of them
+    let mut args = env::args();
+    let arg0 = args.next();
+    let code = run_invoked_as(patched_bin, arg0.as_deref(), args.collect()).await?;
+    Ok(ExitCode::from(code))
+}
+
+/**
+    Loads and executes the bytecode contained in a standalone binary or
+    library, with the given script arguments, returning the exit code.
+*/
+pub async fn run_with_args(patched_bin: StandaloneBytes, args: Vec<String>) -> Result<u8> {
+    run_invoked_as(patched_bin, None, args).await
+}
+
+/**
+    Reads the metadata of the given standalone binary, with bundled files that are read
+    in place from the binary, instead of being copied out of it, whenever possible.
+*/
+fn read_metadata(patched_bin: StandaloneBytes) -> Result<Metadata<BundledFile>> {
+    let storage: Arc<dyn BundledStorage> = Arc::new(StandaloneStorage(patched_bin));
+    Ok(match Metadata::from_bytes_mapped(storage.bytes())? {
+        Some(meta) => meta.map_files(|range| BundledFile::new(Arc::clone(&storage), range)),
+        None => Metadata::from_bytes(storage.bytes())?.map_files(BundledFile::from),
+    })
+}
+
+async fn run_invoked_as(
+    patched_bin: StandaloneBytes,
+    arg0: Option<&str>,
+    mut args: Vec<String>,
+) -> Result<u8> {
+    let mut meta = read_metadata(patched_bin)?;
+    let script = entry::take_script(&meta, &mut args)?;
+    meta.decrypt()?;
+    let args = entry::select(&mut meta, arg0, script.as_deref(), args)?;
+
+    let _console = terminal::setup(!meta.no_console_setup);
+
+    // Default arguments come first, so that arguments given when the binary runs
+    // may add to them, or override them for scripts where later arguments win
+    let args = std::mem::take(&mut meta.args)
+        .into_iter()
+        .chain(args)
+        .collect::<Vec<_>>();
+
+    let crash_reporter = meta.crash_report_dir.as_deref().map(CrashReporter::new);
+    let source_mapper = SourceMapper::new(meta.source_map);
+    let crash_reports_enabled = crash_reporter.is_some();
+
+    // Default environment variables never override the real environment
+    let mut env = meta
+        .env
+        .into_iter()
+        .map(|(key, value)| (OsString::from(key), OsString::from(value)))
+        .collect::<BTreeMap<_, _>>();
+    env.extend(env::vars_os());
 
     let mut rt = Runtime::new()?
         .with_args(args)
+        .with_env(env)
         .with_bundled_files(meta.files)
         .with_bundled_aliases(meta.aliases)
-        .with_executable_path(metadata::CURRENT_EXE.clone());
+        .with_bundled_links(meta.links)
+        .with_executable_path(metadata::CURRENT_EXE.clone())
+        .with_reporting_consent(meta.reporting_consent)
+        .with_defines(meta.defines)
+        .with_exit_codes(meta.exit_codes)
+        .with_std_module_hook(move |lua, library, module| {
+            let module = tracer::trace_std_module(lua, library, module)?;
+            if crash_reports_enabled {
+                crash::track_tasks(lua, library, module)
+            } else {
+                Ok(module)
+            }
+        })
+        .with_error_hook(move |lua, error| {
+            let error = tracer::interleave_builtin_frames(error);
+            let error = match &source_mapper {
+                Some(mapper) => mapper.rewrite(error),
+                None => error,
+            };
+            if let Some(reporter) = &crash_reporter {
+                reporter.report(lua, &error);
+            }
+            error
+        });
+
+    if let Some(build) = meta.build {
+        rt = rt.with_build_info(build);
+    }
+    if let Some(graph) = meta.require_graph {
+        rt = rt.with_require_graph(graph);
+    }
+    let trace_output = TraceOutput::from_env();
+    let trace = trace_output.as_ref().map(|_| RequireTrace::new());
+    if let Some(trace) = &trace {
+        rt = rt.with_require_trace(trace.clone());
+    }
 
     // Compile and run the source with the original entry path.
     // This ensures the chunk name is set correctly for require resolution.
     let result = rt.run_source(&meta.entry_path, meta.source).await;
 
+    if let (Some(output), Some(trace)) = (&trace_output, &trace) {
+        output.write(trace).await?;
+    }
+
     Ok(match result {
         Err(err) => {
             eprintln!("{err}");
-            ExitCode::FAILURE
+            meta.exit_codes.runtime_error
         }
-        Ok(values) => ExitCode::from(values.status()),
+        Ok(values) => values.status(),
     })
 }
@@ -1,14 +1,462 @@
-/*
-    TODO: Implement tracing of requires here
+/*!
+    Tracing of builtin calls for standalone binaries.
 
-    Rough steps / outline:
+    Errors thrown from inside of builtin implementations, such as `fs.readFile`
+    failing to find a file, normally only show the Lua frames of the script that
+    made the call - the builtin itself shows up as an anonymous `[C]` frame, if
+    at all, and the arguments it was given are lost completely.
 
-    1. Create a new tracer struct using a main entrypoint script path
-    2. Some kind of discovery mechanism that goes through all require chains (failing on recursive ones)
-       2a. Conversion of script-relative paths to cwd-relative paths + normalization
-       2b. Cache all found files in a map of file path -> file contents
-       2c. Prepend some kind of symbol to paths that can tell our runtime `require` function that it
-           should look up a bundled/standalone script, a good symbol here is probably a dollar sign ($)
-    3. ???
-    4. Profit
+    The tracer wraps the functions of builtin libraries so that any error they
+    throw remembers which call failed and a short summary of its arguments, and
+    then interleaves that information into the Lua traceback as its own frame
+    right before the error is reported, producing a combined report such as:
+
+    ```plaintext
+    No such file or directory (os error 2)
+    [Stack Begin]
+        Script '[C]' - function 'fs.readFile("config.json")'
+        Script 'main', Line 3
+    [Stack End]
+    ```
+
+    Binaries built with a source map also have the locations of bundled files
+    in their errors rewritten back to the original paths of those files.
+*/
+
+use std::{collections::HashMap, error::Error, fmt, sync::Arc};
+
+use mlua::prelude::*;
+use regex::{Captures, Regex};
+
+/// Libraries whose functions are traced - these are the ones
+/// where builtins interact with the outside world, and where
+/// errors depend on their arguments, such as file paths or urls
+const TRACED_LIBRARIES: &[&str] = &["fs", "net", "process"];
+
+const MAX_SUMMARY_ARGS: usize = 4;
+const MAX_SUMMARY_STRING_LEN: usize = 48;
+
+const TRACEBACK_HEADER: &str = "stack traceback:";
+
+/**
+    An error thrown from a traced builtin call.
+
+    Contains a description of the call, including a summary of its arguments.
+*/
+#[derive(Debug, Clone)]
+struct BuiltinCallError {
+    call: String,
+    cause: LuaError,
+}
+
+impl fmt::Display for BuiltinCallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.cause)
+    }
+}
+
+impl Error for BuiltinCallError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.cause)
+    }
+}
+
+/**
+    Wraps all functions in the given standard library module so that any
+    errors they throw can later be interleaved into the Lua traceback.
+
+    Libraries that are not traced are returned unchanged.
+*/
+pub fn trace_std_module(lua: &Lua, library: &str, module: LuaTable) -> LuaResult<LuaTable> {
+    if !TRACED_LIBRARIES.contains(&library) {
+        return Ok(module);
+    }
+
+    let traced = lua.create_table()?;
+    for pair in module.pairs::<LuaValue, LuaValue>() {
+        let (key, value) = pair?;
+        let value = match (&key, value) {
+            // NOTE: Only native functions are wrapped, functions implemented in
+            // Luau (such as process.exit) rely on yielding to the scheduler and
+            // already show up with proper frames in the traceback
+            (LuaValue::String(name), LuaValue::Function(func)) if func.info().what == "C" => {
+                let call_name = format!("{library}.{}", name.to_string_lossy());
+                LuaValue::Function(trace_function(lua, call_name, func)?)
+            }
+            (_, value) => value,
+        };
+        traced.raw_set(key, value)?;
+    }
+
+    // Preserve any metatable and readonly status of the original module
+    if let Some(meta) = module.metatable() {
+        traced.set_metatable(Some(meta))?;
+    }
+    if module.is_readonly() {
+        traced.set_readonly(true);
+    }
+
+    Ok(traced)
+}
+
+fn trace_function(lua: &Lua, call_name: String, func: LuaFunction) -> LuaResult<LuaFunction> {
+    lua.create_async_function(move |_, args: LuaMultiValue| {
+        let func = func.clone();
+        let call_name = call_name.clone();
+        async move {
+            // The summary must be created before calling, since
+            // the arguments are moved into the traced function
+            let summary = summarize_args(&args);
+            func.call_async::<LuaMultiValue>(args)
+                .await
+                .map_err(|cause| {
+                    LuaError::external(BuiltinCallError {
+                        call: format!("{call_name}({summary})"),
+                        cause,
+                    })
+                })
+        }
+    })
+}
+
+/**
+    Creates a short, single-line summary of the given arguments.
+
+    Strings are quoted and truncated, and values that can not be
+    displayed in a meaningful way are summarized by their type.
+*/
+fn summarize_args(args: &LuaMultiValue) -> String {
+    let mut parts = args
+        .iter()
+        .take(MAX_SUMMARY_ARGS)
+        .map(summarize_value)
+        .collect::<Vec<_>>();
+    if args.len() > MAX_SUMMARY_ARGS {
+        parts.push("...".to_string());
+    }
+    parts.join(", ")
+}
+
+fn summarize_value(value: &LuaValue) -> String {
+    match value {
+        LuaValue::Nil => "nil".to_string(),
+        LuaValue::Boolean(b) => b.to_string(),
+        LuaValue::Integer(i) => i.to_string(),
+        LuaValue::Number(n) => n.to_string(),
+        LuaValue::String(s) => {
+            let s = s.to_string_lossy();
+            if s.chars().count() > MAX_SUMMARY_STRING_LEN {
+                let truncated = s.chars().take(MAX_SUMMARY_STRING_LEN).collect::<String>();
+                format!("{truncated:?}...")
+            } else {
+                format!("{s:?}")
+            }
+        }
+        LuaValue::Buffer(buf) => format!("buffer({})", buf.len()),
+        other => other.type_name().to_string(),
+    }
+}
+
+/**
+    Finds the traced builtin call that an error was thrown from, if any.
+*/
+fn find_builtin_call(error: &LuaError) -> Option<&BuiltinCallError> {
+    match error {
+        LuaError::ExternalError(inner) => inner.downcast_ref::<BuiltinCallError>(),
+        LuaError::CallbackError { cause, .. } | LuaError::WithContext { cause, .. } => {
+            find_builtin_call(cause)
+        }
+        _ => None,
+    }
+}
+
+/**
+    Finds the innermost cause of an error thrown from inside of a builtin,
+    skipping over any callback errors with tracebacks that only contain
+    native frames, and which would otherwise be duplicated in the report.
+*/
+fn root_cause(error: &LuaError) -> LuaError {
+    match error {
+        LuaError::CallbackError { cause, .. } => root_cause(cause),
+        _ => error.clone(),
+    }
+}
+
+/**
+    Inserts a frame for a builtin call into a Lua traceback, replacing
+    the anonymous native frame that the traced call itself would show.
+*/
+fn insert_builtin_frame(traceback: &str, call: &str) -> String {
+    let frame = format!("[C]: in function '{call}'");
+    let Some((before, after)) = traceback.split_once(TRACEBACK_HEADER) else {
+        return format!("{TRACEBACK_HEADER}\n{frame}\n{traceback}");
+    };
+
+    let mut lines = after.trim_start_matches(['\r', '\n']).lines().peekable();
+    if lines.peek().is_some_and(|line| line.trim() == "[C]: in ?") {
+        lines.next();
+    }
+
+    let mut combined = format!("{before}{TRACEBACK_HEADER}\n{frame}");
+    for line in lines {
+        combined.push('\n');
+        combined.push_str(line);
+    }
+    combined
+}
+
+/**
+    Interleaves the builtin call an error was thrown from, if any, into
+    the Lua traceback of the error, so that both are reported together.
+
+    Errors that were not thrown from traced builtins are returned unchanged.
+*/
+pub fn interleave_builtin_frames(error: LuaError) -> LuaError {
+    match error {
+        LuaError::WithContext { context, cause } => LuaError::WithContext {
+            context,
+            cause: Arc::new(interleave_builtin_frames(cause.as_ref().clone())),
+        },
+        LuaError::CallbackError { traceback, cause } => match find_builtin_call(&cause) {
+            Some(builtin) => LuaError::CallbackError {
+                traceback: insert_builtin_frame(&traceback, &builtin.call),
+                cause: Arc::new(root_cause(&builtin.cause)),
+            },
+            None => LuaError::CallbackError { traceback, cause },
+        },
+        other => other,
+    }
+}
+
+/**
+    Rewrites locations of bundled files in errors, such as `/main.luau:3`,
+    back to the original paths of those files, using a source map.
+
+    Bundled files keep their line numbers, so only paths need to be rewritten.
 */
+#[derive(Debug)]
+pub struct SourceMapper {
+    pattern: Regex,
+    paths: HashMap<String, String>,
+}
+
+impl SourceMapper {
+    /**
+        Creates a new source mapper from a source map of bundle paths to original paths.
+
+        Returns `None` if the source map is empty.
+    */
+    pub fn new(source_map: HashMap<String, String>) -> Option<Self> {
+        if source_map.is_empty() {
+            return None;
+        }
+
+        // Longer paths go first, so that no path matches only part of another
+        let mut keys = source_map
+            .keys()
+            .map(|k| regex::escape(k))
+            .collect::<Vec<_>>();
+        keys.sort_by_key(|k| std::cmp::Reverse(k.len()));
+        let pattern = format!(r#"(?m)(^|[\s'"(\[])({}):(\d+)"#, keys.join("|"));
+
+        Some(Self {
+            pattern: Regex::new(&pattern).ok()?,
+            paths: source_map,
+        })
+    }
+
+    fn rewrite_text(&self, text: &str) -> String {
+        self.pattern
+            .replace_all(text, |caps: &Captures| {
+                let original = self.paths.get(&caps[2]).map_or(&caps[2], String::as_str);
+                format!("{}{original}:{}", &caps[1], &caps[3])
+            })
+            .to_string()
+    }
+
+    /**
+        Rewrites all locations of bundled files in the given error,
+        including its messages, tracebacks, and any nested causes.
+    */
+    pub fn rewrite(&self, error: LuaError) -> LuaError {
+        match error {
+            LuaError::RuntimeError(message) => LuaError::RuntimeError(self.rewrite_text(&message)),
+            LuaError::SyntaxError {
+                message,
+                incomplete_input,
+            } => LuaError::SyntaxError {
+                message: self.rewrite_text(&message),
+                incomplete_input,
+            },
+            LuaError::CallbackError { traceback, cause } => LuaError::CallbackError {
+                traceback: self.rewrite_text(&traceback),
+                cause: Arc::new(self.rewrite(cause.as_ref().clone())),
+            },
+            LuaError::WithContext { context, cause } => LuaError::WithContext {
+                context: self.rewrite_text(&context),
+                cause: Arc::new(self.rewrite(cause.as_ref().clone())),
+            },
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source_mapper() -> SourceMapper {
+        SourceMapper::new(HashMap::from([
+            ("/main.luau".to_string(), "tools/cli/main.luau".to_string()),
+            (
+                "/lib/main.luau".to_string(),
+                "tools/cli/lib/main.luau".to_string(),
+            ),
+        ]))
+        .unwrap()
+    }
+
+    fn builtin_error(call: &str, message: &str) -> LuaError {
+        LuaError::CallbackError {
+            traceback: "stack traceback:\n[C]: in ?\n[string \"main\"]:3: in function 'load'"
+                .to_string(),
+            cause: Arc::new(LuaError::external(BuiltinCallError {
+                call: call.to_string(),
+                cause: LuaError::CallbackError {
+                    traceback: "stack traceback:\n[C]: in ?".to_string(),
+                    cause: Arc::new(LuaError::runtime(message)),
+                },
+            })),
+        }
+    }
+
+    #[test]
+    fn summarize_basic_values() {
+        let lua = Lua::new();
+        let args = LuaMultiValue::from_vec(vec![
+            LuaValue::String(lua.create_string("file.txt").unwrap()),
+            LuaValue::Boolean(true),
+            LuaValue::Integer(5),
+            LuaValue::Nil,
+        ]);
+        assert_eq!(summarize_args(&args), "\"file.txt\", true, 5, nil");
+    }
+
+    #[test]
+    fn summarize_truncates() {
+        let lua = Lua::new();
+        let long = "a".repeat(MAX_SUMMARY_STRING_LEN + 10);
+        let args = LuaMultiValue::from_vec(vec![
+            LuaValue::String(lua.create_string(&long).unwrap()),
+            LuaValue::Table(lua.create_table().unwrap()),
+            LuaValue::Nil,
+            LuaValue::Nil,
+            LuaValue::Nil,
+        ]);
+        let summary = summarize_args(&args);
+        assert!(summary.starts_with(&format!("\"{}\"...", "a".repeat(MAX_SUMMARY_STRING_LEN))));
+        assert!(summary.contains("table"));
+        assert!(summary.ends_with("..."));
+    }
+
+    #[test]
+    fn insert_frame_replaces_anonymous_native_frame() {
+        let combined = insert_builtin_frame(
+            "stack traceback:\n[C]: in ?\n[string \"main\"]:3",
+            "fs.readFile(\"a\")",
+        );
+        assert_eq!(
+            combined,
+            "stack traceback:\n[C]: in function 'fs.readFile(\"a\")'\n[string \"main\"]:3"
+        );
+    }
+
+    #[test]
+    fn insert_frame_keeps_other_frames() {
+        let combined = insert_builtin_frame(
+            "stack traceback:\n[string \"main\"]:3\n[string \"lib\"]:10: in function 'run'",
+            "net.request(\"url\")",
+        );
+        assert_eq!(combined.lines().count(), 4);
+        assert_eq!(
+            combined.lines().nth(1),
+            Some("[C]: in function 'net.request(\"url\")'")
+        );
+    }
+
+    #[test]
+    fn interleave_builtin_error() {
+        let error = interleave_builtin_frames(builtin_error("fs.readFile(\"a\")", "not found"));
+        let LuaError::CallbackError { traceback, cause } = error else {
+            panic!("expected callback error");
+        };
+        assert!(traceback.contains("[C]: in function 'fs.readFile(\"a\")'"));
+        assert!(traceback.contains("[string \"main\"]:3"));
+        assert!(matches!(cause.as_ref(), LuaError::RuntimeError(m) if m == "not found"));
+    }
+
+    #[test]
+    fn interleave_preserves_context() {
+        let error = builtin_error("fs.readDir(\"b\")", "oops").context("while loading");
+        let LuaError::WithContext { context, cause } = interleave_builtin_frames(error) else {
+            panic!("expected context error");
+        };
+        assert_eq!(context, "while loading");
+        assert!(
+            matches!(cause.as_ref(), LuaError::CallbackError { traceback, .. }
+            if traceback.contains("fs.readDir"))
+        );
+    }
+
+    #[test]
+    fn source_map_rewrites_messages() {
+        let error = source_mapper().rewrite(LuaError::runtime("/main.luau:3: oops"));
+        let LuaError::RuntimeError(message) = error else {
+            panic!("expected runtime error");
+        };
+        assert_eq!(message, "tools/cli/main.luau:3: oops");
+    }
+
+    #[test]
+    fn source_map_rewrites_tracebacks() {
+        let error = source_mapper().rewrite(LuaError::CallbackError {
+            traceback: "stack traceback:\n[C]: in ?\n/lib/main.luau:10 function run\n/main.luau:3"
+                .to_string(),
+            cause: Arc::new(LuaError::runtime("/lib/main.luau:10: failed")),
+        });
+        let LuaError::CallbackError { traceback, cause } = error else {
+            panic!("expected callback error");
+        };
+        assert!(traceback.contains("\ntools/cli/lib/main.luau:10 function run"));
+        assert!(traceback.ends_with("\ntools/cli/main.luau:3"));
+        assert!(
+            matches!(cause.as_ref(), LuaError::RuntimeError(m) if m == "tools/cli/lib/main.luau:10: failed")
+        );
+    }
+
+    #[test]
+    fn source_map_ignores_unknown_paths() {
+        let mapper = source_mapper();
+        assert_eq!(
+            mapper.rewrite_text("/other.luau:3: oops"),
+            "/other.luau:3: oops"
+        );
+        assert_eq!(
+            mapper.rewrite_text("/main.luau is fine"),
+            "/main.luau is fine"
+        );
+        assert!(SourceMapper::new(HashMap::new()).is_none());
+    }
+
+    #[test]
+    fn interleave_ignores_other_errors() {
+        let error = LuaError::CallbackError {
+            traceback: "stack traceback:\n[C]: in ?".to_string(),
+            cause: Arc::new(LuaError::runtime("plain")),
+        };
+        let LuaError::CallbackError { traceback, .. } = interleave_builtin_frames(error) else {
+            panic!("expected callback error");
+        };
+        assert_eq!(traceback, "stack traceback:\n[C]: in ?");
+    }
+}
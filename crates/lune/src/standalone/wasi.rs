@@ -0,0 +1,48 @@
+/*!
+    Access to the payload embedded into WASI modules built using `lune build`.
+
+    A WASI module can not read its own file, so instead of appending the payload
+    to the end of the module, `lune build` adds it as a passive data segment, and
+    replaces the bodies of the two functions below to copy it into memory.
+
+    The functions must be exported from the module for `lune build` to find them,
+    which is done using linker arguments in `.cargo/config.toml`.
+*/
+
+use std::hint::black_box;
+
+/**
+    Returns the length of the embedded payload, in bytes.
+
+    Replaced when building - always returns zero in the base module.
+*/
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub extern "C" fn lune_payload_len() -> u32 {
+    black_box(0)
+}
+
+/**
+    Copies the embedded payload to the given pointer, which must
+    point to at least [`lune_payload_len`] bytes of writable memory.
+
+    Replaced when building - does nothing in the base module.
+*/
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub extern "C" fn lune_payload_copy(dst: *mut u8) {
+    black_box(dst);
+}
+
+/**
+    Reads the payload embedded into the current module,
+    which is empty if this is not a standalone module.
+*/
+pub fn read_payload() -> Vec<u8> {
+    let len = lune_payload_len() as usize;
+    let mut payload = vec![0; len];
+    if len > 0 {
+        lune_payload_copy(payload.as_mut_ptr());
+    }
+    payload
+}
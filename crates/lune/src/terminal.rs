@@ -0,0 +1,137 @@
+/*!
+    Setup of the console that Lune is running in.
+
+    On Windows, consoles use a legacy code page and do not process ANSI escape
+    sequences by default, which makes emoji and box-drawing characters show up
+    as mojibake, and colors show up as escape sequences. To fix this, we switch
+    the console to the UTF-8 code page and enable virtual terminal processing
+    at startup, and restore the original console state once Lune exits.
+
+    This may be disabled by setting the `LUNE_NO_CONSOLE_SETUP` environment
+    variable, or, for standalone binaries, using `lune build --no-console-setup`.
+    On all other platforms, this does nothing.
+*/
+
+use std::env;
+
+/// Environment variable that, when set to a truthy value, disables console setup
+const DISABLE_ENV_VAR: &str = "LUNE_NO_CONSOLE_SETUP";
+
+/**
+    Guard that restores the original state of the console once dropped.
+*/
+pub struct ConsoleGuard {
+    #[cfg(windows)]
+    original: Option<windows::ConsoleState>,
+}
+
+/**
+    Sets up the console for UTF-8 output and ANSI escape sequences,
+    unless disabled, either by the caller or using the environment.
+
+    The returned guard must be kept alive for as long as Lune is running.
+*/
+#[must_use]
+pub fn setup(enabled: bool) -> ConsoleGuard {
+    let enabled = enabled
+        && !env::var(DISABLE_ENV_VAR)
+            .is_ok_and(|v| !v.is_empty() && !matches!(v.as_str(), "0" | "false" | "off"));
+
+    #[cfg(windows)]
+    {
+        ConsoleGuard {
+            original: enabled.then(windows::setup),
+        }
+    }
+
+    #[cfg(not(windows))]
+    {
+        let _ = enabled;
+        ConsoleGuard {}
+    }
+}
+
+#[cfg(windows)]
+impl Drop for ConsoleGuard {
+    fn drop(&mut self) {
+        if let Some(original) = self.original.take() {
+            original.restore();
+        }
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use windows_sys::Win32::{
+        Globalization::CP_UTF8,
+        System::Console::{
+            CONSOLE_MODE, ENABLE_VIRTUAL_TERMINAL_PROCESSING, GetConsoleCP, GetConsoleMode,
+            GetConsoleOutputCP, GetStdHandle, STD_ERROR_HANDLE, STD_HANDLE, STD_OUTPUT_HANDLE,
+            SetConsoleCP, SetConsoleMode, SetConsoleOutputCP,
+        },
+    };
+
+    /**
+        The state of the console before it was set up.
+
+        Code pages are `0`, and modes are missing, when they could not be
+        read - which happens when input or output is not a console.
+    */
+    pub struct ConsoleState {
+        input_cp: u32,
+        output_cp: u32,
+        modes: Vec<(STD_HANDLE, CONSOLE_MODE)>,
+    }
+
+    impl ConsoleState {
+        pub fn restore(self) {
+            // SAFETY: These are plain FFI calls with no pointers,
+            // and failures are harmless, so they can be ignored
+            unsafe {
+                if self.input_cp != 0 {
+                    SetConsoleCP(self.input_cp);
+                }
+                if self.output_cp != 0 {
+                    SetConsoleOutputCP(self.output_cp);
+                }
+                for (std_handle, mode) in self.modes {
+                    SetConsoleMode(GetStdHandle(std_handle), mode);
+                }
+            }
+        }
+    }
+
+    pub fn setup() -> ConsoleState {
+        // SAFETY: These are plain FFI calls, and the mode pointer
+        // is valid for the duration of the call to GetConsoleMode
+        unsafe {
+            let original = ConsoleState {
+                input_cp: GetConsoleCP(),
+                output_cp: GetConsoleOutputCP(),
+                modes: [STD_OUTPUT_HANDLE, STD_ERROR_HANDLE]
+                    .into_iter()
+                    .filter_map(|std_handle| {
+                        let handle = GetStdHandle(std_handle);
+                        let mut mode: CONSOLE_MODE = 0;
+                        (GetConsoleMode(handle, &raw mut mode) != 0).then_some((std_handle, mode))
+                    })
+                    .collect(),
+            };
+
+            if original.input_cp != 0 {
+                SetConsoleCP(CP_UTF8);
+            }
+            if original.output_cp != 0 {
+                SetConsoleOutputCP(CP_UTF8);
+            }
+            for (std_handle, mode) in &original.modes {
+                SetConsoleMode(
+                    GetStdHandle(*std_handle),
+                    mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+                );
+            }
+
+            original
+        }
+    }
+}
@@ -1,5 +1,6 @@
+use std::collections::{BTreeMap, HashMap};
 use std::env::set_current_dir;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 
 use anyhow::Result;
@@ -7,8 +8,13 @@ use console::set_colors_enabled;
 use console::set_colors_enabled_stderr;
 
 use lune_utils::path::clean_path;
+use lune_utils::remote::{LOCKFILE_NAME, Lockfile, RemoteCache, RemoteRequires};
+use lune_utils::require_graph::RequireGraph;
+use lune_utils::require_trace::RequireTrace;
 
-use crate::Runtime;
+use mlua::prelude::*;
+
+use crate::{BuildInfo, CapabilityMode, NamespaceModule, Runtime};
 
 const ARGS: &[&str] = &["Foo", "Bar"];
 
@@ -49,8 +55,11 @@ macro_rules! create_tests {
 }
 
 #[cfg(any(
+    feature = "std-buffer",
     feature = "std-datetime",
+    feature = "std-events",
     feature = "std-fs",
+    feature = "std-ipc",
     feature = "std-luau",
     feature = "std-net",
     feature = "std-process",
@@ -66,12 +75,17 @@ create_tests! {
     require_async_concurrent: "require/tests/async_concurrent",
     require_async_sequential: "require/tests/async_sequential",
     require_builtins: "require/tests/builtins",
+    require_cache: "require/tests/cache",
+    require_cache_view: "require/tests/cache_view",
     require_children: "require/tests/children",
     require_init: "require/tests/init_files",
     require_invalid: "require/tests/invalid",
     require_multi_ext: "require/tests/multi_ext",
     require_nested: "require/tests/nested",
+    require_nested_luaurc: "require/tests/nested_luaurc",
+    require_packages: "require/tests/packages",
     require_parents: "require/tests/parents",
+    require_paths: "require/paths/main",
     require_siblings: "require/tests/siblings",
     require_state: "require/tests/state",
 
@@ -80,11 +94,386 @@ create_tests! {
     global_coroutine: "globals/coroutine",
     global_error: "globals/error",
     global_pcall: "globals/pcall",
+    global_print: "globals/print",
+    global_runtime: "globals/runtime",
+    global_runtime_reload: "globals/runtime_reload",
+    global_script_iter: "globals/script_iter",
+    global_script_project: "globals/script_project",
+    global_script_read: "globals/script_read",
     global_type: "globals/type",
     global_typeof: "globals/typeof",
     global_warn: "globals/warn",
 }
 
+#[cfg(feature = "std-serde")]
+create_tests! {
+    require_data_files: "require/tests/data_files",
+}
+
+#[cfg(feature = "std-roblox")]
+create_tests! {
+    require_model_files: "require/tests/model_files",
+}
+
+#[cfg(feature = "std-process")]
+create_tests! {
+    require_platform: "require/tests/platform",
+}
+
+#[test]
+fn require_capabilities() -> Result<ExitCode> {
+    async_io::block_on(async {
+        let workspace_dir_str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../");
+        let workspace_dir = clean_path(PathBuf::from(workspace_dir_str));
+
+        let mut rt = Runtime::new()?.with_capabilities(CapabilityMode::Enforce, ["net"]);
+
+        let script_path = workspace_dir.join("tests/require/tests/capabilities.luau");
+        let script_values = rt.run_file(script_path).await?;
+
+        Ok(ExitCode::from(script_values.status()))
+    })
+}
+
+#[test]
+fn require_namespaces() -> Result<ExitCode> {
+    async_io::block_on(async {
+        let workspace_dir_str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../");
+        let workspace_dir = clean_path(PathBuf::from(workspace_dir_str));
+
+        let mut rt = Runtime::new()?.with_namespace("@testns/*", |lua, name| {
+            Ok(match name {
+                "value" => Some(NamespaceModule::Value(LuaValue::String(
+                    lua.create_string("provided by the host")?,
+                ))),
+                "source" => Some(NamespaceModule::Source(b"return { answer = 42 }".to_vec())),
+                _ => None,
+            })
+        })?;
+        assert_eq!(
+            rt.namespace_manifest().namespaces,
+            vec!["@testns".to_string()]
+        );
+
+        let script_path = workspace_dir.join("tests/require/tests/namespaces.luau");
+        let script_values = rt.run_file(script_path).await?;
+
+        Ok(ExitCode::from(script_values.status()))
+    })
+}
+
+#[test]
+fn require_resolvers() -> Result<ExitCode> {
+    async_io::block_on(async {
+        let workspace_dir_str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../");
+        let workspace_dir = clean_path(PathBuf::from(workspace_dir_str));
+
+        let mut rt = Runtime::new()?.with_require_resolver("@require-tests", |lua, name| {
+            Ok(match name {
+                "config" => {
+                    let config = lua.create_table()?;
+                    config.set("name", "resolvers")?;
+                    Some(NamespaceModule::Value(LuaValue::Table(config)))
+                }
+                "broken" => return Err(LuaError::runtime("broken module")),
+                _ => None,
+            })
+        })?;
+
+        let script_path = workspace_dir.join("tests/require/tests/resolvers.luau");
+        let script_values = rt.run_file(script_path).await?;
+
+        Ok(ExitCode::from(script_values.status()))
+    })
+}
+
+#[cfg(all(feature = "std-fs", feature = "std-process", feature = "std-task"))]
+#[test]
+fn require_hot_reload() -> Result<ExitCode> {
+    async_io::block_on(async {
+        let workspace_dir_str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../");
+        let workspace_dir = clean_path(PathBuf::from(workspace_dir_str));
+        set_current_dir(&workspace_dir)?;
+
+        let mut rt = Runtime::new()?.with_hot_reload(true);
+
+        let script_path = workspace_dir.join("tests/require/tests/hot_reload.luau");
+        let script_values = rt.run_file(script_path).await?;
+
+        Ok(ExitCode::from(script_values.status()))
+    })
+}
+
+#[test]
+fn require_custom_chunk() -> Result<ExitCode> {
+    async_io::block_on(async {
+        let workspace_dir_str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../");
+        let workspace_dir = clean_path(PathBuf::from(workspace_dir_str));
+        set_current_dir(&workspace_dir)?;
+
+        // Runs the same way as code entered in the REPL
+        let mut rt = Runtime::new()?;
+
+        let source = std::fs::read(workspace_dir.join("tests/require/tests/custom_chunk.luau"))?;
+        let script_values = rt.run_custom("REPL", source).await?;
+
+        Ok(ExitCode::from(script_values.status()))
+    })
+}
+
+#[test]
+fn require_given_aliases() -> Result<ExitCode> {
+    async_io::block_on(async {
+        let workspace_dir_str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../");
+        let workspace_dir = clean_path(PathBuf::from(workspace_dir_str));
+        let modules_dir = workspace_dir.join("tests/require/tests/modules");
+
+        let mut rt = Runtime::new()?.with_aliases([
+            ("vendored", modules_dir.clone()),
+            ("@require-tests", modules_dir.join("modules")),
+        ])?;
+
+        let script_path = workspace_dir.join("tests/require/tests/given_aliases.luau");
+        let script_values = rt.run_file(script_path).await?;
+
+        Ok(ExitCode::from(script_values.status()))
+    })
+}
+
+#[cfg(feature = "std-task")]
+#[test]
+fn require_allow_list() -> Result<ExitCode> {
+    async_io::block_on(async {
+        let workspace_dir_str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../");
+        let workspace_dir = clean_path(PathBuf::from(workspace_dir_str));
+        let modules_dir = workspace_dir.join("tests/require/tests/modules");
+
+        let mut rt = Runtime::new()?
+            .with_require_allow_list([modules_dir.to_string_lossy().as_ref(), "@lune/task"]);
+
+        let script_path = workspace_dir.join("tests/require/tests/allow_list.luau");
+        let script_values = rt.run_file(script_path).await?;
+
+        Ok(ExitCode::from(script_values.status()))
+    })
+}
+
+#[test]
+fn require_given_aliases_reserved() -> Result<()> {
+    assert!(Runtime::new()?.with_aliases([("lune", "./types")]).is_err());
+    assert!(
+        Runtime::new()?
+            .with_aliases([("@pkg/nested", "./pkg")])
+            .is_err()
+    );
+    Ok(())
+}
+
+#[test]
+fn global_bundle() -> Result<ExitCode> {
+    async_io::block_on(async {
+        let workspace_dir_str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../");
+        let workspace_dir = clean_path(PathBuf::from(workspace_dir_str));
+
+        let mut rt = Runtime::new()?
+            .with_bundled_files(HashMap::from([
+                ("/data/config.json".to_string(), b"{}".to_vec()),
+                (
+                    "/templates/page.html".to_string(),
+                    b"<h1>Hello</h1>".to_vec(),
+                ),
+            ]))
+            .with_bundled_links(HashMap::from([(
+                "/templates/index.html".to_string(),
+                "/templates/page.html".to_string(),
+            )]));
+
+        let script_path = workspace_dir.join("tests/globals/bundle.luau");
+        let script_values = rt.run_file(script_path).await?;
+
+        Ok(ExitCode::from(script_values.status()))
+    })
+}
+
+#[test]
+fn require_linked() -> Result<ExitCode> {
+    async_io::block_on(async {
+        let workspace_dir_str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../");
+        let workspace_dir = clean_path(PathBuf::from(workspace_dir_str));
+        let source = std::fs::read(workspace_dir.join("tests/require/tests/linked.luau"))?;
+
+        let mut rt = Runtime::new()?
+            .with_bundled_files(HashMap::from([
+                ("/lib.luau".to_string(), b"return { name = 'lib' }".to_vec()),
+                (
+                    "/nested.luau".to_string(),
+                    b"return { lib = require('./lib') }".to_vec(),
+                ),
+            ]))
+            .with_require_graph(RequireGraph {
+                modules: vec![
+                    "/lib.luau".to_string(),
+                    "/nested.luau".to_string(),
+                    "/main.luau".to_string(),
+                ],
+                requires: vec![
+                    BTreeMap::new(),
+                    BTreeMap::new(),
+                    BTreeMap::from([
+                        ("./renamed".to_string(), 0),
+                        ("@pkg/lib".to_string(), 0),
+                    ]),
+                ],
+            });
+
+        let script_values = rt.run_source("/main.luau", source).await?;
+
+        Ok(ExitCode::from(script_values.status()))
+    })
+}
+
+#[test]
+fn require_remote() -> Result<ExitCode> {
+    async_io::block_on(async {
+        let workspace_dir_str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../");
+        let workspace_dir = clean_path(PathBuf::from(workspace_dir_str));
+        let script_path = workspace_dir.join("tests/require/tests/remote.luau");
+
+        // Remote modules are only downloaded if missing, so this never touches the network
+        let dir = std::env::temp_dir().join(format!("lune-test-remote-{}", std::process::id()));
+        let options = RemoteRequires {
+            lockfile: dir.join(LOCKFILE_NAME),
+            cache_dir: dir.join("cache"),
+        };
+        let cache = RemoteCache::new(&options.cache_dir);
+        let mut lockfile = Lockfile::default();
+        for (url, source) in [
+            (
+                "https://example.com/lib/mod.luau",
+                "return { util = require('./util') }",
+            ),
+            ("https://example.com/lib/util.luau", "return { value = 42 }"),
+        ] {
+            lockfile.insert(url, cache.write(source.as_bytes())?);
+        }
+        lockfile.write(&options.lockfile)?;
+
+        let mut rt = Runtime::new()?.with_remote_requires(options);
+        let script_values = rt.run_file(script_path).await?;
+
+        std::fs::remove_dir_all(dir).ok();
+        Ok(ExitCode::from(script_values.status()))
+    })
+}
+
+#[test]
+fn require_trace() -> Result<ExitCode> {
+    async_io::block_on(async {
+        let workspace_dir_str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../");
+        let workspace_dir = clean_path(PathBuf::from(workspace_dir_str));
+
+        let trace = RequireTrace::new();
+        let mut rt = Runtime::new()?.with_require_trace(trace.clone());
+
+        let script_path = workspace_dir.join("tests/require/tests/nested.luau");
+        let script_values = rt.run_file(script_path).await?;
+
+        let entries = trace.entries();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].module, "./modules/nested");
+        let parent = entries[0].parent.as_deref().expect("caller is known");
+        assert!(Path::new(parent).ends_with("tests/nested.luau"));
+        assert_eq!(entries[1].module, "./modules/module");
+        assert_eq!(entries[1].parent, entries[0].path);
+        assert!(!entries[0].cached && !entries[1].cached);
+        assert!(entries[2].cached);
+        assert!(entries[0].duration >= entries[1].duration);
+
+        Ok(ExitCode::from(script_values.status()))
+    })
+}
+
+#[test]
+fn global_build() -> Result<ExitCode> {
+    async_io::block_on(async {
+        let workspace_dir_str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../");
+        let workspace_dir = clean_path(PathBuf::from(workspace_dir_str));
+
+        let mut rt = Runtime::new()?.with_build_info(BuildInfo {
+            timestamp: 1_700_000_000,
+            lune_version: "0.10.0".to_string(),
+            target: "linux-x86_64".to_string(),
+            commit: Some("0123456789abcdef".to_string()),
+            fields: BTreeMap::from([("channel".to_string(), "beta".to_string())]),
+        });
+
+        let script_path = workspace_dir.join("tests/globals/build.luau");
+        let script_values = rt.run_file(script_path).await?;
+
+        Ok(ExitCode::from(script_values.status()))
+    })
+}
+
+#[cfg(feature = "std-task")]
+#[test]
+fn runtime_step() -> Result<ExitCode> {
+    let mut rt = Runtime::new()?;
+    rt.start_custom(
+        "step",
+        "local ticks = 0\n\
+        task.spawn(function()\n\
+            while true do ticks += 1 task.wait() end\n\
+        end)\n\
+        task.wait(0.05)\n\
+        assert(ticks > 1, \"spawned thread should run on every tick\")\n\
+        return ticks",
+    )?;
+
+    // Stepping should never block, and the script must
+    // only complete once its timer has elapsed
+    let mut steps = 0;
+    let script_values = loop {
+        assert!(rt.is_running());
+        if let Some(values) = rt.step()? {
+            break values;
+        }
+        steps += 1;
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    };
+    assert!(!rt.is_running());
+    assert!(steps > 1);
+    assert!(rt.step().is_err());
+
+    Ok(ExitCode::from(script_values.status()))
+}
+
+#[cfg(all(feature = "std-events", feature = "std-task"))]
+#[test]
+fn events_host() -> Result<ExitCode> {
+    async_io::block_on(async {
+        let workspace_dir_str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../");
+        let workspace_dir = clean_path(PathBuf::from(workspace_dir_str));
+        set_current_dir(&workspace_dir)?;
+
+        let mut rt = Runtime::new()?;
+
+        // Respond to every ping emitted by the script with a
+        // pong, from a thread that is not running the script
+        let handle = rt.handle();
+        let pings = handle.subscribe("ping");
+        let host = std::thread::spawn(move || {
+            let ping = pings.recv_blocking().expect("script should emit a ping");
+            handle.post("pong", ping);
+        });
+
+        let script_path = workspace_dir.join("tests").join("events/host.luau");
+        let script_values = rt.run_file(script_path).await?;
+        host.join().expect("host thread should not panic");
+
+        Ok(ExitCode::from(script_values.status()))
+    })
+}
+
 // Coverage tests run serially to prevent LUNE_COVERAGE env var contamination.
 // JIT must be disabled because coverage instrumentation is incompatible with
 // native code generation. Coverage is enabled by default; the disabled test
@@ -119,6 +508,11 @@ fn global_debug_getcoverage_script() -> Result<ExitCode> {
     run_test_with_jit("globals/debug_getcoverage_script", false)
 }
 
+#[cfg(feature = "std-buffer")]
+create_tests! {
+    buffer_bulk: "buffer/bulk",
+}
+
 #[cfg(feature = "std-datetime")]
 create_tests! {
     datetime_format_local_time: "datetime/formatLocalTime",
@@ -135,21 +529,42 @@ create_tests! {
     datetime_to_universal_time: "datetime/toUniversalTime",
 }
 
+#[cfg(all(feature = "std-events", feature = "std-task"))]
+create_tests! {
+    events_listen: "events/listen",
+    events_signal: "events/signal",
+}
+
+#[cfg(feature = "std-fs")]
+create_tests! {
+    require_alias_cache: "require/tests/alias_cache",
+    require_diagnostics: "require/tests/diagnostics",
+    require_missing: "require/tests/missing",
+}
+
 #[cfg(feature = "std-fs")]
 create_tests! {
     fs_files: "fs/files",
     fs_copy: "fs/copy",
     fs_dirs: "fs/dirs",
     fs_metadata: "fs/metadata",
+    fs_mmap: "fs/mmap",
     fs_move: "fs/move",
 }
 
+#[cfg(feature = "std-ipc")]
+create_tests! {
+    ipc_connect: "ipc/connect",
+    ipc_serve: "ipc/serve",
+}
+
 #[cfg(feature = "std-luau")]
 create_tests! {
     luau_compile: "luau/compile",
     luau_load: "luau/load",
     luau_options: "luau/options",
     luau_safeenv: "luau/safeenv",
+    luau_spawn: "luau/spawn",
 }
 
 #[cfg(feature = "std-net")]
@@ -182,6 +597,7 @@ create_tests! {
 create_tests! {
     process_args: "process/args",
     process_cwd: "process/cwd",
+    process_daemonize: "process/daemonize",
     process_env: "process/env",
     process_exit: "process/exit",
     process_exec_async: "process/exec/async",
@@ -196,6 +612,12 @@ create_tests! {
     process_spawn_stream: "process/create/stream",
 }
 
+#[cfg(all(feature = "std-net", feature = "std-process"))]
+create_tests! {
+    process_shutdown: "process/shutdown",
+    process_single_instance: "process/single_instance",
+}
+
 #[cfg(feature = "std-regex")]
 create_tests! {
     regex_general: "regex/general",
@@ -265,7 +687,9 @@ create_tests! {
     roblox_instance_methods_is_a: "roblox/instance/methods/IsA",
     roblox_instance_methods_is_ancestor_of: "roblox/instance/methods/IsAncestorOf",
     roblox_instance_methods_is_descendant_of: "roblox/instance/methods/IsDescendantOf",
+    roblox_instance_methods_query_descendants: "roblox/instance/methods/QueryDescendants",
 
+    roblox_misc_geometry: "roblox/misc/geometry",
     roblox_misc_typeof: "roblox/misc/typeof",
 
     roblox_reflection_class: "roblox/reflection/class",
@@ -274,10 +698,18 @@ create_tests! {
     roblox_reflection_property: "roblox/reflection/property",
 }
 
+#[cfg(all(feature = "std-roblox", feature = "std-task"))]
+create_tests! {
+    roblox_misc_transact: "roblox/misc/transact",
+}
+
 #[cfg(feature = "std-serde")]
 create_tests! {
     serde_compression_files: "serde/compression/files",
     serde_compression_roundtrip: "serde/compression/roundtrip",
+    serde_decode_errors: "serde/decode_errors",
+    serde_decode_options: "serde/decode_options",
+    serde_json_stream: "serde/json_stream",
     serde_json_decode: "serde/json/decode",
     serde_json_encode: "serde/json/encode",
     serde_jsonc_decode: "serde/jsonc/decode",
@@ -299,9 +731,11 @@ create_tests! {
 
 #[cfg(feature = "std-task")]
 create_tests! {
+    task_all: "task/all",
     task_cancel: "task/cancel",
     task_defer: "task/defer",
     task_delay: "task/delay",
+    task_race: "task/race",
     task_spawn: "task/spawn",
     task_wait: "task/wait",
 }
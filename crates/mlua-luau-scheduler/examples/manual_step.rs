@@ -0,0 +1,72 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::missing_panics_doc)]
+#![allow(clippy::cargo_common_metadata)]
+
+use std::{
+    cell::Cell,
+    rc::Rc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use async_io::Timer;
+use futures_lite::future::yield_now;
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, Scheduler};
+
+const MAIN_SCRIPT: &str = include_str!("./lua/manual_step.luau");
+
+pub fn main() -> LuaResult<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_target(false)
+        .without_time()
+        .init();
+
+    // Set up persistent Lua environment, with the current frame
+    // of the host application being readable from Lua
+    let lua = Lua::new();
+    let frame = Rc::new(Cell::new(0_u32));
+    let frame_inner = Rc::clone(&frame);
+    lua.globals().set(
+        "getFrame",
+        lua.create_function(move |_, ()| Ok(frame_inner.get()))?,
+    )?;
+    lua.globals().set(
+        "sleep",
+        lua.create_async_function(|_, duration: f64| async move {
+            yield_now().await;
+            let before = Instant::now();
+            let after = Timer::after(Duration::from_secs_f64(duration)).await;
+            Ok((after - before).as_secs_f64())
+        })?,
+    )?;
+
+    // Load the main script into a scheduler, and start it without running it
+    let sched = Scheduler::new(lua.clone());
+    let main = lua.load(MAIN_SCRIPT);
+    let id = sched.push_thread_front(main, ())?;
+    lua.track_thread(id);
+    sched.start();
+
+    // Drive the scheduler from our own loop, one frame at a time
+    while sched.step().is_running() {
+        frame.set(frame.get() + 1);
+        thread::sleep(Duration::from_millis(5));
+    }
+
+    // Verify that the script completed, across many frames
+    let result = sched
+        .get_thread_result(id)
+        .expect("thread should have completed")?;
+    assert_eq!(u32::from_lua_multi(result, &lua)?, 4);
+    assert!(frame.get() > 3);
+
+    Ok(())
+}
+
+#[test]
+fn test_manual_step() -> LuaResult<()> {
+    main()
+}
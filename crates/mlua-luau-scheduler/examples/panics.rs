@@ -0,0 +1,94 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::missing_panics_doc)]
+#![allow(clippy::cargo_common_metadata)]
+
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use async_io::{Timer, block_on};
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{Functions, Scheduler, ThreadPanic};
+
+const MAIN_SCRIPT: &str = include_str!("./lua/panics.luau");
+
+fn panic_with(message: &str) -> LuaResult<()> {
+    panic!("{message}")
+}
+
+pub fn main() -> LuaResult<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_target(false)
+        .without_time()
+        .init();
+
+    // Set up persistent Lua environment, with functions that panic
+    let lua = Lua::new();
+    lua.globals().set(
+        "panicNow",
+        lua.create_function(|_, message: String| panic_with(&message))?,
+    )?;
+    lua.globals().set(
+        "panicLater",
+        lua.create_async_function(|_, message: String| async move {
+            Timer::after(Duration::from_millis(10)).await;
+            panic_with(&message)
+        })?,
+    )?;
+    lua.globals().set(
+        "sleep",
+        lua.create_async_function(|_, duration: f64| async move {
+            Timer::after(Duration::from_secs_f64(duration)).await;
+            Ok(())
+        })?,
+    )?;
+
+    // Collect all of the errors that threads throw
+    let sched = Scheduler::new(lua.clone());
+    let errors = Rc::new(RefCell::new(Vec::new()));
+    let errors_inner = Rc::clone(&errors);
+    sched.set_error_callback(move |e| {
+        let LuaError::ExternalError(inner) = e else {
+            panic!("error should be a panic, got {e}");
+        };
+        let panic = inner.downcast_ref::<ThreadPanic>().unwrap();
+        errors_inner.borrow_mut().push(panic.message().to_string());
+    });
+
+    let fns = Functions::new(lua.clone())?;
+    lua.globals().set("spawn", fns.spawn)?;
+
+    // Load the main script and a thread that panics instantly into the scheduler
+    let main = sched.push_thread_front(lua.load(MAIN_SCRIPT), ())?;
+    let panicking = sched.push_thread_front(
+        lua.globals().get::<LuaFunction>("panicNow")?,
+        "panic in main thread",
+    )?;
+
+    // Run until completion
+    block_on(sched.run());
+
+    // The main thread should have completed, and all panics should have become errors
+    let main_result = sched.get_thread_result(main).unwrap()?;
+    let main_result = String::from_lua_multi(main_result, &lua)?;
+    assert_eq!(main_result, "main thread completed");
+    assert!(sched.get_thread_result(panicking).unwrap().is_err());
+
+    let mut errors = errors.borrow().clone();
+    errors.sort();
+    assert_eq!(
+        errors,
+        [
+            "panic in async function",
+            "panic in main thread",
+            "panic in spawned thread",
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_panics() -> LuaResult<()> {
+    main()
+}
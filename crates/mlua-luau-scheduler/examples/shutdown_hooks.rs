@@ -0,0 +1,71 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::missing_panics_doc)]
+#![allow(clippy::cargo_common_metadata)]
+
+use std::{cell::Cell, rc::Rc, time::Duration};
+
+use async_io::{Timer, block_on};
+use futures_lite::future::yield_now;
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{Functions, LuaSchedulerExt, Scheduler, ShutdownHook};
+
+const MAIN_SCRIPT: &str = include_str!("./lua/shutdown_hooks.luau");
+
+pub fn main() -> LuaResult<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_target(false)
+        .without_time()
+        .init();
+
+    // Set up persistent Lua environment
+    let lua = Lua::new();
+    let sched = Scheduler::new(lua.clone());
+    let fns = Functions::new(lua.clone())?;
+
+    lua.globals().set("spawn", fns.spawn)?;
+    lua.globals().set("exit", fns.exit)?;
+    lua.globals().set(
+        "sleep",
+        lua.create_async_function(|_, duration: f64| async move {
+            yield_now().await;
+            Timer::after(Duration::from_secs_f64(duration)).await;
+            Ok(())
+        })?,
+    )?;
+    lua.globals().set(
+        "onShutdown",
+        lua.create_function(|lua, (priority, hook): (i32, LuaFunction)| {
+            lua.on_shutdown(priority, ShutdownHook::from(hook));
+            Ok(())
+        })?,
+    )?;
+
+    // Register a native hook that runs after all of the Lua hooks
+    let native_ran = Rc::new(Cell::new(false));
+    sched.on_shutdown(i32::MIN, {
+        let native_ran = Rc::clone(&native_ran);
+        ShutdownHook::native(move |_| native_ran.set(true))
+    });
+
+    // Load the main script into the scheduler
+    let main = lua.load(MAIN_SCRIPT);
+    sched.push_thread_front(main, ())?;
+
+    // Run until completion
+    block_on(sched.run());
+
+    // Verify that all hooks ran, in the correct order
+    let result = lua.globals().get::<String>("result")?;
+    assert_eq!(result, "high,middle,low");
+    assert!(native_ran.get());
+    assert_eq!(sched.get_exit_code(), Some(0));
+
+    Ok(())
+}
+
+#[test]
+fn test_shutdown_hooks() -> LuaResult<()> {
+    main()
+}
@@ -2,7 +2,7 @@ use std::{cell::RefCell, rc::Rc};
 
 use mlua::prelude::*;
 
-type ErrorCallback = Box<dyn Fn(LuaError) + Send + 'static>;
+type ErrorCallback = Box<dyn Fn(LuaError) + 'static>;
 
 #[derive(Clone)]
 pub(crate) struct ThreadErrorCallback {
@@ -16,7 +16,7 @@ impl ThreadErrorCallback {
         }
     }
 
-    pub fn replace(&self, callback: impl Fn(LuaError) + Send + 'static) {
+    pub fn replace(&self, callback: impl Fn(LuaError) + 'static) {
         self.inner.borrow_mut().replace(Box::new(callback));
     }
 
@@ -1,31 +1,52 @@
 use std::{cell::Cell, rc::Rc};
 
-use crate::events::OnceEvent;
+use crate::events::{MultiEvent, MultiListener, OnceEvent};
 
 #[derive(Debug, Clone)]
 pub(crate) struct Exit {
     code: Rc<Cell<Option<u8>>>,
+    count: Rc<Cell<usize>>,
     event: OnceEvent,
+    event_next: MultiEvent,
 }
 
 impl Exit {
     pub fn new() -> Self {
         Self {
             code: Rc::new(Cell::new(None)),
+            count: Rc::new(Cell::new(0)),
             event: OnceEvent::new(),
+            event_next: MultiEvent::new(),
         }
     }
 
     pub fn set(&self, code: u8) {
         self.code.set(Some(code));
+        self.count.set(self.count.get() + 1);
         self.event.notify();
+        self.event_next.notify();
     }
 
     pub fn get(&self) -> Option<u8> {
         self.code.get()
     }
 
+    /**
+        Returns the number of times that an exit code has been set.
+    */
+    pub fn count(&self) -> usize {
+        self.count.get()
+    }
+
     pub async fn listen(&self) {
         self.event.listen().await;
     }
+
+    /**
+        Creates a listener that resolves the next time an exit code
+        is set, even if an exit code has already been set before.
+    */
+    pub fn listen_next(&self) -> MultiListener {
+        self.event_next.listen()
+    }
 }
@@ -4,6 +4,7 @@ use mlua::prelude::*;
 
 use crate::{
     error_callback::ThreadErrorCallback,
+    panic::catch_panic,
     queue::{DeferredThreadQueue, SpawnedThreadQueue},
     threads::{ThreadId, ThreadMap},
     traits::LuaSchedulerExt,
@@ -33,6 +34,14 @@ return function(...)
 end
 ";
 
+/**
+    Resumes the given thread, converting any panic in a Rust function that
+    it calls into an error for the thread, instead of for the resuming thread.
+*/
+fn resume_catching_panics(thread: &LuaThread, args: &LuaMultiValue) -> LuaResult<LuaMultiValue> {
+    catch_panic(|| thread.resume::<LuaMultiValue>(args.clone())).and_then(|res| res)
+}
+
 /**
     A collection of lua functions that may be called to interact with a [`Scheduler`].
 
@@ -66,6 +75,9 @@ pub struct Functions {
     pub defer: LuaFunction,
     /**
         Cancels a function / thread, removing it from the queue.
+
+        Also drops any cancellable async operations that the thread is waiting on,
+        see [`LuaSchedulerExt::cancel_thread`] for more information.
     */
     pub cancel: LuaFunction,
     /**
@@ -111,7 +123,7 @@ impl Functions {
         let resume =
             lua.create_function(move |lua, (thread, args): (LuaThread, LuaMultiValue)| {
                 let _span = tracing::trace_span!("Scheduler::fn_resume").entered();
-                match thread.resume::<LuaMultiValue>(args.clone()) {
+                match resume_catching_panics(&thread, &args) {
                     Ok(v) => {
                         if v.front().is_some_and(is_poll_pending) {
                             // Pending, defer to scheduler and return nil
@@ -165,7 +177,7 @@ impl Functions {
                 if thread.status() == LuaThreadStatus::Resumable {
                     // NOTE: We need to resume the thread once instantly for correct behavior,
                     // and only if we get the pending value back we can spawn to async executor
-                    match thread.resume::<LuaMultiValue>(args.clone()) {
+                    match resume_catching_panics(&thread, &args) {
                         Ok(v) => {
                             if v.front().is_some_and(is_poll_pending) {
                                 spawn_queue.push_item(lua, &thread, args)?;
@@ -204,18 +216,9 @@ impl Functions {
             },
         )?;
 
-        let close = lua
-            .globals()
-            .get::<LuaTable>("coroutine")?
-            .get::<LuaFunction>("close")?;
-        let close_key = lua.create_registry_value(close)?;
-        let cancel = lua.create_function(move |lua, thread: LuaThread| {
+        let cancel = lua.create_function(|lua, thread: LuaThread| {
             let _span = tracing::trace_span!("Scheduler::fn_cancel").entered();
-            let close: LuaFunction = lua.registry_value(&close_key)?;
-            match close.call(thread) {
-                Err(LuaError::CoroutineUnresumable) | Ok(()) => Ok(()),
-                Err(e) => Err(e),
-            }
+            lua.cancel_thread(&thread)
         })?;
 
         let exit_env = lua.create_table_from(vec![
@@ -4,15 +4,19 @@ mod error_callback;
 mod events;
 mod exit;
 mod functions;
+mod panic;
 mod queue;
 mod scheduler;
+mod shutdown;
 mod status;
 mod threads;
 mod traits;
 mod util;
 
 pub use functions::Functions;
+pub use panic::ThreadPanic;
 pub use scheduler::Scheduler;
+pub use shutdown::ShutdownHook;
 pub use status::Status;
 pub use threads::ThreadId;
 pub use traits::{IntoLuaThread, LuaSchedulerExt, LuaSpawnExt};
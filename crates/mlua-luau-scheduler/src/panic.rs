@@ -0,0 +1,143 @@
+use std::{
+    any::Any,
+    backtrace::{Backtrace, BacktraceStatus},
+    cell::{Cell, RefCell},
+    error::Error,
+    fmt,
+    panic::{self, AssertUnwindSafe, PanicHookInfo},
+    pin::pin,
+    sync::{Arc, Once},
+    task::Poll,
+};
+
+use mlua::prelude::*;
+
+static INSTALL_HOOK: Once = Once::new();
+
+thread_local! {
+    /// Number of scopes on this thread that are currently catching panics
+    static CATCHING: Cell<usize> = const { Cell::new(0) };
+    /// Location and backtrace of the last panic caught on this thread
+    static LAST_PANIC: RefCell<Option<(Option<String>, Backtrace)>> = const { RefCell::new(None) };
+}
+
+/**
+    A Rust panic that happened while running a Lua thread, such as inside of
+    a builtin function, which was converted into an error for that thread.
+
+    Contains the panic message, and the location and backtrace of the panic -
+    the backtrace is only captured if enabled using `RUST_BACKTRACE`, same
+    as for panics that are not caught.
+*/
+#[derive(Debug, Clone)]
+pub struct ThreadPanic {
+    message: String,
+    location: Option<String>,
+    backtrace: Arc<Backtrace>,
+}
+
+impl ThreadPanic {
+    fn from_payload(payload: &(dyn Any + Send)) -> Self {
+        let message = if let Some(s) = payload.downcast_ref::<&str>() {
+            (*s).to_string()
+        } else if let Some(s) = payload.downcast_ref::<String>() {
+            s.clone()
+        } else {
+            String::from("Box<dyn Any>")
+        };
+        let (location, backtrace) = LAST_PANIC
+            .with_borrow_mut(Option::take)
+            .unwrap_or_else(|| (None, Backtrace::disabled()));
+        Self {
+            message,
+            location,
+            backtrace: Arc::new(backtrace),
+        }
+    }
+
+    /**
+        Returns the message that the panic was created with.
+    */
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /**
+        Returns the source location of the panic, if known.
+    */
+    #[must_use]
+    pub fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+
+    /**
+        Returns the backtrace of the panic.
+    */
+    #[must_use]
+    pub fn backtrace(&self) -> &Backtrace {
+        &self.backtrace
+    }
+}
+
+impl fmt::Display for ThreadPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "panicked: {}", self.message)?;
+        if let Some(location) = &self.location {
+            write!(f, "\n    at {location}")?;
+        }
+        if self.backtrace.status() == BacktraceStatus::Captured {
+            write!(f, "\nbacktrace:\n{}", self.backtrace)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ThreadPanic {}
+
+/**
+    Installs a panic hook that records the location and backtrace of panics
+    which will be caught by the scheduler, without printing them.
+
+    Panics outside of the scheduler are passed to the previous hook, as usual.
+*/
+pub(crate) fn install_hook() {
+    INSTALL_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info: &PanicHookInfo<'_>| {
+            if CATCHING.get() > 0 {
+                let location = info.location().map(ToString::to_string);
+                let backtrace = Backtrace::capture();
+                LAST_PANIC.set(Some((location, backtrace)));
+            } else {
+                previous(info);
+            }
+        }));
+    });
+}
+
+/**
+    Runs the given function, converting any panic into a [`ThreadPanic`] error.
+
+    Any Lua state touched by the function must remain valid if it panics, which is the
+    case for resuming Lua threads, since mlua only resumes panics after Lua has unwound.
+*/
+pub(crate) fn catch_panic<R>(f: impl FnOnce() -> R) -> LuaResult<R> {
+    CATCHING.set(CATCHING.get() + 1);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.set(CATCHING.get() - 1);
+    result.map_err(|payload| LuaError::external(ThreadPanic::from_payload(&*payload)))
+}
+
+/**
+    Runs the given future, converting any panic while polling it into a [`ThreadPanic`] error.
+*/
+pub(crate) async fn catch_panic_async<F: Future>(fut: F) -> LuaResult<F::Output> {
+    let mut fut = pin!(fut);
+    std::future::poll_fn(|cx| match catch_panic(|| fut.as_mut().poll(cx)) {
+        Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+        Ok(Poll::Pending) => Poll::Pending,
+        Err(e) => Poll::Ready(Err(e)),
+    })
+    .await
+}
@@ -1,7 +1,7 @@
 #![allow(clippy::module_name_repetitions)]
 
 use std::{
-    cell::Cell,
+    cell::{Cell, RefCell},
     rc::Rc,
     sync::{Arc, Weak as WeakArc},
     thread::panicking,
@@ -16,10 +16,12 @@ use tracing::{Instrument, debug, instrument, trace, trace_span};
 use crate::{
     error_callback::ThreadErrorCallback,
     exit::Exit,
+    panic::{catch_panic_async, install_hook},
     queue::{DeferredThreadQueue, FuturesQueue, SpawnedThreadQueue},
+    shutdown::{Shutdown, ShutdownHook},
     status::Status,
-    threads::{ThreadId, ThreadMap},
-    traits::IntoLuaThread,
+    threads::{ThreadCancellations, ThreadId, ThreadMap},
+    traits::{IntoLuaThread, LuaSchedulerExt},
     util::run_until_yield,
 };
 
@@ -38,6 +40,22 @@ const ERR_SET_CALLBACK_WHEN_RUNNING: &str = "\
 Cannot set error callback when scheduler is running!\
 ";
 
+const ERR_ALREADY_RUNNING: &str = "\
+Cannot start scheduler when it is already running!\
+";
+
+/**
+    Executors and state of a scheduler that was started using
+    [`Scheduler::start`], which is driven using [`Scheduler::step`].
+*/
+struct Stepper {
+    local_exec: LocalExecutor<'static>,
+    main_exec: Arc<Executor<'static>>,
+    fut_queue: FuturesQueue<'static>,
+    /// Exit count from when shutdown started, if it has started
+    shutdown_from: Cell<Option<usize>>,
+}
+
 /**
     A scheduler for running Lua threads and async tasks.
 */
@@ -48,8 +66,11 @@ pub struct Scheduler {
     queue_defer: DeferredThreadQueue,
     error_callback: ThreadErrorCallback,
     thread_map: ThreadMap,
+    cancellations: ThreadCancellations,
     status: Rc<Cell<Status>>,
     exit: Exit,
+    shutdown: Shutdown,
+    stepper: Rc<RefCell<Option<Rc<Stepper>>>>,
 }
 
 impl Scheduler {
@@ -58,6 +79,10 @@ impl Scheduler {
 
         This scheduler will have a default error callback that prints errors to stderr.
 
+        Rust panics that happen while running Lua threads, such as inside of a function
+        created using [`Lua::create_function`], are converted into errors for the threads
+        that they happened in. A panic hook is installed to capture their backtraces.
+
         # Panics
 
         Panics if the given Lua state already has a scheduler attached to it.
@@ -68,7 +93,11 @@ impl Scheduler {
         let queue_defer = DeferredThreadQueue::new();
         let error_callback = ThreadErrorCallback::default();
         let result_map = ThreadMap::new();
+        let cancellations = ThreadCancellations::new();
         let exit = Exit::new();
+        let shutdown = Shutdown::new();
+
+        install_hook();
 
         assert!(
             lua.app_data_ref::<SpawnedThreadQueue>().is_none(),
@@ -86,16 +115,26 @@ impl Scheduler {
             lua.app_data_ref::<ThreadMap>().is_none(),
             "{ERR_METADATA_ALREADY_ATTACHED}"
         );
+        assert!(
+            lua.app_data_ref::<ThreadCancellations>().is_none(),
+            "{ERR_METADATA_ALREADY_ATTACHED}"
+        );
         assert!(
             lua.app_data_ref::<Exit>().is_none(),
             "{ERR_METADATA_ALREADY_ATTACHED}"
         );
+        assert!(
+            lua.app_data_ref::<Shutdown>().is_none(),
+            "{ERR_METADATA_ALREADY_ATTACHED}"
+        );
 
         lua.set_app_data(queue_spawn.clone());
         lua.set_app_data(queue_defer.clone());
         lua.set_app_data(error_callback.clone());
         lua.set_app_data(result_map.clone());
+        lua.set_app_data(cancellations.clone());
         lua.set_app_data(exit.clone());
+        lua.set_app_data(shutdown.clone());
 
         let status = Rc::new(Cell::new(Status::NotStarted));
 
@@ -105,8 +144,11 @@ impl Scheduler {
             queue_defer,
             error_callback,
             thread_map: result_map,
+            cancellations,
             status,
             exit,
+            shutdown,
+            stepper: Rc::new(RefCell::new(None)),
         }
     }
 
@@ -129,7 +171,9 @@ impl Scheduler {
     /**
         Sets the error callback for this scheduler.
 
-        This callback will be called whenever a Lua thread errors.
+        This callback will be called whenever a Lua thread errors, always
+        on the same thread that the scheduler is running on, so it may
+        capture values that are not `Send`, such as a [`mlua::WeakLua`].
 
         Overwrites any previous error callback.
 
@@ -137,7 +181,7 @@ impl Scheduler {
 
         Panics if the scheduler is currently running.
     */
-    pub fn set_error_callback(&self, callback: impl Fn(LuaError) + Send + 'static) {
+    pub fn set_error_callback(&self, callback: impl Fn(LuaError) + 'static) {
         assert!(
             !self.status().is_running(),
             "{ERR_SET_CALLBACK_WHEN_RUNNING}"
@@ -173,12 +217,22 @@ impl Scheduler {
     /**
         Sets the exit code for this scheduler.
 
-        This will cause [`Scheduler::run`] to exit immediately.
+        This will cause [`Scheduler::run`] to exit immediately, once any shutdown hooks
+        have finished running - see [`LuaSchedulerExt::on_shutdown`] for more information.
     */
     pub fn set_exit_code(&self, code: u8) {
         self.exit.set(code);
     }
 
+    /**
+        Registers a hook to run when this scheduler shuts down.
+
+        See [`LuaSchedulerExt::on_shutdown`] for more information.
+    */
+    pub fn on_shutdown(&self, priority: i32, hook: ShutdownHook) {
+        self.shutdown.register(priority, hook);
+    }
+
     /**
         Spawns a chunk / function / thread onto the scheduler queue.
 
@@ -259,6 +313,199 @@ impl Scheduler {
         self.thread_map.listen(id).await;
     }
 
+    /**
+        Creates the future that runs the given Lua thread until it yields,
+        storing its result if tracked, and reporting any error it throws.
+
+        Returns `None` if the thread was cancelled, or can not be resumed.
+    */
+    fn thread_future(
+        &self,
+        thread: LuaThread,
+        args: LuaMultiValue,
+    ) -> Option<impl Future<Output = ()> + 'static> {
+        // NOTE: Thread may have been cancelled from Lua
+        // before we got here, so we need to check it again
+        let id = ThreadId::from(&thread);
+        if self.cancellations.is_cancelled(id) {
+            // Cancelled while running, it is safe to close it now
+            let _ = self.lua.cancel_thread(&thread);
+            return None;
+        }
+        if thread.status() != LuaThreadStatus::Resumable {
+            return None;
+        }
+
+        // Check if we should be tracking this thread
+        let result_map = self
+            .thread_map
+            .is_tracked(id)
+            .then(|| self.thread_map.clone());
+        let cancellations = self.cancellations.clone();
+        let error_callback = self.error_callback.clone();
+
+        // Create our future which will run the thread and store its final result
+        Some(async move {
+            // Run until yield and check if we got a final result - panics in Rust
+            // functions called by the thread become errors for this thread only,
+            // instead of unwinding through the scheduler and taking down the process
+            let res = match catch_panic_async(run_until_yield(thread.clone(), args)).await {
+                Ok(res) => res,
+                Err(e) => Some(Err(e)),
+            };
+            if let Some(res) = res {
+                if let Err(e) = res.as_ref()
+                    && !cancellations.is_cancelled(id)
+                {
+                    error_callback.call(e);
+                }
+                if let Some(result_map) = result_map
+                    && thread.status() != LuaThreadStatus::Resumable
+                {
+                    result_map.insert(id, res);
+                }
+            }
+            // Clean up any cancellation state, thread ids may be re-used
+            if thread.status() != LuaThreadStatus::Resumable {
+                cancellations.remove(id);
+            }
+        })
+    }
+
+    /**
+        Starts the scheduler without running it, so that it can instead be driven
+        forward one tick at a time using [`Scheduler::step`], such as from the event
+        loop or frame callback of an application that the scheduler is embedded in.
+
+        # Panics
+
+        Panics if the scheduler is already running, or if the given
+        Lua state already has a running scheduler attached to it.
+    */
+    pub fn start(&self) {
+        assert!(!self.status().is_running(), "{ERR_ALREADY_RUNNING}");
+        assert!(
+            self.lua.app_data_ref::<WeakArc<Executor>>().is_none(),
+            "{ERR_METADATA_ALREADY_ATTACHED}"
+        );
+        assert!(
+            self.lua.app_data_ref::<FuturesQueue>().is_none(),
+            "{ERR_METADATA_ALREADY_ATTACHED}"
+        );
+
+        let stepper = Stepper {
+            local_exec: LocalExecutor::new(),
+            main_exec: Arc::new(Executor::new()),
+            fut_queue: FuturesQueue::new(),
+            shutdown_from: Cell::new(None),
+        };
+        self.lua.set_app_data(Arc::downgrade(&stepper.main_exec));
+        self.lua.set_app_data(stepper.fut_queue.clone());
+        self.stepper.replace(Some(Rc::new(stepper)));
+        self.set_status(Status::Running);
+    }
+
+    /**
+        Runs a single tick of a scheduler that was started using [`Scheduler::start`].
+
+        This never blocks - all Lua threads and futures that are ready are run
+        until they yield, and any threads and futures that are still waiting,
+        such as for timers or I/O, are left for following ticks to run.
+
+        Returns the status of the scheduler after the tick, which is [`Status::Completed`]
+        once all Lua threads have completed, or an exit code was set and all shutdown hooks
+        have run, the same as when [`Scheduler::run`] would return. Calling this method
+        when the scheduler is not running does nothing, and returns its current status.
+    */
+    #[instrument(level = "trace", name = "Scheduler::step", skip(self))]
+    pub fn step(&self) -> Status {
+        let Some(stepper) = self.stepper.borrow().clone() else {
+            return self.status();
+        };
+
+        // Check if we should exit, after shutting down
+        if self.exit.get().is_some() && self.should_exit(&stepper) {
+            return self.stop();
+        }
+
+        // Process spawned threads first, then deferred threads, then futures
+        for (thread, args) in self.queue_spawn.take_items() {
+            if let Some(fut) = self.thread_future(thread, args) {
+                stepper.local_exec.spawn(fut).detach();
+            }
+        }
+        for (thread, args) in self.queue_defer.take_items() {
+            if let Some(fut) = self.thread_future(thread, args) {
+                stepper.local_exec.spawn(fut).detach();
+            }
+        }
+        for fut in stepper.fut_queue.take_items() {
+            stepper.local_exec.spawn(fut).detach();
+        }
+
+        // Run everything that is ready, without waiting for anything else
+        while stepper.local_exec.try_tick() {}
+        while stepper.main_exec.try_tick() {}
+
+        if self.exit.get().is_some() && self.should_exit(&stepper) {
+            return self.stop();
+        }
+
+        // Empty executor = there are no remaining tasks to run later
+        let completed = stepper.local_exec.is_empty()
+            && self.queue_spawn.is_empty()
+            && self.queue_defer.is_empty();
+        if completed && !self.start_shutdown(&stepper) {
+            return self.stop();
+        }
+
+        Status::Running
+    }
+
+    /**
+        Starts running shutdown hooks on a started scheduler, unless
+        they are already running, or there are no hooks to run.
+    */
+    fn start_shutdown(&self, stepper: &Stepper) -> bool {
+        if stepper.shutdown_from.get().is_some() || self.shutdown.is_empty() {
+            return false;
+        }
+        debug!("running shutdown hooks");
+        stepper.shutdown_from.set(Some(self.exit.count()));
+        let fut = self
+            .shutdown
+            .clone()
+            .run(self.lua.clone(), self.error_callback.clone());
+        stepper.local_exec.spawn(fut).detach();
+        true
+    }
+
+    /**
+        Checks if a started scheduler should stop, once an exit code has been set.
+    */
+    fn should_exit(&self, stepper: &Stepper) -> bool {
+        match stepper.shutdown_from.get() {
+            None => !self.start_shutdown(stepper),
+            Some(count) => count != self.exit.count() || self.shutdown.is_finished(),
+        }
+    }
+
+    /**
+        Stops a started scheduler, cleaning up its executors.
+    */
+    fn stop(&self) -> Status {
+        debug!("scheduler stopped");
+        self.stepper.replace(None);
+        self.set_status(Status::Completed);
+        self.lua
+            .remove_app_data::<WeakArc<Executor>>()
+            .expect(ERR_METADATA_REMOVED);
+        self.lua
+            .remove_app_data::<FuturesQueue>()
+            .expect(ERR_METADATA_REMOVED);
+        Status::Completed
+    }
+
     /**
         Runs the scheduler until all Lua threads have completed.
 
@@ -323,49 +570,48 @@ impl Scheduler {
 
             This ordering is vital to ensure that we don't accidentally exit the main loop
             when there are new Lua threads to enqueue and potentially more work to be done.
+
+            Once we would exit the main loop, any shutdown hooks are run first, while we keep
+            ticking as usual - an exit code being set again during this forcibly stops the loop.
         */
         let fut = async {
-            let result_map = self.thread_map.clone();
             let process_thread = |thread: LuaThread, args| {
-                // NOTE: Thread may have been cancelled from Lua
-                // before we got here, so we need to check it again
-                if thread.status() == LuaThreadStatus::Resumable {
-                    // Check if we should be tracking this thread
-                    let id = ThreadId::from(&thread);
-                    let id_tracked = result_map.is_tracked(id);
-                    let result_map_inner = if id_tracked {
-                        Some(result_map.clone())
-                    } else {
-                        None
-                    };
-                    // Create our future which will run the thread and store its final result
-                    let fut = async move {
-                        if id_tracked {
-                            // Run until yield and check if we got a final result
-                            if let Some(res) = run_until_yield(thread.clone(), args).await {
-                                if let Err(e) = res.as_ref() {
-                                    self.error_callback.call(e);
-                                }
-                                if thread.status() != LuaThreadStatus::Resumable {
-                                    result_map_inner.unwrap().insert(id, res);
-                                }
-                            }
-                        } else {
-                            // Just run until yield
-                            if let Some(res) = run_until_yield(thread, args).await
-                                && let Err(e) = res.as_ref()
-                            {
-                                self.error_callback.call(e);
-                            }
-                        }
-                    };
-                    // Spawn it on the executor
+                if let Some(fut) = self.thread_future(thread, args) {
                     local_exec.spawn(fut).detach();
                 }
             };
 
+            // Exit count from when shutdown started, if it has started
+            let shutdown_from = Cell::new(None);
+            let start_shutdown = || {
+                if shutdown_from.get().is_some() || self.shutdown.is_empty() {
+                    return false;
+                }
+                debug!("running shutdown hooks");
+                shutdown_from.set(Some(self.exit.count()));
+                let fut = self
+                    .shutdown
+                    .clone()
+                    .run(self.lua.clone(), self.error_callback.clone());
+                local_exec.spawn(fut).detach();
+                true
+            };
+
             loop {
-                let fut_exit = self.exit.listen(); // 1
+                // NOTE: An exit code may already have been set once shutdown has started,
+                // so we must only listen for it being set again, or we would never yield
+                let fut_exit = {
+                    let exit = self.exit.clone();
+                    let exit_next = self.exit.listen_next();
+                    let shutting_down = shutdown_from.get().is_some();
+                    async move {
+                        if shutting_down {
+                            exit_next.await;
+                        } else {
+                            exit.listen().await;
+                        }
+                    }
+                }; // 1
                 let fut_spawn = self.queue_spawn.wait_for_item(); // 2
                 let fut_defer = self.queue_defer.wait_for_item(); // 3
                 let fut_futs = fut_queue.wait_for_item(); // 4
@@ -390,10 +636,16 @@ impl Scheduler {
                     .or(fut_tick.instrument(span_tick.or_current()))
                     .await;
 
-                // Check if we should exit
+                // Check if we should exit, after shutting down
                 if self.exit.get().is_some() {
-                    debug!("exit signal received");
-                    break;
+                    let should_exit = match shutdown_from.get() {
+                        None => !start_shutdown(),
+                        Some(count) => count != self.exit.count() || self.shutdown.is_finished(),
+                    };
+                    if should_exit {
+                        debug!("exit signal received");
+                        break;
+                    }
                 }
 
                 // Process spawned threads first, then deferred threads, then futures
@@ -434,7 +686,7 @@ impl Scheduler {
                     lua_threads_deferred = num_deferred,
                     "loop"
                 );
-                if completed {
+                if completed && !start_shutdown() {
                     break;
                 }
             }
@@ -464,7 +716,9 @@ impl Drop for Scheduler {
             self.lua.remove_app_data::<DeferredThreadQueue>();
             self.lua.remove_app_data::<ThreadErrorCallback>();
             self.lua.remove_app_data::<ThreadMap>();
+            self.lua.remove_app_data::<ThreadCancellations>();
             self.lua.remove_app_data::<Exit>();
+            self.lua.remove_app_data::<Shutdown>();
         } else {
             // In any other case we panic if metadata was removed incorrectly
             self.lua
@@ -479,9 +733,15 @@ impl Drop for Scheduler {
             self.lua
                 .remove_app_data::<ThreadMap>()
                 .expect(ERR_METADATA_REMOVED);
+            self.lua
+                .remove_app_data::<ThreadCancellations>()
+                .expect(ERR_METADATA_REMOVED);
             self.lua
                 .remove_app_data::<Exit>()
                 .expect(ERR_METADATA_REMOVED);
+            self.lua
+                .remove_app_data::<Shutdown>()
+                .expect(ERR_METADATA_REMOVED);
         }
     }
 }
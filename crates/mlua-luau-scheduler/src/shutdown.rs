@@ -0,0 +1,134 @@
+use std::{
+    cell::{Cell, RefCell},
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+};
+
+use mlua::prelude::*;
+use tracing::debug;
+
+use crate::{error_callback::ThreadErrorCallback, traits::LuaSchedulerExt};
+
+type NativeFn = Box<dyn FnOnce(&Lua)>;
+type AsyncFn = Box<dyn FnOnce(&Lua) -> Pin<Box<dyn Future<Output = ()>>>>;
+
+/**
+    A hook that runs when a [`Scheduler`](crate::Scheduler) shuts down.
+
+    See [`LuaSchedulerExt::on_shutdown`] for more information.
+*/
+pub enum ShutdownHook {
+    /// A Rust function, which is called synchronously
+    Native(NativeFn),
+    /// A Rust function returning a future, which must resolve before any other hooks run
+    Async(AsyncFn),
+    /// A Lua function, which runs in its own thread, and must finish before any other hooks run
+    Lua(LuaFunction),
+}
+
+impl ShutdownHook {
+    /**
+        Creates a new hook from a Rust function, which is called synchronously.
+    */
+    pub fn native(f: impl FnOnce(&Lua) + 'static) -> Self {
+        Self::Native(Box::new(f))
+    }
+
+    /**
+        Creates a new hook from a Rust function returning a future,
+        which must resolve before any other hooks run.
+    */
+    pub fn future<F>(f: impl FnOnce(&Lua) -> F + 'static) -> Self
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        Self::Async(Box::new(move |lua| Box::pin(f(lua))))
+    }
+}
+
+impl From<LuaFunction> for ShutdownHook {
+    fn from(value: LuaFunction) -> Self {
+        Self::Lua(value)
+    }
+}
+
+/**
+    Shutdown hooks for a scheduler, along with the current shutdown state.
+*/
+#[derive(Clone)]
+pub(crate) struct Shutdown {
+    hooks: Rc<RefCell<Vec<(i32, ShutdownHook)>>>,
+    finished: Rc<Cell<bool>>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self {
+            hooks: Rc::new(RefCell::new(Vec::new())),
+            finished: Rc::new(Cell::new(false)),
+        }
+    }
+
+    pub fn register(&self, priority: i32, hook: ShutdownHook) {
+        self.hooks.borrow_mut().push((priority, hook));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.borrow().is_empty()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished.get()
+    }
+
+    /**
+        Takes the hook with the highest priority out of the remaining hooks.
+
+        Hooks with the same priority are taken in the order they were registered.
+    */
+    fn take_next(&self) -> Option<ShutdownHook> {
+        let mut hooks = self.hooks.borrow_mut();
+        let mut next: Option<(usize, i32)> = None;
+        for (index, (priority, _)) in hooks.iter().enumerate() {
+            if next.is_none_or(|(_, highest)| *priority > highest) {
+                next = Some((index, *priority));
+            }
+        }
+        next.map(|(index, _)| hooks.remove(index).1)
+    }
+
+    /**
+        Runs all hooks, one at a time, until there are none left.
+
+        Must be spawned on the local executor of the scheduler, since
+        Lua hooks are pushed to the scheduler and waited on to finish.
+    */
+    pub fn run(self, lua: Lua, error_callback: ThreadErrorCallback) -> impl Future<Output = ()> {
+        self.finished.set(false);
+        async move {
+            self.run_inner(lua, error_callback).await;
+            debug!("shutdown hooks finished");
+            self.finished.set(true);
+        }
+    }
+
+    async fn run_inner(&self, lua: Lua, error_callback: ThreadErrorCallback) {
+        while let Some(hook) = self.take_next() {
+            match hook {
+                ShutdownHook::Native(f) => f(&lua),
+                ShutdownHook::Async(f) => f(&lua).await,
+                ShutdownHook::Lua(f) => match lua.push_thread_front(f, ()) {
+                    Ok(id) => {
+                        // NOTE: Errors are reported by the scheduler, like
+                        // for any other tracked thread, we only need to wait
+                        lua.track_thread(id);
+                        lua.wait_for_thread(id).await;
+                        lua.get_thread_result(id);
+                    }
+                    Err(e) => error_callback.call(&e),
+                },
+            }
+        }
+    }
+}
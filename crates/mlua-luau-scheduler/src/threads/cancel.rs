@@ -0,0 +1,179 @@
+#![allow(clippy::inline_always)]
+
+use std::{
+    cell::RefCell,
+    future::{Future, poll_fn},
+    pin::Pin,
+    rc::Rc,
+    task::{Poll, Waker},
+};
+
+use mlua::prelude::*;
+use rustc_hash::FxHashMap;
+
+use super::id::ThreadId;
+
+const ERR_CANCELLED: &str = "task was cancelled";
+
+type AbortFn = Box<dyn FnOnce()>;
+
+struct Abortable<T> {
+    fut: Option<Pin<Box<dyn Future<Output = LuaResult<T>>>>>,
+    waker: Option<Waker>,
+}
+
+#[derive(Default)]
+struct ThreadCancellation {
+    cancelled: bool,
+    next_key: usize,
+    aborts: FxHashMap<usize, AbortFn>,
+}
+
+/**
+    Cancellation state for Lua threads.
+
+    Async operations that run on behalf of a thread may register themselves
+    here, so that they get dropped as soon as the thread is cancelled, instead
+    of whenever the cancelled thread eventually gets garbage collected.
+*/
+#[derive(Clone)]
+pub(crate) struct ThreadCancellations {
+    inner: Rc<RefCell<FxHashMap<ThreadId, ThreadCancellation>>>,
+}
+
+impl ThreadCancellations {
+    pub fn new() -> Self {
+        let inner = Rc::new(RefCell::new(FxHashMap::default()));
+        Self { inner }
+    }
+
+    #[inline(always)]
+    pub fn is_cancelled(&self, id: ThreadId) -> bool {
+        self.inner
+            .borrow()
+            .get(&id)
+            .is_some_and(|state| state.cancelled)
+    }
+
+    /**
+        Marks the given thread as cancelled, and aborts
+        any async operations that are running on its behalf.
+    */
+    pub fn cancel(&self, id: ThreadId) {
+        // NOTE: Aborting drops futures, which may run arbitrary code,
+        // so we must not be holding a borrow of the map while doing so
+        let aborts = {
+            let mut inner = self.inner.borrow_mut();
+            let state = inner.entry(id).or_default();
+            state.cancelled = true;
+            std::mem::take(&mut state.aborts)
+        };
+        for (_, abort) in aborts {
+            abort();
+        }
+    }
+
+    /**
+        Removes all cancellation state for the given thread.
+
+        Must be called once a thread has finished, since thread ids may be re-used.
+    */
+    #[inline(always)]
+    pub fn remove(&self, id: ThreadId) {
+        self.inner.borrow_mut().remove(&id);
+    }
+
+    fn register(&self, id: ThreadId, abort: AbortFn) -> usize {
+        let mut inner = self.inner.borrow_mut();
+        let state = inner.entry(id).or_default();
+        let key = state.next_key;
+        state.next_key += 1;
+        state.aborts.insert(key, abort);
+        key
+    }
+
+    fn unregister(&self, id: ThreadId, key: usize) {
+        // NOTE: This may get called while dropping futures in `cancel`,
+        // in which case the abort function has already been removed
+        let Ok(mut inner) = self.inner.try_borrow_mut() else {
+            return;
+        };
+        if let Some(state) = inner.get_mut(&id) {
+            state.aborts.remove(&key);
+            if !state.cancelled && state.aborts.is_empty() {
+                inner.remove(&id);
+            }
+        }
+    }
+
+    /**
+        Runs the given future on behalf of the given thread.
+
+        If the thread is cancelled while the future is running, the future is dropped
+        immediately, and any further polling will resolve to a cancellation error.
+    */
+    pub async fn run<F, T>(&self, id: ThreadId, fut: F) -> LuaResult<T>
+    where
+        F: Future<Output = LuaResult<T>> + 'static,
+        T: 'static,
+    {
+        if self.is_cancelled(id) {
+            return Err(LuaError::runtime(ERR_CANCELLED));
+        }
+
+        let slot = Rc::new(RefCell::new(Abortable {
+            fut: Some(Box::pin(fut)),
+            waker: None,
+        }));
+        let slot_weak = Rc::downgrade(&slot);
+        let key = self.register(
+            id,
+            Box::new(move || {
+                if let Some(slot) = slot_weak.upgrade()
+                    && let Ok(mut slot) = slot.try_borrow_mut()
+                {
+                    // NOTE: The thread waiting on this future must be woken up,
+                    // otherwise the scheduler would wait for it to resolve forever
+                    drop(slot.fut.take());
+                    if let Some(waker) = slot.waker.take() {
+                        waker.wake();
+                    }
+                }
+            }),
+        );
+
+        let _guard = Unregister {
+            cancellations: self.clone(),
+            id,
+            key,
+        };
+
+        poll_fn(|cx| {
+            let mut slot = slot.borrow_mut();
+            let Some(fut) = slot.fut.as_mut() else {
+                return Poll::Ready(Err(LuaError::runtime(ERR_CANCELLED)));
+            };
+            let res = fut.as_mut().poll(cx);
+            if res.is_ready() {
+                slot.fut.take();
+                slot.waker.take();
+            } else {
+                slot.waker.replace(cx.waker().clone());
+            }
+            res
+        })
+        .await
+    }
+}
+
+struct Unregister {
+    cancellations: ThreadCancellations,
+    id: ThreadId,
+    key: usize,
+}
+
+impl Drop for Unregister {
+    fn drop(&mut self) {
+        self.cancellations.unregister(self.id, self.key);
+    }
+}
@@ -1,5 +1,7 @@
+mod cancel;
 mod id;
 mod map;
 
+pub(crate) use cancel::ThreadCancellations;
 pub use id::ThreadId;
 pub(crate) use map::ThreadMap;
@@ -13,7 +13,8 @@ use crate::{
     exit::Exit,
     queue::{DeferredThreadQueue, FuturesQueue, SpawnedThreadQueue},
     scheduler::Scheduler,
-    threads::{ThreadId, ThreadMap},
+    shutdown::{Shutdown, ShutdownHook},
+    threads::{ThreadCancellations, ThreadId, ThreadMap},
 };
 
 /**
@@ -70,6 +71,8 @@ where
     - Setting the exit code and forcibly stopping the scheduler
     - Pushing (spawning) and deferring (pushing to the back) lua threads
     - Tracking and getting the result of lua threads
+    - Cancelling lua threads, and any async operations they are waiting on
+    - Registering hooks to run when the scheduler shuts down
 */
 pub trait LuaSchedulerExt {
     /**
@@ -141,6 +144,63 @@ pub trait LuaSchedulerExt {
         Panics if called outside of a running [`Scheduler`].
     */
     fn wait_for_thread(&self, id: ThreadId) -> impl Future<Output = ()>;
+
+    /**
+        Cancels the given thread, stopping it from resuming.
+
+        Any async operations that the thread is currently waiting on, and that were
+        started using [`LuaSchedulerExt::with_cancellation`], are dropped immediately.
+
+        Threads that are currently running can not be closed, and will instead be marked as
+        cancelled, stopping at their next yield. Running threads may check if they have been
+        cancelled using [`LuaSchedulerExt::is_thread_cancelled`].
+
+        May also be called outside of a running [`Scheduler`], in which case the thread is only closed.
+    */
+    fn cancel_thread(&self, thread: &LuaThread) -> LuaResult<()>;
+
+    /**
+        Checks if the given thread has been cancelled, but has not yet stopped running.
+
+        See [`LuaSchedulerExt::cancel_thread`] for more information.
+
+        # Panics
+
+        Panics if called outside of a running [`Scheduler`].
+    */
+    fn is_thread_cancelled(&self, id: ThreadId) -> bool;
+
+    /**
+        Runs the given future on behalf of the currently running thread.
+
+        If the thread is cancelled using [`LuaSchedulerExt::cancel_thread`] while
+        waiting on the future, the future is dropped immediately, instead of once the
+        thread is garbage collected. This should be used for any async operation that
+        holds on to external resources, such as network connections or child processes.
+
+        # Panics
+
+        Panics if called outside of a running [`Scheduler`].
+    */
+    fn with_cancellation<F, T>(&self, fut: F) -> impl Future<Output = LuaResult<T>>
+    where
+        F: Future<Output = LuaResult<T>> + 'static,
+        T: 'static;
+
+    /**
+        Registers a hook to run when the current scheduler shuts down.
+
+        The scheduler shuts down once all threads have completed, or once an exit code has been
+        set. Hooks run one at a time, in order of descending priority, and hooks with the same
+        priority run in the order they were registered. The scheduler keeps running as usual while
+        hooks run, so that resources may be closed gracefully, but setting an exit code again while
+        shutting down stops the scheduler immediately, without running any of the remaining hooks.
+
+        # Panics
+
+        Panics if called outside of a running [`Scheduler`].
+    */
+    fn on_shutdown(&self, priority: i32, hook: ShutdownHook);
 }
 
 /**
@@ -331,6 +391,59 @@ impl LuaSchedulerExt for Lua {
             .expect("lua threads results can only be retrieved from within an active scheduler");
         map.listen(id)
     }
+
+    fn cancel_thread(&self, thread: &LuaThread) -> LuaResult<()> {
+        let id = ThreadId::from(thread);
+        let cancellations = self
+            .app_data_ref::<ThreadCancellations>()
+            .map(|c| c.clone());
+        if let Some(cancellations) = &cancellations {
+            cancellations.cancel(id);
+        }
+        let close = self
+            .globals()
+            .get::<LuaTable>("coroutine")?
+            .get::<LuaFunction>("close")?;
+        match close.call::<()>(thread) {
+            Err(LuaError::CoroutineUnresumable) | Ok(()) => {
+                if let Some(cancellations) = &cancellations {
+                    cancellations.remove(id);
+                }
+                Ok(())
+            }
+            // NOTE: Running threads can not be closed, but they
+            // have been marked as cancelled above, which is enough
+            Err(_) if cancellations.is_some() => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn is_thread_cancelled(&self, id: ThreadId) -> bool {
+        let cancellations = self
+            .app_data_ref::<ThreadCancellations>()
+            .expect("lua threads can only be checked for cancellation within an active scheduler");
+        cancellations.is_cancelled(id)
+    }
+
+    fn with_cancellation<F, T>(&self, fut: F) -> impl Future<Output = LuaResult<T>>
+    where
+        F: Future<Output = LuaResult<T>> + 'static,
+        T: 'static,
+    {
+        let cancellations = self
+            .app_data_ref::<ThreadCancellations>()
+            .expect("cancellable futures can only be created from within an active scheduler")
+            .clone();
+        let id = ThreadId::from(&self.current_thread());
+        async move { cancellations.run(id, fut).await }
+    }
+
+    fn on_shutdown(&self, priority: i32, hook: ShutdownHook) {
+        let shutdown = self
+            .app_data_ref::<Shutdown>()
+            .expect("shutdown hooks can only be registered from within an active scheduler");
+        shutdown.register(priority, hook);
+    }
 }
 
 impl LuaSpawnExt for Lua {
@@ -33,6 +33,11 @@ if [ "$OS" = "windows" ]; then
     BIN_EXT=".exe"
 fi
 
+# WASI modules are built on any OS, and always use the wasm extension
+case "$TARGET_TRIPLE" in
+    wasm32-*) BIN_EXT=".wasm" ;;
+esac
+
 # Clean up any previous artifacts and dirs
 rm -rf staging
 rm -rf release.zip
@@ -40,18 +45,30 @@ rm -rf release.zip
 # Create new staging dir to work in and copy the binary into that
 mkdir -p staging
 cp "$TARGET_DIR/$BIN_NAME$BIN_EXT" staging/
+
+# Copy the base shared library used by `lune build --lib` too, if it was built
+LIB_FILE=""
+for CANDIDATE in "liblune_ffi.so" "liblune_ffi.dylib" "lune_ffi.dll"; do
+	if [ -f "$TARGET_DIR/$CANDIDATE" ]; then
+		LIB_FILE="$CANDIDATE"
+		cp "$TARGET_DIR/$LIB_FILE" staging/
+	fi
+done
 cd staging
 
 # Ad-hoc sign macOS binaries to prevent SIGKILL on Apple Silicon
-if [ "$OS" = "macos" ]; then
+if [ "$OS" = "macos" ] && [ -z "$BIN_EXT" ]; then
 	codesign -s - --force "$BIN_NAME"
+	if [ -n "$LIB_FILE" ]; then
+		codesign -s - --force "$LIB_FILE"
+	fi
 fi
 
 # Zip the staging dir up
 if [ "$OS" = "windows" ]; then
 	7z a ../release.zip *
 else
-	chmod +x "$BIN_NAME"
+	chmod +x "$BIN_NAME$BIN_EXT"
 	zip ../release.zip *
 fi
 